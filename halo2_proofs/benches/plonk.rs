@@ -17,6 +17,13 @@ use std::marker::PhantomData;
 
 use criterion::{BenchmarkId, Criterion};
 
+#[cfg(feature = "alloc-stats")]
+use halo2_proofs::dev::heap::{self, CountingAllocator};
+
+#[cfg(feature = "alloc-stats")]
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new(std::alloc::System);
+
 fn criterion_benchmark(c: &mut Criterion) {
     /// This represents an advice column at a certain row in the ConstraintSystem
     #[derive(Copy, Clone, Debug)]
@@ -284,11 +291,30 @@ fn criterion_benchmark(c: &mut Criterion) {
         verify_proof(params, vk, strategy, &[&[]], &mut transcript).unwrap();
     }
 
+    fn preprocessed_verifier(
+        params: &ParamsVerifier<Bn256>,
+        verifier: &PreprocessedVerifier<G1Affine>,
+        proof: &[u8],
+    ) {
+        let strategy = SingleVerifier::new(params);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+        verifier
+            .verify(params, strategy, &[&[]], &mut transcript)
+            .unwrap();
+    }
+
     let k_range = 8..=16;
 
     let mut keygen_group = c.benchmark_group("plonk-keygen");
     keygen_group.sample_size(10);
     for k in k_range.clone() {
+        #[cfg(feature = "alloc-stats")]
+        println!(
+            "plonk-keygen/{}: peak heap usage {} bytes",
+            k,
+            heap::peak_during(|| keygen(k)).1
+        );
+
         keygen_group.bench_with_input(BenchmarkId::from_parameter(k), &k, |b, &k| {
             b.iter(|| keygen(k));
         });
@@ -300,6 +326,13 @@ fn criterion_benchmark(c: &mut Criterion) {
     for k in k_range.clone() {
         let (params, _, pk) = keygen(k);
 
+        #[cfg(feature = "alloc-stats")]
+        println!(
+            "plonk-prover/{}: peak heap usage {} bytes",
+            k,
+            heap::peak_during(|| prover(k, &params, &pk)).1
+        );
+
         prover_group.bench_with_input(
             BenchmarkId::from_parameter(k),
             &(k, &params, &pk),
@@ -311,7 +344,7 @@ fn criterion_benchmark(c: &mut Criterion) {
     prover_group.finish();
 
     let mut verifier_group = c.benchmark_group("plonk-verifier");
-    for k in k_range {
+    for k in k_range.clone() {
         let (params, params_verifier, pk) = keygen(k);
         let proof = prover(k, &params, &pk);
 
@@ -324,6 +357,22 @@ fn criterion_benchmark(c: &mut Criterion) {
         );
     }
     verifier_group.finish();
+
+    let mut preprocessed_group = c.benchmark_group("plonk-verifier-preprocessed");
+    for k in k_range {
+        let (params, params_verifier, pk) = keygen(k);
+        let proof = prover(k, &params, &pk);
+
+        let preprocessed = PreprocessedVerifier::new(pk.get_vk());
+        preprocessed_group.bench_with_input(
+            BenchmarkId::from_parameter(k),
+            &(&preprocessed, &proof[..]),
+            |b, &(preprocessed, proof)| {
+                b.iter(|| preprocessed_verifier(&params_verifier, preprocessed, proof));
+            },
+        );
+    }
+    preprocessed_group.finish();
 }
 
 criterion_group!(benches, criterion_benchmark);
@@ -1,36 +1,229 @@
 //! This module provides common utilities, traits and structures for group,
 //! field and polynomial arithmetic.
+//!
+//! The functions of this module split their work between the threads of the
+//! current thread pool. When there is a single thread, as is always the case
+//! without the `multicore` feature, they run serially on the calling thread
+//! instead, without allocating buffers for the work of other threads.
 
 use super::multicore;
 pub use ff::Field;
 use group::{
     ff::{BatchInvert, PrimeField},
-    Group as _,
+    prime::PrimeCurveAffine,
+    Curve, Group as _,
 };
+use std::time::{Duration, Instant};
+use subtle::{ConditionallySelectable, ConstantTimeEq};
 
 pub use pairing::arithmetic::*;
 
-fn multiexp_serial<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C], acc: &mut C::Curve) {
-    let coeffs: Vec<_> = coeffs.iter().map(|a| a.to_repr()).collect();
-
-    let c = if bases.len() < 4 {
+/// Returns the bucket window width that [`best_multiexp`] uses for a serial
+/// multi-exponentiation of `n` terms, according to a fixed heuristic.
+///
+/// Widths tuned for the host can be used instead with [`MultiexpWindows`].
+pub fn multiexp_window(n: usize) -> usize {
+    if n < 4 {
         1
-    } else if bases.len() < 32 {
+    } else if n < 32 {
         3
     } else {
-        (f64::from(bases.len() as u32)).ln().ceil() as usize
-    };
+        (f64::from(n as u32)).ln().ceil() as usize
+    }
+}
+
+/// Bucket window widths for multi-exponentiations, indexed by the base-2
+/// logarithm of the number of terms.
+///
+/// The default table is empty, so that every size uses [`multiexp_window`].
+/// [`MultiexpWindows::autotune`] fills it with the widths that are fastest on
+/// this host, and [`best_multiexp_with_windows`] or
+/// [`Params::with_multiexp_windows`] use them.
+///
+/// [`Params::with_multiexp_windows`]: crate::poly::commitment::Params::with_multiexp_windows
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MultiexpWindows {
+    windows: Vec<usize>,
+}
+
+impl MultiexpWindows {
+    /// Benchmarks a few bucket window widths around [`multiexp_window`] for
+    /// multi-exponentiations of $2^i$ terms, for each $i \leq$ `max_log_n`,
+    /// and returns the fastest width found for each size on this host.
+    ///
+    /// Since [`best_multiexp_with_windows`] splits its input across threads,
+    /// `max_log_n` only needs to cover the size of a single thread's chunk.
+    pub fn autotune<C: CurveAffine>(max_log_n: u32) -> Self {
+        use rand::thread_rng;
+
+        // A small pool of random bases is enough to time the bucket accumulation.
+        const NUM_BASES: usize = 64;
+        let mut rng = thread_rng();
+        let projective: Vec<_> = (0..NUM_BASES).map(|_| C::Curve::random(&mut rng)).collect();
+        let mut pool = vec![C::identity(); NUM_BASES];
+        C::Curve::batch_normalize(&projective, &mut pool);
+
+        let windows = (0..=max_log_n)
+            .map(|log_n| {
+                let n = 1usize << log_n;
+                let coeffs: Vec<_> = (0..n).map(|_| C::Scalar::random(&mut rng)).collect();
+                let bases: Vec<_> = pool.iter().cycle().take(n).cloned().collect();
+                // Repeat small instances so that their timings are meaningful.
+                let iterations = std::cmp::max(1, 1024 >> log_n);
+
+                let default = multiexp_window(n);
+                let mut best = (default, Duration::from_secs(u64::MAX));
+                for c in default.saturating_sub(2).max(1)..=default + 2 {
+                    let start = Instant::now();
+                    for _ in 0..iterations {
+                        let mut acc = C::Curve::identity();
+                        multiexp_serial(&coeffs, &bases, c, &mut acc);
+                    }
+                    let elapsed = start.elapsed();
+                    if elapsed < best.1 {
+                        best = (c, elapsed);
+                    }
+                }
+                best.0
+            })
+            .collect();
+
+        MultiexpWindows { windows }
+    }
+
+    /// Returns the bucket window width for a serial multi-exponentiation of
+    /// `n` terms: the tuned width for its size if there is one, and
+    /// [`multiexp_window`] otherwise.
+    pub fn window(&self, n: usize) -> usize {
+        if n == 0 {
+            return multiexp_window(n);
+        }
+        self.windows
+            .get(log2_floor(n) as usize)
+            .cloned()
+            .unwrap_or_else(|| multiexp_window(n))
+    }
+
+    /// Returns the tuned widths, indexed by the base-2 logarithm of the number
+    /// of terms.
+    pub fn as_slice(&self) -> &[usize] {
+        &self.windows
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Bucket<C: CurveAffine> {
+    None,
+    Affine(C),
+    Projective(C::Curve),
+}
+
+impl<C: CurveAffine> Bucket<C> {
+    fn add_assign(&mut self, other: &C) {
+        *self = match *self {
+            Bucket::None => Bucket::Affine(*other),
+            Bucket::Affine(a) => Bucket::Projective(a + *other),
+            Bucket::Projective(mut a) => {
+                a += *other;
+                Bucket::Projective(a)
+            }
+        }
+    }
+
+    fn add(self, mut other: C::Curve) -> C::Curve {
+        match self {
+            Bucket::None => other,
+            Bucket::Affine(a) => {
+                other += a;
+                other
+            }
+            Bucket::Projective(a) => other + &a,
+        }
+    }
+}
+
+/// Scratch space for a single serial multi-exponentiation.
+struct SerialBuffer<C: CurveAffine> {
+    /// Little-endian encodings of the scalars, concatenated.
+    coeffs: Vec<u8>,
+    buckets: Vec<Bucket<C>>,
+}
+
+impl<C: CurveAffine> Default for SerialBuffer<C> {
+    fn default() -> Self {
+        SerialBuffer {
+            coeffs: vec![],
+            buckets: vec![],
+        }
+    }
+}
+
+/// Reusable scratch space for [`best_multiexp_with_buffer`].
+///
+/// A multi-exponentiation needs to decompose its scalars into bytes and to
+/// allocate buckets for every window. When many multi-exponentiations are
+/// performed against the same bases (such as when committing to many
+/// polynomials), passing the same buffer to each of them reuses those
+/// allocations instead of reallocating them for every call.
+pub struct MultiexpBuffer<C: CurveAffine> {
+    chunks: Vec<SerialBuffer<C>>,
+}
+
+impl<C: CurveAffine> MultiexpBuffer<C> {
+    /// Creates an empty buffer; it grows to the required size on first use.
+    pub fn new() -> Self {
+        MultiexpBuffer { chunks: vec![] }
+    }
+}
+
+impl<C: CurveAffine> Default for MultiexpBuffer<C> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: CurveAffine> std::fmt::Debug for MultiexpBuffer<C> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MultiexpBuffer")
+            .field("chunks", &self.chunks.len())
+            .finish()
+    }
+}
+
+fn multiexp_serial<C: CurveAffine>(
+    coeffs: &[C::Scalar],
+    bases: &[C],
+    c: usize,
+    acc: &mut C::Curve,
+) {
+    multiexp_serial_with_buffer(coeffs, bases, c, acc, &mut SerialBuffer::default());
+}
+
+fn multiexp_serial_with_buffer<C: CurveAffine>(
+    coeffs: &[C::Scalar],
+    bases: &[C],
+    c: usize,
+    acc: &mut C::Curve,
+    buffer: &mut SerialBuffer<C>,
+) {
+    assert!(c > 0 && c <= 32);
+
+    let repr_len = <C::Scalar as PrimeField>::Repr::default().as_ref().len();
+    buffer.coeffs.clear();
+    for coeff in coeffs {
+        buffer.coeffs.extend_from_slice(coeff.to_repr().as_ref());
+    }
 
-    fn get_at<F: PrimeField>(segment: usize, c: usize, bytes: &F::Repr) -> usize {
+    fn get_at(segment: usize, c: usize, bytes: &[u8]) -> usize {
         let skip_bits = segment * c;
         let skip_bytes = skip_bits / 8;
 
-        if skip_bytes >= 32 {
+        if skip_bytes >= bytes.len() {
             return 0;
         }
 
         let mut v = [0; 8];
-        for (v, o) in v.iter_mut().zip(bytes.as_ref()[skip_bytes..].iter()) {
+        for (v, o) in v.iter_mut().zip(bytes[skip_bytes..].iter()) {
             *v = *o;
         }
 
@@ -48,41 +241,12 @@ fn multiexp_serial<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C], acc: &mut
             *acc = acc.double();
         }
 
-        #[derive(Clone, Copy)]
-        enum Bucket<C: CurveAffine> {
-            None,
-            Affine(C),
-            Projective(C::Curve),
-        }
+        let buckets = &mut buffer.buckets;
+        buckets.clear();
+        buckets.resize((1 << c) - 1, Bucket::None);
 
-        impl<C: CurveAffine> Bucket<C> {
-            fn add_assign(&mut self, other: &C) {
-                *self = match *self {
-                    Bucket::None => Bucket::Affine(*other),
-                    Bucket::Affine(a) => Bucket::Projective(a + *other),
-                    Bucket::Projective(mut a) => {
-                        a += *other;
-                        Bucket::Projective(a)
-                    }
-                }
-            }
-
-            fn add(self, mut other: C::Curve) -> C::Curve {
-                match self {
-                    Bucket::None => other,
-                    Bucket::Affine(a) => {
-                        other += a;
-                        other
-                    }
-                    Bucket::Projective(a) => other + &a,
-                }
-            }
-        }
-
-        let mut buckets: Vec<Bucket<C>> = vec![Bucket::None; (1 << c) - 1];
-
-        for (coeff, base) in coeffs.iter().zip(bases.iter()) {
-            let coeff = get_at::<C::Scalar>(current_segment, c, coeff);
+        for (coeff, base) in buffer.coeffs.chunks(repr_len).zip(bases.iter()) {
+            let coeff = get_at(current_segment, c, coeff);
             if coeff != 0 {
                 buckets[coeff - 1].add_assign(base);
             }
@@ -93,7 +257,7 @@ fn multiexp_serial<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C], acc: &mut
         //                    (a) + b +
         //                    ((a) + b) + c
         let mut running_sum = C::Curve::identity();
-        for exp in buckets.into_iter().rev() {
+        for exp in buckets.iter().rev() {
             running_sum = exp.add(running_sum);
             *acc = *acc + &running_sum;
         }
@@ -128,36 +292,240 @@ pub fn small_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::C
 ///
 /// This function will panic if coeffs and bases have a different length.
 ///
-/// This will use multithreading if beneficial.
+/// This will use multithreading if beneficial. The bucket window width is
+/// chosen by [`multiexp_window`].
 pub fn best_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
+    best_multiexp_inner(
+        coeffs,
+        bases,
+        None,
+        &MultiexpWindows::default(),
+        &mut MultiexpBuffer::new(),
+    )
+}
+
+/// Performs a multi-exponentiation operation using a bucket window of `c`
+/// bits, instead of choosing one according to the size of the input.
+///
+/// This function will panic if coeffs and bases have a different length, or
+/// if `c` is not in the range `1..=32`.
+///
+/// This will use multithreading if beneficial.
+pub fn best_multiexp_with_window<C: CurveAffine>(
+    coeffs: &[C::Scalar],
+    bases: &[C],
+    c: usize,
+) -> C::Curve {
+    best_multiexp_inner(
+        coeffs,
+        bases,
+        Some(c),
+        &MultiexpWindows::default(),
+        &mut MultiexpBuffer::new(),
+    )
+}
+
+/// Performs a multi-exponentiation operation, reusing the allocations held in
+/// `buffer` from previous calls.
+///
+/// This function will panic if coeffs and bases have a different length.
+///
+/// This will use multithreading if beneficial.
+pub fn best_multiexp_with_buffer<C: CurveAffine>(
+    coeffs: &[C::Scalar],
+    bases: &[C],
+    buffer: &mut MultiexpBuffer<C>,
+) -> C::Curve {
+    best_multiexp_inner(coeffs, bases, None, &MultiexpWindows::default(), buffer)
+}
+
+/// Performs a multi-exponentiation operation, choosing the bucket window width
+/// from `windows` and reusing the allocations held in `buffer` from previous
+/// calls.
+///
+/// This function will panic if coeffs and bases have a different length.
+///
+/// This will use multithreading if beneficial.
+pub fn best_multiexp_with_windows<C: CurveAffine>(
+    coeffs: &[C::Scalar],
+    bases: &[C],
+    windows: &MultiexpWindows,
+    buffer: &mut MultiexpBuffer<C>,
+) -> C::Curve {
+    best_multiexp_inner(coeffs, bases, None, windows, buffer)
+}
+
+/// Performs a multi-exponentiation operation whose branches and memory
+/// accesses don't depend on the scalars, for committing to secret values.
+///
+/// Each scalar is split into 4-bit digits, and each digit $d$ selects
+/// $(d + 1) P$ from a table of multiples of its base by scanning the whole
+/// table with [`ConditionallySelectable`]. The extra $P$ per digit keeps the
+/// selected point from being the identity, on which the curve's addition
+/// branches; it is subtracted from the result, which only depends on the
+/// bases.
+///
+/// This function will panic if coeffs and bases have a different length.
+///
+/// This will use multithreading if beneficial, but never the GPU. It is
+/// several times slower than [`best_multiexp`].
+pub fn constant_time_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
     assert_eq!(coeffs.len(), bases.len());
 
     let num_threads = multicore::current_num_threads();
-    if coeffs.len() > num_threads {
+    if num_threads == 1 {
+        return constant_time_multiexp_serial(coeffs, bases);
+    }
+    let chunk = (coeffs.len() / num_threads).max(1);
+    let mut results = vec![C::Curve::identity(); coeffs.chunks(chunk).len()];
+    multicore::scope(|scope| {
+        for ((coeffs, bases), acc) in coeffs
+            .chunks(chunk)
+            .zip(bases.chunks(chunk))
+            .zip(results.iter_mut())
+        {
+            scope.spawn(move |_| {
+                *acc = constant_time_multiexp_serial(coeffs, bases);
+            });
+        }
+    });
+    results.iter().fold(C::Curve::identity(), |a, b| a + b)
+}
+
+fn constant_time_multiexp_serial<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::Curve {
+    const WINDOW: usize = 4;
+
+    let coeffs: Vec<_> = coeffs.iter().map(|a| a.to_repr()).collect();
+    let repr_len = <C::Scalar as PrimeField>::Repr::default().as_ref().len();
+
+    // tables[i][j] = (j + 1) bases[i]
+    let tables: Vec<Vec<C::Curve>> = bases
+        .iter()
+        .map(|base| {
+            let mut multiple = base.to_curve();
+            let mut table = Vec::with_capacity(1 << WINDOW);
+            for _ in 0..(1 << WINDOW) {
+                table.push(multiple);
+                multiple += *base;
+            }
+            table
+        })
+        .collect();
+
+    let mut acc = C::Curve::identity();
+    for byte_idx in (0..repr_len).rev() {
+        for &shift in [WINDOW, 0].iter() {
+            for _ in 0..WINDOW {
+                acc = acc.double();
+            }
+            for (coeff, table) in coeffs.iter().zip(tables.iter()) {
+                let digit = (coeff.as_ref()[byte_idx] >> shift) & ((1 << WINDOW) - 1);
+                let mut selected = table[0];
+                for (j, multiple) in table.iter().enumerate().skip(1) {
+                    selected.conditional_assign(multiple, digit.ct_eq(&(j as u8)));
+                }
+                acc += selected;
+            }
+        }
+    }
+
+    // Every digit position added one more copy of each base.
+    let offset = (0..(2 * repr_len)).fold(C::Scalar::zero(), |offset, _| {
+        offset * C::Scalar::from(1u64 << WINDOW) + C::Scalar::one()
+    });
+    let sum = bases
+        .iter()
+        .fold(C::Curve::identity(), |sum, base| sum + *base);
+    acc - sum * offset
+}
+
+fn best_multiexp_inner<C: CurveAffine>(
+    coeffs: &[C::Scalar],
+    bases: &[C],
+    window: Option<usize>,
+    windows: &MultiexpWindows,
+    buffer: &mut MultiexpBuffer<C>,
+) -> C::Curve {
+    assert_eq!(coeffs.len(), bases.len());
+
+    // An explicit window width only makes sense for the CPU implementation.
+    #[cfg(feature = "gpu")]
+    if window.is_none() && coeffs.len() >= GPU_MULTIEXP_THRESHOLD {
+        if let Some(result) = gpu_multiexp(coeffs, bases) {
+            return result;
+        }
+    }
+
+    let num_threads = multicore::current_num_threads();
+    if num_threads > 1 && coeffs.len() > num_threads {
         let chunk = coeffs.len() / num_threads;
         let num_chunks = coeffs.chunks(chunk).len();
+        let c = window.unwrap_or_else(|| windows.window(chunk));
+        if buffer.chunks.len() < num_chunks {
+            buffer.chunks.resize_with(num_chunks, SerialBuffer::default);
+        }
         let mut results = vec![C::Curve::identity(); num_chunks];
         multicore::scope(|scope| {
             let chunk = coeffs.len() / num_threads;
 
-            for ((coeffs, bases), acc) in coeffs
+            for (((coeffs, bases), acc), buffer) in coeffs
                 .chunks(chunk)
                 .zip(bases.chunks(chunk))
                 .zip(results.iter_mut())
+                .zip(buffer.chunks.iter_mut())
             {
                 scope.spawn(move |_| {
-                    multiexp_serial(coeffs, bases, acc);
+                    multiexp_serial_with_buffer(coeffs, bases, c, acc, buffer);
                 });
             }
         });
         results.iter().fold(C::Curve::identity(), |a, b| a + b)
     } else {
+        let c = window.unwrap_or_else(|| windows.window(coeffs.len()));
+        if buffer.chunks.is_empty() {
+            buffer.chunks.push(SerialBuffer::default());
+        }
         let mut acc = C::Curve::identity();
-        multiexp_serial(coeffs, bases, &mut acc);
+        multiexp_serial_with_buffer(coeffs, bases, c, &mut acc, &mut buffer.chunks[0]);
         acc
     }
 }
 
+/// The minimum number of terms for which [`best_multiexp`] and
+/// [`best_multiexp_with_buffer`] offload the multi-exponentiation to the GPU.
+/// Below this size, transferring the inputs costs more than it saves.
+#[cfg(feature = "gpu")]
+pub const GPU_MULTIEXP_THRESHOLD: usize = 1 << 16;
+
+/// Performs a multi-exponentiation operation on the GPU.
+///
+/// Returns `None` if no GPU kernel could be created, if the kernel fails, or
+/// if the `BELLMAN_NO_GPU` environment variable is set; the caller is expected
+/// to fall back to the CPU implementation in that case.
+#[cfg(feature = "gpu")]
+pub fn gpu_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> Option<C::Curve> {
+    assert_eq!(coeffs.len(), bases.len());
+
+    if std::env::var("BELLMAN_NO_GPU").is_ok() {
+        return None;
+    }
+
+    let log_n = log2_floor(coeffs.len().max(1)) as usize;
+    let mut kern = crate::gpu::LockedMultiexpKernel::<C>::new(log_n, false);
+    match kern.with(|k: &mut crate::gpu::MultiexpKernel<C>| k.multiexp(bases, coeffs)) {
+        Ok(result) => Some(result),
+        Err(e) => {
+            log::warn!(
+                target: crate::logging::GPU,
+                "kernel=multiexp event=fallback n={} error=\"{}\"",
+                coeffs.len(),
+                e
+            );
+            None
+        }
+    }
+}
+
 /// Performs a radix-$2$ Fast-Fourier Transformation (FFT) on a vector of size
 /// $n = 2^k$, when provided `log_n` = $k$ and an element of multiplicative
 /// order $n$ called `omega` ($\omega$). The result is that the vector `a`, when
@@ -169,17 +537,59 @@ pub fn best_multiexp<C: CurveAffine>(coeffs: &[C::Scalar], bases: &[C]) -> C::Cu
 ///
 /// This will use multithreading if beneficial.
 pub fn best_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) {
+    let twiddles = compute_twiddles(omega, log_n);
+    best_fft_with_twiddles(a, omega, &twiddles, log_n);
+}
+
+/// Performs the same transformation as [`best_fft`], but reads the powers of
+/// $\omega$ from a precomputed table instead of recomputing them.
+///
+/// `twiddles` must have been produced by [`compute_twiddles`] for an element
+/// $\omega'$ of order $N = 2^K$ with $K \geq$ `log_n`, such that
+/// $\omega = \omega'^{N / n}$. This allows a single table for a large domain to
+/// be shared by the FFTs of all of its subdomains.
+///
+/// This will use multithreading if beneficial.
+pub fn best_fft_with_twiddles<G: Group>(
+    a: &mut [G],
+    omega: G::Scalar,
+    twiddles: &[G::Scalar],
+    log_n: u32,
+) {
+    let n = 1usize << log_n;
+    assert_eq!(a.len(), n);
+    assert!(twiddles.len() * 2 >= n);
+    // Distance between consecutive powers of `omega` in the table.
+    let stride = (twiddles.len() * 2) >> log_n;
+
     let threads = multicore::current_num_threads();
     let log_threads = log2_floor(threads);
 
-    if log_n <= log_threads {
-        serial_fft(a, omega, log_n);
+    if log_threads == 0 || log_n <= log_threads {
+        serial_fft(a, twiddles, stride, log_n);
     } else {
-        parallel_fft(a, omega, log_n, log_threads);
+        parallel_fft(a, omega, twiddles, stride, log_n, log_threads);
     }
 }
 
-fn serial_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) {
+/// Computes the table of twiddle factors $[1, \omega, \omega^2, \ldots, \omega^{n/2 - 1}]$
+/// used by [`best_fft_with_twiddles`] for a domain of size $n = 2^{\textrm{log\_n}}$.
+pub fn compute_twiddles<F: Field>(omega: F, log_n: u32) -> Vec<F> {
+    let mut twiddles = vec![F::zero(); (1usize << log_n) / 2];
+    if twiddles.is_empty() {
+        return twiddles;
+    }
+    parallelize(&mut twiddles, |twiddles, start| {
+        let mut cur = omega.pow_vartime(&[start as u64, 0, 0, 0]);
+        for twiddle in twiddles.iter_mut() {
+            *twiddle = cur;
+            cur *= &omega;
+        }
+    });
+    twiddles
+}
+
+fn serial_fft<G: Group>(a: &mut [G], twiddles: &[G::Scalar], stride: usize, log_n: u32) {
     fn bitreverse(mut n: u32, l: u32) -> u32 {
         let mut r = 0;
         for _ in 0..l {
@@ -201,18 +611,18 @@ fn serial_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) {
 
     let mut m = 1;
     for _ in 0..log_n {
-        let w_m = omega.pow_vartime(&[u64::from(n / (2 * m)), 0, 0, 0]);
+        // w_m = omega^(n / 2m), so w_m^j lives at index j * (n / 2m) of the
+        // table for omega.
+        let twiddle_step = (n / (2 * m)) as usize * stride;
 
         let mut k = 0;
         while k < n {
-            let mut w = G::Scalar::one();
             for j in 0..m {
                 let mut t = a[(k + j + m) as usize];
-                t.group_scale(&w);
+                t.group_scale(&twiddles[j as usize * twiddle_step]);
                 a[(k + j + m) as usize] = a[(k + j) as usize];
                 a[(k + j + m) as usize].group_sub(&t);
                 a[(k + j) as usize].group_add(&t);
-                w *= &w_m;
             }
 
             k += 2 * m;
@@ -222,13 +632,22 @@ fn serial_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32) {
     }
 }
 
-fn parallel_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32, log_threads: u32) {
+fn parallel_fft<G: Group>(
+    a: &mut [G],
+    omega: G::Scalar,
+    twiddles: &[G::Scalar],
+    stride: usize,
+    log_n: u32,
+    log_threads: u32,
+) {
     assert!(log_n >= log_threads);
 
     let num_threads = 1 << log_threads;
     let log_new_n = log_n - log_threads;
     let mut tmp = vec![vec![G::group_zero(); 1 << log_new_n]; num_threads];
-    let new_omega = omega.pow_vartime(&[num_threads as u64, 0, 0, 0]);
+    // The sub-FFTs use omega^num_threads, whose powers are every
+    // `num_threads`th entry of the table for omega.
+    let new_stride = stride * num_threads;
 
     multicore::scope(|scope| {
         let a = &*a;
@@ -236,7 +655,7 @@ fn parallel_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32, log_threads
         for (j, tmp) in tmp.iter_mut().enumerate() {
             scope.spawn(move |_| {
                 // Shuffle into a sub-FFT
-                let omega_j = omega.pow_vartime(&[j as u64, 0, 0, 0]);
+                let omega_j = twiddles[j * stride];
                 let omega_step = omega.pow_vartime(&[(j as u64) << log_new_n, 0, 0, 0]);
 
                 let mut elt = G::Scalar::one();
@@ -253,7 +672,7 @@ fn parallel_fft<G: Group>(a: &mut [G], omega: G::Scalar, log_n: u32, log_threads
                 }
 
                 // Perform sub-FFT
-                serial_fft(tmp, new_omega, log_new_n);
+                serial_fft(tmp, twiddles, new_stride, log_new_n);
             });
         }
     });
@@ -274,7 +693,7 @@ pub fn eval_polynomial<F: Field>(poly: &[F], point: F) -> F {
     }
     let n = poly.len();
     let num_threads = multicore::current_num_threads();
-    if n * 2 < num_threads {
+    if num_threads == 1 || n * 2 < num_threads {
         evaluate(poly, point)
     } else {
         let chunk_size = (n + num_threads - 1) / num_threads;
@@ -428,7 +847,7 @@ pub(crate) fn evaluate_vanishing_polynomial<F: FieldExt>(roots: &[F], z: F) -> F
     }
     let n = roots.len();
     let num_threads = multicore::current_num_threads();
-    if n * 2 < num_threads {
+    if num_threads == 1 || n * 2 < num_threads {
         evaluate(roots, z)
     } else {
         let chunk_size = (n + num_threads - 1) / num_threads;
@@ -467,3 +886,167 @@ fn test_lagrange_interpolate() {
         }
     }
 }
+
+#[test]
+fn test_fft_with_twiddles() {
+    let rng = OsRng;
+
+    // Twiddles for a domain of size 2^6, shared with all of its subdomains.
+    let max_log_n = 6;
+    let max_omega = Fp::root_of_unity().pow_vartime(&[1 << (Fp::S - max_log_n), 0, 0, 0]);
+    let twiddles = compute_twiddles(max_omega, max_log_n);
+
+    for log_n in 0..=max_log_n {
+        let omega = max_omega.pow_vartime(&[1 << (max_log_n - log_n), 0, 0, 0]);
+        let coeffs = (0..(1 << log_n))
+            .map(|_| Fp::random(rng))
+            .collect::<Vec<_>>();
+
+        let mut expected = coeffs.clone();
+        best_fft(&mut expected, omega, log_n);
+        let mut actual = coeffs.clone();
+        best_fft_with_twiddles(&mut actual, omega, &twiddles, log_n);
+        assert_eq!(expected, actual);
+
+        // Check against direct evaluation of the polynomial.
+        for (i, eval) in expected.iter().enumerate() {
+            let point = omega.pow_vartime(&[i as u64, 0, 0, 0]);
+            assert_eq!(eval_polynomial(&coeffs, point), *eval);
+        }
+    }
+}
+
+#[test]
+fn test_multiexp_window() {
+    use group::prime::PrimeCurveAffine;
+    use pairing::bn256::G1Affine;
+
+    let rng = OsRng;
+    let n = 100;
+    let coeffs = (0..n).map(|_| Fp::random(rng)).collect::<Vec<_>>();
+    let bases = (0..n)
+        .map(|_| (G1Affine::generator() * Fp::random(rng)).to_affine())
+        .collect::<Vec<_>>();
+
+    let expected = small_multiexp(&coeffs, &bases);
+    assert_eq!(best_multiexp(&coeffs, &bases), expected);
+    for c in 1..12 {
+        assert_eq!(best_multiexp_with_window(&coeffs, &bases, c), expected);
+    }
+
+    assert_eq!(MultiexpWindows::default().window(n), multiexp_window(n));
+
+    let windows = MultiexpWindows::autotune::<G1Affine>(4);
+    assert_eq!(windows.as_slice().len(), 5);
+    assert!(windows.as_slice().iter().all(|c| *c > 0));
+    assert_eq!(windows.window(16), windows.as_slice()[4]);
+    assert_eq!(windows.window(31), windows.as_slice()[4]);
+    assert_eq!(windows.window(n), multiexp_window(n));
+    assert_eq!(
+        best_multiexp_with_windows(&coeffs, &bases, &windows, &mut MultiexpBuffer::new()),
+        expected
+    );
+
+    // Tuning doesn't change the windows used elsewhere.
+    assert_eq!(MultiexpWindows::default().window(16), multiexp_window(16));
+}
+
+#[test]
+fn test_constant_time_multiexp() {
+    use group::prime::PrimeCurveAffine;
+    use pairing::bn256::G1Affine;
+
+    let rng = OsRng;
+    let n = 50;
+    let mut coeffs = (0..n).map(|_| Fp::random(rng)).collect::<Vec<_>>();
+    coeffs[0] = Fp::zero();
+    coeffs[1] = -Fp::one();
+    let bases = (0..n)
+        .map(|_| (G1Affine::generator() * Fp::random(rng)).to_affine())
+        .collect::<Vec<_>>();
+
+    assert_eq!(
+        constant_time_multiexp(&coeffs, &bases),
+        small_multiexp(&coeffs, &bases)
+    );
+    assert_eq!(
+        constant_time_multiexp::<G1Affine>(&[], &[]),
+        <G1Affine as PrimeCurveAffine>::Curve::identity()
+    );
+}
+
+#[cfg(feature = "gpu")]
+#[test]
+fn test_gpu_multiexp() {
+    use group::prime::PrimeCurveAffine;
+    use pairing::bn256::G1Affine;
+
+    let rng = OsRng;
+    let n = GPU_MULTIEXP_THRESHOLD;
+    let coeffs = (0..n).map(|_| Fp::random(rng)).collect::<Vec<_>>();
+    let bases = (0..n)
+        .map(|_| (G1Affine::generator() * Fp::random(rng)).to_affine())
+        .collect::<Vec<_>>();
+
+    // The CPU fallback is taken if this machine has no usable GPU.
+    let expected = best_multiexp_with_window(&coeffs, &bases, multiexp_window(n));
+    if let Some(result) = gpu_multiexp(&coeffs, &bases) {
+        assert_eq!(result, expected);
+    }
+    assert_eq!(best_multiexp(&coeffs, &bases), expected);
+}
+
+#[cfg(feature = "multicore")]
+#[test]
+fn test_single_thread() {
+    use group::prime::PrimeCurveAffine;
+    use pairing::bn256::G1Affine;
+
+    let rng = OsRng;
+    let pool = multicore::ThreadPoolBuilder::new()
+        .num_threads(1)
+        .build()
+        .unwrap();
+
+    // On a single thread, the serial code paths are taken.
+    multicore::with_thread_pool(&pool, || {
+        let log_n = 5;
+        let omega = Fp::root_of_unity().pow_vartime(&[1 << (Fp::S - log_n), 0, 0, 0]);
+        let coeffs = (0..(1 << log_n))
+            .map(|_| Fp::random(rng))
+            .collect::<Vec<_>>();
+        let mut evals = coeffs.clone();
+        best_fft(&mut evals, omega, log_n);
+        for (i, eval) in evals.iter().enumerate() {
+            let point = omega.pow_vartime(&[i as u64, 0, 0, 0]);
+            assert_eq!(eval_polynomial(&coeffs, point), *eval);
+        }
+
+        let bases = (0..coeffs.len())
+            .map(|_| (G1Affine::generator() * Fp::random(rng)).to_affine())
+            .collect::<Vec<_>>();
+        let expected = small_multiexp(&coeffs, &bases);
+        assert_eq!(best_multiexp(&coeffs, &bases), expected);
+        assert_eq!(constant_time_multiexp(&coeffs, &bases), expected);
+    });
+}
+
+#[test]
+fn test_multiexp_buffer() {
+    use group::prime::PrimeCurveAffine;
+    use pairing::bn256::G1Affine;
+
+    let rng = OsRng;
+    let mut buffer = MultiexpBuffer::new();
+    for n in [1, 3, 50, 200] {
+        let coeffs = (0..n).map(|_| Fp::random(rng)).collect::<Vec<_>>();
+        let bases = (0..n)
+            .map(|_| (G1Affine::generator() * Fp::random(rng)).to_affine())
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            best_multiexp_with_buffer(&coeffs, &bases, &mut buffer),
+            small_multiexp(&coeffs, &bases)
+        );
+    }
+}
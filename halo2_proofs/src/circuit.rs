@@ -3,6 +3,10 @@
 use std::{convert::TryInto, fmt, marker::PhantomData};
 
 use ff::Field;
+#[cfg(feature = "multicore")]
+use rayon::iter::{
+    IndexedParallelIterator, IntoParallelIterator, IntoParallelRefIterator, ParallelIterator,
+};
 
 use crate::{
     arithmetic::FieldExt,
@@ -12,8 +16,12 @@ use crate::{
 pub mod floor_planner;
 pub use floor_planner::single_pass::SimpleFloorPlanner;
 
+pub mod config;
 pub mod layouter;
 
+mod value;
+pub use value::Value;
+
 /// A chip implements a set of instructions that can be used by gadgets.
 ///
 /// The chip stores state that is required at circuit synthesis time in
@@ -106,6 +114,12 @@ impl<V, F: Field> AssignedCell<V, F> {
         self.value.as_ref()
     }
 
+    /// Returns the value of the [`AssignedCell`] as a [`Value`], which can be
+    /// computed with whether or not it is known.
+    pub fn witness(&self) -> Value<&V> {
+        self.value.as_ref().into()
+    }
+
     /// Returns the cell.
     pub fn cell(&self) -> Cell {
         self.cell
@@ -122,6 +136,18 @@ where
     }
 }
 
+impl<F: Field> AssignedCell<Assigned<F>, F> {
+    /// Evaluates this cell's value, returning it as a cell holding a field element.
+    /// The cell itself is unchanged.
+    pub fn evaluate(self) -> AssignedCell<F, F> {
+        AssignedCell {
+            value: self.value.map(|v| v.evaluate()),
+            cell: self.cell,
+            _marker: PhantomData,
+        }
+    }
+}
+
 impl<V: Clone, F: Field> AssignedCell<V, F>
 where
     for<'v> Assigned<F>: From<&'v V>,
@@ -208,7 +234,9 @@ impl<'r, F: Field> Region<'r, F> {
         let cell =
             self.region
                 .assign_advice(&|| annotation().into(), column, offset, &mut || {
-                    let v = to()?;
+                    let v = to().map_err(|e| {
+                        e.in_assignment(|| annotation().into(), column.into(), offset)
+                    })?;
                     let value_f = (&v).into();
                     value = Some(v);
                     Ok(value_f)
@@ -224,7 +252,8 @@ impl<'r, F: Field> Region<'r, F> {
     /// Assigns a constant value to the column `advice` at `offset` within this region.
     ///
     /// The constant value will be assigned to a cell within one of the fixed columns
-    /// configured via `ConstraintSystem::enable_constant`.
+    /// configured via `ConstraintSystem::enable_constant`. The floor planner assigns
+    /// each distinct constant value once, and copies it to every cell that uses it.
     ///
     /// Returns the advice cell.
     pub fn assign_advice_from_constant<VR, A, AR>(
@@ -304,7 +333,9 @@ impl<'r, F: Field> Region<'r, F> {
         let cell =
             self.region
                 .assign_fixed(&|| annotation().into(), column, offset, &mut || {
-                    let v = to()?;
+                    let v = to().map_err(|e| {
+                        e.in_assignment(|| annotation().into(), column.into(), offset)
+                    })?;
                     let value_f = (&v).into();
                     value = Some(v);
                     Ok(value_f)
@@ -334,6 +365,58 @@ impl<'r, F: Field> Region<'r, F> {
     pub fn constrain_equal(&mut self, left: Cell, right: Cell) -> Result<(), Error> {
         self.region.constrain_equal(left, right)
     }
+
+    /// Asserts that a cell has a constant value.
+    ///
+    /// This constrains the cell like [`Region::constrain_constant`], but
+    /// [`MockProver`] reports a violated assertion as a
+    /// [`VerifyFailure::Assertion`] described by `annotation`, instead of as an
+    /// unsatisfied equality constraint.
+    ///
+    /// Returns an error if the cell is in a column where equality has not been enabled.
+    ///
+    /// [`MockProver`]: crate::dev::MockProver
+    /// [`VerifyFailure::Assertion`]: crate::dev::VerifyFailure::Assertion
+    pub fn assert_equal_constant<A, AR, VR>(
+        &mut self,
+        annotation: A,
+        cell: Cell,
+        constant: VR,
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+        VR: Into<Assigned<F>>,
+    {
+        self.region.constrain_constant(cell, constant.into())?;
+        self.region.record_assertion(&|| annotation().into(), cell)
+    }
+
+    /// Asserts that two cells have the same value.
+    ///
+    /// This constrains the cells like [`Region::constrain_equal`], but
+    /// [`MockProver`] reports a violated assertion as a
+    /// [`VerifyFailure::Assertion`] on `left` described by `annotation`, instead of
+    /// as an unsatisfied equality constraint.
+    ///
+    /// Returns an error if either of the cells are in columns where equality
+    /// has not been enabled.
+    ///
+    /// [`MockProver`]: crate::dev::MockProver
+    /// [`VerifyFailure::Assertion`]: crate::dev::VerifyFailure::Assertion
+    pub fn assert_equal<A, AR>(
+        &mut self,
+        annotation: A,
+        left: Cell,
+        right: Cell,
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        self.region.constrain_equal(left, right)?;
+        self.region.record_assertion(&|| annotation().into(), left)
+    }
 }
 
 /// A lookup table in the circuit.
@@ -372,6 +455,179 @@ impl<'r, F: Field> Table<'r, F> {
                 to().map(|v| v.into())
             })
     }
+
+    /// Assigns `rows` to the table, starting at offset 0. Each row holds one
+    /// value for each of `columns`, in order.
+    ///
+    /// The columns are filled in bulk, which is much faster than assigning
+    /// large tables with [`Table::assign_cell`].
+    ///
+    /// Returns an error if a row doesn't hold a value for each column, or if
+    /// any of the columns has already been assigned to.
+    pub fn assign_table_from_iter<A, AR, I, R>(
+        &mut self,
+        annotation: A,
+        columns: &[TableColumn],
+        rows: I,
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+        I: IntoIterator<Item = R>,
+        I::IntoIter: ExactSizeIterator,
+        R: AsRef<[F]>,
+    {
+        let rows = rows.into_iter();
+        let mut values: Vec<Vec<Assigned<F>>> = columns
+            .iter()
+            .map(|_| Vec::with_capacity(rows.len()))
+            .collect();
+        for row in rows {
+            let row = row.as_ref();
+            if row.len() != columns.len() {
+                return Err(Error::Synthesis);
+            }
+            for (column, value) in values.iter_mut().zip(row) {
+                column.push((*value).into());
+            }
+        }
+
+        self.assign_columns(annotation, columns, &values)
+    }
+
+    /// Assigns `rows` to the table in the same way as
+    /// [`Table::assign_table_from_iter`], generating the rows and splitting them
+    /// into columns in parallel.
+    #[cfg(feature = "multicore")]
+    pub fn assign_table_from_par_iter<A, AR, I, R>(
+        &mut self,
+        annotation: A,
+        columns: &[TableColumn],
+        rows: I,
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+        I: IndexedParallelIterator<Item = R>,
+        R: AsRef<[F]> + Send + Sync,
+    {
+        let rows: Vec<R> = rows.collect();
+        if rows
+            .par_iter()
+            .any(|row| row.as_ref().len() != columns.len())
+        {
+            return Err(Error::Synthesis);
+        }
+        let values: Vec<Vec<Assigned<F>>> = (0..columns.len())
+            .into_par_iter()
+            .map(|index| {
+                rows.par_iter()
+                    .map(|row| row.as_ref()[index].into())
+                    .collect()
+            })
+            .collect();
+
+        self.assign_columns(annotation, columns, &values)
+    }
+
+    /// Sets the value that fills the rows of `column` after its last assigned
+    /// row. By default, these rows repeat the value assigned at offset 0, which
+    /// may collide with a real entry of another table sharing the column.
+    ///
+    /// Returns an error if `column` already has a default value, or if it is
+    /// never assigned to.
+    pub fn set_default<V>(&mut self, column: TableColumn, value: V) -> Result<(), Error>
+    where
+        V: Into<Assigned<F>>,
+    {
+        self.table.set_default(column, value.into())
+    }
+
+    /// Lays out several logical tables, each a list of rows holding one value
+    /// for each of `columns`, one after the other in the same columns. The
+    /// `tag` column holds the tag of the logical table each row belongs to,
+    /// so that a lookup can be restricted to one of them by including its tag
+    /// as the first input:
+    ///
+    /// ```ignore
+    /// meta.lookup("xor", |meta| {
+    ///     let q = meta.query_selector(q_xor);
+    ///     vec![(q.clone() * Expression::Constant(XOR_TAG), tag), (q * a, value)]
+    /// });
+    /// ```
+    ///
+    /// Row 0, and every row after the last logical table, holds zero in each
+    /// column, so that the all-zero input of a disabled lookup is in the
+    /// table while no unused row matches a tagged entry.
+    ///
+    /// Returns an error if a tag is zero or repeated, if a row doesn't hold a
+    /// value for each column, or if any of the columns has already been
+    /// assigned to.
+    pub fn assign_tagged_tables<A, AR, R>(
+        &mut self,
+        annotation: A,
+        tag: TableColumn,
+        columns: &[TableColumn],
+        tables: &[(F, &[R])],
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+        R: AsRef<[F]>,
+    {
+        for (i, (table_tag, _)) in tables.iter().enumerate() {
+            if table_tag.is_zero_vartime()
+                || tables[..i].iter().any(|(other, _)| other == table_tag)
+            {
+                return Err(Error::Synthesis);
+            }
+        }
+
+        let len = 1 + tables.iter().map(|(_, rows)| rows.len()).sum::<usize>();
+        let mut tags = Vec::with_capacity(len);
+        let mut values: Vec<Vec<Assigned<F>>> =
+            columns.iter().map(|_| Vec::with_capacity(len)).collect();
+        tags.push(Assigned::Zero);
+        for column in values.iter_mut() {
+            column.push(Assigned::Zero);
+        }
+        for (table_tag, rows) in tables {
+            for row in rows.iter() {
+                let row = row.as_ref();
+                if row.len() != columns.len() {
+                    return Err(Error::Synthesis);
+                }
+                tags.push((*table_tag).into());
+                for (column, value) in values.iter_mut().zip(row) {
+                    column.push((*value).into());
+                }
+            }
+        }
+
+        for column in Some(&tag).into_iter().chain(columns) {
+            self.table.set_default(*column, Assigned::Zero)?;
+        }
+        self.table
+            .assign_column(&|| annotation().into(), tag, &tags)?;
+        self.assign_columns(annotation, columns, &values)
+    }
+
+    fn assign_columns<A, AR>(
+        &mut self,
+        annotation: A,
+        columns: &[TableColumn],
+        values: &[Vec<Assigned<F>>],
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        for (column, values) in columns.iter().zip(values) {
+            self.table
+                .assign_column(&|| annotation().into(), *column, values)?;
+        }
+        Ok(())
+    }
 }
 
 /// A layout strategy within a circuit. The layouter is chip-agnostic and applies its
@@ -425,6 +681,24 @@ pub trait Layouter<F: Field> {
         row: usize,
     ) -> Result<(), Error>;
 
+    /// Records the value of `cell` as an output of the circuit, named `name`.
+    ///
+    /// Outputs let applications reuse values computed in the circuit, such as
+    /// hashes, instead of duplicating the computation outside of it: they are
+    /// returned by [`circuit_outputs`] after synthesizing the circuit. Recording an
+    /// output doesn't constrain the cell; use [`Layouter::constrain_instance`] to
+    /// expose it as a public input.
+    ///
+    /// [`circuit_outputs`]: crate::plonk::circuit_outputs
+    fn output<V, N, NR>(&mut self, _name: N, _cell: &AssignedCell<V, F>) -> Result<(), Error>
+    where
+        V: Clone + Into<Assigned<F>>,
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        Ok(())
+    }
+
     /// Gets the "root" of this assignment, bypassing the namespacing.
     ///
     /// Not intended for downstream consumption; use [`Layouter::namespace`] instead.
@@ -490,6 +764,15 @@ impl<'a, F: Field, L: Layouter<F> + 'a> Layouter<F> for NamespacedLayouter<'a, F
         self.0.constrain_instance(cell, column, row)
     }
 
+    fn output<V, N, NR>(&mut self, name: N, cell: &AssignedCell<V, F>) -> Result<(), Error>
+    where
+        V: Clone + Into<Assigned<F>>,
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        self.0.output(name, cell)
+    }
+
     fn get_root(&mut self) -> &mut Self::Root {
         self.0.get_root()
     }
@@ -0,0 +1,227 @@
+//! Composing chip configurations inside [`Circuit::configure`].
+//!
+//! [`Circuit::configure`]: crate::plonk::Circuit::configure
+
+use std::fmt;
+
+use super::Chip;
+use crate::{
+    arithmetic::FieldExt,
+    plonk::{Advice, Column, ConstraintSystem, Fixed},
+};
+
+/// How a column requested from a [`ColumnAllocator`] may be shared with other chips.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColumnSharing {
+    /// The column may be handed to other chips as well. The `i`-th shared column
+    /// requested by each chip is the same column, so a circuit only has as many
+    /// shared columns as its widest chip.
+    ///
+    /// This is sound as long as the chips only constrain the column through
+    /// selector-gated constraints; the layouter keeps their regions apart.
+    Shared,
+    /// The column is allocated for the requesting chip alone, e.g. because the chip
+    /// constrains it on every row or uses it as a lookup input.
+    Exclusive,
+}
+
+/// A chip that can configure itself from columns handed out by a [`ColumnAllocator`].
+pub trait ConfigureChip<F: FieldExt>: Chip<F> {
+    /// Allocates the columns this chip uses, creates its gates and lookups, and returns
+    /// its configuration.
+    fn configure(columns: &mut ColumnAllocator<'_, F>) -> Self::Config;
+}
+
+/// Hands out columns to the chips of a circuit, reusing columns between chips where
+/// they allow it.
+///
+/// ```
+/// use halo2_proofs::{
+///     circuit::config::{ColumnAllocator, ColumnSharing},
+///     plonk::ConstraintSystem,
+/// };
+/// use pairing::bn256::Fr;
+///
+/// let mut meta = ConstraintSystem::<Fr>::default();
+/// let mut columns = ColumnAllocator::new(&mut meta);
+///
+/// let (a, b) = columns.chip(|columns| {
+///     let a = columns.advice_column(ColumnSharing::Shared);
+///     let b = columns.advice_column(ColumnSharing::Exclusive);
+///     (a, b)
+/// });
+/// let c = columns.chip(|columns| columns.advice_column(ColumnSharing::Shared));
+///
+/// assert_eq!(a, c);
+/// assert_ne!(b, c);
+/// ```
+pub struct ColumnAllocator<'a, F: FieldExt> {
+    meta: &'a mut ConstraintSystem<F>,
+    shared_advice: Vec<Column<Advice>>,
+    shared_fixed: Vec<Column<Fixed>>,
+    next_advice: usize,
+    next_fixed: usize,
+}
+
+impl<'a, F: FieldExt> fmt::Debug for ColumnAllocator<'a, F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColumnAllocator")
+            .field("shared_advice", &self.shared_advice)
+            .field("shared_fixed", &self.shared_fixed)
+            .field("next_advice", &self.next_advice)
+            .field("next_fixed", &self.next_fixed)
+            .finish()
+    }
+}
+
+impl<'a, F: FieldExt> ColumnAllocator<'a, F> {
+    /// Creates an allocator that adds columns to `meta`.
+    pub fn new(meta: &'a mut ConstraintSystem<F>) -> Self {
+        ColumnAllocator {
+            meta,
+            shared_advice: vec![],
+            shared_fixed: vec![],
+            next_advice: 0,
+            next_fixed: 0,
+        }
+    }
+
+    /// Returns the underlying constraint system, for creating gates, lookups,
+    /// selectors and instance columns.
+    pub fn meta(&mut self) -> &mut ConstraintSystem<F> {
+        self.meta
+    }
+
+    /// Configures the chip `C`.
+    pub fn configure<C: ConfigureChip<F>>(&mut self) -> C::Config {
+        self.chip(C::configure)
+    }
+
+    /// Configures a chip with `configure`, which may take arguments that
+    /// [`ConfigureChip`] has no room for, such as columns shared with another chip.
+    ///
+    /// Shared columns requested by `configure` are numbered from zero again, so they
+    /// overlap with the shared columns of previously configured chips.
+    pub fn chip<T>(&mut self, configure: impl FnOnce(&mut Self) -> T) -> T {
+        self.next_advice = 0;
+        self.next_fixed = 0;
+        configure(self)
+    }
+
+    /// Returns an advice column for the current chip.
+    pub fn advice_column(&mut self, sharing: ColumnSharing) -> Column<Advice> {
+        match sharing {
+            ColumnSharing::Exclusive => self.meta.advice_column(),
+            ColumnSharing::Shared => {
+                if self.next_advice == self.shared_advice.len() {
+                    let column = self.meta.advice_column();
+                    self.shared_advice.push(column);
+                }
+                self.next_advice += 1;
+                self.shared_advice[self.next_advice - 1]
+            }
+        }
+    }
+
+    /// Returns an advice column for the current chip with equality constraints
+    /// enabled on it.
+    pub fn advice_column_with_equality(&mut self, sharing: ColumnSharing) -> Column<Advice> {
+        let column = self.advice_column(sharing);
+        self.meta.enable_equality(column);
+        column
+    }
+
+    /// Returns a fixed column for the current chip.
+    pub fn fixed_column(&mut self, sharing: ColumnSharing) -> Column<Fixed> {
+        match sharing {
+            ColumnSharing::Exclusive => self.meta.fixed_column(),
+            ColumnSharing::Shared => {
+                if self.next_fixed == self.shared_fixed.len() {
+                    let column = self.meta.fixed_column();
+                    self.shared_fixed.push(column);
+                }
+                self.next_fixed += 1;
+                self.shared_fixed[self.next_fixed - 1]
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use pairing::bn256::Fr as Fp;
+
+    use super::{ColumnAllocator, ColumnSharing, ConfigureChip};
+    use crate::{
+        arithmetic::FieldExt,
+        circuit::Chip,
+        plonk::{Advice, Any, Column, ConstraintSystem, Fixed, Selector},
+    };
+
+    #[derive(Clone, Debug)]
+    struct BitConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Fixed>,
+        s: Selector,
+    }
+
+    struct BitChip<F: FieldExt> {
+        config: BitConfig,
+        _marker: PhantomData<F>,
+    }
+
+    impl<F: FieldExt> Chip<F> for BitChip<F> {
+        type Config = BitConfig;
+        type Loaded = ();
+
+        fn config(&self) -> &Self::Config {
+            &self.config
+        }
+
+        fn loaded(&self) -> &Self::Loaded {
+            &()
+        }
+    }
+
+    impl<F: FieldExt> ConfigureChip<F> for BitChip<F> {
+        fn configure(columns: &mut ColumnAllocator<'_, F>) -> Self::Config {
+            let a = columns.advice_column_with_equality(ColumnSharing::Shared);
+            let b = columns.advice_column(ColumnSharing::Shared);
+            let c = columns.fixed_column(ColumnSharing::Shared);
+            let s = columns.meta().selector();
+            columns.meta().boolean_gate(s, b);
+            BitConfig { a, b, c, s }
+        }
+    }
+
+    #[test]
+    fn shared_columns_are_reused() {
+        let mut meta = ConstraintSystem::<Fp>::default();
+        let mut columns = ColumnAllocator::new(&mut meta);
+
+        let first = columns.configure::<BitChip<Fp>>();
+        let second = columns.configure::<BitChip<Fp>>();
+        let exclusive = columns.chip(|columns| {
+            (
+                columns.advice_column(ColumnSharing::Shared),
+                columns.advice_column(ColumnSharing::Exclusive),
+                columns.advice_column(ColumnSharing::Shared),
+                columns.advice_column(ColumnSharing::Shared),
+            )
+        });
+
+        assert_eq!((first.a, first.b, first.c), (second.a, second.b, second.c));
+        assert_ne!(first.s, second.s);
+        assert_eq!(exclusive.0, first.a);
+        assert_eq!(exclusive.2, first.b);
+        assert!(![first.a, first.b, exclusive.3].contains(&exclusive.1));
+
+        assert_eq!(meta.num_advice_columns, 4);
+        assert_eq!(meta.num_fixed_columns, 1);
+        let equality: Column<Any> = first.a.into();
+        assert_eq!(meta.permutation.get_columns(), vec![equality]);
+    }
+}
@@ -3,4 +3,4 @@
 pub(super) mod single_pass;
 
 mod v1;
-pub use v1::{V1Pass, V1};
+pub use v1::{LayoutProfile, LayoutProfileSource, ProfiledV1, V1Pass, V1};
@@ -8,7 +8,7 @@ use ff::Field;
 use crate::{
     circuit::{
         layouter::{RegionColumn, RegionLayouter, RegionShape, TableLayouter},
-        Cell, Layouter, Region, RegionIndex, RegionStart, Table,
+        AssignedCell, Cell, Layouter, Region, RegionIndex, RegionStart, Table,
     },
     plonk::{
         Advice, Any, Assigned, Assignment, Circuit, Column, Error, Fixed, FloorPlanner, Instance,
@@ -40,12 +40,17 @@ impl FloorPlanner for SimpleFloorPlanner {
 pub struct SingleChipLayouter<'a, F: Field, CS: Assignment<F> + 'a> {
     cs: &'a mut CS,
     constants: Vec<Column<Fixed>>,
+    /// Stores the constants assigned so far, and the cells of the constants columns
+    /// they were assigned to.
+    assigned_constants: Vec<(F, Column<Fixed>, usize)>,
     /// Stores the starting row for each region.
     regions: Vec<RegionStart>,
     /// Stores the first empty row for each column.
     columns: HashMap<RegionColumn, usize>,
     /// Stores the table fixed columns.
     table_columns: Vec<TableColumn>,
+    /// Stores the names of the namespaces we are in, for reporting failed assignments.
+    namespaces: Vec<String>,
     _marker: PhantomData<F>,
 }
 
@@ -64,9 +69,11 @@ impl<'a, F: Field, CS: Assignment<F>> SingleChipLayouter<'a, F, CS> {
         let ret = SingleChipLayouter {
             cs,
             constants,
+            assigned_constants: vec![],
             regions: vec![],
             columns: HashMap::default(),
             table_columns: vec![],
+            namespaces: vec![],
             _marker: PhantomData,
         };
         Ok(ret)
@@ -105,41 +112,67 @@ impl<'a, F: Field, CS: Assignment<F> + 'a> Layouter<F> for SingleChipLayouter<'a
         }
 
         // Assign region cells.
-        self.cs.enter_region(name);
+        self.cs.enter_region(&name);
         let mut region = SingleChipLayouterRegion::new(self, region_index.into());
         let result = {
             let region: &mut dyn RegionLayouter<F> = &mut region;
             assignment(region.into())
-        }?;
+        };
         let constants_to_assign = region.constants;
+        let result = result.map_err(|e| e.in_region(|| name().into(), &self.namespaces))?;
         self.cs.exit_region();
 
-        // Assign constants. For the simple floor planner, we assign constants in order in
-        // the first `constants` column.
+        // Assign constants. For the simple floor planner, we assign each constant to the
+        // next row of the `constants` column that has the fewest rows in use, so that
+        // they are packed across all of the columns, reusing the cell of any constant
+        // with the same value that has already been assigned.
         if self.constants.is_empty() {
             if !constants_to_assign.is_empty() {
                 return Err(Error::NotEnoughColumnsForConstants);
             }
         } else {
-            let constants_column = self.constants[0];
-            let next_constant_row = self
-                .columns
-                .entry(Column::<Any>::from(constants_column).into())
-                .or_default();
             for (constant, advice) in constants_to_assign {
-                self.cs.assign_fixed(
-                    || format!("Constant({:?})", constant.evaluate()),
-                    constants_column,
-                    *next_constant_row,
-                    || Ok(constant),
-                )?;
+                let value = constant.evaluate();
+                let (constants_column, constant_row) = match self
+                    .assigned_constants
+                    .iter()
+                    .find(|(assigned, _, _)| *assigned == value)
+                {
+                    Some((_, column, row)) => (*column, *row),
+                    None => {
+                        let constants_column = *self
+                            .constants
+                            .iter()
+                            .min_by_key(|c| {
+                                self.columns
+                                    .get(&Column::<Any>::from(**c).into())
+                                    .cloned()
+                                    .unwrap_or(0)
+                            })
+                            .expect("there is a constants column");
+                        let next_constant_row = self
+                            .columns
+                            .entry(Column::<Any>::from(constants_column).into())
+                            .or_default();
+                        let row = *next_constant_row;
+                        *next_constant_row += 1;
+
+                        self.cs.assign_fixed(
+                            || format!("Constant({:?})", value),
+                            constants_column,
+                            row,
+                            || Ok(constant),
+                        )?;
+                        self.assigned_constants.push((value, constants_column, row));
+                        (constants_column, row)
+                    }
+                };
                 self.cs.copy(
                     constants_column.into(),
-                    *next_constant_row,
+                    constant_row,
                     advice.column,
                     *self.regions[*advice.region_index] + advice.row_offset,
                 )?;
-                *next_constant_row += 1;
             }
         }
 
@@ -161,6 +194,7 @@ impl<'a, F: Field, CS: Assignment<F> + 'a> Layouter<F> for SingleChipLayouter<'a
             assignment(table.into())
         }?;
         let default_and_assigned = table.default_and_assigned;
+        let defaults = table.defaults;
         self.cs.exit_region();
 
         // Check that all table columns have the same length `first_unused`,
@@ -184,6 +218,14 @@ impl<'a, F: Field, CS: Assignment<F> + 'a> Layouter<F> for SingleChipLayouter<'a
             }
         };
 
+        // A column with a default value must also have been assigned.
+        if defaults
+            .keys()
+            .any(|column| !default_and_assigned.contains_key(column))
+        {
+            return Err(Error::Synthesis); // TODO better error
+        }
+
         // Record these columns so that we can prevent them from being used again.
         for column in default_and_assigned.keys() {
             self.table_columns.push(*column);
@@ -193,8 +235,13 @@ impl<'a, F: Field, CS: Assignment<F> + 'a> Layouter<F> for SingleChipLayouter<'a
             // default_val must be Some because we must have assigned
             // at least one cell in each column, and in that case we checked
             // that all cells up to first_unused were assigned.
+            // An explicit default value takes precedence over it.
+            let default_val = match defaults.get(&col) {
+                Some(value) => Some(*value),
+                None => default_val.unwrap(),
+            };
             self.cs
-                .fill_from_row(col.inner(), first_unused, default_val.unwrap())?;
+                .fill_from_row(col.inner(), first_unused, default_val)?;
         }
 
         Ok(())
@@ -214,6 +261,16 @@ impl<'a, F: Field, CS: Assignment<F> + 'a> Layouter<F> for SingleChipLayouter<'a
         )
     }
 
+    fn output<V, N, NR>(&mut self, name: N, cell: &AssignedCell<V, F>) -> Result<(), Error>
+    where
+        V: Clone + Into<Assigned<F>>,
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        self.cs
+            .output(name, cell.value().cloned().map(|v| v.into()))
+    }
+
     fn get_root(&mut self) -> &mut Self::Root {
         self
     }
@@ -223,10 +280,13 @@ impl<'a, F: Field, CS: Assignment<F> + 'a> Layouter<F> for SingleChipLayouter<'a
         NR: Into<String>,
         N: FnOnce() -> NR,
     {
-        self.cs.push_namespace(name_fn)
+        let name = name_fn().into();
+        self.namespaces.push(name.clone());
+        self.cs.push_namespace(|| name)
     }
 
     fn pop_namespace(&mut self, gadget_name: Option<String>) {
+        self.namespaces.pop();
         self.cs.pop_namespace(gadget_name)
     }
 }
@@ -369,6 +429,18 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> RegionLayouter<F>
 
         Ok(())
     }
+
+    fn record_assertion(
+        &mut self,
+        annotation: &dyn Fn() -> String,
+        cell: Cell,
+    ) -> Result<(), Error> {
+        self.layouter.cs.record_assertion(
+            annotation,
+            cell.column,
+            *self.layouter.regions[*cell.region_index] + cell.row_offset,
+        )
+    }
 }
 
 /// The default value to fill a table column with.
@@ -385,6 +457,9 @@ pub(crate) struct SimpleTableLayouter<'r, 'a, F: Field, CS: Assignment<F> + 'a>
     used_columns: &'r [TableColumn],
     // maps from a fixed column to a pair (default value, vector saying which rows are assigned)
     pub(crate) default_and_assigned: HashMap<TableColumn, (DefaultTableValue<F>, Vec<bool>)>,
+    // maps from a fixed column to the value set with `set_default`, which overrides the
+    // value at offset 0 when filling the unused rows of the table
+    pub(crate) defaults: HashMap<TableColumn, Assigned<F>>,
 }
 
 impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> fmt::Debug for SimpleTableLayouter<'r, 'a, F, CS> {
@@ -392,6 +467,7 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> fmt::Debug for SimpleTableLayoute
         f.debug_struct("SimpleTableLayouter")
             .field("used_columns", &self.used_columns)
             .field("default_and_assigned", &self.default_and_assigned)
+            .field("defaults", &self.defaults)
             .finish()
     }
 }
@@ -402,6 +478,7 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> SimpleTableLayouter<'r, 'a, F, CS
             cs,
             used_columns,
             default_and_assigned: HashMap::default(),
+            defaults: HashMap::default(),
         }
     }
 }
@@ -449,16 +526,70 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> TableLayouter<F>
 
         Ok(())
     }
+
+    fn assign_column(
+        &mut self,
+        annotation: &dyn Fn() -> String,
+        column: TableColumn,
+        values: &[Assigned<F>],
+    ) -> Result<(), Error> {
+        if self.used_columns.contains(&column) {
+            return Err(Error::Synthesis); // TODO better error
+        }
+        let first = match values.first() {
+            Some(first) => *first,
+            None => return Ok(()),
+        };
+
+        let entry = self.default_and_assigned.entry(column).or_default();
+
+        // As with `assign_cell`, the value at offset 0 is the default value for
+        // this table column, and may only be assigned once.
+        if entry.0.is_some() {
+            return Err(Error::Synthesis); // TODO better error
+        }
+
+        self.cs
+            .assign_fixed_column(annotation, column.inner(), 0, values)?;
+
+        entry.0 = Some(Some(first));
+        if entry.1.len() < values.len() {
+            entry.1.resize(values.len(), false);
+        }
+        for assigned in &mut entry.1[..values.len()] {
+            *assigned = true;
+        }
+
+        Ok(())
+    }
+
+    fn set_default(&mut self, column: TableColumn, value: Assigned<F>) -> Result<(), Error> {
+        if self.used_columns.contains(&column) {
+            return Err(Error::Synthesis); // TODO better error
+        }
+        if self.defaults.insert(column, value).is_some() {
+            return Err(Error::Synthesis); // TODO better error
+        }
+        Ok(())
+    }
 }
 
 #[cfg(test)]
 mod tests {
-    use pairing::bn256::Fr as Scalar;
+    use ff::Field;
+    use pairing::bn256::{Fr as Scalar, G1};
+
+    use std::marker::PhantomData;
 
     use super::SimpleFloorPlanner;
     use crate::{
-        dev::MockProver,
-        plonk::{Advice, Circuit, Column, Error},
+        circuit::{floor_planner::V1, Layouter, Value},
+        dev::{CircuitCost, MockProver},
+        plonk::{
+            Advice, Any, Assigned, Circuit, Column, ConstraintSystem, Error, Expression,
+            FloorPlanner, Instance, Selector, TableColumn,
+        },
+        poly::Rotation,
     };
 
     #[test]
@@ -499,4 +630,465 @@ mod tests {
             Error::NotEnoughColumnsForConstants,
         ));
     }
+
+    #[test]
+    fn assign_advice_from_instance() {
+        #[derive(Clone)]
+        struct MyConfig {
+            q: Selector,
+            a: Column<Advice>,
+            b: Column<Advice>,
+            input: Column<Instance>,
+        }
+
+        /// Copies the second public input into `a` and constrains `b = a^2`.
+        struct MyCircuit<const EQUALITY: bool>;
+
+        impl<const EQUALITY: bool> Circuit<Scalar> for MyCircuit<EQUALITY> {
+            type Config = MyConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+                let config = MyConfig {
+                    q: meta.selector(),
+                    a: meta.advice_column(),
+                    b: meta.advice_column(),
+                    input: meta.instance_column(),
+                };
+                if EQUALITY {
+                    meta.enable_equality(config.a);
+                    meta.enable_equality(config.input);
+                }
+
+                meta.create_gate("square", |meta| {
+                    let q = meta.query_selector(config.q);
+                    let a = meta.query_advice(config.a, Rotation::cur());
+                    let b = meta.query_advice(config.b, Rotation::cur());
+
+                    vec![q * (a.clone() * a - b)]
+                });
+
+                config
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Scalar>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "square",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        let a = region.assign_advice_from_instance(
+                            || "a",
+                            config.input,
+                            1,
+                            config.a,
+                            0,
+                        )?;
+                        assert_eq!(a.value(), Some(&Scalar::from(3)));
+                        region.assign_advice(
+                            || "b",
+                            config.b,
+                            0,
+                            || a.value().map(|a| a.square()).ok_or(Error::Synthesis),
+                        )?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let instances = vec![vec![Scalar::from(2), Scalar::from(3)]];
+        let prover = MockProver::run(4, &MyCircuit::<true>, instances.clone()).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // The copy is an equality constraint, so both columns need to be in the
+        // permutation.
+        assert!(matches!(
+            MockProver::run(4, &MyCircuit::<false>, instances).unwrap_err(),
+            Error::ColumnNotInPermutation(_),
+        ));
+    }
+
+    #[test]
+    fn constants_are_deduplicated() {
+        const K: u32 = 4;
+        const ROWS: usize = 10;
+
+        /// Assigns the constants 0 and 1 to alternate rows of two advice columns.
+        struct MyCircuit<P>(PhantomData<P>);
+
+        impl<P: FloorPlanner> Circuit<Scalar> for MyCircuit<P> {
+            type Config = [Column<Advice>; 2];
+            type FloorPlanner = P;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit(PhantomData)
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+                let constants = meta.fixed_column();
+                meta.enable_constant(constants);
+
+                let advice = [meta.advice_column(), meta.advice_column()];
+                for column in advice.iter() {
+                    meta.enable_equality(*column);
+                }
+                advice
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Scalar>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "constants",
+                    |mut region| {
+                        for column in config.iter() {
+                            for row in 0..ROWS {
+                                region.assign_advice_from_constant(
+                                    || "constant",
+                                    *column,
+                                    row,
+                                    Scalar::from((row % 2) as u64),
+                                )?;
+                            }
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        // The 20 constants only fit in the constants column because they take two rows.
+        let prover = MockProver::run(K, &MyCircuit::<SimpleFloorPlanner>(PhantomData), vec![]);
+        assert_eq!(prover.unwrap().verify(), Ok(()));
+        let prover = MockProver::run(K, &MyCircuit::<V1>(PhantomData), vec![]);
+        assert_eq!(prover.unwrap().verify(), Ok(()));
+    }
+
+    #[test]
+    fn constants_are_packed() {
+        const K: u32 = 4;
+        const ROWS: usize = 8;
+
+        /// Assigns distinct constants to two advice columns, with two constants columns.
+        struct MyCircuit<P>(PhantomData<P>);
+
+        impl<P: FloorPlanner> Circuit<Scalar> for MyCircuit<P> {
+            type Config = [Column<Advice>; 2];
+            type FloorPlanner = P;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit(PhantomData)
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+                for _ in 0..2 {
+                    let constants = meta.fixed_column();
+                    meta.enable_constant(constants);
+                }
+
+                let advice = [meta.advice_column(), meta.advice_column()];
+                for column in advice.iter() {
+                    meta.enable_equality(*column);
+                }
+                advice
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Scalar>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "constants",
+                    |mut region| {
+                        for (i, column) in config.iter().enumerate() {
+                            for row in 0..ROWS {
+                                region.assign_advice_from_constant(
+                                    || "constant",
+                                    *column,
+                                    row,
+                                    Scalar::from((i * ROWS + row) as u64),
+                                )?;
+                            }
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        fn check<P: FloorPlanner>() {
+            // The 16 constants only fit in the usable rows when they are spread
+            // across both constants columns.
+            let circuit = MyCircuit::<P>(PhantomData);
+            let prover = MockProver::run(K, &circuit, vec![]);
+            assert_eq!(prover.unwrap().verify(), Ok(()));
+
+            let cost = CircuitCost::<G1, _>::measure(K as usize, &circuit);
+            assert_eq!(cost.constants(), 2 * ROWS);
+            assert_eq!(cost.constant_rows(), ROWS);
+        }
+
+        check::<SimpleFloorPlanner>();
+        check::<V1>();
+    }
+
+    #[test]
+    fn assigned_cell_values() {
+        #[derive(Clone)]
+        struct MyConfig {
+            q: Selector,
+            a: Column<Advice>,
+            b: Column<Advice>,
+        }
+
+        /// Assigns `a = 1/2` and copies it to `b`, constraining `2 * b = 1`.
+        struct MyCircuit;
+
+        impl Circuit<Scalar> for MyCircuit {
+            type Config = MyConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+                let config = MyConfig {
+                    q: meta.selector(),
+                    a: meta.advice_column(),
+                    b: meta.advice_column(),
+                };
+                meta.enable_equality(config.a);
+                meta.enable_equality(config.b);
+
+                meta.create_gate("half", |meta| {
+                    let q = meta.query_selector(config.q);
+                    let b = meta.query_advice(config.b, Rotation::cur());
+
+                    vec![q * (b * Scalar::from(2) - Expression::Constant(Scalar::one()))]
+                });
+
+                config
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Scalar>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "half",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        let a = region.assign_advice(
+                            || "a",
+                            config.a,
+                            0,
+                            || Ok(Assigned::from((Scalar::one(), Scalar::from(2)))),
+                        )?;
+                        let a = a.evaluate();
+                        let b = a.copy_advice(|| "b", &mut region, config.b, 0)?;
+
+                        let doubled = b.witness().copied().double();
+                        assert_eq!(doubled, Value::known(Scalar::one()));
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let prover = MockProver::run(4, &MyCircuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn assignment_errors() {
+        /// Fails to compute the value of a cell in a namespaced region.
+        struct MyCircuit<P>(PhantomData<P>);
+
+        impl<P: FloorPlanner> Circuit<Scalar> for MyCircuit<P> {
+            type Config = Column<Advice>;
+            type FloorPlanner = P;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit(PhantomData)
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+                meta.advice_column()
+            }
+
+            fn synthesize(
+                &self,
+                a: Self::Config,
+                mut layouter: impl Layouter<Scalar>,
+            ) -> Result<(), Error> {
+                layouter.namespace(|| "chip").assign_region(
+                    || "region",
+                    |mut region| {
+                        region.assign_advice(|| "known", a, 0, || Ok(Scalar::one()))?;
+                        region.assign_advice(
+                            || "unknown",
+                            a,
+                            1,
+                            || Err::<Scalar, _>(Error::Synthesis),
+                        )?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        fn check<P: FloorPlanner>() {
+            let error = match MockProver::run(4, &MyCircuit::<P>(PhantomData), vec![]) {
+                Err(Error::Assignment(error)) => error,
+                result => panic!("unexpected result: {:?}", result.map(|_| ())),
+            };
+            assert_eq!(error.region.as_deref(), Some("region"));
+            assert_eq!(error.namespaces, vec!["chip".to_string()]);
+            assert_eq!(error.annotation, "unknown");
+            assert_eq!(error.column, Column::new(0, Any::Advice));
+            assert_eq!(error.offset, 1);
+            assert!(matches!(error.error, Error::Synthesis));
+            assert_eq!(
+                error.to_string(),
+                "Failed to assign \"unknown\" to Column { index: 0, column_type: Advice } at offset 1 \
+                 in region \"region\" (in chip): General synthesis error"
+            );
+        }
+
+        check::<SimpleFloorPlanner>();
+        check::<V1>();
+    }
+
+    #[test]
+    #[cfg(feature = "multicore")]
+    fn table_from_iter() {
+        use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+        const K: u32 = 5;
+
+        #[derive(Clone)]
+        struct MyConfig {
+            q: Selector,
+            a: Column<Advice>,
+            b: Column<Advice>,
+            x: TableColumn,
+            square: TableColumn,
+        }
+
+        #[derive(Clone, Copy)]
+        enum Fill {
+            Iter,
+            ParIter,
+            // Rows without a value for the second column.
+            Narrow,
+        }
+
+        /// Looks up `(a, b)` in a table of squares.
+        struct MyCircuit {
+            fill: Fill,
+            b: u64,
+        }
+
+        impl Circuit<Scalar> for MyCircuit {
+            type Config = MyConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit {
+                    fill: self.fill,
+                    b: 0,
+                }
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+                let config = MyConfig {
+                    q: meta.complex_selector(),
+                    a: meta.advice_column(),
+                    b: meta.advice_column(),
+                    x: meta.lookup_table_column(),
+                    square: meta.lookup_table_column(),
+                };
+
+                meta.lookup("square", |meta| {
+                    let q = meta.query_selector(config.q);
+                    let a = meta.query_advice(config.a, Rotation::cur());
+                    let b = meta.query_advice(config.b, Rotation::cur());
+
+                    vec![(q.clone() * a, config.x), (q * b, config.square)]
+                });
+
+                config
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Scalar>,
+            ) -> Result<(), Error> {
+                let columns = [config.x, config.square];
+                layouter.assign_table(
+                    || "squares",
+                    |mut table| match self.fill {
+                        Fill::Iter => table.assign_table_from_iter(
+                            || "square",
+                            &columns,
+                            (0..8u64).map(|x| [Scalar::from(x), Scalar::from(x * x)]),
+                        ),
+                        Fill::ParIter => table.assign_table_from_par_iter(
+                            || "square",
+                            &columns,
+                            (0..8usize)
+                                .into_par_iter()
+                                .map(|x| [Scalar::from(x as u64), Scalar::from((x * x) as u64)]),
+                        ),
+                        Fill::Narrow => table.assign_table_from_iter(
+                            || "square",
+                            &columns,
+                            (0..8u64).map(|x| [Scalar::from(x)]),
+                        ),
+                    },
+                )?;
+
+                layouter.assign_region(
+                    || "lookup",
+                    |mut region| {
+                        config.q.enable(&mut region, 0)?;
+                        region.assign_advice(|| "a", config.a, 0, || Ok(Scalar::from(3)))?;
+                        region.assign_advice(|| "b", config.b, 0, || Ok(Scalar::from(self.b)))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        for fill in [Fill::Iter, Fill::ParIter] {
+            let prover = MockProver::run(K, &MyCircuit { fill, b: 9 }, vec![]).unwrap();
+            assert_eq!(prover.verify(), Ok(()));
+
+            let prover = MockProver::run(K, &MyCircuit { fill, b: 10 }, vec![]).unwrap();
+            assert!(prover.verify().is_err());
+        }
+
+        let circuit = MyCircuit {
+            fill: Fill::Narrow,
+            b: 9,
+        };
+        assert!(matches!(
+            MockProver::run(K, &circuit, vec![]),
+            Err(Error::Synthesis)
+        ));
+    }
 }
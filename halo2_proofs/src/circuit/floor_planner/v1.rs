@@ -6,7 +6,7 @@ use crate::{
     circuit::{
         floor_planner::single_pass::SimpleTableLayouter,
         layouter::{RegionColumn, RegionLayouter, RegionShape, TableLayouter},
-        Cell, Layouter, Region, RegionIndex, RegionStart, Table,
+        AssignedCell, Cell, Layouter, Region, RegionIndex, RegionStart, Table,
     },
     plonk::{
         Advice, Any, Assigned, Assignment, Circuit, Column, Error, Fixed, FloorPlanner, Instance,
@@ -14,8 +14,12 @@ use crate::{
     },
 };
 
+mod profile;
 mod strategy;
 
+pub use profile::{LayoutProfile, LayoutProfileSource, ProfiledV1};
+use strategy::CircuitAllocations;
+
 /// The version 1 [`FloorPlanner`] provided by `halo2`.
 ///
 /// - No column optimizations are performed. Circuit configuration is left entirely to the
@@ -24,6 +28,12 @@ mod strategy;
 /// - Regions are measured as rectangles, bounded on the cells they assign.
 /// - Regions are laid out using a greedy first-fit strategy, after sorting regions by
 ///   their "advice area" (number of advice columns * rows).
+///
+/// Unlike [`SimpleFloorPlanner`], which starts each region after every row used by an
+/// earlier region, regions that use disjoint columns can be placed alongside each
+/// other, so circuits with many differently-shaped regions need fewer rows.
+///
+/// [`SimpleFloorPlanner`]: crate::circuit::SimpleFloorPlanner
 #[derive(Debug)]
 pub struct V1;
 
@@ -35,6 +45,8 @@ struct V1Plan<'a, F: Field, CS: Assignment<F> + 'a> {
     constants: Vec<(Assigned<F>, Cell)>,
     /// Stores the table fixed columns.
     table_columns: Vec<TableColumn>,
+    /// Stores the names of the namespaces we are in, for reporting failed assignments.
+    namespaces: Vec<String>,
 }
 
 impl<'a, F: Field, CS: Assignment<F> + 'a> fmt::Debug for V1Plan<'a, F, CS> {
@@ -51,6 +63,7 @@ impl<'a, F: Field, CS: Assignment<F>> V1Plan<'a, F, CS> {
             regions: vec![],
             constants: vec![],
             table_columns: vec![],
+            namespaces: vec![],
         };
         Ok(ret)
     }
@@ -63,81 +76,122 @@ impl FloorPlanner for V1 {
         config: C::Config,
         constants: Vec<Column<Fixed>>,
     ) -> Result<(), Error> {
-        let mut plan = V1Plan::new(cs)?;
-
-        // First pass: measure the regions within the circuit.
-        let mut measure = MeasurementPass::new();
-        {
-            let pass = &mut measure;
-            circuit
-                .without_witnesses()
-                .synthesize(config.clone(), V1Pass::<_, CS>::measure(pass))?;
-        }
+        synthesize_with(
+            cs,
+            circuit,
+            config,
+            constants,
+            strategy::slot_in_biggest_advice_first,
+        )
+    }
+}
 
-        // Planning:
-        // - Position the regions.
-        let (regions, column_allocations) = strategy::slot_in_biggest_advice_first(measure.regions);
-        plan.regions = regions;
+/// Measures the regions within `circuit`, in the order in which they are assigned.
+fn measure_regions<F: Field, CS: Assignment<F>, C: Circuit<F>>(
+    circuit: &C,
+    config: C::Config,
+) -> Result<Vec<RegionShape>, Error> {
+    let mut measure = MeasurementPass::new();
+    {
+        let pass = &mut measure;
+        circuit
+            .without_witnesses()
+            .synthesize(config, V1Pass::<_, CS>::measure(pass))?;
+    }
+    Ok(measure.regions)
+}
 
-        // - Determine how many rows our planned circuit will require.
-        let first_unassigned_row = column_allocations
-            .iter()
-            .map(|(_, a)| a.unbounded_interval_start())
-            .max()
-            .unwrap_or(0);
-
-        // - Position the constants within those rows.
-        let fixed_allocations: Vec<_> = constants
-            .into_iter()
-            .map(|c| {
-                (
-                    c,
-                    column_allocations
-                        .get(&Column::<Any>::from(c).into())
-                        .cloned()
-                        .unwrap_or_default(),
-                )
-            })
-            .collect();
-        let constant_positions = || {
-            fixed_allocations.iter().flat_map(|(c, a)| {
-                let c = *c;
-                a.free_intervals(0, Some(first_unassigned_row))
-                    .flat_map(move |e| e.range().unwrap().map(move |i| (c, i)))
-            })
-        };
+/// Synthesizes `circuit` as [`V1`] does, positioning its regions with
+/// `position_regions`.
+fn synthesize_with<F: Field, CS: Assignment<F>, C: Circuit<F>>(
+    cs: &mut CS,
+    circuit: &C,
+    config: C::Config,
+    constants: Vec<Column<Fixed>>,
+    position_regions: impl FnOnce(Vec<RegionShape>) -> (Vec<RegionStart>, CircuitAllocations),
+) -> Result<(), Error> {
+    let mut plan = V1Plan::new(cs)?;
+
+    // First pass: measure the regions within the circuit.
+    let region_shapes = measure_regions::<_, CS, _>(circuit, config.clone())?;
+
+    // Planning:
+    // - Position the regions.
+    let (regions, column_allocations) = position_regions(region_shapes);
+    plan.regions = regions;
+
+    // - Determine how many rows our planned circuit will require.
+    let first_unassigned_row = column_allocations
+        .iter()
+        .map(|(_, a)| a.unbounded_interval_start())
+        .max()
+        .unwrap_or(0);
+
+    // - Position the constants within those rows.
+    let fixed_allocations: Vec<_> = constants
+        .into_iter()
+        .map(|c| {
+            (
+                c,
+                column_allocations
+                    .get(&Column::<Any>::from(c).into())
+                    .cloned()
+                    .unwrap_or_default(),
+            )
+        })
+        .collect();
+    let constant_positions = || {
+        fixed_allocations.iter().flat_map(|(c, a)| {
+            let c = *c;
+            a.free_intervals(0, Some(first_unassigned_row))
+                .flat_map(move |e| e.range().unwrap().map(move |i| (c, i)))
+        })
+    };
 
-        // Second pass:
-        // - Assign the regions.
-        let mut assign = AssignmentPass::new(&mut plan);
-        {
-            let pass = &mut assign;
-            circuit.synthesize(config, V1Pass::assign(pass))?;
-        }
+    // Second pass:
+    // - Assign the regions.
+    let mut assign = AssignmentPass::new(&mut plan);
+    {
+        let pass = &mut assign;
+        circuit.synthesize(config, V1Pass::assign(pass))?;
+    }
 
-        // - Assign the constants.
-        if constant_positions().count() < plan.constants.len() {
-            return Err(Error::NotEnoughColumnsForConstants);
+    // - Assign the constants, once for each distinct value.
+    let mut values: Vec<F> = vec![];
+    for (value, _) in plan.constants.iter() {
+        let value = value.evaluate();
+        if !values.contains(&value) {
+            values.push(value);
         }
-        for ((fixed_column, fixed_row), (value, advice)) in
-            constant_positions().zip(plan.constants.into_iter())
-        {
-            plan.cs.assign_fixed(
-                || format!("Constant({:?})", value.evaluate()),
-                fixed_column,
-                fixed_row,
-                || Ok(value),
-            )?;
-            plan.cs.copy(
-                fixed_column.into(),
-                fixed_row,
-                advice.column,
-                *plan.regions[*advice.region_index] + advice.row_offset,
-            )?;
-        }
-
-        Ok(())
     }
+    if constant_positions().count() < values.len() {
+        return Err(Error::NotEnoughColumnsForConstants);
+    }
+    let assigned_constants: Vec<_> = values.into_iter().zip(constant_positions()).collect();
+    for (value, (fixed_column, fixed_row)) in assigned_constants.iter() {
+        plan.cs.assign_fixed(
+            || format!("Constant({:?})", value),
+            *fixed_column,
+            *fixed_row,
+            || Ok(*value),
+        )?;
+    }
+    for (value, advice) in plan.constants.into_iter() {
+        let value = value.evaluate();
+        let (fixed_column, fixed_row) = assigned_constants
+            .iter()
+            .find(|(assigned, _)| *assigned == value)
+            .map(|(_, position)| *position)
+            .expect("every constant value has been assigned");
+        plan.cs.copy(
+            fixed_column.into(),
+            fixed_row,
+            advice.column,
+            *plan.regions[*advice.region_index] + advice.row_offset,
+        )?;
+    }
+
+    Ok(())
 }
 
 #[derive(Debug)]
@@ -199,6 +253,21 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + 'a> Layouter<F> for V1Pass<'p, 'a, F,
         }
     }
 
+    fn output<V, N, NR>(&mut self, name: N, cell: &AssignedCell<V, F>) -> Result<(), Error>
+    where
+        V: Clone + Into<Assigned<F>>,
+        N: FnOnce() -> NR,
+        NR: Into<String>,
+    {
+        match &mut self.0 {
+            Pass::Measurement(_) => Ok(()),
+            Pass::Assignment(pass) => pass
+                .plan
+                .cs
+                .output(name, cell.value().cloned().map(|v| v.into())),
+        }
+    }
+
     fn get_root(&mut self) -> &mut Self::Root {
         self
     }
@@ -209,12 +278,15 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + 'a> Layouter<F> for V1Pass<'p, 'a, F,
         N: FnOnce() -> NR,
     {
         if let Pass::Assignment(pass) = &mut self.0 {
-            pass.plan.cs.push_namespace(name_fn);
+            let name = name_fn().into();
+            pass.plan.namespaces.push(name.clone());
+            pass.plan.cs.push_namespace(|| name);
         }
     }
 
     fn pop_namespace(&mut self, gadget_name: Option<String>) {
         if let Pass::Assignment(pass) = &mut self.0 {
+            pass.plan.namespaces.pop();
             pass.plan.cs.pop_namespace(gadget_name);
         }
     }
@@ -275,12 +347,13 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + 'a> AssignmentPass<'p, 'a, F, CS> {
         let region_index = self.region_index;
         self.region_index += 1;
 
-        self.plan.cs.enter_region(name);
+        self.plan.cs.enter_region(&name);
         let mut region = V1Region::new(self.plan, region_index.into());
         let result = {
             let region: &mut dyn RegionLayouter<F> = &mut region;
             assignment(region.into())
-        }?;
+        }
+        .map_err(|e| e.in_region(|| name().into(), &self.plan.namespaces))?;
         self.plan.cs.exit_region();
 
         Ok(result)
@@ -302,6 +375,7 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + 'a> AssignmentPass<'p, 'a, F, CS> {
             assignment(table.into())
         }?;
         let default_and_assigned = table.default_and_assigned;
+        let defaults = table.defaults;
         self.plan.cs.exit_region();
 
         // Check that all table columns have the same length `first_unused`,
@@ -325,6 +399,14 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + 'a> AssignmentPass<'p, 'a, F, CS> {
             }
         };
 
+        // A column with a default value must also have been assigned.
+        if defaults
+            .keys()
+            .any(|column| !default_and_assigned.contains_key(column))
+        {
+            return Err(Error::Synthesis); // TODO better error
+        }
+
         // Record these columns so that we can prevent them from being used again.
         for column in default_and_assigned.keys() {
             self.plan.table_columns.push(*column);
@@ -334,9 +416,14 @@ impl<'p, 'a, F: Field, CS: Assignment<F> + 'a> AssignmentPass<'p, 'a, F, CS> {
             // default_val must be Some because we must have assigned
             // at least one cell in each column, and in that case we checked
             // that all cells up to first_unused were assigned.
+            // An explicit default value takes precedence over it.
+            let default_val = match defaults.get(&col) {
+                Some(value) => Some(*value),
+                None => default_val.unwrap(),
+            };
             self.plan
                 .cs
-                .fill_from_row(col.inner(), first_unused, default_val.unwrap())?;
+                .fill_from_row(col.inner(), first_unused, default_val)?;
         }
 
         Ok(result)
@@ -485,15 +572,30 @@ impl<'r, 'a, F: Field, CS: Assignment<F> + 'a> RegionLayouter<F> for V1Region<'r
 
         Ok(())
     }
+
+    fn record_assertion(
+        &mut self,
+        annotation: &dyn Fn() -> String,
+        cell: Cell,
+    ) -> Result<(), Error> {
+        self.plan.cs.record_assertion(
+            annotation,
+            cell.column,
+            *self.plan.regions[*cell.region_index] + cell.row_offset,
+        )
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::marker::PhantomData;
+
     use pairing::bn256::Fr as Scalar;
 
     use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
         dev::MockProver,
-        plonk::{Advice, Circuit, Column, Error},
+        plonk::{minimum_k, Advice, Circuit, Column, ConstraintSystem, Error, FloorPlanner},
     };
 
     #[test]
@@ -534,4 +636,56 @@ mod tests {
             Error::NotEnoughColumnsForConstants,
         ));
     }
+
+    #[test]
+    fn regions_with_disjoint_columns_share_rows() {
+        const ROWS: usize = 50;
+
+        /// Assigns `ROWS` rows to each of two columns, in separate regions.
+        struct MyCircuit<P>(PhantomData<P>);
+
+        impl<P: FloorPlanner> Circuit<Scalar> for MyCircuit<P> {
+            type Config = [Column<Advice>; 2];
+            type FloorPlanner = P;
+
+            fn without_witnesses(&self) -> Self {
+                MyCircuit(PhantomData)
+            }
+
+            fn configure(meta: &mut ConstraintSystem<Scalar>) -> Self::Config {
+                [meta.advice_column(), meta.advice_column()]
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Scalar>,
+            ) -> Result<(), Error> {
+                for column in config.iter() {
+                    layouter.assign_region(
+                        || "column",
+                        |mut region| {
+                            for row in 0..ROWS {
+                                region.assign_advice(
+                                    || "a",
+                                    *column,
+                                    row,
+                                    || Ok(Scalar::from(row as u64)),
+                                )?;
+                            }
+                            Ok(())
+                        },
+                    )?;
+                }
+                Ok(())
+            }
+        }
+
+        // 2 * ROWS rows need k = 7, while ROWS rows and the blinding rows fit in k = 6.
+        let simple = MyCircuit::<SimpleFloorPlanner>(PhantomData);
+        let v1 = MyCircuit::<super::V1>(PhantomData);
+        assert_eq!(minimum_k(&simple).unwrap(), 7);
+        assert_eq!(minimum_k(&v1).unwrap(), 6);
+        assert_eq!(MockProver::run(6, &v1, vec![]).unwrap().verify(), Ok(()));
+    }
 }
@@ -0,0 +1,348 @@
+//! Laying out regions in the order recorded by an earlier run.
+
+use std::{io, marker::PhantomData};
+
+use ff::Field;
+
+use super::{measure_regions, strategy, synthesize_with};
+use crate::{
+    circuit::layouter::{RegionColumn, RegionShape},
+    plonk::{Any, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed, FloorPlanner},
+};
+
+/// A column used by a region, as recorded in a [`LayoutProfile`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum ProfiledColumn {
+    Advice(u32),
+    Fixed(u32),
+    Instance(u32),
+    Selector { index: u32, simple: bool },
+}
+
+impl From<&RegionColumn> for ProfiledColumn {
+    fn from(column: &RegionColumn) -> Self {
+        match column {
+            RegionColumn::Column(column) => {
+                let index = column.index() as u32;
+                match column.column_type() {
+                    Any::Advice => ProfiledColumn::Advice(index),
+                    Any::Fixed => ProfiledColumn::Fixed(index),
+                    Any::Instance => ProfiledColumn::Instance(index),
+                }
+            }
+            RegionColumn::Selector(selector) => ProfiledColumn::Selector {
+                index: selector.0 as u32,
+                simple: selector.is_simple(),
+            },
+        }
+    }
+}
+
+impl ProfiledColumn {
+    fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        let (tag, index) = match *self {
+            ProfiledColumn::Advice(index) => (0u8, index),
+            ProfiledColumn::Fixed(index) => (1, index),
+            ProfiledColumn::Instance(index) => (2, index),
+            ProfiledColumn::Selector { index, simple } => (if simple { 3 } else { 4 }, index),
+        };
+        writer.write_all(&[tag])?;
+        writer.write_all(&index.to_le_bytes())
+    }
+
+    fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let mut tag = [0u8; 1];
+        reader.read_exact(&mut tag)?;
+        let index = read_u32(reader)?;
+        match tag[0] {
+            0 => Ok(ProfiledColumn::Advice(index)),
+            1 => Ok(ProfiledColumn::Fixed(index)),
+            2 => Ok(ProfiledColumn::Instance(index)),
+            3 | 4 => Ok(ProfiledColumn::Selector {
+                index,
+                simple: tag[0] == 3,
+            }),
+            _ => Err(io::Error::new(
+                io::ErrorKind::Other,
+                "invalid column in layout profile",
+            )),
+        }
+    }
+}
+
+/// The columns and row count of a region, as recorded in a [`LayoutProfile`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+struct ProfiledRegion {
+    // Sorted by the `Ord` implementation of `RegionColumn`.
+    columns: Vec<ProfiledColumn>,
+    row_count: usize,
+}
+
+impl From<&RegionShape> for ProfiledRegion {
+    fn from(shape: &RegionShape) -> Self {
+        let mut columns: Vec<_> = shape.columns().iter().collect();
+        columns.sort_unstable();
+        ProfiledRegion {
+            columns: columns.into_iter().map(ProfiledColumn::from).collect(),
+            row_count: shape.row_count(),
+        }
+    }
+}
+
+fn read_u32<R: io::Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+/// A profile of the regions of a circuit, and of the order in which to lay them out.
+///
+/// [`V1`] sorts regions by advice area before laying them out, which is quick but
+/// can leave gaps that later regions don't fit in. [`LayoutProfile::measure`] also
+/// tries other orders, and records the one that uses the fewest rows. The profile
+/// can be stored with [`LayoutProfile::write`], and used by [`ProfiledV1`] in
+/// later runs of the circuit.
+///
+/// [`V1`]: super::V1
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LayoutProfile {
+    /// The regions, in the order in which the circuit assigns them.
+    regions: Vec<ProfiledRegion>,
+    /// The order in which to lay out the regions.
+    order: Vec<usize>,
+}
+
+impl LayoutProfile {
+    /// Measures the regions of `circuit`, and finds the order in which to lay them
+    /// out.
+    ///
+    /// The circuit is synthesized without its witness values, as in the first pass
+    /// of [`V1`].
+    ///
+    /// [`V1`]: super::V1
+    pub fn measure<F: Field, C: Circuit<F>>(circuit: &C) -> Result<Self, Error> {
+        let mut cs = ConstraintSystem::default();
+        let config = C::configure(&mut cs);
+
+        // The measurement pass doesn't assign to the constraint system.
+        let region_shapes = measure_regions::<F, crate::plonk::RowCounter, C>(circuit, config)?;
+
+        Ok(LayoutProfile {
+            order: strategy::best_order(&region_shapes),
+            regions: region_shapes.iter().map(ProfiledRegion::from).collect(),
+        })
+    }
+
+    /// Returns whether the measured regions of a circuit are the ones recorded in
+    /// this profile.
+    fn matches(&self, region_shapes: &[RegionShape]) -> bool {
+        self.regions.len() == region_shapes.len()
+            && self
+                .regions
+                .iter()
+                .zip(region_shapes)
+                .all(|(region, shape)| *region == ProfiledRegion::from(shape))
+    }
+
+    /// Writes this profile to a buffer.
+    pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&(self.regions.len() as u32).to_le_bytes())?;
+        for region in &self.regions {
+            writer.write_all(&(region.row_count as u32).to_le_bytes())?;
+            writer.write_all(&(region.columns.len() as u32).to_le_bytes())?;
+            for column in &region.columns {
+                column.write(writer)?;
+            }
+        }
+        for index in &self.order {
+            writer.write_all(&(*index as u32).to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Reads a profile from a buffer.
+    pub fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let num_regions = read_u32(reader)? as usize;
+        let regions = (0..num_regions)
+            .map(|_| {
+                let row_count = read_u32(reader)? as usize;
+                let num_columns = read_u32(reader)?;
+                let columns = (0..num_columns)
+                    .map(|_| ProfiledColumn::read(reader))
+                    .collect::<io::Result<_>>()?;
+                Ok(ProfiledRegion { columns, row_count })
+            })
+            .collect::<io::Result<_>>()?;
+
+        let order: Vec<usize> = (0..num_regions)
+            .map(|_| read_u32(reader).map(|index| index as usize))
+            .collect::<io::Result<_>>()?;
+        let mut sorted = order.clone();
+        sorted.sort_unstable();
+        if !sorted.into_iter().eq(0..num_regions) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "invalid region order in layout profile",
+            ));
+        }
+
+        Ok(LayoutProfile { regions, order })
+    }
+}
+
+/// Provides the [`LayoutProfile`] for a [`ProfiledV1`] floor planner.
+pub trait LayoutProfileSource {
+    /// Returns the profile of the circuit, or `None` if it hasn't been measured.
+    fn layout_profile() -> Option<LayoutProfile>;
+}
+
+/// A [`V1`] floor planner that lays out regions in the order recorded in the
+/// [`LayoutProfile`] provided by `P`.
+///
+/// Regions are still measured before they are assigned. If there is no profile, or
+/// the regions don't match the ones it records (for example because their sizes
+/// depend on the circuit's inputs), they are laid out as with [`V1`].
+///
+/// [`V1`]: super::V1
+#[derive(Debug)]
+pub struct ProfiledV1<P: LayoutProfileSource>(PhantomData<P>);
+
+impl<P: LayoutProfileSource> FloorPlanner for ProfiledV1<P> {
+    fn synthesize<F: Field, CS: Assignment<F>, C: Circuit<F>>(
+        cs: &mut CS,
+        circuit: &C,
+        config: C::Config,
+        constants: Vec<Column<Fixed>>,
+    ) -> Result<(), Error> {
+        let profile = P::layout_profile();
+        synthesize_with(
+            cs,
+            circuit,
+            config,
+            constants,
+            |region_shapes| match profile {
+                Some(profile) if profile.matches(&region_shapes) => {
+                    strategy::slot_in_order(region_shapes, &profile.order)
+                }
+                _ => strategy::slot_in_biggest_advice_first(region_shapes),
+            },
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use pairing::bn256::Fr as Fp;
+
+    use super::{LayoutProfile, LayoutProfileSource, ProfiledV1};
+    use crate::{
+        circuit::{floor_planner::V1, Layouter},
+        dev::MockProver,
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, FloorPlanner},
+    };
+
+    /// Assigns regions that `V1` lays out in 12 rows, but that fit in 8.
+    struct ShapesCircuit<P>(PhantomData<P>);
+
+    impl<P: FloorPlanner> Circuit<Fp> for ShapesCircuit<P> {
+        type Config = [Column<Advice>; 3];
+        type FloorPlanner = P;
+
+        fn without_witnesses(&self) -> Self {
+            ShapesCircuit(PhantomData)
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            [
+                meta.advice_column(),
+                meta.advice_column(),
+                meta.advice_column(),
+            ]
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            for (columns, rows) in [(&[0][..], 5), (&[0, 1][..], 3), (&[1, 2][..], 4)] {
+                layouter.assign_region(
+                    || "shape",
+                    |mut region| {
+                        for column in columns {
+                            for row in 0..rows {
+                                region.assign_advice(
+                                    || "zero",
+                                    config[*column],
+                                    row,
+                                    || Ok(Fp::zero()),
+                                )?;
+                            }
+                        }
+                        Ok(())
+                    },
+                )?;
+            }
+            Ok(())
+        }
+    }
+
+    struct Measured;
+
+    impl LayoutProfileSource for Measured {
+        fn layout_profile() -> Option<LayoutProfile> {
+            Some(LayoutProfile::measure(&ShapesCircuit::<V1>(PhantomData)).unwrap())
+        }
+    }
+
+    struct Unmeasured;
+
+    impl LayoutProfileSource for Unmeasured {
+        fn layout_profile() -> Option<LayoutProfile> {
+            None
+        }
+    }
+
+    #[test]
+    fn profiled_layout() {
+        // 8 rows, plus 5 blinding factors and the row for l_last.
+        const K: u32 = 4;
+
+        let prover = MockProver::run(
+            K,
+            &ShapesCircuit::<ProfiledV1<Measured>>(PhantomData),
+            vec![],
+        )
+        .unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        for result in [
+            MockProver::run(K, &ShapesCircuit::<V1>(PhantomData), vec![]),
+            MockProver::run(
+                K,
+                &ShapesCircuit::<ProfiledV1<Unmeasured>>(PhantomData),
+                vec![],
+            ),
+        ] {
+            assert!(matches!(
+                result,
+                Err(Error::NotEnoughRowsAvailable { current_k: K, .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn profile_round_trip() {
+        let profile = Measured::layout_profile().unwrap();
+        let mut buffer = vec![];
+        profile.write(&mut buffer).unwrap();
+        assert_eq!(LayoutProfile::read(&mut &buffer[..]).unwrap(), profile);
+
+        // The order must be a permutation of the regions.
+        let last = buffer.len() - 4;
+        buffer[last..].copy_from_slice(&0u32.to_le_bytes());
+        assert!(LayoutProfile::read(&mut &buffer[..]).is_err());
+    }
+}
@@ -224,6 +224,79 @@ pub fn slot_in_biggest_advice_first(
     (regions, column_allocations)
 }
 
+/// Lays out the regions with the [`slot_in`] strategy, in the given order of their
+/// indices.
+pub(crate) fn slot_in_order(
+    region_shapes: Vec<RegionShape>,
+    order: &[usize],
+) -> (Vec<RegionStart>, CircuitAllocations) {
+    let mut region_shapes: Vec<_> = region_shapes.into_iter().map(Some).collect();
+    let ordered_regions = order
+        .iter()
+        .map(|index| {
+            region_shapes[*index]
+                .take()
+                .expect("order is a permutation")
+        })
+        .collect();
+
+    let (mut regions, column_allocations) = slot_in(ordered_regions);
+
+    // Un-sort the regions so they match the original indexing.
+    regions.sort_unstable_by_key(|(_, region)| region.region_index().0);
+    let regions = regions.into_iter().map(|(start, _)| start).collect();
+
+    (regions, column_allocations)
+}
+
+/// Returns the order of the regions, among those tried, for which the [`slot_in`]
+/// strategy uses the fewest rows.
+///
+/// The orders tried sort the regions by advice area (as in
+/// [`slot_in_biggest_advice_first`]), row count and column count, each largest first,
+/// and keep the original order. Ties are broken in that order, so the result is never
+/// worse than [`slot_in_biggest_advice_first`].
+pub(crate) fn best_order(region_shapes: &[RegionShape]) -> Vec<usize> {
+    let advice_area = |shape: &RegionShape| {
+        shape
+            .columns()
+            .iter()
+            .filter(|c| match c {
+                RegionColumn::Column(c) => matches!(c.column_type(), Any::Advice),
+                _ => false,
+            })
+            .count()
+            * shape.row_count()
+    };
+    let keys: [&dyn Fn(&RegionShape) -> usize; 3] = [
+        &advice_area,
+        &|shape: &RegionShape| shape.row_count(),
+        &|shape: &RegionShape| shape.columns().len(),
+    ];
+
+    let mut orders: Vec<Vec<usize>> = keys
+        .iter()
+        .map(|key| {
+            let mut order: Vec<_> = (0..region_shapes.len()).collect();
+            order.sort_by_key(|index| cmp::Reverse(key(&region_shapes[*index])));
+            order
+        })
+        .collect();
+    orders.push((0..region_shapes.len()).collect());
+
+    orders
+        .into_iter()
+        .min_by_key(|order| {
+            let (_, column_allocations) = slot_in_order(region_shapes.to_vec(), order);
+            column_allocations
+                .values()
+                .map(|a| a.unbounded_interval_start())
+                .max()
+                .unwrap_or(0)
+        })
+        .expect("at least one order is tried")
+}
+
 #[test]
 fn test_slot_in() {
     use crate::plonk::Column;
@@ -263,3 +336,36 @@ fn test_slot_in() {
         vec![0.into(), 0.into(), 15.into()]
     );
 }
+
+#[test]
+fn test_best_order() {
+    use crate::plonk::Column;
+
+    let shape = |region_index: usize, columns: &[usize], row_count| RegionShape {
+        region_index: region_index.into(),
+        columns: columns
+            .iter()
+            .map(|c| Column::new(*c, Any::Advice).into())
+            .collect(),
+        row_count,
+    };
+
+    // Laying out the biggest region first pushes the wide region below it, where
+    // it is in the way of the tall one.
+    let regions = vec![
+        shape(0, &[0, 2], 3),
+        shape(1, &[0, 1], 2),
+        shape(2, &[1], 4),
+    ];
+    let rows = |order: &[usize]| {
+        slot_in_order(regions.clone(), order)
+            .1
+            .values()
+            .map(|a| a.unbounded_interval_start())
+            .max()
+            .unwrap()
+    };
+
+    assert_eq!(rows(&[0, 1, 2]), 9);
+    assert_eq!(rows(&best_order(&regions)), 6);
+}
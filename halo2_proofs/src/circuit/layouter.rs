@@ -102,6 +102,18 @@ pub trait RegionLayouter<F: Field>: fmt::Debug {
     ///
     /// Returns an error if either of the cells is not within the given permutation.
     fn constrain_equal(&mut self, left: Cell, right: Cell) -> Result<(), Error>;
+
+    /// Records that `cell` is asserted to be equal to the cell or constant it has
+    /// been constrained to, as described by `annotation`.
+    ///
+    /// By default, assertions are not recorded.
+    fn record_assertion(
+        &mut self,
+        _annotation: &dyn Fn() -> String,
+        _cell: Cell,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
 }
 
 /// Helper trait for implementing a custom [`Layouter`].
@@ -120,6 +132,27 @@ pub trait TableLayouter<F: Field>: fmt::Debug {
         offset: usize,
         to: &'v mut (dyn FnMut() -> Result<Assigned<F>, Error> + 'v),
     ) -> Result<(), Error>;
+
+    /// Assigns `values` to the cells of a table column, starting at offset 0.
+    ///
+    /// Returns an error if the first table cell has already been assigned to.
+    fn assign_column(
+        &mut self,
+        annotation: &dyn Fn() -> String,
+        column: TableColumn,
+        values: &[Assigned<F>],
+    ) -> Result<(), Error> {
+        for (offset, value) in values.iter().enumerate() {
+            self.assign_cell(annotation, column, offset, &mut || Ok(*value))?;
+        }
+        Ok(())
+    }
+
+    /// Sets the value that fills the rows of a table column after its last
+    /// assigned row, in place of the value assigned at offset 0.
+    ///
+    /// Returns an error if the column already has a default value.
+    fn set_default(&mut self, column: TableColumn, value: Assigned<F>) -> Result<(), Error>;
 }
 
 /// The shape of a region. For a region at a certain index, we track
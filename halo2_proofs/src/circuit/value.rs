@@ -0,0 +1,252 @@
+//! The [`Value`] type, for witness values that may be unknown.
+
+use std::iter::FromIterator;
+use std::ops::{Add, Mul, Neg, Sub};
+
+use ff::Field;
+
+use crate::plonk::{Assigned, Error};
+
+/// A value that might be unknown, such as a witness value while keys are being
+/// generated.
+///
+/// Circuits compute with `Value`s without checking whether they are known, and hand
+/// them to a region as they are assigned:
+///
+/// ```
+/// use ff::Field;
+/// use halo2_proofs::circuit::Value;
+/// use pairing::bn256::Fr as Fp;
+///
+/// let a = Value::known(Fp::from(3));
+/// let b = Value::known(Fp::from(4));
+/// let c = a * b + Value::known(Fp::one());
+/// assert_eq!(c.assign().unwrap(), Fp::from(13));
+///
+/// let unknown: Value<Fp> = Value::unknown();
+/// assert!((unknown * b).assign().is_err());
+/// ```
+///
+/// The closures passed to [`Region::assign_advice`] and [`Region::assign_fixed`]
+/// return [`Value::assign`], as in `|| c.assign()`.
+///
+/// [`Region::assign_advice`]: super::Region::assign_advice
+/// [`Region::assign_fixed`]: super::Region::assign_fixed
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Value<V> {
+    inner: Option<V>,
+}
+
+impl<V> Default for Value<V> {
+    fn default() -> Self {
+        Value::unknown()
+    }
+}
+
+impl<V> Value<V> {
+    /// Constructs an unknown value.
+    pub const fn unknown() -> Self {
+        Value { inner: None }
+    }
+
+    /// Constructs a known value.
+    pub const fn known(value: V) -> Self {
+        Value { inner: Some(value) }
+    }
+
+    /// Returns the value, or [`Error::Synthesis`] if it is unknown. This is what
+    /// the assignment closures of a region return.
+    pub fn assign(self) -> Result<V, Error> {
+        self.inner.ok_or(Error::Synthesis)
+    }
+
+    /// Converts from `&Value<V>` to `Value<&V>`.
+    pub fn as_ref(&self) -> Value<&V> {
+        Value {
+            inner: self.inner.as_ref(),
+        }
+    }
+
+    /// Converts from `&mut Value<V>` to `Value<&mut V>`.
+    pub fn as_mut(&mut self) -> Value<&mut V> {
+        Value {
+            inner: self.inner.as_mut(),
+        }
+    }
+
+    /// Returns an error if the value is known and `f` returns `true` for it.
+    pub fn error_if_known_and<F: FnOnce(&V) -> bool>(&self, f: F) -> Result<(), Error> {
+        match self.inner.as_ref() {
+            Some(value) if f(value) => Err(Error::Synthesis),
+            _ => Ok(()),
+        }
+    }
+
+    /// Panics if the value is known and `f` returns `false` for it.
+    pub fn assert_if_known<F: FnOnce(&V) -> bool>(&self, f: F) {
+        if let Some(value) = self.inner.as_ref() {
+            assert!(f(value));
+        }
+    }
+
+    /// Maps a `Value<V>` to `Value<W>` by applying `f` to a known value.
+    pub fn map<W, F: FnOnce(V) -> W>(self, f: F) -> Value<W> {
+        Value {
+            inner: self.inner.map(f),
+        }
+    }
+
+    /// Returns [`Value::unknown`] if the value is unknown, and `f` applied to it
+    /// otherwise.
+    pub fn and_then<W, F: FnOnce(V) -> Value<W>>(self, f: F) -> Value<W> {
+        match self.inner {
+            Some(value) => f(value),
+            None => Value::unknown(),
+        }
+    }
+
+    /// Zips `self` with `other`. The result is known if both values are.
+    pub fn zip<W>(self, other: Value<W>) -> Value<(V, W)> {
+        Value {
+            inner: self.inner.zip(other.inner),
+        }
+    }
+}
+
+impl<V, W> Value<(V, W)> {
+    /// Unzips a value of a pair into a pair of values.
+    pub fn unzip(self) -> (Value<V>, Value<W>) {
+        match self.inner {
+            Some((v, w)) => (Value::known(v), Value::known(w)),
+            None => (Value::unknown(), Value::unknown()),
+        }
+    }
+}
+
+impl<V: Copy> Value<&V> {
+    /// Maps a `Value<&V>` to a `Value<V>` by copying the value.
+    pub fn copied(self) -> Value<V> {
+        Value {
+            inner: self.inner.copied(),
+        }
+    }
+}
+
+impl<V: Clone> Value<&V> {
+    /// Maps a `Value<&V>` to a `Value<V>` by cloning the value.
+    pub fn cloned(self) -> Value<V> {
+        Value {
+            inner: self.inner.cloned(),
+        }
+    }
+}
+
+impl<V> From<Option<V>> for Value<V> {
+    fn from(inner: Option<V>) -> Self {
+        Value { inner }
+    }
+}
+
+impl<F: Field> Value<F> {
+    /// Doubles the value.
+    pub fn double(&self) -> Value<F> {
+        self.as_ref().map(|v| v.double())
+    }
+
+    /// Squares the value.
+    pub fn square(&self) -> Value<F> {
+        self.as_ref().map(|v| v.square())
+    }
+
+    /// Inverts the value, mapping zero to zero.
+    pub fn invert(&self) -> Value<F> {
+        self.as_ref().map(|v| v.invert().unwrap_or(F::zero()))
+    }
+}
+
+impl<F: Field> Value<Assigned<F>> {
+    /// Evaluates the value to a field element, mapping `x/0` to zero.
+    pub fn evaluate(self) -> Value<F> {
+        self.map(|v| v.evaluate())
+    }
+}
+
+impl<V: Neg> Neg for Value<V> {
+    type Output = Value<V::Output>;
+
+    fn neg(self) -> Self::Output {
+        self.map(|v| -v)
+    }
+}
+
+impl<V: Add<W>, W> Add<Value<W>> for Value<V> {
+    type Output = Value<V::Output>;
+
+    fn add(self, rhs: Value<W>) -> Self::Output {
+        self.zip(rhs).map(|(a, b)| a + b)
+    }
+}
+
+impl<V: Sub<W>, W> Sub<Value<W>> for Value<V> {
+    type Output = Value<V::Output>;
+
+    fn sub(self, rhs: Value<W>) -> Self::Output {
+        self.zip(rhs).map(|(a, b)| a - b)
+    }
+}
+
+impl<V: Mul<W>, W> Mul<Value<W>> for Value<V> {
+    type Output = Value<V::Output>;
+
+    fn mul(self, rhs: Value<W>) -> Self::Output {
+        self.zip(rhs).map(|(a, b)| a * b)
+    }
+}
+
+impl<V> FromIterator<Value<V>> for Value<Vec<V>> {
+    /// Collects the known values, or returns [`Value::unknown`] if any of them is
+    /// unknown.
+    fn from_iter<I: IntoIterator<Item = Value<V>>>(iter: I) -> Self {
+        iter.into_iter()
+            .map(|value| value.inner)
+            .collect::<Option<_>>()
+            .into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use pairing::bn256::Fr as Fp;
+
+    use super::Value;
+    use crate::plonk::Error;
+
+    #[test]
+    fn combinators() {
+        let a = Value::known(Fp::from(2));
+        let b = Value::known(Fp::from(5));
+        let unknown = Value::<Fp>::unknown();
+
+        assert_eq!(a + b, Value::known(Fp::from(7)));
+        assert_eq!(b - a, Value::known(Fp::from(3)));
+        assert_eq!(-a * b, Value::known(-Fp::from(10)));
+        assert_eq!(a.square().double(), Value::known(Fp::from(8)));
+        assert_eq!(a * a.invert(), Value::known(Fp::one()));
+        assert_eq!(a + unknown, Value::unknown());
+        assert_eq!(a.zip(b).unzip(), (a, b));
+
+        assert_eq!(
+            vec![a, b].into_iter().collect::<Value<Vec<Fp>>>(),
+            Value::known(vec![Fp::from(2), Fp::from(5)])
+        );
+        assert_eq!(
+            vec![a, unknown].into_iter().collect::<Value<Vec<Fp>>>(),
+            Value::unknown()
+        );
+
+        assert!(matches!(unknown.assign(), Err(Error::Synthesis)));
+        assert!(a.error_if_known_and(|a| *a == Fp::from(2)).is_err());
+        assert!(unknown.error_if_known_and(|_| true).is_ok());
+    }
+}
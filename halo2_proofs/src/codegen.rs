@@ -0,0 +1,921 @@
+//! Generation of EVM verifiers for circuits over BN254.
+//!
+//! [`generate_verifier`] turns the verifying key of a circuit into a standalone
+//! Solidity contract, whose `verifyProof(uint256[] instances, bytes proof)`
+//! function performs the same checks as [`verify_proof`] for a single proof,
+//! ending with one pairing check through the EVM precompiles. The contract
+//! replays a [`Keccak256Write`] transcript, so the proofs it accepts must be
+//! created with that transcript.
+//!
+//! The multi-opening argument of the contract is the scheme of the verifier
+//! params, and its lookup argument (`mv-lookup`) is the one this crate was
+//! built with, so the prover must use params with the same scheme and be built
+//! with the same features. Circuits with configuration
+//! columns are verified against the values assigned at keygen, like
+//! [`verify_proof`] does.
+//!
+//! # Examples
+//!
+//! ```ignore
+//! let verifier = generate_verifier(&params_verifier, pk.get_vk(), &[1])?;
+//! std::fs::write("Verifier.sol", verifier.source())?;
+//!
+//! let mut transcript = Keccak256Write::<_, _, Challenge255<_>>::init(vec![]);
+//! create_proof(&params, &pk, &[circuit], &[&[&instances]], OsRng, &mut transcript)?;
+//! let calldata = verifier.encode_calldata(&[&instances], &transcript.finalize())?;
+//! ```
+//!
+//! [`verify_proof`]: crate::plonk::verify_proof
+//! [`Keccak256Write`]: crate::transcript::keccak::Keccak256Write
+
+use std::collections::BTreeMap;
+
+use ff::Field;
+use pairing::bn256::{Bn256, Fr, G1Affine};
+use sha3::{Digest, Keccak256};
+
+use crate::arithmetic::FieldExt;
+use crate::plonk::{Any, Column, Error, Expression, VerifyingKey};
+use crate::poly::{
+    commitment::{CommitmentScheme, ParamsVerifier},
+    Rotation,
+};
+use crate::transcript::keccak::to_be_bytes;
+
+mod yul;
+
+use self::yul::{fr_add, fr_inv, fr_mul, fr_neg, fr_sub, g2_words, hex, Point, Word, Yul};
+
+/// The signature of the verifier contract's entry point.
+const VERIFY_SIGNATURE: &[u8] = b"verifyProof(uint256[],bytes)";
+
+/// A verifier contract generated by [`generate_verifier`].
+#[derive(Clone, Debug)]
+pub struct EvmVerifier {
+    source: String,
+    num_instances: Vec<usize>,
+    proof_len: usize,
+}
+
+impl EvmVerifier {
+    /// Returns the Solidity source of the contract.
+    pub fn source(&self) -> &str {
+        &self.source
+    }
+
+    /// Returns the number of values expected in each instance column.
+    pub fn num_instances(&self) -> &[usize] {
+        &self.num_instances
+    }
+
+    /// Returns the length in bytes of the proofs accepted by the contract.
+    pub fn proof_len(&self) -> usize {
+        self.proof_len
+    }
+
+    /// Encodes a call to `verifyProof` with the given instance columns and
+    /// proof.
+    ///
+    /// Instance columns shorter than expected are padded with zeroes, which
+    /// doesn't change their commitments.
+    pub fn encode_calldata(&self, instances: &[&[Fr]], proof: &[u8]) -> Result<Vec<u8>, Error> {
+        if instances.len() != self.num_instances.len() {
+            return Err(Error::InvalidInstances);
+        }
+        let total: usize = self.num_instances.iter().sum();
+
+        let mut calldata = Keccak256::digest(VERIFY_SIGNATURE)[..4].to_vec();
+        calldata.extend_from_slice(&abi_word(0x40));
+        calldata.extend_from_slice(&abi_word(0x60 + 0x20 * total));
+
+        calldata.extend_from_slice(&abi_word(total));
+        for (column, &len) in instances.iter().zip(self.num_instances.iter()) {
+            if column.len() > len {
+                return Err(Error::InstanceTooLarge);
+            }
+            for value in column.iter() {
+                calldata.extend(to_be_bytes(value));
+            }
+            calldata.resize(calldata.len() + 0x20 * (len - column.len()), 0);
+        }
+
+        calldata.extend_from_slice(&abi_word(proof.len()));
+        calldata.extend_from_slice(proof);
+        calldata.resize(calldata.len() + (0x20 - proof.len() % 0x20) % 0x20, 0);
+
+        Ok(calldata)
+    }
+}
+
+fn abi_word(value: usize) -> [u8; 32] {
+    let mut word = [0u8; 32];
+    word[24..].copy_from_slice(&(value as u64).to_be_bytes());
+    word
+}
+
+/// Generates a verifier contract for the circuit with verifying key `vk`.
+///
+/// `num_instances` holds the number of values in each instance column of the
+/// circuit, which is fixed in the contract. Returns
+/// [`Error::InvalidInstances`] if it doesn't match the number of instance
+/// columns, and [`Error::InstanceTooLarge`] if a column doesn't fit in the
+/// usable rows of the circuit or in the public inputs of `params`.
+pub fn generate_verifier(
+    params: &ParamsVerifier<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    num_instances: &[usize],
+) -> Result<EvmVerifier, Error> {
+    let cs = vk.cs();
+    if num_instances.len() != cs.num_instance_columns {
+        return Err(Error::InvalidInstances);
+    }
+    let max_instances = params.n as usize - (cs.blinding_factors() + 1);
+    if num_instances
+        .iter()
+        .any(|&len| len > max_instances || len > params.g_lagrange.len())
+    {
+        return Err(Error::InstanceTooLarge);
+    }
+
+    let mut yul = Yul::new();
+    let mut commitments = Commitments::default();
+
+    yul.comment("Instance commitments");
+    let mut offset = 0;
+    let instance_commitments: Vec<usize> = num_instances
+        .iter()
+        .map(|&len| {
+            let acc = yul.identity();
+            for (i, base) in params.g_lagrange[..len].iter().enumerate() {
+                let value = format!(
+                    "calldataload(add(instances.offset, {:#x}))",
+                    0x20 * (offset + i)
+                );
+                yul.mul_add(&acc, &Point::constant(base), value);
+            }
+            offset += len;
+            commitments.push(acc)
+        })
+        .collect();
+
+    yul.comment("Verifying key and instance commitments");
+    yul.absorb(hex(&vk.transcript_repr()));
+    for &commitment in instance_commitments.iter() {
+        yul.absorb_point(&commitments[commitment]);
+    }
+
+    yul.comment("Advice and configuration commitments");
+    let advice_commitments: Vec<usize> = yul
+        .read_points(cs.num_advice_columns)
+        .into_iter()
+        .map(|point| commitments.push(point))
+        .collect();
+    for column in cs.configuration_columns.iter() {
+        let expected = Point::constant(&vk.fixed_commitments()[column.index()]);
+        let point = yul.read_point();
+        yul.assert_eq_point(&point, &expected);
+    }
+    let theta = yul.squeeze();
+
+    yul.comment("Lookup permuted commitments");
+    let lookups_permuted: Vec<Vec<usize>> = cs
+        .lookups
+        .iter()
+        .map(|_| read_lookup_permuted(&mut yul, &mut commitments))
+        .collect();
+    let beta = yul.squeeze();
+    let gamma = yul.squeeze();
+
+    yul.comment("Permutation, lookup and shuffle product commitments");
+    let chunk_len = cs.degree() - 2;
+    let permutation_columns = cs.permutation.get_columns();
+    let permutation_products: Vec<usize> = permutation_columns
+        .chunks(chunk_len)
+        .map(|_| {
+            let point = yul.read_point();
+            commitments.push(point)
+        })
+        .collect();
+    let lookup_commitments: Vec<Vec<usize>> = lookups_permuted
+        .into_iter()
+        .map(|mut lookup| {
+            let point = yul.read_point();
+            lookup.push(commitments.push(point));
+            lookup
+        })
+        .collect();
+    let shuffle_products: Vec<usize> = cs
+        .shuffles
+        .iter()
+        .map(|_| {
+            let point = yul.read_point();
+            commitments.push(point)
+        })
+        .collect();
+
+    yul.comment("Vanishing argument commitments");
+    let random_poly = yul.read_point();
+    let random_poly = commitments.push(random_poly);
+    let y = yul.squeeze();
+    let h_commitments = yul.read_points(vk.domain().get_quotient_poly_degree());
+    let x = yul.squeeze();
+
+    yul.comment("Evaluations");
+    let evals = Evals {
+        instance: yul.read_scalars(cs.instance_queries.len()),
+        advice: yul.read_scalars(cs.advice_queries.len()),
+        fixed: yul.read_scalars(cs.fixed_queries.len()),
+    };
+    let random_eval = yul.read_scalar();
+    let permutation_evals = yul.read_scalars(vk.permutation_commitments().len());
+    let permutation_sets: Vec<PermutationSet> = permutation_products
+        .iter()
+        .enumerate()
+        .map(|(i, &commitment)| PermutationSet {
+            commitment,
+            eval: yul.read_scalar(),
+            next_eval: yul.read_scalar(),
+            last_eval: if i + 1 < permutation_products.len() {
+                Some(yul.read_scalar())
+            } else {
+                None
+            },
+        })
+        .collect();
+    let lookups: Vec<Lookup> = lookup_commitments
+        .into_iter()
+        .map(|commitments| Lookup {
+            commitments,
+            evals: yul.read_scalars(LOOKUP_EVALS),
+        })
+        .collect();
+    let shuffles: Vec<(usize, Word, Word)> = shuffle_products
+        .into_iter()
+        .map(|commitment| (commitment, yul.read_scalar(), yul.read_scalar()))
+        .collect();
+
+    yul.comment("Lagrange basis evaluations");
+    let xn = yul.word(x);
+    yul.square_n(xn, params.k);
+    let blinding_factors = cs.blinding_factors();
+    let barycentric_weight = Fr::from(params.n).invert().unwrap();
+    let common = yul.word(fr_mul(fr_sub(xn, "1"), hex(&barycentric_weight)));
+    let l_evals: Vec<Word> = (-((blinding_factors + 1) as i32)..=0)
+        .map(|rotation| {
+            let omega = hex(&vk.domain().rotate_omega(Fr::one(), Rotation(rotation)));
+            yul.word(fr_mul(fr_mul(&omega, common), fr_inv(fr_sub(x, &omega))))
+        })
+        .collect();
+    let l_last = l_evals[0];
+    let l_blind = yul.word("0");
+    for eval in l_evals[1..(1 + blinding_factors)].iter() {
+        yul.set(l_blind, fr_add(l_blind, eval));
+    }
+    let l_0 = l_evals[1 + blinding_factors];
+    let active_rows = yul.word(fr_sub("1", fr_add(l_last, l_blind)));
+
+    yul.comment("Quotient evaluation");
+    let mut quotient = Quotient {
+        h_eval: yul.word("0"),
+        y,
+    };
+    for gate in cs.gates.iter() {
+        for poly in gate.polynomials() {
+            let mark = yul.mark();
+            let value = evaluate(&mut yul, poly, &evals);
+            quotient.push(&mut yul, value);
+            yul.release(mark);
+        }
+    }
+
+    let column_eval = |column: &Column<Any>| match column.column_type() {
+        Any::Advice => evals.advice[cs.get_any_query_index(*column, Rotation::cur())],
+        Any::Fixed => evals.fixed[cs.get_any_query_index(*column, Rotation::cur())],
+        Any::Instance => evals.instance[cs.get_any_query_index(*column, Rotation::cur())],
+    };
+    if let Some(first_set) = permutation_sets.first() {
+        quotient.push(&mut yul, fr_mul(l_0, fr_sub("1", first_set.eval)));
+    }
+    if let Some(last_set) = permutation_sets.last() {
+        let eval = last_set.eval;
+        quotient.push(&mut yul, fr_mul(fr_sub(fr_mul(eval, eval), eval), l_last));
+    }
+    for (set, last_set) in permutation_sets.iter().skip(1).zip(permutation_sets.iter()) {
+        let prev_last = last_set.last_eval.unwrap();
+        quotient.push(&mut yul, fr_mul(fr_sub(set.eval, prev_last), l_0));
+    }
+    let beta_x = yul.word(fr_mul(beta, x));
+    for (chunk_index, ((set, columns), permutation_evals)) in permutation_sets
+        .iter()
+        .zip(permutation_columns.chunks(chunk_len))
+        .zip(permutation_evals.chunks(chunk_len))
+        .enumerate()
+    {
+        let mark = yul.mark();
+        let mut left = set.next_eval;
+        for (column, permutation_eval) in columns.iter().zip(permutation_evals.iter()) {
+            let term = fr_add(
+                fr_add(column_eval(column), fr_mul(beta, permutation_eval)),
+                gamma,
+            );
+            left = yul.word(fr_mul(left, term));
+        }
+        let mut right = set.eval;
+        let mut delta = Fr::DELTA.pow_vartime(&[(chunk_index * chunk_len) as u64]);
+        for column in columns.iter() {
+            let term = fr_add(
+                fr_add(column_eval(column), fr_mul(beta_x, hex(&delta))),
+                gamma,
+            );
+            right = yul.word(fr_mul(right, term));
+            delta *= &Fr::DELTA;
+        }
+        quotient.push(&mut yul, fr_mul(fr_sub(left, right), active_rows));
+        yul.release(mark);
+    }
+
+    let challenges = Challenges {
+        beta,
+        gamma,
+        l_0,
+        l_last,
+        active_rows,
+    };
+    for (lookup, argument) in lookups.iter().zip(cs.lookups.iter()) {
+        let mark = yul.mark();
+        let inputs = compress(&mut yul, &argument.input_expressions, &evals, theta);
+        let tables = compress(&mut yul, &argument.table_expressions, &evals, theta);
+        for value in lookup_expressions(&mut yul, lookup, &challenges, inputs, tables) {
+            quotient.push(&mut yul, value);
+        }
+        yul.release(mark);
+    }
+
+    for (&(_, product_eval, product_next_eval), argument) in shuffles.iter().zip(cs.shuffles.iter())
+    {
+        let mark = yul.mark();
+        quotient.push(&mut yul, fr_mul(l_0, fr_sub("1", product_eval)));
+        quotient.push(
+            &mut yul,
+            fr_mul(
+                l_last,
+                fr_sub(fr_mul(product_eval, product_eval), product_eval),
+            ),
+        );
+        let inputs = compress(&mut yul, &argument.input_expressions, &evals, theta);
+        let shuffled = compress(&mut yul, &argument.shuffle_expressions, &evals, theta);
+        let left = fr_mul(product_next_eval, fr_add(shuffled, gamma));
+        let right = fr_mul(product_eval, fr_add(inputs, gamma));
+        quotient.push(&mut yul, fr_mul(fr_sub(left, right), active_rows));
+        yul.release(mark);
+    }
+
+    let expected_h_eval = yul.word(fr_mul(quotient.h_eval, fr_inv(fr_sub(xn, "1"))));
+    let h_commitment = yul.copy(h_commitments.last().unwrap());
+    for h in h_commitments.iter().rev().skip(1) {
+        yul.scale(&h_commitment, xn);
+        yul.add(&h_commitment, h);
+    }
+    let h_commitment = commitments.push(h_commitment);
+
+    yul.comment("Queries");
+    let mut queries = vec![];
+    for (&(column, at), &eval) in cs.instance_queries.iter().zip(evals.instance.iter()) {
+        queries.push(Query::new(instance_commitments[column.index()], at, eval));
+    }
+    for (&(column, at), &eval) in cs.advice_queries.iter().zip(evals.advice.iter()) {
+        queries.push(Query::new(advice_commitments[column.index()], at, eval));
+    }
+    let last_rotation = Rotation(-((blinding_factors + 1) as i32));
+    for set in permutation_sets.iter() {
+        queries.push(Query::new(set.commitment, Rotation::cur(), set.eval));
+        queries.push(Query::new(set.commitment, Rotation::next(), set.next_eval));
+    }
+    for set in permutation_sets.iter().rev().skip(1) {
+        queries.push(Query::new(
+            set.commitment,
+            last_rotation,
+            set.last_eval.unwrap(),
+        ));
+    }
+    for lookup in lookups.iter() {
+        queries.extend(lookup_queries(lookup));
+    }
+    for &(commitment, product_eval, product_next_eval) in shuffles.iter() {
+        queries.push(Query::new(commitment, Rotation::cur(), product_eval));
+        queries.push(Query::new(commitment, Rotation::next(), product_next_eval));
+    }
+    let mut fixed_commitments = BTreeMap::new();
+    for (&(column, at), &eval) in cs.fixed_queries.iter().zip(evals.fixed.iter()) {
+        let commitment = *fixed_commitments.entry(column.index()).or_insert_with(|| {
+            commitments.push(Point::constant(&vk.fixed_commitments()[column.index()]))
+        });
+        queries.push(Query::new(commitment, at, eval));
+    }
+    for (commitment, &eval) in vk
+        .permutation_commitments()
+        .iter()
+        .zip(permutation_evals.iter())
+    {
+        let commitment = commitments.push(Point::constant(commitment));
+        queries.push(Query::new(commitment, Rotation::cur(), eval));
+    }
+    queries.push(Query::new(h_commitment, Rotation::cur(), expected_h_eval));
+    queries.push(Query::new(random_poly, Rotation::cur(), random_eval));
+
+    // Like the native verifier, only open each commitment once per rotation,
+    // and revert if the dropped queries claim another evaluation.
+    let mut unique_queries: Vec<Query> = vec![];
+    let mut indices = BTreeMap::new();
+    for query in queries {
+        match indices.get(&(query.commitment, query.rotation)) {
+            Some(&index) => {
+                let eval = unique_queries[index].eval;
+                if eval != query.eval {
+                    yul.assert_eq_word(eval, query.eval);
+                }
+            }
+            None => {
+                indices.insert((query.commitment, query.rotation), unique_queries.len());
+                unique_queries.push(query);
+            }
+        }
+    }
+    let queries = unique_queries;
+
+    let mut points = BTreeMap::new();
+    for query in queries.iter() {
+        points.entry(query.rotation).or_insert_with(|| {
+            let omega = vk.domain().rotate_omega(Fr::one(), query.rotation);
+            yul.word(fr_mul(x, hex(&omega)))
+        });
+    }
+
+    yul.comment("Multi-opening argument");
+    let (left, right) = match params.scheme() {
+        CommitmentScheme::KzgGwc => {
+            multiopen_gwc(&mut yul, params, vk, &commitments, &queries, &points, x)
+        }
+        CommitmentScheme::KzgShplonk => {
+            multiopen_shplonk(&mut yul, params, vk, &commitments, &queries, &points, x)
+        }
+    };
+
+    yul.comment("Pairing check");
+    yul.pairing_check([
+        (&left, g2_words(&params.s_g2)),
+        (&right, g2_words(&-params.g2)),
+    ]);
+
+    let proof_len = yul.proof_len();
+    Ok(EvmVerifier {
+        source: yul.into_contract(num_instances.iter().sum()),
+        num_instances: num_instances.to_vec(),
+        proof_len,
+    })
+}
+
+/// The commitments opened by the verifier, identified by their index.
+#[derive(Debug, Default)]
+struct Commitments(Vec<Point>);
+
+impl Commitments {
+    fn push(&mut self, point: Point) -> usize {
+        self.0.push(point);
+        self.0.len() - 1
+    }
+}
+
+impl std::ops::Index<usize> for Commitments {
+    type Output = Point;
+
+    fn index(&self, index: usize) -> &Point {
+        &self.0[index]
+    }
+}
+
+/// The evaluations of the circuit's columns at their queried rotations.
+struct Evals {
+    instance: Vec<Word>,
+    advice: Vec<Word>,
+    fixed: Vec<Word>,
+}
+
+struct PermutationSet {
+    commitment: usize,
+    eval: Word,
+    next_eval: Word,
+    last_eval: Option<Word>,
+}
+
+/// The commitments of a lookup argument, in the order they are read, along
+/// with its evaluations.
+struct Lookup {
+    commitments: Vec<usize>,
+    evals: Vec<Word>,
+}
+
+#[derive(Clone, Copy)]
+struct Challenges {
+    beta: Word,
+    #[cfg_attr(feature = "mv-lookup", allow(dead_code))]
+    gamma: Word,
+    l_0: Word,
+    l_last: Word,
+    active_rows: Word,
+}
+
+/// The running evaluation of the quotient's numerator, `h(x) (x^n - 1)`.
+struct Quotient {
+    h_eval: Word,
+    y: Word,
+}
+
+impl Quotient {
+    fn push(&mut self, yul: &mut Yul, value: String) {
+        yul.set(self.h_eval, fr_add(fr_mul(self.h_eval, self.y), value));
+    }
+}
+
+struct Query {
+    commitment: usize,
+    rotation: Rotation,
+    eval: Word,
+}
+
+impl Query {
+    fn new(commitment: usize, rotation: Rotation, eval: Word) -> Self {
+        Query {
+            commitment,
+            rotation,
+            eval,
+        }
+    }
+}
+
+/// Evaluates an expression, storing every intermediate value in memory.
+fn evaluate(yul: &mut Yul, expression: &Expression<Fr>, evals: &Evals) -> String {
+    match expression {
+        Expression::Constant(scalar) => hex(scalar),
+        Expression::Selector(_) => panic!("virtual selectors are removed during optimization"),
+        Expression::Fixed { query_index, .. } => evals.fixed[*query_index].to_string(),
+        Expression::Advice { query_index, .. } => evals.advice[*query_index].to_string(),
+        Expression::Instance { query_index, .. } => evals.instance[*query_index].to_string(),
+        Expression::Negated(a) => {
+            let a = evaluate(yul, a, evals);
+            yul.word(fr_neg(a)).to_string()
+        }
+        Expression::Sum(a, b) => {
+            let a = evaluate(yul, a, evals);
+            let b = evaluate(yul, b, evals);
+            yul.word(fr_add(a, b)).to_string()
+        }
+        Expression::Product(a, b) => {
+            let a = evaluate(yul, a, evals);
+            let b = evaluate(yul, b, evals);
+            yul.word(fr_mul(a, b)).to_string()
+        }
+        Expression::Scaled(a, scalar) => {
+            let a = evaluate(yul, a, evals);
+            yul.word(fr_mul(a, hex(scalar))).to_string()
+        }
+    }
+}
+
+/// Evaluates `\theta^{m-1} e_0 + ... + e_{m-1}`.
+fn compress(yul: &mut Yul, expressions: &[Expression<Fr>], evals: &Evals, theta: Word) -> Word {
+    let acc = yul.word("0");
+    for expression in expressions {
+        let eval = evaluate(yul, expression, evals);
+        yul.set(acc, fr_add(fr_mul(acc, theta), eval));
+    }
+    acc
+}
+
+#[cfg(not(feature = "mv-lookup"))]
+const LOOKUP_EVALS: usize = 5;
+
+/// Reads the permuted input and table commitments of a lookup.
+#[cfg(not(feature = "mv-lookup"))]
+fn read_lookup_permuted(yul: &mut Yul, commitments: &mut Commitments) -> Vec<usize> {
+    let input = yul.read_point();
+    let table = yul.read_point();
+    vec![commitments.push(input), commitments.push(table)]
+}
+
+#[cfg(not(feature = "mv-lookup"))]
+fn lookup_expressions(
+    yul: &mut Yul,
+    lookup: &Lookup,
+    challenges: &Challenges,
+    inputs: Word,
+    tables: Word,
+) -> Vec<String> {
+    let (product_eval, product_next_eval, input_eval, input_inv_eval, table_eval) = (
+        lookup.evals[0],
+        lookup.evals[1],
+        lookup.evals[2],
+        lookup.evals[3],
+        lookup.evals[4],
+    );
+    let Challenges {
+        beta,
+        gamma,
+        l_0,
+        l_last,
+        active_rows,
+        ..
+    } = *challenges;
+
+    let left = yul.word(fr_mul(
+        fr_mul(product_next_eval, fr_add(input_eval, beta)),
+        fr_add(table_eval, gamma),
+    ));
+    let right = yul.word(fr_mul(
+        fr_mul(product_eval, fr_add(inputs, beta)),
+        fr_add(tables, gamma),
+    ));
+
+    vec![
+        fr_mul(l_0, fr_sub("1", product_eval)),
+        fr_mul(
+            l_last,
+            fr_sub(fr_mul(product_eval, product_eval), product_eval),
+        ),
+        fr_mul(fr_sub(left, right), active_rows),
+        fr_mul(l_0, fr_sub(input_eval, table_eval)),
+        fr_mul(
+            fr_mul(
+                fr_sub(input_eval, table_eval),
+                fr_sub(input_eval, input_inv_eval),
+            ),
+            active_rows,
+        ),
+    ]
+}
+
+#[cfg(not(feature = "mv-lookup"))]
+fn lookup_queries(lookup: &Lookup) -> Vec<Query> {
+    let (input, table, product) = (
+        lookup.commitments[0],
+        lookup.commitments[1],
+        lookup.commitments[2],
+    );
+    vec![
+        Query::new(product, Rotation::cur(), lookup.evals[0]),
+        Query::new(input, Rotation::cur(), lookup.evals[2]),
+        Query::new(table, Rotation::cur(), lookup.evals[4]),
+        Query::new(input, Rotation::prev(), lookup.evals[3]),
+        Query::new(product, Rotation::next(), lookup.evals[1]),
+    ]
+}
+
+#[cfg(feature = "mv-lookup")]
+const LOOKUP_EVALS: usize = 3;
+
+/// Reads the multiplicity commitment of a lookup.
+#[cfg(feature = "mv-lookup")]
+fn read_lookup_permuted(yul: &mut Yul, commitments: &mut Commitments) -> Vec<usize> {
+    let multiplicity = yul.read_point();
+    vec![commitments.push(multiplicity)]
+}
+
+#[cfg(feature = "mv-lookup")]
+fn lookup_expressions(
+    yul: &mut Yul,
+    lookup: &Lookup,
+    challenges: &Challenges,
+    inputs: Word,
+    tables: Word,
+) -> Vec<String> {
+    let (phi_eval, phi_next_eval, multiplicity_eval) =
+        (lookup.evals[0], lookup.evals[1], lookup.evals[2]);
+    let Challenges {
+        beta,
+        l_0,
+        l_last,
+        active_rows,
+        ..
+    } = *challenges;
+
+    let input_term = yul.word(fr_add(inputs, beta));
+    let table_term = yul.word(fr_add(tables, beta));
+    let left = yul.word(fr_mul(
+        fr_mul(fr_sub(phi_next_eval, phi_eval), input_term),
+        table_term,
+    ));
+    let right = yul.word(fr_sub(table_term, fr_mul(multiplicity_eval, input_term)));
+
+    vec![
+        fr_mul(l_0, phi_eval),
+        fr_mul(l_last, phi_eval),
+        fr_mul(fr_sub(left, right), active_rows),
+    ]
+}
+
+#[cfg(feature = "mv-lookup")]
+fn lookup_queries(lookup: &Lookup) -> Vec<Query> {
+    let (multiplicity, phi) = (lookup.commitments[0], lookup.commitments[1]);
+    vec![
+        Query::new(phi, Rotation::cur(), lookup.evals[0]),
+        Query::new(multiplicity, Rotation::cur(), lookup.evals[2]),
+        Query::new(phi, Rotation::next(), lookup.evals[1]),
+    ]
+}
+
+/// Computes `[base^{n-1}, ..., base, 1]`.
+fn descending_powers(yul: &mut Yul, base: Word, n: usize) -> Vec<Word> {
+    let mut powers = vec![yul.word("1")];
+    for _ in 1..n {
+        let last = *powers.last().unwrap();
+        powers.push(yul.word(fr_mul(last, base)));
+    }
+    powers.reverse();
+    powers
+}
+
+/// Verifies the SHPLONK multi-opening argument, returning the two points of
+/// the final pairing check.
+fn multiopen_shplonk(
+    yul: &mut Yul,
+    params: &ParamsVerifier<Bn256>,
+    vk: &VerifyingKey<G1Affine>,
+    commitments: &Commitments,
+    queries: &[Query],
+    points: &BTreeMap<Rotation, Word>,
+    x: Word,
+) -> (Point, Point) {
+    // Group the commitments by the set of rotations at which they are
+    // opened, in the same order as the native verifier.
+    use std::collections::BTreeSet;
+
+    let mut commitment_rotations: Vec<(usize, BTreeSet<Rotation>)> = vec![];
+    for query in queries.iter() {
+        if let Some(pos) = commitment_rotations
+            .iter()
+            .position(|(commitment, _)| *commitment == query.commitment)
+        {
+            commitment_rotations[pos].1.insert(query.rotation);
+        } else {
+            let rotations = std::iter::once(query.rotation).collect();
+            commitment_rotations.push((query.commitment, rotations));
+        }
+    }
+    let mut rotation_sets = BTreeMap::<BTreeSet<Rotation>, Vec<usize>>::new();
+    for (commitment, rotations) in commitment_rotations {
+        let commitments = rotation_sets.entry(rotations).or_insert_with(Vec::new);
+        if !commitments.contains(&commitment) {
+            commitments.push(commitment);
+        }
+    }
+    let eval = |commitment: usize, rotation: Rotation| {
+        queries
+            .iter()
+            .find(|query| query.commitment == commitment && query.rotation == rotation)
+            .unwrap()
+            .eval
+    };
+
+    let y = yul.squeeze();
+    let v = yul.squeeze();
+    let h1 = yul.read_point();
+    let u = yul.squeeze();
+    let h2 = yul.read_point();
+
+    let x_inv = yul.word(fr_inv(x));
+    let v_powers = descending_powers(yul, v, rotation_sets.len());
+    let max_commitments = rotation_sets.values().map(Vec::len).max().unwrap();
+    let y_powers = descending_powers(yul, y, max_commitments);
+
+    let right = yul.identity();
+    let r_outer = yul.word("0");
+    let mut z_0 = None;
+    let mut z_0_diff_inv = None;
+    for (i, (rotations, set_commitments)) in rotation_sets.iter().enumerate() {
+        let z_diff = yul.word("1");
+        for rotation in points
+            .keys()
+            .filter(|rotation| !rotations.contains(*rotation))
+        {
+            yul.set(z_diff, fr_mul(z_diff, fr_sub(u, points[rotation])));
+        }
+        let z_diff = match z_0_diff_inv {
+            None => {
+                let z = yul.word("1");
+                for rotation in rotations.iter() {
+                    yul.set(z, fr_mul(z, fr_sub(u, points[rotation])));
+                }
+                z_0 = Some(z);
+                z_0_diff_inv = Some(yul.word(fr_inv(z_diff)));
+                yul.word("1")
+            }
+            Some(inv) => yul.word(fr_mul(z_diff, inv)),
+        };
+
+        // The Lagrange basis of the set's points, evaluated at u. The points
+        // are x \omega^{r_k}, so the denominators are x^{m-1} times constants.
+        let rotations: Vec<Rotation> = rotations.iter().cloned().collect();
+        let omegas: Vec<Fr> = rotations
+            .iter()
+            .map(|&rotation| vk.domain().rotate_omega(Fr::one(), rotation))
+            .collect();
+        let x_inv_pow = yul.word("1");
+        for _ in 1..rotations.len() {
+            yul.set(x_inv_pow, fr_mul(x_inv_pow, x_inv));
+        }
+        let basis: Vec<Word> = rotations
+            .iter()
+            .enumerate()
+            .map(|(k, _)| {
+                let denominator = omegas
+                    .iter()
+                    .enumerate()
+                    .filter(|&(l, _)| l != k)
+                    .fold(Fr::one(), |acc, (_, omega)| acc * (omegas[k] - omega));
+                let basis = yul.word(fr_mul(x_inv_pow, hex(&denominator.invert().unwrap())));
+                for (l, rotation) in rotations.iter().enumerate() {
+                    if l != k {
+                        yul.set(basis, fr_mul(basis, fr_sub(u, points[rotation])));
+                    }
+                }
+                basis
+            })
+            .collect();
+
+        let r_inner = yul.word("0");
+        let set_y_powers = &y_powers[max_commitments - set_commitments.len()..];
+        for (&commitment, y_power) in set_commitments.iter().zip(set_y_powers.iter()) {
+            let r_eval = yul.word("0");
+            for (rotation, basis) in rotations.iter().zip(basis.iter()) {
+                yul.set(
+                    r_eval,
+                    fr_add(r_eval, fr_mul(eval(commitment, *rotation), basis)),
+                );
+            }
+            yul.set(r_inner, fr_add(fr_mul(r_inner, y), r_eval));
+
+            let coeff = fr_mul(v_powers[i], fr_mul(z_diff, y_power));
+            yul.mul_add(&right, &commitments[commitment], coeff);
+        }
+        yul.set(r_outer, fr_add(fr_mul(r_outer, v), fr_mul(r_inner, z_diff)));
+    }
+
+    yul.mul_add(&right, &Point::constant(&params.g1), fr_neg(r_outer));
+    yul.mul_add(&right, &h1, fr_neg(z_0.unwrap()));
+    yul.mul_add(&right, &h2, u);
+
+    (h2, right)
+}
+
+/// Verifies the GWC multi-opening argument, returning the two points of the
+/// final pairing check.
+fn multiopen_gwc(
+    yul: &mut Yul,
+    params: &ParamsVerifier<Bn256>,
+    _vk: &VerifyingKey<G1Affine>,
+    commitments: &Commitments,
+    queries: &[Query],
+    points: &BTreeMap<Rotation, Word>,
+    _x: Word,
+) -> (Point, Point) {
+    // Group the queries by rotation, in the same order as the native verifier.
+    let mut sets = BTreeMap::<Rotation, Vec<&Query>>::new();
+    for query in queries.iter() {
+        sets.entry(query.rotation)
+            .or_insert_with(Vec::new)
+            .push(query);
+    }
+
+    let v = yul.squeeze();
+    let u = yul.squeeze();
+    let witnesses = yul.read_points(sets.len());
+
+    let u_powers = descending_powers(yul, u, sets.len());
+    let max_queries = sets.values().map(Vec::len).max().unwrap();
+    let v_powers = descending_powers(yul, v, max_queries);
+
+    let left = yul.identity();
+    let right = yul.identity();
+    let eval_multi = yul.word("0");
+    for (((rotation, queries), witness), u_power) in
+        sets.iter().zip(witnesses.iter()).zip(u_powers.iter())
+    {
+        yul.mul_add(&left, witness, u_power);
+        yul.mul_add(&right, witness, fr_mul(u_power, points[rotation]));
+
+        let eval_batch = yul.word("0");
+        let set_v_powers = &v_powers[max_queries - queries.len()..];
+        for (query, v_power) in queries.iter().zip(set_v_powers.iter()) {
+            yul.mul_add(
+                &right,
+                &commitments[query.commitment],
+                fr_mul(u_power, v_power),
+            );
+            yul.set(eval_batch, fr_add(fr_mul(eval_batch, v), query.eval));
+        }
+        yul.set(eval_multi, fr_add(fr_mul(eval_multi, u), eval_batch));
+    }
+    yul.mul_add(&right, &Point::constant(&params.g1), fr_neg(eval_multi));
+
+    (left, right)
+}
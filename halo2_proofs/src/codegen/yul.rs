@@ -0,0 +1,445 @@
+//! A minimal builder for the straight-line Yul code of a verifier contract.
+//!
+//! All the values that the verifier computes live at fixed memory offsets,
+//! which are assigned at generation time. This keeps the number of Yul stack
+//! variables constant no matter how large the circuit is, so the contract
+//! never runs into "stack too deep" errors.
+
+use std::fmt::{self, Write};
+
+use group::ff::PrimeField;
+use pairing::arithmetic::{Coordinates, CurveAffine};
+use pairing::bn256::{Fr, G1Affine, G2Affine};
+
+use crate::transcript::keccak::to_be_bytes;
+
+/// The BN254 scalar field modulus.
+pub(super) const R: &str = "0x30644e72e131a029b85045b68181585d2833e84879b9709143e1f593f0000001";
+/// The BN254 base field modulus.
+pub(super) const Q: &str = "0x30644e72e131a029b85045b68181585d97816a916871ca8d3c208c16d87cfd47";
+
+/// Memory below this offset is scratch space for precompile calls, which
+/// need at most 12 words (for a pairing check of two pairs).
+const SCRATCH_END: usize = 0x200;
+/// Memory offset of the proof's calldata cursor.
+const CURSOR: usize = SCRATCH_END;
+/// Memory offset of the length of the transcript state.
+const TRANSCRIPT_LEN: usize = SCRATCH_END + 0x20;
+
+/// Formats a field element as a Yul hex literal.
+pub(super) fn hex<F: PrimeField>(value: &F) -> String {
+    let mut s = String::from("0x");
+    for byte in to_be_bytes(value) {
+        write!(s, "{:02x}", byte).unwrap();
+    }
+    s
+}
+
+/// A scalar stored in memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(super) struct Word(usize);
+
+impl fmt::Display for Word {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "mload({:#x})", self.0)
+    }
+}
+
+/// A G1 point, either stored in memory or known at generation time.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub(super) enum Point {
+    /// Affine coordinates at `offset` and `offset + 0x20`.
+    Memory(usize),
+    /// Affine coordinates as Yul literals.
+    Constant(String, String),
+}
+
+impl Point {
+    /// Returns a constant point. The identity is encoded as `(0, 0)`, like the
+    /// EVM precompiles do.
+    pub(super) fn constant(point: &G1Affine) -> Self {
+        let coords: Option<Coordinates<G1Affine>> = point.coordinates().into();
+        match coords {
+            Some(coords) => Point::Constant(hex(coords.x()), hex(coords.y())),
+            None => Point::Constant("0".to_string(), "0".to_string()),
+        }
+    }
+
+    pub(super) fn x(&self) -> String {
+        match self {
+            Point::Memory(offset) => format!("mload({:#x})", offset),
+            Point::Constant(x, _) => x.clone(),
+        }
+    }
+
+    pub(super) fn y(&self) -> String {
+        match self {
+            Point::Memory(offset) => format!("mload({:#x})", offset + 0x20),
+            Point::Constant(_, y) => y.clone(),
+        }
+    }
+
+    fn offset(&self) -> usize {
+        match self {
+            Point::Memory(offset) => *offset,
+            Point::Constant(..) => panic!("constant points can't be written to"),
+        }
+    }
+}
+
+/// Returns the words of a G2 point in the order expected by the pairing
+/// precompile: `x.c1, x.c0, y.c1, y.c0`.
+pub(super) fn g2_words(point: &G2Affine) -> [String; 4] {
+    let coords: Coordinates<G2Affine> =
+        Option::from(point.coordinates()).expect("G2 point is not the identity");
+    [
+        hex(&coords.x().c1),
+        hex(&coords.x().c0),
+        hex(&coords.y().c1),
+        hex(&coords.y().c0),
+    ]
+}
+
+pub(super) fn fr_add(a: impl fmt::Display, b: impl fmt::Display) -> String {
+    format!("addmod({}, {}, {})", a, b, R)
+}
+
+pub(super) fn fr_sub(a: impl fmt::Display, b: impl fmt::Display) -> String {
+    format!("fr_sub({}, {})", a, b)
+}
+
+pub(super) fn fr_mul(a: impl fmt::Display, b: impl fmt::Display) -> String {
+    format!("mulmod({}, {}, {})", a, b, R)
+}
+
+pub(super) fn fr_neg(a: impl fmt::Display) -> String {
+    format!("fr_neg({})", a)
+}
+
+pub(super) fn fr_inv(a: impl fmt::Display) -> String {
+    format!("fr_inv({})", a)
+}
+
+/// The body of a verifier, together with its memory layout.
+#[derive(Debug)]
+pub(super) struct Yul {
+    code: String,
+    next: usize,
+    high_water: usize,
+    proof_len: usize,
+}
+
+impl Yul {
+    pub(super) fn new() -> Self {
+        Yul {
+            code: String::new(),
+            next: TRANSCRIPT_LEN + 0x20,
+            high_water: TRANSCRIPT_LEN + 0x20,
+            proof_len: 0,
+        }
+    }
+
+    /// The number of bytes of proof read so far.
+    pub(super) fn proof_len(&self) -> usize {
+        self.proof_len
+    }
+
+    pub(super) fn line(&mut self, line: impl AsRef<str>) {
+        self.code.push_str("            ");
+        self.code.push_str(line.as_ref());
+        self.code.push('\n');
+    }
+
+    pub(super) fn comment(&mut self, comment: impl AsRef<str>) {
+        self.line(format!("// {}", comment.as_ref()));
+    }
+
+    fn alloc(&mut self, words: usize) -> usize {
+        let offset = self.next;
+        self.next += 0x20 * words;
+        self.high_water = self.high_water.max(self.next);
+        offset
+    }
+
+    /// Returns a marker for the memory allocated so far; everything allocated
+    /// after it can be released with [`Self::release`].
+    pub(super) fn mark(&self) -> usize {
+        self.next
+    }
+
+    pub(super) fn release(&mut self, mark: usize) {
+        self.next = mark;
+    }
+
+    /// Stores the value of a Yul expression in a new word.
+    pub(super) fn word(&mut self, value: impl fmt::Display) -> Word {
+        let word = Word(self.alloc(1));
+        self.set(word, value);
+        word
+    }
+
+    pub(super) fn set(&mut self, word: Word, value: impl fmt::Display) {
+        self.line(format!("mstore({:#x}, {})", word.0, value));
+    }
+
+    /// Allocates a point, initialized to the identity.
+    pub(super) fn identity(&mut self) -> Point {
+        let offset = self.alloc(2);
+        self.line(format!("mstore({:#x}, 0)", offset));
+        self.line(format!("mstore({:#x}, 0)", offset + 0x20));
+        Point::Memory(offset)
+    }
+
+    /// Allocates a point, initialized to a copy of `point`.
+    pub(super) fn copy(&mut self, point: &Point) -> Point {
+        let offset = self.alloc(2);
+        self.line(format!("mstore({:#x}, {})", offset, point.x()));
+        self.line(format!("mstore({:#x}, {})", offset + 0x20, point.y()));
+        Point::Memory(offset)
+    }
+
+    /// `acc += scalar * point`
+    pub(super) fn mul_add(&mut self, acc: &Point, point: &Point, scalar: impl fmt::Display) {
+        self.line(format!(
+            "ec_mul_acc({:#x}, {}, {}, {})",
+            acc.offset(),
+            point.x(),
+            point.y(),
+            scalar
+        ));
+    }
+
+    /// `acc += point`
+    pub(super) fn add(&mut self, acc: &Point, point: &Point) {
+        self.line(format!(
+            "ec_add_acc({:#x}, {}, {})",
+            acc.offset(),
+            point.x(),
+            point.y()
+        ));
+    }
+
+    /// `acc *= scalar`
+    pub(super) fn scale(&mut self, acc: &Point, scalar: impl fmt::Display) {
+        self.line(format!("ec_scale({:#x}, {})", acc.offset(), scalar));
+    }
+
+    /// `word = word^(2^k)`
+    pub(super) fn square_n(&mut self, word: Word, k: u32) {
+        self.line(format!(
+            "for {{ let i := 0 }} lt(i, {}) {{ i := add(i, 1) }} {{ mstore({:#x}, mulmod({}, {}, {})) }}",
+            k, word.0, word, word, R
+        ));
+    }
+
+    /// Reverts unless `a == b`.
+    pub(super) fn assert_eq_word(&mut self, a: Word, b: Word) {
+        self.line(format!("if iszero(eq({}, {})) {{ revert(0, 0) }}", a, b));
+    }
+
+    /// Reverts unless `a == b`.
+    pub(super) fn assert_eq_point(&mut self, a: &Point, b: &Point) {
+        self.line(format!(
+            "if iszero(and(eq({}, {}), eq({}, {}))) {{ revert(0, 0) }}",
+            a.x(),
+            b.x(),
+            a.y(),
+            b.y()
+        ));
+    }
+
+    pub(super) fn absorb(&mut self, value: impl fmt::Display) {
+        self.line(format!("absorb({})", value));
+    }
+
+    pub(super) fn absorb_point(&mut self, point: &Point) {
+        self.absorb(point.x());
+        self.absorb(point.y());
+    }
+
+    pub(super) fn squeeze(&mut self) -> Word {
+        self.word("squeeze()")
+    }
+
+    pub(super) fn read_scalar(&mut self) -> Word {
+        self.proof_len += 0x20;
+        self.word("read_scalar()")
+    }
+
+    pub(super) fn read_scalars(&mut self, n: usize) -> Vec<Word> {
+        (0..n).map(|_| self.read_scalar()).collect()
+    }
+
+    pub(super) fn read_point(&mut self) -> Point {
+        self.proof_len += 0x40;
+        let offset = self.alloc(2);
+        self.line(format!("read_point({:#x})", offset));
+        Point::Memory(offset)
+    }
+
+    pub(super) fn read_points(&mut self, n: usize) -> Vec<Point> {
+        (0..n).map(|_| self.read_point()).collect()
+    }
+
+    /// Checks that `e(lhs, rhs_g2) * e(lhs2, rhs2_g2) == 1`, reverting
+    /// otherwise.
+    pub(super) fn pairing_check(&mut self, pairs: [(&Point, [String; 4]); 2]) {
+        let mut offset = 0;
+        for (g1, g2) in pairs.iter() {
+            self.line(format!("mstore({:#x}, {})", offset, g1.x()));
+            self.line(format!("mstore({:#x}, {})", offset + 0x20, g1.y()));
+            offset += 0x40;
+            for word in g2.iter() {
+                self.line(format!("mstore({:#x}, {})", offset, word));
+                offset += 0x20;
+            }
+        }
+        self.line(format!(
+            "let paired := staticcall(gas(), 0x08, 0x00, {:#x}, 0x00, 0x20)",
+            offset
+        ));
+        self.line("if iszero(and(paired, mload(0x00))) { revert(0, 0) }");
+    }
+
+    /// Wraps the body into a Solidity contract.
+    pub(super) fn into_contract(self, num_instances: usize) -> String {
+        let transcript = self.high_water;
+        let helpers = HELPERS
+            .replace("{R}", R)
+            .replace("{Q}", Q)
+            .replace("{CURSOR}", &format!("{:#x}", CURSOR))
+            .replace("{TRANSCRIPT_LEN}", &format!("{:#x}", TRANSCRIPT_LEN))
+            .replace("{TRANSCRIPT}", &format!("{:#x}", transcript));
+
+        CONTRACT
+            .replace("{HELPERS}", &helpers)
+            .replace("{NUM_INSTANCES}", &num_instances.to_string())
+            .replace("{PROOF_LEN}", &self.proof_len.to_string())
+            .replace("{R}", R)
+            .replace("{CURSOR}", &format!("{:#x}", CURSOR))
+            .replace("{TRANSCRIPT_LEN}", &format!("{:#x}", TRANSCRIPT_LEN))
+            .replace("{BODY}", self.code.trim_end())
+    }
+}
+
+/// The Yul helpers used by the body of the verifier. Everything that can fail
+/// reverts, so the body itself never has to check for errors.
+const HELPERS: &str = "\
+            function fr_sub(a, b) -> c {
+                c := addmod(a, sub({R}, b), {R})
+            }
+
+            function fr_neg(a) -> c {
+                c := mod(sub({R}, a), {R})
+            }
+
+            function fr_inv(a) -> c {
+                mstore(0x00, 0x20)
+                mstore(0x20, 0x20)
+                mstore(0x40, 0x20)
+                mstore(0x60, a)
+                mstore(0x80, sub({R}, 2))
+                mstore(0xa0, {R})
+                if iszero(staticcall(gas(), 0x05, 0x00, 0xc0, 0x00, 0x20)) { revert(0, 0) }
+                c := mload(0x00)
+            }
+
+            function absorb(w) {
+                let len := mload({TRANSCRIPT_LEN})
+                mstore(add({TRANSCRIPT}, len), w)
+                mstore({TRANSCRIPT_LEN}, add(len, 0x20))
+            }
+
+            function squeeze() -> c {
+                let h := keccak256({TRANSCRIPT}, mload({TRANSCRIPT_LEN}))
+                mstore({TRANSCRIPT}, h)
+                mstore({TRANSCRIPT_LEN}, 0x20)
+                c := mod(h, {R})
+            }
+
+            function read_scalar() -> s {
+                let cursor := mload({CURSOR})
+                s := calldataload(cursor)
+                if iszero(lt(s, {R})) { revert(0, 0) }
+                mstore({CURSOR}, add(cursor, 0x20))
+                absorb(s)
+            }
+
+            function read_point(ptr) {
+                let cursor := mload({CURSOR})
+                let x := calldataload(cursor)
+                let y := calldataload(add(cursor, 0x20))
+                if iszero(and(lt(x, {Q}), lt(y, {Q}))) { revert(0, 0) }
+                // The point at infinity is encoded as (0, 0).
+                let rhs := addmod(mulmod(x, mulmod(x, x, {Q}), {Q}), 3, {Q})
+                let on_curve := eq(mulmod(y, y, {Q}), rhs)
+                if iszero(or(on_curve, iszero(or(x, y)))) { revert(0, 0) }
+                mstore({CURSOR}, add(cursor, 0x40))
+                mstore(ptr, x)
+                mstore(add(ptr, 0x20), y)
+                absorb(x)
+                absorb(y)
+            }
+
+            function ec_mul_acc(acc, x, y, s) {
+                mstore(0x00, x)
+                mstore(0x20, y)
+                mstore(0x40, s)
+                if iszero(staticcall(gas(), 0x07, 0x00, 0x60, 0x00, 0x40)) { revert(0, 0) }
+                mstore(0x40, mload(acc))
+                mstore(0x60, mload(add(acc, 0x20)))
+                if iszero(staticcall(gas(), 0x06, 0x00, 0x80, acc, 0x40)) { revert(0, 0) }
+            }
+
+            function ec_add_acc(acc, x, y) {
+                mstore(0x00, mload(acc))
+                mstore(0x20, mload(add(acc, 0x20)))
+                mstore(0x40, x)
+                mstore(0x60, y)
+                if iszero(staticcall(gas(), 0x06, 0x00, 0x80, acc, 0x40)) { revert(0, 0) }
+            }
+
+            function ec_scale(acc, s) {
+                mstore(0x00, mload(acc))
+                mstore(0x20, mload(add(acc, 0x20)))
+                mstore(0x40, s)
+                if iszero(staticcall(gas(), 0x07, 0x00, 0x60, acc, 0x40)) { revert(0, 0) }
+            }";
+
+const CONTRACT: &str = "\
+// SPDX-License-Identifier: MIT
+// This file was generated by halo2_proofs::codegen. Do not edit.
+
+pragma solidity ^0.8.0;
+
+contract Halo2Verifier {
+    /// @notice Verifies a proof created with the Keccak256 transcript of
+    /// halo2_proofs. Returns true if the proof is valid, and reverts otherwise.
+    /// @param instances The instance columns of the circuit, concatenated.
+    /// @param proof The proof bytes, as written by the transcript.
+    function verifyProof(uint256[] calldata instances, bytes calldata proof)
+        external
+        view
+        returns (bool)
+    {
+        assembly {
+{HELPERS}
+
+            if iszero(eq(instances.length, {NUM_INSTANCES})) { revert(0, 0) }
+            if iszero(eq(proof.length, {PROOF_LEN})) { revert(0, 0) }
+            for { let i := 0 } lt(i, {NUM_INSTANCES}) { i := add(i, 1) } {
+                if iszero(lt(calldataload(add(instances.offset, mul(i, 0x20))), {R})) {
+                    revert(0, 0)
+                }
+            }
+            mstore({CURSOR}, proof.offset)
+            mstore({TRANSCRIPT_LEN}, 0)
+
+{BODY}
+
+            mstore(0x00, 1)
+            return(0x00, 0x20)
+        }
+    }
+}
+";
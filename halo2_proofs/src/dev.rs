@@ -1,5 +1,6 @@
 //! Tools for developing circuits.
 
+use std::collections::BTreeMap;
 use std::collections::HashMap;
 use std::collections::HashSet;
 use std::fmt;
@@ -15,24 +16,45 @@ use crate::{
         permutation, Advice, Any, Assignment, Circuit, Column, ColumnType, ConstraintSystem, Error,
         Expression, Fixed, FloorPlanner, Instance, Selector, VirtualCell,
     },
-    poly::Rotation,
+    poly::{max_k, Rotation},
 };
 
 pub mod metadata;
 mod util;
 
 pub mod cost;
-pub use cost::CircuitCost;
+pub use crate::poly::commitment::CommitmentScheme;
+pub use cost::{ArgumentCost, Calibration, CircuitCost, ProverWork};
 
 mod gates;
 pub use gates::CircuitGates;
 
+mod export;
+pub(crate) use export::format_polynomial;
+pub use export::CircuitConstraints;
+
+mod pinned;
+pub use pinned::assert_pinned_snapshot;
+
+mod sizing;
+pub use sizing::k_for_circuit;
+
+mod replay;
+pub use replay::{replay_proof, ProofReplay, TranscriptStep, TranscriptValue};
+
+#[cfg(feature = "alloc-stats")]
+#[cfg_attr(docsrs, doc(cfg(feature = "alloc-stats")))]
+pub mod heap;
+
 #[cfg(feature = "dev-graph")]
 mod graph;
 
 #[cfg(feature = "dev-graph")]
 #[cfg_attr(docsrs, doc(cfg(feature = "dev-graph")))]
-pub use graph::{circuit_dot_graph, layout::CircuitLayout};
+pub use graph::{
+    circuit_dot_graph,
+    layout::{CircuitLayout, LayoutDiffError},
+};
 
 /// The location within the circuit at which a particular [`VerifyFailure`] occurred.
 #[derive(Debug, PartialEq)]
@@ -110,7 +132,7 @@ impl FailureLocation {
                 (start..=end).contains(&failure_row) && !failure_columns.is_disjoint(&r.columns)
             })
             .map(|(r_i, r)| FailureLocation::InRegion {
-                region: (r_i, r.name.clone()).into(),
+                region: (r_i, r.name.clone(), r.namespaces.clone()).into(),
                 offset: failure_row as usize - r.rows.unwrap().0 as usize,
             })
             .unwrap_or_else(|| FailureLocation::OutsideRegion {
@@ -146,6 +168,12 @@ pub enum VerifyFailure {
         location: FailureLocation,
         /// The values of the virtual cells used by this constraint.
         cell_values: Vec<(metadata::VirtualCell, String)>,
+        /// The annotations of the virtual cells used by this constraint, for those
+        /// assigned inside a region.
+        cell_annotations: Vec<(metadata::VirtualCell, String)>,
+        /// The constraint's expression as a tree of its sub-terms, each with its
+        /// value, which shows where the constraint becomes non-zero.
+        expression: String,
     },
     /// A constraint was active on an unusable row, and is likely missing a selector.
     ConstraintPoisoned {
@@ -173,6 +201,26 @@ pub enum VerifyFailure {
         /// - The input expressions use a column queried at a non-zero `Rotation`, and the
         ///   lookup is active on a row adjacent to an unrelated region.
         location: FailureLocation,
+        /// The circuit row from which the input was taken.
+        row: usize,
+        /// The values of the input expressions on that row, which don't form an entry of
+        /// the table.
+        input: Vec<String>,
+        /// The entries of the table immediately before and after the input, in the order
+        /// of the values of the table expressions.
+        nearest_entries: Vec<Vec<String>>,
+    },
+    /// A shuffle input did not have a matching row in its shuffle expressions.
+    Shuffle {
+        /// The name of the shuffle that is not satisfied.
+        name: &'static str,
+        /// The index of the shuffle that is not satisfied. These indices are assigned in
+        /// the order in which `ConstraintSystem::shuffle` is called during
+        /// `Circuit::configure`.
+        shuffle_index: usize,
+        /// The location of the first input row whose values are not matched by any
+        /// remaining row of the shuffle expressions.
+        location: FailureLocation,
     },
     /// A permutation did not preserve the original value of a cell.
     Permutation {
@@ -181,6 +229,23 @@ pub enum VerifyFailure {
         /// The row on which this permutation is not satisfied.
         row: usize,
     },
+    /// A cell asserted with [`Region::assert_equal_constant`] or
+    /// [`Region::assert_equal`] did not have the expected value.
+    ///
+    /// [`Region::assert_equal_constant`]: crate::circuit::Region::assert_equal_constant
+    /// [`Region::assert_equal`]: crate::circuit::Region::assert_equal
+    Assertion {
+        /// The annotation of the assertion.
+        annotation: String,
+        /// The column of the asserted cell.
+        column: metadata::Column,
+        /// The location of the asserted cell.
+        location: FailureLocation,
+        /// The value of the asserted cell.
+        value: String,
+        /// The value of the cell or constant it was asserted to be equal to.
+        expected: String,
+    },
 }
 
 impl fmt::Display for VerifyFailure {
@@ -202,12 +267,19 @@ impl fmt::Display for VerifyFailure {
                 constraint,
                 location,
                 cell_values,
+                cell_annotations,
+                expression,
             } => {
                 writeln!(f, "{} is not satisfied {}", constraint, location)?;
                 for (name, value) in cell_values {
-                    writeln!(f, "- {} = {}", name, value)?;
+                    write!(f, "- {} = {}", name, value)?;
+                    match cell_annotations.iter().find(|(cell, _)| cell == name) {
+                        Some((_, annotation)) => writeln!(f, " ('{}')", annotation)?,
+                        None => writeln!(f)?,
+                    }
                 }
-                Ok(())
+                writeln!(f, "Expression:")?;
+                write!(f, "{}", expression)
             }
             Self::ConstraintPoisoned { constraint } => {
                 write!(
@@ -220,11 +292,31 @@ impl fmt::Display for VerifyFailure {
                 name,
                 lookup_index,
                 location,
+                row,
+                input,
+                nearest_entries,
+            } => {
+                writeln!(
+                    f,
+                    "Lookup {}(index: {}) is not satisfied {} (row {})",
+                    name, lookup_index, location, row
+                )?;
+                writeln!(f, "- input: ({})", input.join(", "))?;
+                write!(f, "- nearest table entries:")?;
+                for entry in nearest_entries {
+                    write!(f, " ({})", entry.join(", "))?;
+                }
+                Ok(())
+            }
+            Self::Shuffle {
+                name,
+                shuffle_index,
+                location,
             } => {
                 write!(
                     f,
-                    "Lookup {}(index: {}) is not satisfied {}",
-                    name, lookup_index, location
+                    "Shuffle {}(index: {}) is not satisfied {}",
+                    name, shuffle_index, location
                 )
             }
             Self::Permutation { column, row } => {
@@ -234,6 +326,19 @@ impl fmt::Display for VerifyFailure {
                     column, row
                 )
             }
+            Self::Assertion {
+                annotation,
+                column,
+                location,
+                value,
+                expected,
+            } => {
+                write!(
+                    f,
+                    "Assertion '{}' failed for cell in {} {}: {} != {}",
+                    annotation, column, location, value, expected
+                )
+            }
         }
     }
 }
@@ -242,6 +347,8 @@ impl fmt::Display for VerifyFailure {
 struct Region {
     /// The name of the region. Not required to be unique.
     name: String,
+    /// The namespaces the region was assigned in, outermost first.
+    namespaces: Vec<String>,
     /// The columns involved in this region.
     columns: HashSet<Column<Any>>,
     /// The rows that this region starts and ends on, if known.
@@ -252,6 +359,8 @@ struct Region {
     /// The cells assigned in this region. We store this as a `HashMap` with count
     /// so that if any cells are double-assigned, they will be visibly darker.
     cells: HashMap<(Column<Any>, usize), usize>,
+    /// The annotations of the cells assigned in this region.
+    annotations: HashMap<(Column<Any>, usize), String>,
 }
 
 impl Region {
@@ -277,6 +386,10 @@ impl Region {
         self.cells.insert((column, row), count + 1);
     }
 
+    fn annotate(&mut self, column: Column<Any>, row: usize, annotation: String) {
+        self.annotations.insert((column, row), annotation);
+    }
+
     fn is_assigned(&self, column: Column<Any>, row: usize) -> bool {
         self.cells.contains_key(&(column, row))
     }
@@ -373,6 +486,15 @@ impl<F: Group + Field> Mul<F> for Value<F> {
 /// are invalid: it stores all the private inputs along with the circuit internals, and
 /// then checks every constraint manually.
 ///
+/// `MockProver` reserves the same rows as the real prover: the last
+/// `blinding_factors + 1` rows of every advice column hold random blinding values in a
+/// real proof, so they are poisoned here (except in unblinded advice columns, where
+/// they are zero). Assigning to them (or enabling selectors,
+/// copying cells or reading instance values there) fails with
+/// [`Error::NotEnoughRowsAvailable`], and any constraint whose value depends on them is
+/// reported as [`VerifyFailure::ConstraintPoisoned`]. A circuit that passes `MockProver`
+/// therefore fits in the rows that the real prover makes available.
+///
 /// # Examples
 ///
 /// ```
@@ -469,15 +591,33 @@ impl<F: Group + Field> Mul<F> for Value<F> {
 ///             (((Any::Advice, 1).into(), 0).into(), "0x4".to_string()),
 ///             (((Any::Advice, 2).into(), 0).into(), "0x8".to_string()),
 ///         ],
+///         cell_annotations: vec![
+///             (((Any::Advice, 0).into(), 0).into(), "a".to_string()),
+///             (((Any::Advice, 1).into(), 0).into(), "b".to_string()),
+///             (((Any::Advice, 2).into(), 0).into(), "c".to_string()),
+///         ],
+///         // The selector is a fixed column once the circuit is synthesized.
+///         expression: "\
+/// - F0@0 * (A0@0 * A1@0 + A2@0) = 0x10
+///   - F0@0 = 1
+///   - A0@0 * A1@0 + A2@0 = 0x10
+///     - A0@0 * A1@0 = 0x8
+///       - A0@0 = 0x2
+///       - A1@0 = 0x4
+///     - A2@0 = 0x8
+/// "
+///         .to_string(),
 ///     }])
 /// );
 ///
-/// // If we provide a too-small K, we get an error.
+/// // If we provide a too-small K, we get an error that tells us the smallest K that
+/// // the circuit fits in.
 /// assert!(matches!(
 ///     MockProver::<Fp>::run(2, &circuit, vec![]).unwrap_err(),
 ///     Error::NotEnoughRowsAvailable {
 ///         current_k,
-///     } if current_k == 2,
+///         minimum_k,
+///     } if current_k == 2 && minimum_k == 3,
 /// ));
 /// ```
 #[derive(Debug)]
@@ -491,6 +631,8 @@ pub struct MockProver<F: Group + Field> {
     /// The current region being assigned to. Will be `None` after the circuit has been
     /// synthesized.
     current_region: Option<Region>,
+    /// The namespaces we are in, outermost first.
+    namespaces: Vec<String>,
 
     // The fixed cells in the circuit, arranged as [column][row].
     fixed: Vec<Vec<CellValue<F>>>,
@@ -502,6 +644,9 @@ pub struct MockProver<F: Group + Field> {
     selectors: Vec<Vec<bool>>,
 
     permutation: permutation::keygen::Assembly,
+    // The annotations of the cells asserted to be equal to the cells they are
+    // copied to.
+    assertions: BTreeMap<(Column<Any>, usize), String>,
 
     // A range of available rows for assignment and copies.
     usable_rows: Range<usize>,
@@ -516,10 +661,12 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         assert!(self.current_region.is_none());
         self.current_region = Some(Region {
             name: name().into(),
+            namespaces: self.namespaces.clone(),
             columns: HashSet::default(),
             rows: None,
             enabled_selectors: HashMap::default(),
             cells: HashMap::default(),
+            annotations: HashMap::default(),
         });
     }
 
@@ -565,7 +712,7 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
 
     fn assign_advice<V, VR, A, AR>(
         &mut self,
-        _: A,
+        annotation: A,
         column: Column<Advice>,
         row: usize,
         to: V,
@@ -583,6 +730,7 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         if let Some(region) = self.current_region.as_mut() {
             region.update_extent(column.into(), row);
             region.track_cell(column.into(), row);
+            region.annotate(column.into(), row, annotation().into());
         }
 
         *self
@@ -596,7 +744,7 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
 
     fn assign_fixed<V, VR, A, AR>(
         &mut self,
-        _: A,
+        annotation: A,
         column: Column<Fixed>,
         row: usize,
         to: V,
@@ -614,6 +762,7 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         if let Some(region) = self.current_region.as_mut() {
             region.update_extent(column.into(), row);
             region.track_cell(column.into(), row);
+            region.annotate(column.into(), row, annotation().into());
         }
 
         *self
@@ -657,16 +806,30 @@ impl<F: Field + Group> Assignment<F> for MockProver<F> {
         Ok(())
     }
 
-    fn push_namespace<NR, N>(&mut self, _: N)
+    fn record_assertion<A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Any>,
+        row: usize,
+    ) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.assertions.insert((column, row), annotation().into());
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
     where
         NR: Into<String>,
         N: FnOnce() -> NR,
     {
-        // TODO: Do something with namespaces :)
+        self.namespaces.push(name_fn().into());
     }
 
     fn pop_namespace(&mut self, _: Option<String>) {
-        // TODO: Do something with namespaces :)
+        self.namespaces.pop();
     }
 }
 
@@ -678,6 +841,14 @@ impl<F: FieldExt> MockProver<F> {
         circuit: &ConcreteCircuit,
         instance: Vec<Vec<F>>,
     ) -> Result<Self, Error> {
+        // Rotations are applied to row indices as `i32`s.
+        let max_k = max_k::<F>().min(30);
+        if k > max_k {
+            return Err(Error::TooManyRows {
+                current_k: k,
+                max_k,
+            });
+        }
         let n = 1 << k;
 
         let mut cs = ConstraintSystem::default();
@@ -685,7 +856,7 @@ impl<F: FieldExt> MockProver<F> {
         let cs = cs;
 
         if n < cs.minimum_rows() {
-            return Err(Error::not_enough_rows_available(k));
+            return Err(Error::not_enough_rows_available(k).with_minimum_k(circuit));
         }
 
         if instance.len() != cs.num_instance_columns {
@@ -705,22 +876,30 @@ impl<F: FieldExt> MockProver<F> {
             .collect::<Result<Vec<_>, _>>()?;
 
         // Fixed columns contain no blinding factors.
-        let fixed = vec![vec![CellValue::Unassigned; n]; cs.num_fixed_columns];
+        let mut fixed = vec![vec![CellValue::Unassigned; n]; cs.num_fixed_columns];
+        cs.assign_keygen_tables(k, |column, row, value| {
+            fixed[column.index()][row] = CellValue::Assigned(value);
+        })
+        .map_err(|e| e.with_minimum_k(circuit))?;
         let selectors = vec![vec![false; n]; cs.num_selectors];
-        // Advice columns contain blinding factors.
-        let blinding_factors = cs.blinding_factors();
-        let usable_rows = n - (blinding_factors + 1);
-        let advice = vec![
-            {
+        // Advice columns contain blinding factors, unless they are unblinded.
+        let usable_rows = cs.usable_rows(k);
+        let advice = (0..cs.num_advice_columns)
+            .map(|index| {
                 let mut column = vec![CellValue::Unassigned; n];
-                // Poison unusable rows.
-                for (i, cell) in column.iter_mut().enumerate().skip(usable_rows) {
-                    *cell = CellValue::Poison(i);
+                // Poison unusable rows of blinded columns.
+                if !cs
+                    .unblinded_advice_columns
+                    .iter()
+                    .any(|column| column.index() == index)
+                {
+                    for (i, cell) in column.iter_mut().enumerate().skip(usable_rows.end) {
+                        *cell = CellValue::Poison(i);
+                    }
                 }
                 column
-            };
-            cs.num_advice_columns
-        ];
+            })
+            .collect();
         let permutation = permutation::keygen::Assembly::new(n, &cs.permutation);
         let constants = cs.constants.clone();
 
@@ -730,15 +909,18 @@ impl<F: FieldExt> MockProver<F> {
             cs,
             regions: vec![],
             current_region: None,
+            namespaces: vec![],
             fixed,
             advice,
             instance,
             selectors,
             permutation,
-            usable_rows: 0..usable_rows,
+            assertions: BTreeMap::default(),
+            usable_rows,
         };
 
-        ConcreteCircuit::FloorPlanner::synthesize(&mut prover, circuit, config, constants)?;
+        ConcreteCircuit::FloorPlanner::synthesize(&mut prover, circuit, config, constants)
+            .map_err(|e| e.with_minimum_k(circuit))?;
 
         let (cs, selector_polys) = prover.cs.compress_selectors(prover.selectors.clone());
         prover.cs = cs;
@@ -753,6 +935,12 @@ impl<F: FieldExt> MockProver<F> {
         Ok(prover)
     }
 
+    /// Returns the rows that the circuit can assign to. The rows after these are
+    /// reserved for blinding factors.
+    pub fn usable_rows(&self) -> &Range<usize> {
+        &self.usable_rows
+    }
+
     /// Returns `Ok(())` if this `MockProver` is satisfied, or a list of errors indicating
     /// the reasons that the circuit is not satisfied.
     pub fn verify(&self) -> Result<(), Vec<VerifyFailure>> {
@@ -814,7 +1002,7 @@ impl<F: FieldExt> MockProver<F> {
                                 } else {
                                     Some(VerifyFailure::CellNotAssigned {
                                         gate: (gate_index, gate.name()).into(),
-                                        region: (r_i, r.name.clone()).into(),
+                                        region: (r_i, r.name.clone(), r.namespaces.clone()).into(),
                                         column: cell.column,
                                         offset: cell_row as isize - r.rows.unwrap().0 as isize,
                                     })
@@ -906,6 +1094,27 @@ impl<F: FieldExt> MockProver<F> {
                                             &self.instance,
                                         ),
                                     ),
+                                    cell_annotations: util::cell_annotations(
+                                        gate,
+                                        poly,
+                                        |column, rotation| {
+                                            let cell_row = ((row + n + rotation.0) % n) as usize;
+                                            self.regions.iter().find_map(|region| {
+                                                region.annotations.get(&(column, cell_row)).cloned()
+                                            })
+                                        },
+                                    ),
+                                    expression: util::expression_tree(
+                                        poly,
+                                        &load(n, row, &self.cs.fixed_queries, &self.fixed),
+                                        &load(n, row, &self.cs.advice_queries, &self.advice),
+                                        &load_instance(
+                                            n,
+                                            row,
+                                            &self.cs.instance_queries,
+                                            &self.instance,
+                                        ),
+                                    ),
                                 }),
                                 Value::Poison => Some(VerifyFailure::ConstraintPoisoned {
                                     constraint: (
@@ -990,6 +1199,13 @@ impl<F: FieldExt> MockProver<F> {
                             if lookup_passes {
                                 None
                             } else {
+                                let nearest_entries = table
+                                    .range(..inputs.clone())
+                                    .next_back()
+                                    .into_iter()
+                                    .chain(table.range(inputs.clone()..).next())
+                                    .map(|entry| util::format_values(entry))
+                                    .collect();
                                 Some(VerifyFailure::Lookup {
                                     name: lookup.name,
                                     lookup_index,
@@ -999,26 +1215,160 @@ impl<F: FieldExt> MockProver<F> {
                                         input_row,
                                         lookup.input_expressions.iter(),
                                     ),
+                                    row: input_row,
+                                    input: util::format_values(&inputs),
+                                    nearest_entries,
                                 })
                             }
                         })
                 });
 
+        // Check that the shuffle inputs are a permutation of the shuffle expressions.
+        // Unlike lookups, this is a property of all usable rows at once, so it is not
+        // restricted to `lookup_input_row_ids`.
+        let shuffle_errors =
+            self.cs
+                .shuffles
+                .iter()
+                .enumerate()
+                .filter_map(|(shuffle_index, shuffle)| {
+                    let load = |expression: &Expression<F>, row| {
+                        expression.evaluate_lazy(
+                            &|scalar| Value::Real(scalar),
+                            &|_| panic!("virtual selectors are removed during optimization"),
+                            &|index, _, _| {
+                                let query = self.cs.fixed_queries[index];
+                                let column_index = query.0.index();
+                                let rotation = query.1 .0;
+                                self.fixed[column_index]
+                                    [(row as i32 + n + rotation) as usize % n as usize]
+                                    .into()
+                            },
+                            &|index, _, _| {
+                                let query = self.cs.advice_queries[index];
+                                let column_index = query.0.index();
+                                let rotation = query.1 .0;
+                                self.advice[column_index]
+                                    [(row as i32 + n + rotation) as usize % n as usize]
+                                    .into()
+                            },
+                            &|index, _, _| {
+                                let query = self.cs.instance_queries[index];
+                                let column_index = query.0.index();
+                                let rotation = query.1 .0;
+                                Value::Real(
+                                    self.instance[column_index]
+                                        [(row as i32 + n + rotation) as usize % n as usize],
+                                )
+                            },
+                            &|a| -a,
+                            &|a, b| a + b,
+                            &|a, b| a * b,
+                            &|a, scalar| a * scalar,
+                            &Value::Real(F::zero()),
+                        )
+                    };
+
+                    // In the real prover, the shuffle expressions are never enforced on
+                    // unusable rows, due to the (1 - (l_last(X) + l_blind(X))) term.
+                    let mut shuffled = self.usable_rows.clone().fold(
+                        std::collections::BTreeMap::<Vec<_>, usize>::new(),
+                        |mut acc, shuffle_row| {
+                            let values = shuffle
+                                .shuffle_expressions
+                                .iter()
+                                .map(|c| load(c, shuffle_row))
+                                .collect::<Vec<_>>();
+                            *acc.entry(values).or_insert(0) += 1;
+                            acc
+                        },
+                    );
+                    self.usable_rows.clone().find_map(|input_row| {
+                        let inputs: Vec<_> = shuffle
+                            .input_expressions
+                            .iter()
+                            .map(|c| load(c, input_row))
+                            .collect();
+                        match shuffled.get_mut(&inputs) {
+                            Some(count) if *count > 0 => {
+                                *count -= 1;
+                                None
+                            }
+                            _ => Some(VerifyFailure::Shuffle {
+                                name: shuffle.name,
+                                shuffle_index,
+                                location: FailureLocation::find_expressions(
+                                    &self.cs,
+                                    &self.regions,
+                                    input_row,
+                                    shuffle.input_expressions.iter(),
+                                ),
+                            }),
+                        }
+                    })
+                });
+
+        // Original values of columns involved in the permutation.
+        let permutation_columns = self.cs.permutation.get_columns();
+        let original = |column: usize, row: usize| {
+            let c = permutation_columns[column];
+            match c.column_type() {
+                Any::Advice => self.advice[c.index()][row],
+                Any::Fixed => self.fixed[c.index()][row],
+                Any::Instance => CellValue::Assigned(self.instance[c.index()][row]),
+            }
+        };
+        // The other cells that are copied to the cell, directly or not.
+        let cycle = |column: usize, row: usize| {
+            let mapping = &self.permutation.mapping;
+            iter::successors(Some(mapping[column][row]), move |&(c, r)| {
+                Some(mapping[c][r])
+            })
+            .take_while(move |&cell| cell != (column, row))
+        };
+        let format = |value| match value {
+            CellValue::Unassigned => util::format_value(F::zero()),
+            CellValue::Assigned(v) => util::format_value(v),
+            CellValue::Poison(_) => "poison".to_string(),
+        };
+
+        // Check that asserted cells have the same value as the cells they are copied
+        // to.
+        let failed_assertions: Vec<_> = self
+            .assertions
+            .iter()
+            .filter_map(|(&(column, row), annotation)| {
+                let index = permutation_columns.iter().position(|c| *c == column)?;
+                let value = original(index, row);
+                cycle(index, row)
+                    .map(|(c, r)| original(c, r))
+                    .find(|expected| *expected != value)
+                    .map(|expected| ((index, row), annotation, value, expected))
+            })
+            .collect();
+        let failed_cells: HashSet<_> = failed_assertions.iter().map(|(cell, ..)| *cell).collect();
+        let assertion_errors =
+            failed_assertions
+                .iter()
+                .map(|&((column, row), annotation, value, expected)| {
+                    let column = permutation_columns[column];
+                    VerifyFailure::Assertion {
+                        annotation: annotation.clone(),
+                        column: column.into(),
+                        location: FailureLocation::find(
+                            &self.regions,
+                            row,
+                            Some(column).into_iter().collect(),
+                        ),
+                        value: format(value),
+                        expected: format(expected),
+                    }
+                });
+
         // Check that permutations preserve the original values of the cells.
         let perm_errors = {
-            // Original values of columns involved in the permutation.
-            let original = |column, row| {
-                self.cs
-                    .permutation
-                    .get_columns()
-                    .get(column)
-                    .map(|c: &Column<Any>| match c.column_type() {
-                        Any::Advice => self.advice[c.index()][row],
-                        Any::Fixed => self.fixed[c.index()][row],
-                        Any::Instance => CellValue::Assigned(self.instance[c.index()][row]),
-                    })
-                    .unwrap()
-            };
+            let permutation_columns = &permutation_columns;
+            let failed_cells = &failed_cells;
 
             // Iterate over each column of the permutation
             self.permutation
@@ -1033,10 +1383,15 @@ impl<F: FieldExt> MockProver<F> {
                         let permuted_cell = original(cell.0, cell.1);
                         if original_cell == permuted_cell {
                             None
+                        } else if iter::once((column, row))
+                            .chain(cycle(column, row))
+                            .any(|cell| failed_cells.contains(&cell))
+                        {
+                            // This is reported as a failed assertion.
+                            None
                         } else {
                             Some(VerifyFailure::Permutation {
-                                column: (*self.cs.permutation.get_columns().get(column).unwrap())
-                                    .into(),
+                                column: permutation_columns[column].into(),
                                 row,
                             })
                         }
@@ -1048,7 +1403,9 @@ impl<F: FieldExt> MockProver<F> {
             .chain(selector_errors)
             .chain(gate_errors)
             .chain(lookup_errors)
+            .chain(shuffle_errors)
             .chain(perm_errors)
+            .chain(assertion_errors)
             .collect();
         if errors.is_empty() {
             Ok(())
@@ -1072,7 +1429,7 @@ impl<F: FieldExt> MockProver<F> {
 mod tests {
     use pairing::bn256::Fr as Fp;
 
-    use super::{FailureLocation, MockProver, VerifyFailure};
+    use super::{util::format_value, FailureLocation, MockProver, VerifyFailure};
     use crate::{
         circuit::{Layouter, SimpleFloorPlanner},
         plonk::{
@@ -1154,6 +1511,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn namespaced_region() {
+        const K: u32 = 4;
+
+        struct NamespacedCircuit {}
+
+        impl Circuit<Fp> for NamespacedCircuit {
+            type Config = (Column<Advice>, Selector);
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Is one", |cells| {
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a - Expression::Constant(Fp::one()))]
+                });
+
+                (a, q)
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {}
+            }
+
+            fn synthesize(
+                &self,
+                (a, q): Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter
+                    .namespace(|| "chip")
+                    .namespace(|| "sub-gadget")
+                    .assign_region(
+                        || "one",
+                        |mut region| {
+                            q.enable(&mut region, 0)?;
+                            region.assign_advice(|| "a", a, 0, || Ok(Fp::zero()))?;
+                            Ok(())
+                        },
+                    )
+            }
+        }
+
+        let prover = MockProver::run(K, &NamespacedCircuit {}, vec![]).unwrap();
+        let failures = prover.verify().unwrap_err();
+        let region: super::metadata::Region = (
+            0,
+            "one".to_owned(),
+            vec!["chip".to_owned(), "sub-gadget".to_owned()],
+        )
+            .into();
+        assert_eq!(
+            region.to_string(),
+            "Region 0 ('one') (in chip > sub-gadget)"
+        );
+        assert!(matches!(
+            &failures[..],
+            [VerifyFailure::ConstraintNotSatisfied {
+                location: FailureLocation::InRegion { region: failed, offset: 0 },
+                ..
+            }] if *failed == region
+        ));
+    }
+
     #[test]
     fn bad_lookup() {
         const K: u32 = 4;
@@ -1258,8 +1683,292 @@ mod tests {
                 location: FailureLocation::InRegion {
                     region: (2, "Faulty synthesis").into(),
                     offset: 1,
-                }
+                },
+                row: 3,
+                input: vec!["0x5".to_string()],
+                nearest_entries: vec![vec!["0x4".to_string()], vec!["0x6".to_string()]],
             }])
         );
     }
+
+    #[test]
+    fn too_many_rows() {
+        struct EmptyCircuit;
+
+        impl Circuit<Fp> for EmptyCircuit {
+            type Config = ();
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn without_witnesses(&self) -> Self {
+                EmptyCircuit
+            }
+
+            fn configure(_: &mut ConstraintSystem<Fp>) -> Self::Config {}
+
+            fn synthesize(&self, _: Self::Config, _: impl Layouter<Fp>) -> Result<(), Error> {
+                Ok(())
+            }
+        }
+
+        assert!(matches!(
+            MockProver::run(31, &EmptyCircuit, vec![]),
+            Err(Error::TooManyRows {
+                current_k: 31,
+                max_k: 28,
+            })
+        ));
+    }
+
+    #[test]
+    fn blinding_rows() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct RunningConfig {
+            a: Column<Advice>,
+            q: Selector,
+        }
+
+        /// Enables `a' = a` at `row`, optionally assigning `a'`.
+        struct RunningCircuit {
+            row: usize,
+            assign_next: bool,
+        }
+
+        impl Circuit<Fp> for RunningCircuit {
+            type Config = RunningConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let q = meta.selector();
+
+                meta.create_gate("Constant", |cells| {
+                    let a_next = cells.query_advice(a, Rotation::next());
+                    let a = cells.query_advice(a, Rotation::cur());
+                    let q = cells.query_selector(q);
+
+                    vec![q * (a_next - a)]
+                });
+
+                RunningConfig { a, q }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    row: self.row,
+                    assign_next: self.assign_next,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Running",
+                    |mut region| {
+                        config.q.enable(&mut region, self.row)?;
+                        region.assign_advice(|| "a", config.a, self.row, || Ok(Fp::one()))?;
+                        if self.assign_next {
+                            region.assign_advice(
+                                || "a'",
+                                config.a,
+                                self.row + 1,
+                                || Ok(Fp::one()),
+                            )?;
+                        }
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        // Two queries of `a` need 5 blinding factors, plus the row for l_last.
+        let circuit = RunningCircuit {
+            row: 0,
+            assign_next: true,
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert_eq!(prover.usable_rows(), &(0..10));
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Assigning to the first blinding row fails, as it would in the real prover.
+        let circuit = RunningCircuit {
+            row: 9,
+            assign_next: true,
+        };
+        assert!(matches!(
+            MockProver::run(K, &circuit, vec![]),
+            Err(Error::NotEnoughRowsAvailable {
+                current_k: K,
+                minimum_k: 5,
+            })
+        ));
+
+        // Reading it from the last usable row depends on a blinding factor.
+        let circuit = RunningCircuit {
+            row: 9,
+            assign_next: false,
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert!(prover
+            .verify()
+            .unwrap_err()
+            .iter()
+            .any(|failure| matches!(failure, VerifyFailure::ConstraintPoisoned { .. })));
+    }
+
+    #[test]
+    fn failed_assertions() {
+        const K: u32 = 4;
+
+        #[derive(Clone)]
+        struct AssertionConfig {
+            a: Column<Advice>,
+        }
+
+        /// Asserts that `a` is one, and that `b` equals `c`.
+        struct AssertionCircuit {
+            a: Fp,
+            b: Fp,
+            c: Fp,
+        }
+
+        impl Circuit<Fp> for AssertionCircuit {
+            type Config = AssertionConfig;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                let a = meta.advice_column();
+                let constant = meta.fixed_column();
+                meta.enable_equality(a);
+                meta.enable_constant(constant);
+
+                AssertionConfig { a }
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self {
+                    a: self.a,
+                    b: self.b,
+                    c: self.c,
+                }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Assertions",
+                    |mut region| {
+                        let a = region.assign_advice(|| "a", config.a, 0, || Ok(self.a))?;
+                        region.assert_equal_constant(|| "a is one", a.cell(), Fp::one())?;
+
+                        let b = region.assign_advice(|| "b", config.a, 1, || Ok(self.b))?;
+                        let c = region.assign_advice(|| "c", config.a, 2, || Ok(self.c))?;
+                        region.assert_equal(|| "b equals c", b.cell(), c.cell())
+                    },
+                )
+            }
+        }
+
+        let circuit = AssertionCircuit {
+            a: Fp::one(),
+            b: Fp::from(3),
+            c: Fp::from(3),
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        // Violated assertions are reported instead of the permutation failures.
+        let circuit = AssertionCircuit {
+            a: Fp::from(2),
+            b: Fp::from(3),
+            c: Fp::from(4),
+        };
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert_eq!(
+            prover.verify(),
+            Err(vec![
+                VerifyFailure::Assertion {
+                    annotation: "a is one".to_owned(),
+                    column: Column::new(0, Any::Advice).into(),
+                    location: FailureLocation::InRegion {
+                        region: (0, "Assertions".to_owned()).into(),
+                        offset: 0,
+                    },
+                    value: format_value(Fp::from(2)),
+                    expected: format_value(Fp::one()),
+                },
+                VerifyFailure::Assertion {
+                    annotation: "b equals c".to_owned(),
+                    column: Column::new(0, Any::Advice).into(),
+                    location: FailureLocation::InRegion {
+                        region: (0, "Assertions".to_owned()).into(),
+                        offset: 1,
+                    },
+                    value: format_value(Fp::from(3)),
+                    expected: format_value(Fp::from(4)),
+                },
+            ])
+        );
+    }
+
+    #[test]
+    fn configured_blinding_factors() {
+        const K: u32 = 4;
+
+        /// Assigns `a` at `row`, with at least 8 blinding factors.
+        struct PaddedCircuit {
+            row: usize,
+        }
+
+        impl Circuit<Fp> for PaddedCircuit {
+            type Config = Column<Advice>;
+            type FloorPlanner = SimpleFloorPlanner;
+
+            fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+                meta.set_minimum_blinding_factors(8);
+                meta.advice_column()
+            }
+
+            fn without_witnesses(&self) -> Self {
+                Self { row: self.row }
+            }
+
+            fn synthesize(
+                &self,
+                config: Self::Config,
+                mut layouter: impl Layouter<Fp>,
+            ) -> Result<(), Error> {
+                layouter.assign_region(
+                    || "Padded",
+                    |mut region| {
+                        region.assign_advice(|| "a", config, self.row, || Ok(Fp::one()))?;
+                        Ok(())
+                    },
+                )
+            }
+        }
+
+        let mut cs = ConstraintSystem::<Fp>::default();
+        PaddedCircuit::configure(&mut cs);
+        assert_eq!(cs.blinding_factors(), 8);
+        assert_eq!(cs.minimum_rows(), 11);
+        assert_eq!(cs.usable_rows(K), 0..7);
+        assert_eq!(cs.usable_rows(K - 1), 0..0);
+
+        let prover = MockProver::run(K, &PaddedCircuit { row: 6 }, vec![]).unwrap();
+        assert_eq!(prover.usable_rows(), &cs.usable_rows(K));
+        assert_eq!(prover.verify(), Ok(()));
+
+        assert!(matches!(
+            MockProver::run(K, &PaddedCircuit { row: 7 }, vec![]),
+            Err(Error::NotEnoughRowsAvailable { current_k: K, .. })
+        ));
+    }
 }
@@ -2,22 +2,35 @@
 
 use std::{
     collections::{HashMap, HashSet},
-    iter,
+    fmt, iter,
     marker::PhantomData,
     ops::{Add, Mul},
+    time::{Duration, Instant},
 };
 
 use ff::{Field, PrimeField};
-use group::prime::PrimeGroup;
+use group::{prime::PrimeGroup, Curve, Group};
+use rand_core::OsRng;
 
 use crate::{
+    arithmetic::{best_fft, best_multiexp, CurveAffine},
     plonk::{
-        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
-        FloorPlanner, Instance, Selector,
+        Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Expression,
+        Fixed, FloorPlanner, Instance, Selector,
     },
-    poly::Rotation,
+    poly::{commitment::CommitmentScheme, domain::extended_k, EvaluationDomain, Rotation},
 };
 
+/// The number of commitments and evaluations each lookup argument adds to a proof.
+#[cfg(not(feature = "mv-lookup"))]
+const LOOKUP_COMMITMENTS: usize = 3;
+#[cfg(not(feature = "mv-lookup"))]
+const LOOKUP_EVALUATIONS: usize = 5;
+#[cfg(feature = "mv-lookup")]
+const LOOKUP_COMMITMENTS: usize = 2;
+#[cfg(feature = "mv-lookup")]
+const LOOKUP_EVALUATIONS: usize = 3;
+
 /// Measures a circuit to determine its costs, and explain what contributes to them.
 #[derive(Debug)]
 pub struct CircuitCost<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> {
@@ -27,22 +40,44 @@ pub struct CircuitCost<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> {
     max_deg: usize,
     /// Number of advice columns.
     advice_columns: usize,
+    /// Number of instance columns.
+    instance_columns: usize,
     /// Number of direct queries for each column type.
     instance_queries: usize,
     advice_queries: usize,
+    /// Number of instance queries at non-zero rotations.
+    rotated_instance_queries: usize,
     fixed_queries: usize,
+    /// Number of configuration columns.
+    configuration_columns: usize,
     /// Number of lookup arguments.
     lookups: usize,
+    /// Number of shuffle arguments.
+    shuffles: usize,
     /// Number of columns in the global permutation.
     permutation_cols: usize,
-    /// Number of distinct sets of points in the multiopening argument.
-    point_sets: usize,
+    /// Number of distinct points in the multiopening argument.
+    points: usize,
+    /// Name and required degree of each lookup argument.
+    named_lookups: Vec<(&'static str, usize)>,
+    /// Name, number of distinct queried cells, and degree of each gate.
+    named_gates: Vec<(&'static str, usize, usize)>,
+    /// Number of cells assigned in the columns enabled for constants.
+    constants: usize,
+    /// Number of rows of the columns enabled for constants that are in use.
+    constant_rows: usize,
+    /// Number of multiplications to evaluate the gates on a single row.
+    gate_muls: usize,
 
     _marker: PhantomData<(G, ConcreteCircuit)>,
 }
 
 struct Assembly {
     selectors: Vec<Vec<bool>>,
+    /// The columns enabled for constants.
+    constants_columns: Vec<Column<Fixed>>,
+    /// The cells assigned in `constants_columns`.
+    constants: HashSet<(Column<Fixed>, usize)>,
 }
 
 impl<F: Field> Assignment<F> for Assembly {
@@ -91,8 +126,8 @@ impl<F: Field> Assignment<F> for Assembly {
     fn assign_fixed<V, VR, A, AR>(
         &mut self,
         _: A,
-        _: Column<Fixed>,
-        _: usize,
+        column: Column<Fixed>,
+        row: usize,
         _: V,
     ) -> Result<(), Error>
     where
@@ -101,6 +136,10 @@ impl<F: Field> Assignment<F> for Assembly {
         A: FnOnce() -> AR,
         AR: Into<String>,
     {
+        if self.constants_columns.contains(&column) {
+            self.constants.insert((column, row));
+        }
+
         Ok(())
     }
 
@@ -140,6 +179,8 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
         let config = ConcreteCircuit::configure(&mut cs);
         let mut assembly = Assembly {
             selectors: vec![vec![false; 1 << k]; cs.num_selectors],
+            constants_columns: cs.constants.clone(),
+            constants: HashSet::new(),
         };
         ConcreteCircuit::FloorPlanner::synthesize(
             &mut assembly,
@@ -150,6 +191,35 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
         .unwrap();
         let (cs, _) = cs.compress_selectors(assembly.selectors);
 
+        CircuitCost {
+            constants: assembly.constants.len(),
+            constant_rows: assembly
+                .constants
+                .iter()
+                .map(|(_, row)| row + 1)
+                .max()
+                .unwrap_or(0),
+            ..Self::from_compressed(k, cs)
+        }
+    }
+
+    /// Measures a circuit from its configured constraint system alone, without
+    /// synthesizing it.
+    ///
+    /// Without the synthesized selector assignments, selectors cannot be combined
+    /// into shared fixed columns, so every selector is assumed to be active on every
+    /// row. The fixed commitments reported are therefore an upper bound, and no
+    /// constants are reported.
+    ///
+    /// Panics if `k` is not large enough for the circuit.
+    pub fn from_constraint_system(k: usize, cs: ConstraintSystem<G::Scalar>) -> Self {
+        let selectors = vec![vec![true; 1 << k]; cs.num_selectors];
+        let (cs, _) = cs.compress_selectors(selectors);
+
+        Self::from_compressed(k, cs)
+    }
+
+    fn from_compressed(k: usize, cs: ConstraintSystem<G::Scalar>) -> Self {
         assert!((1 << k) >= cs.minimum_rows());
 
         // Figure out how many point sets we have due to queried cells.
@@ -180,9 +250,22 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
         }
 
         // Include lookup polynomials in point sets:
-        point_sets.insert(vec![0, 1]); // product_poly
-        point_sets.insert(vec![-1, 0]); // permuted_input_poly
-        point_sets.insert(vec![0]); // permuted_table_poly
+        #[cfg(not(feature = "mv-lookup"))]
+        {
+            point_sets.insert(vec![0, 1]); // product_poly
+            point_sets.insert(vec![-1, 0]); // permuted_input_poly
+            point_sets.insert(vec![0]); // permuted_table_poly
+        }
+        #[cfg(feature = "mv-lookup")]
+        {
+            point_sets.insert(vec![0, 1]); // phi_poly
+            point_sets.insert(vec![0]); // multiplicity_poly
+        }
+
+        // Include shuffle polynomials in point sets:
+        if !cs.shuffles.is_empty() {
+            point_sets.insert(vec![0, 1]); // product_poly
+        }
 
         // Include permutation polynomials in point sets.
         point_sets.insert(vec![0, 1]); // permutation_product_poly
@@ -193,20 +276,126 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
             point_sets.insert(vec![-((cs.blinding_factors() + 1) as i32), 0, 1]);
         }
 
+        let points: HashSet<i32> = point_sets.iter().flatten().copied().collect();
+
+        let named_lookups = cs
+            .lookups
+            .iter()
+            .map(|lookup| (lookup.name, lookup.required_degree()))
+            .collect();
+        let named_gates = cs
+            .gates
+            .iter()
+            .map(|gate| {
+                let queries: HashSet<_> = gate
+                    .queried_cells()
+                    .iter()
+                    .map(|cell| (cell.column, cell.rotation.0))
+                    .collect();
+                let degree = gate
+                    .polynomials()
+                    .iter()
+                    .map(|poly| poly.degree())
+                    .max()
+                    .unwrap_or(0);
+                (gate.name(), queries.len(), degree)
+            })
+            .collect();
+        // Each constraint is also multiplied by a power of y.
+        let gate_muls = cs
+            .gates
+            .iter()
+            .flat_map(|gate| gate.polynomials())
+            .map(|poly| multiplications(poly) + 1)
+            .sum();
+
         CircuitCost {
             k,
             max_deg,
             advice_columns: cs.num_advice_columns,
+            instance_columns: cs.num_instance_columns,
             instance_queries: cs.instance_queries.len(),
             advice_queries: cs.advice_queries.len(),
+            rotated_instance_queries: cs.rotated_instance_queries().len(),
             fixed_queries: cs.fixed_queries.len(),
+            configuration_columns: cs.configuration_columns.len(),
             lookups: cs.lookups.len(),
+            shuffles: cs.shuffles.len(),
             permutation_cols,
-            point_sets: point_sets.len(),
+            points: points.len(),
+            named_lookups,
+            named_gates,
+            constants: 0,
+            constant_rows: 0,
+            gate_muls,
             _marker: PhantomData::default(),
         }
     }
 
+    /// Returns the marginal proof size contributed by each lookup argument, in
+    /// the order the lookups were configured.
+    ///
+    /// Each lookup argument commits to its permuted input, permuted table and
+    /// product polynomials, and opens them at five points in total. With the
+    /// `mv-lookup` feature, it instead commits to its multiplicity and running
+    /// sum polynomials, and opens them at three points in total.
+    pub fn lookup_costs(&self) -> Vec<ArgumentCost> {
+        self.named_lookups
+            .iter()
+            .map(|&(name, degree)| ArgumentCost {
+                kind: "lookup",
+                name,
+                commitments: LOOKUP_COMMITMENTS,
+                evaluations: LOOKUP_EVALUATIONS,
+                degree,
+            })
+            .collect()
+    }
+
+    /// Returns the marginal proof size attributable to each gate, in the order
+    /// the gates were created.
+    ///
+    /// Gates add no commitments of their own, but each cell they query must
+    /// be evaluated in the proof. Queries shared between several gates are
+    /// counted once for each gate that uses them, so the evaluations reported
+    /// here may sum to more than the circuit's total.
+    pub fn gate_costs(&self) -> Vec<ArgumentCost> {
+        self.named_gates
+            .iter()
+            .map(|&(name, queries, degree)| ArgumentCost {
+                kind: "gate",
+                name,
+                commitments: 0,
+                evaluations: queries,
+                degree,
+            })
+            .collect()
+    }
+
+    /// Returns the number of evaluations per proof that come from querying
+    /// instance columns at non-zero rotations.
+    ///
+    /// These are included in [`CircuitCost::proof_size`], but are easy to add by
+    /// accident: each one is an evaluation on top of the instance column's query at
+    /// the current row, and its rotation may add a point set to the multiopening
+    /// argument.
+    pub fn rotated_instance_queries(&self) -> usize {
+        self.rotated_instance_queries
+    }
+
+    /// Returns the number of cells that the circuit assigns in the columns enabled
+    /// for constants (see [`ConstraintSystem::enable_constant`]). With the floor
+    /// planners provided by `halo2`, this is the number of distinct constants.
+    pub fn constants(&self) -> usize {
+        self.constants
+    }
+
+    /// Returns the number of rows of the columns enabled for constants that are in
+    /// use, up to the last assigned cell of any of them.
+    pub fn constant_rows(&self) -> usize {
+        self.constant_rows
+    }
+
     fn permutation_chunks(&self) -> usize {
         let chunk_size = self.max_deg - 2;
         (self.permutation_cols + chunk_size - 1) / chunk_size
@@ -225,9 +414,17 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
             advice: ProofContribution::new(self.advice_columns, self.advice_queries),
 
             // Lookup arguments:
-            // - 3 commitments per lookup argument per instance
-            // - 5 evals per lookup argument per instance
-            lookups: ProofContribution::new(3 * self.lookups, 5 * self.lookups),
+            // - 3 commitments per lookup argument per instance (2 with `mv-lookup`)
+            // - 5 evals per lookup argument per instance (3 with `mv-lookup`)
+            lookups: ProofContribution::new(
+                LOOKUP_COMMITMENTS * self.lookups,
+                LOOKUP_EVALUATIONS * self.lookups,
+            ),
+
+            // Shuffle arguments:
+            // - 1 commitment per shuffle argument per instance
+            // - 2 evals per shuffle argument per instance
+            shuffles: ProofContribution::new(self.shuffles, 2 * self.shuffles),
 
             // Global permutation argument:
             // - chunks commitments per instance
@@ -238,22 +435,34 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
         }
     }
 
-    /// Returns the proof size for the given number of instances of this circuit.
+    /// Returns the proof size for the given number of instances of this circuit,
+    /// when proving with [`CommitmentScheme::default`].
     pub fn proof_size(&self, instances: usize) -> ProofSize<G> {
+        self.proof_size_for(instances, CommitmentScheme::default())
+    }
+
+    /// Returns the proof size for the given number of instances of this circuit,
+    /// when proving with `scheme`.
+    pub fn proof_size_for(&self, instances: usize, scheme: CommitmentScheme) -> ProofSize<G> {
         let marginal = self.marginal_proof_size();
 
         ProofSize {
             // Cells:
             // - marginal cost per instance
+            // - 1 commitment per configuration column
             // - 1 eval per fixed column query
             instance: marginal.instance * instances,
             advice: marginal.advice * instances,
-            fixed: ProofContribution::new(0, self.fixed_queries),
+            fixed: ProofContribution::new(self.configuration_columns, self.fixed_queries),
 
             // Lookup arguments:
             // - marginal cost per instance
             lookups: marginal.lookups * instances,
 
+            // Shuffle arguments:
+            // - marginal cost per instance
+            shuffles: marginal.shuffles * instances,
+
             // Global permutation argument:
             // - marginal cost per instance
             // - 1 eval per column
@@ -265,21 +474,199 @@ impl<G: PrimeGroup, ConcreteCircuit: Circuit<G::Scalar>> CircuitCost<G, Concrete
             // - 1 random_poly eval
             vanishing: ProofContribution::new(self.max_deg, 1),
 
-            // Multiopening argument:
-            // - f_commitment
-            // - 1 eval per set of points in multiopen argument
-            multiopen: ProofContribution::new(1, self.point_sets),
-
-            // Polycommit:
-            // - s_poly commitment
-            // - inner product argument (2 * k round commitments)
-            // - a
-            // - xi
-            polycomm: ProofContribution::new(1 + 2 * self.k, 2),
+            // Multiopening argument, whose witnesses are the opening proofs:
+            // - GWC: 1 witness commitment per point
+            // - SHPLONK: h commitment and the final linearisation commitment
+            multiopen: match scheme {
+                CommitmentScheme::KzgGwc => ProofContribution::new(self.points, 0),
+                CommitmentScheme::KzgShplonk => ProofContribution::new(2, 0),
+            },
 
             _marker: PhantomData::default(),
         }
     }
+
+    /// Returns an estimate of the FFTs and MSMs the prover performs to create a
+    /// proof for the given number of instances of this circuit with `scheme`.
+    ///
+    /// Work done once at key generation, such as transforming the fixed and
+    /// permutation polynomials, is not included.
+    pub fn prover_work(&self, instances: usize, scheme: CommitmentScheme) -> ProverWork {
+        let max_deg = self.max_deg as u32;
+        let k = self.k as u32;
+
+        // Each advice, instance, lookup, shuffle and permutation product polynomial
+        // is interpolated once, and evaluated once over the extended domain to
+        // compute the quotient polynomial, which is then interpolated itself.
+        let polys = instances
+            * (self.advice_columns
+                + self.instance_columns
+                + LOOKUP_COMMITMENTS * self.lookups
+                + self.shuffles
+                + self.permutation_chunks());
+
+        // Every commitment in the proof is an MSM over the full domain.
+        let msms = self.proof_size_for(instances, scheme).commitments();
+
+        let extended_fft_size = 1 << extended_k(max_deg, k);
+        ProverWork {
+            fft_size: 1 << self.k,
+            ffts: polys,
+            extended_fft_size,
+            extended_ffts: polys + 1,
+            msm_size: 1 << self.k,
+            msms,
+            field_muls: instances * self.gate_muls * extended_fft_size,
+        }
+    }
+}
+
+/// Returns the number of multiplications needed to evaluate `poly` on a row.
+fn multiplications<F: Field>(poly: &Expression<F>) -> usize {
+    poly.evaluate(
+        &|_| 0,
+        &|_| 0,
+        &|_, _, _| 0,
+        &|_, _, _| 0,
+        &|_, _, _| 0,
+        &|a| a,
+        &|a, b| a + b,
+        &|a, b| a + b + 1,
+        &|a, _| a + 1,
+    )
+}
+
+/// An estimate of the work done by the prover, as returned by
+/// [`CircuitCost::prover_work`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ProverWork {
+    /// Size of the FFTs over the circuit's domain.
+    pub fft_size: usize,
+    /// Number of FFTs over the circuit's domain.
+    pub ffts: usize,
+    /// Size of the FFTs over the extended domain used to compute the quotient
+    /// polynomial.
+    pub extended_fft_size: usize,
+    /// Number of FFTs over the extended domain.
+    pub extended_ffts: usize,
+    /// Number of bases in each multi-scalar multiplication.
+    pub msm_size: usize,
+    /// Number of multi-scalar multiplications.
+    pub msms: usize,
+    /// Number of field multiplications to evaluate the gates over the extended
+    /// domain. The lookup, shuffle and permutation arguments are not included.
+    pub field_muls: usize,
+}
+
+impl ProverWork {
+    /// Estimates the time the prover takes for this work on the host that
+    /// `calibration` was measured on.
+    pub fn estimate(&self, calibration: &Calibration) -> Duration {
+        let fft_elements = |size: usize| size * size.trailing_zeros() as usize;
+        let scale = |time: Duration, count: usize| time.mul_f64(count as f64);
+
+        scale(
+            calibration.fft_per_element,
+            self.ffts * fft_elements(self.fft_size)
+                + self.extended_ffts * fft_elements(self.extended_fft_size),
+        ) + scale(calibration.msm_per_point, self.msms * self.msm_size)
+            + scale(calibration.field_mul, self.field_muls)
+    }
+}
+
+impl fmt::Display for ProverWork {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} FFTs of size {}, {} FFTs of size {}, {} MSMs of size {}, {} field multiplications",
+            self.ffts,
+            self.fft_size,
+            self.extended_ffts,
+            self.extended_fft_size,
+            self.msms,
+            self.msm_size,
+            self.field_muls
+        )
+    }
+}
+
+/// The time the host takes for the operations counted by [`ProverWork`], as
+/// measured by [`Calibration::measure`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Calibration {
+    /// Time of a single field multiplication.
+    pub field_mul: Duration,
+    /// Time per point of a multi-scalar multiplication.
+    pub msm_per_point: Duration,
+    /// Time per element and per round of an FFT, so that an FFT of size `2^k`
+    /// takes `k * 2^k` times this.
+    pub fft_per_element: Duration,
+}
+
+impl Calibration {
+    /// Measures the operations of the scalar field and curve of `C` on the host,
+    /// with an FFT and an MSM of size `2^k`.
+    ///
+    /// The time per point of an MSM decreases as the MSM grows, so `k` should be
+    /// close to the `k` of the circuits whose work is estimated. Runs for about
+    /// as long as creating the commitment to a single column of such a circuit.
+    pub fn measure<C: CurveAffine>(k: u32) -> Self {
+        let n = 1usize << k;
+        let scalars: Vec<C::Scalar> = (0..n).map(|_| C::Scalar::random(OsRng)).collect();
+        let bases = {
+            let projective: Vec<C::Curve> = (0..n).map(|_| C::Curve::random(OsRng)).collect();
+            let mut bases = vec![C::default(); n];
+            C::Curve::batch_normalize(&projective, &mut bases);
+            bases
+        };
+
+        let start = Instant::now();
+        let product = scalars.iter().fold(C::Scalar::one(), |acc, s| acc * s);
+        let field_mul = start.elapsed() / n as u32;
+        // Use the product, so that the multiplications aren't optimized away.
+        assert!(product != C::Scalar::zero() || scalars.contains(&C::Scalar::zero()));
+
+        let start = Instant::now();
+        best_multiexp(&scalars, &bases);
+        let msm_per_point = start.elapsed() / n as u32;
+
+        let omega = EvaluationDomain::<C::Scalar>::new(1, k).get_omega();
+        let mut values = scalars;
+        let start = Instant::now();
+        best_fft(&mut values, omega, k);
+        let fft_per_element = start.elapsed() / (n as u32 * k.max(1));
+
+        Calibration {
+            field_mul,
+            msm_per_point,
+            fft_per_element,
+        }
+    }
+}
+
+/// The per-proof cost of a single named lookup argument or gate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArgumentCost {
+    /// The kind of argument: `"lookup"` or `"gate"`.
+    pub kind: &'static str,
+    /// The name given to the argument when it was configured.
+    pub name: &'static str,
+    /// Number of commitments this argument adds to each proof.
+    pub commitments: usize,
+    /// Number of evaluations this argument adds to each proof.
+    pub evaluations: usize,
+    /// The degree of the argument's constraints.
+    pub degree: usize,
+}
+
+impl fmt::Display for ArgumentCost {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} '{}' costs {} commitments + {} evals per proof (degree {})",
+            self.kind, self.name, self.commitments, self.evaluations, self.degree
+        )
+    }
 }
 
 /// (commitments, evaluations)
@@ -330,6 +717,7 @@ pub struct MarginalProofSize<G: PrimeGroup> {
     instance: ProofContribution,
     advice: ProofContribution,
     lookups: ProofContribution,
+    shuffles: ProofContribution,
     equality: ProofContribution,
     _marker: PhantomData<G>,
 }
@@ -342,6 +730,7 @@ impl<G: PrimeGroup> From<MarginalProofSize<G>> for usize {
         proof.instance.len(point, scalar)
             + proof.advice.len(point, scalar)
             + proof.lookups.len(point, scalar)
+            + proof.shuffles.len(point, scalar)
             + proof.equality.len(point, scalar)
     }
 }
@@ -353,13 +742,38 @@ pub struct ProofSize<G: PrimeGroup> {
     advice: ProofContribution,
     fixed: ProofContribution,
     lookups: ProofContribution,
+    shuffles: ProofContribution,
     equality: ProofContribution,
     vanishing: ProofContribution,
     multiopen: ProofContribution,
-    polycomm: ProofContribution,
     _marker: PhantomData<G>,
 }
 
+impl<G: PrimeGroup> ProofSize<G> {
+    fn contributions(&self) -> [&ProofContribution; 8] {
+        [
+            &self.instance,
+            &self.advice,
+            &self.fixed,
+            &self.lookups,
+            &self.shuffles,
+            &self.equality,
+            &self.vanishing,
+            &self.multiopen,
+        ]
+    }
+
+    /// Returns the number of group elements in the proof.
+    pub fn commitments(&self) -> usize {
+        self.contributions().iter().map(|c| c.commitments).sum()
+    }
+
+    /// Returns the number of scalars in the proof.
+    pub fn evaluations(&self) -> usize {
+        self.contributions().iter().map(|c| c.evaluations).sum()
+    }
+}
+
 impl<G: PrimeGroup> From<ProofSize<G>> for usize {
     fn from(proof: ProofSize<G>) -> Self {
         let point = G::Repr::default().as_ref().len();
@@ -369,9 +783,257 @@ impl<G: PrimeGroup> From<ProofSize<G>> for usize {
             + proof.advice.len(point, scalar)
             + proof.fixed.len(point, scalar)
             + proof.lookups.len(point, scalar)
+            + proof.shuffles.len(point, scalar)
             + proof.equality.len(point, scalar)
             + proof.vanishing.len(point, scalar)
             + proof.multiopen.len(point, scalar)
-            + proof.polycomm.len(point, scalar)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use ff::Field;
+    use pairing::bn256::{Fr as Fp, G1Affine, G1};
+
+    use super::{
+        ArgumentCost, Calibration, CircuitCost, CommitmentScheme, LOOKUP_COMMITMENTS,
+        LOOKUP_EVALUATIONS,
+    };
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Expression, Selector},
+        poly::Rotation,
+    };
+
+    #[derive(Clone)]
+    struct BooleanConfig {
+        a: Column<Advice>,
+        q: Selector,
+    }
+
+    struct BooleanCircuit;
+
+    impl Circuit<Fp> for BooleanCircuit {
+        type Config = BooleanConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let q = meta.selector();
+            meta.boolean_gate(q, a);
+            BooleanConfig { a, q }
+        }
+
+        fn without_witnesses(&self) -> Self {
+            BooleanCircuit
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "bit",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Ok(Fp::one()))
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn commitment_schemes() {
+        const K: usize = 5;
+
+        let cost = CircuitCost::<G1, BooleanCircuit>::measure(K, &BooleanCircuit);
+        let gwc = cost.proof_size_for(1, CommitmentScheme::KzgGwc);
+        let shplonk = cost.proof_size_for(1, CommitmentScheme::KzgShplonk);
+
+        // GWC opens with a witness per point, and SHPLONK with two commitments
+        // however many points there are. Neither adds evaluations.
+        assert_eq!(gwc.commitments() - cost.points, shplonk.commitments() - 2);
+        assert_eq!(shplonk.evaluations(), gwc.evaluations());
+        assert_eq!(
+            usize::from(cost.proof_size(1)),
+            usize::from(cost.proof_size_for(1, CommitmentScheme::default()))
+        );
+
+        let work = cost.prover_work(1, CommitmentScheme::KzgShplonk);
+        assert_eq!(work.fft_size, 1 << K);
+        assert_eq!(work.msm_size, 1 << K);
+        assert_eq!(work.extended_ffts, work.ffts + 1);
+        assert_eq!(
+            work.msms,
+            cost.proof_size_for(1, CommitmentScheme::KzgShplonk)
+                .commitments()
+        );
+    }
+
+    #[test]
+    fn stats_match_proof_size() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        BooleanCircuit::configure(&mut cs);
+        let stats = cs.stats();
+        assert_eq!(stats.advice_columns, 1);
+        assert_eq!(stats.selectors, 1);
+        assert_eq!(stats.gates, 1);
+        assert_eq!(stats.constraints, 1);
+        assert_eq!(stats.degree, cs.degree());
+
+        // SHPLONK adds two commitments of its own.
+        let cost = CircuitCost::<G1, BooleanCircuit>::measure(5, &BooleanCircuit);
+        assert_eq!(
+            stats.commitments,
+            cost.proof_size_for(1, CommitmentScheme::KzgShplonk)
+                .commitments()
+                - 2
+        );
+    }
+
+    #[test]
+    fn calibrated_estimate() {
+        const K: usize = 5;
+
+        let cost = CircuitCost::<G1, BooleanCircuit>::measure(K, &BooleanCircuit);
+        let work = cost.prover_work(1, CommitmentScheme::KzgShplonk);
+        assert!(work.field_muls > 0);
+        assert_eq!(
+            cost.prover_work(2, CommitmentScheme::KzgShplonk).field_muls,
+            2 * work.field_muls
+        );
+
+        let free = Calibration {
+            field_mul: Duration::default(),
+            msm_per_point: Duration::default(),
+            fft_per_element: Duration::default(),
+        };
+        assert_eq!(work.estimate(&free), Duration::default());
+
+        let calibration = Calibration::measure::<G1Affine>(K as u32);
+        assert!(
+            work.estimate(&calibration)
+                >= calibration
+                    .msm_per_point
+                    .mul_f64((work.msms * work.msm_size) as f64)
+        );
+    }
+
+    #[test]
+    fn rotated_instance_queries() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        let i = cs.instance_column();
+        cs.create_gate("running sum", |meta| {
+            let a = meta.query_advice(a, Rotation::cur());
+            let i_cur = meta.query_instance(i, Rotation::cur());
+            let i_next = meta.query_instance(i, Rotation::next());
+            Some(i_next - (i_cur + a))
+        });
+        assert_eq!(cs.rotated_instance_queries(), vec![(i, Rotation::next())]);
+
+        let cost = CircuitCost::<G1, BooleanCircuit>::from_constraint_system(5, cs);
+        assert_eq!(cost.rotated_instance_queries(), 1);
+        assert_eq!(cost.instance_queries, 2);
+    }
+
+    #[test]
+    fn argument_costs() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        let b = cs.advice_column();
+        let table = cs.fixed_column();
+        let q = cs.selector();
+        let q_range = cs.complex_selector();
+        cs.create_gate("mul", |meta| {
+            let q = meta.query_selector(q);
+            let a_cur = meta.query_advice(a, Rotation::cur());
+            let a_next = meta.query_advice(a, Rotation::next());
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * (a_cur * a_next - b)]
+        });
+        cs.create_gate("bool", |meta| {
+            let q = meta.query_selector(q);
+            let b = meta.query_advice(b, Rotation::cur());
+            vec![q * b.clone() * (Expression::Constant(Fp::one()) - b)]
+        });
+        cs.lookup_any("range", |meta| {
+            let q = meta.query_selector(q_range);
+            let a = meta.query_advice(a, Rotation::cur());
+            let table = meta.query_fixed(table, Rotation::cur());
+            vec![(q * a, table)]
+        });
+        cs.lookup_any("unselected", |meta| {
+            let b = meta.query_advice(b, Rotation::cur());
+            let table = meta.query_fixed(table, Rotation::cur());
+            vec![(b, table)]
+        });
+
+        let cost = CircuitCost::<G1, BooleanCircuit>::from_constraint_system(5, cs);
+
+        // `b` is queried by both gates, and counted for each of them.
+        assert_eq!(
+            cost.gate_costs(),
+            vec![
+                ArgumentCost {
+                    kind: "gate",
+                    name: "mul",
+                    commitments: 0,
+                    evaluations: 3,
+                    degree: 3,
+                },
+                ArgumentCost {
+                    kind: "gate",
+                    name: "bool",
+                    commitments: 0,
+                    evaluations: 1,
+                    degree: 3,
+                },
+            ]
+        );
+
+        // A lookup has degree 2 + input degree + table degree, and at least 4.
+        assert_eq!(
+            cost.lookup_costs(),
+            vec![
+                ArgumentCost {
+                    kind: "lookup",
+                    name: "range",
+                    commitments: LOOKUP_COMMITMENTS,
+                    evaluations: LOOKUP_EVALUATIONS,
+                    degree: 5,
+                },
+                ArgumentCost {
+                    kind: "lookup",
+                    name: "unselected",
+                    commitments: LOOKUP_COMMITMENTS,
+                    evaluations: LOOKUP_EVALUATIONS,
+                    degree: 4,
+                },
+            ]
+        );
+        assert_eq!(
+            cost.lookup_costs()[0].to_string(),
+            format!(
+                "lookup 'range' costs {} commitments + {} evals per proof (degree 5)",
+                LOOKUP_COMMITMENTS, LOOKUP_EVALUATIONS
+            )
+        );
+    }
+
+    #[test]
+    fn from_constraint_system_matches_single_selector() {
+        let mut cs = ConstraintSystem::default();
+        BooleanCircuit::configure(&mut cs);
+
+        let measured = CircuitCost::<G1, BooleanCircuit>::measure(5, &BooleanCircuit);
+        let configured = CircuitCost::<G1, BooleanCircuit>::from_constraint_system(5, cs);
+        assert_eq!(
+            usize::from(measured.proof_size(1)),
+            usize::from(configured.proof_size(1))
+        );
     }
 }
@@ -0,0 +1,388 @@
+use std::{
+    collections::BTreeSet,
+    fmt::{self, Write},
+};
+
+use pairing::arithmetic::FieldExt;
+
+use crate::plonk::{Circuit, ConstraintSystem, Expression};
+
+#[derive(Debug)]
+struct ExportedConstraint {
+    gate: &'static str,
+    name: &'static str,
+    smtlib: String,
+    polynomial: String,
+}
+
+/// A struct for exporting the custom gates of a circuit to external formal tools.
+///
+/// Each constraint polynomial of each gate is exported over the circuit's scalar
+/// field, with one variable per queried cell relative to an arbitrary row:
+///
+/// - `A{column}_{rotation}`, `F{column}_{rotation}` and `I{column}_{rotation}` for
+///   advice, fixed and instance queries, where negative rotations are written as
+///   `m{rotation}` (so `A0_m1` is advice column 0 at `Rotation::prev()`);
+/// - `S{index}` for selectors.
+///
+/// Lookup and permutation arguments are not exported.
+///
+/// The constraints can be rendered as an SMT-LIB script in the `QF_FF` logic (as
+/// supported by e.g. cvc5) or as a plain polynomial system, and two exports can be
+/// combined into an SMT-LIB query checking whether one circuit's gates imply the
+/// other's.
+///
+/// # Examples
+///
+/// ```
+/// use ff::Field;
+/// use halo2_proofs::{
+///     circuit::{Layouter, SimpleFloorPlanner},
+///     dev::CircuitConstraints,
+///     plonk::{Circuit, ConstraintSystem, Error},
+///     poly::Rotation,
+/// };
+/// use pairing::bn256::Fr;
+///
+/// #[derive(Copy, Clone)]
+/// struct MyConfig {}
+///
+/// #[derive(Clone, Default)]
+/// struct MyCircuit {}
+///
+/// impl<F: Field> Circuit<F> for MyCircuit {
+///     type Config = MyConfig;
+///     type FloorPlanner = SimpleFloorPlanner;
+///
+///     fn without_witnesses(&self) -> Self {
+///         Self::default()
+///     }
+///
+///     fn configure(meta: &mut ConstraintSystem<F>) -> MyConfig {
+///         let a = meta.advice_column();
+///         let b = meta.advice_column();
+///         let c = meta.advice_column();
+///         let s = meta.selector();
+///
+///         meta.create_gate("R1CS constraint", |meta| {
+///             let a = meta.query_advice(a, Rotation::cur());
+///             let b = meta.query_advice(b, Rotation::cur());
+///             let c = meta.query_advice(c, Rotation::prev());
+///             let s = meta.query_selector(s);
+///
+///             Some(("R1CS", s * (a * b - c)))
+///         });
+///
+///         MyConfig {}
+///     }
+///
+///     fn synthesize(&self, _: MyConfig, _: impl Layouter<F>) -> Result<(), Error> {
+///         Ok(())
+///     }
+/// }
+///
+/// let constraints = CircuitConstraints::collect::<Fr, MyCircuit>();
+/// assert_eq!(
+///     constraints.to_polynomial_system(),
+///     r#####"# Field modulus: 21888242871839275222246405745257275088548364400416034343698204186575808495617
+/// # Variables: A0_0, A1_0, A2_m1, S0
+/// # R1CS constraint/R1CS
+/// S0 * (A0_0 * A1_0 - A2_m1)
+/// "#####,
+/// );
+/// ```
+#[derive(Debug)]
+pub struct CircuitConstraints {
+    modulus: String,
+    variables: BTreeSet<String>,
+    constraints: Vec<ExportedConstraint>,
+}
+
+impl CircuitConstraints {
+    /// Collects the constraints from within the circuit.
+    pub fn collect<F: FieldExt, C: Circuit<F>>() -> Self {
+        let mut cs = ConstraintSystem::default();
+        let _ = C::configure(&mut cs);
+
+        let variables = cs
+            .gates
+            .iter()
+            .flat_map(|gate| gate.polynomials().iter())
+            .flat_map(queried_variables)
+            .collect();
+
+        let constraints = cs
+            .gates
+            .iter()
+            .flat_map(|gate| {
+                gate.polynomials()
+                    .iter()
+                    .enumerate()
+                    .map(move |(i, poly)| ExportedConstraint {
+                        gate: gate.name(),
+                        name: gate.constraint_name(i),
+                        smtlib: format_smtlib(poly),
+                        polynomial: format_polynomial(poly),
+                    })
+            })
+            .collect();
+
+        CircuitConstraints {
+            modulus: hex_to_decimal(F::MODULUS),
+            variables,
+            constraints,
+        }
+    }
+
+    /// Renders the constraints as an SMT-LIB script asserting that every
+    /// constraint polynomial evaluates to zero.
+    pub fn to_smtlib(&self) -> String {
+        let mut ret = String::new();
+        let w = &mut ret;
+        self.write_smtlib_header(w, &self.variables).unwrap();
+        for constraint in &self.constraints {
+            writeln!(w, "; {}", constraint.label()).unwrap();
+            writeln!(w, "(assert (= {} (as ff0 F)))", constraint.smtlib).unwrap();
+        }
+        writeln!(w, "(check-sat)").unwrap();
+        ret
+    }
+
+    /// Renders the constraints as a polynomial system, one polynomial per line,
+    /// each of which is constrained to equal zero.
+    pub fn to_polynomial_system(&self) -> String {
+        self.to_string()
+    }
+
+    /// Renders an SMT-LIB script that is unsatisfiable exactly when every
+    /// assignment satisfying this circuit's constraints also satisfies `other`'s.
+    ///
+    /// Running it in both directions checks that two versions of a circuit's
+    /// gates are equivalent. Variables are matched by name, so both circuits
+    /// must lay out their columns in the same order.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the two circuits are defined over different fields.
+    pub fn implication_smtlib(&self, other: &CircuitConstraints) -> String {
+        assert_eq!(self.modulus, other.modulus, "circuits use different fields");
+
+        let variables = self.variables.union(&other.variables).cloned().collect();
+
+        let mut ret = String::new();
+        let w = &mut ret;
+        self.write_smtlib_header(w, &variables).unwrap();
+        for constraint in &self.constraints {
+            writeln!(w, "; {}", constraint.label()).unwrap();
+            writeln!(w, "(assert (= {} (as ff0 F)))", constraint.smtlib).unwrap();
+        }
+        if other.constraints.is_empty() {
+            // A circuit without gates is always implied.
+            writeln!(w, "(assert false)").unwrap();
+        } else {
+            writeln!(w, "(assert (or").unwrap();
+            for constraint in &other.constraints {
+                writeln!(w, "  ; {}", constraint.label()).unwrap();
+                writeln!(w, "  (not (= {} (as ff0 F)))", constraint.smtlib).unwrap();
+            }
+            // Binary `or` keeps the script valid for a single constraint.
+            writeln!(w, "  false))").unwrap();
+        }
+        writeln!(w, "(check-sat)").unwrap();
+        ret
+    }
+
+    fn write_smtlib_header(
+        &self,
+        w: &mut impl Write,
+        variables: &BTreeSet<String>,
+    ) -> Result<(), fmt::Error> {
+        writeln!(w, "(set-logic QF_FF)")?;
+        writeln!(w, "(define-sort F () (_ FiniteField {}))", self.modulus)?;
+        for variable in variables {
+            writeln!(w, "(declare-const {} F)", variable)?;
+        }
+        Ok(())
+    }
+}
+
+impl ExportedConstraint {
+    fn label(&self) -> String {
+        if self.name.is_empty() {
+            self.gate.to_string()
+        } else {
+            format!("{}/{}", self.gate, self.name)
+        }
+    }
+}
+
+impl fmt::Display for CircuitConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> Result<(), fmt::Error> {
+        writeln!(f, "# Field modulus: {}", self.modulus)?;
+        writeln!(
+            f,
+            "# Variables: {}",
+            self.variables
+                .iter()
+                .map(|v| v.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )?;
+        for constraint in &self.constraints {
+            writeln!(f, "# {}", constraint.label())?;
+            writeln!(f, "{}", constraint.polynomial)?;
+        }
+        Ok(())
+    }
+}
+
+fn format_variable(prefix: &str, column: usize, rotation: i32) -> String {
+    if rotation < 0 {
+        format!("{}{}_m{}", prefix, column, -rotation)
+    } else {
+        format!("{}{}_{}", prefix, column, rotation)
+    }
+}
+
+fn queried_variables<F: FieldExt>(poly: &Expression<F>) -> BTreeSet<String> {
+    poly.evaluate(
+        &|_| BTreeSet::default(),
+        &|selector| vec![format!("S{}", selector.0)].into_iter().collect(),
+        &|_, column, rotation| {
+            vec![format_variable("F", column, rotation.0)]
+                .into_iter()
+                .collect()
+        },
+        &|_, column, rotation| {
+            vec![format_variable("A", column, rotation.0)]
+                .into_iter()
+                .collect()
+        },
+        &|_, column, rotation| {
+            vec![format_variable("I", column, rotation.0)]
+                .into_iter()
+                .collect()
+        },
+        &|a| a,
+        &|mut a, mut b| {
+            a.append(&mut b);
+            a
+        },
+        &|mut a, mut b| {
+            a.append(&mut b);
+            a
+        },
+        &|a, _| a,
+    )
+}
+
+fn format_smtlib<F: FieldExt>(poly: &Expression<F>) -> String {
+    let constant = |v: F| format!("(as ff{} F)", field_to_decimal(v));
+    poly.evaluate(
+        &constant,
+        &|selector| format!("S{}", selector.0),
+        &|_, column, rotation| format_variable("F", column, rotation.0),
+        &|_, column, rotation| format_variable("A", column, rotation.0),
+        &|_, column, rotation| format_variable("I", column, rotation.0),
+        &|a| format!("(ff.neg {})", a),
+        &|a, b| format!("(ff.add {} {})", a, b),
+        &|a, b| format!("(ff.mul {} {})", a, b),
+        &|a, s| format!("(ff.mul {} {})", a, constant(s)),
+    )
+}
+
+pub(crate) fn format_polynomial<F: FieldExt>(poly: &Expression<F>) -> String {
+    poly.evaluate(
+        &field_to_decimal,
+        &|selector| format!("S{}", selector.0),
+        &|_, column, rotation| format_variable("F", column, rotation.0),
+        &|_, column, rotation| format_variable("A", column, rotation.0),
+        &|_, column, rotation| format_variable("I", column, rotation.0),
+        &|a| format!("(-{})", a),
+        &|a, b| {
+            if let Some(b) = b.strip_prefix("(-").and_then(|b| b.strip_suffix(')')) {
+                format!("({} - {})", a, b)
+            } else {
+                format!("({} + {})", a, b)
+            }
+        },
+        &|a, b| format!("{} * {}", a, b),
+        &|a, s| format!("{} * {}", a, field_to_decimal(s)),
+    )
+}
+
+/// Formats a field element as its canonical decimal representative.
+fn field_to_decimal<F: FieldExt>(v: F) -> String {
+    // Field elements are formatted as big-endian hex.
+    hex_to_decimal(&format!("{:?}", v))
+}
+
+fn hex_to_decimal(hex: &str) -> String {
+    let hex = hex.strip_prefix("0x").unwrap_or(hex);
+
+    // Little-endian decimal digits.
+    let mut digits = vec![0u32];
+    for c in hex.chars() {
+        let mut carry = c.to_digit(16).expect("valid hex digit");
+        for digit in digits.iter_mut() {
+            let value = *digit * 16 + carry;
+            *digit = value % 10;
+            carry = value / 10;
+        }
+        while carry > 0 {
+            digits.push(carry % 10);
+            carry /= 10;
+        }
+    }
+    while digits.len() > 1 && digits.last() == Some(&0) {
+        digits.pop();
+    }
+
+    digits
+        .iter()
+        .rev()
+        .map(|digit| std::char::from_digit(*digit, 10).unwrap())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use pairing::bn256::Fr;
+
+    use super::{field_to_decimal, format_polynomial, format_smtlib, hex_to_decimal};
+    use crate::plonk::{Advice, Column, ConstraintSystem, Expression};
+    use crate::poly::Rotation;
+
+    #[test]
+    fn decimal_conversion() {
+        assert_eq!(hex_to_decimal("0x0"), "0");
+        assert_eq!(hex_to_decimal("0x00ff"), "255");
+        assert_eq!(
+            hex_to_decimal("0x10000000000000000"),
+            "18446744073709551616"
+        );
+        assert_eq!(field_to_decimal(Fr::from(1234567)), "1234567");
+        assert_eq!(
+            field_to_decimal(-Fr::one()),
+            "21888242871839275222246405745257275088548364400416034343698204186575808495616"
+        );
+    }
+
+    #[test]
+    fn expression_formats() {
+        let mut meta = ConstraintSystem::<Fr>::default();
+        let a: Column<Advice> = meta.advice_column();
+        meta.create_gate("square", |meta| {
+            let cur = meta.query_advice(a, Rotation::cur());
+            let next = meta.query_advice(a, Rotation::next());
+            vec![cur.clone() * cur - next * Fr::from(3) + Expression::Constant(Fr::from(5))]
+        });
+        let expr = &meta.gates[0].polynomials()[0];
+
+        assert_eq!(format_polynomial(expr), "((A0_0 * A0_0 - A0_1 * 3) + 5)");
+        assert_eq!(
+            format_smtlib(expr),
+            "(ff.add (ff.add (ff.mul A0_0 A0_0) (ff.neg (ff.mul A0_1 (as ff3 F)))) (as ff5 F))"
+        );
+    }
+}
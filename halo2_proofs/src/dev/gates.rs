@@ -122,33 +122,10 @@ impl CircuitGates {
                             &|_, column, rotation| format!("F{}@{}", column, rotation.0),
                             &|_, column, rotation| format!("A{}@{}", column, rotation.0),
                             &|_, column, rotation| format!("I{}@{}", column, rotation.0),
-                            &|a| {
-                                if a.contains(' ') {
-                                    format!("-({})", a)
-                                } else {
-                                    format!("-{}", a)
-                                }
-                            },
-                            &|a, b| {
-                                if let Some(b) = b.strip_prefix('-') {
-                                    format!("{} - {}", a, b)
-                                } else {
-                                    format!("{} + {}", a, b)
-                                }
-                            },
-                            &|a, b| match (a.contains(' '), b.contains(' ')) {
-                                (false, false) => format!("{} * {}", a, b),
-                                (false, true) => format!("{} * ({})", a, b),
-                                (true, false) => format!("({}) * {}", a, b),
-                                (true, true) => format!("({}) * ({})", a, b),
-                            },
-                            &|a, s| {
-                                if a.contains(' ') {
-                                    format!("({}) * {}", a, util::format_value(s))
-                                } else {
-                                    format!("{} * {}", a, util::format_value(s))
-                                }
-                            },
+                            &util::format_negated,
+                            &util::format_sum,
+                            &util::format_product,
+                            &util::format_scaled,
                         ),
                         queries: constraint.evaluate(
                             &|_| BTreeSet::default(),
@@ -4,7 +4,10 @@ use plotters::{
     prelude::{DrawingArea, DrawingAreaErrorKind, DrawingBackend},
 };
 use std::cmp;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::error;
+use std::fmt;
+use std::iter;
 use std::ops::Range;
 
 use crate::circuit::layouter::RegionColumn;
@@ -15,10 +18,18 @@ use crate::plonk::{
 
 /// Graphical renderer for circuit layouts.
 ///
+/// Instance columns are drawn in white, advice columns in red and fixed columns in
+/// blue, with a lighter blue for the fixed columns that selectors are compressed into.
+/// Regions are outlined and labelled with their names, after the names of the
+/// namespaces they were assigned in, such as `chip > sub-gadget > region`.
+///
 /// Cells that have been assigned to by the circuit will be shaded. If any cells are
 /// assigned to more than once (which is usually a mistake), they will be shaded darker
 /// than the surrounding cells.
 ///
+/// The layout can be rendered to any `plotters` backend, such as `SVGBackend` or
+/// `BitMapBackend` for PNG files.
+///
 /// # Examples
 ///
 /// ```ignore
@@ -39,6 +50,7 @@ use crate::plonk::{
 #[derive(Debug, Default)]
 pub struct CircuitLayout {
     hide_labels: bool,
+    show_column_labels: bool,
     mark_equality_cells: bool,
     show_equality_constraints: bool,
     view_width: Option<Range<usize>>,
@@ -54,6 +66,15 @@ impl CircuitLayout {
         self
     }
 
+    /// Labels each column with its kind and index, e.g. `A0` for the first advice
+    /// column, matching the names used by `MockProver`.
+    ///
+    /// The default is to not show these labels.
+    pub fn show_column_labels(mut self, show: bool) -> Self {
+        self.show_column_labels = show;
+        self
+    }
+
     /// Marks cells involved in equality constraints, in red.
     ///
     /// The default is to not mark these cells.
@@ -62,7 +83,8 @@ impl CircuitLayout {
         self
     }
 
-    /// Draws red lines between equality-constrained cells.
+    /// Draws red arcs between equality-constrained cells. The arcs bulge to one side,
+    /// so that constraints between cells of the same column remain visible.
     ///
     /// The default is to not show these, as they can get _very_ messy.
     pub fn show_equality_constraints(mut self, show: bool) -> Self {
@@ -125,6 +147,7 @@ impl CircuitLayout {
 
         let view_width = self.view_width.unwrap_or(0..total_columns);
         let view_height = self.view_height.unwrap_or(0..n);
+        let view_top = view_height.start;
         let view_bottom = view_height.end;
 
         // Prepare the grid layout. We render a red background for advice columns, white for
@@ -223,7 +246,7 @@ impl CircuitLayout {
                         Some((start, end)) => {
                             draw_region(&root, (start, offset), (end, offset + region.rows))?;
                             if let Some(labels) = &mut labels {
-                                labels.push((region.name.clone(), (start, offset)));
+                                labels.push((region.label(), (start, offset)));
                             }
                             width = Some((column, column + 1));
                         }
@@ -235,7 +258,7 @@ impl CircuitLayout {
                 if let Some((start, end)) = width {
                     draw_region(&root, (start, offset), (end, offset + region.rows))?;
                     if let Some(labels) = &mut labels {
-                        labels.push((region.name.clone(), (start, offset)));
+                        labels.push((region.label(), (start, offset)));
                     }
                 }
             }
@@ -273,15 +296,27 @@ impl CircuitLayout {
             }
         }
 
-        // Draw lines between equality-constrained cells.
+        // Draw arcs between equality-constrained cells.
         if self.show_equality_constraints {
+            // Arcs are drawn in pixels, between the centres of the cells.
+            let origin = root.map_coordinate(&(0, 0));
+            let cell = root.map_coordinate(&(1, 1));
+            let centre = ((cell.0 - origin.0) / 2, (cell.1 - origin.1) / 2);
             for (l_col, l_row, r_col, r_row) in &layout.equality {
                 let l_col = column_index(&cs, (*l_col).into());
                 let r_col = column_index(&cs, (*r_col).into());
-                root.draw(&PathElement::new(
-                    [(l_col, *l_row), (r_col, *r_row)],
-                    ShapeStyle::from(&RED),
-                ))?;
+                let from = root.map_coordinate(&(l_col, *l_row));
+                let to = root.map_coordinate(&(r_col, *r_row));
+                root.draw(
+                    &(EmptyElement::at((l_col, *l_row))
+                        + PathElement::new(
+                            equality_arc(
+                                centre,
+                                (to.0 - from.0 + centre.0, to.1 - from.1 + centre.1),
+                            ),
+                            ShapeStyle::from(&RED),
+                        )),
+                )?;
             }
         }
 
@@ -292,6 +327,18 @@ impl CircuitLayout {
         ))?;
 
         // Render labels last, on top of everything else.
+        if self.show_column_labels {
+            let columns = iter::empty()
+                .chain((0..cs.num_instance_columns).map(|i| format!("I{}", i)))
+                .chain((0..cs.num_advice_columns).map(|i| format!("A{}", i)))
+                .chain((0..cs.num_fixed_columns).map(|i| format!("F{}", i)));
+            for (column, label) in columns.enumerate() {
+                root.draw(
+                    &(EmptyElement::at((column, view_top))
+                        + Text::new(label, (2, 2), ("sans-serif", 12.0).into_font())),
+                )?;
+            }
+        }
         if let Some(labels) = labels {
             for (label, top_left) in labels {
                 root.draw(
@@ -318,12 +365,401 @@ impl CircuitLayout {
         }
         Ok(())
     }
+
+    /// Renders a visual diff between the layouts of two circuits on the given drawing
+    /// area.
+    ///
+    /// This is intended for reviewing how a change to a circuit (or to its floor
+    /// planner) affects its layout:
+    /// - Regions are matched between the two layouts by name, in the order they were
+    ///   assigned. Regions that are unchanged are drawn in grey.
+    /// - Regions that were moved or resized are drawn in red at their old position and
+    ///   in green at their new position.
+    /// - Regions that only exist in `before` are drawn in red, and regions that only
+    ///   exist in `after` are drawn in green.
+    /// - Columns that are only used by `before` are shaded red, and columns that are
+    ///   only used by `after` are shaded green.
+    ///
+    /// The view width, view height and label options of this `CircuitLayout` are
+    /// respected; the equality constraint options are ignored.
+    ///
+    /// Returns [`LayoutDiffError::Synthesis`] if either circuit fails to synthesize.
+    pub fn render_diff<F, Before, After, DB>(
+        self,
+        k: u32,
+        before: &Before,
+        after: &After,
+        drawing_area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), LayoutDiffError<DB::ErrorType>>
+    where
+        F: Field,
+        Before: Circuit<F>,
+        After: Circuit<F>,
+        DB: DrawingBackend,
+    {
+        let n = 1 << k;
+        let before = SynthesizedLayout::new::<F, Before>(k, n, before)
+            .map_err(LayoutDiffError::Synthesis)?;
+        let after =
+            SynthesizedLayout::new::<F, After>(k, n, after).map_err(LayoutDiffError::Synthesis)?;
+
+        self.draw_diff(n, &before, &after, drawing_area)
+            .map_err(LayoutDiffError::Drawing)
+    }
+
+    fn draw_diff<DB: DrawingBackend>(
+        self,
+        n: usize,
+        before: &SynthesizedLayout,
+        after: &SynthesizedLayout,
+        drawing_area: &DrawingArea<DB, Shift>,
+    ) -> Result<(), DrawingAreaErrorKind<DB::ErrorType>> {
+        use plotters::coord::types::RangedCoordusize;
+        use plotters::prelude::*;
+
+        let total_columns = cmp::max(before.total_columns, after.total_columns);
+        let view_width = self.view_width.unwrap_or(0..total_columns);
+        let view_height = self.view_height.unwrap_or(0..n);
+        let view_bottom = view_height.end;
+
+        let root =
+            drawing_area.apply_coord_spec(Cartesian2d::<RangedCoordusize, RangedCoordusize>::new(
+                view_width,
+                view_height,
+                drawing_area.get_pixel_range(),
+            ));
+        root.draw(&Rectangle::new(
+            [(0, 0), (total_columns, view_bottom)],
+            ShapeStyle::from(&WHITE).filled(),
+        ))?;
+
+        // Shade the columns whose usage changed.
+        let before_columns = before.used_columns();
+        let after_columns = after.used_columns();
+        for column in before_columns.difference(&after_columns) {
+            root.draw(&Rectangle::new(
+                [(*column, 0), (column + 1, view_bottom)],
+                ShapeStyle::from(&RED.mix(0.1)).filled(),
+            ))?;
+        }
+        for column in after_columns.difference(&before_columns) {
+            root.draw(&Rectangle::new(
+                [(*column, 0), (column + 1, view_bottom)],
+                ShapeStyle::from(&GREEN.mix(0.1)).filled(),
+            ))?;
+        }
+
+        root.draw(&Rectangle::new(
+            [(0, 0), (total_columns, view_bottom)],
+            &BLACK,
+        ))?;
+
+        let draw_boxes = |root: &DrawingArea<_, _>,
+                          boxes: &[((usize, usize), (usize, usize))],
+                          color: &RGBColor,
+                          opacity: f64| {
+            for (top_left, bottom_right) in boxes {
+                root.draw(&Rectangle::new(
+                    [*top_left, *bottom_right],
+                    ShapeStyle::from(&color.mix(opacity)).filled(),
+                ))?;
+                root.draw(&Rectangle::new([*top_left, *bottom_right], color))?;
+            }
+            Ok(())
+        };
+
+        let mut labels = if self.hide_labels { None } else { Some(vec![]) };
+        for region in before.diff_regions(after) {
+            let (change, boxes) = match region.change {
+                RegionChange::Unchanged => {
+                    draw_boxes(&root, &region.before, &BLACK, 0.1)?;
+                    continue;
+                }
+                RegionChange::Changed => {
+                    draw_boxes(&root, &region.before, &RED, 0.2)?;
+                    draw_boxes(&root, &region.after, &GREEN, 0.2)?;
+                    ("changed", &region.after)
+                }
+                RegionChange::Removed => {
+                    draw_boxes(&root, &region.before, &RED, 0.3)?;
+                    ("removed", &region.before)
+                }
+                RegionChange::Added => {
+                    draw_boxes(&root, &region.after, &GREEN, 0.3)?;
+                    ("added", &region.after)
+                }
+            };
+            if let (Some(labels), Some((top_left, _))) = (&mut labels, boxes.first()) {
+                labels.push((format!("{} ({})", region.label, change), *top_left));
+            }
+        }
+
+        // Show how the total number of used rows changed.
+        root.draw(&PathElement::new(
+            [
+                (0, before.layout.total_rows),
+                (total_columns, before.layout.total_rows),
+            ],
+            ShapeStyle::from(&RED),
+        ))?;
+        root.draw(&PathElement::new(
+            [
+                (0, after.layout.total_rows),
+                (total_columns, after.layout.total_rows),
+            ],
+            ShapeStyle::from(&GREEN),
+        ))?;
+
+        // Render labels last, on top of everything else.
+        if let Some(labels) = labels {
+            for (label, top_left) in labels {
+                root.draw(
+                    &(EmptyElement::at(top_left)
+                        + Text::new(label, (10, 10), ("sans-serif", 15.0).into_font())),
+                )?;
+            }
+            root.draw(
+                &(EmptyElement::at((0, after.layout.total_rows))
+                    + Text::new(
+                        format!(
+                            "{} used rows (was {})",
+                            after.layout.total_rows, before.layout.total_rows
+                        ),
+                        (10, 10),
+                        ("sans-serif", 15.0).into_font(),
+                    )),
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// An error returned by [`CircuitLayout::render_diff`].
+#[derive(Debug)]
+pub enum LayoutDiffError<E: error::Error + Send + Sync> {
+    /// One of the circuits failed to synthesize.
+    Synthesis(Error),
+    /// The diff could not be drawn on the drawing area.
+    Drawing(DrawingAreaErrorKind<E>),
+}
+
+impl<E: error::Error + Send + Sync> fmt::Display for LayoutDiffError<E> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LayoutDiffError::Synthesis(e) => write!(f, "failed to synthesize the circuit: {}", e),
+            LayoutDiffError::Drawing(e) => write!(f, "failed to draw the layout diff: {}", e),
+        }
+    }
+}
+
+impl<E: error::Error + Send + Sync> error::Error for LayoutDiffError<E> {}
+
+/// Returns the points of an arc from `from` to `to`, in pixels.
+///
+/// The arc is a quadratic Bézier curve whose control point is offset to one side of
+/// the line between its ends, by a quarter of their distance.
+fn equality_arc(from: (i32, i32), to: (i32, i32)) -> Vec<(i32, i32)> {
+    const SEGMENTS: usize = 16;
+
+    let (x0, y0) = (f64::from(from.0), f64::from(from.1));
+    let (x2, y2) = (f64::from(to.0), f64::from(to.1));
+    let (dx, dy) = (x2 - x0, y2 - y0);
+    let (x1, y1) = (x0 + dx / 2.0 - dy / 4.0, y0 + dy / 2.0 + dx / 4.0);
+
+    (0..=SEGMENTS)
+        .map(|i| {
+            let t = i as f64 / SEGMENTS as f64;
+            let u = 1.0 - t;
+            let x = u * u * x0 + 2.0 * u * t * x1 + t * t * x2;
+            let y = u * u * y0 + 2.0 * u * t * y1 + t * t * y2;
+            (x.round() as i32, y.round() as i32)
+        })
+        .collect()
+}
+
+/// How a region changed between the two layouts of [`CircuitLayout::render_diff`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum RegionChange {
+    /// The region covers the same cells in both layouts.
+    Unchanged,
+    /// The region was moved or resized.
+    Changed,
+    /// The region only exists in the layout before the change.
+    Removed,
+    /// The region only exists in the layout after the change.
+    Added,
+}
+
+/// A region of either layout, with the boxes covering it before and after the
+/// change. A region that is missing from one of the layouts has no boxes in it.
+#[derive(Debug)]
+struct RegionDiff {
+    label: String,
+    change: RegionChange,
+    before: Vec<((usize, usize), (usize, usize))>,
+    after: Vec<((usize, usize), (usize, usize))>,
+}
+
+/// The layout of a circuit, together with the column ordering used to render it.
+struct SynthesizedLayout {
+    layout: Layout,
+    /// Render index of each region column, keyed by the column.
+    column_indices: HashMap<RegionColumn, usize>,
+    total_columns: usize,
+}
+
+impl SynthesizedLayout {
+    fn new<F: Field, ConcreteCircuit: Circuit<F>>(
+        k: u32,
+        n: usize,
+        circuit: &ConcreteCircuit,
+    ) -> Result<Self, Error> {
+        let mut cs = ConstraintSystem::default();
+        let config = ConcreteCircuit::configure(&mut cs);
+        let mut layout = Layout::new(k, n, cs.num_selectors);
+        ConcreteCircuit::FloorPlanner::synthesize(
+            &mut layout,
+            circuit,
+            config,
+            cs.constants.clone(),
+        )?;
+        let selectors = std::mem::take(&mut layout.selectors);
+        let (cs, _) = cs.compress_selectors(selectors);
+
+        // Use the same column ordering as `CircuitLayout::render`.
+        let column_index = |column: RegionColumn| {
+            let column: Column<Any> = match column {
+                RegionColumn::Column(col) => col,
+                RegionColumn::Selector(selector) => cs.selector_map[selector.0].into(),
+            };
+            column.index()
+                + match column.column_type() {
+                    Any::Instance => 0,
+                    Any::Advice => cs.num_instance_columns,
+                    Any::Fixed => cs.num_instance_columns + cs.num_advice_columns,
+                }
+        };
+        let column_indices = layout
+            .regions
+            .iter()
+            .flat_map(|region| region.columns.iter())
+            .chain(layout.loose_cells.iter().map(|(column, _)| column))
+            .map(|column| (*column, column_index(*column)))
+            .collect();
+
+        Ok(SynthesizedLayout {
+            layout,
+            column_indices,
+            total_columns: cs.num_instance_columns + cs.num_advice_columns + cs.num_fixed_columns,
+        })
+    }
+
+    /// Matches the regions of this layout with those of `after` by name, in the
+    /// order they were assigned. Returns the regions of this layout, followed by
+    /// those that only exist in `after`.
+    fn diff_regions(&self, after: &SynthesizedLayout) -> Vec<RegionDiff> {
+        let mut unmatched_after: Vec<Option<usize>> =
+            (0..after.layout.regions.len()).map(Some).collect();
+        let mut regions: Vec<_> = self
+            .layout
+            .regions
+            .iter()
+            .enumerate()
+            .map(|(before_index, region)| {
+                let matched = unmatched_after
+                    .iter_mut()
+                    .find(|index| {
+                        matches!(index, Some(i) if after.layout.regions[*i].name == region.name)
+                    })
+                    .and_then(|index| index.take());
+
+                let old_boxes = self.region_boxes(before_index);
+                let (change, new_boxes) = match matched {
+                    Some(after_index) => {
+                        let new_boxes = after.region_boxes(after_index);
+                        if old_boxes == new_boxes {
+                            (RegionChange::Unchanged, new_boxes)
+                        } else {
+                            (RegionChange::Changed, new_boxes)
+                        }
+                    }
+                    None => (RegionChange::Removed, vec![]),
+                };
+                RegionDiff {
+                    label: region.label(),
+                    change,
+                    before: old_boxes,
+                    after: new_boxes,
+                }
+            })
+            .collect();
+        regions.extend(
+            unmatched_after
+                .into_iter()
+                .flatten()
+                .map(|after_index| RegionDiff {
+                    label: after.layout.regions[after_index].label(),
+                    change: RegionChange::Added,
+                    before: vec![],
+                    after: after.region_boxes(after_index),
+                }),
+        );
+        regions
+    }
+
+    /// Returns the render indices of all columns with assigned cells.
+    fn used_columns(&self) -> HashSet<usize> {
+        self.layout
+            .regions
+            .iter()
+            .flat_map(|region| region.cells.iter())
+            .chain(self.layout.loose_cells.iter())
+            .map(|(column, _)| self.column_indices[column])
+            .collect()
+    }
+
+    /// Returns the boxes covering the contiguous parts of a region, as
+    /// `(top_left, bottom_right)` corners.
+    fn region_boxes(&self, region: usize) -> Vec<((usize, usize), (usize, usize))> {
+        let region = &self.layout.regions[region];
+        let offset = match region.offset {
+            Some(offset) => offset,
+            None => return vec![],
+        };
+
+        let mut columns: Vec<_> = region
+            .columns
+            .iter()
+            .map(|column| self.column_indices[column])
+            .collect();
+        columns.sort_unstable();
+        columns.dedup();
+
+        let mut boxes = vec![];
+        let mut width: Option<(usize, usize)> = None;
+        for column in columns {
+            match width {
+                Some((start, end)) if end == column => width = Some((start, end + 1)),
+                Some((start, end)) => {
+                    boxes.push(((start, offset), (end, offset + region.rows)));
+                    width = Some((column, column + 1));
+                }
+                None => width = Some((column, column + 1)),
+            }
+        }
+        if let Some((start, end)) = width {
+            boxes.push(((start, offset), (end, offset + region.rows)));
+        }
+        boxes
+    }
 }
 
 #[derive(Debug)]
 struct Region {
     /// The name of the region. Not required to be unique.
     name: String,
+    /// The namespaces the region was assigned in, outermost first.
+    namespaces: Vec<String>,
     /// The columns used by this region.
     columns: HashSet<RegionColumn>,
     /// The row that this region starts on, if known.
@@ -335,11 +771,25 @@ struct Region {
     cells: Vec<(RegionColumn, usize)>,
 }
 
+impl Region {
+    /// Returns the name of this region after the names of its namespaces.
+    fn label(&self) -> String {
+        self.namespaces
+            .iter()
+            .chain(iter::once(&self.name))
+            .map(String::as_str)
+            .collect::<Vec<_>>()
+            .join(" > ")
+    }
+}
+
 #[derive(Default)]
 struct Layout {
     k: u32,
     regions: Vec<Region>,
     current_region: Option<usize>,
+    /// The namespaces we are in, outermost first.
+    namespaces: Vec<String>,
     total_rows: usize,
     /// Any cells assigned outside of a region. We store this as a `Vec` so that if any
     /// cells are double-assigned, they will be visibly darker.
@@ -356,6 +806,7 @@ impl Layout {
             k,
             regions: vec![],
             current_region: None,
+            namespaces: vec![],
             total_rows: 0,
             /// Any cells assigned outside of a region. We store this as a `Vec` so that if any
             /// cells are double-assigned, they will be visibly darker.
@@ -403,6 +854,7 @@ impl<F: Field> Assignment<F> for Layout {
         self.current_region = Some(self.regions.len());
         self.regions.push(Region {
             name: name_fn().into(),
+            namespaces: self.namespaces.clone(),
             columns: HashSet::default(),
             offset: None,
             rows: 0,
@@ -488,15 +940,157 @@ impl<F: Field> Assignment<F> for Layout {
         Ok(())
     }
 
-    fn push_namespace<NR, N>(&mut self, _: N)
+    fn push_namespace<NR, N>(&mut self, name_fn: N)
     where
         NR: Into<String>,
         N: FnOnce() -> NR,
     {
-        // Do nothing; we don't care about namespaces in this context.
+        self.namespaces.push(name_fn().into());
     }
 
     fn pop_namespace(&mut self, _: Option<String>) {
-        // Do nothing; we don't care about namespaces in this context.
+        self.namespaces.pop();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use pairing::bn256::Fr as Fp;
+    use plotters::prelude::{IntoDrawingArea, SVGBackend};
+
+    use super::{equality_arc, CircuitLayout, LayoutDiffError, RegionChange, SynthesizedLayout};
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
+    };
+
+    const K: u32 = 4;
+
+    /// Assigns a single cell in each of `regions`, given by name and advice
+    /// column, then fails if `fail` is set.
+    #[derive(Clone, Copy)]
+    struct RegionsCircuit {
+        regions: &'static [(&'static str, usize)],
+        fail: bool,
+    }
+
+    const BEFORE: RegionsCircuit = RegionsCircuit {
+        regions: &[("kept", 0), ("moved", 0), ("removed", 1)],
+        fail: false,
+    };
+
+    const AFTER: RegionsCircuit = RegionsCircuit {
+        regions: &[("kept", 0), ("moved", 1), ("added", 1)],
+        fail: false,
+    };
+
+    impl Circuit<Fp> for RegionsCircuit {
+        type Config = [Column<Advice>; 2];
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            *self
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            [meta.advice_column(), meta.advice_column()]
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            for &(name, column) in self.regions.iter() {
+                layouter.assign_region(
+                    || name,
+                    |mut region| {
+                        region.assign_advice(|| "cell", config[column], 0, || Ok(Fp::zero()))?;
+                        Ok(())
+                    },
+                )?;
+            }
+            if self.fail {
+                return Err(Error::Synthesis);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn diff_regions() {
+        let before = SynthesizedLayout::new::<Fp, _>(K, 1 << K, &BEFORE).unwrap();
+        let after = SynthesizedLayout::new::<Fp, _>(K, 1 << K, &AFTER).unwrap();
+        let regions = before.diff_regions(&after);
+
+        let changes: Vec<_> = regions
+            .iter()
+            .map(|region| (region.label.as_str(), region.change))
+            .collect();
+        assert_eq!(
+            changes,
+            vec![
+                ("kept", RegionChange::Unchanged),
+                ("moved", RegionChange::Changed),
+                ("removed", RegionChange::Removed),
+                ("added", RegionChange::Added),
+            ]
+        );
+
+        // The simple floor planner stacks the regions of each column.
+        assert_eq!(regions[0].before, vec![((0, 0), (1, 1))]);
+        assert_eq!(regions[0].after, regions[0].before);
+        assert_eq!(regions[1].before, vec![((0, 1), (1, 2))]);
+        assert_eq!(regions[1].after, vec![((1, 0), (2, 1))]);
+        assert_eq!(regions[2].before, vec![((1, 0), (2, 1))]);
+        assert!(regions[2].after.is_empty());
+        assert!(regions[3].before.is_empty());
+        assert_eq!(regions[3].after, vec![((1, 1), (2, 2))]);
+
+        // A layout doesn't differ from itself.
+        assert!(before
+            .diff_regions(&before)
+            .iter()
+            .all(|region| region.change == RegionChange::Unchanged));
+    }
+
+    #[test]
+    fn render_diff() {
+        let mut svg = String::new();
+        {
+            let root = SVGBackend::with_string(&mut svg, (256, 256)).into_drawing_area();
+            CircuitLayout::default()
+                .show_labels(false)
+                .render_diff(K, &BEFORE, &AFTER, &root)
+                .unwrap();
+        }
+        assert!(svg.contains("<rect"));
+
+        let failing = RegionsCircuit {
+            fail: true,
+            ..AFTER
+        };
+        assert!(matches!(
+            SynthesizedLayout::new::<Fp, _>(K, 1 << K, &failing),
+            Err(Error::Synthesis)
+        ));
+        let mut svg = String::new();
+        let root = SVGBackend::with_string(&mut svg, (256, 256)).into_drawing_area();
+        assert!(matches!(
+            CircuitLayout::default().render_diff(K, &BEFORE, &failing, &root),
+            Err(LayoutDiffError::Synthesis(Error::Synthesis))
+        ));
+    }
+
+    #[test]
+    fn equality_arc_bulges_sideways() {
+        let arc = equality_arc((10, 0), (10, 100));
+        assert_eq!(arc.first(), Some(&(10, 0)));
+        assert_eq!(arc.last(), Some(&(10, 100)));
+
+        // An arc within a single column leaves the column halfway along.
+        let (x, y) = arc[arc.len() / 2];
+        assert_eq!((x, y), (-3, 50));
     }
 }
@@ -0,0 +1,111 @@
+//! Accounting of heap usage, for catching memory regressions in keygen and proving.
+//!
+//! A test or benchmark binary opts in by installing [`CountingAllocator`] as its
+//! global allocator:
+//!
+//! ```
+//! use halo2_proofs::dev::heap::{self, CountingAllocator};
+//! use std::alloc::System;
+//!
+//! #[global_allocator]
+//! static ALLOCATOR: CountingAllocator = CountingAllocator::new(System);
+//!
+//! fn main() {
+//!     let (buffer, peak) = heap::peak_during(|| vec![0u8; 1 << 20]);
+//!     assert!(peak >= buffer.len());
+//! }
+//! ```
+//!
+//! The pipeline stages logged by keygen, proving and verification then also report
+//! the peak number of heap bytes allocated while they ran, as `peak_heap_bytes`.
+//! The counts are shared by all threads. Without the allocator installed, they are
+//! always zero.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The number of bytes currently allocated.
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+/// The largest value of `ALLOCATED` since the innermost measurement started.
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+/// A global allocator that counts the bytes allocated through `A`.
+#[derive(Debug)]
+pub struct CountingAllocator<A = System> {
+    inner: A,
+}
+
+impl<A> CountingAllocator<A> {
+    /// Wraps the allocator `inner`.
+    pub const fn new(inner: A) -> Self {
+        CountingAllocator { inner }
+    }
+}
+
+fn record_alloc(size: usize) {
+    let allocated = ALLOCATED.fetch_add(size, Ordering::Relaxed) + size;
+    PEAK.fetch_max(allocated, Ordering::Relaxed);
+}
+
+fn record_dealloc(size: usize) {
+    ALLOCATED.fetch_sub(size, Ordering::Relaxed);
+}
+
+#[allow(unsafe_code)]
+unsafe impl<A: GlobalAlloc> GlobalAlloc for CountingAllocator<A> {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn alloc_zeroed(&self, layout: Layout) -> *mut u8 {
+        let ptr = self.inner.alloc_zeroed(layout);
+        if !ptr.is_null() {
+            record_alloc(layout.size());
+        }
+        ptr
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        self.inner.dealloc(ptr, layout);
+        record_dealloc(layout.size());
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        let new_ptr = self.inner.realloc(ptr, layout, new_size);
+        if !new_ptr.is_null() {
+            record_dealloc(layout.size());
+            record_alloc(new_size);
+        }
+        new_ptr
+    }
+}
+
+/// Returns the number of bytes currently allocated.
+pub fn allocated() -> usize {
+    ALLOCATED.load(Ordering::Relaxed)
+}
+
+/// Starts measuring the peak heap usage, and returns the peak of the enclosing
+/// measurement so that [`end_measurement`] can restore it.
+pub(crate) fn start_measurement() -> usize {
+    PEAK.swap(allocated(), Ordering::Relaxed)
+}
+
+/// Returns the peak heap usage since the matching [`start_measurement`].
+pub(crate) fn end_measurement(outer_peak: usize) -> usize {
+    let peak = PEAK.load(Ordering::Relaxed);
+    PEAK.fetch_max(outer_peak, Ordering::Relaxed);
+    peak
+}
+
+/// Runs `f`, and returns its result together with the peak number of bytes that
+/// were allocated while it ran, including the ones allocated before it started.
+pub fn peak_during<R>(f: impl FnOnce() -> R) -> (R, usize) {
+    let outer_peak = start_measurement();
+    let result = f();
+    (result, end_measurement(outer_peak))
+}
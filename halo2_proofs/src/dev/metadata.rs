@@ -151,17 +151,31 @@ pub struct Region {
     /// The name of the region. This is specified by the region creator (such as a chip
     /// implementation), and is not enforced to be unique.
     name: String,
+    /// The namespaces the region was assigned in, outermost first. These are the
+    /// names given to [`Layouter::namespace`], usually by the gadgets that
+    /// assigned the region.
+    ///
+    /// [`Layouter::namespace`]: crate::circuit::Layouter::namespace
+    namespaces: Vec<String>,
 }
 
 impl fmt::Display for Region {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "Region {} ('{}')", self.index, self.name)
+        write!(f, "Region {} ('{}')", self.index, self.name)?;
+        if !self.namespaces.is_empty() {
+            write!(f, " (in {})", self.namespaces.join(" > "))?;
+        }
+        Ok(())
     }
 }
 
 impl From<(usize, String)> for Region {
     fn from((index, name): (usize, String)) -> Self {
-        Region { index, name }
+        Region {
+            index,
+            name,
+            namespaces: vec![],
+        }
     }
 }
 
@@ -170,6 +184,17 @@ impl From<(usize, &str)> for Region {
         Region {
             index,
             name: name.to_owned(),
+            namespaces: vec![],
+        }
+    }
+}
+
+impl From<(usize, String, Vec<String>)> for Region {
+    fn from((index, name, namespaces): (usize, String, Vec<String>)) -> Self {
+        Region {
+            index,
+            name,
+            namespaces,
         }
     }
 }
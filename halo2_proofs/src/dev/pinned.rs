@@ -0,0 +1,60 @@
+use std::{env, fs, io, path::Path};
+
+use crate::{arithmetic::CurveAffine, plonk::VerifyingKey};
+
+/// Environment variable which, when set, makes [`assert_pinned_snapshot`]
+/// overwrite existing snapshots instead of comparing against them.
+const UPDATE_VAR: &str = "HALO2_UPDATE_PINNED";
+
+/// Asserts that the pinned representation of `vk` matches the snapshot stored
+/// at `path`.
+///
+/// If no snapshot exists at `path` yet, or if the `HALO2_UPDATE_PINNED`
+/// environment variable is set, the snapshot is (re)written instead, so that
+/// it can be reviewed and checked in alongside the circuit.
+///
+/// # Panics
+///
+/// Panics if the snapshot can't be read or written, or if it doesn't match,
+/// reporting the first differing line.
+///
+/// # Examples
+///
+/// ```ignore
+/// #[test]
+/// fn circuit_did_not_change() {
+///     let params = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+///     let vk = keygen_vk(&params, &MyCircuit::default()).unwrap();
+///     assert_pinned_snapshot(&vk, "tests/snapshots/my_circuit.txt");
+/// }
+/// ```
+pub fn assert_pinned_snapshot<C: CurveAffine>(vk: &VerifyingKey<C>, path: impl AsRef<Path>) {
+    let path = path.as_ref();
+    let pinned = vk.pinned();
+
+    let snapshot = match fs::read_to_string(path) {
+        Ok(snapshot) if env::var_os(UPDATE_VAR).is_none() => snapshot,
+        Ok(_) => return write_snapshot(vk, path),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return write_snapshot(vk, path),
+        Err(e) => panic!("failed to read snapshot {}: {}", path.display(), e),
+    };
+
+    if let Err(mismatch) = pinned.check_snapshot(&snapshot) {
+        panic!(
+            "{}\nin snapshot {} (set {} to update it)",
+            mismatch,
+            path.display(),
+            UPDATE_VAR
+        );
+    }
+}
+
+fn write_snapshot<C: CurveAffine>(vk: &VerifyingKey<C>, path: &Path) {
+    let res = path
+        .parent()
+        .map_or(Ok(()), fs::create_dir_all)
+        .and_then(|_| fs::write(path, vk.pinned().to_snapshot()));
+    if let Err(e) = res {
+        panic!("failed to write snapshot {}: {}", path.display(), e);
+    }
+}
@@ -0,0 +1,440 @@
+//! Step-by-step replay of the verifier transcript of a proof.
+
+use std::fmt;
+use std::io;
+
+use crate::arithmetic::{CurveAffine, MultiMillerLoop};
+use crate::plonk::{verify_proof, Error, SingleVerifier, VerifyingKey};
+use crate::poly::commitment::ParamsVerifier;
+use crate::transcript::{EncodedChallenge, Transcript, TranscriptRead};
+
+/// A value that the verifier absorbed into, or squeezed from, its transcript.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum TranscriptValue<C: CurveAffine> {
+    /// A point absorbed by the verifier without reading it from the proof, such
+    /// as a commitment to an instance column.
+    CommonPoint(C),
+    /// A scalar absorbed by the verifier without reading it from the proof, such
+    /// as the digest of the verifying key.
+    CommonScalar(C::Scalar),
+    /// A commitment read from the proof.
+    Point(C),
+    /// An evaluation read from the proof.
+    Scalar(C::Scalar),
+    /// A challenge squeezed from the transcript.
+    Challenge(C::Scalar),
+}
+
+/// A step of the verifier transcript, labelled with what it stands for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct TranscriptStep<C: CurveAffine> {
+    /// What the value stands for, such as `"advice 0 commitment"` or `"x"`.
+    pub label: String,
+    /// The value itself.
+    pub value: TranscriptValue<C>,
+}
+
+impl<C: CurveAffine> fmt::Display for TranscriptStep<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.value {
+            TranscriptValue::CommonPoint(point) => {
+                write!(f, "absorb  {}: {:?}", self.label, point)
+            }
+            TranscriptValue::CommonScalar(scalar) => {
+                write!(f, "absorb  {}: {:?}", self.label, scalar)
+            }
+            TranscriptValue::Point(point) => write!(f, "read    {}: {:?}", self.label, point),
+            TranscriptValue::Scalar(scalar) => write!(f, "read    {}: {:?}", self.label, scalar),
+            TranscriptValue::Challenge(challenge) => {
+                write!(f, "squeeze {}: {:?}", self.label, challenge)
+            }
+        }
+    }
+}
+
+/// The verifier transcript of a proof, as replayed by [`replay_proof`].
+#[derive(Debug)]
+pub struct ProofReplay<C: CurveAffine> {
+    /// The steps of the transcript, in the order in which the verifier took them.
+    /// If verification failed, these are the steps taken up to the failure.
+    pub steps: Vec<TranscriptStep<C>>,
+    /// The outcome of verifying the proof.
+    pub result: Result<(), Error>,
+}
+
+impl<C: CurveAffine> fmt::Display for ProofReplay<C> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (index, step) in self.steps.iter().enumerate() {
+            writeln!(f, "{:>4} {}", index, step)?;
+        }
+        match &self.result {
+            Ok(()) => write!(f, "proof verified"),
+            Err(e) => write!(f, "proof rejected: {}", e),
+        }
+    }
+}
+
+/// Replays the verifier transcript of the proofs in `transcript`, recording each
+/// commitment and scalar that the verifier absorbs, each evaluation it reads and
+/// each challenge it squeezes, labelled with what they stand for.
+///
+/// This is meant for debugging verifiers written outside this crate: when such a
+/// verifier disagrees with [`verify_proof`], comparing its transcript with the
+/// one printed here shows the first step at which the two diverge.
+///
+/// # Examples
+///
+/// ```ignore
+/// let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+/// let replay = replay_proof(&params_verifier, pk.get_vk(), &[&[&instances]], &mut transcript);
+/// println!("{}", replay);
+/// ```
+pub fn replay_proof<
+    C: MultiMillerLoop,
+    E: EncodedChallenge<C::G1Affine>,
+    T: TranscriptRead<C::G1Affine, E>,
+>(
+    params: &ParamsVerifier<C>,
+    vk: &VerifyingKey<C::G1Affine>,
+    instances: &[&[&[C::Scalar]]],
+    transcript: &mut T,
+) -> ProofReplay<C::G1Affine> {
+    let mut recorder = Recorder {
+        transcript,
+        labels: vk.transcript_labels(instances.len(), params.scheme()),
+        steps: vec![],
+    };
+    let result = verify_proof(
+        params,
+        vk,
+        SingleVerifier::new(params),
+        instances,
+        &mut recorder,
+    );
+
+    ProofReplay {
+        steps: recorder.steps,
+        result,
+    }
+}
+
+/// A transcript that records the steps taken through the transcript it wraps.
+struct Recorder<'t, C: CurveAffine, T> {
+    transcript: &'t mut T,
+    labels: Vec<String>,
+    steps: Vec<TranscriptStep<C>>,
+}
+
+impl<'t, C: CurveAffine, T> Recorder<'t, C, T> {
+    fn record(&mut self, value: TranscriptValue<C>) {
+        let label = self
+            .labels
+            .get(self.steps.len())
+            .cloned()
+            .unwrap_or_else(|| {
+                format!("multiopen witness {}", self.steps.len() - self.labels.len())
+            });
+        self.steps.push(TranscriptStep { label, value });
+    }
+}
+
+impl<'t, C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>> Transcript<C, E>
+    for Recorder<'t, C, T>
+{
+    fn squeeze_challenge(&mut self) -> E {
+        let challenge = self.transcript.squeeze_challenge();
+        self.record(TranscriptValue::Challenge(challenge.get_scalar()));
+        challenge
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.transcript.common_point(point)?;
+        self.record(TranscriptValue::CommonPoint(point));
+        Ok(())
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.transcript.common_scalar(scalar)?;
+        self.record(TranscriptValue::CommonScalar(scalar));
+        Ok(())
+    }
+}
+
+impl<'t, C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>> TranscriptRead<C, E>
+    for Recorder<'t, C, T>
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let point = self.transcript.read_point()?;
+        self.record(TranscriptValue::Point(point));
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let scalar = self.transcript.read_scalar()?;
+        self.record(TranscriptValue::Scalar(scalar));
+        Ok(scalar)
+    }
+
+    fn read_non_identity_point(&mut self) -> io::Result<C> {
+        let point = self.transcript.read_non_identity_point()?;
+        self.record(TranscriptValue::Point(point));
+        Ok(point)
+    }
+
+    fn ensure_exhausted(&mut self) -> io::Result<()> {
+        self.transcript.ensure_exhausted()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+    use rand_core::OsRng;
+
+    use super::{replay_proof, TranscriptValue};
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{
+            create_proof, keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error,
+            Selector, TableColumn,
+        },
+        poly::{
+            commitment::{CommitmentScheme, Params, ParamsVerifier},
+            Rotation,
+        },
+        transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+    };
+
+    #[derive(Clone)]
+    struct BooleanConfig {
+        a: Column<Advice>,
+        q: Selector,
+    }
+
+    struct BooleanCircuit;
+
+    impl Circuit<Fp> for BooleanCircuit {
+        type Config = BooleanConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let q = meta.selector();
+            meta.boolean_gate(q, a);
+            BooleanConfig { a, q }
+        }
+
+        fn without_witnesses(&self) -> Self {
+            BooleanCircuit
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "bit",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Ok(Fp::one()))
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn replay() {
+        const K: u32 = 5;
+
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+        let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+        let vk = keygen_vk(&params, &BooleanCircuit).unwrap();
+        let pk = keygen_pk(&params, vk, &BooleanCircuit).unwrap();
+        let instances: &[&[&[Fp]]] = &[&[]];
+
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof(
+            &params,
+            &pk,
+            &[BooleanCircuit],
+            instances,
+            OsRng,
+            &mut transcript,
+        )
+        .unwrap();
+        let proof = transcript.finalize();
+
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        let replay = replay_proof(&params_verifier, pk.get_vk(), instances, &mut transcript);
+        assert!(replay.result.is_ok());
+        assert_eq!(replay.steps[0].label, "vk digest");
+        assert_eq!(replay.steps[1].label, "advice 0 commitment");
+        assert_eq!(
+            replay.steps[1].value,
+            TranscriptValue::Point(proof_point(&proof))
+        );
+
+        // Every commitment and evaluation in the proof is read, and labelled.
+        let reads = replay
+            .steps
+            .iter()
+            .filter(|step| {
+                matches!(
+                    step.value,
+                    TranscriptValue::Point(_) | TranscriptValue::Scalar(_)
+                )
+            })
+            .count();
+        assert_eq!(reads * 32, proof.len());
+        assert!(replay
+            .steps
+            .iter()
+            .any(|step| step.label == "x" && matches!(step.value, TranscriptValue::Challenge(_))));
+        assert!(replay.to_string().ends_with("proof verified"));
+
+        // A tampered proof is still replayed up to the point of failure.
+        let mut tampered = proof.clone();
+        let last = tampered.len() - 1;
+        tampered[last] ^= 1;
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&tampered[..]);
+        let replay = replay_proof(&params_verifier, pk.get_vk(), instances, &mut transcript);
+        assert!(replay.result.is_err());
+        assert!(replay.steps.len() > 1);
+        assert!(replay.to_string().contains("proof rejected"));
+    }
+
+    #[derive(Clone)]
+    struct LookupConfig {
+        a: Column<Advice>,
+        q: Selector,
+        table: TableColumn,
+    }
+
+    struct LookupCircuit;
+
+    impl Circuit<Fp> for LookupCircuit {
+        type Config = LookupConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let config = LookupConfig {
+                a: meta.advice_column(),
+                q: meta.complex_selector(),
+                table: meta.lookup_table_column(),
+            };
+            meta.lookup("a", |meta| {
+                let q = meta.query_selector(config.q);
+                let a = meta.query_advice(config.a, Rotation::cur());
+                vec![(q * a, config.table)]
+            });
+            config
+        }
+
+        fn without_witnesses(&self) -> Self {
+            LookupCircuit
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_table(
+                || "table",
+                |mut table| {
+                    for row in 0..4 {
+                        table.assign_cell(
+                            || "table",
+                            config.table,
+                            row,
+                            || Ok(Fp::from(row as u64)),
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            layouter.assign_region(
+                || "a",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || Ok(Fp::from(3)))
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn replay_lookup() {
+        const K: u32 = 5;
+
+        // The multiopen witnesses of gwc are labelled past the end of the
+        // labels, so the proof is created with shplonk.
+        let params =
+            Params::<G1Affine>::unsafe_setup::<Bn256>(K).with_scheme(CommitmentScheme::KzgShplonk);
+        let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+        let vk = keygen_vk(&params, &LookupCircuit).unwrap();
+        let pk = keygen_pk(&params, vk, &LookupCircuit).unwrap();
+        let instances: &[&[&[Fp]]] = &[&[]];
+
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof(
+            &params,
+            &pk,
+            &[LookupCircuit],
+            instances,
+            OsRng,
+            &mut transcript,
+        )
+        .unwrap();
+        let proof = transcript.finalize();
+
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        let replay = replay_proof(&params_verifier, pk.get_vk(), instances, &mut transcript);
+        assert!(replay.result.is_ok());
+
+        // There is a label for each step, and the labels of the lookup argument
+        // line up with what its verifier reads.
+        assert_eq!(
+            replay.steps.len(),
+            pk.get_vk()
+                .transcript_labels(1, CommitmentScheme::KzgShplonk)
+                .len()
+        );
+        for step in replay.steps.iter() {
+            if step.label.ends_with("commitment") {
+                assert!(matches!(step.value, TranscriptValue::Point(_)), "{}", step);
+            } else if step.label.ends_with("eval") {
+                assert!(matches!(step.value, TranscriptValue::Scalar(_)), "{}", step);
+            }
+        }
+        #[cfg(feature = "mv-lookup")]
+        let lookup_labels = [
+            "lookup 0 multiplicity commitment",
+            "lookup 0 phi commitment",
+            "lookup 0 phi eval",
+            "lookup 0 phi next eval",
+            "lookup 0 multiplicity eval",
+        ];
+        #[cfg(not(feature = "mv-lookup"))]
+        let lookup_labels = [
+            "lookup 0 permuted input commitment",
+            "lookup 0 permuted table commitment",
+            "lookup 0 product commitment",
+            "lookup 0 product eval",
+            "lookup 0 permuted table eval",
+        ];
+        for label in lookup_labels.iter() {
+            assert!(replay.steps.iter().any(|step| step.label == *label));
+        }
+    }
+
+    fn proof_point(proof: &[u8]) -> G1Affine {
+        use group::GroupEncoding;
+
+        let mut repr = <G1Affine as GroupEncoding>::Repr::default();
+        repr.as_mut().copy_from_slice(&proof[..32]);
+        G1Affine::from_bytes(&repr).unwrap()
+    }
+}
@@ -0,0 +1,60 @@
+//! Finding the smallest `k` that a circuit fits in.
+
+use ff::Field;
+
+use crate::plonk::{minimum_k, Circuit};
+
+/// Returns the smallest `k` such that `circuit` fits in `2^k` rows, which is the
+/// smallest `k` that [`MockProver::run`], key generation and proving accept.
+///
+/// The circuit is synthesized without its witness values being computed, and the
+/// rows it assigns are counted together with the rows reserved for blinding factors.
+/// Rows used by instance values are not counted, as the instances are not known here.
+///
+/// Panics if the circuit fails to synthesize.
+///
+/// [`MockProver::run`]: super::MockProver::run
+///
+/// ```
+/// use halo2_proofs::{
+///     circuit::{Layouter, SimpleFloorPlanner},
+///     dev::{k_for_circuit, MockProver},
+///     plonk::{Advice, Circuit, Column, ConstraintSystem, Error},
+/// };
+/// use pairing::bn256::Fr as Fp;
+///
+/// struct MyCircuit;
+///
+/// impl Circuit<Fp> for MyCircuit {
+///     type Config = Column<Advice>;
+///     type FloorPlanner = SimpleFloorPlanner;
+///
+///     fn without_witnesses(&self) -> Self {
+///         MyCircuit
+///     }
+///
+///     fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+///         meta.advice_column()
+///     }
+///
+///     fn synthesize(&self, a: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+///         layouter.assign_region(
+///             || "rows",
+///             |mut region| {
+///                 for row in 0..100 {
+///                     region.assign_advice(|| "a", a, row, || Ok(Fp::from(row as u64)))?;
+///                 }
+///                 Ok(())
+///             },
+///         )
+///     }
+/// }
+///
+/// let k = k_for_circuit(&MyCircuit);
+/// assert_eq!(k, 7);
+/// assert!(MockProver::run(k, &MyCircuit, vec![]).is_ok());
+/// assert!(MockProver::run(k - 1, &MyCircuit, vec![]).is_err());
+/// ```
+pub fn k_for_circuit<F: Field, ConcreteCircuit: Circuit<F>>(circuit: &ConcreteCircuit) -> u32 {
+    minimum_k(circuit).expect("circuit should synthesize")
+}
@@ -1,11 +1,12 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
 
 use group::ff::Field;
 use pairing::arithmetic::FieldExt;
 
 use super::{metadata, Value};
 use crate::{
-    plonk::{Any, Expression, Gate, VirtualCell},
+    plonk::{Any, Column, Expression, Gate, VirtualCell},
     poly::Rotation,
 };
 
@@ -26,6 +27,50 @@ pub(super) fn format_value<F: Field>(v: F) -> String {
     }
 }
 
+pub(super) fn format_negated(a: String) -> String {
+    if a.contains(' ') {
+        format!("-({})", a)
+    } else {
+        format!("-{}", a)
+    }
+}
+
+pub(super) fn format_sum(a: String, b: String) -> String {
+    if let Some(b) = b.strip_prefix('-') {
+        format!("{} - {}", a, b)
+    } else {
+        format!("{} + {}", a, b)
+    }
+}
+
+pub(super) fn format_product(a: String, b: String) -> String {
+    match (a.contains(' '), b.contains(' ')) {
+        (false, false) => format!("{} * {}", a, b),
+        (false, true) => format!("{} * ({})", a, b),
+        (true, false) => format!("({}) * {}", a, b),
+        (true, true) => format!("({}) * ({})", a, b),
+    }
+}
+
+pub(super) fn format_scaled<F: Field>(a: String, scalar: F) -> String {
+    if a.contains(' ') {
+        format!("({}) * {}", a, format_value(scalar))
+    } else {
+        format!("{} * {}", a, format_value(scalar))
+    }
+}
+
+/// Formats values of expressions, which may be poisoned.
+pub(super) fn format_values<F: FieldExt>(values: &[Value<F>]) -> Vec<String> {
+    values
+        .iter()
+        .map(|value| match value {
+            Value::Real(v) => format_value(*v),
+            Value::Poison => "poison".into(),
+        })
+        .collect()
+}
+
 fn cell_value<'a, F: FieldExt>(
     virtual_cells: &'a [VirtualCell],
     column_type: Any,
@@ -81,3 +126,155 @@ pub(super) fn cell_values<'a, F: FieldExt>(
     );
     cell_values.into_iter().collect()
 }
+
+/// Returns the annotations of the cells that `poly` queries, for those that
+/// `annotation` knows of.
+pub(super) fn cell_annotations<F: FieldExt>(
+    gate: &Gate<F>,
+    poly: &Expression<F>,
+    annotation: impl Fn(Column<Any>, Rotation) -> Option<String>,
+) -> Vec<(metadata::VirtualCell, String)> {
+    let query = |column_type: Any| {
+        move |_: usize, column_index: usize, rotation: Rotation| {
+            vec![(column_type, column_index, rotation.0)]
+                .into_iter()
+                .collect::<HashSet<_>>()
+        }
+    };
+    let queries = poly.evaluate(
+        &|_| HashSet::default(),
+        &|_| panic!("virtual selectors are removed during optimization"),
+        &query(Any::Fixed),
+        &query(Any::Advice),
+        &query(Any::Instance),
+        &|a| a,
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|mut a, b| {
+            a.extend(b);
+            a
+        },
+        &|a, _| a,
+    );
+
+    gate.queried_cells()
+        .iter()
+        .filter(|cell| {
+            queries.contains(&(
+                *cell.column.column_type(),
+                cell.column.index(),
+                cell.rotation.0,
+            ))
+        })
+        .filter_map(|cell| {
+            annotation(cell.column, cell.rotation)
+                .map(|annotation| (cell.clone().into(), annotation))
+        })
+        .collect::<BTreeMap<_, _>>()
+        .into_iter()
+        .collect()
+}
+
+/// A sub-term of a constraint, with its value.
+struct Term<F: FieldExt> {
+    expression: String,
+    value: Value<F>,
+    is_sum: bool,
+    children: Vec<Term<F>>,
+}
+
+impl<F: FieldExt> Term<F> {
+    fn leaf(expression: String, value: Value<F>) -> Self {
+        Term {
+            expression,
+            value,
+            is_sum: false,
+            children: vec![],
+        }
+    }
+
+    fn write(&self, depth: usize, out: &mut String) {
+        let value = format_values(&[self.value]).remove(0);
+        writeln!(
+            out,
+            "{:indent$}- {} = {}",
+            "",
+            self.expression,
+            value,
+            indent = 2 * depth
+        )
+        .unwrap();
+        for child in self.children.iter() {
+            child.write(depth + 1, out);
+        }
+    }
+}
+
+/// Renders `poly` as a tree of its sub-terms, each with its value, which shows
+/// where a constraint that isn't satisfied becomes non-zero. The terms of a sum
+/// are siblings.
+pub(super) fn expression_tree<'a, F: FieldExt>(
+    poly: &Expression<F>,
+    load_fixed: impl Fn(usize, usize, Rotation) -> Value<F> + 'a,
+    load_advice: impl Fn(usize, usize, Rotation) -> Value<F> + 'a,
+    load_instance: impl Fn(usize, usize, Rotation) -> Value<F> + 'a,
+) -> String {
+    let query = |prefix, column, rotation: Rotation, value| {
+        Term::leaf(format!("{}{}@{}", prefix, column, rotation.0), value)
+    };
+    let terms = |term: Term<F>| {
+        if term.is_sum {
+            term.children
+        } else {
+            vec![term]
+        }
+    };
+    let tree = poly.evaluate(
+        &|scalar| Term::leaf(format_value(scalar), Value::Real(scalar)),
+        &|_| panic!("virtual selectors are removed during optimization"),
+        &|index, column, rotation| {
+            query("F", column, rotation, load_fixed(index, column, rotation))
+        },
+        &|index, column, rotation| {
+            query("A", column, rotation, load_advice(index, column, rotation))
+        },
+        &|index, column, rotation| {
+            query(
+                "I",
+                column,
+                rotation,
+                load_instance(index, column, rotation),
+            )
+        },
+        &|a| Term {
+            expression: format_negated(a.expression.clone()),
+            value: -a.value,
+            is_sum: false,
+            children: vec![a],
+        },
+        &|a, b| Term {
+            expression: format_sum(a.expression.clone(), b.expression.clone()),
+            value: a.value + b.value,
+            is_sum: true,
+            children: terms(a).into_iter().chain(terms(b)).collect(),
+        },
+        &|a, b| Term {
+            expression: format_product(a.expression.clone(), b.expression.clone()),
+            value: a.value * b.value,
+            is_sum: false,
+            children: vec![a, b],
+        },
+        &|a, scalar| Term {
+            expression: format_scaled(a.expression.clone(), scalar),
+            value: a.value * scalar,
+            is_sum: false,
+            children: vec![a],
+        },
+    );
+
+    let mut out = String::new();
+    tree.write(0, &mut out);
+    out
+}
@@ -1,4 +1,9 @@
 use std::io;
+#[cfg(feature = "zeroize")]
+use std::mem::{self, MaybeUninit};
+
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
 
 use pairing::arithmetic::CurveAffine;
 
@@ -14,3 +19,51 @@ pub(crate) trait CurveRead: CurveAffine {
 }
 
 impl<C: CurveAffine> CurveRead for C {}
+
+/// Drops the elements of `values`, then wipes its whole allocation with
+/// `zeroize`.
+///
+/// `zeroize` only wipes elements of a type it knows to be zeroable, which the
+/// field types of `pairing` aren't, but it wipes `MaybeUninit<T>` for any `T`.
+/// The elements are therefore replaced by uninitialized ones, which collecting
+/// a mapped `vec::IntoIter` does in place, so the allocation that is wiped is
+/// the one that held them.
+#[cfg(feature = "zeroize")]
+pub(crate) fn wipe<T>(values: &mut Vec<T>) {
+    wiped(mem::take(values));
+}
+
+#[cfg(feature = "zeroize")]
+fn wiped<T>(values: Vec<T>) -> Vec<MaybeUninit<T>> {
+    let capacity = values.capacity();
+    let mut uninit: Vec<MaybeUninit<T>> = values
+        .into_iter()
+        .map(|value| {
+            drop(value);
+            MaybeUninit::uninit()
+        })
+        .collect();
+    uninit.resize_with(capacity, MaybeUninit::uninit);
+    uninit[..].zeroize();
+    uninit
+}
+
+#[cfg(all(test, feature = "zeroize"))]
+#[test]
+fn test_wipe() {
+    use std::rc::Rc;
+
+    let mut values: Vec<u64> = (1..=5).collect();
+    values.truncate(3);
+    let (ptr, capacity) = (values.as_ptr() as usize, values.capacity());
+    let uninit = wiped(values);
+    assert_eq!(uninit.as_ptr() as usize, ptr);
+    assert_eq!(uninit.len(), capacity);
+
+    // The elements are dropped before they are wiped.
+    let rc = Rc::new(());
+    let mut values = vec![rc.clone(), rc.clone()];
+    wipe(&mut values);
+    assert!(values.is_empty());
+    assert_eq!(Rc::strong_count(&rc), 1);
+}
@@ -26,17 +26,21 @@
 
 pub mod arithmetic;
 pub mod circuit;
+pub mod codegen;
 pub use pairing;
 mod multicore;
 pub mod worker {
     pub use super::multicore::*;
 }
 pub mod plonk;
+#[cfg(feature = "gpu")]
 pub mod gpu;
 pub mod poly;
+pub mod recursion;
 pub mod transcript;
 
 pub mod dev;
 mod helpers;
+mod logging;
 #[macro_use]
 extern crate lazy_static;
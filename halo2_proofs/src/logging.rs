@@ -0,0 +1,83 @@
+//! Structured logging for the keygen, proving and verification pipelines.
+//!
+//! All events are emitted through the [`log`] facade under one target per
+//! module (see the constants below), so that they can be enabled and filtered
+//! separately, e.g. with `RUST_LOG=halo2::prover=debug`. Messages are written
+//! as space-separated `key=value` fields to make them easy to aggregate:
+//!
+//! - each pipeline stage logs `stage=<name> event=start` at trace level, and
+//!   `stage=<name> event=end elapsed_ms=<ms>` at debug level once it is done,
+//!   followed by `peak_heap_bytes=<bytes>` with the `alloc-stats` feature;
+//! - summaries of a whole keygen, proof or verification are logged at info
+//!   level.
+
+use std::time::{Duration, Instant};
+
+use log::{debug, trace};
+
+/// Target for events emitted while generating keys.
+pub(crate) const KEYGEN: &str = "halo2::keygen";
+/// Target for events emitted while creating proofs.
+pub(crate) const PROVER: &str = "halo2::prover";
+/// Target for events emitted while verifying proofs.
+pub(crate) const VERIFIER: &str = "halo2::verifier";
+/// Target for events emitted by the worker thread pool.
+#[cfg(feature = "multicore")]
+pub(crate) const MULTICORE: &str = "halo2::multicore";
+/// Target for events emitted by the GPU backends.
+#[cfg(feature = "gpu")]
+pub(crate) const GPU: &str = "halo2::gpu";
+
+/// A timed stage of a pipeline. The end of the stage is logged when this is
+/// dropped, including on early returns.
+pub(crate) struct Stage {
+    target: &'static str,
+    name: &'static str,
+    start: Instant,
+    #[cfg(feature = "alloc-stats")]
+    outer_peak: usize,
+}
+
+impl Stage {
+    /// Logs the start of the stage `name` under `target`.
+    pub(crate) fn start(target: &'static str, name: &'static str) -> Self {
+        trace!(target: target, "stage={} event=start", name);
+        Stage {
+            target,
+            name,
+            start: Instant::now(),
+            #[cfg(feature = "alloc-stats")]
+            outer_peak: crate::dev::heap::start_measurement(),
+        }
+    }
+
+    /// Returns the name of the stage.
+    pub(crate) fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Ends the stage, returning the time it took.
+    pub(crate) fn finish(self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl Drop for Stage {
+    fn drop(&mut self) {
+        #[cfg(feature = "alloc-stats")]
+        let heap = format!(
+            " peak_heap_bytes={}",
+            crate::dev::heap::end_measurement(self.outer_peak)
+        );
+        #[cfg(not(feature = "alloc-stats"))]
+        let heap = "";
+
+        debug!(
+            target: self.target,
+            "stage={} event=end elapsed_ms={}{}",
+            self.name,
+            self.start.elapsed().as_millis(),
+            heap
+        );
+    }
+}
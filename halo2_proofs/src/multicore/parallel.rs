@@ -0,0 +1,399 @@
+//! The parallel implementation of [`multicore`](super), on [`rayon`].
+//!
+//! All of the parallel work of `halo2` runs on the [`rayon`] thread pool of the
+//! calling thread. By default this is a pool shared by the whole process; use
+//! [`with_thread_pool`] to run proving or key generation on a pool of its own.
+//!
+//! The pool shared by the whole process has as many threads as there are CPUs,
+//! unless the `HALO2_NUM_THREADS` environment variable (or `BELLMAN_NUM_CPUS`, if
+//! it is not set) or [`set_num_threads`] says otherwise.
+
+pub use rayon::{Scope, ThreadPool, ThreadPoolBuilder};
+
+use crossbeam_channel::{bounded, Receiver};
+use lazy_static::lazy_static;
+use log::{error, trace};
+
+use crate::logging::MULTICORE;
+use std::env;
+use std::fmt;
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Once;
+
+static WORKER_SPAWN_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The number of threads passed to [`set_num_threads`], or zero if it wasn't called.
+static REQUESTED_NUM_THREADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether the number of threads has been fixed by building the thread pools.
+static POOLS_BUILT: AtomicBool = AtomicBool::new(false);
+
+static GLOBAL_POOL: Once = Once::new();
+
+#[deny(missing_docs)]
+lazy_static! {
+    static ref NUM_CPUS: usize = {
+        POOLS_BUILT.store(true, Ordering::SeqCst);
+        match REQUESTED_NUM_THREADS.load(Ordering::SeqCst) {
+            0 => num_threads_from_env(|var| env::var(var).ok()).unwrap_or_else(num_cpus::get),
+            num => num,
+        }
+    };
+    pub static ref THREAD_POOL: rayon::ThreadPool = rayon::ThreadPoolBuilder::new()
+        .num_threads(*NUM_CPUS)
+        .build()
+        .unwrap();
+}
+
+/// Returns the number of threads set by the `HALO2_NUM_THREADS` environment
+/// variable, falling back to the `BELLMAN_NUM_CPUS` variable of `bellman`.
+fn num_threads_from_env(var: impl Fn(&str) -> Option<String>) -> Option<usize> {
+    ["HALO2_NUM_THREADS", "BELLMAN_NUM_CPUS"]
+        .iter()
+        .filter_map(|name| var(name)?.parse().ok())
+        .find(|&num| num > 0)
+}
+
+/// Builds the global [`rayon`] thread pool with the configured number of threads,
+/// unless it was already built.
+fn init_global_pool() {
+    GLOBAL_POOL.call_once(|| {
+        // This fails if the global pool was already built by a direct use of
+        // `rayon`, in which case that pool keeps its number of threads.
+        let _ = rayon::ThreadPoolBuilder::new()
+            .num_threads(*NUM_CPUS)
+            .build_global();
+    });
+}
+
+/// The error returned by [`set_num_threads`] once the thread pools have been built.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThreadPoolAlreadyBuilt;
+
+impl fmt::Display for ThreadPoolAlreadyBuilt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the thread pools were already built; set_num_threads must be called before any parallel work"
+        )
+    }
+}
+
+impl std::error::Error for ThreadPoolAlreadyBuilt {}
+
+/// Sets the number of threads of the thread pools shared by the whole process,
+/// overriding the `HALO2_NUM_THREADS` environment variable.
+///
+/// The pools are built on their first use, after which their number of threads
+/// can't change, so this must be called before any parallel work; otherwise
+/// [`ThreadPoolAlreadyBuilt`] is returned.
+///
+/// Panics if `num_threads` is zero.
+pub fn set_num_threads(num_threads: usize) -> Result<(), ThreadPoolAlreadyBuilt> {
+    assert!(num_threads > 0, "the number of threads must be positive");
+    if POOLS_BUILT.load(Ordering::SeqCst) {
+        return Err(ThreadPoolAlreadyBuilt);
+    }
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(num_threads)
+        .build_global()
+        .map_err(|_| ThreadPoolAlreadyBuilt)?;
+    REQUESTED_NUM_THREADS.store(num_threads, Ordering::SeqCst);
+    GLOBAL_POOL.call_once(|| {});
+
+    Ok(())
+}
+
+/// Returns the number of threads in the current thread pool.
+///
+/// See [`rayon::current_num_threads`].
+pub fn current_num_threads() -> usize {
+    init_global_pool();
+    rayon::current_num_threads()
+}
+
+/// Creates a scope for spawning parallel tasks on the current thread pool.
+///
+/// See [`rayon::scope`].
+pub fn scope<'scope, OP, R>(op: OP) -> R
+where
+    OP: FnOnce(&Scope<'scope>) -> R + Send,
+    R: Send,
+{
+    init_global_pool();
+    rayon::scope(op)
+}
+
+/// Runs `f` on `pool`, so that the parallel work of the `halo2` functions it
+/// calls, such as [`create_proof`] and [`keygen_pk`], runs on the threads of
+/// `pool` rather than on the pool shared by the whole process.
+///
+/// Applications that run several provers at once can give each of them a pool
+/// of its own, to control how many threads each proof takes:
+///
+/// ```ignore
+/// let pool = ThreadPoolBuilder::new().num_threads(4).build()?;
+/// with_thread_pool(&pool, || {
+///     create_proof(&params, &pk, &[circuit], instances, OsRng, &mut transcript)
+/// })?;
+/// ```
+///
+/// [`create_proof`]: crate::plonk::create_proof
+/// [`keygen_pk`]: crate::plonk::keygen_pk
+pub fn with_thread_pool<F, R>(pool: &ThreadPool, f: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
+    pool.install(f)
+}
+
+/// Returns whether the current thread belongs to a thread pool, such as one
+/// installed with [`with_thread_pool`], in which case [`Worker`] runs its work on
+/// that pool instead of on [`THREAD_POOL`].
+fn in_thread_pool() -> bool {
+    rayon::current_thread_index().is_some()
+}
+
+/// Returns the number of threads of the pool that [`Worker`] runs on.
+fn num_threads() -> usize {
+    if in_thread_pool() {
+        rayon::current_num_threads()
+    } else {
+        *NUM_CPUS
+    }
+}
+
+#[derive(Clone)]
+pub struct Worker {}
+
+impl Worker {
+    pub fn new() -> Worker {
+        Worker {}
+    }
+
+    pub fn get_num_cpus(&self) -> usize {
+        num_threads()
+    }
+
+    pub fn log_num_cpus(&self) -> u32 {
+        log2_floor(num_threads())
+    }
+
+    pub fn compute<F, R>(&self, f: F) -> Waiter<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        let (sender, receiver) = bounded(1);
+
+        let thread_index = rayon::current_thread_index().unwrap_or(0);
+
+        // We keep track here of how many times spawn has been called.
+        // It can be called without limit, each time, putting a
+        // request for a new thread to execute a method on the
+        // ThreadPool.  However, if we allow it to be called without
+        // limits, we run the risk of memory exhaustion due to limited
+        // stack space consumed by all of the pending closures to be
+        // executed.
+        let previous_count = WORKER_SPAWN_COUNTER.fetch_add(1, Ordering::SeqCst);
+
+        // If the number of spawns requested has exceeded the number
+        // of cores available for processing by some factor (the
+        // default being 4), instead of requesting that we spawn a new
+        // thread, we instead execute the closure in the context of an
+        // install call to help clear the growing work queue and
+        // minimize the chances of memory exhaustion.
+        let job = move || {
+            let res = f();
+            sender.send(res).unwrap();
+            WORKER_SPAWN_COUNTER.fetch_sub(1, Ordering::SeqCst);
+        };
+        if previous_count > num_threads() * 4 {
+            trace!(
+                target: MULTICORE,
+                "event=install_backlog thread={} current_threads={} requested_threads={}",
+                thread_index,
+                num_threads(),
+                WORKER_SPAWN_COUNTER.load(Ordering::SeqCst)
+            );
+            if in_thread_pool() {
+                // We are already running on the pool.
+                job();
+            } else {
+                THREAD_POOL.install(job);
+            }
+        } else if in_thread_pool() {
+            rayon::spawn(job);
+        } else {
+            THREAD_POOL.spawn(job);
+        }
+
+        Waiter { receiver }
+    }
+
+    pub fn scope<'a, F, R>(&self, elements: usize, f: F) -> R
+    where
+        F: FnOnce(&rayon::Scope<'a>, usize) -> R + Send,
+        R: Send,
+    {
+        let chunk_size = self.get_chunk_size(elements);
+
+        if in_thread_pool() {
+            rayon::scope(|scope| f(scope, chunk_size))
+        } else {
+            THREAD_POOL.scope(|scope| f(scope, chunk_size))
+        }
+    }
+
+    pub fn in_place_scope<'a, F, R>(&self, elements: usize, f: F) -> R
+    where
+        F: FnOnce(&rayon::Scope<'a>, usize) -> R,
+    {
+        let chunk_size = self.get_chunk_size(elements);
+
+        if in_thread_pool() {
+            rayon::in_place_scope(|scope| f(scope, chunk_size))
+        } else {
+            THREAD_POOL.in_place_scope(|scope| f(scope, chunk_size))
+        }
+    }
+
+    pub fn get_chunk_size(&self, elements: usize) -> usize {
+        let num_threads = num_threads();
+        let chunk_size = if elements <= num_threads {
+            1
+        } else {
+            Self::chunk_size_for_num_spawned_threads(elements, num_threads)
+        };
+
+        chunk_size
+    }
+    // TODO: check +1?
+    pub fn chunk_size_for_num_spawned_threads(elements: usize, num_threads: usize) -> usize {
+        assert!(
+            elements >= num_threads,
+            "received {} elements to spawn {} threads",
+            elements,
+            num_threads
+        );
+        if elements % num_threads == 0 {
+            elements / num_threads
+        } else {
+            elements / num_threads + 1
+        }
+    }
+
+    pub fn get_num_spawned_threads(&self, elements: usize) -> usize {
+        let num_threads = num_threads();
+        let num_spawned = if elements <= num_threads {
+            elements
+        } else {
+            let chunk = self.get_chunk_size(elements);
+            let mut spawned = elements / chunk;
+            if spawned * chunk < elements {
+                spawned += 1;
+            }
+            assert!(spawned <= 2 * num_threads);
+
+            spawned
+        };
+
+        num_spawned
+    }
+}
+
+pub struct Waiter<T> {
+    receiver: Receiver<T>,
+}
+
+impl<T> Waiter<T> {
+    /// Wait for the result.
+    pub fn wait(&self) -> T {
+        if in_thread_pool() {
+            // Calling `wait()` from within the worker thread pool can lead to dead logs
+            error!(
+                target: MULTICORE,
+                "event=wait_in_pool message=\"the wait call should never be done inside the worker thread pool\""
+            );
+            debug_assert!(false);
+        }
+        self.receiver.recv().unwrap()
+    }
+
+    /// One off sending.
+    pub fn done(val: T) -> Self {
+        let (sender, receiver) = bounded(1);
+        sender.send(val).unwrap();
+
+        Waiter { receiver }
+    }
+}
+
+fn log2_floor(num: usize) -> u32 {
+    assert!(num > 0);
+
+    let mut pow = 0;
+
+    while (1 << (pow + 1)) <= num {
+        pow += 1;
+    }
+
+    pow
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_log2_floor() {
+        assert_eq!(log2_floor(1), 0);
+        assert_eq!(log2_floor(3), 1);
+        assert_eq!(log2_floor(4), 2);
+        assert_eq!(log2_floor(5), 2);
+        assert_eq!(log2_floor(6), 2);
+        assert_eq!(log2_floor(7), 2);
+        assert_eq!(log2_floor(8), 3);
+    }
+
+    #[test]
+    fn test_num_threads_from_env() {
+        let env = |halo2: Option<&str>, bellman: Option<&str>| {
+            num_threads_from_env(move |name| match name {
+                "HALO2_NUM_THREADS" => halo2.map(String::from),
+                "BELLMAN_NUM_CPUS" => bellman.map(String::from),
+                _ => None,
+            })
+        };
+
+        assert_eq!(env(None, None), None);
+        assert_eq!(env(Some("4"), None), Some(4));
+        assert_eq!(env(None, Some("3")), Some(3));
+        assert_eq!(env(Some("4"), Some("3")), Some(4));
+        assert_eq!(env(Some("many"), Some("3")), Some(3));
+        assert_eq!(env(Some("0"), None), None);
+    }
+
+    #[test]
+    fn test_set_num_threads_after_use() {
+        // Parallel work builds the thread pools, after which their size is fixed.
+        current_num_threads();
+        assert_eq!(set_num_threads(2), Err(ThreadPoolAlreadyBuilt));
+    }
+
+    #[test]
+    fn test_with_thread_pool() {
+        let pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let worker = Worker::new();
+
+        let waiter = with_thread_pool(&pool, || {
+            assert_eq!(current_num_threads(), 2);
+            assert_eq!(worker.get_num_cpus(), 2);
+
+            // Work spawned by the worker stays on the installed pool.
+            worker.compute(current_num_threads)
+        });
+        assert_eq!(waiter.wait(), 2);
+    }
+}
@@ -0,0 +1,241 @@
+//! The sequential implementation of [`multicore`](super), for targets without
+//! threads. Work that would be spawned onto a thread pool runs immediately on
+//! the calling thread instead.
+
+use std::cell::Cell;
+use std::convert::Infallible;
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Returns the number of threads that parallel work is split between, which is
+/// always one without the `multicore` feature.
+pub fn current_num_threads() -> usize {
+    1
+}
+
+/// A scope in which tasks can be spawned, as with [`scope`].
+pub struct Scope<'scope> {
+    // Invariant in `'scope`, like `rayon::Scope`.
+    marker: PhantomData<fn(&'scope ()) -> &'scope ()>,
+}
+
+impl<'scope> Scope<'scope> {
+    /// Runs `body` to completion on the calling thread.
+    pub fn spawn<BODY>(&self, body: BODY)
+    where
+        BODY: FnOnce(&Scope<'scope>) + Send + 'scope,
+    {
+        body(self)
+    }
+}
+
+/// Creates a scope in which tasks can be spawned, and runs `op` in it on the
+/// calling thread.
+pub fn scope<'scope, OP, R>(op: OP) -> R
+where
+    OP: FnOnce(&Scope<'scope>) -> R + Send,
+    R: Send,
+{
+    op(&Scope {
+        marker: PhantomData,
+    })
+}
+
+/// The error returned by [`set_num_threads`] once the thread pools have been
+/// built. It is never returned without the `multicore` feature.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ThreadPoolAlreadyBuilt;
+
+impl fmt::Display for ThreadPoolAlreadyBuilt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "the thread pools were already built; set_num_threads must be called before any parallel work"
+        )
+    }
+}
+
+impl std::error::Error for ThreadPoolAlreadyBuilt {}
+
+/// Sets the number of threads of the thread pools shared by the whole process.
+/// Without the `multicore` feature all of the work runs on the calling thread,
+/// so this has no effect.
+///
+/// Panics if `num_threads` is zero.
+pub fn set_num_threads(num_threads: usize) -> Result<(), ThreadPoolAlreadyBuilt> {
+    assert!(num_threads > 0, "the number of threads must be positive");
+    Ok(())
+}
+
+/// A builder for a [`ThreadPool`], with the API of `rayon::ThreadPoolBuilder`.
+/// The number of threads is ignored without the `multicore` feature.
+#[derive(Debug, Default)]
+pub struct ThreadPoolBuilder {}
+
+impl ThreadPoolBuilder {
+    /// Creates a builder for a thread pool.
+    pub fn new() -> Self {
+        ThreadPoolBuilder {}
+    }
+
+    /// Sets the number of threads of the pool, which is ignored.
+    pub fn num_threads(self, _num_threads: usize) -> Self {
+        self
+    }
+
+    /// Builds the thread pool, which can't fail.
+    pub fn build(self) -> Result<ThreadPool, Infallible> {
+        Ok(ThreadPool {})
+    }
+}
+
+/// A thread pool that runs all of its work on the calling thread.
+#[derive(Debug)]
+pub struct ThreadPool {}
+
+impl ThreadPool {
+    /// Runs `op` on the calling thread.
+    pub fn install<OP, R>(&self, op: OP) -> R
+    where
+        OP: FnOnce() -> R + Send,
+        R: Send,
+    {
+        op()
+    }
+}
+
+/// Runs `f` on `pool`, which is the calling thread without the `multicore`
+/// feature.
+pub fn with_thread_pool<F, R>(pool: &ThreadPool, f: F) -> R
+where
+    F: FnOnce() -> R + Send,
+    R: Send,
+{
+    pool.install(f)
+}
+
+#[derive(Clone)]
+pub struct Worker {}
+
+impl Worker {
+    pub fn new() -> Worker {
+        Worker {}
+    }
+
+    pub fn get_num_cpus(&self) -> usize {
+        1
+    }
+
+    pub fn log_num_cpus(&self) -> u32 {
+        0
+    }
+
+    /// Runs `f` to completion on the calling thread.
+    pub fn compute<F, R>(&self, f: F) -> Waiter<R>
+    where
+        F: FnOnce() -> R + Send + 'static,
+        R: Send + 'static,
+    {
+        Waiter::done(f())
+    }
+
+    pub fn scope<'a, F, R>(&self, elements: usize, f: F) -> R
+    where
+        F: FnOnce(&Scope<'a>, usize) -> R + Send,
+        R: Send,
+    {
+        let chunk_size = self.get_chunk_size(elements);
+        scope(|scope| f(scope, chunk_size))
+    }
+
+    pub fn in_place_scope<'a, F, R>(&self, elements: usize, f: F) -> R
+    where
+        F: FnOnce(&Scope<'a>, usize) -> R,
+    {
+        f(
+            &Scope {
+                marker: PhantomData,
+            },
+            self.get_chunk_size(elements),
+        )
+    }
+
+    /// Returns `elements`, so that the work is done in a single chunk.
+    pub fn get_chunk_size(&self, elements: usize) -> usize {
+        elements.max(1)
+    }
+
+    pub fn chunk_size_for_num_spawned_threads(elements: usize, num_threads: usize) -> usize {
+        assert!(
+            elements >= num_threads,
+            "received {} elements to spawn {} threads",
+            elements,
+            num_threads
+        );
+        if elements % num_threads == 0 {
+            elements / num_threads
+        } else {
+            elements / num_threads + 1
+        }
+    }
+
+    pub fn get_num_spawned_threads(&self, elements: usize) -> usize {
+        elements.min(1)
+    }
+}
+
+pub struct Waiter<T> {
+    value: Cell<Option<T>>,
+}
+
+impl<T> Waiter<T> {
+    /// Returns the result, which was computed when the work was submitted.
+    ///
+    /// Panics if the result was already taken.
+    pub fn wait(&self) -> T {
+        self.value
+            .take()
+            .expect("the result of the work was already taken")
+    }
+
+    /// One off sending.
+    pub fn done(val: T) -> Self {
+        Waiter {
+            value: Cell::new(Some(val)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scope() {
+        let mut values = vec![0; 8];
+        scope(|scope| {
+            for (index, chunk) in values.chunks_mut(current_num_threads()).enumerate() {
+                scope.spawn(move |_| {
+                    for value in chunk.iter_mut() {
+                        *value = index;
+                    }
+                });
+            }
+        });
+        assert_eq!(values, (0..8).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn test_with_thread_pool() {
+        let pool = ThreadPoolBuilder::new().num_threads(2).build().unwrap();
+        let worker = Worker::new();
+
+        let waiter = with_thread_pool(&pool, || {
+            assert_eq!(current_num_threads(), 1);
+            assert_eq!(worker.get_num_cpus(), 1);
+            worker.compute(current_num_threads)
+        });
+        assert_eq!(waiter.wait(), 1);
+        assert_eq!(set_num_threads(2), Ok(()));
+    }
+}
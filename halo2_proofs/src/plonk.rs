@@ -16,23 +16,37 @@ use crate::poly::{
 use crate::transcript::{ChallengeScalar, EncodedChallenge, Transcript};
 
 mod assigned;
+mod backend;
 mod circuit;
 mod error;
 mod evaluation;
+mod instance;
+mod json;
 mod keygen;
 mod lookup;
+mod outputs;
 pub(crate) mod permutation;
+mod shuffle;
+mod sizing;
 mod vanishing;
 
 mod prover;
 mod verifier;
+mod witness;
 
 pub use assigned::*;
+pub use backend::{Halo2Backend, TranscriptHash};
 pub use circuit::*;
 pub use error::*;
+pub use instance::InstanceSet;
 pub use keygen::*;
+pub use lookup::Argument as LookupArgument;
+pub use outputs::circuit_outputs;
 pub use prover::*;
+pub use shuffle::Argument as ShuffleArgument;
+pub(crate) use sizing::{minimum_k, RowCounter};
 pub use verifier::*;
+pub use witness::{WitnessColumns, WitnessCompression};
 
 use std::io;
 
@@ -64,7 +78,8 @@ impl<C: CurveAffine> VerifyingKey<C> {
         reader: &mut R,
         params: &Params<C>,
     ) -> io::Result<Self> {
-        let (domain, cs, _) = keygen::create_domain::<C, ConcreteCircuit>(params);
+        let (domain, cs, _) = keygen::create_domain::<C, ConcreteCircuit>(params)
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e.to_string()))?;
 
         let fixed_commitments: Vec<_> = (0..cs.num_fixed_columns)
             .map(|_| C::read(reader))
@@ -85,6 +100,15 @@ impl<C: CurveAffine> VerifyingKey<C> {
         &self,
         transcript: &mut T,
     ) -> io::Result<()> {
+        transcript.common_scalar(self.transcript_repr())?;
+
+        Ok(())
+    }
+
+    /// Returns the digest of this verification key that [`Self::hash_into`]
+    /// writes to the transcript: the BLAKE2b-512 hash of the `Debug` output of
+    /// [`Self::pinned`], prefixed by its length, reduced to a scalar.
+    pub fn transcript_repr(&self) -> C::Scalar {
         let mut hasher = Blake2bParams::new()
             .hash_length(64)
             .personal(b"Halo2-Verify-Key")
@@ -96,9 +120,79 @@ impl<C: CurveAffine> VerifyingKey<C> {
         hasher.update(s.as_bytes());
 
         // Hash in final Blake2bState
-        transcript.common_scalar(C::Scalar::from_bytes_wide(hasher.finalize().as_array()))?;
+        C::Scalar::from_bytes_wide(hasher.finalize().as_array())
+    }
 
-        Ok(())
+    /// Returns a 32-byte fingerprint of this verification key, computed with
+    /// `hash` over the same encoding of [`Self::pinned`] that
+    /// [`Self::transcript_repr`] hashes.
+    ///
+    /// Two verifying keys have the same fingerprint exactly when their proofs
+    /// are interchangeable, so the fingerprint identifies a circuit, for
+    /// example as the key under which its verifier is registered on chain. It
+    /// only changes when the pinned verification key does, which
+    /// [`PinnedVerificationKey::check_snapshot`] can guard against.
+    ///
+    /// - [`TranscriptHash::Blake2b`] is the BLAKE2b-256 hash, personalized
+    ///   with `Halo2-Circuit-ID`, of the length of the encoding as a
+    ///   little-endian `u64` followed by the encoding.
+    /// - [`TranscriptHash::Keccak256`] is the Keccak256 hash of those same
+    ///   bytes.
+    /// - [`TranscriptHash::Poseidon`] hashes the encoding in 31-byte chunks
+    ///   with the permutation of the Poseidon transcript, and returns the
+    ///   canonical little-endian encoding of the resulting scalar, so that it
+    ///   can be exposed as a public input.
+    pub fn fingerprint(&self, hash: TranscriptHash) -> [u8; 32] {
+        use ff::PrimeField;
+        use sha3::{Digest, Keccak256};
+
+        const PERSONAL: &[u8; 16] = b"Halo2-Circuit-ID";
+
+        let s = format!("{:?}", self.pinned());
+        let len = (s.len() as u64).to_le_bytes();
+
+        let mut fingerprint = [0u8; 32];
+        match hash {
+            TranscriptHash::Blake2b => {
+                let mut hasher = Blake2bParams::new()
+                    .hash_length(32)
+                    .personal(PERSONAL)
+                    .to_state();
+                hasher.update(&len);
+                hasher.update(s.as_bytes());
+                fingerprint.copy_from_slice(hasher.finalize().as_bytes());
+            }
+            TranscriptHash::Keccak256 => {
+                let mut hasher = Keccak256::new();
+                hasher.update(&len);
+                hasher.update(s.as_bytes());
+                fingerprint.copy_from_slice(&hasher.finalize());
+            }
+            TranscriptHash::Poseidon => {
+                let scalar: C::Scalar =
+                    crate::transcript::poseidon::hash_bytes(PERSONAL, s.as_bytes());
+                for (byte, repr) in fingerprint.iter_mut().zip(scalar.to_repr().as_ref()) {
+                    *byte = *repr;
+                }
+            }
+        }
+        fingerprint
+    }
+
+    pub(crate) fn domain(&self) -> &EvaluationDomain<C::Scalar> {
+        &self.domain
+    }
+
+    pub(crate) fn cs(&self) -> &ConstraintSystem<C::Scalar> {
+        &self.cs
+    }
+
+    pub(crate) fn fixed_commitments(&self) -> &[C] {
+        &self.fixed_commitments
+    }
+
+    pub(crate) fn permutation_commitments(&self) -> &[C] {
+        self.permutation.commitments()
     }
 
     /// Obtains a pinned representation of this verification key that contains
@@ -117,6 +211,16 @@ impl<C: CurveAffine> VerifyingKey<C> {
 
 /// Minimal representation of a verification key that can be used to identify
 /// its active contents.
+///
+/// The pinned key covers the curve and field moduli, the evaluation domain,
+/// the constraint system (gates, lookups, permutation columns, ...), and the
+/// commitments to the fixed columns and permutation. Its `Debug` output is
+/// what [`VerifyingKey::hash_into`] commits to, so two circuits have the same
+/// pinned representation exactly when their proofs are interchangeable.
+///
+/// A pretty-printed [snapshot](Self::to_snapshot) of it can be checked into a
+/// downstream repository, and compared against on every build to catch
+/// accidental changes to a circuit; see [`crate::dev::assert_pinned_snapshot`].
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct PinnedVerificationKey<'a, C: CurveAffine> {
@@ -127,6 +231,96 @@ pub struct PinnedVerificationKey<'a, C: CurveAffine> {
     fixed_commitments: &'a Vec<C>,
     permutation: &'a permutation::VerifyingKey<C>,
 }
+
+impl<'a, C: CurveAffine> PinnedVerificationKey<'a, C> {
+    /// Returns the pretty-printed snapshot of this pinned verification key,
+    /// terminated by a newline.
+    pub fn to_snapshot(&self) -> String {
+        format!("{:#?}\n", self)
+    }
+
+    /// Writes the snapshot of this pinned verification key to a buffer.
+    pub fn write_snapshot<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.to_snapshot().as_bytes())
+    }
+
+    /// Returns a JSON representation of this pinned verification key, terminated
+    /// by a newline.
+    ///
+    /// Unlike the [snapshot](Self::to_snapshot), which is the `Debug` output
+    /// that the verifying key is hashed from, this is meant to be read by tools
+    /// and reviewers. Each gate lists its constraints by name, and constraints,
+    /// lookups and shuffles are written as polynomials over the variables of
+    /// [`crate::dev::CircuitConstraints`], such as `A0_m1` for advice column 0
+    /// at the previous row. Columns are written as `"advice 0"`, field elements
+    /// and moduli as big-endian hex, and commitments as the hex of their
+    /// encoding. The fields are always written in the same order, one per line,
+    /// so the JSON of two releases of a circuit can be compared with `diff`.
+    pub fn to_json(&self) -> String {
+        format!("{}\n", json::pinned_vk(self))
+    }
+
+    /// Writes the JSON representation of this pinned verification key to a
+    /// buffer.
+    pub fn write_json<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(self.to_json().as_bytes())
+    }
+
+    /// Compares this pinned verification key against a previously written
+    /// snapshot, returning the first differing line if they don't match.
+    ///
+    /// Line endings are normalized, so a snapshot checked out with `\r\n`
+    /// endings still matches.
+    pub fn check_snapshot(&self, snapshot: &str) -> Result<(), SnapshotMismatch> {
+        let actual = self.to_snapshot();
+        let mut expected_lines = snapshot.lines();
+        let mut actual_lines = actual.lines();
+
+        let mut line = 1;
+        loop {
+            match (expected_lines.next(), actual_lines.next()) {
+                (None, None) => return Ok(()),
+                (expected, actual) if expected != actual => {
+                    return Err(SnapshotMismatch {
+                        line,
+                        expected: expected.map(String::from),
+                        actual: actual.map(String::from),
+                    })
+                }
+                _ => line += 1,
+            }
+        }
+    }
+}
+
+/// The first difference between a [`PinnedVerificationKey`] and a snapshot it
+/// was checked against.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SnapshotMismatch {
+    /// The 1-based line number at which the snapshots diverge.
+    pub line: usize,
+    /// The line in the snapshot, or `None` if the snapshot ended early.
+    pub expected: Option<String>,
+    /// The line in the pinned verification key, or `None` if it ended early.
+    pub actual: Option<String>,
+}
+
+impl std::fmt::Display for SnapshotMismatch {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "pinned verification key differs at line {}:", self.line)?;
+        writeln!(
+            f,
+            "  expected: {}",
+            self.expected.as_deref().unwrap_or("<end of snapshot>")
+        )?;
+        write!(
+            f,
+            "  actual:   {}",
+            self.actual.as_deref().unwrap_or("<end of snapshot>")
+        )
+    }
+}
+
 /// This is a proving key which allows for the creation of proofs for a
 /// particular circuit.
 #[derive(Debug)]
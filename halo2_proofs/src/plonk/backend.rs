@@ -0,0 +1,343 @@
+use rand_core::RngCore;
+use std::{fmt, io, str::FromStr};
+
+use super::{create_proof, verify_proof, Circuit, Error, ProvingKey, SingleVerifier, VerifyingKey};
+use crate::arithmetic::{CurveAffine, MultiMillerLoop};
+use crate::poly::commitment::{Params, ParamsVerifier};
+use crate::transcript::{
+    keccak::{Keccak256Read, Keccak256Write},
+    poseidon::{PoseidonRead, PoseidonWrite},
+    Blake2bRead, Blake2bWrite, Challenge255,
+};
+
+/// The hash function that a proof's transcript is built with.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TranscriptHash {
+    /// BLAKE2b, as used by [`Blake2bWrite`] and [`Blake2bRead`].
+    Blake2b,
+    /// Keccak256, as used by [`Keccak256Write`] and [`Keccak256Read`], which is
+    /// cheap to verify on Ethereum.
+    Keccak256,
+    /// Poseidon, as used by [`PoseidonWrite`] and [`PoseidonRead`], which is cheap to
+    /// verify inside a circuit.
+    Poseidon,
+}
+
+impl TranscriptHash {
+    fn name(self) -> &'static str {
+        match self {
+            TranscriptHash::Blake2b => "blake2b",
+            TranscriptHash::Keccak256 => "keccak256",
+            TranscriptHash::Poseidon => "poseidon",
+        }
+    }
+}
+
+impl fmt::Display for TranscriptHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for TranscriptHash {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        [
+            TranscriptHash::Blake2b,
+            TranscriptHash::Keccak256,
+            TranscriptHash::Poseidon,
+        ]
+        .iter()
+        .copied()
+        .find(|hash| hash.name() == s)
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("unknown transcript hash {:?}", s),
+            )
+        })
+    }
+}
+
+/// Creates and verifies proofs with a transcript chosen at runtime, so that
+/// callers don't have to name the transcript and verification strategy types
+/// themselves. The commitment scheme is that of the params, which can be chosen
+/// at runtime too.
+///
+/// Proofs are verified one at a time with [`SingleVerifier`]; batch verification
+/// needs [`verify_proof`] with a [`BatchVerifier`](super::BatchVerifier).
+///
+/// ```ignore
+/// let params = params.with_scheme(config.scheme.parse()?);
+/// let params_verifier = params.verifier(1)?;
+/// let backend = Halo2Backend::new(config.transcript.parse()?);
+/// let proof = backend.create_proof(&params, &pk, &[circuit], instances, OsRng)?;
+/// backend.verify_proof(&params_verifier, pk.get_vk(), instances, &proof)?;
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Halo2Backend {
+    transcript: TranscriptHash,
+}
+
+impl Halo2Backend {
+    /// Constructs a backend with the given transcript hash.
+    pub fn new(transcript: TranscriptHash) -> Self {
+        Halo2Backend { transcript }
+    }
+
+    /// Returns the transcript hash of this backend.
+    pub fn transcript(&self) -> TranscriptHash {
+        self.transcript
+    }
+
+    /// Creates a proof for the given circuits and instances, and returns its bytes.
+    /// See [`create_proof`].
+    pub fn create_proof<C: CurveAffine, ConcreteCircuit: Circuit<C::Scalar>, R: RngCore>(
+        &self,
+        params: &Params<C>,
+        pk: &ProvingKey<C>,
+        circuits: &[ConcreteCircuit],
+        instances: &[&[&[C::Scalar]]],
+        rng: R,
+    ) -> Result<Vec<u8>, Error> {
+        match self.transcript {
+            TranscriptHash::Blake2b => {
+                let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+                create_proof(params, pk, circuits, instances, rng, &mut transcript)?;
+                Ok(transcript.finalize())
+            }
+            TranscriptHash::Keccak256 => {
+                let mut transcript = Keccak256Write::<_, _, Challenge255<_>>::init(vec![]);
+                create_proof(params, pk, circuits, instances, rng, &mut transcript)?;
+                Ok(transcript.finalize())
+            }
+            TranscriptHash::Poseidon => {
+                let mut transcript = PoseidonWrite::init(vec![]);
+                create_proof(params, pk, circuits, instances, rng, &mut transcript)?;
+                Ok(transcript.finalize())
+            }
+        }
+    }
+
+    /// Verifies a proof created by [`Halo2Backend::create_proof`] with the same
+    /// backend, and params with the same commitment scheme. See [`verify_proof`].
+    pub fn verify_proof<E: MultiMillerLoop>(
+        &self,
+        params: &ParamsVerifier<E>,
+        vk: &VerifyingKey<E::G1Affine>,
+        instances: &[&[&[E::Scalar]]],
+        proof: &[u8],
+    ) -> Result<(), Error> {
+        let strategy = SingleVerifier::new(params);
+        match self.transcript {
+            TranscriptHash::Blake2b => {
+                let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+                verify_proof(params, vk, strategy, instances, &mut transcript)
+            }
+            TranscriptHash::Keccak256 => {
+                let mut transcript = Keccak256Read::<_, _, Challenge255<_>>::init(proof);
+                verify_proof(params, vk, strategy, instances, &mut transcript)
+            }
+            TranscriptHash::Poseidon => {
+                let mut transcript = PoseidonRead::init(proof);
+                verify_proof(params, vk, strategy, instances, &mut transcript)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+    use rand_core::OsRng;
+
+    use super::{Halo2Backend, TranscriptHash};
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        plonk::{
+            keygen_pk, keygen_vk, verify_proof, Advice, BatchVerifier, Circuit, Column,
+            ConstraintSystem, Error, Selector,
+        },
+        poly::{
+            commitment::{CommitmentScheme, Params},
+            Rotation,
+        },
+        transcript::{Blake2bRead, Challenge255},
+    };
+
+    #[derive(Clone)]
+    struct SquareConfig {
+        q: Selector,
+        a: Column<Advice>,
+        b: Column<Advice>,
+    }
+
+    /// Constrains `b = a^2` on the first row.
+    #[derive(Default)]
+    struct SquareCircuit {
+        a: Option<Fp>,
+    }
+
+    impl Circuit<Fp> for SquareCircuit {
+        type Config = SquareConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let config = SquareConfig {
+                q: meta.selector(),
+                a: meta.advice_column(),
+                b: meta.advice_column(),
+            };
+
+            meta.create_gate("square", |meta| {
+                let q = meta.query_selector(config.q);
+                let a = meta.query_advice(config.a, Rotation::cur());
+                let b = meta.query_advice(config.b, Rotation::cur());
+
+                vec![q * (a.clone() * a - b)]
+            });
+
+            config
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "square",
+                |mut region| {
+                    config.q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", config.a, 0, || self.a.ok_or(Error::Synthesis))?;
+                    region.assign_advice(
+                        || "b",
+                        config.b,
+                        0,
+                        || self.a.map(|a| a.square()).ok_or(Error::Synthesis),
+                    )?;
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    #[test]
+    fn transcripts() {
+        const K: u32 = 4;
+
+        let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+        let params_verifier = params.verifier::<Bn256>(0).unwrap();
+        let vk = keygen_vk(&params, &SquareCircuit::default()).unwrap();
+        let pk = keygen_pk(&params, vk, &SquareCircuit::default()).unwrap();
+        let instances: &[&[&[Fp]]] = &[&[]];
+
+        let hashes = [
+            TranscriptHash::Blake2b,
+            TranscriptHash::Keccak256,
+            TranscriptHash::Poseidon,
+        ];
+        for hash in hashes.iter() {
+            assert_eq!(hash.to_string().parse::<TranscriptHash>().unwrap(), *hash);
+
+            let backend = Halo2Backend::new(*hash);
+            let proof = backend
+                .create_proof(
+                    &params,
+                    &pk,
+                    &[SquareCircuit {
+                        a: Some(Fp::from(3)),
+                    }],
+                    instances,
+                    OsRng,
+                )
+                .unwrap();
+            for other in hashes.iter() {
+                let other = Halo2Backend::new(*other);
+                let result = other.verify_proof(&params_verifier, pk.get_vk(), instances, &proof);
+                assert_eq!(result.is_ok(), other == backend);
+            }
+        }
+    }
+
+    #[test]
+    fn schemes() {
+        const K: u32 = 4;
+
+        let mut params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+        let vk = keygen_vk(&params, &SquareCircuit::default()).unwrap();
+        let pk = keygen_pk(&params, vk, &SquareCircuit::default()).unwrap();
+        let instances: &[&[&[Fp]]] = &[&[]];
+        let backend = Halo2Backend::new(TranscriptHash::Blake2b);
+
+        let schemes = [CommitmentScheme::KzgGwc, CommitmentScheme::KzgShplonk];
+        for scheme in schemes.iter() {
+            params = params.with_scheme(*scheme);
+            let proof = backend
+                .create_proof(
+                    &params,
+                    &pk,
+                    &[SquareCircuit {
+                        a: Some(Fp::from(3)),
+                    }],
+                    instances,
+                    OsRng,
+                )
+                .unwrap();
+
+            // The proof only verifies with params of the same scheme.
+            for other in schemes.iter() {
+                let params_verifier = params.verifier::<Bn256>(0).unwrap().with_scheme(*other);
+                let result = backend.verify_proof(&params_verifier, pk.get_vk(), instances, &proof);
+                assert_eq!(result.is_ok(), other == scheme);
+            }
+        }
+    }
+
+    #[test]
+    fn strategies_outlive_params() {
+        const K: u32 = 4;
+
+        // The strategy doesn't borrow the verifier params it is constructed from.
+        fn batch_verifier(params: &Params<G1Affine>) -> BatchVerifier<Bn256, OsRng> {
+            BatchVerifier::new(&params.verifier::<Bn256>(0).unwrap(), OsRng)
+        }
+
+        let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+        let vk = keygen_vk(&params, &SquareCircuit::default()).unwrap();
+        let pk = keygen_pk(&params, vk, &SquareCircuit::default()).unwrap();
+        let instances: &[&[&[Fp]]] = &[&[]];
+        let strategy = batch_verifier(&params);
+
+        let backend = Halo2Backend::new(TranscriptHash::Blake2b);
+        let proof = backend
+            .create_proof(
+                &params,
+                &pk,
+                &[SquareCircuit {
+                    a: Some(Fp::from(3)),
+                }],
+                instances,
+                OsRng,
+            )
+            .unwrap();
+
+        let params_verifier = params.verifier::<Bn256>(0).unwrap();
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        let strategy = verify_proof(
+            &params_verifier,
+            pk.get_vk(),
+            strategy,
+            instances,
+            &mut transcript,
+        )
+        .unwrap();
+        assert!(strategy.finalize());
+    }
+}
@@ -3,14 +3,17 @@ use core::ops::{Add, Mul};
 use ff::Field;
 use std::{
     convert::TryFrom,
-    ops::{Neg, Sub},
+    iter::{Product, Sum},
+    ops::{Neg, Range, Sub},
+    sync::Arc,
 };
 
-use super::{lookup, permutation, Assigned, Error};
+use super::{lookup, permutation, shuffle, Assigned, Error};
 use crate::circuit::Layouter;
 use crate::{circuit::Region, poly::Rotation};
 
 mod compress_selectors;
+mod gates;
 
 /// A column type
 pub trait ColumnType:
@@ -360,6 +363,28 @@ pub trait Assignment<F: Field> {
         A: FnOnce() -> AR,
         AR: Into<String>;
 
+    /// Assigns `values` to consecutive rows of a fixed `column`, starting at
+    /// `row`.
+    ///
+    /// By default each value is assigned with [`Assignment::assign_fixed`];
+    /// backends that store fixed columns directly copy them in bulk.
+    fn assign_fixed_column<A, AR>(
+        &mut self,
+        annotation: A,
+        column: Column<Fixed>,
+        row: usize,
+        values: &[Assigned<F>],
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        for (offset, value) in values.iter().enumerate() {
+            self.assign_fixed(&annotation, column, row + offset, || Ok(*value))?;
+        }
+        Ok(())
+    }
+
     /// Assign two cells to have the same value
     fn copy(
         &mut self,
@@ -377,6 +402,42 @@ pub trait Assignment<F: Field> {
         to: Option<Assigned<F>>,
     ) -> Result<(), Error>;
 
+    /// Records `value` as an output of the circuit, named `name`.
+    ///
+    /// Not intended for downstream consumption; use [`Layouter::output`] instead.
+    ///
+    /// [`Layouter::output`]: crate::circuit::Layouter#method.output
+    fn output<A, AR>(&mut self, _name: A, _value: Option<Assigned<F>>) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Only backends that collect the outputs care about them.
+        Ok(())
+    }
+
+    /// Records that the cell in `column` at `row` is asserted to be equal to the
+    /// cell it is copied to, as described by `annotation`.
+    ///
+    /// Not intended for downstream consumption; use [`Region::assert_equal_constant`]
+    /// or [`Region::assert_equal`] instead.
+    ///
+    /// [`Region::assert_equal_constant`]: crate::circuit::Region::assert_equal_constant
+    /// [`Region::assert_equal`]: crate::circuit::Region::assert_equal
+    fn record_assertion<A, AR>(
+        &mut self,
+        _annotation: A,
+        _column: Column<Any>,
+        _row: usize,
+    ) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Only backends that report failed assertions care about them.
+        Ok(())
+    }
+
     /// Creates a new (sub)namespace and enters into it.
     ///
     /// Not intended for downstream consumption; use [`Layouter::namespace`] instead.
@@ -442,6 +503,43 @@ pub trait Circuit<F: Field> {
     fn synthesize(&self, config: Self::Config, layouter: impl Layouter<F>) -> Result<(), Error>;
 }
 
+/// A source of advice values that is independent of [`Circuit::synthesize`].
+///
+/// The constraint system, fixed columns and copy constraints of a circuit are
+/// still fixed at keygen time by a [`Circuit`] implementation. A
+/// `WitnessCalculator` only has to produce the advice columns for that same
+/// layout, which allows witness generation to be driven by e.g. an interpreter
+/// or VM trace rather than by the layouter. See
+/// [`create_proof_with_witness`](crate::plonk::create_proof_with_witness).
+pub trait WitnessCalculator<F: Field> {
+    /// Computes the advice columns given the instance columns of a single
+    /// proof.
+    ///
+    /// The result must contain one vector per advice column, in column index
+    /// order. Each vector holds the values of the first rows of that column
+    /// and must not be longer than `usable_rows`; any remaining usable rows
+    /// are set to zero.
+    fn calculate_witness(
+        &self,
+        instances: &[&[F]],
+        usable_rows: usize,
+    ) -> Result<Vec<Vec<Assigned<F>>>, Error>;
+
+    /// Computes the configuration columns (see
+    /// [`ConstraintSystem::configuration_column`]) of a single proof, in the
+    /// order in which they were allocated and with the same shape as
+    /// [`WitnessCalculator::calculate_witness`].
+    ///
+    /// Returns `None` by default, which keeps the values assigned at keygen.
+    fn calculate_configuration(
+        &self,
+        _instances: &[&[F]],
+        _usable_rows: usize,
+    ) -> Result<Option<Vec<Vec<Assigned<F>>>>, Error> {
+        Ok(None)
+    }
+}
+
 /// Low-degree expression representing an identity that must hold over the committed columns.
 #[derive(Clone, Debug)]
 pub enum Expression<F> {
@@ -802,6 +900,25 @@ impl<F: Field> Expression<F> {
         self.clone() * self
     }
 
+    /// Raises this expression to the power `exponent`, by repeated squaring.
+    pub fn pow(self, mut exponent: u32) -> Self {
+        let mut acc: Option<Self> = None;
+        let mut base = self;
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                acc = Some(match acc {
+                    Some(acc) => acc * base.clone(),
+                    None => base.clone(),
+                });
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.square();
+            }
+        }
+        acc.unwrap_or(Expression::Constant(F::one()))
+    }
+
     /// Returns whether or not this expression contains a simple `Selector`.
     fn contains_simple_selector(&self) -> bool {
         self.evaluate(
@@ -889,6 +1006,20 @@ impl<F: Field> Mul<F> for Expression<F> {
     }
 }
 
+impl<F: Field> Sum for Expression<F> {
+    fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, x| acc + x)
+            .unwrap_or(Expression::Constant(F::zero()))
+    }
+}
+
+impl<F: Field> Product for Expression<F> {
+    fn product<I: Iterator<Item = Self>>(iter: I) -> Self {
+        iter.reduce(|acc, x| acc * x)
+            .unwrap_or(Expression::Constant(F::one()))
+    }
+}
+
 /// Represents an index into a vector where each entry corresponds to a distinct
 /// point that polynomials are queried at.
 #[derive(Copy, Clone, Debug)]
@@ -897,11 +1028,23 @@ pub(crate) struct PointIndex(pub usize);
 /// A "virtual cell" is a PLONK cell that has been queried at a particular relative offset
 /// within a custom gate.
 #[derive(Clone, Debug)]
-pub(crate) struct VirtualCell {
+pub struct VirtualCell {
     pub(crate) column: Column<Any>,
     pub(crate) rotation: Rotation,
 }
 
+impl VirtualCell {
+    /// Returns the column that is queried.
+    pub fn column(&self) -> Column<Any> {
+        self.column
+    }
+
+    /// Returns the rotation at which the column is queried.
+    pub fn rotation(&self) -> Rotation {
+        self.rotation
+    }
+}
+
 impl<Col: Into<Column<Any>>> From<(Col, Rotation)> for VirtualCell {
     fn from((column, rotation): (Col, Rotation)) -> Self {
         VirtualCell {
@@ -938,8 +1081,93 @@ impl<F: Field> From<Expression<F>> for Vec<Constraint<F>> {
     }
 }
 
+/// A set of polynomial constraints with a common selector, which multiplies
+/// each of them.
+///
+/// ```
+/// use ff::Field;
+/// use halo2_proofs::{
+///     plonk::{ConstraintSystem, Constraints, Expression},
+///     poly::Rotation,
+/// };
+/// use pairing::bn256::Fr;
+///
+/// let mut meta = ConstraintSystem::<Fr>::default();
+/// let a = meta.advice_column();
+/// let b = meta.advice_column();
+/// let c = meta.advice_column();
+/// let s = meta.selector();
+///
+/// meta.create_gate("ternary", |meta| {
+///     let next = meta.query_advice(a, Rotation::next());
+///     let a = meta.query_advice(a, Rotation::cur());
+///     let b = meta.query_advice(b, Rotation::cur());
+///     let c = meta.query_advice(c, Rotation::cur());
+///     let s = meta.query_selector(s);
+///
+///     let one_minus_a = Expression::Constant(Fr::one()) - a.clone();
+///
+///     Constraints::with_selector(
+///         s,
+///         [
+///             ("a is boolean", a.clone() * one_minus_a.clone()),
+///             ("next == a ? b : c", next - (a * b + one_minus_a * c)),
+///         ],
+///     )
+/// });
+/// ```
+#[derive(Debug)]
+pub struct Constraints<F: Field, C: Into<Constraint<F>>, Iter: IntoIterator<Item = C>> {
+    selector: Expression<F>,
+    constraints: Iter,
+}
+
+impl<F: Field, C: Into<Constraint<F>>, Iter: IntoIterator<Item = C>> Constraints<F, C, Iter> {
+    /// Constructs a set of constraints that are controlled by the given selector.
+    ///
+    /// Each constraint `c` in `iterator` will be converted into the constraint
+    /// `selector * c`.
+    pub fn with_selector(selector: Expression<F>, constraints: Iter) -> Self {
+        Constraints {
+            selector,
+            constraints,
+        }
+    }
+}
+
+fn apply_selector_to_constraint<F: Field, C: Into<Constraint<F>>>(
+    (selector, c): (Expression<F>, C),
+) -> Constraint<F> {
+    let constraint: Constraint<F> = c.into();
+    Constraint {
+        name: constraint.name,
+        poly: selector * constraint.poly,
+    }
+}
+
+type ApplySelectorToConstraint<F, C> = fn((Expression<F>, C)) -> Constraint<F>;
+type ConstraintsIterator<F, C, I> = std::iter::Map<
+    std::iter::Zip<std::iter::Repeat<Expression<F>>, I>,
+    ApplySelectorToConstraint<F, C>,
+>;
+
+impl<F: Field, C: Into<Constraint<F>>, Iter: IntoIterator<Item = C>> IntoIterator
+    for Constraints<F, C, Iter>
+{
+    type Item = Constraint<F>;
+    type IntoIter = ConstraintsIterator<F, C, Iter::IntoIter>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        std::iter::repeat(self.selector)
+            .zip(self.constraints.into_iter())
+            .map(apply_selector_to_constraint)
+    }
+}
+
+/// A custom gate of a [`ConstraintSystem`], as returned by
+/// [`ConstraintSystem::gates`].
 #[derive(Clone, Debug)]
-pub(crate) struct Gate<F: Field> {
+pub struct Gate<F: Field> {
     name: &'static str,
     constraint_names: Vec<&'static str>,
     polys: Vec<Expression<F>>,
@@ -950,23 +1178,30 @@ pub(crate) struct Gate<F: Field> {
 }
 
 impl<F: Field> Gate<F> {
-    pub(crate) fn name(&self) -> &'static str {
+    /// Returns the name of the gate.
+    pub fn name(&self) -> &'static str {
         self.name
     }
 
-    pub(crate) fn constraint_name(&self, constraint_index: usize) -> &'static str {
+    /// Returns the name of the constraint at `constraint_index`, which is empty if
+    /// the constraint was not given a name.
+    pub fn constraint_name(&self, constraint_index: usize) -> &'static str {
         self.constraint_names[constraint_index]
     }
 
-    pub(crate) fn polynomials(&self) -> &[Expression<F>] {
+    /// Returns the polynomial constraints of the gate, each of which must be zero
+    /// on every row.
+    pub fn polynomials(&self) -> &[Expression<F>] {
         &self.polys
     }
 
-    pub(crate) fn queried_selectors(&self) -> &[Selector] {
+    /// Returns the selectors queried by the gate.
+    pub fn queried_selectors(&self) -> &[Selector] {
         &self.queried_selectors
     }
 
-    pub(crate) fn queried_cells(&self) -> &[VirtualCell] {
+    /// Returns the cells queried by the gate, not including selectors.
+    pub fn queried_cells(&self) -> &[VirtualCell] {
         &self.queried_cells
     }
 }
@@ -996,33 +1231,123 @@ pub struct ConstraintSystem<F: Field> {
     // input expressions and a sequence of table expressions involved in the lookup.
     pub(crate) lookups: Vec<lookup::Argument<F>>,
 
+    // Vector of shuffle arguments, where each corresponds to a sequence of
+    // input expressions and a sequence of shuffle expressions involved in the shuffle.
+    pub(crate) shuffles: Vec<shuffle::Argument<F>>,
+
     // Vector of fixed columns, which can be used to store constant values
     // that are copied into advice columns.
     pub(crate) constants: Vec<Column<Fixed>>,
 
+    // Vector of fixed columns whose values are supplied by the prover for each
+    // proof, rather than being fixed at keygen time.
+    pub(crate) configuration_columns: Vec<Column<Fixed>>,
+
+    // Vector of advice columns whose unusable rows are not filled with random
+    // blinding factors.
+    pub(crate) unblinded_advice_columns: Vec<Column<Advice>>,
+
+    // Whether the prover's witness polynomials are blinded.
+    pub(crate) zero_knowledge: bool,
+
+    pub(crate) minimum_blinding_factors: Option<usize>,
+
     pub(crate) minimum_degree: Option<usize>,
+
+    // The degree that gates and arguments may not exceed, if any.
+    maximum_degree: Option<usize>,
+
+    // Tables of the range checks, with the number of bits each one checks.
+    // They are filled at keygen rather than by the circuit.
+    pub(crate) range_tables: Vec<(TableColumn, usize)>,
+
+    // Tables registered with `lookup_table_from`, which are also filled at keygen.
+    pub(crate) generated_tables: Vec<GeneratedTable<F>>,
+}
+
+/// A lookup table column whose values are computed from a closure at keygen.
+#[derive(Clone)]
+pub(crate) struct GeneratedTable<F> {
+    pub(crate) column: TableColumn,
+    pub(crate) rows: usize,
+    pub(crate) value: Arc<dyn Fn(usize) -> F + Send + Sync>,
+}
+
+impl<F> std::fmt::Debug for GeneratedTable<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        f.debug_struct("GeneratedTable")
+            .field("column", &self.column)
+            .field("rows", &self.rows)
+            .finish()
+    }
 }
 
 /// Represents the minimal parameters that determine a `ConstraintSystem`.
-#[allow(dead_code)]
-#[derive(Debug)]
 pub struct PinnedConstraintSystem<'a, F: Field> {
-    num_fixed_columns: &'a usize,
-    num_advice_columns: &'a usize,
-    num_instance_columns: &'a usize,
-    num_selectors: &'a usize,
-    selector_map: &'a [Column<Fixed>],
-    gates: PinnedGates<'a, F>,
-    advice_queries: &'a Vec<(Column<Advice>, Rotation)>,
-    instance_queries: &'a Vec<(Column<Instance>, Rotation)>,
-    fixed_queries: &'a Vec<(Column<Fixed>, Rotation)>,
-    permutation: &'a permutation::Argument,
-    lookups: &'a Vec<lookup::Argument<F>>,
-    constants: &'a Vec<Column<Fixed>>,
-    minimum_degree: &'a Option<usize>,
+    pub(crate) num_fixed_columns: &'a usize,
+    pub(crate) num_advice_columns: &'a usize,
+    pub(crate) num_instance_columns: &'a usize,
+    pub(crate) num_selectors: &'a usize,
+    pub(crate) selector_map: &'a [Column<Fixed>],
+    pub(crate) gates: PinnedGates<'a, F>,
+    pub(crate) advice_queries: &'a Vec<(Column<Advice>, Rotation)>,
+    pub(crate) instance_queries: &'a Vec<(Column<Instance>, Rotation)>,
+    pub(crate) fixed_queries: &'a Vec<(Column<Fixed>, Rotation)>,
+    pub(crate) permutation: &'a permutation::Argument,
+    pub(crate) lookups: &'a Vec<lookup::Argument<F>>,
+    pub(crate) shuffles: &'a Vec<shuffle::Argument<F>>,
+    pub(crate) constants: &'a Vec<Column<Fixed>>,
+    pub(crate) configuration_columns: &'a Vec<Column<Fixed>>,
+    pub(crate) unblinded_advice_columns: &'a Vec<Column<Advice>>,
+    pub(crate) zero_knowledge: &'a bool,
+    pub(crate) minimum_blinding_factors: &'a Option<usize>,
+    pub(crate) minimum_degree: &'a Option<usize>,
 }
 
-struct PinnedGates<'a, F: Field>(&'a Vec<Gate<F>>);
+impl<'a, F: Field> std::fmt::Debug for PinnedConstraintSystem<'a, F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
+        let mut debug_struct = f.debug_struct("PinnedConstraintSystem");
+        debug_struct
+            .field("num_fixed_columns", self.num_fixed_columns)
+            .field("num_advice_columns", self.num_advice_columns)
+            .field("num_instance_columns", self.num_instance_columns)
+            .field("num_selectors", self.num_selectors)
+            .field("selector_map", &self.selector_map)
+            .field("gates", &self.gates)
+            .field("advice_queries", self.advice_queries)
+            .field("instance_queries", self.instance_queries)
+            .field("fixed_queries", self.fixed_queries)
+            .field("permutation", self.permutation)
+            .field("lookups", self.lookups);
+        // Shuffles are only pinned when present, so that the verifying keys of
+        // circuits without shuffle arguments are unchanged.
+        if !self.shuffles.is_empty() {
+            debug_struct.field("shuffles", self.shuffles);
+        }
+        debug_struct.field("constants", self.constants);
+        // Likewise for configuration columns.
+        if !self.configuration_columns.is_empty() {
+            debug_struct.field("configuration_columns", self.configuration_columns);
+        }
+        // Likewise for unblinded advice columns.
+        if !self.unblinded_advice_columns.is_empty() {
+            debug_struct.field("unblinded_advice_columns", self.unblinded_advice_columns);
+        }
+        // Likewise, zero knowledge is only pinned when it is disabled.
+        if !self.zero_knowledge {
+            debug_struct.field("zero_knowledge", self.zero_knowledge);
+        }
+        // Likewise for a minimum number of blinding factors.
+        if self.minimum_blinding_factors.is_some() {
+            debug_struct.field("minimum_blinding_factors", self.minimum_blinding_factors);
+        }
+        debug_struct
+            .field("minimum_degree", self.minimum_degree)
+            .finish()
+    }
+}
+
+pub(crate) struct PinnedGates<'a, F: Field>(pub(crate) &'a Vec<Gate<F>>);
 
 impl<'a, F: Field> std::fmt::Debug for PinnedGates<'a, F> {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> Result<(), std::fmt::Error> {
@@ -1047,8 +1372,16 @@ impl<F: Field> Default for ConstraintSystem<F> {
             instance_queries: Vec::new(),
             permutation: permutation::Argument::new(),
             lookups: Vec::new(),
+            shuffles: Vec::new(),
             constants: vec![],
+            configuration_columns: vec![],
+            unblinded_advice_columns: vec![],
+            zero_knowledge: true,
+            minimum_blinding_factors: None,
             minimum_degree: None,
+            maximum_degree: None,
+            range_tables: vec![],
+            generated_tables: vec![],
         }
     }
 }
@@ -1070,13 +1403,24 @@ impl<F: Field> ConstraintSystem<F> {
             instance_queries: &self.instance_queries,
             permutation: &self.permutation,
             lookups: &self.lookups,
+            shuffles: &self.shuffles,
             constants: &self.constants,
+            configuration_columns: &self.configuration_columns,
+            unblinded_advice_columns: &self.unblinded_advice_columns,
+            zero_knowledge: &self.zero_knowledge,
+            minimum_blinding_factors: &self.minimum_blinding_factors,
             minimum_degree: &self.minimum_degree,
         }
     }
 
     /// Enables this fixed column to be used for global constant assignments.
     ///
+    /// The floor planners assign each distinct constant once, packing them across all
+    /// of the columns enabled for constants. [`CircuitCost::constant_rows`] reports
+    /// how many rows they take up.
+    ///
+    /// [`CircuitCost::constant_rows`]: crate::dev::CircuitCost::constant_rows
+    ///
     /// # Side-effects
     ///
     /// The column will be equality-enabled.
@@ -1098,6 +1442,17 @@ impl<F: Field> ConstraintSystem<F> {
     ///
     /// `table_map` returns a map between input expressions and the table columns
     /// they need to match.
+    ///
+    /// The tuples of input and table expressions are compressed by the prover
+    /// with a random challenge that the verifier draws after the advice
+    /// commitments, and that all lookup and shuffle arguments share. There is
+    /// no API for gates to query challenges, so multi-column lookups should be
+    /// expressed as tuples here rather than compressed by the circuit itself.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the argument requires a degree above the maximum set with
+    /// [`ConstraintSystem::set_maximum_degree`].
     pub fn lookup(
         &mut self,
         name: &'static str,
@@ -1117,30 +1472,180 @@ impl<F: Field> ConstraintSystem<F> {
             })
             .collect();
 
+        let argument = lookup::Argument::new(name, table_map);
+        self.check_degree("lookup", name, argument.required_degree());
+
         let index = self.lookups.len();
 
-        self.lookups.push(lookup::Argument::new(name, table_map));
+        self.lookups.push(argument);
 
         index
     }
 
-    /// Add a lookup argument for some input expressions and table columns.
+    /// Add a lookup argument for some input expressions and table expressions.
     ///
-    /// `table_map` returns a map between input expressions and the table columns
+    /// `table_map` returns a map between input expressions and the table expressions
     /// they need to match.
     ///
-    /// This API allows any column type to be used as table columns.
+    /// This API allows any column type to be used in the table, so tables can be
+    /// built at proving time from advice or instance columns (for example, a table of
+    /// transactions that is only known to the prover). Every usable row of the table
+    /// expressions is part of the table, so a table built from advice cells should
+    /// usually be multiplied by a complex selector, making unused rows evaluate to a
+    /// zero row that inputs can also default to.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the argument requires a degree above the maximum set with
+    /// [`ConstraintSystem::set_maximum_degree`].
     pub fn lookup_any(
         &mut self,
         name: &'static str,
         table_map: impl FnOnce(&mut VirtualCells<'_, F>) -> Vec<(Expression<F>, Expression<F>)>,
     ) -> usize {
         let mut cells = VirtualCells::new(self);
-        let table_map = table_map(&mut cells);
+        let table_map = table_map(&mut cells)
+            .into_iter()
+            .map(|(input, table)| {
+                if input.contains_simple_selector() || table.contains_simple_selector() {
+                    panic!("expression containing simple selector supplied to lookup argument");
+                }
+
+                (input, table)
+            })
+            .collect();
+
+        let argument = lookup::Argument::new(name, table_map);
+        self.check_degree("lookup", name, argument.required_degree());
 
         let index = self.lookups.len();
 
-        self.lookups.push(lookup::Argument::new(name, table_map));
+        self.lookups.push(argument);
+
+        index
+    }
+
+    /// Adds a range check, constraining `input` to be less than `2^bits` on every
+    /// row, and returns the index of the lookup argument it is made with.
+    ///
+    /// The check is a lookup into a table of the values `0..2^bits`. The table is
+    /// allocated by the first range check of each number of bits, shared by the
+    /// others, and filled at keygen, so circuits don't assign it in `synthesize`.
+    /// It takes `2^bits` rows, and keygen returns `Error::NotEnoughRowsAvailable`
+    /// if the circuit has fewer usable rows; wider values can be decomposed into
+    /// limbs that are checked separately. As with any lookup, `input` must be in
+    /// the table on every row, so it is typically multiplied by a complex
+    /// selector, which makes it zero where the check doesn't apply.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `bits` is 32 or more, as such a table fits in no circuit.
+    pub fn range_check(
+        &mut self,
+        name: &'static str,
+        bits: usize,
+        input: impl FnOnce(&mut VirtualCells<'_, F>) -> Expression<F>,
+    ) -> usize {
+        assert!(bits < 32, "range checks are limited to 31 bits");
+
+        let table = match self.range_tables.iter().find(|(_, b)| *b == bits) {
+            Some((table, _)) => *table,
+            None => {
+                let table = self.lookup_table_column();
+                self.range_tables.push((table, bits));
+                table
+            }
+        };
+        self.lookup(name, |meta| vec![(input(meta), table)])
+    }
+
+    /// Returns the tables allocated by [`ConstraintSystem::range_check`], with
+    /// the number of bits each one checks.
+    pub fn range_tables(&self) -> &[(TableColumn, usize)] {
+        &self.range_tables
+    }
+
+    /// Returns the number of rows taken by the tables that are filled at keygen,
+    /// those of the range checks and those registered with
+    /// [`ConstraintSystem::lookup_table_from`].
+    pub(crate) fn keygen_table_rows(&self) -> usize {
+        self.range_tables
+            .iter()
+            .map(|(_, bits)| 1 << bits)
+            .chain(self.generated_tables.iter().map(|table| table.rows))
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Calls `assign` with the column, row and value of every cell of the tables
+    /// that are filled at keygen, or returns `Error::NotEnoughRowsAvailable` if
+    /// they don't fit in the usable rows of a circuit with `2^k` rows.
+    pub(crate) fn assign_keygen_tables(
+        &self,
+        k: u32,
+        mut assign: impl FnMut(Column<Fixed>, usize, F),
+    ) -> Result<(), Error> {
+        let usable_rows = self.usable_rows(k);
+        if self.keygen_table_rows() > usable_rows.end {
+            return Err(Error::not_enough_rows_available(k));
+        }
+        for (table, bits) in self.range_tables.iter() {
+            let mut value = F::zero();
+            for row in 0..1 << bits {
+                assign(table.inner(), row, value);
+                value += F::one();
+            }
+        }
+        for table in self.generated_tables.iter() {
+            for row in 0..table.rows {
+                assign(table.column.inner(), row, (table.value)(row));
+            }
+            // As with tables assigned by the circuit, the unused rows repeat the
+            // value at row 0.
+            if table.rows > 0 {
+                let default = (table.value)(0);
+                for row in table.rows..usable_rows.end {
+                    assign(table.column.inner(), row, default);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Add a shuffle argument for some input expressions and shuffle expressions.
+    ///
+    /// `shuffle_map` returns a map between input expressions and the shuffle
+    /// expressions they need to match. The argument proves that, over the usable
+    /// rows, the tuples of input values are a permutation of the tuples of
+    /// shuffle values.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the argument requires a degree above the maximum set with
+    /// [`ConstraintSystem::set_maximum_degree`].
+    pub fn shuffle(
+        &mut self,
+        name: &'static str,
+        shuffle_map: impl FnOnce(&mut VirtualCells<'_, F>) -> Vec<(Expression<F>, Expression<F>)>,
+    ) -> usize {
+        let mut cells = VirtualCells::new(self);
+        let shuffle_map = shuffle_map(&mut cells)
+            .into_iter()
+            .map(|(input, shuffle)| {
+                if input.contains_simple_selector() || shuffle.contains_simple_selector() {
+                    panic!("expression containing simple selector supplied to shuffle argument");
+                }
+
+                (input, shuffle)
+            })
+            .collect();
+
+        let argument = shuffle::Argument::new(name, shuffle_map);
+        self.check_degree("shuffle", name, argument.required_degree());
+
+        let index = self.shuffles.len();
+
+        self.shuffles.push(argument);
 
         index
     }
@@ -1252,12 +1757,46 @@ impl<F: Field> ConstraintSystem<F> {
         self.minimum_degree = Some(degree);
     }
 
+    /// Sets the maximum degree of the circuit. Gates and lookup and shuffle
+    /// arguments that would require a larger degree are then rejected as soon as
+    /// they are created, rather than making the extended domain, and so the
+    /// memory needed by keygen and the prover, larger than planned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the constraint system already requires a larger degree.
+    pub fn set_maximum_degree(&mut self, degree: usize) {
+        assert!(
+            self.degree() <= degree,
+            "the constraint system already has degree {}, above the maximum degree {}",
+            self.degree(),
+            degree
+        );
+        self.maximum_degree = Some(degree);
+    }
+
+    /// Panics if `degree`, required by the gate or argument `name`, is above the
+    /// maximum degree of the circuit.
+    fn check_degree(&self, kind: &str, name: &str, degree: usize) {
+        if let Some(maximum_degree) = self.maximum_degree {
+            assert!(
+                degree <= maximum_degree,
+                "{} \"{}\" has degree {}, above the maximum degree {}",
+                kind,
+                name,
+                degree,
+                maximum_degree
+            );
+        }
+    }
+
     /// Creates a new gate.
     ///
     /// # Panics
     ///
     /// A gate is required to contain polynomial constraints. This method will panic if
-    /// `constraints` returns an empty iterator.
+    /// `constraints` returns an empty iterator, or if one of the constraints has a
+    /// degree above the maximum set with [`ConstraintSystem::set_maximum_degree`].
     pub fn create_gate<C: Into<Constraint<F>>, Iter: IntoIterator<Item = C>>(
         &mut self,
         name: &'static str,
@@ -1278,6 +1817,11 @@ impl<F: Field> ConstraintSystem<F> {
             !polys.is_empty(),
             "Gates must contain at least one constraint."
         );
+        self.check_degree(
+            "gate",
+            name,
+            polys.iter().map(|poly| poly.degree()).max().unwrap(),
+        );
 
         self.gates.push(Gate {
             name,
@@ -1412,6 +1956,17 @@ impl<F: Field> ConstraintSystem<F> {
             replace_selectors(expr, &selector_replacements, true);
         }
 
+        // Substitute non-simple selectors for the real fixed columns in all
+        // shuffle expressions
+        for expr in self.shuffles.iter_mut().flat_map(|shuffle| {
+            shuffle
+                .input_expressions
+                .iter_mut()
+                .chain(shuffle.shuffle_expressions.iter_mut())
+        }) {
+            replace_selectors(expr, &selector_replacements, true);
+        }
+
         (self, polys)
     }
 
@@ -1440,6 +1995,34 @@ impl<F: Field> ConstraintSystem<F> {
         }
     }
 
+    /// Allocates a new lookup table column holding `value(i)` on each row `i`
+    /// in `0..rows`, and `value(0)` on the rows after them.
+    ///
+    /// Unlike a column allocated with [`ConstraintSystem::lookup_table_column`],
+    /// the table is filled at keygen (and by `MockProver`) rather than by the
+    /// circuit, so `synthesize` must not assign it. Tables with several
+    /// columns are made by registering each column with the same number of
+    /// rows. Keygen returns `Error::NotEnoughRowsAvailable` if the circuit has
+    /// fewer than `rows` usable rows.
+    ///
+    /// ```ignore
+    /// let input = meta.lookup_table_from(256, |i| F::from(i as u64));
+    /// let square = meta.lookup_table_from(256, |i| F::from((i * i) as u64));
+    /// ```
+    pub fn lookup_table_from(
+        &mut self,
+        rows: usize,
+        value: impl Fn(usize) -> F + Send + Sync + 'static,
+    ) -> TableColumn {
+        let column = self.lookup_table_column();
+        self.generated_tables.push(GeneratedTable {
+            column,
+            rows,
+            value: Arc::new(value),
+        });
+        column
+    }
+
     /// Allocate a new fixed column
     pub fn fixed_column(&mut self) -> Column<Fixed> {
         let tmp = Column {
@@ -1450,7 +2033,35 @@ impl<F: Field> ConstraintSystem<F> {
         tmp
     }
 
+    /// Allocate a new fixed column whose values are supplied at proving time.
+    ///
+    /// The values assigned to a configuration column during keygen are only
+    /// defaults. When creating a proof, the values assigned to it by the
+    /// circuit are committed to in the proof instead, and the verifier checks
+    /// that commitment against the one it is given by
+    /// [`verify_proof_with_configuration`](crate::plonk::verify_proof_with_configuration).
+    /// This allows a circuit to be parameterized by a small per-proof
+    /// configuration without generating new keys.
+    pub fn configuration_column(&mut self) -> Column<Fixed> {
+        let column = self.fixed_column();
+        self.configuration_columns.push(column);
+        column
+    }
+
     /// Allocate a new advice column
+    ///
+    /// The prover commits to all advice columns at once, before it draws any
+    /// challenge, and expressions can't refer to challenges. Gates and lookups
+    /// can therefore query any advice column together with any other, with no
+    /// phase ordering to respect.
+    ///
+    /// Each advice column has its own commitment in the proof. Grouping `m`
+    /// columns under one interleaved commitment `P(X) = sum_j X^j a_j(X^m)`,
+    /// as fflonk does, isn't implemented: it needs params `m` times as large,
+    /// and a verifier that squeezes `z` and sets `x = z^m`, so as to open `P`
+    /// at the `m`-th roots `z ω_m^i` of `x` and recover each `a_j(x)` from
+    /// those evaluations. The vanishing and multiopening arguments would change
+    /// accordingly, and the group would save commitments but not evaluations.
     pub fn advice_column(&mut self) -> Column<Advice> {
         let tmp = Column {
             index: self.num_advice_columns,
@@ -1461,6 +2072,19 @@ impl<F: Field> ConstraintSystem<F> {
         tmp
     }
 
+    /// Allocate a new advice column that is not blinded.
+    ///
+    /// The prover fills the unusable rows of other advice columns with random
+    /// blinding factors, but leaves them zero in this column. Its commitment is then
+    /// determined by the values assigned to it, so it can be compared across proofs,
+    /// or reused as a commitment to those values elsewhere. The proof reveals
+    /// evaluations of the column, so it is not zero-knowledge for the values in it.
+    pub fn unblinded_advice_column(&mut self) -> Column<Advice> {
+        let column = self.advice_column();
+        self.unblinded_advice_columns.push(column);
+        column
+    }
+
     /// Allocate a new instance column
     pub fn instance_column(&mut self) -> Column<Instance> {
         let tmp = Column {
@@ -1471,6 +2095,20 @@ impl<F: Field> ConstraintSystem<F> {
         tmp
     }
 
+    /// Returns the queries of instance columns at non-zero rotations.
+    ///
+    /// These are supported, but unlike queries at the current row they add to the
+    /// cost of verification: each one is an extra evaluation in every proof, and the
+    /// rotation can add a point set to the multiopening argument. Key generation
+    /// logs a warning for each circuit that makes such queries.
+    pub fn rotated_instance_queries(&self) -> Vec<(Column<Instance>, Rotation)> {
+        self.instance_queries
+            .iter()
+            .filter(|(_, rotation)| rotation.0 != 0)
+            .copied()
+            .collect()
+    }
+
     /// Compute the degree of the constraint system (the maximum degree of all
     /// constraints).
     pub fn degree(&self) -> usize {
@@ -1489,6 +2127,17 @@ impl<F: Field> ConstraintSystem<F> {
                 .unwrap_or(1),
         );
 
+        // The shuffle argument also serves alongside the gates and must be accounted
+        // for.
+        degree = std::cmp::max(
+            degree,
+            self.shuffles
+                .iter()
+                .map(|s| s.required_degree())
+                .max()
+                .unwrap_or(1),
+        );
+
         // Account for each gate to ensure our quotient polynomial is the
         // correct degree and that our extended domain is the right size.
         degree = std::cmp::max(
@@ -1503,9 +2152,38 @@ impl<F: Field> ConstraintSystem<F> {
         std::cmp::max(degree, self.minimum_degree.unwrap_or(1))
     }
 
+    /// Disables zero knowledge: the prover's witness polynomials are not blinded,
+    /// so proofs may reveal the witness, but are still sound.
+    ///
+    /// Without blinding factors, only the last row of each column is unusable, so
+    /// circuits for which only soundness matters get the rows back that would hold
+    /// them. This changes the verifying key.
+    pub fn disable_zero_knowledge(&mut self) {
+        self.zero_knowledge = false;
+    }
+
+    /// Sets the minimum number of blinding factors, which can be set to a larger
+    /// amount than actually needed.
+    ///
+    /// The number of blinding factors grows with the number of times an advice
+    /// column is queried, and determines the number of usable rows. Setting it up
+    /// front keeps [`ConstraintSystem::usable_rows`] the same as gates are added.
+    /// Has no effect if zero knowledge is disabled. This changes the verifying key.
+    pub fn set_minimum_blinding_factors(&mut self, blinding_factors: usize) {
+        self.minimum_blinding_factors = Some(blinding_factors);
+    }
+
     /// Compute the number of blinding factors necessary to perfectly blind
     /// each of the prover's witness polynomials.
+    ///
+    /// This is zero if zero knowledge has been disabled with
+    /// [`ConstraintSystem::disable_zero_knowledge`], and at least the number
+    /// set with [`ConstraintSystem::set_minimum_blinding_factors`] otherwise.
     pub fn blinding_factors(&self) -> usize {
+        if !self.zero_knowledge {
+            return 0;
+        }
+
         // All of the prover's advice columns are evaluated at no more than
         let factors = *self.num_advice_queries.iter().max().unwrap_or(&1);
         // distinct points during gate checks.
@@ -1529,7 +2207,9 @@ impl<F: Field> ConstraintSystem<F> {
 
         // Add an additional blinding factor as a slight defense against
         // off-by-one errors.
-        factors + 1
+        let factors = factors + 1;
+
+        std::cmp::max(factors, self.minimum_blinding_factors.unwrap_or(0))
     }
 
     /// Returns the minimum necessary rows that need to exist in order to
@@ -1543,6 +2223,171 @@ impl<F: Field> ConstraintSystem<F> {
                 // and the interstitial values.)
             + 1 // for at least one row
     }
+
+    /// Returns the rows that a circuit with `2^k` rows can assign to. The rows
+    /// after these hold the blinding factors, and the row used by `l_last`.
+    ///
+    /// The range is empty if `2^k` is smaller than
+    /// [`ConstraintSystem::minimum_rows`].
+    pub fn usable_rows(&self, k: u32) -> Range<usize> {
+        if (1 << k) < self.minimum_rows() {
+            return 0..0;
+        }
+        0..(1 << k) - (self.blinding_factors() + 1)
+    }
+
+    /// Returns the number of fixed columns, not including the ones selectors are
+    /// combined into.
+    pub fn num_fixed_columns(&self) -> usize {
+        self.num_fixed_columns
+    }
+
+    /// Returns the number of advice columns.
+    pub fn num_advice_columns(&self) -> usize {
+        self.num_advice_columns
+    }
+
+    /// Returns the number of instance columns.
+    pub fn num_instance_columns(&self) -> usize {
+        self.num_instance_columns
+    }
+
+    /// Returns the number of selectors.
+    pub fn num_selectors(&self) -> usize {
+        self.num_selectors
+    }
+
+    /// Returns the custom gates, in the order in which they were created.
+    pub fn gates(&self) -> &[Gate<F>] {
+        &self.gates
+    }
+
+    /// Returns the distinct queries of advice columns made by gates and arguments.
+    pub fn advice_queries(&self) -> &[(Column<Advice>, Rotation)] {
+        &self.advice_queries
+    }
+
+    /// Returns the distinct queries of instance columns made by gates and
+    /// arguments.
+    pub fn instance_queries(&self) -> &[(Column<Instance>, Rotation)] {
+        &self.instance_queries
+    }
+
+    /// Returns the distinct queries of fixed columns made by gates and arguments.
+    pub fn fixed_queries(&self) -> &[(Column<Fixed>, Rotation)] {
+        &self.fixed_queries
+    }
+
+    /// Returns the columns that take part in the permutation argument, in the
+    /// order in which they were enabled for equality.
+    pub fn permutation_columns(&self) -> Vec<Column<Any>> {
+        self.permutation.get_columns()
+    }
+
+    /// Returns the lookup arguments, in the order in which they were created.
+    pub fn lookups(&self) -> &[lookup::Argument<F>] {
+        &self.lookups
+    }
+
+    /// Returns the shuffle arguments, in the order in which they were created.
+    pub fn shuffles(&self) -> &[shuffle::Argument<F>] {
+        &self.shuffles
+    }
+
+    /// Returns the fixed columns that hold constants copied into advice columns.
+    pub fn constants(&self) -> &[Column<Fixed>] {
+        &self.constants
+    }
+
+    /// Returns a summary of the size of this constraint system, for logging or for
+    /// tracking how a circuit grows over time.
+    pub fn stats(&self) -> ConstraintSystemStats {
+        #[cfg(not(feature = "mv-lookup"))]
+        const LOOKUP_COMMITMENTS: usize = 3;
+        #[cfg(feature = "mv-lookup")]
+        const LOOKUP_COMMITMENTS: usize = 2;
+
+        let degree = self.degree();
+        let permutation_columns = self.permutation.get_columns().len();
+        let chunk_len = degree - 2;
+        let permutation_chunks = (permutation_columns + chunk_len - 1) / chunk_len;
+
+        ConstraintSystemStats {
+            advice_columns: self.num_advice_columns,
+            fixed_columns: self.num_fixed_columns,
+            instance_columns: self.num_instance_columns,
+            selectors: self.num_selectors,
+            gates: self.gates.len(),
+            constraints: self.gates.iter().map(|gate| gate.polynomials().len()).sum(),
+            lookups: self.lookups.len(),
+            shuffles: self.shuffles.len(),
+            degree,
+            permutation_columns,
+            // - 1 per advice column and per configuration column
+            // - 3 per lookup argument (2 with `mv-lookup`)
+            // - 1 per shuffle argument
+            // - 1 per chunk of the permutation columns
+            // - the random polynomial and the (degree - 1) pieces of h
+            commitments: self.num_advice_columns
+                + self.configuration_columns.len()
+                + LOOKUP_COMMITMENTS * self.lookups.len()
+                + self.shuffles.len()
+                + permutation_chunks
+                + degree,
+        }
+    }
+}
+
+/// A summary of the size of a [`ConstraintSystem`], as returned by
+/// [`ConstraintSystem::stats`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ConstraintSystemStats {
+    /// Number of advice columns.
+    pub advice_columns: usize,
+    /// Number of fixed columns, not including the ones selectors are combined into.
+    pub fixed_columns: usize,
+    /// Number of instance columns.
+    pub instance_columns: usize,
+    /// Number of selectors.
+    pub selectors: usize,
+    /// Number of custom gates.
+    pub gates: usize,
+    /// Number of constraints, across all custom gates.
+    pub constraints: usize,
+    /// Number of lookup arguments.
+    pub lookups: usize,
+    /// Number of shuffle arguments.
+    pub shuffles: usize,
+    /// The maximum degree of the constraint system.
+    pub degree: usize,
+    /// Number of columns in the permutation argument.
+    pub permutation_columns: usize,
+    /// Estimated number of commitments in a proof for one instance of the circuit,
+    /// not including the ones added by the multiopening argument and the
+    /// commitment scheme.
+    pub commitments: usize,
+}
+
+impl std::fmt::Display for ConstraintSystemStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} advice, {} fixed and {} instance columns, {} selectors, \
+             {} gates with {} constraints, {} lookups, {} shuffles, degree {}, \
+             {} permutation columns, ~{} commitments per proof",
+            self.advice_columns,
+            self.fixed_columns,
+            self.instance_columns,
+            self.selectors,
+            self.gates,
+            self.constraints,
+            self.lookups,
+            self.shuffles,
+            self.degree,
+            self.permutation_columns,
+            self.commitments
+        )
+    }
 }
 
 /// Exposes the "virtual cells" that can be queried while creating a custom gate or lookup
@@ -1590,6 +2435,14 @@ impl<'a, F: Field> VirtualCells<'a, F> {
     }
 
     /// Query an instance column at a relative position
+    ///
+    /// Queries at rotations other than [`Rotation::cur`] are allowed, but each one
+    /// makes proofs larger and verification slower; see
+    /// [`ConstraintSystem::rotated_instance_queries`]. A gate can therefore
+    /// relate neighbouring public inputs directly, without copying them into an
+    /// advice column first. Like other columns, instance columns wrap around, so
+    /// the row before the first one is the last row of the domain, which holds
+    /// zero.
     pub fn query_instance(&mut self, column: Column<Instance>, at: Rotation) -> Expression<F> {
         self.queried_cells.push((column, at).into());
         Expression::Instance {
@@ -1609,3 +2462,73 @@ impl<'a, F: Field> VirtualCells<'a, F> {
         }
     }
 }
+
+/// Queries columns within a gate, binding each query to the name of its column.
+///
+/// The columns are grouped by kind (`advice`, `fixed`, `instance` or `selector`),
+/// and are queried in order. A column is queried at the current row unless it is
+/// followed by `@` and a [`Rotation`](crate::poly::Rotation); selectors are always queried at the
+/// current row. A query can be bound to another name with `name = column`, which
+/// must come before the query that rebinds `column` itself.
+///
+/// ```
+/// use halo2_proofs::{
+///     plonk::{ConstraintSystem, Constraints},
+///     poly::Rotation,
+///     query_cells,
+/// };
+/// use pairing::bn256::Fr;
+///
+/// let mut meta = ConstraintSystem::<Fr>::default();
+/// let a = meta.advice_column();
+/// let b = meta.advice_column();
+/// let c = meta.fixed_column();
+/// let s = meta.selector();
+///
+/// meta.create_gate("running sum", |meta| {
+///     query_cells!(meta;
+///         advice: a_next = a @ Rotation::next(), a, b;
+///         fixed: c;
+///         selector: s;
+///     );
+///
+///     Constraints::with_selector(s, Some(a_next - (a + b * c)))
+/// });
+/// ```
+#[macro_export]
+macro_rules! query_cells {
+    (@column $name:ident) => {
+        $name
+    };
+    (@column $name:ident, $column:ident) => {
+        $column
+    };
+    (@query $meta:ident, advice, $column:expr) => {
+        $meta.query_advice($column, $crate::poly::Rotation::cur())
+    };
+    (@query $meta:ident, advice, $column:expr, $rotation:expr) => {
+        $meta.query_advice($column, $rotation)
+    };
+    (@query $meta:ident, fixed, $column:expr) => {
+        $meta.query_fixed($column, $crate::poly::Rotation::cur())
+    };
+    (@query $meta:ident, fixed, $column:expr, $rotation:expr) => {
+        $meta.query_fixed($column, $rotation)
+    };
+    (@query $meta:ident, instance, $column:expr) => {
+        $meta.query_instance($column, $crate::poly::Rotation::cur())
+    };
+    (@query $meta:ident, instance, $column:expr, $rotation:expr) => {
+        $meta.query_instance($column, $rotation)
+    };
+    (@query $meta:ident, selector, $column:expr) => {
+        $meta.query_selector($column)
+    };
+    ($meta:ident; $($kind:ident: $($name:ident $(= $column:ident)? $(@ $rotation:expr)?),+;)+) => {
+        $($(
+            let $name = $crate::query_cells!(
+                @query $meta, $kind, $crate::query_cells!(@column $name $(, $column)?) $(, $rotation)?
+            );
+        )+)+
+    };
+}
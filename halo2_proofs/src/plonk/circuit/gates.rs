@@ -0,0 +1,260 @@
+//! Reusable gate constructors for common constraints.
+
+use ff::Field;
+
+use super::{Advice, Column, ConstraintSystem, Constraints, Expression, Fixed, Selector};
+use crate::query_cells;
+
+impl<F: Field> ConstraintSystem<F> {
+    /// Creates the standard PLONK gate
+    ///
+    /// `a * sa + b * sb + a * b * sm - c * sc = 0`
+    ///
+    /// on every row, where the fixed columns `sa`, `sb`, `sc` and `sm` select the
+    /// operation performed there. Rows on which all four are zero are unconstrained,
+    /// so addition (`sa = sb = sc = 1`) and multiplication (`sm = sc = 1`) can share
+    /// the same columns.
+    pub fn standard_plonk_gate(
+        &mut self,
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Advice>,
+        sa: Column<Fixed>,
+        sb: Column<Fixed>,
+        sc: Column<Fixed>,
+        sm: Column<Fixed>,
+    ) {
+        self.create_gate("standard PLONK", |meta| {
+            query_cells!(meta;
+                advice: a, b, c;
+                fixed: sa, sb, sc, sm;
+            );
+
+            Some(a.clone() * sa + b.clone() * sb + a * b * sm - c * sc)
+        });
+    }
+
+    /// Constrains `value` to be either zero or one on the rows where `selector` is
+    /// enabled.
+    pub fn boolean_gate(&mut self, selector: Selector, value: Column<Advice>) {
+        self.create_gate("boolean", |meta| {
+            query_cells!(meta;
+                advice: value;
+                selector: selector;
+            );
+
+            Constraints::with_selector(
+                selector,
+                Some((
+                    "value is boolean",
+                    value.clone() * (Expression::Constant(F::one()) - value),
+                )),
+            )
+        });
+    }
+
+    /// Constrains `output` to be one if `value` is zero and zero otherwise, on the
+    /// rows where `selector` is enabled.
+    ///
+    /// The prover must assign the inverse of `value` to `inverse`, or any value if
+    /// `value` is zero.
+    pub fn is_zero_gate(
+        &mut self,
+        selector: Selector,
+        value: Column<Advice>,
+        inverse: Column<Advice>,
+        output: Column<Advice>,
+    ) {
+        self.create_gate("is zero", |meta| {
+            query_cells!(meta;
+                advice: value, inverse, output;
+                selector: selector;
+            );
+
+            Constraints::with_selector(
+                selector,
+                vec![
+                    (
+                        "output is 1 - value * inverse",
+                        output.clone() - (Expression::Constant(F::one()) - value.clone() * inverse),
+                    ),
+                    ("value * output is zero", value * output),
+                ],
+            )
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use pairing::bn256::Fr as Fp;
+
+    use crate::{
+        circuit::{Layouter, SimpleFloorPlanner},
+        dev::{MockProver, VerifyFailure},
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Fixed, Selector},
+    };
+
+    #[derive(Clone)]
+    struct GatesConfig {
+        a: Column<Advice>,
+        b: Column<Advice>,
+        c: Column<Advice>,
+        sa: Column<Fixed>,
+        sb: Column<Fixed>,
+        sc: Column<Fixed>,
+        sm: Column<Fixed>,
+        q_bool: Selector,
+        q_is_zero: Selector,
+    }
+
+    struct GatesCircuit {
+        boolean: Fp,
+        is_zero_value: Fp,
+        is_zero_output: Fp,
+    }
+
+    impl Circuit<Fp> for GatesCircuit {
+        type Config = GatesConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let a = meta.advice_column();
+            let b = meta.advice_column();
+            let c = meta.advice_column();
+            let sa = meta.fixed_column();
+            let sb = meta.fixed_column();
+            let sc = meta.fixed_column();
+            let sm = meta.fixed_column();
+            let q_bool = meta.selector();
+            let q_is_zero = meta.selector();
+
+            meta.standard_plonk_gate(a, b, c, sa, sb, sc, sm);
+            meta.boolean_gate(q_bool, a);
+            meta.is_zero_gate(q_is_zero, a, b, c);
+
+            GatesConfig {
+                a,
+                b,
+                c,
+                sa,
+                sb,
+                sc,
+                sm,
+                q_bool,
+                q_is_zero,
+            }
+        }
+
+        fn without_witnesses(&self) -> Self {
+            Self {
+                boolean: Fp::zero(),
+                is_zero_value: Fp::zero(),
+                is_zero_output: Fp::one(),
+            }
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            layouter.assign_region(
+                || "gates",
+                |mut region| {
+                    // 2 + 3 = 5
+                    region.assign_advice(|| "a", config.a, 0, || Ok(Fp::from(2)))?;
+                    region.assign_advice(|| "b", config.b, 0, || Ok(Fp::from(3)))?;
+                    region.assign_advice(|| "c", config.c, 0, || Ok(Fp::from(5)))?;
+                    for column in [config.sa, config.sb, config.sc] {
+                        region.assign_fixed(|| "add", column, 0, || Ok(Fp::one()))?;
+                    }
+
+                    // 2 * 3 = 6
+                    region.assign_advice(|| "a", config.a, 1, || Ok(Fp::from(2)))?;
+                    region.assign_advice(|| "b", config.b, 1, || Ok(Fp::from(3)))?;
+                    region.assign_advice(|| "c", config.c, 1, || Ok(Fp::from(6)))?;
+                    for column in [config.sm, config.sc] {
+                        region.assign_fixed(|| "mul", column, 1, || Ok(Fp::one()))?;
+                    }
+
+                    config.q_bool.enable(&mut region, 2)?;
+                    region.assign_advice(|| "boolean", config.a, 2, || Ok(self.boolean))?;
+
+                    config.q_is_zero.enable(&mut region, 3)?;
+                    region.assign_advice(|| "value", config.a, 3, || Ok(self.is_zero_value))?;
+                    region.assign_advice(
+                        || "inverse",
+                        config.b,
+                        3,
+                        || Ok(self.is_zero_value.invert().unwrap_or(Fp::zero())),
+                    )?;
+                    region.assign_advice(|| "output", config.c, 3, || Ok(self.is_zero_output))?;
+
+                    Ok(())
+                },
+            )
+        }
+    }
+
+    fn failing_gates(circuit: &GatesCircuit) -> Vec<String> {
+        let prover = MockProver::run(4, circuit, vec![]).unwrap();
+        match prover.verify() {
+            Ok(()) => vec![],
+            Err(failures) => failures
+                .into_iter()
+                .map(|failure| match failure {
+                    VerifyFailure::ConstraintNotSatisfied { constraint, .. } => {
+                        constraint.to_string()
+                    }
+                    _ => panic!("unexpected failure: {}", failure),
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn gate_templates_satisfied() {
+        for (boolean, value, output) in [
+            (Fp::zero(), Fp::zero(), Fp::one()),
+            (Fp::one(), Fp::from(5), Fp::zero()),
+        ] {
+            let circuit = GatesCircuit {
+                boolean,
+                is_zero_value: value,
+                is_zero_output: output,
+            };
+            assert_eq!(failing_gates(&circuit), Vec::<String>::new());
+        }
+    }
+
+    #[test]
+    fn boolean_gate_rejects_non_boolean() {
+        let circuit = GatesCircuit {
+            boolean: Fp::from(2),
+            is_zero_value: Fp::zero(),
+            is_zero_output: Fp::one(),
+        };
+        assert_eq!(
+            failing_gates(&circuit),
+            vec!["Constraint 0 ('value is boolean') in gate 1 ('boolean')".to_string()]
+        );
+    }
+
+    #[test]
+    fn is_zero_gate_rejects_wrong_output() {
+        let circuit = GatesCircuit {
+            boolean: Fp::zero(),
+            is_zero_value: Fp::from(5),
+            is_zero_output: Fp::one(),
+        };
+        assert_eq!(
+            failing_gates(&circuit),
+            vec![
+                "Constraint 0 ('output is 1 - value * inverse') in gate 2 ('is zero')".to_string(),
+                "Constraint 1 ('value * output is zero') in gate 2 ('is zero')".to_string(),
+            ]
+        );
+    }
+}
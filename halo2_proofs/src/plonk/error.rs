@@ -3,7 +3,9 @@ use std::error;
 use std::fmt;
 use std::io;
 
-use super::{Any, Column};
+use ff::Field;
+
+use super::{Advice, Any, Circuit, Column, Fixed};
 
 /// This is an error that could occur during proving or circuit synthesis.
 // TODO: these errors need to be cleaned up
@@ -26,6 +28,18 @@ pub enum Error {
     NotEnoughRowsAvailable {
         /// The current value of `k` being used.
         current_k: u32,
+        /// The smallest value of `k` that the circuit fits in, as measured from the
+        /// rows used by its floor planner. If the circuit could not be measured, this
+        /// is `current_k + 1`.
+        minimum_k: u32,
+    },
+    /// `k` is too large: the circuit's domains don't fit in the field's
+    /// subgroups, or its rows can't be indexed on this platform.
+    TooManyRows {
+        /// The current value of `k` being used.
+        current_k: u32,
+        /// The largest value of `k` supported for the given circuit.
+        max_k: u32,
     },
     /// Instance provided exceeds number of available rows
     InstanceTooLarge,
@@ -37,6 +51,146 @@ pub enum Error {
     /// The instance sets up a copy constraint involving a column that has not been
     /// included in the permutation.
     ColumnNotInPermutation(Column<Any>),
+    /// The circuit assigned a fixed cell a different value than the proving key
+    /// holds for it, so the key was generated from a different circuit. This is only
+    /// checked in debug builds.
+    FixedAssignmentMismatch {
+        /// The fixed column of the cell.
+        column: Column<Fixed>,
+        /// The row of the cell.
+        row: usize,
+    },
+    /// The value to be assigned to a cell could not be computed.
+    Assignment(Box<AssignmentError>),
+    /// An advice cell queried by a gate, on a row where one of the gate's
+    /// selectors is enabled, was not assigned. This is only checked by
+    /// [`create_proof_checked`](crate::plonk::create_proof_checked).
+    CellNotAssigned {
+        /// The name of the gate.
+        gate: &'static str,
+        /// The advice column of the cell.
+        column: Column<Advice>,
+        /// The row of the cell.
+        row: usize,
+    },
+    /// The proof was cancelled by its [`ProverProgress`] handler.
+    ///
+    /// [`ProverProgress`]: crate::plonk::ProverProgress
+    Aborted,
+    /// The instances given to the prover don't match the circuits, or the
+    /// instance columns of the proving key. This is checked before any work is
+    /// done for the proof.
+    InstanceLayout(InstanceLayoutError),
+    /// The commitments to the configuration columns (see
+    /// [`ConstraintSystem::configuration_column`]) in the proof differ from the
+    /// expected ones, or the verifier was given the wrong number of them.
+    ///
+    /// [`ConstraintSystem::configuration_column`]: crate::plonk::ConstraintSystem::configuration_column
+    ConfigurationMismatch,
+}
+
+/// How the instances given to the prover fail to match the circuits of a
+/// proof. See [`Error::InstanceLayout`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum InstanceLayoutError {
+    /// The number of instance sets differs from the number of circuits.
+    CircuitCount {
+        /// The number of circuits.
+        circuits: usize,
+        /// The number of instance sets.
+        instances: usize,
+    },
+    /// The instances of a circuit hold a different number of columns than the
+    /// constraint system has instance columns.
+    ColumnCount {
+        /// The index of the circuit.
+        circuit: usize,
+        /// The number of columns given for the circuit.
+        columns: usize,
+        /// The number of instance columns of the constraint system.
+        expected: usize,
+    },
+    /// An instance column holds more values than the circuit has usable rows.
+    ColumnTooLong {
+        /// The index of the circuit.
+        circuit: usize,
+        /// The index of the instance column.
+        column: usize,
+        /// The number of values in the column.
+        len: usize,
+        /// The number of usable rows of the circuit.
+        max: usize,
+    },
+}
+
+impl fmt::Display for InstanceLayoutError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InstanceLayoutError::CircuitCount {
+                circuits,
+                instances,
+            } => write!(
+                f,
+                "{} instance sets were given for {} circuits",
+                instances, circuits
+            ),
+            InstanceLayoutError::ColumnCount {
+                circuit,
+                columns,
+                expected,
+            } => write!(
+                f,
+                "Circuit {} was given {} instance columns, but the circuit has {}",
+                circuit, columns, expected
+            ),
+            InstanceLayoutError::ColumnTooLong {
+                circuit,
+                column,
+                len,
+                max,
+            } => write!(
+                f,
+                "Instance column {} of circuit {} holds {} values, but the circuit has {} usable rows",
+                column, circuit, len, max
+            ),
+        }
+    }
+}
+
+/// The location of a failed assignment, together with the error that the value's
+/// closure returned. See [`Error::Assignment`].
+#[derive(Debug)]
+pub struct AssignmentError {
+    /// The name of the region the cell is in, if the assignment was made by a floor
+    /// planner provided by `halo2`.
+    pub region: Option<String>,
+    /// The namespaces the region was assigned in, outermost first.
+    pub namespaces: Vec<String>,
+    /// The annotation of the assignment.
+    pub annotation: String,
+    /// The column of the cell.
+    pub column: Column<Any>,
+    /// The offset of the cell within its region.
+    pub offset: usize,
+    /// The error returned by the closure, usually [`Error::Synthesis`].
+    pub error: Error,
+}
+
+impl fmt::Display for AssignmentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to assign {:?} to {:?} at offset {}",
+            self.annotation, self.column, self.offset
+        )?;
+        if let Some(region) = &self.region {
+            write!(f, " in region {:?}", region)?;
+        }
+        if !self.namespaces.is_empty() {
+            write!(f, " (in {})", self.namespaces.join(" > "))?;
+        }
+        write!(f, ": {}", self.error)
+    }
 }
 
 impl From<io::Error> for Error {
@@ -47,9 +201,70 @@ impl From<io::Error> for Error {
 }
 
 impl Error {
+    /// Wraps an error returned by the closure computing the value of a cell at
+    /// `offset` in `column` within a region, unless it has been wrapped already.
+    pub(crate) fn in_assignment(
+        self,
+        annotation: impl FnOnce() -> String,
+        column: Column<Any>,
+        offset: usize,
+    ) -> Self {
+        match self {
+            Error::Assignment(_) => self,
+            error => Error::Assignment(Box::new(AssignmentError {
+                region: None,
+                namespaces: vec![],
+                annotation: annotation(),
+                column,
+                offset,
+                error,
+            })),
+        }
+    }
+
+    /// Sets the region and namespaces of an `Error::Assignment` made in that region,
+    /// if they are not set yet. Other errors are returned unchanged.
+    pub(crate) fn in_region(self, region: impl FnOnce() -> String, namespaces: &[String]) -> Self {
+        match self {
+            Error::Assignment(mut assignment) if assignment.region.is_none() => {
+                assignment.region = Some(region());
+                assignment.namespaces = namespaces.to_vec();
+                Error::Assignment(assignment)
+            }
+            error => error,
+        }
+    }
+
     /// Constructs an `Error::NotEnoughRowsAvailable`.
+    ///
+    /// The minimum `k` is not known where rows run out, so it is set to the next
+    /// value of `k`; [`Error::with_minimum_k`] measures the actual one.
     pub(crate) fn not_enough_rows_available(current_k: u32) -> Self {
-        Error::NotEnoughRowsAvailable { current_k }
+        Error::NotEnoughRowsAvailable {
+            current_k,
+            minimum_k: current_k + 1,
+        }
+    }
+
+    /// Sets the minimum `k` of an `Error::NotEnoughRowsAvailable` to the one
+    /// measured for `circuit`. Other errors are returned unchanged.
+    pub(crate) fn with_minimum_k<F: Field, ConcreteCircuit: Circuit<F>>(
+        self,
+        circuit: &ConcreteCircuit,
+    ) -> Self {
+        match self {
+            Error::NotEnoughRowsAvailable {
+                current_k,
+                minimum_k,
+            } => Error::NotEnoughRowsAvailable {
+                current_k,
+                // Rows used by instances aren't measured, so never report a k that
+                // has just failed.
+                minimum_k: super::minimum_k(circuit)
+                    .map_or(minimum_k, |measured| cmp::max(measured, minimum_k)),
+            },
+            error => error,
+        }
     }
 }
 
@@ -62,10 +277,18 @@ impl fmt::Display for Error {
             Error::BoundsFailure => write!(f, "An out-of-bounds index was passed to the backend"),
             Error::Opening => write!(f, "Multi-opening proof was invalid"),
             Error::Transcript(e) => write!(f, "Transcript error: {}", e),
-            Error::NotEnoughRowsAvailable { current_k } => write!(
-                f,
-                "k = {} is too small for the given circuit. Try using a larger value of k",
+            Error::NotEnoughRowsAvailable {
                 current_k,
+                minimum_k,
+            } => write!(
+                f,
+                "k = {} is too small for the given circuit. Try using k = {}",
+                current_k, minimum_k,
+            ),
+            Error::TooManyRows { current_k, max_k } => write!(
+                f,
+                "k = {} is too large for the given circuit, which supports k up to {}",
+                current_k, max_k,
             ),
             Error::InstanceTooLarge => write!(f, "Instance vectors are larger than the circuit"),
             Error::NotEnoughColumnsForConstants => {
@@ -79,6 +302,23 @@ impl fmt::Display for Error {
                 "Column {:?} must be included in the permutation. Help: try applying `meta.enable_equalty` on the column",
                 column
             ),
+            Error::FixedAssignmentMismatch { column, row } => write!(
+                f,
+                "Fixed cell in column {:?} at row {} differs from the proving key. Help: was the proving key generated from a different version of the circuit?",
+                column, row
+            ),
+            Error::Assignment(e) => e.fmt(f),
+            Error::CellNotAssigned { gate, column, row } => write!(
+                f,
+                "Cell in column {:?} at row {} is queried by gate {:?} but was not assigned",
+                column, row, gate
+            ),
+            Error::Aborted => write!(f, "The proof was aborted"),
+            Error::InstanceLayout(e) => write!(f, "Invalid instance layout: {}", e),
+            Error::ConfigurationMismatch => write!(
+                f,
+                "The configuration columns of the proof do not match the expected ones"
+            ),
         }
     }
 }
@@ -87,6 +327,7 @@ impl error::Error for Error {
     fn source(&self) -> Option<&(dyn error::Error + 'static)> {
         match self {
             Error::Transcript(e) => Some(e),
+            Error::Assignment(e) => Some(&e.error),
             _ => None,
         }
     }
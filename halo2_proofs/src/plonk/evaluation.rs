@@ -1,7 +1,7 @@
 use crate::multicore;
 use crate::plonk::lookup::prover::Committed;
 use crate::plonk::permutation::Argument;
-use crate::plonk::{lookup, permutation, Any, ProvingKey};
+use crate::plonk::{lookup, permutation, shuffle, Any, ProvingKey};
 use crate::poly::Basis;
 use crate::{
     arithmetic::{eval_polynomial, parallelize, BaseExt, CurveAffine, FieldExt},
@@ -17,11 +17,12 @@ use group::{
     Curve,
 };
 use std::any::TypeId;
+use std::borrow::Cow;
 use std::convert::TryInto;
 use std::num::ParseIntError;
 use std::slice;
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet},
     iter,
     ops::{Index, Mul, MulAssign},
 };
@@ -33,6 +34,25 @@ fn get_rotation_idx(idx: usize, rot: i32, rot_scale: i32, isize: i32) -> usize {
     (((idx as i32) + (rot * rot_scale)).rem_euclid(isize)) as usize
 }
 
+/// Returns the fixed columns that `expr` is a multiple of, at any rotation. If
+/// one of them is zero on every row, so is `expr`, over the extended domain too.
+fn fixed_factors<F: Field>(expr: &Expression<F>) -> BTreeSet<usize> {
+    expr.evaluate(
+        &|_| BTreeSet::new(),
+        &|_| BTreeSet::new(),
+        &|_, column_index, _| iter::once(column_index).collect(),
+        &|_, _, _| BTreeSet::new(),
+        &|_, _, _| BTreeSet::new(),
+        &|a| a,
+        &|a, b| a.intersection(&b).copied().collect(),
+        &|mut a, mut b| {
+            a.append(&mut b);
+            a
+        },
+        &|a, _| a,
+    )
+}
+
 /// Value used in a calculation
 #[derive(Clone, Copy, Debug, PartialEq, PartialOrd)]
 pub enum ValueSource {
@@ -236,6 +256,79 @@ impl Calculation {
     }
 }
 
+/// The points of the extended domain over which [`Evaluator::evaluate_h`]
+/// evaluates h(X) at once.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(in crate::plonk) enum ExtendedPart {
+    /// All of the points of the extended domain.
+    All,
+    /// The points whose index is the given one modulo
+    /// [`EvaluationDomain::extended_parts`]. They form a coset of the original
+    /// domain, which rotations over the original domain map onto itself.
+    Coset(usize),
+}
+
+impl ExtendedPart {
+    /// Returns the distance between the index of a point in this part and that
+    /// of its rotation by one row.
+    fn rot_scale<F: FieldExt>(self, domain: &EvaluationDomain<F>) -> i32 {
+        match self {
+            ExtendedPart::All => 1 << (domain.extended_k() - domain.k()),
+            ExtendedPart::Coset(_) => 1,
+        }
+    }
+
+    /// Returns the first point of this part divided by zeta, and the ratio
+    /// between consecutive points.
+    fn points<F: FieldExt>(self, domain: &EvaluationDomain<F>) -> (F, F) {
+        match self {
+            ExtendedPart::All => (F::one(), domain.get_extended_omega()),
+            ExtendedPart::Coset(part) => (
+                domain
+                    .get_extended_omega()
+                    .pow_vartime(&[part as u64, 0, 0, 0]),
+                domain.get_omega(),
+            ),
+        }
+    }
+
+    /// Returns an empty (zero) polynomial over this part.
+    pub(in crate::plonk) fn empty<F: FieldExt>(
+        self,
+        domain: &EvaluationDomain<F>,
+    ) -> Polynomial<F, ExtendedLagrangeCoeff> {
+        match self {
+            ExtendedPart::All => domain.empty_extended(),
+            ExtendedPart::Coset(_) => domain.empty_extended_part(),
+        }
+    }
+
+    /// Evaluates a polynomial in coefficient form over this part.
+    pub(in crate::plonk) fn coeff_to_extended<F: FieldExt>(
+        self,
+        domain: &EvaluationDomain<F>,
+        poly: &Polynomial<F, Coeff>,
+    ) -> Polynomial<F, ExtendedLagrangeCoeff> {
+        match self {
+            ExtendedPart::All => domain.coeff_to_extended(poly.clone()),
+            ExtendedPart::Coset(part) => domain.coeff_to_extended_part(poly, part),
+        }
+    }
+
+    /// Returns the values of a polynomial over the extended domain at the points
+    /// of this part.
+    fn restrict<'a, F: FieldExt>(
+        self,
+        domain: &EvaluationDomain<F>,
+        poly: &'a Polynomial<F, ExtendedLagrangeCoeff>,
+    ) -> Cow<'a, Polynomial<F, ExtendedLagrangeCoeff>> {
+        match self {
+            ExtendedPart::All => Cow::Borrowed(poly),
+            ExtendedPart::Coset(part) => Cow::Owned(domain.extended_part(poly, part)),
+        }
+    }
+}
+
 /// EvaluationData
 #[derive(Default, Debug)]
 pub struct Evaluator<C: CurveAffine> {
@@ -249,6 +342,8 @@ pub struct Evaluator<C: CurveAffine> {
     pub value_parts: Vec<ValueSource>,
     /// Lookup results
     pub lookup_results: Vec<Calculation>,
+    /// Shuffle results
+    pub shuffle_results: Vec<Calculation>,
 }
 
 /// CaluclationInfo
@@ -262,7 +357,12 @@ pub struct CalculationInfo {
 
 impl<C: CurveAffine> Evaluator<C> {
     /// Creates a new evaluation structure
-    pub fn new(cs: &ConstraintSystem<C::ScalarExt>) -> Self {
+    ///
+    /// The constraints that are a multiple of one of the `zero_fixed_columns`,
+    /// which must be zero on every row, are zero over the extended domain as
+    /// well. They are not evaluated, which skips the gates of selectors that a
+    /// circuit never enables.
+    pub fn new(cs: &ConstraintSystem<C::ScalarExt>, zero_fixed_columns: &BTreeSet<usize>) -> Self {
         let mut ev = Evaluator::default();
         ev.add_constant(&C::ScalarExt::zero());
         ev.add_constant(&C::ScalarExt::one());
@@ -270,37 +370,132 @@ impl<C: CurveAffine> Evaluator<C> {
         // Custom gates
         for gate in cs.gates.iter() {
             for poly in gate.polynomials().iter() {
-                let vs = ev.add_expression(poly);
+                // The constraint still takes its power of y, as a zero.
+                let vs = if fixed_factors(poly).is_disjoint(zero_fixed_columns) {
+                    ev.add_expression(poly)
+                } else {
+                    ValueSource::Constant(0)
+                };
                 ev.value_parts.push(vs);
             }
         }
 
         // Lookups
         for lookup in cs.lookups.iter() {
-            let evaluate_lc = |ev: &mut Evaluator<_>, expressions: &Vec<Expression<_>>| {
-                let parts = expressions
-                    .iter()
-                    .map(|expr| ev.add_expression(expr))
-                    .collect::<Vec<_>>();
-                let mut lc = parts[0];
-                for part in parts.iter().skip(1) {
-                    lc = ev.add_calculation(Calculation::LcTheta(lc, *part));
-                }
-                lc
-            };
             // Input coset
-            let compressed_input_coset = evaluate_lc(&mut ev, &lookup.input_expressions);
+            let compressed_input_coset = ev.add_compressed(&lookup.input_expressions);
             // table coset
-            let compressed_table_coset = evaluate_lc(&mut ev, &lookup.table_expressions);
-            // z(\omega X) (a'(X) + \beta) (s'(X) + \gamma)
-            let right_gamma = ev.add_calculation(Calculation::AddGamma(compressed_table_coset));
-            ev.lookup_results
-                .push(Calculation::LcBeta(compressed_input_coset, right_gamma));
+            let compressed_table_coset = ev.add_compressed(&lookup.table_expressions);
+            #[cfg(not(feature = "mv-lookup"))]
+            {
+                // z(\omega X) (a'(X) + \beta) (s'(X) + \gamma)
+                let right_gamma = ev.add_calculation(Calculation::AddGamma(compressed_table_coset));
+                ev.lookup_results
+                    .push(Calculation::LcBeta(compressed_input_coset, right_gamma));
+            }
+            #[cfg(feature = "mv-lookup")]
+            {
+                // (a(X) + \beta) and (s(X) + \beta)
+                let one = ev.add_constant(&C::ScalarExt::one());
+                ev.lookup_results
+                    .push(Calculation::LcBeta(compressed_input_coset, one));
+                ev.lookup_results
+                    .push(Calculation::LcBeta(compressed_table_coset, one));
+            }
+        }
+
+        // Shuffles
+        for shuffle in cs.shuffles.iter() {
+            // Input coset
+            let compressed_input_coset = ev.add_compressed(&shuffle.input_expressions);
+            // Shuffle coset
+            let compressed_shuffle_coset = ev.add_compressed(&shuffle.shuffle_expressions);
+            // (a(X) + \gamma) and (s(X) + \gamma)
+            ev.shuffle_results
+                .push(Calculation::AddGamma(compressed_input_coset));
+            ev.shuffle_results
+                .push(Calculation::AddGamma(compressed_shuffle_coset));
         }
 
         ev
     }
 
+    /// Creates an evaluation structure for the compression of `expressions` by
+    /// powers of theta, as used for the inputs and tables of lookups and shuffles
+    pub fn new_compressed(expressions: &[Expression<C::ScalarExt>]) -> Self {
+        let mut ev = Evaluator::default();
+        ev.add_constant(&C::ScalarExt::zero());
+        ev.add_constant(&C::ScalarExt::one());
+
+        let compressed = ev.add_compressed(expressions);
+        ev.value_parts.push(compressed);
+
+        ev
+    }
+
+    /// Evaluates the compressed expressions of an evaluation structure created
+    /// with [`Evaluator::new_compressed`] at each of the `size` rows of the
+    /// columns
+    pub fn evaluate_compressed<B: Basis>(
+        &self,
+        size: usize,
+        rot_scale: i32,
+        fixed: &[Polynomial<C::ScalarExt, B>],
+        advice: &[Polynomial<C::ScalarExt, B>],
+        instance: &[Polynomial<C::ScalarExt, B>],
+        theta: C::ScalarExt,
+    ) -> Vec<C::ScalarExt> {
+        assert_eq!(self.value_parts.len(), 1);
+        let zero = C::ScalarExt::zero();
+        let isize = size as i32;
+        let mut values = vec![zero; size];
+        parallelize(&mut values, |values, start| {
+            let mut rotations = vec![0usize; self.rotations.len()];
+            let mut intermediates = vec![zero; self.calculations.len()];
+            for (i, value) in values.iter_mut().enumerate() {
+                let idx = start + i;
+                for (rot_idx, rot) in self.rotations.iter().enumerate() {
+                    rotations[rot_idx] = get_rotation_idx(idx, *rot, rot_scale, isize);
+                }
+                for (i_idx, calc) in self.calculations.iter().enumerate() {
+                    intermediates[i_idx] = calc.calculation.evaluate(
+                        &rotations,
+                        &self.constants,
+                        &intermediates,
+                        fixed,
+                        advice,
+                        instance,
+                        &zero,
+                        &zero,
+                        &theta,
+                    );
+                }
+                *value = self.value_parts[0].get(
+                    &rotations,
+                    &self.constants,
+                    &intermediates,
+                    fixed,
+                    advice,
+                    instance,
+                );
+            }
+        });
+        values
+    }
+
+    /// Adds the compression of `expressions` by powers of theta
+    fn add_compressed(&mut self, expressions: &[Expression<C::ScalarExt>]) -> ValueSource {
+        let parts = expressions
+            .iter()
+            .map(|expr| self.add_expression(expr))
+            .collect::<Vec<_>>();
+        let mut lc = parts[0];
+        for part in parts.iter().skip(1) {
+            lc = self.add_calculation(Calculation::LcTheta(lc, *part));
+        }
+        lc
+    }
+
     /// Adds a rotation
     fn add_rotation(&mut self, rotation: &Rotation) -> usize {
         let position = self.rotations.iter().position(|&c| c == rotation.0);
@@ -455,10 +650,13 @@ impl<C: CurveAffine> Evaluator<C> {
         }
     }
 
-    /// Evaluate h poly
+    /// Evaluate h poly over `part` of the extended domain, given the values of
+    /// the fixed, advice and instance columns at its points
     pub(in crate::plonk) fn evaluate_h(
         &self,
         pk: &ProvingKey<C>,
+        part: ExtendedPart,
+        fixed: &[Polynomial<C::ScalarExt, ExtendedLagrangeCoeff>],
         advice: Vec<&Vec<Polynomial<C::ScalarExt, ExtendedLagrangeCoeff>>>,
         instance: Vec<&Vec<Polynomial<C::ScalarExt, ExtendedLagrangeCoeff>>>,
         y: C::ScalarExt,
@@ -466,31 +664,39 @@ impl<C: CurveAffine> Evaluator<C> {
         gamma: C::ScalarExt,
         theta: C::ScalarExt,
         lookups: &[Vec<lookup::prover::Committed<C>>],
+        shuffles: &[Vec<shuffle::prover::Committed<C>>],
         permutations: &[permutation::prover::Committed<C>],
     ) -> Polynomial<C::ScalarExt, ExtendedLagrangeCoeff> {
         let domain = &pk.vk.domain;
-        let size = domain.extended_len();
-        let rot_scale = 1 << (domain.extended_k() - domain.k());
-        let fixed = &pk.fixed_cosets[..];
-        let extended_omega = domain.get_extended_omega();
-        let num_lookups = pk.vk.cs.lookups.len();
+        let mut values = part.empty(domain);
+        let size = values.len();
+        let rot_scale = part.rot_scale(domain);
+        let (first_point, point_step) = part.points(domain);
         let isize = size as i32;
         let one = C::ScalarExt::one();
-        let l0 = &pk.l0;
-        let l_last = &pk.l_last;
-        let l_active_row = &pk.l_active_row;
+        let l0 = part.restrict(domain, &pk.l0);
+        let l_last = part.restrict(domain, &pk.l_last);
+        let l_active_row = part.restrict(domain, &pk.l_active_row);
+        let permutation_cosets: Vec<_> = pk
+            .permutation
+            .cosets
+            .iter()
+            .map(|coset| part.restrict(domain, coset))
+            .collect();
         let p = &pk.vk.cs.permutation;
 
-        let mut values = domain.empty_extended();
-        let mut lookup_values = vec![C::Scalar::zero(); size * num_lookups];
+        let mut lookup_values = vec![C::Scalar::zero(); size * self.lookup_results.len()];
 
         // Core expression evaluations
         let num_threads = multicore::current_num_threads();
         let mut table_values_box = ThreadBox::wrap(&mut lookup_values);
-        for (((advice, instance), lookups), permutation) in advice
+        let mut shuffle_values = vec![C::Scalar::zero(); size * self.shuffle_results.len()];
+        let mut shuffle_values_box = ThreadBox::wrap(&mut shuffle_values);
+        for ((((advice, instance), lookups), shuffles), permutation) in advice
             .iter()
             .zip(instance.iter())
             .zip(lookups.iter())
+            .zip(shuffles.iter())
             .zip(permutations.iter())
         {
             multicore::scope(|scope| {
@@ -499,6 +705,7 @@ impl<C: CurveAffine> Evaluator<C> {
                     let start = thread_idx * chunk_size;
                     scope.spawn(move |_| {
                         let table_values = table_values_box.unwrap();
+                        let shuffle_values = shuffle_values_box.unwrap();
                         let mut rotations = vec![0usize; self.rotations.len()];
                         let mut intermediates: Vec<C::ScalarExt> =
                             vec![C::ScalarExt::zero(); self.calculations.len()];
@@ -552,13 +759,34 @@ impl<C: CurveAffine> Evaluator<C> {
                                     &theta,
                                 );
                             }
+
+                            // Values required for the shuffles
+                            for (t, shuffle_result) in self.shuffle_results.iter().enumerate() {
+                                shuffle_values[t * size + idx] = shuffle_result.evaluate(
+                                    &rotations,
+                                    &self.constants,
+                                    &intermediates,
+                                    fixed,
+                                    advice,
+                                    instance,
+                                    &beta,
+                                    &gamma,
+                                    &theta,
+                                );
+                            }
                         }
                     });
                 }
             });
 
             // Permutations
-            let sets = &permutation.sets;
+            // Calculated here so these only have to be kept in memory for the short time
+            // they are actually needed.
+            let sets: Vec<_> = permutation
+                .sets
+                .iter()
+                .map(|set| part.coeff_to_extended(domain, &set.permutation_product_poly))
+                .collect();
             if !sets.is_empty() {
                 let blinding_factors = pk.vk.cs.blinding_factors();
                 let last_rotation = Rotation(-((blinding_factors + 1) as i32));
@@ -570,7 +798,8 @@ impl<C: CurveAffine> Evaluator<C> {
 
                 // Permutation constraints
                 parallelize(&mut values, |values, start| {
-                    let mut beta_term = extended_omega.pow_vartime(&[start as u64, 0, 0, 0]);
+                    let mut beta_term =
+                        first_point * point_step.pow_vartime(&[start as u64, 0, 0, 0]);
                     for (i, value) in values.iter_mut().enumerate() {
                         let idx = start + i;
                         let r_next = get_rotation_idx(idx, 1, rot_scale, isize);
@@ -578,24 +807,17 @@ impl<C: CurveAffine> Evaluator<C> {
 
                         // Enforce only for the first set.
                         // l_0(X) * (1 - z_0(X)) = 0
-                        *value = *value * y
-                            + ((one - first_set.permutation_product_coset[idx]) * l0[idx]);
+                        *value = *value * y + ((one - first_set[idx]) * l0[idx]);
                         // Enforce only for the last set.
                         // l_last(X) * (z_l(X)^2 - z_l(X)) = 0
                         *value = *value * y
-                            + ((last_set.permutation_product_coset[idx]
-                                * last_set.permutation_product_coset[idx]
-                                - last_set.permutation_product_coset[idx])
-                                * l_last[idx]);
+                            + ((last_set[idx] * last_set[idx] - last_set[idx]) * l_last[idx]);
                         // Except for the first set, enforce.
                         // l_0(X) * (z_i(X) - z_{i-1}(\omega^(last) X)) = 0
                         for (set_idx, set) in sets.iter().enumerate() {
                             if set_idx != 0 {
-                                *value = *value * y
-                                    + ((set.permutation_product_coset[idx]
-                                        - permutation.sets[set_idx - 1].permutation_product_coset
-                                            [r_last])
-                                        * l0[idx]);
+                                *value =
+                                    *value * y + ((set[idx] - sets[set_idx - 1][r_last]) * l0[idx]);
                             }
                         }
                         // And for all the sets we enforce:
@@ -607,9 +829,9 @@ impl<C: CurveAffine> Evaluator<C> {
                         for ((set, columns), cosets) in sets
                             .iter()
                             .zip(p.columns.chunks(chunk_len))
-                            .zip(pk.permutation.cosets.chunks(chunk_len))
+                            .zip(permutation_cosets.chunks(chunk_len))
                         {
-                            let mut left = set.permutation_product_coset[r_next];
+                            let mut left = set[r_next];
                             for (values, permutation) in columns
                                 .iter()
                                 .map(|&column| match column.column_type() {
@@ -622,7 +844,7 @@ impl<C: CurveAffine> Evaluator<C> {
                                 left *= values[idx] + beta * permutation[idx] + gamma;
                             }
 
-                            let mut right = set.permutation_product_coset[idx];
+                            let mut right = set[idx];
                             for values in columns.iter().map(|&column| match column.column_type() {
                                 Any::Advice => &advice[column.index()],
                                 Any::Fixed => &fixed[column.index()],
@@ -634,25 +856,22 @@ impl<C: CurveAffine> Evaluator<C> {
 
                             *value = *value * y + ((left - right) * l_active_row[idx]);
                         }
-                        beta_term *= &extended_omega;
+                        beta_term *= &point_step;
                     }
                 });
             }
 
             // Lookups
+            #[cfg(not(feature = "mv-lookup"))]
             for (n, lookup) in lookups.iter().enumerate() {
                 // Polynomials required for this lookup.
                 // Calculated here so these only have to be kept in memory for the short time
                 // they are actually needed.
-                let product_coset = pk.vk.domain.coeff_to_extended(lookup.product_poly.clone());
-                let permuted_input_coset = pk
-                    .vk
-                    .domain
-                    .coeff_to_extended(lookup.permuted_input_poly.clone());
-                let permuted_table_coset = pk
-                    .vk
-                    .domain
-                    .coeff_to_extended(lookup.permuted_table_poly.clone());
+                let product_coset = part.coeff_to_extended(domain, &lookup.product_poly);
+                let permuted_input_coset =
+                    part.coeff_to_extended(domain, &lookup.permuted_input_poly);
+                let permuted_table_coset =
+                    part.coeff_to_extended(domain, &lookup.permuted_table_poly);
 
                 // Lookup constraints
                 let table = &lookup_values[n * size..(n + 1) * size];
@@ -696,6 +915,68 @@ impl<C: CurveAffine> Evaluator<C> {
                     }
                 });
             }
+            #[cfg(feature = "mv-lookup")]
+            for (n, lookup) in lookups.iter().enumerate() {
+                // Polynomials required for this lookup.
+                // Calculated here so these only have to be kept in memory for the short time
+                // they are actually needed.
+                let phi_coset = part.coeff_to_extended(domain, &lookup.phi_poly);
+                let multiplicity_coset = part.coeff_to_extended(domain, &lookup.multiplicity_poly);
+
+                // Lookup constraints
+                let inputs = &lookup_values[2 * n * size..(2 * n + 1) * size];
+                let table = &lookup_values[(2 * n + 1) * size..(2 * n + 2) * size];
+                parallelize(&mut values, |values, start| {
+                    for (i, value) in values.iter_mut().enumerate() {
+                        let idx = start + i;
+
+                        let r_next = get_rotation_idx(idx, 1, rot_scale, isize);
+
+                        // l_0(X) * \phi(X) = 0
+                        *value = *value * y + (phi_coset[idx] * l0[idx]);
+                        // l_last(X) * \phi(X) = 0
+                        *value = *value * y + (phi_coset[idx] * l_last[idx]);
+                        // (1 - (l_last(X) + l_blind(X))) * (
+                        //   (\phi(\omega X) - \phi(X)) (a(X) + \beta) (s(X) + \beta)
+                        //   - ((s(X) + \beta) - m(X) (a(X) + \beta))
+                        // ) = 0
+                        *value = *value * y
+                            + (((phi_coset[r_next] - phi_coset[idx]) * inputs[idx] * table[idx]
+                                - (table[idx] - multiplicity_coset[idx] * inputs[idx]))
+                                * l_active_row[idx]);
+                    }
+                });
+            }
+
+            // Shuffles
+            for (n, shuffle) in shuffles.iter().enumerate() {
+                let product_coset = part.coeff_to_extended(domain, &shuffle.product_poly);
+
+                // Shuffle constraints
+                let inputs = &shuffle_values[2 * n * size..(2 * n + 1) * size];
+                let shuffled = &shuffle_values[(2 * n + 1) * size..(2 * n + 2) * size];
+                parallelize(&mut values, |values, start| {
+                    for (i, value) in values.iter_mut().enumerate() {
+                        let idx = start + i;
+
+                        let r_next = get_rotation_idx(idx, 1, rot_scale, isize);
+
+                        // l_0(X) * (1 - z(X)) = 0
+                        *value = *value * y + ((one - product_coset[idx]) * l0[idx]);
+                        // l_last(X) * (z(X)^2 - z(X)) = 0
+                        *value = *value * y
+                            + ((product_coset[idx] * product_coset[idx] - product_coset[idx])
+                                * l_last[idx]);
+                        // (1 - (l_last(X) + l_blind(X))) * (
+                        //   z(\omega X) (s(X) + \gamma) - z(X) (a(X) + \gamma)
+                        // ) = 0
+                        *value = *value * y
+                            + ((product_coset[r_next] * shuffled[idx]
+                                - product_coset[idx] * inputs[idx])
+                                * l_active_row[idx]);
+                    }
+                });
+            }
         }
         values
     }
@@ -723,38 +1004,102 @@ impl<T> ThreadBox<T> {
     }
 }
 
-/// Simple evaluation of an expression
-pub fn evaluate<F: FieldExt, B: Basis>(
-    expression: &Expression<F>,
-    size: usize,
-    rot_scale: i32,
-    fixed: &[Polynomial<F, B>],
-    advice: &[Polynomial<F, B>],
-    instance: &[Polynomial<F, B>],
-) -> Vec<F> {
-    let mut values = vec![F::zero(); size];
-    let isize = size as i32;
-    parallelize(&mut values, |values, start| {
-        for (i, value) in values.iter_mut().enumerate() {
-            let idx = start + i;
-            *value = expression.evaluate(
-                &|scalar| scalar,
-                &|_| panic!("virtual selectors are removed during optimization"),
-                &|_, column_index, rotation| {
-                    fixed[column_index][get_rotation_idx(idx, rotation.0, rot_scale, isize)]
-                },
-                &|_, column_index, rotation| {
-                    advice[column_index][get_rotation_idx(idx, rotation.0, rot_scale, isize)]
-                },
-                &|_, column_index, rotation| {
-                    instance[column_index][get_rotation_idx(idx, rotation.0, rot_scale, isize)]
-                },
-                &|a| -a,
-                &|a, b| a + &b,
-                &|a, b| a * b,
-                &|a, scalar| a * scalar,
-            );
+#[cfg(test)]
+mod tests {
+    use super::{Evaluator, ValueSource};
+    use crate::plonk::{ConstraintSystem, Expression};
+    use crate::poly::{EvaluationDomain, Rotation};
+    use ff::Field;
+    use pairing::bn256::{Fr as Fp, G1Affine};
+    use rand_core::OsRng;
+
+    #[test]
+    fn skips_gates_of_zero_fixed_columns() {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        let a = cs.advice_column();
+        let b = cs.advice_column();
+        let q_used = cs.fixed_column();
+        let q_unused = cs.fixed_column();
+        cs.create_gate("used", |meta| {
+            let q = meta.query_fixed(q_used, Rotation::cur());
+            let a = meta.query_advice(a, Rotation::cur());
+            vec![q * a]
+        });
+        cs.create_gate("unused", |meta| {
+            let q = meta.query_fixed(q_unused, Rotation::cur());
+            let q_used = meta.query_fixed(q_used, Rotation::cur());
+            let a = meta.query_advice(a, Rotation::cur());
+            let b = meta.query_advice(b, Rotation::next());
+            vec![
+                q.clone() * (a.clone() * b.clone() - Expression::Constant(Fp::one())),
+                // The factor only needs to multiply the constraint as a whole.
+                (q.clone() * a + q * b) * Fp::from(3),
+                // A sum isn't a multiple of the column if one of its terms isn't.
+                q_used * Fp::from(3) + meta.query_fixed(q_unused, Rotation::next()),
+            ]
+        });
+
+        let all = Evaluator::<G1Affine>::new(&cs, &Default::default());
+        let skipped =
+            Evaluator::<G1Affine>::new(&cs, &Some(q_unused.index()).into_iter().collect());
+
+        assert_eq!(skipped.value_parts.len(), all.value_parts.len());
+        assert_ne!(skipped.value_parts[0], ValueSource::Constant(0));
+        assert_eq!(skipped.value_parts[1], ValueSource::Constant(0));
+        assert_eq!(skipped.value_parts[2], ValueSource::Constant(0));
+        assert_ne!(skipped.value_parts[3], ValueSource::Constant(0));
+        assert!(skipped.calculations.len() < all.calculations.len());
+    }
+
+    #[test]
+    fn compressed_matches_expressions() {
+        let domain = EvaluationDomain::<Fp>::new(1, 3);
+        let n = 1 << 3;
+        let columns: Vec<_> = (0..2)
+            .map(|_| {
+                domain.lagrange_from_vec((0..n).map(|_| Fp::random(OsRng)).collect::<Vec<_>>())
+            })
+            .collect();
+        let query = |column_index, rotation| Expression::<Fp>::Advice {
+            query_index: 0,
+            column_index,
+            rotation: Rotation(rotation),
+        };
+        let expressions = vec![
+            query(0, 0) * query(1, 1) - Expression::Constant(Fp::from(5)),
+            query(1, -1) * Fp::from(3) + query(0, 0),
+            -query(0, 1),
+        ];
+        let theta = Fp::random(OsRng);
+
+        let values = Evaluator::<G1Affine>::new_compressed(&expressions).evaluate_compressed(
+            n,
+            1,
+            &[],
+            &columns,
+            &[],
+            theta,
+        );
+
+        for (row, value) in values.into_iter().enumerate() {
+            let expected = expressions.iter().fold(Fp::zero(), |acc, expression| {
+                acc * theta
+                    + expression.evaluate(
+                        &|scalar| scalar,
+                        &|_| unreachable!(),
+                        &|_, _, _| unreachable!(),
+                        &|_, column_index, rotation| {
+                            columns[column_index]
+                                [(row as i32 + rotation.0).rem_euclid(n as i32) as usize]
+                        },
+                        &|_, _, _| unreachable!(),
+                        &|a| -a,
+                        &|a, b| a + b,
+                        &|a, b| a * b,
+                        &|a, scalar| a * scalar,
+                    )
+            });
+            assert_eq!(value, expected);
         }
-    });
-    values
+    }
 }
@@ -0,0 +1,135 @@
+use std::iter::FromIterator;
+
+use ff::Field;
+
+use super::{Error, InstanceLayoutError, VerifyingKey};
+use crate::arithmetic::CurveAffine;
+
+/// The instance columns of each circuit of a proof.
+///
+/// The prover and verifier take instances as nested slices, indexed by circuit,
+/// then by instance column, then by row. An `InstanceSet` owns them, so that
+/// they can be built up one circuit at a time, checked against a verifying key
+/// with [`InstanceSet::validate`], and lent to the prover or verifier with
+/// [`InstanceSet::with_slices`]:
+///
+/// ```ignore
+/// let mut instances = InstanceSet::new();
+/// instances.push(vec![vec![a, b], vec![]]);
+/// instances.push(vec![vec![c], vec![d, e, f]]);
+/// instances.validate(pk.get_vk(), circuits.len())?;
+/// instances.with_slices(|instances| {
+///     create_proof(&params, &pk, &circuits, instances, OsRng, &mut transcript)
+/// })?;
+/// ```
+///
+/// The columns of each circuit may hold any number of values, up to the usable
+/// rows of the circuit, and the rows after them are zero. All circuits of a
+/// proof share the constraint system of the proving key, so each one must be
+/// given every instance column. Omitted columns aren't padded with empty ones,
+/// so that a missing column is reported rather than proved as all zeros.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct InstanceSet<F> {
+    circuits: Vec<Vec<Vec<F>>>,
+}
+
+impl<F: Field> InstanceSet<F> {
+    /// Creates an empty set of instances.
+    pub fn new() -> Self {
+        InstanceSet { circuits: vec![] }
+    }
+
+    /// Adds the instance columns of the next circuit, and returns the index of
+    /// the circuit.
+    pub fn push(&mut self, columns: Vec<Vec<F>>) -> usize {
+        self.circuits.push(columns);
+        self.circuits.len() - 1
+    }
+
+    /// Returns the number of circuits in the set.
+    pub fn len(&self) -> usize {
+        self.circuits.len()
+    }
+
+    /// Returns `true` if the set holds no circuits.
+    pub fn is_empty(&self) -> bool {
+        self.circuits.is_empty()
+    }
+
+    /// Returns the instance columns of the circuit at `index`.
+    pub fn circuit(&self, index: usize) -> Option<&[Vec<F>]> {
+        self.circuits.get(index).map(|columns| &columns[..])
+    }
+
+    /// Checks that the set holds instances for `num_circuits` circuits, each
+    /// with the instance columns of `vk`, none longer than its usable rows.
+    ///
+    /// The prover makes the same check before starting on a proof; this lets
+    /// the instances be checked before the circuits are built.
+    pub fn validate<C: CurveAffine<ScalarExt = F>>(
+        &self,
+        vk: &VerifyingKey<C>,
+        num_circuits: usize,
+    ) -> Result<(), Error> {
+        self.with_slices(|instances| check_instance_layout(vk, num_circuits, instances))
+    }
+
+    /// Calls `f` with the instances as the nested slices that the prover and
+    /// verifier take.
+    pub fn with_slices<R>(&self, f: impl FnOnce(&[&[&[F]]]) -> R) -> R {
+        let columns: Vec<Vec<&[F]>> = self
+            .circuits
+            .iter()
+            .map(|columns| columns.iter().map(|column| &column[..]).collect())
+            .collect();
+        let circuits: Vec<&[&[F]]> = columns.iter().map(|columns| &columns[..]).collect();
+        f(&circuits)
+    }
+}
+
+impl<F: Field> FromIterator<Vec<Vec<F>>> for InstanceSet<F> {
+    fn from_iter<I: IntoIterator<Item = Vec<Vec<F>>>>(iter: I) -> Self {
+        InstanceSet {
+            circuits: iter.into_iter().collect(),
+        }
+    }
+}
+
+/// Checks that `instances` holds the instance columns of `vk` for each of
+/// `num_circuits` circuits, returning an [`Error::InstanceLayout`] describing
+/// the first mismatch.
+pub(crate) fn check_instance_layout<C: CurveAffine>(
+    vk: &VerifyingKey<C>,
+    num_circuits: usize,
+    instances: &[&[&[C::Scalar]]],
+) -> Result<(), Error> {
+    if instances.len() != num_circuits {
+        return Err(Error::InstanceLayout(InstanceLayoutError::CircuitCount {
+            circuits: num_circuits,
+            instances: instances.len(),
+        }));
+    }
+
+    let max = vk.cs.usable_rows(vk.domain.k()).end;
+    for (circuit, columns) in instances.iter().enumerate() {
+        if columns.len() != vk.cs.num_instance_columns {
+            return Err(Error::InstanceLayout(InstanceLayoutError::ColumnCount {
+                circuit,
+                columns: columns.len(),
+                expected: vk.cs.num_instance_columns,
+            }));
+        }
+        for (column, values) in columns.iter().enumerate() {
+            if values.len() > max {
+                return Err(Error::InstanceLayout(InstanceLayoutError::ColumnTooLong {
+                    circuit,
+                    column,
+                    len: values.len(),
+                    max,
+                }));
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,272 @@
+//! The JSON export of a [`PinnedVerificationKey`].
+
+use std::fmt::{self, Write};
+
+use super::{Any, Column, Expression, PinnedVerificationKey};
+use crate::arithmetic::CurveAffine;
+use crate::dev::format_polynomial;
+use crate::poly::Rotation;
+
+/// A JSON value. The fields of an object are written in the order in which
+/// they are given, so that the encoding is canonical.
+pub(crate) enum Json {
+    Null,
+    Bool(bool),
+    Number(i64),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(&'static str, Json)>),
+}
+
+impl Json {
+    fn write(&self, f: &mut fmt::Formatter<'_>, indent: usize) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(value) => write!(f, "{}", value),
+            Json::Number(value) => write!(f, "{}", value),
+            Json::String(value) => write_string(f, value),
+            Json::Array(values) if values.is_empty() => write!(f, "[]"),
+            Json::Array(values) => {
+                writeln!(f, "[")?;
+                for (i, value) in values.iter().enumerate() {
+                    write!(f, "{:1$}", "", indent + 2)?;
+                    value.write(f, indent + 2)?;
+                    writeln!(f, "{}", if i + 1 < values.len() { "," } else { "" })?;
+                }
+                write!(f, "{:1$}]", "", indent)
+            }
+            Json::Object(fields) if fields.is_empty() => write!(f, "{{}}"),
+            Json::Object(fields) => {
+                writeln!(f, "{{")?;
+                for (i, (key, value)) in fields.iter().enumerate() {
+                    write!(f, "{:1$}", "", indent + 2)?;
+                    write_string(f, key)?;
+                    write!(f, ": ")?;
+                    value.write(f, indent + 2)?;
+                    writeln!(f, "{}", if i + 1 < fields.len() { "," } else { "" })?;
+                }
+                write!(f, "{:1$}}}", "", indent)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.write(f, 0)
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter<'_>, value: &str) -> fmt::Result {
+    f.write_char('"')?;
+    for c in value.chars() {
+        match c {
+            '"' => f.write_str("\\\"")?,
+            '\\' => f.write_str("\\\\")?,
+            '\n' => f.write_str("\\n")?,
+            '\r' => f.write_str("\\r")?,
+            '\t' => f.write_str("\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => f.write_char(c)?,
+        }
+    }
+    f.write_char('"')
+}
+
+fn number(value: usize) -> Json {
+    Json::Number(value as i64)
+}
+
+fn optional(value: &Option<usize>) -> Json {
+    value.map_or(Json::Null, number)
+}
+
+fn column<C: Into<Column<Any>> + Copy>(column: &C) -> Json {
+    let column: Column<Any> = (*column).into();
+    let column_type = match column.column_type() {
+        Any::Advice => "advice",
+        Any::Fixed => "fixed",
+        Any::Instance => "instance",
+    };
+    Json::String(format!("{} {}", column_type, column.index()))
+}
+
+fn columns<C: Into<Column<Any>> + Copy>(columns: &[C]) -> Json {
+    Json::Array(columns.iter().map(column).collect())
+}
+
+fn queries<C: Into<Column<Any>> + Copy>(queries: &[(C, Rotation)]) -> Json {
+    Json::Array(
+        queries
+            .iter()
+            .map(|(c, rotation)| {
+                Json::Object(vec![
+                    ("column", column(c)),
+                    ("rotation", Json::Number(rotation.0 as i64)),
+                ])
+            })
+            .collect(),
+    )
+}
+
+fn point<C: CurveAffine>(point: &C) -> Json {
+    let mut hex = String::from("0x");
+    for byte in point.to_bytes().as_ref() {
+        write!(hex, "{:02x}", byte).unwrap();
+    }
+    Json::String(hex)
+}
+
+fn points<C: CurveAffine>(points: &[C]) -> Json {
+    Json::Array(points.iter().map(point).collect())
+}
+
+pub(crate) fn pinned_vk<C: CurveAffine>(pinned: &PinnedVerificationKey<'_, C>) -> Json {
+    let expressions = |expressions: &[Expression<C::Scalar>]| {
+        Json::Array(
+            expressions
+                .iter()
+                .map(|expression| Json::String(format_polynomial(expression)))
+                .collect(),
+        )
+    };
+
+    let domain = &pinned.domain;
+    let cs = &pinned.cs;
+
+    let gates = cs
+        .gates
+        .0
+        .iter()
+        .map(|gate| {
+            let constraints = gate
+                .polynomials()
+                .iter()
+                .enumerate()
+                .map(|(i, poly)| {
+                    Json::Object(vec![
+                        ("name", Json::String(gate.constraint_name(i).to_string())),
+                        ("polynomial", Json::String(format_polynomial(poly))),
+                    ])
+                })
+                .collect();
+            Json::Object(vec![
+                ("name", Json::String(gate.name().to_string())),
+                ("constraints", Json::Array(constraints)),
+            ])
+        })
+        .collect();
+    let lookups = cs
+        .lookups
+        .iter()
+        .map(|lookup| {
+            Json::Object(vec![
+                ("name", Json::String(lookup.name().to_string())),
+                ("input_expressions", expressions(lookup.input_expressions())),
+                ("table_expressions", expressions(lookup.table_expressions())),
+            ])
+        })
+        .collect();
+    let shuffles = cs
+        .shuffles
+        .iter()
+        .map(|shuffle| {
+            Json::Object(vec![
+                ("name", Json::String(shuffle.name().to_string())),
+                (
+                    "input_expressions",
+                    expressions(shuffle.input_expressions()),
+                ),
+                (
+                    "shuffle_expressions",
+                    expressions(shuffle.shuffle_expressions()),
+                ),
+            ])
+        })
+        .collect();
+
+    Json::Object(vec![
+        (
+            "base_modulus",
+            Json::String(pinned.base_modulus.to_string()),
+        ),
+        (
+            "scalar_modulus",
+            Json::String(pinned.scalar_modulus.to_string()),
+        ),
+        (
+            "domain",
+            Json::Object(vec![
+                ("k", number(*domain.k as usize)),
+                ("extended_k", number(*domain.extended_k as usize)),
+                ("omega", Json::String(format!("{:?}", domain.omega))),
+            ]),
+        ),
+        (
+            "cs",
+            Json::Object(vec![
+                ("num_fixed_columns", number(*cs.num_fixed_columns)),
+                ("num_advice_columns", number(*cs.num_advice_columns)),
+                ("num_instance_columns", number(*cs.num_instance_columns)),
+                ("num_selectors", number(*cs.num_selectors)),
+                ("selector_map", columns(cs.selector_map)),
+                ("gates", Json::Array(gates)),
+                ("advice_queries", queries(cs.advice_queries)),
+                ("instance_queries", queries(cs.instance_queries)),
+                ("fixed_queries", queries(cs.fixed_queries)),
+                (
+                    "permutation_columns",
+                    columns(&cs.permutation.get_columns()),
+                ),
+                ("lookups", Json::Array(lookups)),
+                ("shuffles", Json::Array(shuffles)),
+                ("constants", columns(cs.constants)),
+                ("configuration_columns", columns(cs.configuration_columns)),
+                (
+                    "unblinded_advice_columns",
+                    columns(cs.unblinded_advice_columns),
+                ),
+                ("zero_knowledge", Json::Bool(*cs.zero_knowledge)),
+                (
+                    "minimum_blinding_factors",
+                    optional(cs.minimum_blinding_factors),
+                ),
+                ("minimum_degree", optional(cs.minimum_degree)),
+            ]),
+        ),
+        ("fixed_commitments", points(pinned.fixed_commitments)),
+        (
+            "permutation_commitments",
+            points(pinned.permutation.commitments()),
+        ),
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Json;
+
+    #[test]
+    fn encoding() {
+        let json = Json::Object(vec![
+            ("name", Json::String("a \"quoted\"\nname".to_string())),
+            ("empty", Json::Array(vec![])),
+            (
+                "values",
+                Json::Array(vec![Json::Number(-1), Json::Bool(true), Json::Null]),
+            ),
+        ]);
+        assert_eq!(
+            json.to_string(),
+            r#"{
+  "name": "a \"quoted\"\nname",
+  "empty": [],
+  "values": [
+    -1,
+    true,
+    null
+  ]
+}"#
+        );
+    }
+}
@@ -18,19 +18,26 @@ use crate::{
     plonk::Expression,
     poly::{
         commitment::{Blind, Params},
-        EvaluationDomain, Rotation,
+        domain::extended_k,
+        max_k, EvaluationDomain, Rotation,
     },
 };
 
 use crate::arithmetic::parallelize;
+use crate::logging::{Stage, KEYGEN};
+use log::{info, warn};
 
+#[allow(clippy::type_complexity)]
 pub(crate) fn create_domain<C, ConcreteCircuit>(
     params: &Params<C>,
-) -> (
-    EvaluationDomain<C::Scalar>,
-    ConstraintSystem<C::Scalar>,
-    ConcreteCircuit::Config,
-)
+) -> Result<
+    (
+        EvaluationDomain<C::Scalar>,
+        ConstraintSystem<C::Scalar>,
+        ConcreteCircuit::Config,
+    ),
+    Error,
+>
 where
     C: CurveAffine,
     ConcreteCircuit: Circuit<C::Scalar>,
@@ -38,11 +45,29 @@ where
     let mut cs = ConstraintSystem::default();
     let config = ConcreteCircuit::configure(&mut cs);
 
-    let degree = cs.degree();
+    let domain = domain_for(params, &cs)?;
 
-    let domain = EvaluationDomain::new(degree as u32, params.k);
+    Ok((domain, cs, config))
+}
+
+/// Creates the evaluation domain of `cs` at the size of `params`.
+fn domain_for<C: CurveAffine>(
+    params: &Params<C>,
+    cs: &ConstraintSystem<C::Scalar>,
+) -> Result<EvaluationDomain<C::Scalar>, Error> {
+    let degree = cs.degree() as u32;
+
+    // The extended domain is the largest one, 2^{extended_k(degree, 0)}
+    // times larger than the circuit.
+    let max_k = max_k::<C::Scalar>().saturating_sub(extended_k(degree, 0));
+    if params.k > max_k {
+        return Err(Error::TooManyRows {
+            current_k: params.k,
+            max_k,
+        });
+    }
 
-    (domain, cs, config)
+    Ok(EvaluationDomain::new(degree, params.k))
 }
 
 /// Assembly to be used in circuit synthesis.
@@ -136,6 +161,30 @@ impl<F: Field> Assignment<F> for Assembly<F> {
         Ok(())
     }
 
+    fn assign_fixed_column<A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Fixed>,
+        row: usize,
+        values: &[Assigned<F>],
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        if row + values.len() > self.usable_rows.end {
+            return Err(Error::not_enough_rows_available(self.k));
+        }
+
+        let col = self
+            .fixed
+            .get_mut(column.index())
+            .ok_or(Error::BoundsFailure)?;
+        col[row..][..values.len()].copy_from_slice(values);
+
+        Ok(())
+    }
+
     fn copy(
         &mut self,
         left_column: Column<Any>,
@@ -186,39 +235,228 @@ impl<F: Field> Assignment<F> for Assembly<F> {
     }
 }
 
-/// Generate a `VerifyingKey` from an instance of `Circuit`.
-pub fn keygen_vk<C, ConcreteCircuit>(
+/// An assignment made during synthesis, which doesn't depend on the size of the
+/// circuit.
+#[derive(Debug)]
+enum Recorded<F: Field> {
+    Selector(Selector, usize),
+    Fixed(Column<Fixed>, usize, Assigned<F>),
+    Copy(Column<Any>, usize, Column<Any>, usize),
+    Fill(Column<Fixed>, usize, Option<Assigned<F>>),
+}
+
+/// Records the assignments of a circuit's synthesis, so that they can be replayed
+/// into an [`Assembly`] of each size.
+#[derive(Debug)]
+struct Recording<F: Field> {
+    assignments: Vec<Recorded<F>>,
+    // The number of rows used by advice and instance cells, which are otherwise
+    // ignored.
+    rows: usize,
+}
+
+impl<F: Field> Recording<F> {
+    fn replay(&self, assembly: &mut Assembly<F>) -> Result<(), Error> {
+        if self.rows > assembly.usable_rows.end {
+            return Err(Error::not_enough_rows_available(assembly.k));
+        }
+
+        for assignment in &self.assignments {
+            match *assignment {
+                Recorded::Selector(selector, row) => {
+                    assembly.enable_selector(|| "", &selector, row)?
+                }
+                Recorded::Fixed(column, row, value) => {
+                    assembly.assign_fixed(|| "", column, row, || Ok(value))?
+                }
+                Recorded::Copy(left_column, left_row, right_column, right_row) => {
+                    assembly.copy(left_column, left_row, right_column, right_row)?
+                }
+                Recorded::Fill(column, from_row, value) => {
+                    assembly.fill_from_row(column, from_row, value)?
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl<F: Field> Assignment<F> for Recording<F> {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about regions in this context.
+    }
+
+    fn exit_region(&mut self) {
+        // Do nothing; we don't care about regions in this context.
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.assignments.push(Recorded::Selector(*selector, row));
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Option<F>, Error> {
+        // There is no instance in this context. The row is only checked on replay,
+        // so the recording can't take it into account; the layouters assign every
+        // queried instance value to an advice cell in the same region anyway.
+        Ok(None)
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.rows = std::cmp::max(self.rows, row + 1);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.assignments
+            .push(Recorded::Fixed(column, row, to()?.into()));
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        left_column: Column<Any>,
+        left_row: usize,
+        right_column: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.assignments.push(Recorded::Copy(
+            left_column,
+            left_row,
+            right_column,
+            right_row,
+        ));
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        from_row: usize,
+        to: Option<Assigned<F>>,
+    ) -> Result<(), Error> {
+        self.assignments.push(Recorded::Fill(column, from_row, to));
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+}
+
+/// Synthesizes `circuit` into an [`Assembly`], returning the compressed
+/// constraint system, the fixed columns (including those generated for
+/// selectors) and the permutation assembly.
+#[allow(clippy::type_complexity)]
+fn synthesize_fixed<C, ConcreteCircuit>(
     params: &Params<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    cs: ConstraintSystem<C::Scalar>,
+    config: ConcreteCircuit::Config,
     circuit: &ConcreteCircuit,
-) -> Result<VerifyingKey<C>, Error>
+) -> Result<
+    (
+        ConstraintSystem<C::Scalar>,
+        Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+        permutation::keygen::Assembly,
+    ),
+    Error,
+>
 where
     C: CurveAffine,
     ConcreteCircuit: Circuit<C::Scalar>,
 {
-    let (domain, cs, config) = create_domain::<C, ConcreteCircuit>(params);
+    let constants = cs.constants.clone();
+    assemble(params, domain, cs, circuit, |assembly| {
+        ConcreteCircuit::FloorPlanner::synthesize(assembly, circuit, config, constants)
+    })
+}
 
+/// Fills an [`Assembly`] of the size of `params` with `synthesize`, and returns
+/// the output of [`synthesize_fixed`].
+#[allow(clippy::type_complexity)]
+fn assemble<C, ConcreteCircuit>(
+    params: &Params<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    cs: ConstraintSystem<C::Scalar>,
+    circuit: &ConcreteCircuit,
+    synthesize: impl FnOnce(&mut Assembly<C::Scalar>) -> Result<(), Error>,
+) -> Result<
+    (
+        ConstraintSystem<C::Scalar>,
+        Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+        permutation::keygen::Assembly,
+    ),
+    Error,
+>
+where
+    C: CurveAffine,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
     if (params.n as usize) < cs.minimum_rows() {
-        return Err(Error::not_enough_rows_available(params.k));
+        return Err(Error::not_enough_rows_available(params.k).with_minimum_k(circuit));
     }
 
+    let stage = Stage::start(KEYGEN, "synthesize");
+
     let mut assembly: Assembly<C::Scalar> = Assembly {
         k: params.k,
         fixed: vec![domain.empty_lagrange_assigned(); cs.num_fixed_columns],
         permutation: permutation::keygen::Assembly::new(params.n as usize, &cs.permutation),
         selectors: vec![vec![false; params.n as usize]; cs.num_selectors],
-        usable_rows: 0..params.n as usize - (cs.blinding_factors() + 1),
+        usable_rows: cs.usable_rows(params.k),
         _marker: std::marker::PhantomData,
     };
 
     // Synthesize the circuit to obtain URS
-    ConcreteCircuit::FloorPlanner::synthesize(
-        &mut assembly,
-        circuit,
-        config,
-        cs.constants.clone(),
-    )?;
+    synthesize(&mut assembly).map_err(|e| e.with_minimum_k(circuit))?;
+    cs.assign_keygen_tables(params.k, |column, row, value| {
+        assembly.fixed[column.index()][row] = value.into();
+    })
+    .map_err(|e| e.with_minimum_k(circuit))?;
 
-    let mut fixed = batch_invert_assigned(assembly.fixed);
+    let mut fixed = batch_invert_assigned(&assembly.fixed);
     let (cs, selector_polys) = cs.compress_selectors(assembly.selectors);
     fixed.extend(
         selector_polys
@@ -226,21 +464,76 @@ where
             .map(|poly| domain.lagrange_from_vec(poly)),
     );
 
-    let permutation_vk = assembly
-        .permutation
-        .build_vk(params, &domain, &cs.permutation);
+    stage.finish();
+
+    Ok((cs, fixed, assembly.permutation))
+}
+
+/// Builds a `VerifyingKey` from the output of [`synthesize_fixed`].
+fn build_vk<C: CurveAffine>(
+    params: &Params<C>,
+    domain: EvaluationDomain<C::Scalar>,
+    cs: ConstraintSystem<C::Scalar>,
+    fixed: &[Polynomial<C::Scalar, LagrangeCoeff>],
+    permutation: &permutation::keygen::Assembly,
+) -> VerifyingKey<C> {
+    let stage = Stage::start(KEYGEN, "vk");
+
+    let permutation_vk = permutation.build_vk(params, &domain, &cs.permutation);
 
     let fixed_commitments = fixed
         .iter()
         .map(|poly| params.commit_lagrange(poly).to_affine())
         .collect();
 
-    Ok(VerifyingKey {
+    stage.finish();
+    info!(
+        target: KEYGEN,
+        "event=vk k={} fixed_columns={} advice_columns={} instance_columns={} gates={} lookups={} shuffles={}",
+        params.k,
+        cs.num_fixed_columns,
+        cs.num_advice_columns,
+        cs.num_instance_columns,
+        cs.gates.len(),
+        cs.lookups.len(),
+        cs.shuffles.len()
+    );
+    let rotated_instance_queries = cs.rotated_instance_queries();
+    if !rotated_instance_queries.is_empty() {
+        warn!(
+            target: KEYGEN,
+            "event=rotated_instance_queries count={} queries={}",
+            rotated_instance_queries.len(),
+            rotated_instance_queries
+                .iter()
+                .map(|(column, rotation)| format!("I{}@{}", column.index(), rotation.0))
+                .collect::<Vec<_>>()
+                .join(",")
+        );
+    }
+
+    VerifyingKey {
         domain,
         fixed_commitments,
         permutation: permutation_vk,
         cs,
-    })
+    }
+}
+
+/// Generate a `VerifyingKey` from an instance of `Circuit`.
+pub fn keygen_vk<C, ConcreteCircuit>(
+    params: &Params<C>,
+    circuit: &ConcreteCircuit,
+) -> Result<VerifyingKey<C>, Error>
+where
+    C: CurveAffine,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    let (domain, cs, config) = create_domain::<C, ConcreteCircuit>(params)?;
+
+    let (cs, fixed, permutation) = synthesize_fixed(params, &domain, cs, config, circuit)?;
+
+    Ok(build_vk(params, domain, cs, &fixed, &permutation))
 }
 
 /// Generate a `ProvingKey` from a `VerifyingKey` and an instance of `Circuit`.
@@ -256,36 +549,93 @@ where
     let mut cs = ConstraintSystem::default();
     let config = ConcreteCircuit::configure(&mut cs);
 
-    let cs = cs;
+    let (_, fixed, permutation) = synthesize_fixed(params, &vk.domain, cs, config, circuit)?;
 
-    if (params.n as usize) < cs.minimum_rows() {
-        return Err(Error::not_enough_rows_available(params.k));
-    }
+    Ok(build_pk(params, vk, fixed, permutation))
+}
 
-    let mut assembly: Assembly<C::Scalar> = Assembly {
-        k: params.k,
-        fixed: vec![vk.domain.empty_lagrange_assigned(); cs.num_fixed_columns],
-        permutation: permutation::keygen::Assembly::new(params.n as usize, &cs.permutation),
-        selectors: vec![vec![false; params.n as usize]; cs.num_selectors],
-        usable_rows: 0..params.n as usize - (cs.blinding_factors() + 1),
-        _marker: std::marker::PhantomData,
-    };
+/// Generate a `ProvingKey` (which contains its `VerifyingKey`) from an instance
+/// of `Circuit`.
+///
+/// This is equivalent to calling [`keygen_vk`] followed by [`keygen_pk`], but
+/// only synthesizes the circuit once.
+pub fn keygen_pk2<C, ConcreteCircuit>(
+    params: &Params<C>,
+    circuit: &ConcreteCircuit,
+) -> Result<ProvingKey<C>, Error>
+where
+    C: CurveAffine,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    let (domain, cs, config) = create_domain::<C, ConcreteCircuit>(params)?;
 
-    // Synthesize the circuit to obtain URS
+    let (cs, fixed, permutation) = synthesize_fixed(params, &domain, cs, config, circuit)?;
+
+    let vk = build_vk(params, domain, cs, &fixed, &permutation);
+
+    Ok(build_pk(params, vk, fixed, permutation))
+}
+
+/// Generate a `ProvingKey` for each of `params`, from a single synthesis of
+/// `circuit`.
+///
+/// The circuit is configured and synthesized once, and its assignments are then
+/// laid out at each size. This suits provers that keep keys for several sizes of
+/// the same circuit, and pick the smallest one that fits each proof. The keys are
+/// returned in the order of `params`, and each is the one [`keygen_pk2`] would
+/// generate with the same parameters.
+///
+/// Returns `Error::NotEnoughRowsAvailable` if the circuit doesn't fit in any of
+/// `params`.
+pub fn keygen_pks<C, ConcreteCircuit>(
+    params: &[&Params<C>],
+    circuit: &ConcreteCircuit,
+) -> Result<Vec<ProvingKey<C>>, Error>
+where
+    C: CurveAffine,
+    ConcreteCircuit: Circuit<C::Scalar>,
+{
+    let mut cs = ConstraintSystem::default();
+    let config = ConcreteCircuit::configure(&mut cs);
+
+    let mut recording = Recording {
+        assignments: vec![],
+        rows: 0,
+    };
     ConcreteCircuit::FloorPlanner::synthesize(
-        &mut assembly,
+        &mut recording,
         circuit,
         config,
         cs.constants.clone(),
     )?;
 
-    let mut fixed = batch_invert_assigned(assembly.fixed);
-    let (cs, selector_polys) = cs.compress_selectors(assembly.selectors);
-    fixed.extend(
-        selector_polys
-            .into_iter()
-            .map(|poly| vk.domain.lagrange_from_vec(poly)),
-    );
+    params
+        .iter()
+        .map(|params| {
+            let domain = domain_for(params, &cs)?;
+
+            let (cs, fixed, permutation) =
+                assemble(params, &domain, cs.clone(), circuit, |assembly| {
+                    recording.replay(assembly)
+                })?;
+
+            let vk = build_vk(params, domain, cs, &fixed, &permutation);
+
+            Ok(build_pk(params, vk, fixed, permutation))
+        })
+        .collect()
+}
+
+/// Builds a `ProvingKey` from its `VerifyingKey` and the output of
+/// [`synthesize_fixed`].
+fn build_pk<C: CurveAffine>(
+    params: &Params<C>,
+    vk: VerifyingKey<C>,
+    fixed: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
+    permutation: permutation::keygen::Assembly,
+) -> ProvingKey<C> {
+    let stage = Stage::start(KEYGEN, "pk");
+    let cs = &vk.cs;
 
     let fixed_polys: Vec<_> = fixed
         .iter()
@@ -297,9 +647,7 @@ where
         .map(|poly| vk.domain.coeff_to_extended(poly.clone()))
         .collect();
 
-    let permutation_pk = assembly
-        .permutation
-        .build_pk(params, &vk.domain, &cs.permutation);
+    let permutation_pk = permutation.build_pk(params, &vk.domain, &cs.permutation);
 
     // Compute l_0(X)
     // TODO: this can be done more efficiently
@@ -334,10 +682,27 @@ where
         }
     });
 
-    // Compute the optimized evaluation data structure
-    let ev = Evaluator::new(&vk.cs);
+    // Compute the optimized evaluation data structure, skipping the gates that
+    // are multiplied by a fixed column that is zero on every row, such as a
+    // complex selector that the circuit never enables. Simple selectors may be
+    // combined with others into a column that isn't zero. Configuration columns
+    // are only assigned when proving, so they are never skipped.
+    let zero_fixed_columns = fixed
+        .iter()
+        .enumerate()
+        .filter(|(index, values)| {
+            !cs.configuration_columns
+                .iter()
+                .any(|column| column.index() == *index)
+                && values.iter().all(|value| *value == C::Scalar::zero())
+        })
+        .map(|(index, _)| index)
+        .collect();
+    let ev = Evaluator::new(cs, &zero_fixed_columns);
+
+    stage.finish();
 
-    Ok(ProvingKey {
+    ProvingKey {
         vk,
         l0,
         l_last,
@@ -347,5 +712,5 @@ where
         fixed_cosets,
         permutation: permutation_pk,
         ev,
-    })
+    }
 }
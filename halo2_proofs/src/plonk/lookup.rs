@@ -1,21 +1,31 @@
 use super::circuit::Expression;
 use ff::Field;
 
+#[cfg(not(feature = "mv-lookup"))]
 pub(crate) mod prover;
+#[cfg(not(feature = "mv-lookup"))]
 pub(crate) mod verifier;
 
+#[cfg(feature = "mv-lookup")]
+mod mv;
+#[cfg(feature = "mv-lookup")]
+pub(crate) use mv::{prover, verifier};
+
+/// A lookup argument, which constrains the input expressions to take values
+/// that appear in the table expressions, as returned by
+/// [`ConstraintSystem::lookups`](super::ConstraintSystem::lookups).
 #[derive(Clone, Debug)]
-pub(crate) struct Argument<F: Field> {
-    pub name: &'static str,
-    pub input_expressions: Vec<Expression<F>>,
-    pub table_expressions: Vec<Expression<F>>,
+pub struct Argument<F: Field> {
+    pub(crate) name: &'static str,
+    pub(crate) input_expressions: Vec<Expression<F>>,
+    pub(crate) table_expressions: Vec<Expression<F>>,
 }
 
 impl<F: Field> Argument<F> {
     /// Constructs a new lookup argument.
     ///
     /// `table_map` is a sequence of `(input, table)` tuples.
-    pub fn new(name: &'static str, table_map: Vec<(Expression<F>, Expression<F>)>) -> Self {
+    pub(crate) fn new(name: &'static str, table_map: Vec<(Expression<F>, Expression<F>)>) -> Self {
         let (input_expressions, table_expressions) = table_map.into_iter().unzip();
         Argument {
             name,
@@ -24,6 +34,22 @@ impl<F: Field> Argument<F> {
         }
     }
 
+    /// Returns the name of the lookup argument.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the input expressions of the lookup argument.
+    pub fn input_expressions(&self) -> &[Expression<F>] {
+        &self.input_expressions
+    }
+
+    /// Returns the table expressions of the lookup argument, one for each input
+    /// expression.
+    pub fn table_expressions(&self) -> &[Expression<F>] {
+        &self.table_expressions
+    }
+
     pub(crate) fn required_degree(&self) -> usize {
         assert_eq!(self.input_expressions.len(), self.table_expressions.len());
 
@@ -51,6 +77,13 @@ impl<F: Field> Argument<F> {
         // value of a' is the same as the current value.
         // degree 3:
         // (1 - (l_last(X) + l_blind(X))) * (a′(X) − s′(X))⋅(a′(X) − a′(\omega^{-1} X)) = 0
+        //
+        // With the `mv-lookup` feature, the running sum argument has the same
+        // degree bound:
+        // (1 - (l_last(X) + l_blind(X))) * (
+        //   (\phi(\omega X) - \phi(X)) (a(X) + \beta) (s(X) + \beta)
+        //   - ((s(X) + \beta) - m(X) (a(X) + \beta))
+        // ) = 0
         let mut input_degree = 1;
         for expr in self.input_expressions.iter() {
             input_degree = std::cmp::max(input_degree, expr.degree());
@@ -0,0 +1,19 @@
+//! A logarithmic-derivative ("LogUp") variant of the lookup argument, enabled
+//! with the `mv-lookup` feature.
+//!
+//! Instead of committing to a permuted input, a permuted table and a grand
+//! product, the prover commits to the multiplicity $m(X)$ of every table row
+//! and to a running sum
+//!
+//! $$\phi(\omega X) = \phi(X) + \frac{1}{f(X) + \beta} - \frac{m(X)}{t(X) + \beta}$$
+//!
+//! where $f$ and $t$ are the $\theta$-compressed input and table expressions.
+//! The lookup holds if $\phi$ starts and ends at zero over the usable rows.
+//!
+//! The submodules expose the same phases as the default argument, so that
+//! the prover and verifier do not need to know which variant is enabled: the
+//! "permuted" commitment is the multiplicity commitment, and the "product"
+//! commitment is the running sum.
+
+pub(crate) mod prover;
+pub(crate) mod verifier;
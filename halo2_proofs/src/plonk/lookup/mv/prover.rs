@@ -0,0 +1,332 @@
+use super::super::super::{
+    circuit::Expression, ChallengeBeta, ChallengeGamma, ChallengeTheta, ChallengeX, Error,
+    ProvingKey,
+};
+use super::super::Argument;
+use crate::plonk::evaluation::Evaluator;
+use crate::{
+    arithmetic::{eval_polynomial, parallelize, CurveAffine, FieldExt},
+    poly::{
+        commitment::Params, multiopen::ProverQuery, Coeff, EvaluationDomain, LagrangeCoeff,
+        Polynomial, Rotation,
+    },
+    transcript::{EncodedChallenge, TranscriptWrite},
+};
+use group::{
+    ff::{BatchInvert, Field},
+    Curve,
+};
+use rand_core::RngCore;
+use std::{
+    collections::BTreeMap,
+    iter,
+    ops::{Mul, MulAssign},
+};
+
+#[derive(Debug)]
+pub(in crate::plonk) struct Permuted<C: CurveAffine> {
+    compressed_input_expression: Polynomial<C::Scalar, LagrangeCoeff>,
+    compressed_table_expression: Polynomial<C::Scalar, LagrangeCoeff>,
+    multiplicity_expression: Polynomial<C::Scalar, LagrangeCoeff>,
+    multiplicity_poly: Polynomial<C::Scalar, Coeff>,
+}
+
+#[derive(Debug)]
+pub(in crate::plonk) struct Committed<C: CurveAffine> {
+    pub(in crate::plonk) multiplicity_poly: Polynomial<C::Scalar, Coeff>,
+    pub(in crate::plonk) phi_poly: Polynomial<C::Scalar, Coeff>,
+}
+
+pub(in crate::plonk) struct Evaluated<C: CurveAffine> {
+    constructed: Committed<C>,
+}
+
+impl<F: FieldExt> Argument<F> {
+    /// Given a Lookup with input expressions [A_0, A_1, ..., A_{m-1}] and table expressions
+    /// [S_0, S_1, ..., S_{m-1}], this method
+    /// - constructs A_compressed = \theta^{m-1} A_0 + theta^{m-2} A_1 + ... + \theta A_{m-2} + A_{m-1}
+    ///   and S_compressed = \theta^{m-1} S_0 + theta^{m-2} S_1 + ... + \theta S_{m-2} + S_{m-1},
+    /// - counts how many times each row of S_compressed is looked up by A_compressed, and
+    /// - commits to these multiplicities.
+    pub(in crate::plonk) fn commit_permuted<
+        'a,
+        C,
+        E: EncodedChallenge<C>,
+        R: RngCore,
+        T: TranscriptWrite<C, E>,
+    >(
+        &self,
+        pk: &ProvingKey<C>,
+        params: &Params<C>,
+        domain: &EvaluationDomain<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        advice_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        fixed_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        instance_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        transcript: &mut T,
+        mut rng: R,
+    ) -> Result<Permuted<C>, Error>
+    where
+        C: CurveAffine<ScalarExt = F>,
+        C::Curve: Mul<F, Output = C::Curve> + MulAssign<F>,
+    {
+        // Closure to get values of expressions and compress them
+        let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
+            domain.lagrange_from_vec(
+                Evaluator::<C>::new_compressed(expressions).evaluate_compressed(
+                    params.n as usize,
+                    1,
+                    fixed_values,
+                    advice_values,
+                    instance_values,
+                    *theta,
+                ),
+            )
+        };
+
+        // Get values of input expressions involved in the lookup and compress them
+        let compressed_input_expression = compress_expressions(&self.input_expressions);
+
+        // Get values of table expressions involved in the lookup and compress them
+        let compressed_table_expression = compress_expressions(&self.table_expressions);
+
+        let multiplicity_expression = compute_multiplicities::<C, _>(
+            pk,
+            params,
+            domain,
+            &mut rng,
+            &compressed_input_expression,
+            &compressed_table_expression,
+        )?;
+
+        // Commit to the multiplicities
+        let multiplicity_commitment = params.commit_lagrange(&multiplicity_expression).to_affine();
+        let multiplicity_poly = pk
+            .vk
+            .domain
+            .lagrange_to_coeff(multiplicity_expression.clone());
+
+        // Hash multiplicity commitment
+        transcript.write_point(multiplicity_commitment)?;
+
+        Ok(Permuted {
+            compressed_input_expression,
+            compressed_table_expression,
+            multiplicity_expression,
+            multiplicity_poly,
+        })
+    }
+}
+
+impl<C: CurveAffine> Permuted<C> {
+    /// Given the compressed input and table expressions and the multiplicities,
+    /// this method constructs and commits to the running sum polynomial
+    ///
+    /// \phi(\omega^{i+1}) = \phi(\omega^i) + 1 / (a(\omega^i) + \beta) - m(\omega^i) / (s(\omega^i) + \beta)
+    ///
+    /// starting from \phi(\omega^0) = 0. The gamma challenge is not used.
+    pub(in crate::plonk) fn commit_product<
+        E: EncodedChallenge<C>,
+        R: RngCore,
+        T: TranscriptWrite<C, E>,
+    >(
+        self,
+        pk: &ProvingKey<C>,
+        params: &Params<C>,
+        beta: ChallengeBeta<C>,
+        _gamma: ChallengeGamma<C>,
+        transcript: &mut T,
+        mut rng: R,
+    ) -> Result<Committed<C>, Error> {
+        let blinding_factors = pk.vk.cs.blinding_factors();
+
+        // 1 / (a(\omega^i) + \beta)
+        let mut input_inverses = vec![C::Scalar::zero(); params.n as usize];
+        parallelize(&mut input_inverses, |input_inverses, start| {
+            for (inverse, input_value) in input_inverses
+                .iter_mut()
+                .zip(self.compressed_input_expression[start..].iter())
+            {
+                *inverse = *beta + input_value;
+            }
+        });
+        input_inverses.iter_mut().batch_invert();
+
+        // 1 / (s(\omega^i) + \beta)
+        let mut table_inverses = vec![C::Scalar::zero(); params.n as usize];
+        parallelize(&mut table_inverses, |table_inverses, start| {
+            for (inverse, table_value) in table_inverses
+                .iter_mut()
+                .zip(self.compressed_table_expression[start..].iter())
+            {
+                *inverse = *beta + table_value;
+            }
+        });
+        table_inverses.iter_mut().batch_invert();
+
+        // 1 / (a(\omega^i) + \beta) - m(\omega^i) / (s(\omega^i) + \beta)
+        let mut summands = input_inverses;
+        parallelize(&mut summands, |summands, start| {
+            for (i, summand) in summands.iter_mut().enumerate() {
+                let i = i + start;
+                *summand -= self.multiplicity_expression[i] * table_inverses[i];
+            }
+        });
+
+        // Compute the evaluations of the running sum polynomial
+        // over our domain, starting with phi[0] = 0
+        let phi = iter::once(C::Scalar::zero())
+            .chain(summands)
+            .scan(C::Scalar::zero(), |state, cur| {
+                *state += &cur;
+                Some(*state)
+            })
+            // Take all rows including the "last" row which should
+            // be zero
+            .take(params.n as usize - blinding_factors)
+            // Chain random blinding factors.
+            .chain((0..blinding_factors).map(|_| C::Scalar::random(&mut rng)))
+            .collect::<Vec<_>>();
+        assert_eq!(phi.len(), params.n as usize);
+        let phi = pk.vk.domain.lagrange_from_vec(phi);
+
+        #[cfg(feature = "sanity-checks")]
+        // This test works only with intermediate representations in this method.
+        // It can be used for debugging purposes.
+        {
+            // While in Lagrange basis, check that the running sum is correctly constructed
+            let u = (params.n as usize) - (blinding_factors + 1);
+
+            // l_0(X) * \phi(X) = 0
+            assert_eq!(phi[0], C::Scalar::zero());
+
+            // (\phi(\omega X) - \phi(X)) (a(X) + \beta) (s(X) + \beta)
+            // - ((s(X) + \beta) - m(X) (a(X) + \beta))
+            for i in 0..u {
+                let input_term = self.compressed_input_expression[i] + &*beta;
+                let table_term = self.compressed_table_expression[i] + &*beta;
+
+                let left = (phi[i + 1] - &phi[i]) * &input_term * &table_term;
+                let right = table_term - &(self.multiplicity_expression[i] * &input_term);
+
+                assert_eq!(left, right);
+            }
+
+            // l_last(X) * \phi(X) = 0
+            assert_eq!(phi[u], C::Scalar::zero());
+        }
+        let phi_commitment = params.commit_lagrange(&phi).to_affine();
+
+        // Hash running sum commitment
+        transcript.write_point(phi_commitment)?;
+
+        let phi = pk.vk.domain.lagrange_to_coeff(phi);
+
+        Ok(Committed::<C> {
+            multiplicity_poly: self.multiplicity_poly,
+            phi_poly: phi,
+        })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    pub(in crate::plonk) fn evaluate<E: EncodedChallenge<C>, T: TranscriptWrite<C, E>>(
+        self,
+        pk: &ProvingKey<C>,
+        x: ChallengeX<C>,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let domain = &pk.vk.domain;
+        let x_next = domain.rotate_omega(*x, Rotation::next());
+
+        let phi_eval = eval_polynomial(&self.phi_poly, *x);
+        let phi_next_eval = eval_polynomial(&self.phi_poly, x_next);
+        let multiplicity_eval = eval_polynomial(&self.multiplicity_poly, *x);
+
+        // Hash each advice evaluation
+        for eval in iter::empty()
+            .chain(Some(phi_eval))
+            .chain(Some(phi_next_eval))
+            .chain(Some(multiplicity_eval))
+        {
+            transcript.write_scalar(eval)?;
+        }
+
+        Ok(Evaluated { constructed: self })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    pub(in crate::plonk) fn open<'a>(
+        &'a self,
+        pk: &'a ProvingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = ProverQuery<'a, C>> + Clone {
+        let x_next = pk.vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open lookup running sum commitments at x
+            .chain(Some(ProverQuery {
+                point: *x,
+                rotation: Rotation::cur(),
+                poly: &self.constructed.phi_poly,
+            }))
+            // Open lookup multiplicity commitments at x
+            .chain(Some(ProverQuery {
+                point: *x,
+                rotation: Rotation::cur(),
+                poly: &self.constructed.multiplicity_poly,
+            }))
+            // Open lookup running sum commitments at x_next
+            .chain(Some(ProverQuery {
+                point: x_next,
+                rotation: Rotation::next(),
+                poly: &self.constructed.phi_poly,
+            }))
+    }
+}
+
+/// Given a vector of input values A and a vector of table values S,
+/// this method computes the multiplicity vector M, such that M_j is the
+/// number of usable rows i with A_i = S_j, counting each value only at the
+/// first row of S that contains it. Blinding rows of M are random.
+fn compute_multiplicities<C: CurveAffine, R: RngCore>(
+    pk: &ProvingKey<C>,
+    params: &Params<C>,
+    domain: &EvaluationDomain<C::Scalar>,
+    mut rng: R,
+    input_expression: &Polynomial<C::Scalar, LagrangeCoeff>,
+    table_expression: &Polynomial<C::Scalar, LagrangeCoeff>,
+) -> Result<Polynomial<C::Scalar, LagrangeCoeff>, Error> {
+    let blinding_factors = pk.vk.cs.blinding_factors();
+    let usable_rows = params.n as usize - (blinding_factors + 1);
+
+    // A BTreeMap of each unique element in the table expression and the
+    // first row it appears in
+    let table_rows: BTreeMap<C::Scalar, usize> = table_expression
+        .iter()
+        .take(usable_rows)
+        .enumerate()
+        .fold(BTreeMap::new(), |mut acc, (row, coeff)| {
+            acc.entry(*coeff).or_insert(row);
+            acc
+        });
+
+    let mut counts = vec![0u64; usable_rows];
+    for input_value in input_expression.iter().take(usable_rows) {
+        // Return error if input_value not found
+        let row = table_rows
+            .get(input_value)
+            .ok_or(Error::ConstraintSystemFailure)?;
+        counts[*row] += 1;
+    }
+
+    let multiplicities = counts
+        .into_iter()
+        .map(C::Scalar::from)
+        .chain((0..(blinding_factors + 1)).map(|_| C::Scalar::random(&mut rng)))
+        .collect::<Vec<_>>();
+    assert_eq!(multiplicities.len(), params.n as usize);
+
+    Ok(domain.lagrange_from_vec(multiplicities))
+}
@@ -0,0 +1,177 @@
+use std::iter;
+
+use super::super::super::{
+    circuit::Expression, ChallengeBeta, ChallengeGamma, ChallengeTheta, ChallengeX,
+};
+use super::super::Argument;
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    plonk::{Error, VerifyingKey},
+    poly::{multiopen::VerifierQuery, Rotation},
+    transcript::{EncodedChallenge, TranscriptRead},
+};
+use ff::Field;
+
+pub struct PermutationCommitments<C: CurveAffine> {
+    multiplicity_commitment: C,
+}
+
+pub struct Committed<C: CurveAffine> {
+    permuted: PermutationCommitments<C>,
+    phi_commitment: C,
+}
+
+pub struct Evaluated<C: CurveAffine> {
+    committed: Committed<C>,
+    phi_eval: C::Scalar,
+    phi_next_eval: C::Scalar,
+    multiplicity_eval: C::Scalar,
+}
+
+impl<F: FieldExt> Argument<F> {
+    pub(in crate::plonk) fn read_permuted_commitments<
+        C: CurveAffine,
+        E: EncodedChallenge<C>,
+        T: TranscriptRead<C, E>,
+    >(
+        &self,
+        transcript: &mut T,
+    ) -> Result<PermutationCommitments<C>, Error> {
+        let multiplicity_commitment = transcript.read_point()?;
+
+        Ok(PermutationCommitments {
+            multiplicity_commitment,
+        })
+    }
+}
+
+impl<C: CurveAffine> PermutationCommitments<C> {
+    pub(in crate::plonk) fn read_product_commitment<
+        E: EncodedChallenge<C>,
+        T: TranscriptRead<C, E>,
+    >(
+        self,
+        transcript: &mut T,
+    ) -> Result<Committed<C>, Error> {
+        let phi_commitment = transcript.read_point()?;
+
+        Ok(Committed {
+            permuted: self,
+            phi_commitment,
+        })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    pub(crate) fn evaluate<E: EncodedChallenge<C>, T: TranscriptRead<C, E>>(
+        self,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let phi_eval = transcript.read_scalar()?;
+        let phi_next_eval = transcript.read_scalar()?;
+        let multiplicity_eval = transcript.read_scalar()?;
+
+        Ok(Evaluated {
+            committed: self,
+            phi_eval,
+            phi_next_eval,
+            multiplicity_eval,
+        })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    pub(in crate::plonk) fn expressions<'a>(
+        &'a self,
+        l_0: C::Scalar,
+        l_last: C::Scalar,
+        l_blind: C::Scalar,
+        argument: &'a Argument<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        beta: ChallengeBeta<C>,
+        _gamma: ChallengeGamma<C>,
+        advice_evals: &[C::Scalar],
+        fixed_evals: &[C::Scalar],
+        instance_evals: &[C::Scalar],
+    ) -> impl Iterator<Item = C::Scalar> + 'a {
+        let active_rows = C::Scalar::one() - (l_last + l_blind);
+
+        let sum_expression = || {
+            // (\phi(\omega X) - \phi(X)) (a(X) + \beta) (s(X) + \beta)
+            // - ((s(X) + \beta) - m(X) (a(X) + \beta))
+            let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
+                expressions
+                    .iter()
+                    .map(|expression| {
+                        expression.evaluate(
+                            &|scalar| scalar,
+                            &|_| panic!("virtual selectors are removed during optimization"),
+                            &|index, _, _| fixed_evals[index],
+                            &|index, _, _| advice_evals[index],
+                            &|index, _, _| instance_evals[index],
+                            &|a| -a,
+                            &|a, b| a + &b,
+                            &|a, b| a * &b,
+                            &|a, scalar| a * &scalar,
+                        )
+                    })
+                    .fold(C::Scalar::zero(), |acc, eval| acc * &*theta + &eval)
+            };
+            let input_term = compress_expressions(&argument.input_expressions) + &*beta;
+            let table_term = compress_expressions(&argument.table_expressions) + &*beta;
+
+            let left = (self.phi_next_eval - &self.phi_eval) * &input_term * &table_term;
+            let right = table_term - &(self.multiplicity_eval * &input_term);
+
+            (left - &right) * &active_rows
+        };
+
+        std::iter::empty()
+            .chain(
+                // l_0(X) * \phi(X) = 0
+                Some(l_0 * &self.phi_eval),
+            )
+            .chain(
+                // l_last(X) * \phi(X) = 0
+                Some(l_last * &self.phi_eval),
+            )
+            .chain(
+                // (1 - (l_last(X) + l_blind(X))) * (
+                //   (\phi(\omega X) - \phi(X)) (a(X) + \beta) (s(X) + \beta)
+                //   - ((s(X) + \beta) - m(X) (a(X) + \beta))
+                // ) = 0
+                Some(sum_expression()),
+            )
+    }
+
+    pub(in crate::plonk) fn queries<'r>(
+        &'r self,
+        vk: &'r VerifyingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = VerifierQuery<'r, C>> + Clone {
+        let x_next = vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open lookup running sum commitment at x
+            .chain(Some(VerifierQuery::new_commitment(
+                &self.committed.phi_commitment,
+                *x,
+                Rotation::cur(),
+                self.phi_eval,
+            )))
+            // Open lookup multiplicity commitment at x
+            .chain(Some(VerifierQuery::new_commitment(
+                &self.committed.permuted.multiplicity_commitment,
+                *x,
+                Rotation::cur(),
+                self.multiplicity_eval,
+            )))
+            // Open lookup running sum commitment at \omega x
+            .chain(Some(VerifierQuery::new_commitment(
+                &self.committed.phi_commitment,
+                x_next,
+                Rotation::next(),
+                self.phi_next_eval,
+            )))
+    }
+}
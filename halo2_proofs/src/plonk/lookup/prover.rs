@@ -3,7 +3,7 @@ use super::super::{
     ProvingKey,
 };
 use super::Argument;
-use crate::plonk::evaluation::evaluate;
+use crate::plonk::evaluation::Evaluator;
 use crate::poly::Basis;
 use crate::{
     arithmetic::{eval_polynomial, parallelize, BaseExt, CurveAffine, FieldExt},
@@ -83,22 +83,16 @@ impl<F: FieldExt> Argument<F> {
     {
         // Closure to get values of expressions and compress them
         let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
-            let compressed_expression = expressions
-                .iter()
-                .map(|expression| {
-                    pk.vk.domain.lagrange_from_vec(evaluate(
-                        expression,
-                        params.n as usize,
-                        1,
-                        fixed_values,
-                        advice_values,
-                        instance_values,
-                    ))
-                })
-                .fold(domain.empty_lagrange(), |acc, expression| {
-                    acc * *theta + &expression
-                });
-            compressed_expression
+            domain.lagrange_from_vec(
+                Evaluator::<C>::new_compressed(expressions).evaluate_compressed(
+                    params.n as usize,
+                    1,
+                    fixed_values,
+                    advice_values,
+                    instance_values,
+                    *theta,
+                ),
+            )
         };
 
         // Closure to construct commitment to vector of values
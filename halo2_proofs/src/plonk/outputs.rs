@@ -0,0 +1,241 @@
+//! Reading the outputs of a circuit's synthesis.
+
+use ff::Field;
+
+use super::{
+    Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+    FloorPlanner, Instance, Selector,
+};
+
+/// Records the outputs of a circuit's synthesis, ignoring everything else.
+struct OutputCollector<'a, F: Field> {
+    instances: &'a [&'a [F]],
+    outputs: Vec<(String, F)>,
+}
+
+impl<'a, F: Field> Assignment<F> for OutputCollector<'a, F> {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about regions in this context.
+    }
+
+    fn exit_region(&mut self) {
+        // Do nothing; we don't care about regions in this context.
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, _: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Option<F>, Error> {
+        // As in a proof, the rows after the given values are zero.
+        Ok(Some(
+            self.instances[column.index()]
+                .get(row)
+                .copied()
+                .unwrap_or_else(F::zero),
+        ))
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        _: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // The values of the cells are computed by the region, and the layouter hands
+        // them to `output` directly.
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        _: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        _: usize,
+        _: Option<Assigned<F>>,
+    ) -> Result<(), Error> {
+        Ok(())
+    }
+
+    fn output<A, AR>(&mut self, name: A, value: Option<Assigned<F>>) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        let value = value.ok_or(Error::Synthesis)?;
+        self.outputs.push((name().into(), value.evaluate()));
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+}
+
+/// Synthesizes `circuit`, and returns the name and value of each cell that it
+/// recorded with [`Layouter::output`], in the order in which they were recorded.
+///
+/// Only the witness is computed: no keys are needed, and no constraints are
+/// checked. `instances` holds the values of the circuit's instance columns, as
+/// passed to [`create_proof`] for a single proof.
+///
+/// Returns [`Error::Synthesis`] if the value of an output is unknown.
+///
+/// [`Layouter::output`]: crate::circuit::Layouter#method.output
+/// [`create_proof`]: crate::plonk::create_proof
+pub fn circuit_outputs<F: Field, ConcreteCircuit: Circuit<F>>(
+    circuit: &ConcreteCircuit,
+    instances: &[&[F]],
+) -> Result<Vec<(String, F)>, Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = ConcreteCircuit::configure(&mut cs);
+    if instances.len() != cs.num_instance_columns {
+        return Err(Error::InvalidInstances);
+    }
+
+    let mut collector = OutputCollector {
+        instances,
+        outputs: vec![],
+    };
+    ConcreteCircuit::FloorPlanner::synthesize(
+        &mut collector,
+        circuit,
+        config,
+        cs.constants.clone(),
+    )?;
+
+    Ok(collector.outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::marker::PhantomData;
+
+    use ff::Field;
+    use pairing::bn256::Fr as Fp;
+
+    use super::circuit_outputs;
+    use crate::{
+        circuit::{floor_planner::V1, Layouter, SimpleFloorPlanner},
+        plonk::{Advice, Circuit, Column, ConstraintSystem, Error, FloorPlanner, Instance},
+    };
+
+    #[derive(Clone)]
+    struct MyConfig {
+        a: Column<Advice>,
+        instance: Column<Instance>,
+    }
+
+    /// Squares its instance value, and outputs the result.
+    struct MyCircuit<P>(PhantomData<P>);
+
+    impl<P: FloorPlanner> Circuit<Fp> for MyCircuit<P> {
+        type Config = MyConfig;
+        type FloorPlanner = P;
+
+        fn without_witnesses(&self) -> Self {
+            MyCircuit(PhantomData)
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+            let config = MyConfig {
+                a: meta.advice_column(),
+                instance: meta.instance_column(),
+            };
+            meta.enable_equality(config.a);
+            meta.enable_equality(config.instance);
+            config
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fp>,
+        ) -> Result<(), Error> {
+            let square = layouter.assign_region(
+                || "square",
+                |mut region| {
+                    let a = region.assign_advice_from_instance(
+                        || "a",
+                        config.instance,
+                        0,
+                        config.a,
+                        0,
+                    )?;
+                    region.assign_advice(
+                        || "a^2",
+                        config.a,
+                        1,
+                        || a.value().map(|a| a.square()).ok_or(Error::Synthesis),
+                    )
+                },
+            )?;
+
+            layouter
+                .namespace(|| "outputs")
+                .output(|| "square", &square)
+        }
+    }
+
+    fn outputs<P: FloorPlanner>() {
+        let outputs = circuit_outputs(&MyCircuit::<P>(PhantomData), &[&[Fp::from(3)]]).unwrap();
+        assert_eq!(outputs, vec![("square".to_string(), Fp::from(9))]);
+
+        assert!(matches!(
+            circuit_outputs(&MyCircuit::<P>(PhantomData), &[]),
+            Err(Error::InvalidInstances)
+        ));
+    }
+
+    #[test]
+    fn outputs_simple() {
+        outputs::<SimpleFloorPlanner>();
+    }
+
+    #[test]
+    fn outputs_v1() {
+        outputs::<V1>();
+    }
+}
@@ -93,6 +93,10 @@ impl<C: CurveAffine> VerifyingKey<C> {
             .collect::<Result<Vec<_>, _>>()?;
         Ok(VerifyingKey { commitments })
     }
+
+    pub(crate) fn commitments(&self) -> &[C] {
+        &self.commitments
+    }
 }
 
 /// The proving key for a single permutation argument.
@@ -98,7 +98,7 @@ impl Assembly {
     }
 
     pub(crate) fn build_vk<C: CurveAffine>(
-        self,
+        &self,
         params: &Params<C>,
         domain: &EvaluationDomain<C::Scalar>,
         p: &Argument,
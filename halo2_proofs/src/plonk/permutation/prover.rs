@@ -11,15 +11,13 @@ use crate::{
     arithmetic::{eval_polynomial, parallelize, BaseExt, CurveAffine, FieldExt},
     plonk::{self, Error},
     poly::{
-        commitment::Params, multiopen::ProverQuery, Coeff, ExtendedLagrangeCoeff, LagrangeCoeff,
-        Polynomial, Rotation,
+        commitment::Params, multiopen::ProverQuery, Coeff, LagrangeCoeff, Polynomial, Rotation,
     },
     transcript::{EncodedChallenge, TranscriptWrite},
 };
 
 pub(crate) struct CommittedSet<C: CurveAffine> {
     pub(crate) permutation_product_poly: Polynomial<C::Scalar, Coeff>,
-    pub(crate) permutation_product_coset: Polynomial<C::Scalar, ExtendedLagrangeCoeff>,
 }
 
 pub(crate) struct Committed<C: CurveAffine> {
@@ -161,10 +159,7 @@ impl Argument {
             last_z = z[params.n as usize - (blinding_factors + 1)];
 
             let permutation_product_commitment_projective = params.commit_lagrange(&z);
-            let z = domain.lagrange_to_coeff(z);
-            let permutation_product_poly = z.clone();
-
-            let permutation_product_coset = domain.coeff_to_extended(z.clone());
+            let permutation_product_poly = domain.lagrange_to_coeff(z);
 
             let permutation_product_commitment =
                 permutation_product_commitment_projective.to_affine();
@@ -174,7 +169,6 @@ impl Argument {
 
             sets.push(CommittedSet {
                 permutation_product_poly,
-                permutation_product_coset,
             });
         }
 
@@ -1,24 +1,33 @@
 use ff::Field;
 use group::Curve;
 use rand_core::RngCore;
+use std::borrow::Cow;
 use std::env::var;
+use std::fmt;
 use std::ops::RangeTo;
 use std::sync::atomic::AtomicUsize;
-use std::time::Instant;
+use std::time::{Duration, Instant};
 use std::{iter, sync::atomic::Ordering};
 
 use super::{
     circuit::{
         Advice, Any, Assignment, Circuit, Column, ConstraintSystem, Fixed, FloorPlanner, Instance,
-        Selector,
+        Selector, WitnessCalculator,
     },
-    lookup, permutation, vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta, ChallengeX,
-    ChallengeY, Error, ProvingKey,
+    evaluation::ExtendedPart,
+    instance::check_instance_layout,
+    lookup, permutation, shuffle, vanishing, ChallengeBeta, ChallengeGamma, ChallengeTheta,
+    ChallengeX, ChallengeY, Error, ProvingKey,
 };
 use crate::{
-    arithmetic::{eval_polynomial, BaseExt, CurveAffine, FieldExt},
+    arithmetic::{eval_polynomial, BaseExt, CurveAffine, FieldExt, MultiexpBuffer},
     plonk::Assigned,
 };
+use crate::{
+    logging::{Stage, PROVER},
+    poly::batch_invert_assigned,
+    transcript::{EncodedChallenge, TranscriptWrite},
+};
 use crate::{
     plonk::Expression,
     poly::{
@@ -28,15 +37,68 @@ use crate::{
         Coeff, ExtendedLagrangeCoeff, LagrangeCoeff, Polynomial, Rotation,
     },
 };
-use crate::{
-    poly::batch_invert_assigned,
-    transcript::{EncodedChallenge, TranscriptWrite},
-};
+use log::info;
+
+/// How the prover trades proving time for memory.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ProverMemory {
+    /// Keeps the advice and instance columns of every proof both in coefficient
+    /// form and over the extended domain until the proof is done, and evaluates
+    /// the quotient polynomial over the whole extended domain at once. This is
+    /// what [`create_proof`] does.
+    Fast,
+    /// Keeps only the values of the advice columns until the lookup, permutation
+    /// and shuffle arguments have been committed to, and then only their
+    /// coefficients. The quotient polynomial is evaluated over one coset of the
+    /// original domain at a time, so that apart from the proving key and the
+    /// quotient polynomial itself, no polynomial is held over the whole extended
+    /// domain.
+    ///
+    /// Every column is transformed again for each coset, so this is slower than
+    /// [`ProverMemory::Fast`], but takes a fraction of its memory for circuits
+    /// with many advice columns.
+    Low,
+}
 
 /// This creates a proof for the provided `circuit` when given the public
 /// parameters `params` and the proving key [`ProvingKey`] that was
 /// generated previously for the same circuit. The provided `instances`
 /// are zero-padded internally.
+///
+/// `instances` holds the instance columns of each of `circuits`, in order. Its
+/// layout is checked before any work is done: [`Error::InstanceLayout`] is
+/// returned if it holds instances for a different number of circuits, if the
+/// instances of a circuit don't hold every instance column of the proving key,
+/// or if a column is longer than the usable rows of the circuit. An
+/// [`InstanceSet`](crate::plonk::InstanceSet) can build and check them.
+///
+/// # Determinism
+///
+/// The blinding factors of the proof are the only values drawn from `rng`, and
+/// they are drawn in a fixed order from the calling thread. Nothing else about
+/// the proof depends on how it was computed: field and group arithmetic is
+/// exact, so the order in which parallel work is reduced doesn't matter, and
+/// neither do the number of threads, the GPU or the [`ProverMemory`] setting.
+/// Two runs with the same witness and a deterministic `rng` seeded the same way,
+/// such as `rand_chacha::ChaCha20Rng::from_seed`, therefore produce
+/// byte-identical proofs, which is useful for tests and audits. Such proofs are
+/// not zero knowledge if the seed is known.
+///
+/// This is guaranteed by the API, for every prover in this module: the proof
+/// doesn't depend on `HALO2_NUM_THREADS` or `BELLMAN_NUM_CPUS`, on
+/// `worker::set_num_threads`, or on the thread pool that
+/// `worker::with_thread_pool` runs the prover in. A proof that changes with the
+/// number of threads is a bug.
+///
+/// # Streaming
+///
+/// The proof is written to the transcript's writer as it is computed, rather
+/// than once it is complete, and the prover flushes the transcript at the end
+/// of each round: after the advice commitments, the lookup commitments, the
+/// permutation, lookup and shuffle products, the quotient commitments and the
+/// opening proof. A transcript writing to a buffered socket or file therefore
+/// sends each round as soon as it is done, and the prover holds none of the
+/// proof in memory.
 pub fn create_proof<
     C: CurveAffine,
     E: EncodedChallenge<C>,
@@ -48,25 +110,479 @@ pub fn create_proof<
     pk: &ProvingKey<C>,
     circuits: &[ConcreteCircuit],
     instances: &[&[&[C::Scalar]]],
-    mut rng: R,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    create_proof_with_memory(
+        params,
+        pk,
+        circuits,
+        instances,
+        ProverMemory::Fast,
+        rng,
+        transcript,
+    )
+}
+
+/// This creates a proof in the same way as [`create_proof`], trading proving
+/// time for memory as set by `memory`. The proofs are the same for either
+/// setting.
+pub fn create_proof_with_memory<
+    C: CurveAffine,
+    E: EncodedChallenge<C>,
+    R: RngCore,
+    T: TranscriptWrite<C, E>,
+    ConcreteCircuit: Circuit<C::Scalar>,
+>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[C::Scalar]]],
+    memory: ProverMemory,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    create_proof_from_circuits(
+        params,
+        pk,
+        circuits,
+        instances,
+        memory,
+        false,
+        &mut (),
+        rng,
+        transcript,
+    )
+    .map(|_| ())
+}
+
+/// This creates a proof in the same way as [`create_proof`], but first checks
+/// that the witness of each circuit is complete, to fail fast with a helpful
+/// error rather than after the circuit's polynomials have been computed.
+///
+/// Every advice cell queried by a gate on a row where one of the gate's
+/// selectors is enabled must have been assigned, or
+/// [`Error::CellNotAssigned`] is returned. The check only needs the
+/// assignments made during synthesis, but keeps track of every cell, so it
+/// takes some extra memory.
+///
+/// This doesn't check that the gates are satisfied; use
+/// [`MockProver`](crate::dev::MockProver) for that.
+pub fn create_proof_checked<
+    C: CurveAffine,
+    E: EncodedChallenge<C>,
+    R: RngCore,
+    T: TranscriptWrite<C, E>,
+    ConcreteCircuit: Circuit<C::Scalar>,
+>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[C::Scalar]]],
+    rng: R,
     transcript: &mut T,
 ) -> Result<(), Error> {
-    for instance in instances.iter() {
-        if instance.len() != pk.vk.cs.num_instance_columns {
-            return Err(Error::InvalidInstances);
+    create_proof_from_circuits(
+        params,
+        pk,
+        circuits,
+        instances,
+        ProverMemory::Fast,
+        true,
+        &mut (),
+        rng,
+        transcript,
+    )
+    .map(|_| ())
+}
+
+/// The time taken by each stage of a proof, as returned by
+/// [`create_proof_with_metrics`].
+///
+/// The stages are, in order: `instance` and `advice` (committing to the
+/// instance and advice columns), `lookup_permuted`, `permutation`,
+/// `lookup_product` and `shuffle` (committing to the polynomials of the
+/// arguments), `quotient` (committing to the vanishing argument), `evaluate`
+/// and `multiopen`. They are also logged at debug level under the
+/// `halo2::prover` target.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofMetrics {
+    /// The stages of the prover with the time each one took, in the order in
+    /// which they ran.
+    pub stages: Vec<(&'static str, Duration)>,
+    /// The time taken by the whole proof.
+    pub total: Duration,
+}
+
+impl ProofMetrics {
+    /// Returns the time taken by the stage `name`, if it ran.
+    pub fn stage(&self, name: &str) -> Option<Duration> {
+        self.stages
+            .iter()
+            .find(|(stage, _)| *stage == name)
+            .map(|(_, elapsed)| *elapsed)
+    }
+
+    fn finish_stage(&mut self, stage: Stage) {
+        let name = stage.name();
+        self.stages.push((name, stage.finish()));
+    }
+}
+
+impl fmt::Display for ProofMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (name, elapsed) in self.stages.iter() {
+            writeln!(f, "{}: {:?}", name, elapsed)?;
         }
+        write!(f, "total: {:?}", self.total)
     }
+}
 
-    // Hash verification key into transcript
-    pk.vk.hash_into(transcript)?;
+/// This creates a proof in the same way as [`create_proof`], and returns the
+/// time taken by each stage of the prover, so that services can monitor where
+/// the time of each proof goes.
+pub fn create_proof_with_metrics<
+    C: CurveAffine,
+    E: EncodedChallenge<C>,
+    R: RngCore,
+    T: TranscriptWrite<C, E>,
+    ConcreteCircuit: Circuit<C::Scalar>,
+>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[C::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+) -> Result<ProofMetrics, Error> {
+    create_proof_from_circuits(
+        params,
+        pk,
+        circuits,
+        instances,
+        ProverMemory::Fast,
+        false,
+        &mut (),
+        rng,
+        transcript,
+    )
+}
 
-    let domain = &pk.vk.domain;
+/// Receives updates on the progress of a proof created with
+/// [`create_proof_with_progress`], and can cancel it.
+///
+/// The methods are called between the stages of the prover (see
+/// [`ProofMetrics`] for their names), so a proof can only be cancelled at the
+/// start of a stage.
+pub trait ProverProgress {
+    /// Called when the prover starts the stage `stage`, with the percentage of
+    /// the stages that are done. Once the proof is done, this is called with the
+    /// stage `"done"` and 100 percent.
+    fn stage(&mut self, _stage: &'static str, _percent: u8) {}
+
+    /// Returns whether the proof should be cancelled, in which case the prover
+    /// stops and returns [`Error::Aborted`].
+    fn cancelled(&self) -> bool {
+        false
+    }
+}
+
+/// Reports no progress, and never cancels the proof.
+impl ProverProgress for () {}
+
+/// The stages of the prover, in the order in which they run.
+const PROVER_STAGES: [&str; 9] = [
+    "instance",
+    "advice",
+    "lookup_permuted",
+    "permutation",
+    "lookup_product",
+    "shuffle",
+    "quotient",
+    "evaluate",
+    "multiopen",
+];
+
+/// Starts the prover stage `name`, after reporting it to `progress`, unless the
+/// proof was cancelled.
+fn start_stage(progress: &mut dyn ProverProgress, name: &'static str) -> Result<Stage, Error> {
+    if progress.cancelled() {
+        info!(target: PROVER, "event=aborted stage={}", name);
+        return Err(Error::Aborted);
+    }
+    let index = PROVER_STAGES
+        .iter()
+        .position(|stage| *stage == name)
+        .expect("unknown prover stage");
+    progress.stage(name, (index * 100 / PROVER_STAGES.len()) as u8);
+
+    Ok(Stage::start(PROVER, name))
+}
+
+/// This creates a proof in the same way as [`create_proof`], reporting the
+/// progress of the prover to `progress`, which can cancel the proof. A cancelled
+/// proof returns [`Error::Aborted`], leaving `transcript` incomplete.
+pub fn create_proof_with_progress<
+    C: CurveAffine,
+    E: EncodedChallenge<C>,
+    R: RngCore,
+    T: TranscriptWrite<C, E>,
+    ConcreteCircuit: Circuit<C::Scalar>,
+>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[C::Scalar]]],
+    progress: &mut dyn ProverProgress,
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    create_proof_from_circuits(
+        params,
+        pk,
+        circuits,
+        instances,
+        ProverMemory::Fast,
+        false,
+        progress,
+        rng,
+        transcript,
+    )
+    .map(|_| ())
+}
+
+/// Creates proofs for `circuits`, checking their witnesses first if
+/// `check_witness` is set.
+#[allow(clippy::too_many_arguments)]
+fn create_proof_from_circuits<
+    C: CurveAffine,
+    E: EncodedChallenge<C>,
+    R: RngCore,
+    T: TranscriptWrite<C, E>,
+    ConcreteCircuit: Circuit<C::Scalar>,
+>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    circuits: &[ConcreteCircuit],
+    instances: &[&[&[C::Scalar]]],
+    memory: ProverMemory,
+    check_witness: bool,
+    progress: &mut dyn ProverProgress,
+    rng: R,
+    transcript: &mut T,
+) -> Result<ProofMetrics, Error> {
     let mut meta = ConstraintSystem::default();
     let config = ConcreteCircuit::configure(&mut meta);
 
+    create_proof_from_advice(
+        params,
+        pk,
+        circuits.len(),
+        instances,
+        memory,
+        |index, instances, unusable_rows_start| {
+            synthesize_witness(
+                pk,
+                &circuits[index],
+                config.clone(),
+                instances,
+                unusable_rows_start,
+                check_witness,
+            )
+        },
+        progress,
+        rng,
+        transcript,
+    )
+}
+
+/// Synthesizes `circuit` to obtain its unblinded columns, given its instance
+/// columns and the first row that is not usable for witness values.
+///
+/// If `check_witness` is set, returns [`Error::CellNotAssigned`] if an advice
+/// cell that an enabled gate queries was not assigned.
+pub(crate) fn synthesize_witness<C: CurveAffine, ConcreteCircuit: Circuit<C::Scalar>>(
+    pk: &ProvingKey<C>,
+    circuit: &ConcreteCircuit,
+    config: ConcreteCircuit::Config,
+    instances: &[&[C::Scalar]],
+    unusable_rows_start: usize,
+    check_witness: bool,
+) -> Result<Witness<C::Scalar>, Error> {
     // Selector optimizations cannot be applied here; use the ConstraintSystem
     // from the verification key.
     let meta = &pk.vk.cs;
+    let domain = &pk.vk.domain;
+    let n = 1 << domain.k();
+
+    // Without the check, no assignments are tracked.
+    let tracked = |columns: usize| {
+        if check_witness {
+            vec![vec![false; n]; columns]
+        } else {
+            vec![]
+        }
+    };
+
+    let mut witness = WitnessCollection {
+        k: domain.k(),
+        advice: vec![domain.empty_lagrange_assigned(); meta.num_advice_columns],
+        assigned_advice: tracked(meta.num_advice_columns),
+        enabled_selectors: tracked(meta.num_selectors),
+        configuration_columns: &meta.configuration_columns,
+        configuration: vec![domain.empty_lagrange_assigned(); meta.configuration_columns.len()],
+        instances,
+        // The prover will not be allowed to assign values to advice
+        // cells that exist within inactive rows, which include some
+        // number of blinding factors and an extra row for use in the
+        // permutation argument.
+        usable_rows: ..unusable_rows_start,
+        #[cfg(debug_assertions)]
+        fixed_values: &pk.fixed_values,
+        _marker: std::marker::PhantomData,
+    };
+
+    // Synthesize the circuit to obtain the witness and other information.
+    ConcreteCircuit::FloorPlanner::synthesize(
+        &mut witness,
+        circuit,
+        config,
+        meta.constants.clone(),
+    )
+    .map_err(|e| e.with_minimum_k(circuit))?;
+
+    if check_witness {
+        witness.check_assigned(meta)?;
+    }
+
+    Ok(Witness {
+        advice: witness.advice,
+        configuration: Some(witness.configuration),
+    })
+}
+
+/// This creates a proof in the same way as [`create_proof`], except that the
+/// advice columns of each proof are computed by a [`WitnessCalculator`]
+/// instead of by synthesizing a [`Circuit`]. The proving key must have been
+/// generated from a circuit with the same advice column layout.
+pub fn create_proof_with_witness<
+    C: CurveAffine,
+    E: EncodedChallenge<C>,
+    R: RngCore,
+    T: TranscriptWrite<C, E>,
+    W: WitnessCalculator<C::Scalar>,
+>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    witnesses: &[W],
+    instances: &[&[&[C::Scalar]]],
+    rng: R,
+    transcript: &mut T,
+) -> Result<(), Error> {
+    let meta = &pk.vk.cs;
+    let domain = &pk.vk.domain;
+
+    create_proof_from_advice(
+        params,
+        pk,
+        witnesses.len(),
+        instances,
+        ProverMemory::Fast,
+        |index, instances, unusable_rows_start| {
+            // Checks the shape of the calculated columns and pads them to
+            // the size of the domain.
+            let into_polys = |columns: Vec<Vec<Assigned<C::Scalar>>>, num_columns: usize| {
+                if columns.len() != num_columns {
+                    return Err(Error::BoundsFailure);
+                }
+
+                columns
+                    .into_iter()
+                    .map(|values| {
+                        if values.len() > unusable_rows_start {
+                            return Err(Error::not_enough_rows_available(params.k));
+                        }
+                        let mut poly = domain.empty_lagrange_assigned();
+                        for (cell, value) in poly.iter_mut().zip(values.into_iter()) {
+                            *cell = value;
+                        }
+                        Ok(poly)
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            };
+
+            let witness = &witnesses[index];
+            let advice = witness.calculate_witness(instances, unusable_rows_start)?;
+            let configuration = witness.calculate_configuration(instances, unusable_rows_start)?;
+
+            Ok(Witness {
+                advice: into_polys(advice, meta.num_advice_columns)?,
+                configuration: configuration
+                    .map(|columns| into_polys(columns, meta.configuration_columns.len()))
+                    .transpose()?,
+            })
+        },
+        &mut (),
+        rng,
+        transcript,
+    )
+    .map(|_| ())
+}
+
+/// The unblinded columns of a single circuit, as produced by witness
+/// generation.
+pub(crate) struct Witness<F: Field> {
+    pub(crate) advice: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>,
+    // The values of the configuration columns, or `None` to keep the values
+    // assigned at keygen.
+    pub(crate) configuration: Option<Vec<Polynomial<Assigned<F>, LagrangeCoeff>>>,
+}
+
+/// Creates proofs for the first `num_circuits` entries of `instances`, keeping
+/// polynomials in memory as set by `memory`. The unblinded columns of each
+/// proof are obtained from `synthesize`, which is given the index of the proof,
+/// its instance columns and the first row that is not usable for witness
+/// values. Progress is reported to `progress`, which can cancel the proof.
+#[allow(clippy::too_many_arguments)]
+fn create_proof_from_advice<
+    C: CurveAffine,
+    E: EncodedChallenge<C>,
+    R: RngCore,
+    T: TranscriptWrite<C, E>,
+    S: FnMut(usize, &[&[C::Scalar]], usize) -> Result<Witness<C::Scalar>, Error>,
+>(
+    params: &Params<C>,
+    pk: &ProvingKey<C>,
+    num_circuits: usize,
+    instances: &[&[&[C::Scalar]]],
+    memory: ProverMemory,
+    mut synthesize: S,
+    progress: &mut dyn ProverProgress,
+    mut rng: R,
+    transcript: &mut T,
+) -> Result<ProofMetrics, Error> {
+    check_instance_layout(&pk.vk, num_circuits, instances)?;
+
+    let start = Instant::now();
+    let mut metrics = ProofMetrics::default();
+    info!(
+        target: PROVER,
+        "event=start k={} proofs={}",
+        params.k,
+        num_circuits
+    );
+
+    // Hash verification key into transcript
+    pk.vk.hash_into(transcript)?;
+
+    let domain = &pk.vk.domain;
+    let meta = &pk.vk.cs;
+
+    let stage = start_stage(progress, "instance")?;
+
+    // Shared scratch space for the instance and advice commitments, which are
+    // all taken over the same Lagrange basis.
+    let mut msm_buffer = MultiexpBuffer::new();
 
     struct InstanceSingle<C: CurveAffine> {
         pub instance_values: Vec<Polynomial<C::Scalar, LagrangeCoeff>>,
@@ -93,7 +609,7 @@ pub fn create_proof<
                 .collect::<Result<Vec<_>, _>>()?;
             let instance_commitments_projective: Vec<_> = instance_values
                 .iter()
-                .map(|poly| params.commit_lagrange(poly))
+                .map(|poly| params.commit_lagrange_with_buffer(poly, &mut msm_buffer))
                 .collect();
             let mut instance_commitments =
                 vec![C::identity(); instance_commitments_projective.len()];
@@ -113,10 +629,13 @@ pub fn create_proof<
                 })
                 .collect();
 
-            let instance_cosets: Vec<_> = instance_polys
-                .iter()
-                .map(|poly| domain.coeff_to_extended(poly.clone()))
-                .collect();
+            let instance_cosets: Vec<_> = match memory {
+                ProverMemory::Fast => instance_polys
+                    .iter()
+                    .map(|poly| domain.coeff_to_extended(poly.clone()))
+                    .collect(),
+                ProverMemory::Low => vec![],
+            };
 
             Ok(InstanceSingle {
                 instance_values,
@@ -132,166 +651,48 @@ pub fn create_proof<
         pub advice_cosets: Vec<Polynomial<C::Scalar, ExtendedLagrangeCoeff>>,
     }
 
-    let advice: Vec<AdviceSingle<C>> = circuits
-        .iter()
-        .zip(instances.iter())
-        .map(|(circuit, instances)| -> Result<AdviceSingle<C>, Error> {
-            struct WitnessCollection<'a, F: Field> {
-                k: u32,
-                pub advice: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>,
-                instances: &'a [&'a [F]],
-                usable_rows: RangeTo<usize>,
-                _marker: std::marker::PhantomData<F>,
-            }
+    metrics.finish_stage(stage);
+    let stage = start_stage(progress, "advice")?;
 
-            impl<'a, F: Field> Assignment<F> for WitnessCollection<'a, F> {
-                fn enter_region<NR, N>(&mut self, _: N)
-                where
-                    NR: Into<String>,
-                    N: FnOnce() -> NR,
-                {
-                    // Do nothing; we don't care about regions in this context.
-                }
-
-                fn exit_region(&mut self) {
-                    // Do nothing; we don't care about regions in this context.
-                }
-
-                fn enable_selector<A, AR>(
-                    &mut self,
-                    _: A,
-                    _: &Selector,
-                    _: usize,
-                ) -> Result<(), Error>
-                where
-                    A: FnOnce() -> AR,
-                    AR: Into<String>,
-                {
-                    // We only care about advice columns here
+    // The configuration columns are shared by all circuits in the proof.
+    let mut configuration: Option<Vec<Polynomial<C::Scalar, LagrangeCoeff>>> = None;
 
-                    Ok(())
-                }
-
-                fn query_instance(
-                    &self,
-                    column: Column<Instance>,
-                    row: usize,
-                ) -> Result<Option<F>, Error> {
-                    if !self.usable_rows.contains(&row) {
-                        return Err(Error::not_enough_rows_available(self.k));
-                    }
+    let mut advice: Vec<AdviceSingle<C>> = instances
+        .iter()
+        .take(num_circuits)
+        .enumerate()
+        .map(|(index, instances)| -> Result<AdviceSingle<C>, Error> {
+            let unusable_rows_start = params.n as usize - (meta.blinding_factors() + 1);
 
-                    self.instances
-                        .get(column.index())
-                        .and_then(|column| column.get(row))
-                        .map(|v| Some(*v))
-                        .ok_or(Error::BoundsFailure)
-                }
+            let witness = synthesize(index, instances, unusable_rows_start)?;
 
-                fn assign_advice<V, VR, A, AR>(
-                    &mut self,
-                    _: A,
-                    column: Column<Advice>,
-                    row: usize,
-                    to: V,
-                ) -> Result<(), Error>
-                where
-                    V: FnOnce() -> Result<VR, Error>,
-                    VR: Into<Assigned<F>>,
-                    A: FnOnce() -> AR,
-                    AR: Into<String>,
-                {
-                    if !self.usable_rows.contains(&row) {
-                        return Err(Error::not_enough_rows_available(self.k));
+            if let Some(circuit_configuration) = witness.configuration {
+                let circuit_configuration = batch_invert_assigned(&circuit_configuration);
+                if let Some(configuration) = configuration.as_ref() {
+                    if configuration
+                        .iter()
+                        .zip(circuit_configuration.iter())
+                        .any(|(a, b)| a[..] != b[..])
+                    {
+                        return Err(Error::Synthesis);
                     }
-
-                    *self
-                        .advice
-                        .get_mut(column.index())
-                        .and_then(|v| v.get_mut(row))
-                        .ok_or(Error::BoundsFailure)? = to()?.into();
-
-                    Ok(())
-                }
-
-                fn assign_fixed<V, VR, A, AR>(
-                    &mut self,
-                    _: A,
-                    _: Column<Fixed>,
-                    _: usize,
-                    _: V,
-                ) -> Result<(), Error>
-                where
-                    V: FnOnce() -> Result<VR, Error>,
-                    VR: Into<Assigned<F>>,
-                    A: FnOnce() -> AR,
-                    AR: Into<String>,
-                {
-                    // We only care about advice columns here
-
-                    Ok(())
-                }
-
-                fn copy(
-                    &mut self,
-                    _: Column<Any>,
-                    _: usize,
-                    _: Column<Any>,
-                    _: usize,
-                ) -> Result<(), Error> {
-                    // We only care about advice columns here
-
-                    Ok(())
+                } else {
+                    configuration = Some(circuit_configuration);
                 }
+            }
 
-                fn fill_from_row(
-                    &mut self,
-                    _: Column<Fixed>,
-                    _: usize,
-                    _: Option<Assigned<F>>,
-                ) -> Result<(), Error> {
-                    Ok(())
-                }
+            let mut advice = batch_invert_assigned(&witness.advice);
 
-                fn push_namespace<NR, N>(&mut self, _: N)
-                where
-                    NR: Into<String>,
-                    N: FnOnce() -> NR,
+            // Add blinding factors to advice columns, except unblinded ones
+            for (index, advice) in advice.iter_mut().enumerate() {
+                if !meta.zero_knowledge
+                    || meta
+                        .unblinded_advice_columns
+                        .iter()
+                        .any(|column| column.index() == index)
                 {
-                    // Do nothing; we don't care about namespaces in this context.
-                }
-
-                fn pop_namespace(&mut self, _: Option<String>) {
-                    // Do nothing; we don't care about namespaces in this context.
+                    continue;
                 }
-            }
-
-            let unusable_rows_start = params.n as usize - (meta.blinding_factors() + 1);
-
-            let mut witness = WitnessCollection {
-                k: params.k,
-                advice: vec![domain.empty_lagrange_assigned(); meta.num_advice_columns],
-                instances,
-                // The prover will not be allowed to assign values to advice
-                // cells that exist within inactive rows, which include some
-                // number of blinding factors and an extra row for use in the
-                // permutation argument.
-                usable_rows: ..unusable_rows_start,
-                _marker: std::marker::PhantomData,
-            };
-
-            // Synthesize the circuit to obtain the witness and other information.
-            ConcreteCircuit::FloorPlanner::synthesize(
-                &mut witness,
-                circuit,
-                config.clone(),
-                meta.constants.clone(),
-            )?;
-
-            let mut advice = batch_invert_assigned(witness.advice);
-
-            // Add blinding factors to advice columns
-            for advice in &mut advice {
                 for cell in &mut advice[unusable_rows_start..] {
                     *cell = C::Scalar::random(&mut rng);
                 }
@@ -299,7 +700,7 @@ pub fn create_proof<
 
             let advice_commitments_projective: Vec<_> = advice
                 .iter()
-                .map(|poly| params.commit_lagrange(poly))
+                .map(|poly| params.commit_lagrange_with_buffer(poly, &mut msm_buffer))
                 .collect();
             let mut advice_commitments = vec![C::identity(); advice_commitments_projective.len()];
             C::Curve::batch_normalize(&advice_commitments_projective, &mut advice_commitments);
@@ -310,16 +711,25 @@ pub fn create_proof<
                 transcript.write_point(*commitment)?;
             }
 
-            let advice_polys: Vec<_> = advice
-                .clone()
-                .into_iter()
-                .map(|poly| domain.lagrange_to_coeff(poly))
-                .collect();
+            // With `ProverMemory::Low`, the coefficients are only computed once
+            // the values are no longer needed.
+            let (advice_polys, advice_cosets) = match memory {
+                ProverMemory::Fast => {
+                    let advice_polys: Vec<_> = advice
+                        .clone()
+                        .into_iter()
+                        .map(|poly| domain.lagrange_to_coeff(poly))
+                        .collect();
 
-            let advice_cosets: Vec<_> = advice_polys
-                .iter()
-                .map(|poly| domain.coeff_to_extended(poly.clone()))
-                .collect();
+                    let advice_cosets: Vec<_> = advice_polys
+                        .iter()
+                        .map(|poly| domain.coeff_to_extended(poly.clone()))
+                        .collect();
+
+                    (advice_polys, advice_cosets)
+                }
+                ProverMemory::Low => (vec![], vec![]),
+            };
 
             Ok(AdviceSingle {
                 advice_values: advice,
@@ -329,8 +739,39 @@ pub fn create_proof<
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    // Commit to the configuration columns, substituting their values for the
+    // ones assigned at keygen.
+    let mut fixed_values = Cow::Borrowed(&pk.fixed_values[..]);
+    let mut fixed_polys = Cow::Borrowed(&pk.fixed_polys[..]);
+    let mut fixed_cosets = Cow::Borrowed(&pk.fixed_cosets[..]);
+    match configuration {
+        Some(configuration) => {
+            for (column, values) in meta.configuration_columns.iter().zip(configuration) {
+                let commitment = params.commit_lagrange(&values).to_affine();
+                transcript.write_point(commitment)?;
+
+                let poly = domain.lagrange_to_coeff(values.clone());
+                if memory == ProverMemory::Fast {
+                    fixed_cosets.to_mut()[column.index()] = domain.coeff_to_extended(poly.clone());
+                }
+                fixed_polys.to_mut()[column.index()] = poly;
+                fixed_values.to_mut()[column.index()] = values;
+            }
+        }
+        None => {
+            for column in meta.configuration_columns.iter() {
+                transcript.write_point(pk.vk.fixed_commitments[column.index()])?;
+            }
+        }
+    }
+
+    metrics.finish_stage(stage);
+
     // Sample theta challenge for keeping lookup columns linearly independent
     let theta: ChallengeTheta<_> = transcript.squeeze_challenge_scalar();
+    transcript.flush()?;
+
+    let stage = start_stage(progress, "lookup_permuted")?;
 
     let lookups: Vec<Vec<lookup::prover::Permuted<C>>> = instance
         .iter()
@@ -348,7 +789,7 @@ pub fn create_proof<
                         domain,
                         theta,
                         &advice.advice_values,
-                        &pk.fixed_values,
+                        &fixed_values,
                         &instance.instance_values,
                         transcript,
                         &mut rng,
@@ -358,11 +799,16 @@ pub fn create_proof<
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    metrics.finish_stage(stage);
+
     // Sample beta challenge
     let beta: ChallengeBeta<_> = transcript.squeeze_challenge_scalar();
 
     // Sample gamma challenge
     let gamma: ChallengeGamma<_> = transcript.squeeze_challenge_scalar();
+    transcript.flush()?;
+
+    let stage = start_stage(progress, "permutation")?;
 
     // Commit to permutations.
     let permutations: Vec<permutation::prover::Committed<C>> = instance
@@ -374,7 +820,7 @@ pub fn create_proof<
                 pk,
                 &pk.permutation,
                 &advice.advice_values,
-                &pk.fixed_values,
+                &fixed_values,
                 &instance.instance_values,
                 beta,
                 gamma,
@@ -384,6 +830,9 @@ pub fn create_proof<
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    metrics.finish_stage(stage);
+    let stage = start_stage(progress, "lookup_product")?;
+
     let lookups: Vec<Vec<lookup::prover::Committed<C>>> = lookups
         .into_iter()
         .map(|lookups| -> Result<Vec<_>, _> {
@@ -395,31 +844,129 @@ pub fn create_proof<
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    metrics.finish_stage(stage);
+    let stage = start_stage(progress, "shuffle")?;
+
+    let shuffles: Vec<Vec<shuffle::prover::Committed<C>>> = instance
+        .iter()
+        .zip(advice.iter())
+        .map(|(instance, advice)| -> Result<Vec<_>, _> {
+            // Construct and commit to products for each shuffle
+            pk.vk
+                .cs
+                .shuffles
+                .iter()
+                .map(|shuffle| {
+                    shuffle.commit_product(
+                        pk,
+                        params,
+                        domain,
+                        theta,
+                        gamma,
+                        &advice.advice_values,
+                        &fixed_values,
+                        &instance.instance_values,
+                        transcript,
+                        &mut rng,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // The values of the advice columns are no longer needed, so replace them
+    // with their coefficients one column at a time.
+    if memory == ProverMemory::Low {
+        for advice in advice.iter_mut() {
+            advice.advice_polys = advice
+                .advice_values
+                .drain(..)
+                .map(|poly| domain.lagrange_to_coeff(poly))
+                .collect();
+        }
+    }
+
+    metrics.finish_stage(stage);
+
     // Commit to the vanishing argument's random polynomial for blinding h(x_3)
     let vanishing = vanishing::Argument::commit(params, domain, rng, transcript)?;
 
     // Obtain challenge for keeping all separate gates linearly independent
     let y: ChallengeY<_> = transcript.squeeze_challenge_scalar();
+    transcript.flush()?;
+
+    let stage = start_stage(progress, "quotient")?;
 
     // Evaluate the h(X) polynomial
-    let h_poly = pk.ev.evaluate_h(
-        pk,
-        advice.iter().map(|a| &a.advice_cosets).collect(),
-        instance.iter().map(|i| &i.instance_cosets).collect(),
-        *y,
-        *beta,
-        *gamma,
-        *theta,
-        &lookups,
-        &permutations,
-    );
+    let h_poly = match memory {
+        ProverMemory::Fast => pk.ev.evaluate_h(
+            pk,
+            ExtendedPart::All,
+            &fixed_cosets,
+            advice.iter().map(|a| &a.advice_cosets).collect(),
+            instance.iter().map(|i| &i.instance_cosets).collect(),
+            *y,
+            *beta,
+            *gamma,
+            *theta,
+            &lookups,
+            &shuffles,
+            &permutations,
+        ),
+        ProverMemory::Low => {
+            // Each coset of the original domain is made of every
+            // `num_parts`th point of the extended domain.
+            let num_parts = domain.extended_parts();
+            let mut h_poly = domain.empty_extended();
+            for index in 0..num_parts {
+                let part = ExtendedPart::Coset(index);
+                let to_part = |polys: &[Polynomial<C::Scalar, Coeff>]| -> Vec<_> {
+                    polys
+                        .iter()
+                        .map(|poly| part.coeff_to_extended(domain, poly))
+                        .collect()
+                };
+                let fixed_part = to_part(&fixed_polys);
+                let advice_parts: Vec<_> =
+                    advice.iter().map(|a| to_part(&a.advice_polys)).collect();
+                let instance_parts: Vec<_> = instance
+                    .iter()
+                    .map(|i| to_part(&i.instance_polys))
+                    .collect();
+
+                let h_part = pk.ev.evaluate_h(
+                    pk,
+                    part,
+                    &fixed_part,
+                    advice_parts.iter().collect(),
+                    instance_parts.iter().collect(),
+                    *y,
+                    *beta,
+                    *gamma,
+                    *theta,
+                    &lookups,
+                    &shuffles,
+                    &permutations,
+                );
+                for (i, value) in h_part.iter().enumerate() {
+                    h_poly[i * num_parts + index] = *value;
+                }
+            }
+            h_poly
+        }
+    };
 
     // Construct the vanishing argument's h(X) commitments
     let vanishing = vanishing.construct(params, domain, h_poly, transcript)?;
 
+    metrics.finish_stage(stage);
+
     let x: ChallengeX<_> = transcript.squeeze_challenge_scalar();
+    transcript.flush()?;
     let xn = x.pow(&[params.n as u64, 0, 0, 0]);
 
+    let stage = start_stage(progress, "evaluate")?;
+
     // Compute and hash instance evals for each circuit instance
     for instance in instance.iter() {
         // Evaluate polynomials at omega^i x
@@ -465,7 +1012,7 @@ pub fn create_proof<
         .fixed_queries
         .iter()
         .map(|&(column, at)| {
-            eval_polynomial(&pk.fixed_polys[column.index()], domain.rotate_omega(*x, at))
+            eval_polynomial(&fixed_polys[column.index()], domain.rotate_omega(*x, at))
         })
         .collect();
 
@@ -496,12 +1043,26 @@ pub fn create_proof<
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    // Evaluate the shuffles, if any, at omega^i x.
+    let shuffles: Vec<Vec<shuffle::prover::Evaluated<C>>> = shuffles
+        .into_iter()
+        .map(|shuffles| -> Result<Vec<_>, _> {
+            shuffles
+                .into_iter()
+                .map(|p| p.evaluate(pk, x, transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    metrics.finish_stage(stage);
+
     let instances = instance
         .iter()
         .zip(advice.iter())
         .zip(permutations.iter())
         .zip(lookups.iter())
-        .flat_map(|(((instance, advice), permutation), lookups)| {
+        .zip(shuffles.iter())
+        .flat_map(|((((instance, advice), permutation), lookups), shuffles)| {
             iter::empty()
                 .chain(
                     pk.vk
@@ -527,6 +1088,7 @@ pub fn create_proof<
                 )
                 .chain(permutation.open(pk, x))
                 .chain(lookups.iter().flat_map(move |p| p.open(pk, x)).into_iter())
+                .chain(shuffles.iter().flat_map(move |p| p.open(pk, x)).into_iter())
         })
         .chain(
             pk.vk
@@ -536,12 +1098,290 @@ pub fn create_proof<
                 .map(|&(column, at)| ProverQuery {
                     point: domain.rotate_omega(*x, at),
                     rotation: at,
-                    poly: &pk.fixed_polys[column.index()],
+                    poly: &fixed_polys[column.index()],
                 }),
         )
         .chain(pk.permutation.open(x))
         // We query the h(X) polynomial at x
         .chain(vanishing.open(x));
 
-    multiopen::create_proof(params, transcript, instances).map_err(|_| Error::Opening)
+    let stage = start_stage(progress, "multiopen")?;
+    multiopen::create_proof(params, transcript, instances).map_err(|_| Error::Opening)?;
+    transcript.flush()?;
+    metrics.finish_stage(stage);
+
+    info!(
+        target: PROVER,
+        "event=done k={} elapsed_ms={}",
+        params.k,
+        start.elapsed().as_millis()
+    );
+
+    progress.stage("done", 100);
+    metrics.total = start.elapsed();
+    Ok(metrics)
+}
+
+struct WitnessCollection<'a, F: Field> {
+    k: u32,
+    pub advice: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>,
+    // The advice cells that have been assigned, and the rows on which each
+    // selector is enabled, if the witness is checked.
+    assigned_advice: Vec<Vec<bool>>,
+    enabled_selectors: Vec<Vec<bool>>,
+    configuration_columns: &'a [Column<Fixed>],
+    pub configuration: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>,
+    instances: &'a [&'a [F]],
+    usable_rows: RangeTo<usize>,
+    #[cfg(debug_assertions)]
+    fixed_values: &'a [Polynomial<F, LagrangeCoeff>],
+    _marker: std::marker::PhantomData<F>,
+}
+
+impl<'a, F: Field> WitnessCollection<'a, F> {
+    fn configuration_index(&self, column: Column<Fixed>) -> Option<usize> {
+        self.configuration_columns
+            .iter()
+            .position(|configuration| *configuration == column)
+    }
+
+    /// Checks that a fixed cell has the value it was given at keygen, to catch
+    /// proving with a key generated from a different version of the circuit.
+    #[cfg(debug_assertions)]
+    fn check_fixed(
+        &self,
+        column: Column<Fixed>,
+        row: usize,
+        value: Assigned<F>,
+    ) -> Result<(), Error> {
+        match self
+            .fixed_values
+            .get(column.index())
+            .and_then(|values| values.get(row))
+        {
+            Some(expected) if *expected == value.evaluate() => Ok(()),
+            _ => Err(Error::FixedAssignmentMismatch { column, row }),
+        }
+    }
+
+    /// Checks that every advice cell queried by a gate, on a row where one of
+    /// the gate's selectors is enabled, has been assigned.
+    fn check_assigned(&self, meta: &ConstraintSystem<F>) -> Result<(), Error> {
+        let n = self.advice.first().map_or(0, |column| column.len()) as i32;
+        for gate in meta.gates.iter() {
+            for selector in gate.queried_selectors() {
+                let rows = self.enabled_selectors[selector.0]
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, enabled)| **enabled);
+                for (row, _) in rows {
+                    for cell in gate.queried_cells() {
+                        if *cell.column.column_type() != Any::Advice {
+                            continue;
+                        }
+                        let cell_row = ((row as i32 + n + cell.rotation.0) % n) as usize;
+                        if !self.assigned_advice[cell.column.index()][cell_row] {
+                            return Err(Error::CellNotAssigned {
+                                gate: gate.name(),
+                                column: Column::new(cell.column.index(), Advice),
+                                row: cell_row,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a, F: Field> Assignment<F> for WitnessCollection<'a, F> {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about regions in this context.
+    }
+
+    fn exit_region(&mut self) {
+        // Do nothing; we don't care about regions in this context.
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, selector: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // We only care about advice columns here, unless the witness is checked.
+        if let Some(enabled) = self
+            .enabled_selectors
+            .get_mut(selector.0)
+            .and_then(|rows| rows.get_mut(row))
+        {
+            *enabled = true;
+        }
+
+        Ok(())
+    }
+
+    fn query_instance(&self, column: Column<Instance>, row: usize) -> Result<Option<F>, Error> {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::not_enough_rows_available(self.k));
+        }
+
+        self.instances
+            .get(column.index())
+            .and_then(|column| column.get(row))
+            .map(|v| Some(*v))
+            .ok_or(Error::BoundsFailure)
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Advice>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::not_enough_rows_available(self.k));
+        }
+
+        *self
+            .advice
+            .get_mut(column.index())
+            .and_then(|v| v.get_mut(row))
+            .ok_or(Error::BoundsFailure)? = to()?.into();
+        if let Some(assigned) = self.assigned_advice.get_mut(column.index()) {
+            assigned[row] = true;
+        }
+
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Fixed>,
+        row: usize,
+        to: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        // Other than advice columns, we only care about configuration columns
+        // here, as the rest were fixed at keygen.
+        let index = match self.configuration_index(column) {
+            Some(index) => index,
+            #[cfg(debug_assertions)]
+            None => return self.check_fixed(column, row, to()?.into()),
+            #[cfg(not(debug_assertions))]
+            None => return Ok(()),
+        };
+
+        if !self.usable_rows.contains(&row) {
+            return Err(Error::not_enough_rows_available(self.k));
+        }
+
+        self.configuration[index][row] = to()?.into();
+
+        Ok(())
+    }
+
+    fn assign_fixed_column<A, AR>(
+        &mut self,
+        _: A,
+        column: Column<Fixed>,
+        row: usize,
+        values: &[Assigned<F>],
+    ) -> Result<(), Error>
+    where
+        A: Fn() -> AR,
+        AR: Into<String>,
+    {
+        let index = match self.configuration_index(column) {
+            Some(index) => index,
+            #[cfg(debug_assertions)]
+            None => {
+                for (offset, value) in values.iter().enumerate() {
+                    self.check_fixed(column, row + offset, *value)?;
+                }
+                return Ok(());
+            }
+            #[cfg(not(debug_assertions))]
+            None => return Ok(()),
+        };
+
+        if row + values.len() > self.usable_rows.end {
+            return Err(Error::not_enough_rows_available(self.k));
+        }
+
+        self.configuration[index][row..][..values.len()].copy_from_slice(values);
+
+        Ok(())
+    }
+
+    fn copy(&mut self, _: Column<Any>, _: usize, _: Column<Any>, _: usize) -> Result<(), Error> {
+        // We only care about advice columns here
+
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        column: Column<Fixed>,
+        from_row: usize,
+        to: Option<Assigned<F>>,
+    ) -> Result<(), Error> {
+        let index = match self.configuration_index(column) {
+            Some(index) => index,
+            #[cfg(debug_assertions)]
+            None => {
+                if let Some(value) = to {
+                    for row in from_row..self.usable_rows.end {
+                        self.check_fixed(column, row, value)?;
+                    }
+                }
+                return Ok(());
+            }
+            #[cfg(not(debug_assertions))]
+            None => return Ok(()),
+        };
+
+        if !self.usable_rows.contains(&from_row) {
+            return Err(Error::not_enough_rows_available(self.k));
+        }
+
+        for cell in self.configuration[index]
+            .iter_mut()
+            .take(self.usable_rows.end)
+            .skip(from_row)
+        {
+            *cell = to.ok_or(Error::Synthesis)?;
+        }
+
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
 }
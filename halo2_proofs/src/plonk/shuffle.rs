@@ -0,0 +1,80 @@
+use super::circuit::Expression;
+use ff::Field;
+
+pub(crate) mod prover;
+pub(crate) mod verifier;
+
+/// A shuffle argument, which constrains the input expressions to be a
+/// permutation of the shuffle expressions, as returned by
+/// [`ConstraintSystem::shuffles`](super::ConstraintSystem::shuffles).
+#[derive(Clone, Debug)]
+pub struct Argument<F: Field> {
+    pub(crate) name: &'static str,
+    pub(crate) input_expressions: Vec<Expression<F>>,
+    pub(crate) shuffle_expressions: Vec<Expression<F>>,
+}
+
+impl<F: Field> Argument<F> {
+    /// Constructs a new shuffle argument.
+    ///
+    /// `shuffle` is a sequence of `(input, shuffle)` tuples.
+    pub(crate) fn new(name: &'static str, shuffle: Vec<(Expression<F>, Expression<F>)>) -> Self {
+        let (input_expressions, shuffle_expressions) = shuffle.into_iter().unzip();
+        Argument {
+            name,
+            input_expressions,
+            shuffle_expressions,
+        }
+    }
+
+    /// Returns the name of the shuffle argument.
+    pub fn name(&self) -> &'static str {
+        self.name
+    }
+
+    /// Returns the input expressions of the shuffle argument.
+    pub fn input_expressions(&self) -> &[Expression<F>] {
+        &self.input_expressions
+    }
+
+    /// Returns the shuffle expressions of the shuffle argument, one for each input
+    /// expression.
+    pub fn shuffle_expressions(&self) -> &[Expression<F>] {
+        &self.shuffle_expressions
+    }
+
+    pub(crate) fn required_degree(&self) -> usize {
+        assert_eq!(self.input_expressions.len(), self.shuffle_expressions.len());
+
+        // The first value in the product poly should be one.
+        // degree 2:
+        // l_0(X) * (1 - z(X)) = 0
+        //
+        // The "last" value in the product poly should be a boolean, for
+        // completeness and soundness.
+        // degree 3:
+        // l_last(X) * (z(X)^2 - z(X)) = 0
+        //
+        // Enable the product argument for only the rows involved.
+        // degree (2 + max(input_degree, shuffle_degree)) or 3, whichever is larger:
+        // (1 - (l_last(X) + l_blind(X))) * (
+        //   z(\omega X) (\theta^{m-1} s_0(X) + ... + s_{m-1}(X) + \gamma)
+        //   - z(X) (\theta^{m-1} a_0(X) + ... + a_{m-1}(X) + \gamma)
+        // ) = 0
+        let mut input_degree = 1;
+        for expr in self.input_expressions.iter() {
+            input_degree = std::cmp::max(input_degree, expr.degree());
+        }
+        let mut shuffle_degree = 1;
+        for expr in self.shuffle_expressions.iter() {
+            shuffle_degree = std::cmp::max(shuffle_degree, expr.degree());
+        }
+
+        std::cmp::max(
+            // l_last(X) * (z(X)^2 - z(X))
+            3,
+            // (1 - (l_last + l_blind)) z(\omega X) (\theta^{m-1} s_0(X) + ... + s_{m-1}(X) + \gamma)
+            2 + std::cmp::max(input_degree, shuffle_degree),
+        )
+    }
+}
@@ -0,0 +1,213 @@
+use super::super::{
+    circuit::Expression, ChallengeGamma, ChallengeTheta, ChallengeX, Error, ProvingKey,
+};
+use super::Argument;
+use crate::plonk::evaluation::Evaluator;
+use crate::{
+    arithmetic::{eval_polynomial, parallelize, CurveAffine, FieldExt},
+    poly::{
+        commitment::Params, multiopen::ProverQuery, Coeff, EvaluationDomain, LagrangeCoeff,
+        Polynomial, Rotation,
+    },
+    transcript::{EncodedChallenge, TranscriptWrite},
+};
+use group::{
+    ff::{BatchInvert, Field},
+    Curve,
+};
+use rand_core::RngCore;
+use std::{
+    iter,
+    ops::{Mul, MulAssign},
+};
+
+#[derive(Debug)]
+pub(in crate::plonk) struct Committed<C: CurveAffine> {
+    pub(in crate::plonk) product_poly: Polynomial<C::Scalar, Coeff>,
+}
+
+pub(in crate::plonk) struct Evaluated<C: CurveAffine> {
+    constructed: Committed<C>,
+}
+
+impl<F: FieldExt> Argument<F> {
+    /// Given a Shuffle with input expressions [A_0, A_1, ..., A_{m-1}] and shuffle expressions
+    /// [S_0, S_1, ..., S_{m-1}], this method
+    /// - constructs A_compressed = \theta^{m-1} A_0 + theta^{m-2} A_1 + ... + \theta A_{m-2} + A_{m-1}
+    ///   and S_compressed = \theta^{m-1} S_0 + theta^{m-2} S_1 + ... + \theta S_{m-2} + S_{m-1},
+    /// - constructs the grand product polynomial
+    ///   z(\omega^{i+1}) = z(\omega^i) (A_compressed(\omega^i) + \gamma) / (S_compressed(\omega^i) + \gamma),
+    ///   and
+    /// - commits to it.
+    /// Returns an error if A_compressed is not a permutation of S_compressed
+    /// over the usable rows.
+    pub(in crate::plonk) fn commit_product<
+        'a,
+        C,
+        E: EncodedChallenge<C>,
+        R: RngCore,
+        T: TranscriptWrite<C, E>,
+    >(
+        &self,
+        pk: &ProvingKey<C>,
+        params: &Params<C>,
+        domain: &EvaluationDomain<C::Scalar>,
+        theta: ChallengeTheta<C>,
+        gamma: ChallengeGamma<C>,
+        advice_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        fixed_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        instance_values: &'a [Polynomial<C::Scalar, LagrangeCoeff>],
+        transcript: &mut T,
+        mut rng: R,
+    ) -> Result<Committed<C>, Error>
+    where
+        C: CurveAffine<ScalarExt = F>,
+        C::Curve: Mul<F, Output = C::Curve> + MulAssign<F>,
+    {
+        let blinding_factors = pk.vk.cs.blinding_factors();
+
+        // Closure to get values of expressions and compress them
+        let compress_expressions = |expressions: &[Expression<C::Scalar>]| {
+            domain.lagrange_from_vec(
+                Evaluator::<C>::new_compressed(expressions).evaluate_compressed(
+                    params.n as usize,
+                    1,
+                    fixed_values,
+                    advice_values,
+                    instance_values,
+                    *theta,
+                ),
+            )
+        };
+
+        // Get values of input expressions involved in the shuffle and compress them
+        let compressed_input_expression = compress_expressions(&self.input_expressions);
+
+        // Get values of shuffle expressions involved in the shuffle and compress them
+        let compressed_shuffle_expression = compress_expressions(&self.shuffle_expressions);
+
+        // Denominator: (\theta^{m-1} s_0(\omega^i) + ... + s_{m-1}(\omega^i) + \gamma)
+        let mut shuffle_product = vec![C::Scalar::zero(); params.n as usize];
+        parallelize(&mut shuffle_product, |shuffle_product, start| {
+            for (shuffle_product, shuffle_value) in shuffle_product
+                .iter_mut()
+                .zip(compressed_shuffle_expression[start..].iter())
+            {
+                *shuffle_product = *gamma + shuffle_value;
+            }
+        });
+
+        // Batch invert to obtain the denominators for the shuffle product
+        // polynomials
+        shuffle_product.iter_mut().batch_invert();
+
+        // Finish the computation of the entire fraction by computing the numerators
+        // (\theta^{m-1} a_0(\omega^i) + ... + a_{m-1}(\omega^i) + \gamma)
+        parallelize(&mut shuffle_product, |product, start| {
+            for (i, product) in product.iter_mut().enumerate() {
+                let i = i + start;
+
+                *product *= &(compressed_input_expression[i] + &*gamma);
+            }
+        });
+
+        // Compute the evaluations of the shuffle product polynomial
+        // over our domain, starting with z[0] = 1
+        let z = iter::once(C::Scalar::one())
+            .chain(shuffle_product)
+            .scan(C::Scalar::one(), |state, cur| {
+                *state *= &cur;
+                Some(*state)
+            })
+            // Take all rows including the "last" row which should
+            // be one if the expressions are a permutation of each other
+            .take(params.n as usize - blinding_factors)
+            // Chain random blinding factors.
+            .chain((0..blinding_factors).map(|_| C::Scalar::random(&mut rng)))
+            .collect::<Vec<_>>();
+        assert_eq!(z.len(), params.n as usize);
+
+        // The product over the usable rows is one only if the input values
+        // are a permutation of the shuffle values.
+        let u = (params.n as usize) - (blinding_factors + 1);
+        if z[u] != C::Scalar::one() {
+            return Err(Error::ConstraintSystemFailure);
+        }
+
+        let z = pk.vk.domain.lagrange_from_vec(z);
+
+        #[cfg(feature = "sanity-checks")]
+        // This test works only with intermediate representations in this method.
+        // It can be used for debugging purposes.
+        {
+            // l_0(X) * (1 - z(X)) = 0
+            assert_eq!(z[0], C::Scalar::one());
+
+            // z(\omega X) (s(X) + \gamma) - z(X) (a(X) + \gamma)
+            for i in 0..u {
+                let left = z[i + 1] * &(compressed_shuffle_expression[i] + &*gamma);
+                let right = z[i] * &(compressed_input_expression[i] + &*gamma);
+
+                assert_eq!(left, right);
+            }
+        }
+
+        let product_commitment = params.commit_lagrange(&z).to_affine();
+
+        // Hash product commitment
+        transcript.write_point(product_commitment)?;
+
+        Ok(Committed::<C> {
+            product_poly: pk.vk.domain.lagrange_to_coeff(z),
+        })
+    }
+}
+
+impl<C: CurveAffine> Committed<C> {
+    pub(in crate::plonk) fn evaluate<E: EncodedChallenge<C>, T: TranscriptWrite<C, E>>(
+        self,
+        pk: &ProvingKey<C>,
+        x: ChallengeX<C>,
+        transcript: &mut T,
+    ) -> Result<Evaluated<C>, Error> {
+        let domain = &pk.vk.domain;
+        let x_next = domain.rotate_omega(*x, Rotation::next());
+
+        let product_eval = eval_polynomial(&self.product_poly, *x);
+        let product_next_eval = eval_polynomial(&self.product_poly, x_next);
+
+        // Hash each advice evaluation
+        for eval in iter::empty()
+            .chain(Some(product_eval))
+            .chain(Some(product_next_eval))
+        {
+            transcript.write_scalar(eval)?;
+        }
+
+        Ok(Evaluated { constructed: self })
+    }
+}
+
+impl<C: CurveAffine> Evaluated<C> {
+    pub(in crate::plonk) fn open<'a>(
+        &'a self,
+        pk: &'a ProvingKey<C>,
+        x: ChallengeX<C>,
+    ) -> impl Iterator<Item = ProverQuery<'a, C>> + Clone {
+        let x_next = pk.vk.domain.rotate_omega(*x, Rotation::next());
+
+        iter::empty()
+            // Open shuffle product commitments at x
+            .chain(Some(ProverQuery {
+                point: *x,
+                rotation: Rotation::cur(),
+                poly: &self.constructed.product_poly,
+            }))
+            // Open shuffle product commitments at x_next
+            .chain(Some(ProverQuery {
+                point: x_next,
+                rotation: Rotation::next(),
+                poly: &self.constructed.product_poly,
+            }))
+    }
+}
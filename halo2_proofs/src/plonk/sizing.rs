@@ -0,0 +1,138 @@
+//! Measuring the number of rows a circuit uses.
+
+use std::cmp;
+
+use ff::Field;
+
+use super::{
+    Advice, Any, Assigned, Assignment, Circuit, Column, ConstraintSystem, Error, Fixed,
+    FloorPlanner, Instance, Selector,
+};
+
+/// Records the number of rows used by a circuit's synthesis.
+pub(crate) struct RowCounter {
+    rows: usize,
+}
+
+impl RowCounter {
+    fn use_row(&mut self, row: usize) {
+        self.rows = cmp::max(self.rows, row + 1);
+    }
+}
+
+impl<F: Field> Assignment<F> for RowCounter {
+    fn enter_region<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about regions in this context.
+    }
+
+    fn exit_region(&mut self) {
+        // Do nothing; we don't care about regions in this context.
+    }
+
+    fn enable_selector<A, AR>(&mut self, _: A, _: &Selector, row: usize) -> Result<(), Error>
+    where
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        Ok(())
+    }
+
+    fn query_instance(&self, _: Column<Instance>, _: usize) -> Result<Option<F>, Error> {
+        Ok(None)
+    }
+
+    fn assign_advice<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Advice>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        Ok(())
+    }
+
+    fn assign_fixed<V, VR, A, AR>(
+        &mut self,
+        _: A,
+        _: Column<Fixed>,
+        row: usize,
+        _: V,
+    ) -> Result<(), Error>
+    where
+        V: FnOnce() -> Result<VR, Error>,
+        VR: Into<Assigned<F>>,
+        A: FnOnce() -> AR,
+        AR: Into<String>,
+    {
+        self.use_row(row);
+        Ok(())
+    }
+
+    fn copy(
+        &mut self,
+        _: Column<Any>,
+        left_row: usize,
+        _: Column<Any>,
+        right_row: usize,
+    ) -> Result<(), Error> {
+        self.use_row(cmp::max(left_row, right_row));
+        Ok(())
+    }
+
+    fn fill_from_row(
+        &mut self,
+        _: Column<Fixed>,
+        row: usize,
+        _: Option<Assigned<F>>,
+    ) -> Result<(), Error> {
+        // Filling extends to the last usable row, however many there are, so only
+        // the first filled row has to fit.
+        self.use_row(row);
+        Ok(())
+    }
+
+    fn push_namespace<NR, N>(&mut self, _: N)
+    where
+        NR: Into<String>,
+        N: FnOnce() -> NR,
+    {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+
+    fn pop_namespace(&mut self, _: Option<String>) {
+        // Do nothing; we don't care about namespaces in this context.
+    }
+}
+
+/// Returns the smallest `k` such that `circuit` fits in `2^k` rows.
+///
+/// The circuit is synthesized without its witness values being computed, and the
+/// rows it assigns are counted together with the rows reserved for blinding factors.
+pub(crate) fn minimum_k<F: Field, ConcreteCircuit: Circuit<F>>(
+    circuit: &ConcreteCircuit,
+) -> Result<u32, Error> {
+    let mut cs = ConstraintSystem::default();
+    let config = ConcreteCircuit::configure(&mut cs);
+
+    let mut counter = RowCounter { rows: 0 };
+    ConcreteCircuit::FloorPlanner::synthesize(&mut counter, circuit, config, cs.constants.clone())?;
+
+    // The last blinding_factors + 1 rows are not usable by the circuit, and the
+    // tables of range checks and generated tables are filled in at keygen.
+    let rows = cmp::max(counter.rows, cs.keygen_table_rows());
+    let rows = cmp::max(rows + cs.blinding_factors() + 1, cs.minimum_rows());
+
+    Ok(rows.next_power_of_two().trailing_zeros())
+}
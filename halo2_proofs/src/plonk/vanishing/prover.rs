@@ -72,7 +72,13 @@ impl<C: CurveAffine> Committed<C> {
             .chunks_exact(params.n as usize)
             .map(|v| domain.coeff_from_vec(v.to_vec()))
             .collect::<Vec<_>>();
+        #[cfg(not(feature = "zeroize"))]
         drop(h_poly);
+        #[cfg(feature = "zeroize")]
+        {
+            let mut h_poly = h_poly;
+            crate::helpers::wipe(&mut h_poly);
+        }
 
         // Compute commitments to each h(X) piece
         let h_commitments_projective: Vec<_> = h_pieces
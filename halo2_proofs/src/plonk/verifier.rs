@@ -1,6 +1,8 @@
-use ff::Field;
+use blake2b_simd::Params as Blake2bParams;
+use ff::{Field, PrimeField};
 use group::Curve;
 use rand_core::RngCore;
+use std::borrow::Cow;
 use std::iter;
 use std::marker::PhantomData;
 use std::ops::Mul;
@@ -11,13 +13,17 @@ use super::{
 };
 use crate::arithmetic::{BaseExt, CurveAffine, FieldExt, MultiMillerLoop};
 
+use crate::logging::{Stage, VERIFIER};
 use crate::poly::{
-    commitment::{Blind, Params, ParamsVerifier},
+    commitment::{Blind, CommitmentScheme, Params, ParamsVerifier},
     multiopen::Decider,
     multiopen::{self, VerifierQuery},
     PairMSM, MSM,
 };
-use crate::transcript::{read_n_points, read_n_scalars, EncodedChallenge, TranscriptRead};
+use crate::transcript::{
+    read_n_points, read_n_scalars, EncodedChallenge, StrictRead, TranscriptRead,
+};
+use log::info;
 
 /// Trait representing a strategy for verifying Halo 2 proofs.
 pub trait VerificationStrategy<C: CurveAffine> {
@@ -30,19 +36,24 @@ pub trait VerificationStrategy<C: CurveAffine> {
 }
 
 /// A verifier that checks a single proof at a time.
+///
+/// Like the other strategies, it doesn't borrow the verifier params it is
+/// constructed from.
 #[derive(Debug)]
-pub struct SingleVerifier<'a, E: MultiMillerLoop> {
-    params: &'a ParamsVerifier<E>,
+pub struct SingleVerifier<E: MultiMillerLoop> {
+    decider: Decider<E>,
 }
 
-impl<'a, C: MultiMillerLoop> SingleVerifier<'a, C> {
+impl<C: MultiMillerLoop> SingleVerifier<C> {
     /// Constructs a new single proof verifier.
-    pub fn new(params: &'a ParamsVerifier<C>) -> Self {
-        SingleVerifier { params }
+    pub fn new(params: &ParamsVerifier<C>) -> Self {
+        SingleVerifier {
+            decider: Decider::new(params),
+        }
     }
 }
 
-impl<'a, C: MultiMillerLoop> VerificationStrategy<C::G1Affine> for SingleVerifier<'a, C> {
+impl<C: MultiMillerLoop> VerificationStrategy<C::G1Affine> for SingleVerifier<C> {
     type Output = ();
 
     fn process(
@@ -50,7 +61,7 @@ impl<'a, C: MultiMillerLoop> VerificationStrategy<C::G1Affine> for SingleVerifie
         f: impl FnOnce() -> Result<PairMSM<C::G1Affine>, Error>,
     ) -> Result<Self::Output, Error> {
         let guard = f()?;
-        if Decider::verify(self.params, guard) {
+        if self.decider.decide(guard) {
             Ok(())
         } else {
             Err(Error::ConstraintSystemFailure)
@@ -60,17 +71,17 @@ impl<'a, C: MultiMillerLoop> VerificationStrategy<C::G1Affine> for SingleVerifie
 
 /// A verifier that checks multiple proofs in a batch.
 #[derive(Debug)]
-pub struct BatchVerifier<'a, E: MultiMillerLoop, R: RngCore> {
-    params: &'a ParamsVerifier<E>,
+pub struct BatchVerifier<E: MultiMillerLoop, R: RngCore> {
+    decider: Decider<E>,
     msm: PairMSM<E::G1Affine>,
     rng: R,
 }
 
-impl<'a, E: MultiMillerLoop, R: RngCore> BatchVerifier<'a, E, R> {
+impl<E: MultiMillerLoop, R: RngCore> BatchVerifier<E, R> {
     /// Constructs a new batch verifier.
-    pub fn new(params: &'a ParamsVerifier<E>, rng: R) -> Self {
+    pub fn new(params: &ParamsVerifier<E>, rng: R) -> Self {
         BatchVerifier {
-            params,
+            decider: Decider::new(params),
             msm: PairMSM::default(),
             rng,
         }
@@ -82,13 +93,11 @@ impl<'a, E: MultiMillerLoop, R: RngCore> BatchVerifier<'a, E, R> {
     /// specific failing proofs, it must re-process the proofs separately.
     #[must_use]
     pub fn finalize(self) -> bool {
-        Decider::verify(self.params, self.msm)
+        self.decider.decide(self.msm)
     }
 }
 
-impl<'a, C: MultiMillerLoop, R: RngCore> VerificationStrategy<C::G1Affine>
-    for BatchVerifier<'a, C, R>
-{
+impl<C: MultiMillerLoop, R: RngCore> VerificationStrategy<C::G1Affine> for BatchVerifier<C, R> {
     type Output = Self;
 
     fn process(
@@ -105,25 +114,337 @@ impl<'a, C: MultiMillerLoop, R: RngCore> VerificationStrategy<C::G1Affine>
         Ok(Self {
             msm: self.msm,
             rng: self.rng,
-            params: self.params,
+            decider: self.decider,
+        })
+    }
+}
+
+/// The deferred final pairing check of one or more proofs.
+///
+/// The proofs it was built from are valid if and only if
+/// `e(lhs, [s]_2) = e(rhs, [1]_2)`. Unlike a [`PairMSM`], it only holds two
+/// points, so it can be passed on to an outer circuit, or folded into a later
+/// batch with [`AccumulatorStrategy::with_accumulator`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Accumulator<C: CurveAffine> {
+    /// The point paired with `[s]_2`.
+    pub lhs: C,
+    /// The point paired with `[1]_2`.
+    pub rhs: C,
+}
+
+impl<C: CurveAffine> Accumulator<C> {
+    /// Performs the deferred pairing check.
+    pub fn decide<E: MultiMillerLoop<G1Affine = C>>(&self, params: &ParamsVerifier<E>) -> bool {
+        Decider::verify(params, self.to_msm(params))
+    }
+
+    fn to_msm<E: MultiMillerLoop<G1Affine = C>>(&self, params: &ParamsVerifier<E>) -> PairMSM<C> {
+        let mut left = params.empty_msm();
+        left.append_term(C::Scalar::one(), self.lhs);
+        let mut right = params.empty_msm();
+        right.append_term(C::Scalar::one(), self.rhs);
+        PairMSM::with(left, right)
+    }
+}
+
+/// A verifier that checks multiple proofs in a batch, like [`BatchVerifier`],
+/// but defers the final pairing check: the batch is reduced to an
+/// [`Accumulator`] instead.
+#[derive(Debug)]
+pub struct AccumulatorStrategy<E: MultiMillerLoop, R: RngCore> {
+    decider: Decider<E>,
+    msm: PairMSM<E::G1Affine>,
+    rng: R,
+}
+
+impl<E: MultiMillerLoop, R: RngCore> AccumulatorStrategy<E, R> {
+    /// Constructs a new accumulator strategy.
+    pub fn new(params: &ParamsVerifier<E>, rng: R) -> Self {
+        AccumulatorStrategy {
+            decider: Decider::new(params),
+            msm: PairMSM::default(),
+            rng,
+        }
+    }
+
+    /// Constructs an accumulator strategy that folds new proofs into an
+    /// existing accumulator.
+    pub fn with_accumulator(
+        params: &ParamsVerifier<E>,
+        accumulator: Accumulator<E::G1Affine>,
+        rng: R,
+    ) -> Self {
+        AccumulatorStrategy {
+            decider: Decider::new(params),
+            msm: accumulator.to_msm(params),
+            rng,
+        }
+    }
+
+    /// Returns the accumulator of the proofs processed so far.
+    pub fn accumulator(&self) -> Accumulator<E::G1Affine> {
+        let (lhs, rhs) = self.msm.eval();
+        Accumulator { lhs, rhs }
+    }
+
+    /// Finalizes the batch and checks its validity.
+    ///
+    /// Returns `false` if *some* proof was invalid.
+    #[must_use]
+    pub fn finalize(self) -> bool {
+        self.decider.decide(self.msm)
+    }
+}
+
+impl<C: MultiMillerLoop, R: RngCore> VerificationStrategy<C::G1Affine>
+    for AccumulatorStrategy<C, R>
+{
+    type Output = Self;
+
+    fn process(
+        mut self,
+        f: impl FnOnce() -> Result<PairMSM<C::G1Affine>, Error>,
+    ) -> Result<Self::Output, Error> {
+        // As in `BatchVerifier`, the random factor keeps an invalid proof from
+        // cancelling out the accumulated ones.
+        self.msm.scale(C::Scalar::random(&mut self.rng));
+        let to_add = f()?;
+        self.msm.add_msm(to_add);
+
+        Ok(Self {
+            msm: self.msm,
+            rng: self.rng,
+            decider: self.decider,
         })
     }
 }
 
 /// Returns a boolean indicating whether or not the proof is valid
+///
+/// Any configuration columns of the circuit are expected to hold the values
+/// that were assigned to them at keygen.
+///
+/// # Memory
+///
+/// Besides `params` and `vk`, the verifier holds the proof as it is read from
+/// the transcript, the queries made to its commitments, and the two MSMs of the
+/// final pairing check. Each of these has a term per commitment or evaluation of
+/// the proof, or per commitment of `vk`, so the memory that verification needs
+/// grows with the size of the proof and of `vk`, but not with the number of rows
+/// `2^k` of the circuit. The MSMs are accumulated in place and handed to the
+/// strategy without being copied. [`SingleVerifier`] drops them after the
+/// pairing check; [`BatchVerifier`] and [`AccumulatorStrategy`] keep them until
+/// they are finalized, so their memory grows with the number of proofs in the
+/// batch. To verify many proofs in bounded memory, take the
+/// [`AccumulatorStrategy::accumulator`] after each proof, which is two points, and
+/// fold the next proof into it with [`AccumulatorStrategy::with_accumulator`].
 pub fn verify_proof<
-    'params,
     C: MultiMillerLoop,
     E: EncodedChallenge<C::G1Affine>,
     T: TranscriptRead<C::G1Affine, E>,
     V: VerificationStrategy<C::G1Affine>,
 >(
-    params: &'params ParamsVerifier<C>,
+    params: &ParamsVerifier<C>,
     vk: &VerifyingKey<C::G1Affine>,
     strategy: V,
     instances: &[&[&[C::Scalar]]],
     transcript: &mut T,
 ) -> Result<V::Output, Error> {
+    let configuration = keygen_configuration(vk);
+
+    verify_proof_with_configuration(params, vk, strategy, &configuration, instances, transcript)
+}
+
+/// Like [`verify_proof`], but only accepts the unique encoding of a valid proof, so
+/// that a proof can't be altered into another one that also verifies. Besides what
+/// every transcript rejects (non-canonical scalars and points), this rejects bytes
+/// after the end of the proof, and the point at infinity in place of a commitment
+/// that an honest prover never sets to it: the random polynomial of the vanishing
+/// argument and, with SHPLONK params, the opening proof. Commitments to
+/// columns can be the point at infinity, as non-ZK proofs commit to columns that
+/// are zero on every row without blinding them.
+pub fn verify_proof_strict<
+    C: MultiMillerLoop,
+    E: EncodedChallenge<C::G1Affine>,
+    T: TranscriptRead<C::G1Affine, E>,
+    V: VerificationStrategy<C::G1Affine>,
+>(
+    params: &ParamsVerifier<C>,
+    vk: &VerifyingKey<C::G1Affine>,
+    strategy: V,
+    instances: &[&[&[C::Scalar]]],
+    transcript: &mut T,
+) -> Result<V::Output, Error> {
+    let mut transcript = StrictRead(transcript);
+    let output = verify_proof(params, vk, strategy, instances, &mut transcript)?;
+    transcript.ensure_exhausted()?;
+
+    Ok(output)
+}
+
+/// Verifies a batch of proofs, each read from its own transcript, for any number
+/// of circuits.
+///
+/// Each proof is given with the verifying key of its circuit, its instances and
+/// its transcript, as they would be passed to [`verify_proof`]. The final pairing
+/// checks of all the proofs are combined with a random linear combination drawn
+/// from `rng`, so that verifying the batch costs a single pairing check, as with
+/// [`BatchVerifier`].
+///
+/// Returns [`Error::ConstraintSystemFailure`] if *some* proof is invalid. If the
+/// caller needs to identify the failing proofs, it must verify them separately.
+pub fn verify_proofs_batch<
+    C: MultiMillerLoop,
+    E: EncodedChallenge<C::G1Affine>,
+    T: TranscriptRead<C::G1Affine, E>,
+    R: RngCore,
+>(
+    params: &ParamsVerifier<C>,
+    proofs: &mut [(&VerifyingKey<C::G1Affine>, &[&[&[C::Scalar]]], T)],
+    rng: R,
+) -> Result<(), Error> {
+    let strategy = proofs.iter_mut().try_fold(
+        BatchVerifier::new(params, rng),
+        |strategy, (vk, instances, transcript)| {
+            verify_proof(params, vk, strategy, instances, transcript)
+        },
+    )?;
+
+    if strategy.finalize() {
+        Ok(())
+    } else {
+        Err(Error::ConstraintSystemFailure)
+    }
+}
+
+/// Computes a digest that binds a batch of independent proofs, each given with the
+/// verifying key of its circuit and its instances as they would be passed to
+/// [`verify_proofs_batch`], and the bytes of the proof, so that the batch can be
+/// anchored under a single commitment, such as one stored on chain.
+///
+/// The digest is the 32-byte BLAKE2b hash, personalized with `Halo2-ProofBatch`,
+/// of the following encoding, in which lengths are 8-byte little-endian integers
+/// and field elements are in their canonical encoding:
+///
+/// - the number of proofs;
+/// - for each proof, in order:
+///   - the digest of its verifying key, as absorbed by [`VerifyingKey::hash_into`];
+///   - the number of circuit instances it proves, then for each of them the
+///     number of instance columns, then for each column its length followed by
+///     its values;
+///   - the length of the proof in bytes, followed by its bytes.
+///
+/// Computing the digest does not verify the proofs.
+pub fn proofs_digest<C: CurveAffine>(
+    proofs: &[(&VerifyingKey<C>, &[&[&[C::Scalar]]], &[u8])],
+) -> [u8; 32] {
+    let mut hasher = Blake2bParams::new()
+        .hash_length(32)
+        .personal(b"Halo2-ProofBatch")
+        .to_state();
+    let update_len = |hasher: &mut blake2b_simd::State, len: usize| {
+        hasher.update(&(len as u64).to_le_bytes());
+    };
+
+    update_len(&mut hasher, proofs.len());
+    for (vk, instances, proof) in proofs {
+        hasher.update(vk.transcript_repr().to_repr().as_ref());
+        update_len(&mut hasher, instances.len());
+        for instance in instances.iter() {
+            update_len(&mut hasher, instance.len());
+            for column in instance.iter() {
+                update_len(&mut hasher, column.len());
+                for value in column.iter() {
+                    hasher.update(value.to_repr().as_ref());
+                }
+            }
+        }
+        update_len(&mut hasher, proof.len());
+        hasher.update(proof);
+    }
+
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(hasher.finalize().as_bytes());
+    digest
+}
+
+/// Verifies proofs for different circuits that were written one after another
+/// into the same transcript, by calling [`create_proof`] with the same transcript
+/// for each circuit in turn.
+///
+/// Each segment is the verifying key of a circuit and the instances of its proofs,
+/// as they would be passed to [`verify_proof`]. The segments must be in the order
+/// in which they were proven, as the challenges of each proof depend on all of
+/// the proofs before it. The proofs of all segments are processed by the same
+/// `strategy`, which must be one that accumulates them, such as [`BatchVerifier`]
+/// or [`AccumulatorStrategy`].
+///
+/// [`create_proof`]: crate::plonk::create_proof
+pub fn verify_proof_segments<
+    C: MultiMillerLoop,
+    E: EncodedChallenge<C::G1Affine>,
+    T: TranscriptRead<C::G1Affine, E>,
+    V: VerificationStrategy<C::G1Affine, Output = V>,
+>(
+    params: &ParamsVerifier<C>,
+    segments: &[(&VerifyingKey<C::G1Affine>, &[&[&[C::Scalar]]])],
+    strategy: V,
+    transcript: &mut T,
+) -> Result<V, Error> {
+    segments
+        .iter()
+        .try_fold(strategy, |strategy, (vk, instances)| {
+            verify_proof(params, vk, strategy, instances, transcript)
+        })
+}
+
+/// Returns a boolean indicating whether or not the proof is valid, given the
+/// expected commitments to the circuit's configuration columns (see
+/// [`ConstraintSystem::configuration_column`]) in the order in which they were
+/// allocated.
+///
+/// These commitments are unblinded commitments to the configuration values in
+/// the Lagrange basis. They can be computed with [`Params::commit_lagrange`],
+/// or with [`ParamsVerifier::commit_lagrange`] if the values fit within its
+/// public inputs. Returns [`Error::ConfigurationMismatch`] if the proof commits
+/// to other configuration values.
+///
+/// [`ConstraintSystem::configuration_column`]: crate::plonk::ConstraintSystem::configuration_column
+pub fn verify_proof_with_configuration<
+    C: MultiMillerLoop,
+    E: EncodedChallenge<C::G1Affine>,
+    T: TranscriptRead<C::G1Affine, E>,
+    V: VerificationStrategy<C::G1Affine>,
+>(
+    params: &ParamsVerifier<C>,
+    vk: &VerifyingKey<C::G1Affine>,
+    strategy: V,
+    configuration: &[C::G1Affine],
+    instances: &[&[&[C::Scalar]]],
+    transcript: &mut T,
+) -> Result<V::Output, Error> {
+    let instance_commitments = instance_commitments(params, vk, instances)?;
+    let instance_commitments: Vec<_> = instance_commitments.iter().map(|c| &c[..]).collect();
+
+    verify_committed(
+        params,
+        vk,
+        vk.transcript_repr(),
+        strategy,
+        configuration,
+        &instance_commitments,
+        transcript,
+    )
+}
+
+/// Commits to the instance columns of each proof with the Lagrange bases of
+/// `params`.
+fn instance_commitments<C: MultiMillerLoop>(
+    params: &ParamsVerifier<C>,
+    vk: &VerifyingKey<C::G1Affine>,
+    instances: &[&[&[C::Scalar]]],
+) -> Result<Vec<Vec<C::G1Affine>>, Error> {
     // Check that instances matches the expected number of instance columns
     for instances in instances.iter() {
         if instances.len() != vk.cs.num_instance_columns {
@@ -131,7 +452,7 @@ pub fn verify_proof<
         }
     }
 
-    let instance_commitments = instances
+    instances
         .iter()
         .map(|instance| {
             instance
@@ -145,12 +466,205 @@ pub fn verify_proof<
                 })
                 .collect::<Result<Vec<_>, _>>()
         })
-        .collect::<Result<Vec<_>, _>>()?;
+        .collect()
+}
+
+/// The commitments to the configuration columns that were assigned at keygen.
+fn keygen_configuration<C: CurveAffine>(vk: &VerifyingKey<C>) -> Vec<C> {
+    vk.cs
+        .configuration_columns
+        .iter()
+        .map(|column| vk.fixed_commitments[column.index()])
+        .collect()
+}
+
+/// Like [`verify_proof`], but takes the commitments to the instance columns
+/// of each proof instead of their values, as computed by [`commit_instances`].
+///
+/// [`verify_proof`] commits to the instance columns itself, with an MSM of
+/// the size of each column, and needs `params` with at least as many Lagrange
+/// bases as the longest column. When a circuit has many public inputs, they
+/// can instead be committed to once, by whoever provides them, and handed to
+/// any number of verifiers, which then only need `params` built with
+/// [`Params::verifier`]`(0)`. The proof is the same either way, and the
+/// instance values are never interpolated: their evaluations are read from
+/// the proof and checked against the commitments by the multiopen argument.
+///
+/// The verifier trusts the commitments to be those of the intended public
+/// inputs, so they must come from a source that is bound to those inputs.
+pub fn verify_proof_with_instance_commitments<
+    C: MultiMillerLoop,
+    E: EncodedChallenge<C::G1Affine>,
+    T: TranscriptRead<C::G1Affine, E>,
+    V: VerificationStrategy<C::G1Affine>,
+>(
+    params: &ParamsVerifier<C>,
+    vk: &VerifyingKey<C::G1Affine>,
+    strategy: V,
+    instance_commitments: &[&[C::G1Affine]],
+    transcript: &mut T,
+) -> Result<V::Output, Error> {
+    let configuration = keygen_configuration(vk);
+
+    verify_committed(
+        params,
+        vk,
+        vk.transcript_repr(),
+        strategy,
+        &configuration,
+        instance_commitments,
+        transcript,
+    )
+}
+
+/// Commits to the instance columns of a proof, for
+/// [`verify_proof_with_instance_commitments`].
+///
+/// The commitments are unblinded commitments to the values in the Lagrange
+/// basis, which are the ones the prover writes to the transcript.
+pub fn commit_instances<C: CurveAffine>(
+    params: &Params<C>,
+    vk: &VerifyingKey<C>,
+    instance: &[&[C::Scalar]],
+) -> Result<Vec<C>, Error> {
+    if instance.len() != vk.cs.num_instance_columns {
+        return Err(Error::InvalidInstances);
+    }
+
+    let commitments: Vec<_> = instance
+        .iter()
+        .map(|values| {
+            let mut poly = vk.domain.empty_lagrange();
+            if values.len() > poly.len() - (vk.cs.blinding_factors() + 1) {
+                return Err(Error::InstanceTooLarge);
+            }
+            for (poly, value) in poly.iter_mut().zip(values.iter()) {
+                *poly = *value;
+            }
+            Ok(params.commit_lagrange(&poly))
+        })
+        .collect::<Result<_, _>>()?;
+    let mut affine = vec![C::identity(); commitments.len()];
+    C::Curve::batch_normalize(&commitments, &mut affine);
+
+    Ok(affine)
+}
+
+/// A verifying key together with the parts of verification that don't depend
+/// on the proof, for services that verify many proofs of the same circuit.
+///
+/// [`verify_proof`] recomputes these for every call. Most of this work is the
+/// digest of `vk` that is hashed into the transcript
+/// ([`VerifyingKey::transcript_repr`]), which hashes a description of the whole
+/// constraint system and so costs more than the rest of verification for
+/// circuits with many gates. The commitments to the circuit's configuration
+/// columns are also kept. The pairing checks, which depend on the proof, are
+/// left to the strategy as usual.
+///
+/// The checks made by [`PreprocessedVerifier::verify`] and the proofs it accepts
+/// are exactly those of [`verify_proof`].
+#[derive(Debug)]
+pub struct PreprocessedVerifier<'a, C: CurveAffine> {
+    vk: &'a VerifyingKey<C>,
+    vk_repr: C::Scalar,
+    configuration: Vec<C>,
+}
+
+impl<'a, C: CurveAffine> PreprocessedVerifier<'a, C> {
+    /// Precomputes the proof-independent parts of verifying proofs for `vk`.
+    pub fn new(vk: &'a VerifyingKey<C>) -> Self {
+        PreprocessedVerifier {
+            vk,
+            vk_repr: vk.transcript_repr(),
+            configuration: keygen_configuration(vk),
+        }
+    }
+
+    /// Returns the verifying key this was built from.
+    pub fn vk(&self) -> &'a VerifyingKey<C> {
+        self.vk
+    }
+
+    /// Verifies a proof, like [`verify_proof`].
+    pub fn verify<
+        M: MultiMillerLoop<G1Affine = C, Scalar = C::Scalar>,
+        E: EncodedChallenge<C>,
+        T: TranscriptRead<C, E>,
+        V: VerificationStrategy<C>,
+    >(
+        &self,
+        params: &ParamsVerifier<M>,
+        strategy: V,
+        instances: &[&[&[C::Scalar]]],
+        transcript: &mut T,
+    ) -> Result<V::Output, Error> {
+        let instance_commitments = instance_commitments(params, self.vk, instances)?;
+        let instance_commitments: Vec<_> = instance_commitments.iter().map(|c| &c[..]).collect();
+
+        self.verify_with_instance_commitments(params, strategy, &instance_commitments, transcript)
+    }
+
+    /// Verifies a proof against commitments to its instance columns, like
+    /// [`verify_proof_with_instance_commitments`].
+    pub fn verify_with_instance_commitments<
+        M: MultiMillerLoop<G1Affine = C, Scalar = C::Scalar>,
+        E: EncodedChallenge<C>,
+        T: TranscriptRead<C, E>,
+        V: VerificationStrategy<C>,
+    >(
+        &self,
+        params: &ParamsVerifier<M>,
+        strategy: V,
+        instance_commitments: &[&[C]],
+        transcript: &mut T,
+    ) -> Result<V::Output, Error> {
+        verify_committed(
+            params,
+            self.vk,
+            self.vk_repr,
+            strategy,
+            &self.configuration,
+            instance_commitments,
+            transcript,
+        )
+    }
+}
+
+fn verify_committed<
+    C: MultiMillerLoop,
+    E: EncodedChallenge<C::G1Affine>,
+    T: TranscriptRead<C::G1Affine, E>,
+    V: VerificationStrategy<C::G1Affine>,
+>(
+    params: &ParamsVerifier<C>,
+    vk: &VerifyingKey<C::G1Affine>,
+    vk_repr: C::Scalar,
+    strategy: V,
+    configuration: &[C::G1Affine],
+    instance_commitments: &[&[C::G1Affine]],
+    transcript: &mut T,
+) -> Result<V::Output, Error> {
+    if configuration.len() != vk.cs.configuration_columns.len() {
+        return Err(Error::ConfigurationMismatch);
+    }
+    for instance_commitments in instance_commitments.iter() {
+        if instance_commitments.len() != vk.cs.num_instance_columns {
+            return Err(Error::InvalidInstances);
+        }
+    }
+
+    info!(
+        target: VERIFIER,
+        "event=start k={} proofs={}",
+        vk.domain.k(),
+        instance_commitments.len()
+    );
+    let stage = Stage::start(VERIFIER, "read");
 
     let num_proofs = instance_commitments.len();
 
     // Hash verification key into transcript
-    vk.hash_into(transcript)?;
+    transcript.common_scalar(vk_repr)?;
 
     for instance_commitments in instance_commitments.iter() {
         // Hash the instance (external) commitments into the transcript
@@ -166,6 +680,19 @@ pub fn verify_proof<
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    // The prover's configuration commitments must match the expected ones,
+    // which are then used in place of those read from the proof.
+    let configuration_commitments = read_n_points(transcript, vk.cs.configuration_columns.len())?;
+    if configuration_commitments[..] != configuration[..] {
+        return Err(Error::ConfigurationMismatch);
+    }
+    // The fixed commitments of the verifying key are only copied if some of
+    // them are replaced by configuration commitments.
+    let mut fixed_commitments = Cow::Borrowed(&vk.fixed_commitments[..]);
+    for (column, commitment) in vk.cs.configuration_columns.iter().zip(configuration) {
+        fixed_commitments.to_mut()[column.index()] = *commitment;
+    }
+
     // Sample theta challenge for keeping lookup columns linearly independent
     let theta: ChallengeTheta<_> = transcript.squeeze_challenge_scalar();
 
@@ -204,6 +731,17 @@ pub fn verify_proof<
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    let shuffles_committed = (0..num_proofs)
+        .map(|_| -> Result<Vec<_>, _> {
+            // Hash each shuffle product commitment
+            vk.cs
+                .shuffles
+                .iter()
+                .map(|argument| argument.read_product_commitment(transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
     let vanishing = vanishing::Argument::read_commitments_before_y(transcript)?;
 
     // Sample y challenge, which keeps the gates linearly independent.
@@ -243,6 +781,19 @@ pub fn verify_proof<
         })
         .collect::<Result<Vec<_>, _>>()?;
 
+    let shuffles_evaluated = shuffles_committed
+        .into_iter()
+        .map(|shuffles| -> Result<Vec<_>, _> {
+            shuffles
+                .into_iter()
+                .map(|shuffle| shuffle.evaluate(transcript))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    stage.finish();
+    let stage = Stage::start(VERIFIER, "quotient");
+
     // This check ensures the circuit is satisfied so long as the polynomial
     // commitments open to the correct values.
     let vanishing = {
@@ -266,60 +817,84 @@ pub fn verify_proof<
             .zip(instance_evals.iter())
             .zip(permutations_evaluated.iter())
             .zip(lookups_evaluated.iter())
-            .flat_map(|(((advice_evals, instance_evals), permutation), lookups)| {
-                let fixed_evals = &fixed_evals;
-                std::iter::empty()
-                    // Evaluate the circuit using the custom gates provided
-                    .chain(vk.cs.gates.iter().flat_map(move |gate| {
-                        gate.polynomials().iter().map(move |poly| {
-                            poly.evaluate(
-                                &|scalar| scalar,
-                                &|_| panic!("virtual selectors are removed during optimization"),
-                                &|index, _, _| fixed_evals[index],
-                                &|index, _, _| advice_evals[index],
-                                &|index, _, _| instance_evals[index],
-                                &|a| -a,
-                                &|a, b| a + &b,
-                                &|a, b| a * &b,
-                                &|a, scalar| a * &scalar,
-                            )
-                        })
-                    }))
-                    .chain(permutation.expressions(
-                        vk,
-                        &vk.cs.permutation,
-                        &permutations_common,
-                        advice_evals,
-                        fixed_evals,
-                        instance_evals,
-                        l_0,
-                        l_last,
-                        l_blind,
-                        beta,
-                        gamma,
-                        x,
-                    ))
-                    .chain(
-                        lookups
-                            .iter()
-                            .zip(vk.cs.lookups.iter())
-                            .flat_map(move |(p, argument)| {
-                                p.expressions(
-                                    l_0,
-                                    l_last,
-                                    l_blind,
-                                    argument,
-                                    theta,
-                                    beta,
-                                    gamma,
-                                    advice_evals,
-                                    fixed_evals,
-                                    instance_evals,
+            .zip(shuffles_evaluated.iter())
+            .flat_map(
+                |((((advice_evals, instance_evals), permutation), lookups), shuffles)| {
+                    let fixed_evals = &fixed_evals;
+                    std::iter::empty()
+                        // Evaluate the circuit using the custom gates provided
+                        .chain(vk.cs.gates.iter().flat_map(move |gate| {
+                            gate.polynomials().iter().map(move |poly| {
+                                poly.evaluate(
+                                    &|scalar| scalar,
+                                    &|_| {
+                                        panic!("virtual selectors are removed during optimization")
+                                    },
+                                    &|index, _, _| fixed_evals[index],
+                                    &|index, _, _| advice_evals[index],
+                                    &|index, _, _| instance_evals[index],
+                                    &|a| -a,
+                                    &|a, b| a + &b,
+                                    &|a, b| a * &b,
+                                    &|a, scalar| a * &scalar,
                                 )
                             })
-                            .into_iter(),
-                    )
-            });
+                        }))
+                        .chain(permutation.expressions(
+                            vk,
+                            &vk.cs.permutation,
+                            &permutations_common,
+                            advice_evals,
+                            fixed_evals,
+                            instance_evals,
+                            l_0,
+                            l_last,
+                            l_blind,
+                            beta,
+                            gamma,
+                            x,
+                        ))
+                        .chain(
+                            lookups
+                                .iter()
+                                .zip(vk.cs.lookups.iter())
+                                .flat_map(move |(p, argument)| {
+                                    p.expressions(
+                                        l_0,
+                                        l_last,
+                                        l_blind,
+                                        argument,
+                                        theta,
+                                        beta,
+                                        gamma,
+                                        advice_evals,
+                                        fixed_evals,
+                                        instance_evals,
+                                    )
+                                })
+                                .into_iter(),
+                        )
+                        .chain(
+                            shuffles
+                                .iter()
+                                .zip(vk.cs.shuffles.iter())
+                                .flat_map(move |(p, argument)| {
+                                    p.expressions(
+                                        l_0,
+                                        l_last,
+                                        l_blind,
+                                        argument,
+                                        theta,
+                                        gamma,
+                                        advice_evals,
+                                        fixed_evals,
+                                        instance_evals,
+                                    )
+                                })
+                                .into_iter(),
+                        )
+                },
+            );
 
         vanishing.verify(expressions, y, xn)
     };
@@ -331,13 +906,20 @@ pub fn verify_proof<
         .zip(advice_evals.iter())
         .zip(permutations_evaluated.iter())
         .zip(lookups_evaluated.iter())
+        .zip(shuffles_evaluated.iter())
         .flat_map(
             |(
                 (
-                    (((instance_commitments, instance_evals), advice_commitments), advice_evals),
-                    permutation,
+                    (
+                        (
+                            ((instance_commitments, instance_evals), advice_commitments),
+                            advice_evals,
+                        ),
+                        permutation,
+                    ),
+                    lookups,
                 ),
-                lookups,
+                shuffles,
             )| {
                 iter::empty()
                     .chain(vk.cs.instance_queries.iter().enumerate().map(
@@ -367,6 +949,12 @@ pub fn verify_proof<
                             .flat_map(move |p| p.queries(vk, x))
                             .into_iter(),
                     )
+                    .chain(
+                        shuffles
+                            .iter()
+                            .flat_map(move |p| p.queries(vk, x))
+                            .into_iter(),
+                    )
             },
         )
         .chain(
@@ -376,7 +964,7 @@ pub fn verify_proof<
                 .enumerate()
                 .map(|(query_index, &(column, at))| {
                     VerifierQuery::new_commitment(
-                        &vk.fixed_commitments[column.index()],
+                        &fixed_commitments[column.index()],
                         vk.domain.rotate_omega(*x, at),
                         at,
                         fixed_evals[query_index],
@@ -386,9 +974,180 @@ pub fn verify_proof<
         .chain(permutations_common.queries(&vk.permutation, x))
         .chain(vanishing.queries(x));
 
+    stage.finish();
+
     // We are now convinced the circuit is satisfied so long as the
     // polynomial commitments open to the correct values.
+    let _stage = Stage::start(VERIFIER, "multiopen");
     strategy.process(|| {
         multiopen::verify_proof(params, transcript, queries).map_err(|_| Error::Opening)
     })
 }
+
+impl<C: CurveAffine> VerifyingKey<C> {
+    /// Returns labels for the steps that [`verify_proof`] takes through its
+    /// transcript to verify `num_proofs` proofs, in the order in which it takes
+    /// them, when the multi-opening argument is that of `scheme`. This must be
+    /// kept in sync with [`verify_proof_with_configuration`].
+    ///
+    /// The number of witness commitments read by the GWC multi-opening argument
+    /// depends on the queries, so they are not labelled here.
+    pub(crate) fn transcript_labels(
+        &self,
+        num_proofs: usize,
+        scheme: CommitmentScheme,
+    ) -> Vec<String> {
+        let proof = |index: usize| {
+            if num_proofs > 1 {
+                format!("proof {}: ", index)
+            } else {
+                String::new()
+            }
+        };
+        let at = |rotation: &crate::poly::Rotation| format!("at rotation {}", rotation.0);
+        let permutation_sets = {
+            let columns = self.cs.permutation.get_columns().len();
+            let chunk_len = self.cs.degree() - 2;
+            (columns + chunk_len - 1) / chunk_len
+        };
+
+        let mut labels = vec!["vk digest".to_string()];
+        for p in 0..num_proofs {
+            for i in 0..self.cs.num_instance_columns {
+                labels.push(format!("{}instance {} commitment", proof(p), i));
+            }
+        }
+        for p in 0..num_proofs {
+            for i in 0..self.cs.num_advice_columns {
+                labels.push(format!("{}advice {} commitment", proof(p), i));
+            }
+        }
+        for column in self.cs.configuration_columns.iter() {
+            labels.push(format!("configuration {} commitment", column.index()));
+        }
+        labels.push("theta".to_string());
+        for p in 0..num_proofs {
+            for i in 0..self.cs.lookups.len() {
+                #[cfg(not(feature = "mv-lookup"))]
+                {
+                    labels.push(format!(
+                        "{}lookup {} permuted input commitment",
+                        proof(p),
+                        i
+                    ));
+                    labels.push(format!(
+                        "{}lookup {} permuted table commitment",
+                        proof(p),
+                        i
+                    ));
+                }
+                #[cfg(feature = "mv-lookup")]
+                labels.push(format!("{}lookup {} multiplicity commitment", proof(p), i));
+            }
+        }
+        labels.push("beta".to_string());
+        labels.push("gamma".to_string());
+        for p in 0..num_proofs {
+            for set in 0..permutation_sets {
+                labels.push(format!(
+                    "{}permutation product {} commitment",
+                    proof(p),
+                    set
+                ));
+            }
+        }
+        for p in 0..num_proofs {
+            for i in 0..self.cs.lookups.len() {
+                #[cfg(not(feature = "mv-lookup"))]
+                labels.push(format!("{}lookup {} product commitment", proof(p), i));
+                #[cfg(feature = "mv-lookup")]
+                labels.push(format!("{}lookup {} phi commitment", proof(p), i));
+            }
+        }
+        for p in 0..num_proofs {
+            for i in 0..self.cs.shuffles.len() {
+                labels.push(format!("{}shuffle {} product commitment", proof(p), i));
+            }
+        }
+        labels.push("vanishing random commitment".to_string());
+        labels.push("y".to_string());
+        for i in 0..self.domain.get_quotient_poly_degree() {
+            labels.push(format!("vanishing h piece {} commitment", i));
+        }
+        labels.push("x".to_string());
+        for p in 0..num_proofs {
+            for (column, rotation) in self.cs.instance_queries.iter() {
+                labels.push(format!(
+                    "{}instance {} eval {}",
+                    proof(p),
+                    column.index(),
+                    at(rotation)
+                ));
+            }
+        }
+        for p in 0..num_proofs {
+            for (column, rotation) in self.cs.advice_queries.iter() {
+                labels.push(format!(
+                    "{}advice {} eval {}",
+                    proof(p),
+                    column.index(),
+                    at(rotation)
+                ));
+            }
+        }
+        for (column, rotation) in self.cs.fixed_queries.iter() {
+            labels.push(format!("fixed {} eval {}", column.index(), at(rotation)));
+        }
+        labels.push("vanishing random eval".to_string());
+        for i in 0..self.permutation.commitments().len() {
+            labels.push(format!("permutation sigma {} eval", i));
+        }
+        for p in 0..num_proofs {
+            for set in 0..permutation_sets {
+                labels.push(format!("{}permutation product {} eval", proof(p), set));
+                labels.push(format!("{}permutation product {} next eval", proof(p), set));
+                if set + 1 < permutation_sets {
+                    labels.push(format!("{}permutation product {} last eval", proof(p), set));
+                }
+            }
+        }
+        for p in 0..num_proofs {
+            for i in 0..self.cs.lookups.len() {
+                #[cfg(not(feature = "mv-lookup"))]
+                {
+                    labels.push(format!("{}lookup {} product eval", proof(p), i));
+                    labels.push(format!("{}lookup {} product next eval", proof(p), i));
+                    labels.push(format!("{}lookup {} permuted input eval", proof(p), i));
+                    labels.push(format!("{}lookup {} permuted input prev eval", proof(p), i));
+                    labels.push(format!("{}lookup {} permuted table eval", proof(p), i));
+                }
+                #[cfg(feature = "mv-lookup")]
+                {
+                    labels.push(format!("{}lookup {} phi eval", proof(p), i));
+                    labels.push(format!("{}lookup {} phi next eval", proof(p), i));
+                    labels.push(format!("{}lookup {} multiplicity eval", proof(p), i));
+                }
+            }
+        }
+        for p in 0..num_proofs {
+            for i in 0..self.cs.shuffles.len() {
+                labels.push(format!("{}shuffle {} product eval", proof(p), i));
+                labels.push(format!("{}shuffle {} product next eval", proof(p), i));
+            }
+        }
+
+        let multiopen: &[&str] = match scheme {
+            CommitmentScheme::KzgGwc => &["gwc v", "gwc u"],
+            CommitmentScheme::KzgShplonk => &[
+                "shplonk y",
+                "shplonk v",
+                "shplonk h1 commitment",
+                "shplonk u",
+                "shplonk h2 commitment",
+            ],
+        };
+        labels.extend(multiopen.iter().map(|label| label.to_string()));
+
+        labels
+    }
+}
@@ -0,0 +1,375 @@
+//! Storing the witness of a proof, for proving on another machine.
+
+use std::io;
+
+use ff::PrimeField;
+
+use super::{
+    prover::{synthesize_witness, Witness},
+    Assigned, Circuit, ConstraintSystem, Error, ProvingKey, WitnessCalculator,
+};
+use crate::{
+    arithmetic::{CurveAffine, FieldExt},
+    poly::{batch_invert_assigned, LagrangeCoeff, Polynomial},
+};
+
+/// The number of cells that share a bit width in [`WitnessCompression::Packed`].
+const BLOCK_SIZE: usize = 64;
+
+/// Marks a block of cells that are stored as full field elements.
+const FULL_WIDTH: u8 = u8::MAX;
+
+/// The encoding of the cells of [`WitnessColumns`] when they are written.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum WitnessCompression {
+    /// Each cell is stored as a full field element.
+    None,
+    /// Each block of 64 cells is stored with the fewest bits that hold all of its
+    /// values as integers below `2^64`, or as full field elements otherwise.
+    ///
+    /// Boolean and small integer cells, which most circuits are made of, take up
+    /// one or a few bits each, and unassigned cells almost nothing.
+    Packed,
+}
+
+/// The advice columns (and optionally configuration columns) of a single proof.
+///
+/// This allows the witness to be generated on one machine with
+/// [`WitnessColumns::generate`], written out with [`WitnessColumns::write`], and
+/// proven on another with
+/// [`create_proof_with_witness`](crate::plonk::create_proof_with_witness), which
+/// it implements [`WitnessCalculator`] for.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WitnessColumns<F: PrimeField> {
+    advice: Vec<Vec<F>>,
+    configuration: Option<Vec<Vec<F>>>,
+}
+
+impl<F: PrimeField> WitnessColumns<F> {
+    /// Creates the witness of a proof from the values of its advice columns, in
+    /// column index order, and optionally of its configuration columns.
+    pub fn new(advice: Vec<Vec<F>>, configuration: Option<Vec<Vec<F>>>) -> Self {
+        WitnessColumns {
+            advice,
+            configuration,
+        }
+    }
+
+    /// Returns the values of the advice columns.
+    pub fn advice(&self) -> &[Vec<F>] {
+        &self.advice
+    }
+
+    /// Returns the values of the configuration columns, if any.
+    pub fn configuration(&self) -> Option<&[Vec<F>]> {
+        self.configuration.as_deref()
+    }
+
+    /// Writes the witness to a buffer, encoding its cells with `compression`.
+    pub fn write<W: io::Write>(
+        &self,
+        writer: &mut W,
+        compression: WitnessCompression,
+    ) -> io::Result<()> {
+        writer.write_all(&[match compression {
+            WitnessCompression::None => 0,
+            WitnessCompression::Packed => 1,
+        }])?;
+        write_columns(writer, &self.advice, compression)?;
+        match &self.configuration {
+            Some(configuration) => {
+                writer.write_all(&[1])?;
+                write_columns(writer, configuration, compression)
+            }
+            None => writer.write_all(&[0]),
+        }
+    }
+
+    /// Reads a witness from a buffer, with the compression it was written with.
+    pub fn read<R: io::Read>(reader: &mut R) -> io::Result<Self> {
+        let compression = match read_u8(reader)? {
+            0 => WitnessCompression::None,
+            1 => WitnessCompression::Packed,
+            _ => return Err(invalid_data("invalid witness compression")),
+        };
+        let advice = read_columns(reader, compression)?;
+        let configuration = match read_u8(reader)? {
+            0 => None,
+            1 => Some(read_columns(reader, compression)?),
+            _ => return Err(invalid_data("invalid witness configuration flag")),
+        };
+
+        Ok(WitnessColumns {
+            advice,
+            configuration,
+        })
+    }
+}
+
+impl<F: FieldExt> WitnessColumns<F> {
+    /// Synthesizes `circuit` to obtain the witness of a single proof, given its
+    /// instance columns, as [`create_proof`](crate::plonk::create_proof) does.
+    ///
+    /// Each column holds the values of the usable rows of the circuit.
+    pub fn generate<C, ConcreteCircuit>(
+        pk: &ProvingKey<C>,
+        circuit: &ConcreteCircuit,
+        instances: &[&[F]],
+    ) -> Result<Self, Error>
+    where
+        C: CurveAffine<ScalarExt = F>,
+        ConcreteCircuit: Circuit<F>,
+    {
+        if instances.len() != pk.vk.cs.num_instance_columns {
+            return Err(Error::InvalidInstances);
+        }
+
+        let mut meta = ConstraintSystem::default();
+        let config = ConcreteCircuit::configure(&mut meta);
+
+        let usable_rows = pk.vk.cs.usable_rows(pk.vk.domain.k()).end;
+        let Witness {
+            advice,
+            configuration,
+        } = synthesize_witness(pk, circuit, config, instances, usable_rows, false)?;
+
+        let into_columns = |columns: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>| {
+            batch_invert_assigned(&columns)
+                .into_iter()
+                .map(|column| column[..][..usable_rows].to_vec())
+                .collect::<Vec<_>>()
+        };
+
+        Ok(WitnessColumns {
+            advice: into_columns(advice),
+            configuration: configuration.map(into_columns),
+        })
+    }
+}
+
+impl<F: PrimeField> WitnessCalculator<F> for WitnessColumns<F> {
+    fn calculate_witness(
+        &self,
+        _instances: &[&[F]],
+        _usable_rows: usize,
+    ) -> Result<Vec<Vec<Assigned<F>>>, Error> {
+        Ok(into_assigned(&self.advice))
+    }
+
+    fn calculate_configuration(
+        &self,
+        _instances: &[&[F]],
+        _usable_rows: usize,
+    ) -> Result<Option<Vec<Vec<Assigned<F>>>>, Error> {
+        Ok(self.configuration.as_deref().map(into_assigned))
+    }
+}
+
+fn into_assigned<F: PrimeField>(columns: &[Vec<F>]) -> Vec<Vec<Assigned<F>>> {
+    columns
+        .iter()
+        .map(|column| {
+            column
+                .iter()
+                .map(|value| Assigned::Trivial(*value))
+                .collect()
+        })
+        .collect()
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+fn read_u8<R: io::Read>(reader: &mut R) -> io::Result<u8> {
+    let mut byte = [0u8; 1];
+    reader.read_exact(&mut byte)?;
+    Ok(byte[0])
+}
+
+fn read_u32<R: io::Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn write_field<W: io::Write, F: PrimeField>(writer: &mut W, value: &F) -> io::Result<()> {
+    writer.write_all(value.to_repr().as_ref())
+}
+
+fn read_field<R: io::Read, F: PrimeField>(reader: &mut R) -> io::Result<F> {
+    let mut repr = F::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+    Option::from(F::from_repr(repr))
+        .ok_or_else(|| invalid_data("invalid field element encoding in witness"))
+}
+
+/// Returns `value` as an integer, if it is below `2^64`.
+fn as_small<F: PrimeField>(value: &F) -> Option<u64> {
+    // The byte order of the representation isn't specified, so try both ends of
+    // it, and check the guess.
+    let repr = value.to_repr();
+    let bytes = repr.as_ref();
+    let mut low = [0u8; 8];
+    low.copy_from_slice(&bytes[..8]);
+    let mut high = [0u8; 8];
+    high.copy_from_slice(&bytes[bytes.len() - 8..]);
+    [u64::from_le_bytes(low), u64::from_be_bytes(high)]
+        .iter()
+        .copied()
+        .find(|small| F::from(*small) == *value)
+}
+
+fn write_columns<W: io::Write, F: PrimeField>(
+    writer: &mut W,
+    columns: &[Vec<F>],
+    compression: WitnessCompression,
+) -> io::Result<()> {
+    writer.write_all(&(columns.len() as u32).to_le_bytes())?;
+    for column in columns {
+        writer.write_all(&(column.len() as u32).to_le_bytes())?;
+        match compression {
+            WitnessCompression::None => {
+                for value in column {
+                    write_field(writer, value)?;
+                }
+            }
+            WitnessCompression::Packed => {
+                for block in column.chunks(BLOCK_SIZE) {
+                    write_packed_block(writer, block)?;
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+fn read_columns<R: io::Read, F: PrimeField>(
+    reader: &mut R,
+    compression: WitnessCompression,
+) -> io::Result<Vec<Vec<F>>> {
+    let num_columns = read_u32(reader)?;
+    (0..num_columns)
+        .map(|_| {
+            let len = read_u32(reader)? as usize;
+            match compression {
+                WitnessCompression::None => (0..len).map(|_| read_field(reader)).collect(),
+                WitnessCompression::Packed => {
+                    let mut column = Vec::with_capacity(len);
+                    while column.len() < len {
+                        let block_len = std::cmp::min(BLOCK_SIZE, len - column.len());
+                        read_packed_block(reader, block_len, &mut column)?;
+                    }
+                    Ok(column)
+                }
+            }
+        })
+        .collect()
+}
+
+/// Writes a block of cells as a bit width followed by the cells, either packed
+/// with that many bits each, or as full field elements.
+fn write_packed_block<W: io::Write, F: PrimeField>(writer: &mut W, block: &[F]) -> io::Result<()> {
+    let small: Option<Vec<u64>> = block.iter().map(as_small).collect();
+    let small = match small {
+        Some(small) => small,
+        None => {
+            writer.write_all(&[FULL_WIDTH])?;
+            for value in block {
+                write_field(writer, value)?;
+            }
+            return Ok(());
+        }
+    };
+
+    let width = small
+        .iter()
+        .map(|value| 64 - value.leading_zeros())
+        .max()
+        .unwrap_or(0);
+    writer.write_all(&[width as u8])?;
+
+    let mut bytes = vec![0u8; (block.len() * width as usize + 7) / 8];
+    for (i, value) in small.iter().enumerate() {
+        for bit in 0..width as usize {
+            if (value >> bit) & 1 == 1 {
+                let position = i * width as usize + bit;
+                bytes[position / 8] |= 1 << (position % 8);
+            }
+        }
+    }
+    writer.write_all(&bytes)
+}
+
+fn read_packed_block<R: io::Read, F: PrimeField>(
+    reader: &mut R,
+    block_len: usize,
+    column: &mut Vec<F>,
+) -> io::Result<()> {
+    let width = read_u8(reader)?;
+    if width == FULL_WIDTH {
+        for _ in 0..block_len {
+            column.push(read_field(reader)?);
+        }
+        return Ok(());
+    }
+    if width > 64 {
+        return Err(invalid_data("invalid bit width in witness"));
+    }
+
+    let width = width as usize;
+    let mut bytes = vec![0u8; (block_len * width + 7) / 8];
+    reader.read_exact(&mut bytes)?;
+    for i in 0..block_len {
+        let mut value = 0u64;
+        for bit in 0..width {
+            let position = i * width + bit;
+            if (bytes[position / 8] >> (position % 8)) & 1 == 1 {
+                value |= 1 << bit;
+            }
+        }
+        column.push(F::from(value));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use pairing::bn256::Fr as Fp;
+    use rand_core::OsRng;
+
+    use super::{WitnessColumns, WitnessCompression};
+
+    #[test]
+    fn witness_round_trip() {
+        let bits: Vec<Fp> = (0..100).map(|i| Fp::from((i % 3 == 0) as u64)).collect();
+        let bytes: Vec<Fp> = (0..100).map(|i| Fp::from(i * 7 % 256)).collect();
+        let mixed: Vec<Fp> = (0..100)
+            .map(|i| {
+                if i == 70 {
+                    -Fp::one()
+                } else {
+                    Fp::from(u64::MAX - i)
+                }
+            })
+            .collect();
+        let random: Vec<Fp> = (0..10).map(|_| Fp::random(OsRng)).collect();
+        let witness = WitnessColumns::new(
+            vec![bits, bytes, mixed, vec![Fp::zero(); 1000], vec![]],
+            Some(vec![random]),
+        );
+
+        let mut full = vec![];
+        witness.write(&mut full, WitnessCompression::None).unwrap();
+        let mut packed = vec![];
+        witness
+            .write(&mut packed, WitnessCompression::Packed)
+            .unwrap();
+        assert!(packed.len() * 3 < full.len());
+
+        for buffer in [full, packed] {
+            assert_eq!(WitnessColumns::read(&mut &buffer[..]).unwrap(), witness);
+        }
+    }
+}
@@ -112,6 +112,24 @@ impl<F, B> DerefMut for Polynomial<F, B> {
     }
 }
 
+#[cfg(feature = "zeroize")]
+impl<F, B> zeroize::Zeroize for Polynomial<F, B> {
+    /// Drops the values and wipes the memory that held them, leaving an empty
+    /// polynomial.
+    fn zeroize(&mut self) {
+        crate::helpers::wipe(&mut self.values);
+    }
+}
+
+/// Polynomials hold the witness and blinding factors while proving, so they
+/// are wiped when dropped.
+#[cfg(feature = "zeroize")]
+impl<F, B> Drop for Polynomial<F, B> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
 impl<F, B> Polynomial<F, B> {
     /// Iterate over the values, which are either in coefficient or evaluation
     /// form depending on the basis `B`.
@@ -133,7 +151,7 @@ impl<F, B> Polynomial<F, B> {
 }
 
 pub(crate) fn batch_invert_assigned<F: FieldExt>(
-    assigned: Vec<Polynomial<Assigned<F>, LagrangeCoeff>>,
+    assigned: &[Polynomial<Assigned<F>, LagrangeCoeff>],
 ) -> Vec<Polynomial<F, LagrangeCoeff>> {
     let mut assigned_denominators: Vec<_> = assigned
         .iter()
@@ -252,7 +270,7 @@ impl<'a, F: Field, B: Basis> Mul<F> for Polynomial<F, B> {
 /// Describes the relative rotation of a vector. Negative numbers represent
 /// reverse (leftmost) rotations and positive numbers represent forward (rightmost)
 /// rotations. Zero represents no rotation.
-#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord)]
+#[derive(Copy, Clone, Debug, PartialEq, PartialOrd, Eq, Ord, Hash)]
 pub struct Rotation(pub i32);
 
 impl Rotation {
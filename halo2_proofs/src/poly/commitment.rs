@@ -3,12 +3,15 @@
 //!
 //! [halo]: https://eprint.iacr.org/2019/1021
 
-use super::{Coeff, LagrangeCoeff, Polynomial, MSM};
+use super::{max_k, Coeff, EvaluationDomain, LagrangeCoeff, Polynomial, MSM};
 use crate::arithmetic::{
-    best_fft, best_multiexp, parallelize, CurveAffine, CurveExt, Engine, FieldExt, Group,
+    best_fft, best_multiexp, best_multiexp_with_windows, constant_time_multiexp, parallelize,
+    CurveAffine, CurveExt, Engine, FieldExt, Group, MillerLoopResult, MultiMillerLoop,
+    MultiexpBuffer, MultiexpWindows,
 };
 use crate::helpers::CurveRead;
 
+use blake2b_simd::Params as Blake2bParams;
 use ff::{Field, PrimeField};
 use group::{prime::PrimeCurveAffine, Curve, Group as _, GroupEncoding};
 use rand_core::OsRng;
@@ -16,6 +19,103 @@ use std::marker::PhantomData;
 use std::ops::{Add, AddAssign, Mul, MulAssign};
 
 use std::io;
+use std::{fmt, str::FromStr};
+
+mod ptau;
+
+/// The polynomial commitment scheme, together with its multiopening argument, that
+/// proofs of a circuit are created with.
+///
+/// Both KZG multiopening arguments are compiled into every build. [`Params`] and
+/// [`ParamsVerifier`] carry the scheme that proofs are created and verified
+/// with, which is [`CommitmentScheme::default`] unless it is set with
+/// [`Params::with_scheme`] or [`ParamsVerifier::with_scheme`], so an
+/// application can read it from configuration:
+///
+/// ```
+/// use halo2_proofs::pairing::bn256::{Bn256, G1Affine};
+/// use halo2_proofs::poly::commitment::{CommitmentScheme, Params};
+///
+/// let scheme: CommitmentScheme = "kzg-gwc".parse().unwrap();
+/// assert_eq!(scheme.to_string(), "kzg-gwc");
+/// let params = Params::<G1Affine>::unsafe_setup::<Bn256>(4).with_scheme(scheme);
+/// assert_eq!(params.scheme(), CommitmentScheme::KzgGwc);
+/// assert!("ipa".parse::<CommitmentScheme>().is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CommitmentScheme {
+    /// KZG commitments opened with the GWC multiopening argument.
+    KzgGwc,
+    /// KZG commitments opened with the SHPLONK multiopening argument.
+    KzgShplonk,
+}
+
+impl Default for CommitmentScheme {
+    /// Returns SHPLONK with the `shplonk` feature, and GWC without it.
+    fn default() -> Self {
+        if cfg!(feature = "shplonk") {
+            CommitmentScheme::KzgShplonk
+        } else {
+            CommitmentScheme::KzgGwc
+        }
+    }
+}
+
+impl CommitmentScheme {
+    /// Returns the byte identifying this scheme in a
+    /// [`ProofEnvelope`](crate::transcript::ProofEnvelope).
+    pub(crate) fn id(self) -> u8 {
+        match self {
+            CommitmentScheme::KzgGwc => 1,
+            CommitmentScheme::KzgShplonk => 2,
+        }
+    }
+
+    /// Returns the scheme identified by `id` in a
+    /// [`ProofEnvelope`](crate::transcript::ProofEnvelope).
+    pub(crate) fn from_id(id: u8) -> io::Result<Self> {
+        [CommitmentScheme::KzgGwc, CommitmentScheme::KzgShplonk]
+            .iter()
+            .copied()
+            .find(|scheme| scheme.id() == id)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("unknown commitment scheme id {}", id),
+                )
+            })
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            CommitmentScheme::KzgGwc => "kzg-gwc",
+            CommitmentScheme::KzgShplonk => "kzg-shplonk",
+        }
+    }
+}
+
+impl fmt::Display for CommitmentScheme {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.name())
+    }
+}
+
+impl FromStr for CommitmentScheme {
+    type Err = io::Error;
+
+    fn from_str(s: &str) -> io::Result<Self> {
+        [CommitmentScheme::KzgGwc, CommitmentScheme::KzgShplonk]
+            .iter()
+            .copied()
+            .find(|scheme| scheme.name() == s)
+            .ok_or_else(|| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    format!("unknown commitment scheme {:?}", s),
+                )
+            })
+    }
+}
 
 /// These are the prover parameters for the polynomial commitment scheme.
 #[derive(Debug)]
@@ -25,6 +125,8 @@ pub struct Params<C: CurveAffine> {
     pub(crate) g: Vec<C>,
     pub(crate) g_lagrange: Vec<C>,
     pub(crate) additional_data: Vec<u8>,
+    pub(crate) scheme: CommitmentScheme,
+    pub(crate) multiexp_windows: MultiexpWindows,
 }
 
 /// These are the verifier parameters for the polynomial commitment scheme.
@@ -36,6 +138,7 @@ pub struct ParamsVerifier<E: Engine> {
     pub(crate) g2: E::G2Affine,
     pub(crate) s_g2: E::G2Affine,
     pub(crate) g_lagrange: Vec<E::G1Affine>,
+    pub(crate) scheme: CommitmentScheme,
 }
 
 #[cfg(test)]
@@ -54,14 +157,34 @@ impl<C: CurveAffine> Params<C> {
     /// MUST NOT be used in production
     pub fn unsafe_setup<E: Engine>(k: u32) -> Params<E::G1Affine> {
         // TODO: Make this function only available in test mod
+        Self::unsafe_setup_with_secret::<E>(k, E::Scalar::random(OsRng))
+    }
+
+    /// Initializes parameters for the curve with a toxic point derived from
+    /// `seed`, so that the same seed gives the same parameters on every machine.
+    ///
+    /// This is for test suites and benchmarks that compare proofs across runs,
+    /// which also need the prover's `rng` to be seeded. Anyone who knows the seed
+    /// can forge proofs, so this MUST NOT be used in production.
+    pub fn unsafe_setup_from_seed<E: Engine>(k: u32, seed: &[u8]) -> Params<E::G1Affine> {
+        let mut hasher = Blake2bParams::new()
+            .hash_length(64)
+            .personal(b"Halo2-Setup-Seed")
+            .to_state();
+        hasher.update(seed);
+        let s = E::Scalar::from_bytes_wide(hasher.finalize().as_array());
+
+        Self::unsafe_setup_with_secret::<E>(k, s)
+    }
+
+    fn unsafe_setup_with_secret<E: Engine>(k: u32, s: E::Scalar) -> Params<E::G1Affine> {
         // Largest root of unity exponent of the Engine is `2^E::Scalar::S`, so we can
         // only support FFTs of polynomials below degree `2^E::Scalar::S`.
-        assert!(k <= E::Scalar::S);
+        assert!(k <= max_k::<E::Scalar>());
         let n: u64 = 1 << k;
 
         // Calculate g = [G1, [s] G1, [s^2] G1, ..., [s^(n-1)] G1] in parallel.
         let g1 = <E::G1Affine as PrimeCurveAffine>::generator();
-        let s = E::Scalar::random(OsRng);
 
         let mut g_projective = vec![E::G1::group_zero(); n as usize];
         parallelize(&mut g_projective, |g, start| {
@@ -119,6 +242,8 @@ impl<C: CurveAffine> Params<C> {
             g,
             g_lagrange,
             additional_data,
+            scheme: CommitmentScheme::default(),
+            multiexp_windows: MultiexpWindows::default(),
         }
     }
 
@@ -131,7 +256,12 @@ impl<C: CurveAffine> Params<C> {
         let bases = &self.g;
         let size = scalars.len();
         assert!(bases.len() >= size);
-        best_multiexp(&scalars, &bases[0..size])
+        commit_multiexp(
+            &scalars,
+            &bases[0..size],
+            &self.multiexp_windows,
+            &mut MultiexpBuffer::new(),
+        )
     }
 
     /// This commits to a polynomial using its evaluations over the $2^k$ size
@@ -143,7 +273,41 @@ impl<C: CurveAffine> Params<C> {
         let bases = &self.g_lagrange;
         let size = scalars.len();
         assert!(bases.len() >= size);
-        best_multiexp(&scalars, &bases[0..size])
+        commit_multiexp(
+            &scalars,
+            &bases[0..size],
+            &self.multiexp_windows,
+            &mut MultiexpBuffer::new(),
+        )
+    }
+
+    /// Like [`Params::commit`], but reuses the scratch space in `buffer`
+    /// across calls.
+    pub fn commit_with_buffer(
+        &self,
+        poly: &Polynomial<C::Scalar, Coeff>,
+        buffer: &mut MultiexpBuffer<C>,
+    ) -> C::Curve {
+        let size = poly.len();
+        assert!(self.g.len() >= size);
+        commit_multiexp(&poly[..], &self.g[0..size], &self.multiexp_windows, buffer)
+    }
+
+    /// Like [`Params::commit_lagrange`], but reuses the scratch space in
+    /// `buffer` across calls.
+    pub fn commit_lagrange_with_buffer(
+        &self,
+        poly: &Polynomial<C::Scalar, LagrangeCoeff>,
+        buffer: &mut MultiexpBuffer<C>,
+    ) -> C::Curve {
+        let size = poly.len();
+        assert!(self.g_lagrange.len() >= size);
+        commit_multiexp(
+            &poly[..],
+            &self.g_lagrange[0..size],
+            &self.multiexp_windows,
+            buffer,
+        )
     }
 
     /// Generates an empty multiscalar multiplication struct using the
@@ -157,6 +321,31 @@ impl<C: CurveAffine> Params<C> {
         self.g.clone()
     }
 
+    /// Returns the commitment scheme that proofs are created with.
+    pub fn scheme(&self) -> CommitmentScheme {
+        self.scheme
+    }
+
+    /// Creates proofs with `scheme` instead. It isn't written with the params,
+    /// and carries over to [`Params::verifier`].
+    pub fn with_scheme(mut self, scheme: CommitmentScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
+    /// Returns the bucket window widths that commitments are computed with.
+    pub fn multiexp_windows(&self) -> &MultiexpWindows {
+        &self.multiexp_windows
+    }
+
+    /// Computes commitments with the bucket window widths in `windows`, such
+    /// as those found by [`MultiexpWindows::autotune`], instead. They aren't
+    /// written with the params.
+    pub fn with_multiexp_windows(mut self, windows: MultiexpWindows) -> Self {
+        self.multiexp_windows = windows;
+        self
+    }
+
     /// Writes params to a buffer.
     pub fn write<W: io::Write>(&self, writer: &mut W) -> io::Result<()> {
         writer.write_all(&self.k.to_le_bytes())?;
@@ -177,6 +366,7 @@ impl<C: CurveAffine> Params<C> {
         let mut k = [0u8; 4];
         reader.read_exact(&mut k[..])?;
         let k = u32::from_le_bytes(k);
+        check_k::<C::Scalar>(k)?;
         let n = 1 << k;
 
         let load_points_from_file_parallelly = |reader: &mut R| -> io::Result<Vec<C>> {
@@ -210,6 +400,8 @@ impl<C: CurveAffine> Params<C> {
             g,
             g_lagrange,
             additional_data,
+            scheme: CommitmentScheme::default(),
+            multiexp_windows: MultiexpWindows::default(),
         })
     }
 
@@ -233,8 +425,68 @@ impl<C: CurveAffine> Params<C> {
             g_lagrange,
             g2,
             s_g2,
+            scheme: self.scheme,
         })
     }
+
+    /// Checks that these parameters are a well-formed structured reference string,
+    /// as should be done before generating keys with parameters from a third party.
+    ///
+    /// This checks that `g` starts at the generator and holds successive powers of
+    /// the secret `s`, with $e([s^i] G_1, G_2) = e([s^{i - 1}] G_1, [s] G_2)$ for
+    /// every `i`, that the Lagrange bases commit to the same polynomials as `g`,
+    /// and that $[s] G_2$ is consistent with both. All the powers are checked at
+    /// once against a random linear combination, so a corrupted or tampered point
+    /// is detected except with negligible probability.
+    ///
+    /// This can't tell whether anyone knows `s`: parameters from
+    /// [`Params::unsafe_setup`] pass.
+    pub fn verify<E: MultiMillerLoop<G1Affine = C>>(&self) -> io::Result<()> {
+        let invalid = |message: &str| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                format!("invalid parameters: {}", message),
+            )
+        };
+
+        let n = self.n as usize;
+        if self.g.len() != n || self.g_lagrange.len() != n {
+            return Err(invalid("wrong number of points"));
+        }
+        if self.g[0] != <C as PrimeCurveAffine>::generator() {
+            return Err(invalid("the first point is not the generator"));
+        }
+        let s_g2 = E::G2Affine::read(&mut self.additional_data.as_slice())?;
+        if bool::from(s_g2.is_identity()) {
+            return Err(invalid("[s] G2 is the identity"));
+        }
+
+        // Check e(sum r_i [s^i] G1, G2) = e(sum r_i [s^{i - 1}] G1, [s] G2).
+        let scalars: Vec<_> = (1..n).map(|_| C::Scalar::random(OsRng)).collect();
+        let higher = best_multiexp(&scalars, &self.g[1..]).to_affine();
+        let lower = best_multiexp(&scalars, &self.g[..n - 1]).to_affine();
+        let n_g2 = E::G2Prepared::from(-<E::G2Affine as PrimeCurveAffine>::generator());
+        let s_g2 = E::G2Prepared::from(s_g2);
+        let powers_match = E::multi_miller_loop(&[(&higher, &n_g2), (&lower, &s_g2)])
+            .final_exponentiation()
+            .is_identity();
+        if !bool::from(powers_match) {
+            return Err(invalid("the points are not successive powers of s"));
+        }
+
+        // Check that a random polynomial has the same commitment in both bases.
+        let domain = EvaluationDomain::new(1, self.k);
+        let mut values = domain.empty_lagrange();
+        for value in values.iter_mut() {
+            *value = C::Scalar::random(OsRng);
+        }
+        let coeffs = domain.lagrange_to_coeff(values.clone());
+        if best_multiexp(&coeffs, &self.g) != best_multiexp(&values, &self.g_lagrange) {
+            return Err(invalid("the Lagrange bases don't match the powers of s"));
+        }
+
+        Ok(())
+    }
 }
 
 /// Wrapper type around a blinding factor.
@@ -293,6 +545,17 @@ impl<E: Engine> ParamsVerifier<E> {
         self.g_lagrange.len()
     }
 
+    /// Returns the commitment scheme that proofs are verified with.
+    pub fn scheme(&self) -> CommitmentScheme {
+        self.scheme
+    }
+
+    /// Verifies proofs with `scheme` instead. It isn't written with the params.
+    pub fn with_scheme(mut self, scheme: CommitmentScheme) -> Self {
+        self.scheme = scheme;
+        self
+    }
+
     /// Generates an empty multiscalar multiplication struct using the
     /// appropriate params.
     pub fn empty_msm(&self) -> MSM<E::G1Affine> {
@@ -328,6 +591,7 @@ impl<E: Engine> ParamsVerifier<E> {
         let mut k = [0u8; 4];
         reader.read_exact(&mut k[..])?;
         let k = u32::from_le_bytes(k);
+        check_k::<E::Scalar>(k)?;
 
         let mut public_inputs_size = [0u8; 4];
         reader.read_exact(&mut public_inputs_size[..])?;
@@ -349,13 +613,78 @@ impl<E: Engine> ParamsVerifier<E> {
             g2,
             s_g2,
             g_lagrange,
+            scheme: CommitmentScheme::default(),
         })
     }
 }
 
+/// The multi-exponentiation behind the commitments of [`Params`], which are
+/// taken to polynomials that can depend on the witness.
+#[cfg(not(feature = "constant-time"))]
+fn commit_multiexp<C: CurveAffine>(
+    scalars: &[C::Scalar],
+    bases: &[C],
+    windows: &MultiexpWindows,
+    buffer: &mut MultiexpBuffer<C>,
+) -> C::Curve {
+    best_multiexp_with_windows(scalars, bases, windows, buffer)
+}
+
+/// The multi-exponentiation behind the commitments of [`Params`], which are
+/// taken to polynomials that can depend on the witness.
+#[cfg(feature = "constant-time")]
+fn commit_multiexp<C: CurveAffine>(
+    scalars: &[C::Scalar],
+    bases: &[C],
+    _: &MultiexpWindows,
+    _: &mut MultiexpBuffer<C>,
+) -> C::Curve {
+    constant_time_multiexp(scalars, bases)
+}
+
+/// Returns an error if parameters for `k` can't be used on this platform.
+fn check_k<F: FieldExt>(k: u32) -> io::Result<()> {
+    if k > max_k::<F>() {
+        return Err(io::Error::new(
+            io::ErrorKind::Other,
+            format!(
+                "parameters for k = {} are larger than the largest supported k of {}",
+                k,
+                max_k::<F>()
+            ),
+        ));
+    }
+    Ok(())
+}
+
 #[cfg(test)]
 use pairing::bn256::{Bn256, Fr, G1Affine};
 
+#[test]
+fn test_commitment_scheme() {
+    for scheme in [CommitmentScheme::KzgGwc, CommitmentScheme::KzgShplonk].iter() {
+        assert_eq!(
+            scheme.to_string().parse::<CommitmentScheme>().unwrap(),
+            *scheme
+        );
+        assert_eq!(CommitmentScheme::from_id(scheme.id()).unwrap(), *scheme);
+
+        // The scheme carries over to the verifier params, but isn't written.
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(4).with_scheme(*scheme);
+        let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+        assert_eq!(params_verifier.scheme(), *scheme);
+        let mut data = vec![];
+        params_verifier.write(&mut data).unwrap();
+        assert_eq!(
+            ParamsVerifier::<Bn256>::read(&data[..]).unwrap().scheme(),
+            CommitmentScheme::default()
+        );
+    }
+    assert!("kzg".parse::<CommitmentScheme>().is_err());
+    assert!("ipa".parse::<CommitmentScheme>().is_err());
+    assert!(CommitmentScheme::from_id(0).is_err());
+}
+
 #[test]
 fn test_parameter_serialization() {
     const K: u32 = 4;
@@ -412,4 +741,58 @@ fn test_commit_lagrange() {
 
     let b = domain.lagrange_to_coeff(a.clone());
     assert_eq!(params.commit(&b), params.commit_lagrange(&a));
+
+    // Tuned window widths don't change the commitments.
+    let commitment = params.commit(&b);
+    let params = params.with_multiexp_windows(MultiexpWindows::autotune::<G1Affine>(K));
+    assert_eq!(params.multiexp_windows().as_slice().len(), K as usize + 1);
+    assert_eq!(params.commit(&b), commitment);
+    assert_eq!(params.commit_lagrange(&a), commitment);
+}
+
+#[test]
+fn test_unsafe_setup_from_seed() {
+    const K: u32 = 4;
+
+    let params0 = Params::<G1Affine>::unsafe_setup_from_seed::<Bn256>(K, b"seed");
+    let params1 = Params::<G1Affine>::unsafe_setup_from_seed::<Bn256>(K, b"seed");
+    let params2 = Params::<G1Affine>::unsafe_setup_from_seed::<Bn256>(K, b"other seed");
+    assert_eq!(params0.g, params1.g);
+    assert_eq!(params0.g_lagrange, params1.g_lagrange);
+    assert_eq!(params0.additional_data, params1.additional_data);
+    assert_ne!(params0.g[1], params2.g[1]);
+    assert!(params0.verify::<Bn256>().is_ok());
+}
+
+#[test]
+fn test_verify_params() {
+    const K: u32 = 4;
+
+    let params = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    assert!(params.verify::<Bn256>().is_ok());
+
+    // A power of s replaced by another.
+    let mut tampered = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    tampered.g[3] = tampered.g[2];
+    assert!(tampered.verify::<Bn256>().is_err());
+
+    // Lagrange bases from a different secret.
+    let other = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let mut tampered = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    tampered.g_lagrange = other.g_lagrange.clone();
+    assert!(tampered.verify::<Bn256>().is_err());
+
+    // [s] G2 from a different secret.
+    let mut tampered = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    tampered.additional_data = other.additional_data;
+    assert!(tampered.verify::<Bn256>().is_err());
+}
+
+#[test]
+fn test_read_rejects_large_k() {
+    let mut data = 64u32.to_le_bytes().to_vec();
+    data.extend_from_slice(&[0u8; 4]);
+
+    assert!(Params::<G1Affine>::read(&data[..]).is_err());
+    assert!(ParamsVerifier::<Bn256>::read(&data[..]).is_err());
 }
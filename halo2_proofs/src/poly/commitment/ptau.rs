@@ -0,0 +1,601 @@
+//! Reading parameters from the `.ptau` files of snarkjs, and from the raw
+//! `response` files of powers of tau ceremonies.
+//!
+//! A `.ptau` file holds the output of a powers of tau ceremony over BN254,
+//! such as the Perpetual Powers of Tau files published by Hermez. It starts
+//! with the magic bytes `ptau`, a version and a number of sections; each
+//! section has a type, a length and a body. The sections read here are:
+//!
+//! - the header (type 1): the size of base field elements in bytes, the
+//!   base field modulus, and the number of powers `2^power`;
+//! - the powers of tau in G1 (type 2): $[\tau^i] G_1$ for
+//!   $0 \leq i < 2^{power + 1} - 1$;
+//! - the powers of tau in G2 (type 3): $[\tau^i] G_2$ for
+//!   $0 \leq i < 2^{power}$.
+//!
+//! Points are uncompressed, with each coordinate in Montgomery form and
+//! little-endian.
+//!
+//! A `response` file has no header: it starts with the 64-byte hash of the
+//! challenge it responds to, followed by the same powers in G1 and then in G2,
+//! and by other points that aren't read here. Points are compressed to their
+//! x-coordinate, in Montgomery form and big-endian, with $c_1$ before $c_0$ in
+//! G2. The two most significant bits of a point are flags: the first is set
+//! when $y$ is the larger of $\pm y$, and the second for the point at
+//! infinity.
+
+use std::cmp::Ordering;
+use std::io::{self, Read};
+
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve, GroupEncoding};
+use pairing::bn256::{Fq, Fq2, Fr, G1Affine, G2Affine, G1};
+
+use super::{CommitmentScheme, Params};
+use crate::arithmetic::{best_fft, parallelize, CurveAffine, FieldExt, Group, MultiexpWindows};
+use crate::poly::max_k;
+
+const MAGIC: &[u8; 4] = b"ptau";
+
+const HEADER_SECTION: u32 = 1;
+const TAU_G1_SECTION: u32 = 2;
+const TAU_G2_SECTION: u32 = 3;
+
+/// The size in bytes of an element of the base field.
+const FIELD_SIZE: usize = 32;
+
+/// The size in bytes of the hash that starts a response file.
+const RESPONSE_HASH_SIZE: u64 = 64;
+
+/// The flag of a compressed point whose y-coordinate is the larger one.
+const GREATEST_FLAG: u8 = 1 << 7;
+
+/// The flag of a compressed point at infinity.
+const INFINITY_FLAG: u8 = 1 << 6;
+
+fn invalid(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+fn read_u32<R: Read>(reader: &mut R) -> io::Result<u32> {
+    let mut bytes = [0u8; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut bytes = [0u8; 8];
+    reader.read_exact(&mut bytes)?;
+    Ok(u64::from_le_bytes(bytes))
+}
+
+/// Skips the `len` next bytes of `reader`.
+fn skip<R: Read>(reader: &mut R, len: u64) -> io::Result<()> {
+    let skipped = io::copy(&mut reader.take(len), &mut io::sink())?;
+    if skipped != len {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    }
+    Ok(())
+}
+
+/// Returns $2^{-256}$, which maps the Montgomery form of an element to the
+/// element.
+fn montgomery_inv() -> Fq {
+    Fq::from(2).pow_vartime(&[256]).invert().unwrap()
+}
+
+/// Returns the base field element whose Montgomery form is `repr`.
+fn from_montgomery(repr: <Fq as PrimeField>::Repr) -> io::Result<Fq> {
+    let montgomery = Option::<Fq>::from(Fq::from_repr(repr))
+        .ok_or_else(|| invalid("field element is not reduced".to_string()))?;
+    Ok(montgomery * montgomery_inv())
+}
+
+/// Reads a base field element in Montgomery form.
+fn read_fq<R: Read>(reader: &mut R) -> io::Result<Fq> {
+    let mut repr = <Fq as PrimeField>::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+    from_montgomery(repr)
+}
+
+/// Reads a base field element in Montgomery form and big-endian, and returns
+/// it with the flags held in its two most significant bits.
+fn read_compressed_fq<R: Read>(reader: &mut R) -> io::Result<(Fq, u8)> {
+    let mut repr = <Fq as PrimeField>::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+    let flags = repr.as_ref()[0] & (GREATEST_FLAG | INFINITY_FLAG);
+    repr.as_mut()[0] &= !flags;
+    repr.as_mut().reverse();
+    Ok((from_montgomery(repr)?, flags))
+}
+
+/// Compares the canonical encodings of `a` and `b` as integers.
+fn cmp_fq(a: &Fq, b: &Fq) -> Ordering {
+    let (a, b) = (a.to_repr(), b.to_repr());
+    a.as_ref().iter().rev().cmp(b.as_ref().iter().rev())
+}
+
+/// Compares `a` and `b` by their $c_1$, and then by their $c_0$.
+fn cmp_fq2(a: &Fq2, b: &Fq2) -> Ordering {
+    cmp_fq(&a.c1, &b.c1).then_with(|| cmp_fq(&a.c0, &b.c0))
+}
+
+/// Returns whichever of `y` and `-y` the `GREATEST_FLAG` of `flags` selects.
+fn select_y<F: Field>(y: F, flags: u8, cmp: impl Fn(&F, &F) -> Ordering) -> F {
+    let greatest = cmp(&y, &-y) == Ordering::Greater;
+    if greatest == (flags & GREATEST_FLAG != 0) {
+        y
+    } else {
+        -y
+    }
+}
+
+/// Reads a point of G1 other than the identity.
+fn read_g1<R: Read>(reader: &mut R) -> io::Result<G1Affine> {
+    let x = read_fq(reader)?;
+    let y = read_fq(reader)?;
+    Option::<G1Affine>::from(G1Affine::from_xy(x, y))
+        .filter(|point| !bool::from(point.is_identity()))
+        .ok_or_else(|| invalid("invalid point of G1".to_string()))
+}
+
+/// Returns the point of G2 with the given coordinates, if it is one other than
+/// the identity.
+///
+/// Unlike G1, the curve of G2 has points outside of G2, so a point is only
+/// accepted if multiplying it by the order $r$ of G2 gives the identity, that
+/// is if $[r - 1] P = -P$.
+fn g2_from_xy(x: Fq2, y: Fq2) -> io::Result<G2Affine> {
+    Option::<G2Affine>::from(G2Affine::from_xy(x, y))
+        .filter(|point| !bool::from(point.is_identity()))
+        .filter(|point| *point * -Fr::one() == -point.to_curve())
+        .ok_or_else(|| invalid("invalid point of G2".to_string()))
+}
+
+/// Reads a point of G2 other than the identity.
+fn read_g2<R: Read>(reader: &mut R) -> io::Result<G2Affine> {
+    let x = Fq2 {
+        c0: read_fq(reader)?,
+        c1: read_fq(reader)?,
+    };
+    let y = Fq2 {
+        c0: read_fq(reader)?,
+        c1: read_fq(reader)?,
+    };
+    g2_from_xy(x, y)
+}
+
+/// Reads a compressed point of G1 other than the identity.
+fn read_compressed_g1<R: Read>(reader: &mut R) -> io::Result<G1Affine> {
+    let (x, flags) = read_compressed_fq(reader)?;
+    let y = Option::<Fq>::from((x.square() * x + G1Affine::b()).sqrt())
+        .filter(|_| flags & INFINITY_FLAG == 0)
+        .ok_or_else(|| invalid("invalid point of G1".to_string()))?;
+    Option::<G1Affine>::from(G1Affine::from_xy(x, select_y(y, flags, cmp_fq)))
+        .ok_or_else(|| invalid("invalid point of G1".to_string()))
+}
+
+/// Reads a compressed point of G2 other than the identity.
+fn read_compressed_g2<R: Read>(reader: &mut R) -> io::Result<G2Affine> {
+    let (c1, flags) = read_compressed_fq(reader)?;
+    let (c0, _) = read_compressed_fq(reader)?;
+    let x = Fq2 { c0, c1 };
+    let y = Option::<Fq2>::from((x.square() * x + G2Affine::b()).sqrt())
+        .filter(|_| flags & INFINITY_FLAG == 0)
+        .ok_or_else(|| invalid("invalid point of G2".to_string()))?;
+    g2_from_xy(x, select_y(y, flags, cmp_fq2))
+}
+
+/// Returns an error unless `k` is supported.
+fn check_k(k: u32) -> io::Result<()> {
+    if k > max_k::<Fr>() {
+        return Err(invalid(format!(
+            "k = {} is larger than the largest supported k of {}",
+            k,
+            max_k::<Fr>()
+        )));
+    }
+    Ok(())
+}
+
+/// Reads the first `n` powers of tau in G1 with `read_g1`.
+fn read_powers_g1<R: Read>(
+    reader: &mut R,
+    n: usize,
+    read_g1: impl Fn(&mut R) -> io::Result<G1Affine>,
+) -> io::Result<Vec<G1Affine>> {
+    let points = (0..n)
+        .map(|_| read_g1(reader))
+        .collect::<io::Result<Vec<_>>>()?;
+    if points[0] != G1Affine::generator() {
+        return Err(invalid("the first power in G1 is not 1".to_string()));
+    }
+    Ok(points)
+}
+
+/// Reads $[\tau] G_2$ from the first two powers of tau in G2, read with
+/// `read_g2`.
+fn read_s_g2<R: Read>(
+    reader: &mut R,
+    read_g2: impl Fn(&mut R) -> io::Result<G2Affine>,
+) -> io::Result<G2Affine> {
+    if read_g2(reader)? != G2Affine::generator() {
+        return Err(invalid("the first power in G2 is not 1".to_string()));
+    }
+    read_g2(reader)
+}
+
+impl Params<G1Affine> {
+    /// Reads the parameters for circuits of `2^k` rows from a `.ptau` file
+    /// of snarkjs, such as those of the Perpetual Powers of Tau ceremony.
+    ///
+    /// The file can hold more powers than needed: only the first `2^k` are
+    /// read, and reading stops once they are. Returns an error if the file
+    /// is malformed, or holds fewer than `2^k` powers.
+    pub fn read_ptau<R: Read>(mut reader: R, k: u32) -> io::Result<Self> {
+        let reader = &mut reader;
+        check_k(k)?;
+        let n = 1usize << k;
+
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if &magic != MAGIC {
+            return Err(invalid("not a ptau file".to_string()));
+        }
+        let _version = read_u32(reader)?;
+        let num_sections = read_u32(reader)?;
+
+        let mut header_read = false;
+        let mut g = None;
+        let mut s_g2 = None;
+        for _ in 0..num_sections {
+            if g.is_some() && s_g2.is_some() {
+                break;
+            }
+
+            let section = read_u32(reader)?;
+            let len = read_u64(reader)?;
+            let read_len = match section {
+                HEADER_SECTION => {
+                    let field_size = read_u32(reader)? as usize;
+                    let mut modulus = [0u8; FIELD_SIZE];
+                    if field_size != FIELD_SIZE {
+                        return Err(invalid(format!(
+                            "base field elements take {} bytes instead of {}",
+                            field_size, FIELD_SIZE
+                        )));
+                    }
+                    reader.read_exact(&mut modulus)?;
+                    // The modulus is odd, so adding one to its predecessor
+                    // doesn't carry.
+                    let mut expected = (-Fq::one()).to_repr();
+                    expected.as_mut()[0] += 1;
+                    if modulus[..] != expected.as_ref()[..] {
+                        return Err(invalid("the curve is not BN254".to_string()));
+                    }
+                    let power = read_u32(reader)?;
+                    let _ceremony_power = read_u32(reader)?;
+                    if k > power {
+                        return Err(invalid(format!(
+                            "the file holds 2^{} powers, fewer than the 2^{} needed",
+                            power, k
+                        )));
+                    }
+                    header_read = true;
+                    4 + FIELD_SIZE as u64 + 8
+                }
+                TAU_G1_SECTION | TAU_G2_SECTION if !header_read => {
+                    return Err(invalid("the points precede the header".to_string()));
+                }
+                TAU_G1_SECTION => {
+                    g = Some(read_powers_g1(reader, n, read_g1)?);
+                    (n * 2 * FIELD_SIZE) as u64
+                }
+                TAU_G2_SECTION => {
+                    s_g2 = Some(read_s_g2(reader, read_g2)?);
+                    2 * 4 * FIELD_SIZE as u64
+                }
+                _ => 0,
+            };
+            if read_len > len {
+                return Err(invalid(format!("section {} is too short", section)));
+            }
+            skip(reader, len - read_len)?;
+        }
+
+        let g = g.ok_or_else(|| invalid("missing the powers in G1".to_string()))?;
+        let s_g2 = s_g2.ok_or_else(|| invalid("missing the powers in G2".to_string()))?;
+        Ok(Self::from_powers(k, g, s_g2))
+    }
+
+    /// Reads the parameters for circuits of `2^k` rows from a raw `response`
+    /// file of a ceremony of `2^power` powers, such as those of the Perpetual
+    /// Powers of Tau ceremony.
+    ///
+    /// A response file has no header, so `power` must be that of the
+    /// ceremony. As with [`Params::read_ptau`], only the first `2^k` powers
+    /// are read.
+    pub fn read_ptau_response<R: Read>(mut reader: R, power: u32, k: u32) -> io::Result<Self> {
+        let reader = &mut reader;
+        check_k(k)?;
+        if k > power {
+            return Err(invalid(format!(
+                "the file holds 2^{} powers, fewer than the 2^{} needed",
+                power, k
+            )));
+        }
+        let n = 1usize << k;
+
+        skip(reader, RESPONSE_HASH_SIZE)?;
+        let g = read_powers_g1(reader, n, read_compressed_g1)?;
+        let num_g1 = (1u64 << (power + 1)) - 1;
+        skip(reader, (num_g1 - n as u64) * FIELD_SIZE as u64)?;
+        let s_g2 = read_s_g2(reader, read_compressed_g2)?;
+        Ok(Self::from_powers(k, g, s_g2))
+    }
+
+    /// Returns the parameters with the powers of tau `g` in G1 and
+    /// $[\tau] G_2$.
+    fn from_powers(k: u32, g: Vec<G1Affine>, s_g2: G2Affine) -> Self {
+        let n = g.len();
+        // The Lagrange basis is the inverse FFT of the powers of tau.
+        let mut omega_inv = Fr::ROOT_OF_UNITY_INV;
+        for _ in k..Fr::S {
+            omega_inv = omega_inv.square();
+        }
+        let n_inv = Fr::from(n as u64).invert().unwrap();
+        let mut g_lagrange_projective: Vec<G1> = g.iter().map(|g| g.to_curve()).collect();
+        best_fft(&mut g_lagrange_projective, omega_inv, k);
+        parallelize(&mut g_lagrange_projective, |g, _| {
+            for g in g.iter_mut() {
+                g.group_scale(&n_inv);
+            }
+        });
+        let mut g_lagrange = vec![G1Affine::identity(); n];
+        parallelize(&mut g_lagrange, |g_lagrange, start| {
+            G1::batch_normalize(
+                &g_lagrange_projective[start..(start + g_lagrange.len())],
+                g_lagrange,
+            );
+        });
+
+        Params {
+            k,
+            n: n as u64,
+            g,
+            g_lagrange,
+            additional_data: Vec::from(s_g2.to_bytes().as_ref()),
+            scheme: CommitmentScheme::default(),
+            multiexp_windows: MultiexpWindows::default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::{Field, PrimeField};
+    use group::{prime::PrimeCurveAffine, Curve, GroupEncoding};
+    use pairing::bn256::{Bn256, Fq, Fq2, Fr, G1Affine, G2Affine};
+    use rand_core::OsRng;
+
+    use super::{
+        Params, GREATEST_FLAG, HEADER_SECTION, INFINITY_FLAG, TAU_G1_SECTION, TAU_G2_SECTION,
+    };
+    use crate::arithmetic::CurveAffine;
+    use crate::poly::EvaluationDomain;
+
+    /// The start of the file that `snarkjs powersoftau new bn128 1` writes,
+    /// up to the end of its powers in G2. Until a contribution is made, all of
+    /// its powers are of tau = 1.
+    const NEW_PTAU: &str = concat!(
+        // The file header.
+        "707461750100000007000000",
+        // The header section.
+        "010000002c0000000000000020000000",
+        "47fd7cd8168c203c8dca7168916a81975d588181b64550b829a031e1724e6430",
+        "0100000001000000",
+        // The powers in G1.
+        "02000000c000000000000000",
+        "9d0d8fc58d435dd33d0bc7f528eb780a2c4679786fa36e662fdf079ac1770a0e",
+        "3a1b1e8b1b87baa67b168eeb51d6f114588cf2f0de46ddcc5ebe0f3483ef141c",
+        "9d0d8fc58d435dd33d0bc7f528eb780a2c4679786fa36e662fdf079ac1770a0e",
+        "3a1b1e8b1b87baa67b168eeb51d6f114588cf2f0de46ddcc5ebe0f3483ef141c",
+        "9d0d8fc58d435dd33d0bc7f528eb780a2c4679786fa36e662fdf079ac1770a0e",
+        "3a1b1e8b1b87baa67b168eeb51d6f114588cf2f0de46ddcc5ebe0f3483ef141c",
+        // The powers in G2.
+        "030000000001000000000000",
+        "2620bc02d1b5838e72017b493519ebdcdf1a81974726b8fb3b5096af41385719",
+        "40614ca87d73b4afc4d802585add4360862fa052fc50e9096b7bea3a83f0fe14",
+        "f6e96b889dfa9d61789b9ef597d27ffefe7d1b23621a9eff06429eaeeb7efd28",
+        "ee5618c7565b0964bb3c7d3222f957dc76103533be35f9558264fd93e6a0a40d",
+        "2620bc02d1b5838e72017b493519ebdcdf1a81974726b8fb3b5096af41385719",
+        "40614ca87d73b4afc4d802585add4360862fa052fc50e9096b7bea3a83f0fe14",
+        "f6e96b889dfa9d61789b9ef597d27ffefe7d1b23621a9eff06429eaeeb7efd28",
+        "ee5618c7565b0964bb3c7d3222f957dc76103533be35f9558264fd93e6a0a40d",
+    );
+
+    fn powers<C: PrimeCurveAffine<Scalar = Fr>>(tau: Fr, count: u64) -> Vec<C> {
+        (0..count)
+            .map(|i| (C::generator() * tau.pow_vartime(&[i])).to_affine())
+            .collect()
+    }
+
+    fn write_fq(out: &mut Vec<u8>, value: &Fq) {
+        let montgomery = *value * Fq::from(2).pow_vartime(&[256]);
+        out.extend_from_slice(montgomery.to_repr().as_ref());
+    }
+
+    fn write_compressed_fq(out: &mut Vec<u8>, value: &Fq, flags: u8) {
+        let montgomery = *value * Fq::from(2).pow_vartime(&[256]);
+        let start = out.len();
+        out.extend(montgomery.to_repr().as_ref().iter().rev());
+        out[start] |= flags;
+    }
+
+    /// Returns the `GREATEST_FLAG` if `y` is larger than `-y`.
+    fn greatest_flag(y: &Fq) -> u8 {
+        let (y, neg_y) = (y.to_repr(), (-*y).to_repr());
+        if y.as_ref().iter().rev().gt(neg_y.as_ref().iter().rev()) {
+            GREATEST_FLAG
+        } else {
+            0
+        }
+    }
+
+    fn write_section(out: &mut Vec<u8>, section: u32, body: &[u8]) {
+        out.extend_from_slice(&section.to_le_bytes());
+        out.extend_from_slice(&(body.len() as u64).to_le_bytes());
+        out.extend_from_slice(body);
+    }
+
+    /// Returns a ptau file for `2^power` powers, with an unrelated section in
+    /// between.
+    fn ptau(power: u32, tau_g1: &[G1Affine], tau_g2: &[G2Affine]) -> Vec<u8> {
+        let mut header = vec![];
+        header.extend_from_slice(&32u32.to_le_bytes());
+        let mut modulus = (-Fq::one()).to_repr();
+        modulus.as_mut()[0] += 1;
+        header.extend_from_slice(modulus.as_ref());
+        header.extend_from_slice(&power.to_le_bytes());
+        header.extend_from_slice(&power.to_le_bytes());
+
+        let mut g1 = vec![];
+        for point in tau_g1 {
+            let coordinates = point.coordinates().unwrap();
+            write_fq(&mut g1, coordinates.x());
+            write_fq(&mut g1, coordinates.y());
+        }
+
+        let mut g2 = vec![];
+        for point in tau_g2 {
+            let coordinates = point.coordinates().unwrap();
+            for coordinate in [coordinates.x(), coordinates.y()].iter() {
+                write_fq(&mut g2, &coordinate.c0);
+                write_fq(&mut g2, &coordinate.c1);
+            }
+        }
+
+        let mut out = b"ptau".to_vec();
+        out.extend_from_slice(&1u32.to_le_bytes());
+        out.extend_from_slice(&4u32.to_le_bytes());
+        write_section(&mut out, HEADER_SECTION, &header);
+        write_section(&mut out, TAU_G1_SECTION, &g1);
+        write_section(&mut out, 7, &[1, 2, 3]);
+        write_section(&mut out, TAU_G2_SECTION, &g2);
+        out
+    }
+
+    /// Returns the start of a response file for `2^power` powers of `tau`, up
+    /// to the end of its powers in G2.
+    fn response(power: u32, tau: Fr) -> Vec<u8> {
+        let mut out = vec![0; 64];
+        for point in powers::<G1Affine>(tau, (1 << (power + 1)) - 1) {
+            let coordinates = point.coordinates().unwrap();
+            let flags = greatest_flag(coordinates.y());
+            write_compressed_fq(&mut out, coordinates.x(), flags);
+        }
+        for point in powers::<G2Affine>(tau, 1 << power) {
+            let coordinates = point.coordinates().unwrap();
+            let y = coordinates.y();
+            let flags = if y.c1 == Fq::zero() {
+                greatest_flag(&y.c0)
+            } else {
+                greatest_flag(&y.c1)
+            };
+            write_compressed_fq(&mut out, &coordinates.x().c1, flags);
+            write_compressed_fq(&mut out, &coordinates.x().c0, 0);
+        }
+        out
+    }
+
+    fn check_params(params: &Params<G1Affine>, k: u32, tau: Fr) {
+        assert_eq!(params.n, 1 << k);
+        assert_eq!(params.g, powers::<G1Affine>(tau, 1 << k));
+        let s_g2 = (G2Affine::generator() * tau).to_affine();
+        assert_eq!(params.additional_data, s_g2.to_bytes().as_ref());
+
+        let domain = EvaluationDomain::new(1, k);
+        let mut a = domain.empty_lagrange();
+        for (i, a) in a.iter_mut().enumerate() {
+            *a = Fr::from(i as u64 + 1);
+        }
+        let b = domain.lagrange_to_coeff(a.clone());
+        assert_eq!(params.commit(&b), params.commit_lagrange(&a));
+
+        params.verifier::<Bn256>(1).unwrap();
+    }
+
+    #[test]
+    fn read_ptau() {
+        let tau = Fr::random(OsRng);
+        let file = ptau(3, &powers::<G1Affine>(tau, 15), &powers::<G2Affine>(tau, 8));
+
+        for k in 1..=3 {
+            let params = Params::read_ptau(&file[..], k).unwrap();
+            check_params(&params, k, tau);
+        }
+
+        assert!(Params::read_ptau(&file[..], 4).is_err());
+        assert!(Params::read_ptau(&file[..file.len() - 1], 3).is_err());
+        assert!(Params::read_ptau(&b"zkey"[..], 1).is_err());
+    }
+
+    #[test]
+    fn read_new_ptau() {
+        let file: Vec<u8> = (0..NEW_PTAU.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&NEW_PTAU[i..i + 2], 16).unwrap())
+            .collect();
+
+        let params = Params::read_ptau(&file[..], 1).unwrap();
+        check_params(&params, 1, Fr::one());
+        assert!(Params::read_ptau(&file[..], 2).is_err());
+    }
+
+    #[test]
+    fn read_ptau_response() {
+        let tau = Fr::random(OsRng);
+        let file = response(3, tau);
+
+        for k in 1..=3 {
+            let params = Params::read_ptau_response(&file[..], 3, k).unwrap();
+            check_params(&params, k, tau);
+        }
+        assert!(Params::read_ptau_response(&file[..], 3, 4).is_err());
+        assert!(Params::read_ptau_response(&file[..file.len() - 1], 3, 3).is_err());
+
+        // The flag selects the sign of the y-coordinate.
+        let mut flipped = file.clone();
+        flipped[64 + 32] ^= GREATEST_FLAG;
+        let params = Params::read_ptau_response(&flipped[..], 3, 1).unwrap();
+        assert_eq!(params.g[1], -powers::<G1Affine>(tau, 2)[1]);
+
+        let mut infinity = file;
+        infinity[64 + 32] |= INFINITY_FLAG;
+        assert!(Params::read_ptau_response(&infinity[..], 3, 1).is_err());
+    }
+
+    #[test]
+    fn g2_subgroup() {
+        // A point of the curve of G2 that isn't in G2, as almost none are.
+        let point = (1u64..)
+            .find_map(|i| {
+                let x = Fq2 {
+                    c0: Fq::from(i),
+                    c1: Fq::zero(),
+                };
+                let y = Option::<Fq2>::from((x.square() * x + G2Affine::b()).sqrt())?;
+                Option::<G2Affine>::from(G2Affine::from_xy(x, y))
+            })
+            .unwrap();
+
+        let tau = Fr::random(OsRng);
+        let mut tau_g2 = powers::<G2Affine>(tau, 2);
+        let file = ptau(1, &powers::<G1Affine>(tau, 3), &tau_g2);
+        assert!(Params::read_ptau(&file[..], 1).is_ok());
+
+        tau_g2[1] = point;
+        let file = ptau(1, &powers::<G1Affine>(tau, 3), &tau_g2);
+        assert!(Params::read_ptau(&file[..], 1).is_err());
+    }
+}
@@ -2,7 +2,9 @@
 //! domain that is of a suitable size for the application.
 
 use crate::{
-    arithmetic::{best_fft, parallelize, FieldExt, Group},
+    arithmetic::{
+        best_fft, best_fft_with_twiddles, compute_twiddles, parallelize, FieldExt, Group,
+    },
     plonk::Assigned,
 };
 
@@ -12,10 +14,39 @@ use group::ff::{BatchInvert, Field, PrimeField};
 
 use group::Group as CryptGroup;
 
+#[cfg(feature = "gpu")]
+use std::any::Any;
 use std::marker::PhantomData;
+use std::sync::{Arc, Mutex};
 
+#[cfg(feature = "gpu")]
 use crate::gpu;
-use log::{info, warn};
+#[cfg(feature = "gpu")]
+use crate::logging::GPU;
+use log::{debug, info, warn};
+
+/// Returns the largest `k` for which a domain of size $2^k$ is supported: its
+/// rows must be indexable by a `usize`, and $2^k$ must divide the order of the
+/// multiplicative group of `F`.
+pub fn max_k<F: FieldExt>() -> u32 {
+    // `usize::BITS` needs Rust 1.53.
+    F::S.min((std::mem::size_of::<usize>() * 8) as u32 - 1)
+}
+
+/// Returns the `k` of the extended domain that [`EvaluationDomain::new`]
+/// constructs for the values $j, k$.
+pub(crate) fn extended_k(j: u32, k: u32) -> u32 {
+    // We need to work within an extended domain, not params.k but params.k + i
+    // for some integer i such that 2^(params.k + i) is sufficiently large to
+    // describe the quotient polynomial.
+    // i is the smallest integer such that 2^i >= j - 1, computed without
+    // shifting by k so that it can't overflow for large k.
+    let i = match j - 1 {
+        0 | 1 => 0,
+        quotient_poly_degree => 32 - (quotient_poly_degree - 1).leading_zeros(),
+    };
+    k.saturating_add(i)
+}
 
 /// This structure contains precomputed constants and other details needed for
 /// performing operations on an evaluation domain of size $2^k$ and an extended
@@ -28,7 +59,6 @@ pub struct EvaluationDomain<G: Group> {
     omega: G::Scalar,
     omega_inv: G::Scalar,
     extended_omega: G::Scalar,
-    extended_omega_inv: G::Scalar,
     g_coset: G::Scalar,
     g_coset_inv: G::Scalar,
     quotient_poly_degree: u64,
@@ -36,6 +66,47 @@ pub struct EvaluationDomain<G: Group> {
     extended_ifft_divisor: G::Scalar,
     t_evaluations: Vec<G::Scalar>,
     barycentric_weight: G::Scalar,
+    // The tables of twiddle factors of the FFTs over the base and the extended
+    // domain. They are computed on first use, as the verifier, which holds an
+    // `EvaluationDomain` in every `VerifyingKey`, performs no FFTs.
+    twiddles: Twiddles<G::Scalar>,
+    inv_twiddles: Twiddles<G::Scalar>,
+    extended_twiddles: Twiddles<G::Scalar>,
+    extended_inv_twiddles: Twiddles<G::Scalar>,
+}
+
+/// The table of twiddle factors of an FFT, which is computed by
+/// [`compute_twiddles`] the first time it is needed.
+#[derive(Debug)]
+struct Twiddles<F> {
+    omega: F,
+    log_n: u32,
+    table: Mutex<Option<Arc<Vec<F>>>>,
+}
+
+impl<F: Field> Twiddles<F> {
+    fn new(omega: F, log_n: u32) -> Self {
+        Twiddles {
+            omega,
+            log_n,
+            table: Mutex::new(None),
+        }
+    }
+
+    /// Returns the table, computing it if this is its first use. The lock isn't
+    /// held while the table is computed, as that work runs on the thread pool.
+    fn get(&self) -> Arc<Vec<F>> {
+        if let Some(table) = self.table.lock().unwrap().as_ref() {
+            return table.clone();
+        }
+        let table = Arc::new(compute_twiddles(self.omega, self.log_n));
+        self.table.lock().unwrap().get_or_insert(table).clone()
+    }
+
+    #[cfg(test)]
+    fn is_computed(&self) -> bool {
+        self.table.lock().unwrap().is_some()
+    }
 }
 
 impl<G: Group> EvaluationDomain<G> {
@@ -48,13 +119,13 @@ impl<G: Group> EvaluationDomain<G> {
         // n = 2^k
         let n = 1u64 << k;
 
-        // We need to work within an extended domain, not params.k but params.k + i
-        // for some integer i such that 2^(params.k + i) is sufficiently large to
-        // describe the quotient polynomial.
-        let mut extended_k = k;
-        while (1 << extended_k) < (n * quotient_poly_degree) {
-            extended_k += 1;
-        }
+        let extended_k = extended_k(j, k);
+        assert!(
+            extended_k <= max_k::<G::Scalar>(),
+            "the extended domain of size 2^{} is larger than 2^{}, the largest supported",
+            extended_k,
+            max_k::<G::Scalar>(),
+        );
 
         let mut extended_omega = G::Scalar::root_of_unity();
 
@@ -135,7 +206,6 @@ impl<G: Group> EvaluationDomain<G> {
             omega,
             omega_inv,
             extended_omega,
-            extended_omega_inv,
             g_coset,
             g_coset_inv,
             quotient_poly_degree,
@@ -143,6 +213,10 @@ impl<G: Group> EvaluationDomain<G> {
             extended_ifft_divisor,
             t_evaluations,
             barycentric_weight,
+            twiddles: Twiddles::new(omega, k),
+            inv_twiddles: Twiddles::new(omega_inv, k),
+            extended_twiddles: Twiddles::new(extended_omega, extended_k),
+            extended_inv_twiddles: Twiddles::new(extended_omega_inv, extended_k),
         }
     }
 
@@ -232,10 +306,10 @@ impl<G: Group> EvaluationDomain<G> {
         assert_eq!(a.values.len(), 1 << self.k);
 
         // Perform inverse FFT to obtain the polynomial in coefficient form
-        Self::ifft(&mut a.values, self.omega_inv, self.k, self.ifft_divisor);
+        self.ifft(&mut a.values, &self.inv_twiddles, self.ifft_divisor);
 
         Polynomial {
-            values: a.values,
+            values: std::mem::take(&mut a.values),
             _marker: PhantomData,
         }
     }
@@ -249,11 +323,83 @@ impl<G: Group> EvaluationDomain<G> {
         assert_eq!(a.values.len(), 1 << self.k);
 
         self.distribute_powers_zeta(&mut a.values, true);
-        a.values.resize(self.extended_len(), G::group_zero());
-        best_fft(&mut a.values, self.extended_omega, self.extended_k);
+        // Copy into a buffer of the extended length rather than growing `a`,
+        // so that the old buffer is freed like any dropped polynomial.
+        let mut values = Vec::with_capacity(self.extended_len());
+        values.extend_from_slice(&a.values);
+        values.resize(self.extended_len(), G::group_zero());
+        drop(a);
+        self.best_fft(&mut values, &self.extended_twiddles);
 
         Polynomial {
-            values: a.values,
+            values,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the number of parts that the extended domain is split into by
+    /// [`EvaluationDomain::coeff_to_extended_part`].
+    pub(crate) fn extended_parts(&self) -> usize {
+        1 << (self.extended_k - self.k)
+    }
+
+    /// Returns an empty (zero) polynomial over a part of the extended domain.
+    pub(crate) fn empty_extended_part(&self) -> Polynomial<G, ExtendedLagrangeCoeff> {
+        Polynomial {
+            values: vec![G::group_zero(); self.n as usize],
+            _marker: PhantomData,
+        }
+    }
+
+    /// This takes us from an n-length coefficient vector to the points of the
+    /// extended domain whose index is `part` modulo
+    /// [`EvaluationDomain::extended_parts`], in order. These points form a
+    /// coset of the original domain, so only an n-length FFT is needed.
+    pub(crate) fn coeff_to_extended_part(
+        &self,
+        a: &Polynomial<G, Coeff>,
+        part: usize,
+    ) -> Polynomial<G, ExtendedLagrangeCoeff> {
+        assert_eq!(a.values.len(), 1 << self.k);
+        assert!(part < self.extended_parts());
+
+        let mut values = a.values.clone();
+        self.distribute_powers_zeta(&mut values, true);
+        // Scale the i'th coefficient by extended_omega^(part * i), so that the
+        // FFT over the original domain evaluates at zeta * extended_omega^part * omega^j.
+        let shift = self.extended_omega.pow_vartime(&[part as u64, 0, 0, 0]);
+        parallelize(&mut values, |values, start| {
+            let mut power = shift.pow_vartime(&[start as u64, 0, 0, 0]);
+            for value in values {
+                value.group_scale(&power);
+                power *= &shift;
+            }
+        });
+        self.best_fft(&mut values, &self.twiddles);
+
+        Polynomial {
+            values,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Returns the values of a polynomial over the extended domain at the points
+    /// that [`EvaluationDomain::coeff_to_extended_part`] evaluates at.
+    pub(crate) fn extended_part(
+        &self,
+        a: &Polynomial<G, ExtendedLagrangeCoeff>,
+        part: usize,
+    ) -> Polynomial<G, ExtendedLagrangeCoeff> {
+        assert_eq!(a.values.len(), self.extended_len());
+
+        Polynomial {
+            values: a
+                .values
+                .iter()
+                .skip(part)
+                .step_by(self.extended_parts())
+                .copied()
+                .collect(),
             _marker: PhantomData,
         }
     }
@@ -287,10 +433,9 @@ impl<G: Group> EvaluationDomain<G> {
         assert_eq!(a.values.len(), self.extended_len());
 
         // Inverse FFT
-        Self::ifft(
+        self.ifft(
             &mut a.values,
-            self.extended_omega_inv,
-            self.extended_k,
+            &self.extended_inv_twiddles,
             self.extended_ifft_divisor,
         );
 
@@ -304,7 +449,7 @@ impl<G: Group> EvaluationDomain<G> {
         a.values
             .truncate((&self.n * self.quotient_poly_degree) as usize);
 
-        a.values
+        std::mem::take(&mut a.values)
     }
 
     /// This divides the polynomial (in the extended domain) by the vanishing
@@ -325,7 +470,7 @@ impl<G: Group> EvaluationDomain<G> {
         });
 
         Polynomial {
-            values: a.values,
+            values: std::mem::take(&mut a.values),
             _marker: PhantomData,
         }
     }
@@ -355,8 +500,8 @@ impl<G: Group> EvaluationDomain<G> {
         });
     }
 
-    fn ifft(a: &mut [G], omega_inv: G::Scalar, log_n: u32, divisor: G::Scalar) {
-        best_fft(a, omega_inv, log_n);
+    fn ifft(&self, a: &mut Vec<G>, twiddles: &Twiddles<G::Scalar>, divisor: G::Scalar) {
+        self.best_fft(a, twiddles);
         parallelize(a, |a, _| {
             for a in a {
                 // Finish iFFT
@@ -365,6 +510,27 @@ impl<G: Group> EvaluationDomain<G> {
         });
     }
 
+    /// Performs the FFT of `a` in place whose root of unity and twiddle factors
+    /// are those of `twiddles`. With the `gpu` feature, vectors of at least
+    /// $2^{16}$ scalars are transformed on the GPU if one is available;
+    /// otherwise this falls back to [`best_fft_with_twiddles`]. The table of
+    /// twiddle factors is only computed in the latter case, as the GPU kernels
+    /// compute their own.
+    fn best_fft(&self, a: &mut Vec<G>, twiddles: &Twiddles<G::Scalar>) {
+        #[cfg(feature = "gpu")]
+        if twiddles.log_n >= GPU_FFT_MIN_LOG_N {
+            // The GPU kernels only operate on scalars, which is what the
+            // prover's polynomials are made of.
+            if let Some(a) = (a as &mut dyn Any).downcast_mut::<Vec<G::Scalar>>() {
+                if gpu_fft::<G>(a, twiddles.omega, twiddles.log_n) {
+                    return;
+                }
+            }
+        }
+
+        best_fft_with_twiddles(a, twiddles.omega, &twiddles.get(), twiddles.log_n);
+    }
+
     /// Get the size of the domain
     pub fn k(&self) -> u32 {
         self.k
@@ -396,6 +562,12 @@ impl<G: Group> EvaluationDomain<G> {
         self.extended_omega
     }
 
+    /// Performs an FFT over the $2^k$ domain using the cached twiddle factors;
+    /// equivalent to `best_fft(a, self.get_omega(), self.k())`.
+    pub fn fft(&self, a: &mut [G]) {
+        best_fft_with_twiddles(a, self.omega, &self.twiddles.get(), self.k);
+    }
+
     /// Multiplies a value by some power of $\omega$, essentially rotating over
     /// the domain.
     pub fn rotate_omega(&self, value: G::Scalar, rotation: Rotation) -> G::Scalar {
@@ -485,28 +657,55 @@ impl<G: Group> EvaluationDomain<G> {
 #[allow(dead_code)]
 #[derive(Debug)]
 pub struct PinnedEvaluationDomain<'a, G: Group> {
-    k: &'a u32,
-    extended_k: &'a u32,
-    omega: &'a G::Scalar,
+    pub(crate) k: &'a u32,
+    pub(crate) extended_k: &'a u32,
+    pub(crate) omega: &'a G::Scalar,
 }
 
+#[cfg(feature = "gpu")]
 pub fn create_fft_kernel<G>(_log_d: usize, priority: bool) -> Option<gpu::MultiFFTKernel<G>>
 where
     G: Group,
 {
     match gpu::MultiFFTKernel::create(priority) {
         Ok(k) => {
-            info!("GPU FFT kernel instantiated!");
+            info!(target: GPU, "kernel=fft event=created");
             Some(k)
         }
         Err(e) => {
-            warn!("Cannot instantiate GPU FFT kernel! Error: {}", e);
+            warn!(target: GPU, "kernel=fft event=create_failed error=\"{}\"", e);
             None
         }
     }
 }
 
+/// The base-2 logarithm of the smallest FFT that
+/// [`EvaluationDomain`] offloads to the GPU.
+#[cfg(feature = "gpu")]
+pub const GPU_FFT_MIN_LOG_N: u32 = 16;
+
+/// Performs an FFT of `a` on the GPU, returning `false` without modifying `a`
+/// if no GPU kernel could be created or the `BELLMAN_NO_GPU` environment
+/// variable is set.
+#[cfg(feature = "gpu")]
+pub fn gpu_fft<G: Group>(a: &mut [G::Scalar], omega: G::Scalar, log_n: u32) -> bool {
+    if std::env::var("BELLMAN_NO_GPU").is_ok() {
+        return false;
+    }
+
+    let mut kern = gpu::LockedMultiFFTKernel::<G>::new(log_n as usize, false);
+    match kern.with(|k: &mut gpu::MultiFFTKernel<G>| gpu_fft_multiple(k, &mut [a], &omega, log_n)) {
+        Ok(()) => true,
+        Err(e) => {
+            warn!(target: GPU, "kernel=fft event=fallback log_n={} error=\"{}\"", log_n, e);
+            false
+        }
+    }
+}
+
+#[cfg(feature = "gpu")]
 use crate::worker::Worker;
+#[cfg(feature = "gpu")]
 pub fn best_fft_multiple_gpu<G: Group>(
     kern: &mut Option<gpu::LockedMultiFFTKernel<G>>,
     polys: &mut [&mut [G::Scalar]],
@@ -519,13 +718,14 @@ pub fn best_fft_multiple_gpu<G: Group>(
             .with(|k: &mut gpu::MultiFFTKernel<G>| gpu_fft_multiple(k, polys, omega, log_n))
             .is_ok()
         {
-            println!("use multiple GPUs");
+            debug!(target: GPU, "kernel=fft event=done polys={} log_n={}", polys.len(), log_n);
             return Ok(());
         }
     }
     Ok(())
 }
 
+#[cfg(feature = "gpu")]
 pub fn gpu_fft_multiple<G: Group>(
     kern: &mut gpu::MultiFFTKernel<G>,
     polys: &mut [&mut [G::Scalar]],
@@ -537,6 +737,7 @@ pub fn gpu_fft_multiple<G: Group>(
     Ok(())
 }
 
+#[cfg(feature = "gpu")]
 #[test]
 fn test_best_fft_multiple_gpu() {
     use crate::gpu::LockedMultiFFTKernel;
@@ -613,6 +814,90 @@ fn test_fft() {
     }
 }
 
+#[test]
+fn test_cached_twiddles() {
+    use crate::arithmetic::eval_polynomial;
+    use pairing::bn256::Fr as Scalar;
+    use rand_core::OsRng;
+
+    let k = 4;
+    let domain = EvaluationDomain::<Scalar>::new(3, k);
+    let coeffs: Vec<_> = (0..(1 << k)).map(|_| Scalar::random(OsRng)).collect();
+    assert!(!domain.twiddles.is_computed());
+
+    let mut expected = coeffs.clone();
+    best_fft(&mut expected, domain.get_omega(), k);
+    let mut actual = coeffs.clone();
+    domain.fft(&mut actual);
+    assert_eq!(expected, actual);
+
+    let lagrange = domain.lagrange_from_vec(expected);
+    assert_eq!(domain.lagrange_to_coeff(lagrange).values, coeffs);
+
+    // FFTs over the base domain don't compute the extended domain's tables.
+    assert!(domain.twiddles.is_computed());
+    assert!(domain.inv_twiddles.is_computed());
+    assert!(!domain.extended_twiddles.is_computed());
+    assert!(!domain.extended_inv_twiddles.is_computed());
+
+    // Round trip through the extended domain.
+    let poly = domain.coeff_from_vec(coeffs.clone());
+    let extended = domain.coeff_to_extended(poly);
+    let x = domain.g_coset * domain.extended_omega;
+    assert_eq!(extended[1], eval_polynomial(&coeffs, x));
+    let mut recovered = domain.extended_to_coeff(extended);
+    recovered.truncate(coeffs.len());
+    assert_eq!(recovered, coeffs);
+}
+
+#[test]
+fn test_extended_parts() {
+    use pairing::bn256::Fr as Scalar;
+    use rand_core::OsRng;
+
+    let k = 4;
+    let domain = EvaluationDomain::<Scalar>::new(5, k);
+    assert_eq!(domain.extended_parts(), 4);
+
+    let poly = domain.coeff_from_vec((0..(1 << k)).map(|_| Scalar::random(OsRng)).collect());
+    let extended = domain.coeff_to_extended(poly.clone());
+    for part in 0..domain.extended_parts() {
+        let expected = domain.extended_part(&extended, part);
+        assert_eq!(expected.len(), 1 << k);
+        assert_eq!(domain.coeff_to_extended_part(&poly, part).values, expected.values);
+    }
+}
+
+#[cfg(feature = "gpu")]
+#[test]
+fn test_gpu_domain_fft() {
+    use crate::arithmetic::eval_polynomial;
+    use pairing::bn256::Fr as Scalar;
+    use rand_core::OsRng;
+
+    // Large enough for every transform to be offloaded to the GPU, if there is one.
+    let k = GPU_FFT_MIN_LOG_N;
+    let domain = EvaluationDomain::<Scalar>::new(3, k);
+    let coeffs: Vec<_> = (0..(1 << k)).map(|_| Scalar::random(OsRng)).collect();
+
+    let mut expected = coeffs.clone();
+    best_fft(&mut expected, domain.get_omega(), k);
+    let lagrange = domain.lagrange_from_vec(expected);
+    assert_eq!(domain.lagrange_to_coeff(lagrange).values, coeffs);
+
+    // The twiddle factors are only computed when falling back to the CPU.
+    let on_gpu = gpu_fft::<Scalar>(&mut coeffs.clone(), domain.get_omega(), k);
+    assert_eq!(domain.inv_twiddles.is_computed(), !on_gpu);
+
+    let poly = domain.coeff_from_vec(coeffs.clone());
+    let extended = domain.coeff_to_extended(poly);
+    let x = domain.g_coset * domain.extended_omega;
+    assert_eq!(extended[1], eval_polynomial(&coeffs, x));
+    let mut recovered = domain.extended_to_coeff(extended);
+    recovered.truncate(coeffs.len());
+    assert_eq!(recovered, coeffs);
+}
+
 #[test]
 fn test_rotate() {
     use rand_core::OsRng;
@@ -683,3 +968,20 @@ fn test_l_i() {
         assert_eq!(eval_polynomial(&l[(8 - i) % 8][..], x), evaluations[7 - i]);
     }
 }
+
+#[test]
+fn test_extended_k() {
+    use pairing::bn256::Fr as Scalar;
+
+    for j in 1..20u32 {
+        for k in 1..20u32 {
+            let mut expected = k;
+            while (1u64 << expected) < (1u64 << k) * (j - 1) as u64 {
+                expected += 1;
+            }
+            assert_eq!(extended_k(j, k), expected);
+        }
+    }
+    assert_eq!(extended_k(5, u32::MAX), u32::MAX);
+    assert!(max_k::<Scalar>() <= Scalar::S);
+}
@@ -23,6 +23,11 @@ impl<'a, C: CurveAffine> MSM<C> {
         }
     }
 
+    /// Returns the number of terms in the MSM.
+    pub fn size(&self) -> usize {
+        self.scalars.len()
+    }
+
     /// Add another multiexp into this one
     pub fn add_msm(&mut self, other: &Self) {
         self.scalars.extend(other.scalars.iter());
@@ -82,6 +87,11 @@ impl<C: CurveAffine> PairMSM<C> {
         Self { left, right }
     }
 
+    /// Returns the number of terms on both channels.
+    pub fn size(&self) -> usize {
+        self.left.size() + self.right.size()
+    }
+
     /// Perform multiexp on both channels
     pub fn eval(&self) -> (C, C) {
         (self.left.eval(), self.right.eval())
@@ -100,14 +110,12 @@ impl<C: CurveAffine> PairMSM<C> {
     }
 }
 
-#[cfg(feature = "shplonk")]
 #[derive(Debug, Clone)]
 pub struct ProjectiveMSM<E: Engine> {
     scalars: Vec<E::Scalar>,
     bases: Vec<E::G1>,
 }
 
-#[cfg(feature = "shplonk")]
 impl<'a, E: Engine> ProjectiveMSM<E> {
     /// Create a new, empty MSM using the provided parameters.
     pub fn new() -> Self {
@@ -148,13 +156,11 @@ impl<'a, E: Engine> ProjectiveMSM<E> {
 }
 
 /// A projective point collector
-#[cfg(feature = "shplonk")]
 #[derive(Debug, Clone)]
 pub struct PreMSM<E: Engine> {
     projectives_msms: Vec<ProjectiveMSM<E>>,
 }
 
-#[cfg(feature = "shplonk")]
 impl<'a, E: Engine> PreMSM<E> {
     pub fn new() -> Self {
         PreMSM {
@@ -165,23 +171,19 @@ impl<'a, E: Engine> PreMSM<E> {
     pub fn normalize(self) -> MSM<E::G1Affine> {
         use group::prime::PrimeCurveAffine;
 
-        let bases: Vec<E::G1> = self
+        // The terms are moved out of the collected MSMs rather than cloned, so
+        // that they are only held once.
+        let size = self
             .projectives_msms
             .iter()
-            .map(|msm| msm.bases.clone())
-            .collect::<Vec<Vec<E::G1>>>()
-            .into_iter()
-            .flatten()
-            .collect();
-
-        let scalars: Vec<E::Scalar> = self
-            .projectives_msms
-            .iter()
-            .map(|msm| msm.scalars.clone())
-            .collect::<Vec<Vec<E::Scalar>>>()
-            .into_iter()
-            .flatten()
-            .collect();
+            .map(|msm| msm.scalars.len())
+            .sum();
+        let mut bases: Vec<E::G1> = Vec::with_capacity(size);
+        let mut scalars: Vec<E::Scalar> = Vec::with_capacity(size);
+        for msm in self.projectives_msms {
+            bases.extend(msm.bases);
+            scalars.extend(msm.scalars);
+        }
 
         let mut affine_bases = vec![E::G1Affine::identity(); bases.len()];
         E::G1::batch_normalize(&bases[..], &mut affine_bases);
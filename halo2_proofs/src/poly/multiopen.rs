@@ -3,44 +3,93 @@
 //!
 //! [halo]: https://eprint.iacr.org/2019/1021
 
-use super::{commitment::ParamsVerifier, PairMSM};
+use super::{
+    commitment::{CommitmentScheme, Params, ParamsVerifier},
+    PairMSM,
+};
 use crate::{
     arithmetic::{eval_polynomial, CurveAffine, FieldExt},
     pairing::arithmetic::{MillerLoopResult, MultiMillerLoop},
     poly::{msm::MSM, Coeff, Error, Polynomial},
+    transcript::{EncodedChallenge, TranscriptRead, TranscriptWrite},
 };
 
 use crate::poly::Rotation;
 use ff::Field;
 use group::Group;
+use log::debug;
 use rand::RngCore;
 use std::{
-    collections::{BTreeMap, BTreeSet},
-    marker::PhantomData,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    hash::{Hash, Hasher},
+    io,
     thread::AccessError,
 };
 use subtle::Choice;
 
-cfg_if::cfg_if! {
-    if #[cfg(feature = "shplonk")] {
-        mod shplonk;
-        pub use shplonk::*;
-    } else {
-        mod gwc;
-        pub use gwc::*;
+mod gwc;
+mod shplonk;
+
+/// Create a multi-opening proof with the multiopening argument of
+/// `params.scheme()`.
+pub fn create_proof<'a, I, C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptWrite<C, E>>(
+    params: &Params<C>,
+    transcript: &mut T,
+    queries: I,
+) -> io::Result<()>
+where
+    I: IntoIterator<Item = ProverQuery<'a, C>> + Clone,
+{
+    match params.scheme() {
+        CommitmentScheme::KzgGwc => gwc::create_proof(params, transcript, queries),
+        CommitmentScheme::KzgShplonk => shplonk::create_proof(params, transcript, queries),
+    }
+}
+
+/// Verify a multi-opening proof with the multiopening argument of
+/// `params.scheme()`.
+pub fn verify_proof<
+    'r,
+    I,
+    C: MultiMillerLoop,
+    E: EncodedChallenge<C::G1Affine>,
+    T: TranscriptRead<C::G1Affine, E>,
+>(
+    params: &ParamsVerifier<C>,
+    transcript: &mut T,
+    queries: I,
+) -> Result<PairMSM<C::G1Affine>, Error>
+where
+    I: IntoIterator<Item = VerifierQuery<'r, C::G1Affine>> + Clone,
+{
+    match params.scheme() {
+        CommitmentScheme::KzgGwc => gwc::verify_proof(params, transcript, queries),
+        CommitmentScheme::KzgShplonk => shplonk::verify_proof(params, transcript, queries),
     }
 }
 
 /// Decider performs final pairing check with given verifier params and two channel linear combination
-#[derive(Debug)]
+///
+/// It only keeps the two `G2` points of the verifier params that the check needs,
+/// so it can be held without borrowing the params.
+#[derive(Clone, Debug)]
 pub struct Decider<E: MultiMillerLoop> {
-    _marker: PhantomData<E>,
+    g2: E::G2Affine,
+    s_g2: E::G2Affine,
 }
 
 impl<E: MultiMillerLoop> Decider<E> {
-    fn prepare(params: &ParamsVerifier<E>) -> (E::G2Prepared, E::G2Prepared) {
-        let s_g2_prepared = E::G2Prepared::from(params.s_g2);
-        let n_g2_prepared = E::G2Prepared::from(-params.g2);
+    /// Copies the points needed for the pairing check out of the verifier params.
+    pub fn new(params: &ParamsVerifier<E>) -> Self {
+        Decider {
+            g2: params.g2,
+            s_g2: params.s_g2,
+        }
+    }
+
+    fn prepare(&self) -> (E::G2Prepared, E::G2Prepared) {
+        let s_g2_prepared = E::G2Prepared::from(self.s_g2);
+        let n_g2_prepared = E::G2Prepared::from(-self.g2);
         (s_g2_prepared, n_g2_prepared)
     }
 
@@ -54,7 +103,12 @@ impl<E: MultiMillerLoop> Decider<E> {
 
     /// Performs final pairing check with given verifier params and two channel linear combination
     pub fn verify(params: &ParamsVerifier<E>, msm: PairMSM<E::G1Affine>) -> bool {
-        let (s_g2, n_g2) = Self::prepare(params);
+        Self::new(params).decide(msm)
+    }
+
+    /// Performs final pairing check on a two channel linear combination
+    pub fn decide(&self, msm: PairMSM<E::G1Affine>) -> bool {
+        let (s_g2, n_g2) = self.prepare();
         let (left, right) = msm.eval();
         let (term_1, term_2) = ((&left, &s_g2), (&right, &n_g2));
         Self::pairing_check(&[term_1, term_2])
@@ -84,7 +138,7 @@ pub struct VerifierQuery<'r, C: CurveAffine> {
     eval: C::Scalar,
 }
 
-impl<'r, 'params: 'r, C: CurveAffine> VerifierQuery<'r, C> {
+impl<'r, C: CurveAffine> VerifierQuery<'r, C> {
     /// Create a new verifier query based on a commitment
     pub fn new_commitment(
         commitment: &'r C,
@@ -117,7 +171,7 @@ enum CommitmentReference<'r, C: CurveAffine> {
     MSM(&'r MSM<C>),
 }
 
-impl<'r, 'params: 'r, C: CurveAffine> PartialEq for CommitmentReference<'r, C> {
+impl<'r, C: CurveAffine> PartialEq for CommitmentReference<'r, C> {
     fn eq(&self, other: &Self) -> bool {
         match (self, other) {
             (&CommitmentReference::Commitment(a), &CommitmentReference::Commitment(b)) => {
@@ -129,13 +183,144 @@ impl<'r, 'params: 'r, C: CurveAffine> PartialEq for CommitmentReference<'r, C> {
     }
 }
 
+impl<'r, C: CurveAffine> Eq for CommitmentReference<'r, C> {}
+
+impl<'r, C: CurveAffine> Hash for CommitmentReference<'r, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match *self {
+            CommitmentReference::Commitment(a) => {
+                0u8.hash(state);
+                std::ptr::hash(a, state);
+            }
+            CommitmentReference::MSM(a) => {
+                1u8.hash(state);
+                std::ptr::hash(a, state);
+            }
+        }
+    }
+}
+
+#[doc(hidden)]
+#[derive(Copy, Clone, Debug)]
+pub struct PolynomialPointer<'a, C: CurveAffine> {
+    poly: &'a Polynomial<C::Scalar, Coeff>,
+}
+
+impl<'a, C: CurveAffine> PartialEq for PolynomialPointer<'a, C> {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.poly, other.poly)
+    }
+}
+
+impl<'a, C: CurveAffine> Eq for PolynomialPointer<'a, C> {}
+
+impl<'a, C: CurveAffine> Hash for PolynomialPointer<'a, C> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::ptr::hash(self.poly, state)
+    }
+}
+
 trait Query<F: FieldExt>: Sized + Clone {
-    type Commitment: PartialEq + Clone;
+    type Commitment: Eq + Hash + Clone;
 
     fn get_rotation(&self) -> Rotation;
     fn get_point(&self) -> F;
     fn get_eval(&self) -> F;
     fn get_commitment(&self) -> Self::Commitment;
+
+    /// Returns whether `other`, which opens the same commitment at the same
+    /// rotation, is known to have the same evaluation, without evaluating
+    /// anything expensive.
+    fn same_eval(&self, other: &Self) -> bool;
+}
+
+impl<'a, C: CurveAffine> Query<C::Scalar> for ProverQuery<'a, C> {
+    type Commitment = PolynomialPointer<'a, C>;
+
+    fn get_rotation(&self) -> Rotation {
+        self.rotation
+    }
+    fn get_point(&self) -> C::Scalar {
+        self.point
+    }
+    fn get_eval(&self) -> C::Scalar {
+        eval_polynomial(self.poly, self.get_point())
+    }
+    fn get_commitment(&self) -> Self::Commitment {
+        PolynomialPointer { poly: self.poly }
+    }
+    fn same_eval(&self, other: &Self) -> bool {
+        // Both queries evaluate the same polynomial, so they agree whenever
+        // they are at the same point.
+        self.point == other.point
+    }
+}
+
+impl<'r, C: CurveAffine> Query<C::Scalar> for VerifierQuery<'r, C> {
+    type Commitment = CommitmentReference<'r, C>;
+
+    fn get_rotation(&self) -> Rotation {
+        self.rotation
+    }
+    fn get_point(&self) -> C::Scalar {
+        self.point
+    }
+    fn get_eval(&self) -> C::Scalar {
+        self.eval
+    }
+    fn get_commitment(&self) -> Self::Commitment {
+        self.commitment
+    }
+    fn same_eval(&self, other: &Self) -> bool {
+        self.eval == other.eval
+    }
+}
+
+/// Removes the queries that open the same commitment at the same rotation as
+/// an earlier query, which happens when several arguments query the same
+/// column, and logs the savings under `target`. The remaining queries keep
+/// their original order.
+///
+/// Returns `Error::OpeningError` if a removed query disagrees with the kept
+/// one about the evaluation, since dropping it would leave its claim
+/// unchecked.
+///
+/// With GWC both the prover and the verifier must apply this pass, since
+/// duplicate queries would otherwise be batched into the opening argument.
+/// SHPLONK collapses them when it builds its rotation sets, so only its
+/// verifier applies the pass, to reject conflicting evaluations.
+fn dedup_queries<F: FieldExt, I, Q: Query<F>>(
+    target: &'static str,
+    queries: I,
+) -> Result<Vec<Q>, Error>
+where
+    I: IntoIterator<Item = Q>,
+{
+    let mut unique: Vec<Q> = vec![];
+    let mut indices = HashMap::new();
+    let mut removed = 0;
+    for query in queries {
+        let key = (query.get_rotation(), query.get_commitment());
+        if let Some(&index) = indices.get(&key) {
+            if !unique[index].same_eval(&query) {
+                return Err(Error::OpeningError);
+            }
+            removed += 1;
+        } else {
+            indices.insert(key, unique.len());
+            unique.push(query);
+        }
+    }
+
+    debug!(
+        target: target,
+        "event=dedup_queries queries={} unique={} removed={}",
+        unique.len() + removed,
+        unique.len(),
+        removed
+    );
+
+    Ok(unique)
 }
 
 #[cfg(test)]
@@ -144,7 +329,7 @@ mod tests {
     use crate::arithmetic::{eval_polynomial, FieldExt};
     use crate::pairing::bn256::{Bn256, Fr, G1Affine};
     use crate::poly::{
-        commitment::{Params, ParamsVerifier},
+        commitment::{CommitmentScheme, Params, ParamsVerifier},
         multiopen::{create_proof, verify_proof, Decider, ProverQuery, Query, VerifierQuery},
         Coeff, Polynomial, Rotation,
     };
@@ -168,6 +353,11 @@ mod tests {
 
     #[test]
     fn test_roundtrip() {
+        roundtrip(CommitmentScheme::KzgGwc);
+        roundtrip(CommitmentScheme::KzgShplonk);
+    }
+
+    fn roundtrip(scheme: CommitmentScheme) {
         use ff::Field;
         use group::Curve;
         use rand_core::OsRng;
@@ -181,7 +371,8 @@ mod tests {
 
         const K: u32 = 4;
 
-        let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+        let params: Params<G1Affine> =
+            Params::<G1Affine>::unsafe_setup::<Bn256>(K).with_scheme(scheme);
         let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
 
         let domain = EvaluationDomain::new(1, K);
@@ -273,18 +464,127 @@ mod tests {
             )
             .unwrap();
 
+            // The MSMs have a term per commitment, and at most two per opening
+            // point, plus the generator, however large the polynomials are.
+            assert!(guard.size() <= 3 + 2 * 2 + 1);
+
             // Should succeed.
             assert!(Decider::verify(&params_verifier, guard));
         }
     }
 
     #[test]
-    fn test_multiopen() {
+    fn test_dedup_queries() {
         const K: u32 = 3;
 
         let params = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
         let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
 
+        let a = rand_poly(params.n as usize, OsRng);
+        let b = rand_poly(params.n as usize, OsRng);
+        let a_commitment: G1Affine = params.commit(&a).into();
+        let b_commitment: G1Affine = params.commit(&b).into();
+
+        let x = Fr::random(OsRng);
+        let x_next = x * Fr::from(2);
+        let queries = [
+            (&a, &a_commitment, Rotation::cur(), x),
+            (&b, &b_commitment, Rotation::cur(), x),
+            (&a, &a_commitment, Rotation::cur(), x),
+            (&a, &a_commitment, Rotation::next(), x_next),
+            (&b, &b_commitment, Rotation::cur(), x),
+        ];
+        let prover_queries = queries
+            .iter()
+            .map(|&(poly, _, rotation, point)| ProverQuery {
+                poly,
+                point,
+                rotation,
+            });
+
+        let unique = super::dedup_queries(crate::logging::PROVER, prover_queries.clone()).unwrap();
+        assert_eq!(
+            unique.iter().map(|q| q.get_rotation()).collect::<Vec<_>>(),
+            vec![Rotation::cur(), Rotation::cur(), Rotation::next()]
+        );
+
+        // The prover opens the duplicated queries, and the verifier only the
+        // unique ones.
+        let proof = {
+            let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+            create_proof(&params, &mut transcript, prover_queries).unwrap();
+            transcript.finalize()
+        };
+        let verifier_queries = queries
+            .iter()
+            .take(2)
+            .chain(queries.iter().skip(3).take(1))
+            .map(|&(poly, commitment, rotation, point)| {
+                VerifierQuery::new_commitment(
+                    commitment,
+                    point,
+                    rotation,
+                    eval_polynomial(poly, point),
+                )
+            })
+            .collect::<Vec<_>>();
+        let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
+        let pair = verify_proof(&params_verifier, &mut transcript, verifier_queries).unwrap();
+        assert!(Decider::verify(&params_verifier, pair));
+    }
+
+    #[test]
+    fn test_dedup_queries_conflicting_evals() {
+        const K: u32 = 3;
+
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+        let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+
+        let a = rand_poly(params.n as usize, OsRng);
+        let a_commitment: G1Affine = params.commit(&a).into();
+        let x = Fr::random(OsRng);
+        let eval = eval_polynomial(&a, x);
+
+        let proof = {
+            let mut transcript = Blake2bWrite::<_, G1Affine, Challenge255<_>>::init(vec![]);
+            create_proof(
+                &params,
+                &mut transcript,
+                Some(ProverQuery {
+                    poly: &a,
+                    point: x,
+                    rotation: Rotation::cur(),
+                }),
+            )
+            .unwrap();
+            transcript.finalize()
+        };
+
+        // The second query claims another evaluation of the same commitment
+        // at the same point, and must not be dropped unchecked.
+        let verifier_queries = vec![
+            VerifierQuery::new_commitment(&a_commitment, x, Rotation::cur(), eval),
+            VerifierQuery::new_commitment(&a_commitment, x, Rotation::cur(), eval + Fr::one()),
+        ];
+        let mut transcript = Blake2bRead::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
+        assert!(matches!(
+            verify_proof(&params_verifier, &mut transcript, verifier_queries),
+            Err(crate::poly::Error::OpeningError)
+        ));
+    }
+
+    #[test]
+    fn test_multiopen() {
+        multiopen(CommitmentScheme::KzgGwc);
+        multiopen(CommitmentScheme::KzgShplonk);
+    }
+
+    fn multiopen(scheme: CommitmentScheme) {
+        const K: u32 = 3;
+
+        let params = Params::<G1Affine>::unsafe_setup::<Bn256>(K).with_scheme(scheme);
+        let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+
         let rotation_sets_init = vec![
             vec![1i32, 2, 3],
             vec![2, 3, 4],
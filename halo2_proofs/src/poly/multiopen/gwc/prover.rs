@@ -1,7 +1,8 @@
 use super::{construct_intermediate_sets, ChallengeV, Query};
-use crate::arithmetic::{eval_polynomial, kate_division, CurveAffine, FieldExt};
+use crate::arithmetic::{kate_division, CurveAffine, FieldExt};
+use crate::logging::PROVER;
+use crate::poly::multiopen::dedup_queries;
 use crate::poly::multiopen::ProverQuery;
-use crate::poly::Rotation;
 use crate::poly::{commitment::Params, Coeff, Polynomial};
 use crate::transcript::{EncodedChallenge, TranscriptWrite};
 
@@ -20,6 +21,8 @@ where
     I: IntoIterator<Item = ProverQuery<'a, C>> + Clone,
 {
     let v: ChallengeV<_> = transcript.squeeze_challenge_scalar();
+    let queries = dedup_queries(PROVER, queries)
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "conflicting queries"))?;
     let commitment_data = construct_intermediate_sets(queries);
 
     let zero = || Polynomial::<C::Scalar, Coeff> {
@@ -50,32 +53,3 @@ where
     }
     Ok(())
 }
-
-#[doc(hidden)]
-#[derive(Copy, Clone, Debug)]
-pub struct PolynomialPointer<'a, C: CurveAffine> {
-    poly: &'a Polynomial<C::Scalar, Coeff>,
-}
-
-impl<'a, C: CurveAffine> PartialEq for PolynomialPointer<'a, C> {
-    fn eq(&self, other: &Self) -> bool {
-        std::ptr::eq(self.poly, other.poly)
-    }
-}
-
-impl<'a, C: CurveAffine> Query<C::Scalar> for ProverQuery<'a, C> {
-    type Commitment = PolynomialPointer<'a, C>;
-
-    fn get_point(&self) -> C::Scalar {
-        self.point
-    }
-    fn get_rotation(&self) -> Rotation {
-        self.rotation
-    }
-    fn get_eval(&self) -> C::Scalar {
-        eval_polynomial(self.poly, self.get_point())
-    }
-    fn get_commitment(&self) -> Self::Commitment {
-        PolynomialPointer { poly: self.poly }
-    }
-}
@@ -1,6 +1,7 @@
 use super::{construct_intermediate_sets, ChallengeU, ChallengeV};
 use crate::arithmetic::{eval_polynomial, lagrange_interpolate, CurveAffine, FieldExt};
-use crate::poly::Rotation;
+use crate::logging::VERIFIER;
+use crate::poly::multiopen::dedup_queries;
 use crate::poly::{
     commitment::{Params, ParamsVerifier},
     multiopen::{CommitmentReference, Query, VerifierQuery},
@@ -16,13 +17,12 @@ use subtle::Choice;
 /// Verify a multi-opening proof
 pub fn verify_proof<
     'r,
-    'params: 'r,
     I,
     C: MultiMillerLoop,
     E: EncodedChallenge<C::G1Affine>,
     T: TranscriptRead<C::G1Affine, E>,
 >(
-    params: &'params ParamsVerifier<C>,
+    params: &ParamsVerifier<C>,
     transcript: &mut T,
     queries: I,
 ) -> Result<PairMSM<C::G1Affine>, Error>
@@ -32,7 +32,7 @@ where
     let v: ChallengeV<_> = transcript.squeeze_challenge_scalar();
     let u: ChallengeU<_> = transcript.squeeze_challenge_scalar();
 
-    let commitment_data = construct_intermediate_sets(queries);
+    let commitment_data = construct_intermediate_sets(dedup_queries(VERIFIER, queries)?);
 
     let mut commitment_multi = params.empty_msm();
     let mut eval_multi = C::Scalar::zero();
@@ -79,30 +79,11 @@ where
         eval_multi += eval_batch;
     }
 
-    let mut left = params.empty_msm();
-    left.add_msm(&witness);
-
-    let mut right = params.empty_msm();
+    // The accumulated MSMs become the two sides of the pairing check as they
+    // are, rather than being copied into new ones.
+    let mut right = commitment_multi;
     right.add_msm(&witness_with_aux);
-    right.add_msm(&commitment_multi);
     right.append_term(eval_multi, -params.g1);
 
-    Ok(PairMSM::with(left, right))
-}
-
-impl<'a, 'b, C: CurveAffine> Query<C::Scalar> for VerifierQuery<'a, C> {
-    type Commitment = CommitmentReference<'a, C>;
-
-    fn get_point(&self) -> C::Scalar {
-        self.point
-    }
-    fn get_rotation(&self) -> Rotation {
-        self.rotation
-    }
-    fn get_eval(&self) -> C::Scalar {
-        self.eval
-    }
-    fn get_commitment(&self) -> Self::Commitment {
-        self.commitment
-    }
+    Ok(PairMSM::with(witness, right))
 }
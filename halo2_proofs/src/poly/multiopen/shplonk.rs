@@ -199,6 +199,9 @@ mod tests {
         fn get_commitment(&self) -> Self::Commitment {
             self.commitment
         }
+        fn same_eval(&self, other: &Self) -> bool {
+            self.eval == other.eval
+        }
     }
 
     #[test]
@@ -5,8 +5,8 @@ use crate::arithmetic::{
     eval_polynomial, evaluate_vanishing_polynomial, kate_division, lagrange_interpolate,
     CurveAffine, FieldExt,
 };
-use crate::poly::multiopen::ProverQuery;
-use crate::poly::{commitment::Params, Coeff, Error, Polynomial, Rotation};
+use crate::poly::multiopen::{PolynomialPointer, ProverQuery};
+use crate::poly::{commitment::Params, Coeff, Error, Polynomial};
 use crate::transcript::{ChallengeScalar, EncodedChallenge, Transcript, TranscriptWrite};
 
 use ff::Field;
@@ -16,10 +16,12 @@ use std::io;
 use std::marker::PhantomData;
 use std::ops::MulAssign;
 
-fn div_by_vanishing<F: FieldExt>(poly: Polynomial<F, Coeff>, roots: &[F]) -> Vec<F> {
+fn div_by_vanishing<F: FieldExt>(mut poly: Polynomial<F, Coeff>, roots: &[F]) -> Vec<F> {
     let poly = roots
         .iter()
-        .fold(poly.values, |poly, point| kate_division(&poly, *point));
+        .fold(std::mem::take(&mut poly.values), |poly, point| {
+            kate_division(&poly, *point)
+        });
 
     poly
 }
@@ -231,32 +233,3 @@ where
 
     Ok(())
 }
-
-#[doc(hidden)]
-#[derive(Copy, Clone, Debug)]
-pub struct PolynomialPointer<'a, C: CurveAffine> {
-    poly: &'a Polynomial<C::Scalar, Coeff>,
-}
-
-impl<'a, C: CurveAffine> PartialEq for PolynomialPointer<'a, C> {
-    fn eq(&self, other: &Self) -> bool {
-        std::ptr::eq(self.poly, other.poly)
-    }
-}
-
-impl<'a, C: CurveAffine> Query<C::Scalar> for ProverQuery<'a, C> {
-    type Commitment = PolynomialPointer<'a, C>;
-
-    fn get_rotation(&self) -> Rotation {
-        self.rotation
-    }
-    fn get_point(&self) -> C::Scalar {
-        self.point
-    }
-    fn get_eval(&self) -> C::Scalar {
-        eval_polynomial(self.poly, self.get_point())
-    }
-    fn get_commitment(&self) -> Self::Commitment {
-        PolynomialPointer { poly: self.poly }
-    }
-}
@@ -3,11 +3,13 @@ use crate::arithmetic::{
     eval_polynomial, evaluate_vanishing_polynomial, lagrange_interpolate, CurveAffine, Engine,
     FieldExt, MillerLoopResult, MultiMillerLoop,
 };
+use crate::logging::VERIFIER;
+use crate::poly::multiopen::dedup_queries;
 use crate::poly::{
     commitment::{Params, ParamsVerifier},
     msm::{PairMSM, PreMSM, ProjectiveMSM, MSM},
     multiopen::{CommitmentReference, Query, VerifierQuery},
-    Rotation, {Coeff, Error, Polynomial},
+    {Coeff, Error, Polynomial},
 };
 use crate::transcript::{EncodedChallenge, TranscriptRead};
 
@@ -22,20 +24,19 @@ use subtle::Choice;
 /// Verify a multi-opening proof
 pub fn verify_proof<
     'r,
-    'params: 'r,
     I,
     C: MultiMillerLoop,
     E: EncodedChallenge<C::G1Affine>,
     T: TranscriptRead<C::G1Affine, E>,
 >(
-    params: &'params ParamsVerifier<C>,
+    params: &ParamsVerifier<C>,
     transcript: &mut T,
     queries: I,
 ) -> Result<PairMSM<C::G1Affine>, Error>
 where
     I: IntoIterator<Item = VerifierQuery<'r, C::G1Affine>> + Clone,
 {
-    let intermediate_sets = construct_intermediate_sets(queries);
+    let intermediate_sets = construct_intermediate_sets(dedup_queries(VERIFIER, queries)?);
     let (rotation_sets, super_point_set) = (
         intermediate_sets.rotation_sets,
         intermediate_sets.super_point_set,
@@ -44,9 +45,13 @@ where
     let y: ChallengeY<_> = transcript.squeeze_challenge_scalar();
     let v: ChallengeV<_> = transcript.squeeze_challenge_scalar();
 
-    let h1 = transcript.read_point().map_err(|_| Error::SamplingError)?;
+    let h1 = transcript
+        .read_non_identity_point()
+        .map_err(|_| Error::SamplingError)?;
     let u: ChallengeU<_> = transcript.squeeze_challenge_scalar();
-    let h2 = transcript.read_point().map_err(|_| Error::SamplingError)?;
+    let h2 = transcript
+        .read_non_identity_point()
+        .map_err(|_| Error::SamplingError)?;
 
     let (mut z_0_diff_inverse, mut z_0) = (C::Scalar::zero(), C::Scalar::zero());
     let (mut outer_msm, mut r_outer_acc) = (PreMSM::<C>::new(), C::Scalar::zero());
@@ -97,25 +102,5 @@ where
     let mut left = params.empty_msm();
     left.append_term(C::Scalar::one(), h2);
 
-    let mut right = params.empty_msm();
-    right.add_msm(&outer_msm);
-
-    Ok(PairMSM::with(left, right))
-}
-
-impl<'a, 'b, C: CurveAffine> Query<C::Scalar> for VerifierQuery<'a, C> {
-    type Commitment = CommitmentReference<'a, C>;
-
-    fn get_rotation(&self) -> Rotation {
-        self.rotation
-    }
-    fn get_point(&self) -> C::Scalar {
-        self.point
-    }
-    fn get_eval(&self) -> C::Scalar {
-        self.eval
-    }
-    fn get_commitment(&self) -> Self::Commitment {
-        self.commitment
-    }
+    Ok(PairMSM::with(left, outer_msm))
 }
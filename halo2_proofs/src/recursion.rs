@@ -0,0 +1,640 @@
+//! Chips for verifying proofs inside a circuit over BN254.
+//!
+//! [`VerifierChip::verify_proof`] replays the verifier of a SHPLONK proof
+//! created with a [`PoseidonWrite`] transcript, up to its final pairing
+//! check. The check is returned as an [`AssignedAccumulator`]; the outer
+//! circuit exposes its limbs as instances, and whoever verifies the outer
+//! proof checks the pairing with [`accumulator_from_limbs`].
+//!
+//! All the chips share a single [`RecursionConfig`] and lay out their rows
+//! in one region, through a [`Context`]:
+//!
+//! ```ignore
+//! fn synthesize(&self, config: RecursionConfig, mut layouter: impl Layouter<Fr>) -> Result<(), Error> {
+//!     config.load_range_table(&mut layouter)?;
+//!     let accumulator = layouter.assign_region(
+//!         || "verifier",
+//!         |region| {
+//!             let mut ctx = Context::new(region);
+//!             let chip = VerifierChip::new(config.clone());
+//!             let main_gate = chip.main_gate();
+//!             let instances = self.instances.iter()
+//!                 .map(|value| main_gate.assign(&mut ctx, *value))
+//!                 .collect::<Result<Vec<_>, Error>>()?;
+//!             chip.verify_proof(&mut ctx, &self.params, &self.vk, &[&instances], self.proof.as_deref())
+//!         },
+//!     )?;
+//!     for (i, limb) in accumulator.limbs().iter().enumerate() {
+//!         layouter.constrain_instance(limb.cell(), config.instance, i)?;
+//!     }
+//!     Ok(())
+//! }
+//! ```
+//!
+//! [`PoseidonWrite`]: crate::transcript::poseidon::PoseidonWrite
+
+use pairing::bn256::{Fq, Fr, G1Affine};
+#[cfg(feature = "multicore")]
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+
+use crate::arithmetic::CurveAffine;
+use crate::circuit::{Layouter, Region};
+use crate::plonk::{
+    Accumulator, Advice, Column, ConstraintSystem, Error, Expression, Fixed, Selector, TableColumn,
+};
+use crate::poly::Rotation;
+
+mod ecc;
+mod integer;
+mod main_gate;
+mod transcript;
+mod verifier;
+
+pub use ecc::{AssignedPoint, Base, EccChip};
+pub use integer::{AssignedInteger, IntegerChip, LIMB_BITS, NUM_LIMBS};
+pub use main_gate::{AssignedValue, MainGate, LOOKUP_BITS};
+pub use transcript::{PoseidonChip, TranscriptChip};
+pub use verifier::VerifierChip;
+
+use integer::{
+    fq_from_native_limbs, fq_to_native_limbs, modulus, CARRY_BITS, NUM_CARRIES, QUOTIENT_OFFSET,
+};
+use main_gate::pow2;
+
+/// The number of advice columns of the main gate.
+pub(crate) const WIDTH: usize = 5;
+
+/// The columns, gates and lookups shared by the recursion chips.
+#[derive(Clone, Debug)]
+pub struct RecursionConfig {
+    advice: Vec<Column<Advice>>,
+    q_linear: Vec<Column<Fixed>>,
+    q_ab: Column<Fixed>,
+    q_cd: Column<Fixed>,
+    q_constant: Column<Fixed>,
+    q_next: Column<Fixed>,
+    q_range: Selector,
+    q_integer: Selector,
+    range_table: TableColumn,
+}
+
+impl RecursionConfig {
+    /// Configures the columns, gates and lookups of the recursion chips.
+    ///
+    /// The range table has `2^LOOKUP_BITS` rows, so circuits using it need
+    /// `k > LOOKUP_BITS`.
+    pub fn configure(meta: &mut ConstraintSystem<Fr>) -> Self {
+        let advice: Vec<_> = (0..WIDTH).map(|_| meta.advice_column()).collect();
+        let q_linear: Vec<_> = (0..WIDTH).map(|_| meta.fixed_column()).collect();
+        let q_ab = meta.fixed_column();
+        let q_cd = meta.fixed_column();
+        let q_constant = meta.fixed_column();
+        let q_next = meta.fixed_column();
+        let q_range = meta.complex_selector();
+        let q_integer = meta.selector();
+        let range_table = meta.lookup_table_column();
+
+        for column in advice.iter() {
+            meta.enable_equality(*column);
+        }
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
+        meta.create_gate("main gate", |meta| {
+            let a: Vec<_> = advice
+                .iter()
+                .map(|column| meta.query_advice(*column, Rotation::cur()))
+                .collect();
+            let e_next = meta.query_advice(advice[WIDTH - 1], Rotation::next());
+            let q_linear: Vec<_> = q_linear
+                .iter()
+                .map(|column| meta.query_fixed(*column, Rotation::cur()))
+                .collect();
+            let q_ab = meta.query_fixed(q_ab, Rotation::cur());
+            let q_cd = meta.query_fixed(q_cd, Rotation::cur());
+            let q_constant = meta.query_fixed(q_constant, Rotation::cur());
+            let q_next = meta.query_fixed(q_next, Rotation::cur());
+
+            let linear = a
+                .iter()
+                .zip(q_linear.into_iter())
+                .map(|(a, q)| q * a.clone())
+                .reduce(|acc, term| acc + term)
+                .unwrap();
+            vec![
+                linear
+                    + q_ab * a[0].clone() * a[1].clone()
+                    + q_cd * a[2].clone() * a[3].clone()
+                    + q_constant
+                    + q_next * e_next,
+            ]
+        });
+
+        for column in advice[..WIDTH - 1].iter() {
+            meta.lookup("range check", |meta| {
+                let q_range = meta.query_selector(q_range);
+                let value = meta.query_advice(*column, Rotation::cur());
+                vec![(q_range * value, range_table)]
+            });
+        }
+
+        meta.create_gate("integer", |meta| {
+            let q_integer = meta.query_selector(q_integer);
+            let mut rows = (0..integer::GATE_ROWS).map(|row| {
+                advice[..integer::NUM_LIMBS]
+                    .iter()
+                    .map(|column| meta.query_advice(*column, Rotation(row as i32)))
+                    .collect::<Vec<_>>()
+            });
+            let (a, b, c, r, k) = (
+                rows.next().unwrap(),
+                rows.next().unwrap(),
+                rows.next().unwrap(),
+                rows.next().unwrap(),
+                rows.next().unwrap(),
+            );
+            let carries: Vec<_> = rows.flatten().take(NUM_CARRIES).collect();
+            let q: Vec<Fr> = modulus().iter().map(|limb| Fr::from(*limb)).collect();
+            let carry_offset = pow2(CARRY_BITS - 1);
+
+            // Column j of a b + c + K q - r - k q, plus the carry into it,
+            // equals the carry out of it, shifted by the limb size.
+            (0..=NUM_CARRIES)
+                .map(|j| {
+                    let mut sum: Option<Expression<Fr>> = None;
+                    let mut push = |term: Expression<Fr>| {
+                        sum = Some(match sum.take() {
+                            Some(sum) => sum + term,
+                            None => term,
+                        });
+                    };
+                    for (i, (a, k)) in a.iter().zip(k.iter()).enumerate() {
+                        if j >= i && j - i < NUM_LIMBS {
+                            push(a.clone() * b[j - i].clone());
+                            push(k.clone() * -q[j - i]);
+                        }
+                    }
+                    if j < NUM_LIMBS {
+                        push(c[j].clone() - r[j].clone());
+                        push(Expression::Constant(Fr::from(QUOTIENT_OFFSET) * q[j]));
+                    }
+                    if j > 0 {
+                        push(Expression::Constant(-carry_offset));
+                        push(carries[j - 1].clone());
+                    }
+                    if j < NUM_CARRIES {
+                        push(Expression::Constant(carry_offset * pow2(LIMB_BITS)));
+                        push(carries[j].clone() * -pow2(LIMB_BITS));
+                    }
+                    q_integer.clone() * sum.unwrap()
+                })
+                .collect::<Vec<_>>()
+        });
+
+        RecursionConfig {
+            advice,
+            q_linear,
+            q_ab,
+            q_cd,
+            q_constant,
+            q_next,
+            q_range,
+            q_integer,
+            range_table,
+        }
+    }
+
+    /// Loads the table of the range checks.
+    pub fn load_range_table(&self, layouter: &mut impl Layouter<Fr>) -> Result<(), Error> {
+        layouter.assign_table(
+            || "range table",
+            |mut table| {
+                #[cfg(feature = "multicore")]
+                {
+                    table.assign_table_from_par_iter(
+                        || "range",
+                        &[self.range_table],
+                        (0..(1 << LOOKUP_BITS))
+                            .into_par_iter()
+                            .map(|value| [Fr::from(value as u64)]),
+                    )
+                }
+                #[cfg(not(feature = "multicore"))]
+                {
+                    table.assign_table_from_iter(
+                        || "range",
+                        &[self.range_table],
+                        (0..(1u64 << LOOKUP_BITS)).map(|value| [Fr::from(value)]),
+                    )
+                }
+            },
+        )
+    }
+}
+
+/// The region in which the recursion chips lay out their rows, and the next
+/// free row in it.
+#[derive(Debug)]
+pub struct Context<'a> {
+    region: Region<'a, Fr>,
+    offset: usize,
+}
+
+impl<'a> Context<'a> {
+    /// Starts laying out rows at the beginning of `region`.
+    pub fn new(region: Region<'a, Fr>) -> Self {
+        Context { region, offset: 0 }
+    }
+
+    /// Returns the number of rows laid out so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// An [`Accumulator`] assigned in the circuit.
+#[derive(Clone, Debug)]
+pub struct AssignedAccumulator {
+    /// The point paired with `[s]_2`.
+    pub lhs: AssignedPoint,
+    /// The point paired with `[1]_2`.
+    pub rhs: AssignedPoint,
+}
+
+impl AssignedAccumulator {
+    /// Returns the limbs of the coordinates of `lhs` and `rhs`, in the order
+    /// [`accumulator_limbs`] returns them.
+    pub fn limbs(&self) -> Vec<AssignedValue> {
+        [&self.lhs, &self.rhs]
+            .iter()
+            .flat_map(|point| {
+                point
+                    .x()
+                    .limbs()
+                    .iter()
+                    .chain(point.y().limbs().iter())
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .collect()
+    }
+
+    /// Returns the accumulator.
+    pub fn value(&self) -> Option<Accumulator<G1Affine>> {
+        Some(Accumulator {
+            lhs: self.lhs.value()?,
+            rhs: self.rhs.value()?,
+        })
+    }
+}
+
+/// Returns the limbs of the coordinates of an accumulator, as exposed by a
+/// circuit constraining [`AssignedAccumulator::limbs`] to its instances, or
+/// `None` if either point is the identity.
+pub fn accumulator_limbs(accumulator: &Accumulator<G1Affine>) -> Option<Vec<Fr>> {
+    let mut limbs = vec![];
+    for point in [accumulator.lhs, accumulator.rhs].iter() {
+        let coordinates = Option::from(point.coordinates())?;
+        limbs.extend(fq_to_native_limbs(coordinates.x()));
+        limbs.extend(fq_to_native_limbs(coordinates.y()));
+    }
+    Some(limbs)
+}
+
+/// Recovers an accumulator from the limbs returned by [`accumulator_limbs`],
+/// returning `None` if they don't encode two points of G1.
+pub fn accumulator_from_limbs(limbs: &[Fr]) -> Option<Accumulator<G1Affine>> {
+    if limbs.len() != 4 * NUM_LIMBS {
+        return None;
+    }
+    let mut points = limbs.chunks(2 * NUM_LIMBS).map(|limbs| {
+        let x: Fq = fq_from_native_limbs(&limbs[..NUM_LIMBS])?;
+        let y: Fq = fq_from_native_limbs(&limbs[NUM_LIMBS..])?;
+        Option::from(G1Affine::from_xy(x, y))
+    });
+    let lhs = points.next()??;
+    let rhs = points.next()??;
+    Some(Accumulator { lhs, rhs })
+}
+
+#[cfg(test)]
+mod tests {
+    use ff::Field;
+    use group::{prime::PrimeCurveAffine, Curve};
+    use pairing::bn256::{Fq, Fr, G1Affine};
+    use rand_core::OsRng;
+
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::circuit::SimpleFloorPlanner;
+    use crate::dev::MockProver;
+    use crate::plonk::{create_proof, keygen_pk, keygen_vk, Circuit, Instance, VerifyingKey};
+    use crate::poly::commitment::{CommitmentScheme, Params, ParamsVerifier};
+    use crate::transcript::poseidon::{self, PoseidonChallenge, PoseidonWrite, Spec};
+    use pairing::bn256::Bn256;
+
+    type Synthesize = fn(&RecursionConfig, &mut Context<'_>) -> Result<(), Error>;
+
+    #[derive(Clone)]
+    struct TestCircuit(Synthesize);
+
+    impl Circuit<Fr> for TestCircuit {
+        type Config = RecursionConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            self.clone()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            RecursionConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.load_range_table(&mut layouter)?;
+            layouter.assign_region(
+                || "test",
+                |region| {
+                    let mut ctx = Context::new(region);
+                    (self.0)(&config, &mut ctx)
+                },
+            )
+        }
+    }
+
+    fn run(k: u32, synthesize: Synthesize) {
+        let prover = MockProver::run(k, &TestCircuit(synthesize), vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+    }
+
+    #[test]
+    fn integer_arithmetic() {
+        run(17, |config, ctx| {
+            let chip = IntegerChip::new(config.clone());
+            let (a, b, c) = (Fq::random(OsRng), Fq::random(OsRng), Fq::random(OsRng));
+            let a_assigned = chip.assign_canonical(ctx, Some(a))?;
+            let b_assigned = chip.assign(ctx, Some(b))?;
+            let c_assigned = chip.assign(ctx, Some(c))?;
+
+            let r = chip.mul_add(ctx, &a_assigned, &b_assigned, &c_assigned)?;
+            chip.assert_constant(ctx, &chip.reduce(ctx, &r)?, a * b + c)?;
+            let r = chip.sub(ctx, &a_assigned, &b_assigned)?;
+            chip.assert_constant(ctx, &chip.reduce(ctx, &r)?, a - b)?;
+            let r = chip.div(ctx, &a_assigned, &b_assigned)?;
+            chip.assert_constant(ctx, &chip.reduce(ctx, &r)?, a * b.invert().unwrap())?;
+
+            let halves = chip.to_native_halves(ctx, &a_assigned)?;
+            let limbs = fq_to_native_limbs(&a);
+            let shift = pow2(LIMB_BITS);
+            chip.main_gate()
+                .assert_constant(ctx, &halves[0], limbs[0] + limbs[1] * shift)?;
+            chip.main_gate()
+                .assert_constant(ctx, &halves[1], limbs[2] + limbs[3] * shift)
+        });
+    }
+
+    #[test]
+    fn point_addition() {
+        run(17, |config, ctx| {
+            let chip = EccChip::new(config.clone());
+            let p = (G1Affine::generator() * Fr::random(OsRng)).to_affine();
+            let q = (G1Affine::generator() * Fr::random(OsRng)).to_affine();
+            let p_assigned = chip.assign_point(ctx, Some(p))?;
+            let q_assigned = chip.assign_point(ctx, Some(q))?;
+
+            let sum = chip.add(ctx, &p_assigned, &q_assigned)?;
+            chip.assert_constant(ctx, &chip.reduce(ctx, &sum)?, (p + q).to_affine())?;
+            let double = chip.double(ctx, &p_assigned)?;
+            chip.assert_constant(ctx, &chip.reduce(ctx, &double)?, (p + p).to_affine())
+        });
+    }
+
+    #[test]
+    fn poseidon_permutation() {
+        run(17, |config, ctx| {
+            let spec = Spec::<Fr>::new(
+                poseidon::WIDTH,
+                poseidon::FULL_ROUNDS,
+                poseidon::PARTIAL_ROUNDS,
+            );
+            let main_gate = MainGate::new(config.clone());
+            let chip = PoseidonChip::new(main_gate.clone(), spec.clone());
+
+            let mut state: Vec<Fr> = (0..poseidon::WIDTH).map(|_| Fr::random(OsRng)).collect();
+            let assigned = state
+                .iter()
+                .map(|value| main_gate.assign(ctx, Some(*value)))
+                .collect::<Result<Vec<_>, Error>>()?;
+            let permuted = chip.permute(ctx, &assigned)?;
+            spec.permute(&mut state);
+            for (cell, value) in permuted.iter().zip(state.iter()) {
+                main_gate.assert_constant(ctx, cell, *value)?;
+            }
+            Ok(())
+        });
+    }
+
+    /// Constrains `b = a^2` on the first row, and exposes `b` as an instance.
+    #[derive(Clone, Default)]
+    struct SquareCircuit {
+        a: Option<Fr>,
+    }
+
+    impl Circuit<Fr> for SquareCircuit {
+        type Config = (Selector, Column<Advice>, Column<Advice>, Column<Instance>);
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            Self::default()
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            let (q, a, b) = (meta.selector(), meta.advice_column(), meta.advice_column());
+            let instance = meta.instance_column();
+            meta.enable_equality(b);
+            meta.enable_equality(instance);
+            meta.create_gate("square", |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(a, Rotation::cur());
+                let b = meta.query_advice(b, Rotation::cur());
+                vec![q * (a.clone() * a - b)]
+            });
+            (q, a, b, instance)
+        }
+
+        fn synthesize(
+            &self,
+            (q, a, b, instance): Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            let cell = layouter.assign_region(
+                || "square",
+                |mut region| {
+                    q.enable(&mut region, 0)?;
+                    region.assign_advice(|| "a", a, 0, || self.a.ok_or(Error::Synthesis))?;
+                    region.assign_advice(
+                        || "b",
+                        b,
+                        0,
+                        || self.a.map(|a| a.square()).ok_or(Error::Synthesis),
+                    )
+                },
+            )?;
+            layouter.constrain_instance(cell.cell(), instance, 0)
+        }
+    }
+
+    /// Verifies a proof of [`SquareCircuit`] in-circuit, and records the
+    /// accumulator it reduces to.
+    struct VerifierCircuit<'a> {
+        params: &'a ParamsVerifier<Bn256>,
+        vk: &'a VerifyingKey<G1Affine>,
+        instance: Option<Fr>,
+        proof: Option<Vec<u8>>,
+        accumulator: Mutex<Option<Accumulator<G1Affine>>>,
+    }
+
+    impl<'a> Circuit<Fr> for VerifierCircuit<'a> {
+        type Config = RecursionConfig;
+        type FloorPlanner = SimpleFloorPlanner;
+
+        fn without_witnesses(&self) -> Self {
+            VerifierCircuit {
+                params: self.params,
+                vk: self.vk,
+                instance: None,
+                proof: None,
+                accumulator: Mutex::new(None),
+            }
+        }
+
+        fn configure(meta: &mut ConstraintSystem<Fr>) -> Self::Config {
+            RecursionConfig::configure(meta)
+        }
+
+        fn synthesize(
+            &self,
+            config: Self::Config,
+            mut layouter: impl Layouter<Fr>,
+        ) -> Result<(), Error> {
+            config.load_range_table(&mut layouter)?;
+            let accumulator = layouter.assign_region(
+                || "verifier",
+                |region| {
+                    let mut ctx = Context::new(region);
+                    let chip = VerifierChip::new(config.clone());
+                    let instance = chip.main_gate().assign(&mut ctx, self.instance)?;
+                    chip.verify_proof(
+                        &mut ctx,
+                        self.params,
+                        self.vk,
+                        &[&[instance]],
+                        self.proof.as_deref(),
+                    )
+                },
+            )?;
+            *self.accumulator.lock().unwrap() = accumulator.value();
+            Ok(())
+        }
+    }
+
+    /// Proves [`SquareCircuit`], lets `tamper` alter the proof, and returns
+    /// whether the accumulator of the in-circuit verifier passes the pairing
+    /// check.
+    fn verify_in_circuit(tamper: fn(&mut Vec<u8>)) -> bool {
+        const K: u32 = 4;
+
+        let params =
+            Params::<G1Affine>::unsafe_setup::<Bn256>(K).with_scheme(CommitmentScheme::KzgShplonk);
+        let params_verifier: ParamsVerifier<Bn256> = params.verifier(1).unwrap();
+        let vk = keygen_vk(&params, &SquareCircuit::default()).unwrap();
+        let pk = keygen_pk(&params, vk, &SquareCircuit::default()).unwrap();
+
+        let a = Fr::from(3);
+        let instance = a.square();
+        let mut proof = {
+            let mut transcript = PoseidonWrite::<_, G1Affine, PoseidonChallenge<_>>::init(vec![]);
+            create_proof(
+                &params,
+                &pk,
+                &[SquareCircuit { a: Some(a) }],
+                &[&[&[instance]]],
+                OsRng,
+                &mut transcript,
+            )
+            .unwrap();
+            transcript.finalize()
+        };
+        tamper(&mut proof);
+
+        let circuit = VerifierCircuit {
+            params: &params_verifier,
+            vk: pk.get_vk(),
+            instance: Some(instance),
+            proof: Some(proof),
+            accumulator: Mutex::new(None),
+        };
+        let prover = MockProver::run(20, &circuit, vec![]).unwrap();
+        assert_eq!(prover.verify(), Ok(()));
+
+        let accumulator = circuit.accumulator.lock().unwrap().unwrap();
+        accumulator.decide(&params_verifier)
+    }
+
+    #[test]
+    fn verify_proof_in_circuit() {
+        assert!(verify_in_circuit(|_| {}));
+    }
+
+    #[test]
+    fn verify_tampered_proof_in_circuit() {
+        // Alters the last evaluation, which precedes the two points of the
+        // SHPLONK opening, so that the proof is still well formed.
+        assert!(!verify_in_circuit(|proof| {
+            let len = proof.len();
+            proof[len - 3 * 32] ^= 1;
+        }));
+    }
+
+    #[test]
+    fn verify_gwc_proof_in_circuit() {
+        let params =
+            Params::<G1Affine>::unsafe_setup::<Bn256>(4).with_scheme(CommitmentScheme::KzgGwc);
+        let params_verifier: ParamsVerifier<Bn256> = params.verifier(1).unwrap();
+        let vk = keygen_vk(&params, &SquareCircuit::default()).unwrap();
+
+        let circuit = VerifierCircuit {
+            params: &params_verifier,
+            vk: &vk,
+            instance: Some(Fr::from(9)),
+            proof: None,
+            accumulator: Mutex::new(None),
+        };
+        assert!(matches!(
+            MockProver::run(20, &circuit, vec![]),
+            Err(Error::Opening)
+        ));
+    }
+
+    #[test]
+    fn accumulator_limbs_roundtrip() {
+        let accumulator = Accumulator {
+            lhs: (G1Affine::generator() * Fr::random(OsRng)).to_affine(),
+            rhs: (G1Affine::generator() * Fr::random(OsRng)).to_affine(),
+        };
+        let limbs = accumulator_limbs(&accumulator).unwrap();
+        assert_eq!(limbs.len(), 4 * NUM_LIMBS);
+        let recovered = accumulator_from_limbs(&limbs).unwrap();
+        assert_eq!(recovered.lhs, accumulator.lhs);
+        assert_eq!(recovered.rhs, accumulator.rhs);
+
+        let mut limbs = limbs;
+        limbs[0] += Fr::one();
+        assert!(accumulator_from_limbs(&limbs).is_none());
+    }
+}
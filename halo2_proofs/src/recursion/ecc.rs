@@ -0,0 +1,253 @@
+//! Arithmetic over the G1 group of BN254, with emulated coordinates.
+//!
+//! Points are added with incomplete formulas, which can't represent the
+//! identity and fail when adding a point to itself or its inverse. The
+//! failure is a constraint that can't be satisfied, so it costs completeness
+//! but not soundness. [`EccChip::msm`] starts its accumulator at an
+//! auxiliary point, which makes these cases negligibly likely for points
+//! that don't depend on it.
+
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, Curve};
+use pairing::bn256::{Fq, Fr, G1Affine};
+
+use super::integer::{AssignedInteger, IntegerChip};
+use super::main_gate::{AssignedValue, MainGate};
+use super::{Context, RecursionConfig};
+use crate::arithmetic::{CurveAffine, FieldExt};
+use crate::plonk::Error;
+
+/// A point of G1 assigned in the circuit. It can't be the identity.
+#[derive(Clone, Debug)]
+pub struct AssignedPoint {
+    x: AssignedInteger,
+    y: AssignedInteger,
+}
+
+impl AssignedPoint {
+    /// Returns the `x` coordinate.
+    pub fn x(&self) -> &AssignedInteger {
+        &self.x
+    }
+
+    /// Returns the `y` coordinate.
+    pub fn y(&self) -> &AssignedInteger {
+        &self.y
+    }
+
+    /// Returns the point, or the identity if its coordinates aren't on the
+    /// curve.
+    pub fn value(&self) -> Option<G1Affine> {
+        self.x
+            .value()
+            .zip(self.y.value())
+            .map(|(x, y)| Option::from(G1Affine::from_xy(x, y)).unwrap_or_else(G1Affine::identity))
+    }
+}
+
+/// The base of a term of an [`EccChip::msm`].
+#[derive(Clone, Debug)]
+pub enum Base {
+    /// An assigned point.
+    Assigned(AssignedPoint),
+    /// A constant point, which may be the identity.
+    Constant(G1Affine),
+}
+
+/// Returns the offset point at which [`EccChip::msm`] starts its
+/// accumulator.
+fn aux_generator() -> G1Affine {
+    let mut tag = [0u8; 64];
+    tag[..16].copy_from_slice(b"Halo2-Recursion-");
+    (G1Affine::generator() * Fr::from_bytes_wide(&tag)).to_affine()
+}
+
+/// A chip for arithmetic over G1.
+#[derive(Clone, Debug)]
+pub struct EccChip {
+    integer: IntegerChip,
+}
+
+impl EccChip {
+    /// Constructs an ECC chip.
+    pub fn new(config: RecursionConfig) -> Self {
+        EccChip {
+            integer: IntegerChip::new(config),
+        }
+    }
+
+    /// Returns the integer chip this chip is built on.
+    pub fn integer(&self) -> &IntegerChip {
+        &self.integer
+    }
+
+    /// Returns the main gate chip this chip is built on.
+    pub fn main_gate(&self) -> &MainGate {
+        self.integer.main_gate()
+    }
+
+    /// Assigns a point, constrained to be on the curve and to have reduced
+    /// coordinates. Returns [`Error::Synthesis`] if the point is the
+    /// identity.
+    pub fn assign_point(
+        &self,
+        ctx: &mut Context<'_>,
+        point: Option<G1Affine>,
+    ) -> Result<AssignedPoint, Error> {
+        let coordinates = match point {
+            Some(point) => {
+                let coordinates = Option::from(point.coordinates()).ok_or(Error::Synthesis)?;
+                Some(coordinates)
+            }
+            None => None,
+        };
+        let x = self
+            .integer
+            .assign_canonical(ctx, coordinates.as_ref().map(|c| *c.x()))?;
+        let y = self
+            .integer
+            .assign_canonical(ctx, coordinates.as_ref().map(|c| *c.y()))?;
+
+        // y^2 = x^3 + b
+        let y_square = self.integer.mul(ctx, &y, &y)?;
+        let x_square = self.integer.mul(ctx, &x, &x)?;
+        let b = self.integer.assign_constant(ctx, G1Affine::b())?;
+        self.integer
+            .assert_mul_add(ctx, &x_square, &x, &b, &y_square)?;
+
+        Ok(AssignedPoint { x, y })
+    }
+
+    /// Assigns a constant point. Returns [`Error::Synthesis`] if the point is
+    /// the identity.
+    pub fn assign_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        point: G1Affine,
+    ) -> Result<AssignedPoint, Error> {
+        let coordinates = Option::from(point.coordinates()).ok_or(Error::Synthesis)?;
+        Ok(AssignedPoint {
+            x: self.integer.assign_constant(ctx, *coordinates.x())?,
+            y: self.integer.assign_constant(ctx, *coordinates.y())?,
+        })
+    }
+
+    /// Constrains a point with reduced coordinates to equal a constant.
+    pub fn assert_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedPoint,
+        point: G1Affine,
+    ) -> Result<(), Error> {
+        let coordinates = Option::from(point.coordinates()).ok_or(Error::Synthesis)?;
+        self.integer.assert_constant(ctx, &a.x, *coordinates.x())?;
+        self.integer.assert_constant(ctx, &a.y, *coordinates.y())
+    }
+
+    /// Returns `a` with reduced coordinates.
+    pub fn reduce(&self, ctx: &mut Context<'_>, a: &AssignedPoint) -> Result<AssignedPoint, Error> {
+        Ok(AssignedPoint {
+            x: self.integer.reduce(ctx, &a.x)?,
+            y: self.integer.reduce(ctx, &a.y)?,
+        })
+    }
+
+    /// Returns `a + b`, constraining `a` and `b` to have different `x`
+    /// coordinates.
+    pub fn add(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedPoint,
+        b: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        let dx = self.integer.sub(ctx, &b.x, &a.x)?;
+        let dy = self.integer.sub(ctx, &b.y, &a.y)?;
+        let lambda = self.integer.div(ctx, &dy, &dx)?;
+        self.add_with_slope(ctx, a, &b.x, &lambda)
+    }
+
+    /// Returns `2 a`.
+    pub fn double(&self, ctx: &mut Context<'_>, a: &AssignedPoint) -> Result<AssignedPoint, Error> {
+        // There are no points of order two, so 2y is never zero.
+        let x_square = self.integer.mul(ctx, &a.x, &a.x)?;
+        let numerator = self.integer.mul_constant(ctx, &x_square, Fq::from(3))?;
+        let denominator = self.integer.add(ctx, &a.y, &a.y)?;
+        let lambda = self.integer.div(ctx, &numerator, &denominator)?;
+        self.add_with_slope(ctx, a, &a.x, &lambda)
+    }
+
+    /// Returns the third point on the line of slope `lambda` through `a` and
+    /// a point with `x` coordinate `bx`, reflected.
+    fn add_with_slope(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedPoint,
+        bx: &AssignedInteger,
+        lambda: &AssignedInteger,
+    ) -> Result<AssignedPoint, Error> {
+        // x = lambda^2 - a.x - b.x
+        let lambda_square = self.integer.mul(ctx, lambda, lambda)?;
+        let x = self.integer.sub(ctx, &lambda_square, &a.x)?;
+        let x = self.integer.sub(ctx, &x, bx)?;
+        // y = lambda (a.x - x) - a.y
+        let dx = self.integer.sub(ctx, &a.x, &x)?;
+        let y = self.integer.mul(ctx, lambda, &dx)?;
+        let y = self.integer.sub(ctx, &y, &a.y)?;
+        Ok(AssignedPoint { x, y })
+    }
+
+    /// Returns `a` if `bit` is set, and `b` otherwise. `bit` must already be
+    /// constrained to be 0 or 1.
+    pub fn select(
+        &self,
+        ctx: &mut Context<'_>,
+        bit: &AssignedValue,
+        a: &AssignedPoint,
+        b: &AssignedPoint,
+    ) -> Result<AssignedPoint, Error> {
+        Ok(AssignedPoint {
+            x: self.integer.select(ctx, bit, &a.x, &b.x)?,
+            y: self.integer.select(ctx, bit, &a.y, &b.y)?,
+        })
+    }
+
+    /// Returns `\sum_i s_i P_i` for the given terms `(P_i, s_i)`.
+    ///
+    /// The scalars are decomposed into bits, and the multiplications share
+    /// their doublings. Terms with the identity as a constant base are
+    /// skipped; the result itself can't be the identity.
+    pub fn msm(
+        &self,
+        ctx: &mut Context<'_>,
+        terms: &[(Base, AssignedValue)],
+    ) -> Result<AssignedPoint, Error> {
+        let num_bits = Fr::NUM_BITS as usize;
+
+        let mut bases = vec![];
+        let mut bits = vec![];
+        for (base, scalar) in terms.iter() {
+            let base = match base {
+                Base::Assigned(point) => point.clone(),
+                Base::Constant(point) if bool::from(point.is_identity()) => continue,
+                Base::Constant(point) => self.assign_constant(ctx, *point)?,
+            };
+            bases.push(base);
+            bits.push(self.main_gate().to_bits(ctx, scalar, num_bits)?);
+        }
+
+        // After the loop, the accumulator holds 2^{num_bits} aux + sum.
+        let aux = aux_generator();
+        let mut acc = self.assign_constant(ctx, aux)?;
+        for i in (0..num_bits).rev() {
+            acc = self.double(ctx, &acc)?;
+            for (base, bits) in bases.iter().zip(bits.iter()) {
+                let sum = self.add(ctx, &acc, base)?;
+                acc = self.select(ctx, &bits[i], &sum, &acc)?;
+            }
+        }
+
+        let offset = -(aux * Fr::from(2).pow_vartime(&[num_bits as u64]));
+        let offset = self.assign_constant(ctx, offset.to_affine())?;
+        self.add(ctx, &acc, &offset)
+    }
+}
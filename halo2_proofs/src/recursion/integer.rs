@@ -0,0 +1,729 @@
+//! Emulated arithmetic over the base field of BN254.
+//!
+//! An integer is held in four 64-bit limbs, each range checked, and
+//! represents its class modulo the base field modulus `q`: results are
+//! reduced, but inputs don't have to be. Every operation is an instance of
+//! the integer gate, which checks
+//!
+//! $$
+//! a b + c = r + (k - K) q
+//! $$
+//!
+//! exactly over the integers, for a quotient `k` witnessed by the prover and
+//! a small offset `K` that lets `r` exceed `a b + c`. The gate splits the
+//! equation into seven column sums of limb products, chained by range
+//! checked carries; the bounds on the limbs, quotient and carries keep every
+//! column sum far below the native modulus, so no sum can wrap around.
+
+use std::cmp::Ordering;
+
+use ff::{Field, PrimeField};
+use pairing::bn256::{Fq, Fr};
+
+use super::main_gate::{pow2, AssignedValue, MainGate, Term};
+use super::{Context, RecursionConfig};
+use crate::arithmetic::FieldExt;
+use crate::plonk::Error;
+
+/// The number of limbs of an emulated integer.
+pub const NUM_LIMBS: usize = 4;
+
+/// The number of bits in each limb of an emulated integer.
+pub const LIMB_BITS: usize = 64;
+
+/// The offset `K` subtracted from the quotient of the integer gate.
+pub(crate) const QUOTIENT_OFFSET: u64 = 8;
+
+/// The number of bits of the most significant limb of a quotient. The
+/// quotient of two reduced operands takes 259 bits.
+const QUOTIENT_TOP_BITS: usize = 80;
+
+/// The number of bits of a carry between two column sums, which is stored
+/// shifted by `2^{CARRY_BITS - 1}` to fit negative carries.
+pub(crate) const CARRY_BITS: usize = 80;
+
+/// The number of carries of the integer gate.
+pub(crate) const NUM_CARRIES: usize = 2 * NUM_LIMBS - 2;
+
+/// The number of rows of the integer gate.
+pub(crate) const GATE_ROWS: usize = 7;
+
+/// Little-endian 64-bit limbs of a non-negative integer.
+type Natural = Vec<u64>;
+
+fn nat_add(a: &[u64], b: &[u64]) -> Natural {
+    let mut out = Vec::with_capacity(a.len().max(b.len()) + 1);
+    let mut carry = 0u128;
+    for i in 0..a.len().max(b.len()) {
+        let sum = *a.get(i).unwrap_or(&0) as u128 + *b.get(i).unwrap_or(&0) as u128 + carry;
+        out.push(sum as u64);
+        carry = sum >> 64;
+    }
+    out.push(carry as u64);
+    out
+}
+
+/// Returns `a - b`, or `None` if `b > a`.
+fn nat_sub(a: &[u64], b: &[u64]) -> Option<Natural> {
+    let mut out = Vec::with_capacity(a.len());
+    let mut borrow = 0u64;
+    for i in 0..a.len().max(b.len()) {
+        let (diff, b1) = a
+            .get(i)
+            .unwrap_or(&0)
+            .overflowing_sub(*b.get(i).unwrap_or(&0));
+        let (diff, b2) = diff.overflowing_sub(borrow);
+        out.push(diff);
+        borrow = (b1 || b2) as u64;
+    }
+    if borrow == 0 {
+        Some(out)
+    } else {
+        None
+    }
+}
+
+fn nat_mul(a: &[u64], b: &[u64]) -> Natural {
+    let mut out = vec![0u64; a.len() + b.len()];
+    for (i, &a) in a.iter().enumerate() {
+        let mut carry = 0u128;
+        for (j, &b) in b.iter().enumerate() {
+            let t = out[i + j] as u128 + a as u128 * b as u128 + carry;
+            out[i + j] = t as u64;
+            carry = t >> 64;
+        }
+        out[i + b.len()] = carry as u64;
+    }
+    out
+}
+
+fn nat_cmp(a: &[u64], b: &[u64]) -> Ordering {
+    for i in (0..a.len().max(b.len())).rev() {
+        match a.get(i).unwrap_or(&0).cmp(b.get(i).unwrap_or(&0)) {
+            Ordering::Equal => continue,
+            ordering => return ordering,
+        }
+    }
+    Ordering::Equal
+}
+
+/// Returns the quotient and remainder of `n / d`, by long division.
+fn nat_div_rem(n: &[u64], d: &[u64]) -> (Natural, Natural) {
+    let mut quotient = vec![0u64; n.len()];
+    let mut remainder: Natural = vec![0u64; d.len() + 1];
+    for bit in (0..n.len() * 64).rev() {
+        for i in (0..remainder.len()).rev() {
+            remainder[i] = remainder[i] << 1 | if i > 0 { remainder[i - 1] >> 63 } else { 0 };
+        }
+        remainder[0] |= (n[bit / 64] >> (bit % 64)) & 1;
+        if nat_cmp(&remainder, d) != Ordering::Less {
+            remainder = nat_sub(&remainder, d).unwrap();
+            quotient[bit / 64] |= 1 << (bit % 64);
+        }
+    }
+    (quotient, remainder)
+}
+
+fn nat_to_fr(n: &[u64]) -> Fr {
+    let mut bytes = [0u8; 64];
+    for (chunk, limb) in bytes.chunks_mut(8).zip(n.iter()) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    Fr::from_bytes_wide(&bytes)
+}
+
+fn fr_to_u64(value: &Fr) -> u64 {
+    let repr = value.to_repr();
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&repr.as_ref()[..8]);
+    u64::from_le_bytes(bytes)
+}
+
+fn fq_to_limbs(value: &Fq) -> Natural {
+    value
+        .to_repr()
+        .as_ref()
+        .chunks(8)
+        .map(|chunk| {
+            let mut bytes = [0u8; 8];
+            bytes.copy_from_slice(chunk);
+            u64::from_le_bytes(bytes)
+        })
+        .collect()
+}
+
+/// Reduces an integer modulo `q`.
+fn fq_from_natural(n: &[u64]) -> Fq {
+    let (_, remainder) = nat_div_rem(n, &modulus());
+    let mut repr = <Fq as PrimeField>::Repr::default();
+    for (chunk, limb) in repr.as_mut().chunks_mut(8).zip(remainder.iter()) {
+        chunk.copy_from_slice(&limb.to_le_bytes());
+    }
+    Fq::from_repr(repr).unwrap()
+}
+
+/// Returns the limbs of the base field modulus `q`.
+pub(crate) fn modulus() -> Natural {
+    // q - 1 is even, so adding one to it doesn't carry.
+    let mut limbs = fq_to_limbs(&-Fq::one());
+    limbs[0] += 1;
+    limbs
+}
+
+/// Returns the limbs of an element of the base field, as native field
+/// elements.
+pub(crate) fn fq_to_native_limbs(value: &Fq) -> Vec<Fr> {
+    fq_to_limbs(value)
+        .iter()
+        .map(|limb| Fr::from(*limb))
+        .collect()
+}
+
+/// Recovers an element of the base field from its limbs, returning `None` if
+/// the limbs are out of range or don't encode a reduced integer.
+pub(crate) fn fq_from_native_limbs(limbs: &[Fr]) -> Option<Fq> {
+    let mut repr = <Fq as PrimeField>::Repr::default();
+    for (chunk, limb) in repr.as_mut().chunks_mut(8).zip(limbs.iter()) {
+        if *limb != Fr::from(fr_to_u64(limb)) {
+            return None;
+        }
+        chunk.copy_from_slice(&fr_to_u64(limb).to_le_bytes());
+    }
+    Option::from(Fq::from_repr(repr))
+}
+
+/// An integer assigned in the circuit, representing an element of the base
+/// field of BN254.
+#[derive(Clone, Debug)]
+pub struct AssignedInteger {
+    limbs: Vec<AssignedValue>,
+}
+
+impl AssignedInteger {
+    /// Returns the little-endian limbs of the integer.
+    pub fn limbs(&self) -> &[AssignedValue] {
+        &self.limbs
+    }
+
+    /// Returns the base field element represented by the integer.
+    pub fn value(&self) -> Option<Fq> {
+        self.natural().map(|n| fq_from_natural(&n))
+    }
+
+    fn natural(&self) -> Option<Natural> {
+        self.limbs
+            .iter()
+            .map(|limb| limb.value().map(fr_to_u64))
+            .collect()
+    }
+}
+
+/// An operand of the integer gate.
+#[derive(Clone, Debug)]
+enum Operand<'a> {
+    Assigned(&'a AssignedInteger),
+    Constant(Fq),
+    /// A new, reduced witness.
+    Witness(Option<Fq>),
+}
+
+impl<'a> Operand<'a> {
+    fn natural(&self) -> Option<Natural> {
+        match self {
+            Operand::Assigned(integer) => integer.natural(),
+            Operand::Constant(value) => Some(fq_to_limbs(value)),
+            Operand::Witness(value) => value.as_ref().map(fq_to_limbs),
+        }
+    }
+
+    fn terms(&self) -> Vec<Term<'a>> {
+        match self {
+            Operand::Assigned(integer) => integer.limbs.iter().map(Term::Assigned).collect(),
+            Operand::Constant(value) => fq_to_native_limbs(value)
+                .into_iter()
+                .map(Term::Constant)
+                .collect(),
+            Operand::Witness(value) => (0..NUM_LIMBS)
+                .map(|i| Term::Witness(value.as_ref().map(|v| Fr::from(fq_to_limbs(v)[i]))))
+                .collect(),
+        }
+    }
+}
+
+/// A chip for arithmetic over the base field of BN254.
+#[derive(Clone, Debug)]
+pub struct IntegerChip {
+    config: RecursionConfig,
+    main_gate: MainGate,
+}
+
+impl IntegerChip {
+    /// Constructs an integer chip.
+    pub fn new(config: RecursionConfig) -> Self {
+        IntegerChip {
+            main_gate: MainGate::new(config.clone()),
+            config,
+        }
+    }
+
+    /// Returns the main gate chip this chip is built on.
+    pub fn main_gate(&self) -> &MainGate {
+        &self.main_gate
+    }
+
+    /// Lays out the integer gate for `a b + c = r + (k - K) q`, returning the
+    /// assigned `[a, b, c, r]`. New witnesses among them are range checked.
+    ///
+    /// If `exact` is set, `k` is fixed to `K`, so that `a b + c = r` holds
+    /// over the integers.
+    fn assign_gate(
+        &self,
+        ctx: &mut Context<'_>,
+        operands: [Operand<'_>; 4],
+        exact: bool,
+    ) -> Result<Vec<AssignedInteger>, Error> {
+        let modulus = modulus();
+        let naturals: Option<Vec<Natural>> = operands.iter().map(Operand::natural).collect();
+
+        // Witness the quotient, offset by K, and the carries.
+        let witness = match naturals {
+            Some(naturals) => {
+                let (a, b, c, r) = (&naturals[0], &naturals[1], &naturals[2], &naturals[3]);
+                let offset = nat_mul(&[QUOTIENT_OFFSET], &modulus);
+                let sum = nat_add(&nat_add(&nat_mul(a, b), c), &offset);
+                let (quotient, remainder) =
+                    nat_div_rem(&nat_sub(&sum, r).ok_or(Error::Synthesis)?, &modulus);
+                if nat_cmp(&remainder, &[]) != Ordering::Equal {
+                    return Err(Error::Synthesis);
+                }
+                let quotient = quotient[..NUM_LIMBS].to_vec();
+
+                // The weighted column sums, split by sign.
+                let column = |j: usize, x: &[u64], y: &[u64]| -> Natural {
+                    (0..NUM_LIMBS)
+                        .filter(|&i| j >= i && j - i < NUM_LIMBS)
+                        .fold(vec![], |acc, i| {
+                            nat_add(&acc, &nat_mul(&[x[i]], &[y[j - i]]))
+                        })
+                };
+                let mut positive = vec![];
+                let mut negative = vec![];
+                let mut carries = vec![];
+                for j in 0..NUM_CARRIES {
+                    let mut pos = column(j, a, b);
+                    let mut neg = column(j, &quotient, &modulus);
+                    if j < NUM_LIMBS {
+                        pos = nat_add(&pos, &[c[j]]);
+                        pos = nat_add(&pos, &nat_mul(&[QUOTIENT_OFFSET], &[modulus[j]]));
+                        neg = nat_add(&neg, &[r[j]]);
+                    }
+                    let shift = vec![0u64; j];
+                    positive = nat_add(&positive, &[&shift[..], &pos[..]].concat());
+                    negative = nat_add(&negative, &[&shift[..], &neg[..]].concat());
+
+                    // The partial sums are divisible by 2^{64 (j + 1)}.
+                    let carry = match nat_sub(&positive, &negative) {
+                        Some(diff) => nat_to_fr(&diff[(j + 1).min(diff.len())..]),
+                        None => {
+                            let diff = nat_sub(&negative, &positive).unwrap();
+                            -nat_to_fr(&diff[(j + 1).min(diff.len())..])
+                        }
+                    };
+                    carries.push(Some(carry + pow2(CARRY_BITS - 1)));
+                }
+
+                let quotient: Vec<Option<Fr>> =
+                    quotient.iter().map(|limb| Some(Fr::from(*limb))).collect();
+                (quotient, carries)
+            }
+            None => (vec![None; NUM_LIMBS], vec![None; NUM_CARRIES]),
+        };
+        let (quotient, carries) = witness;
+
+        self.config.q_integer.enable(&mut ctx.region, ctx.offset)?;
+        let mut assigned = vec![];
+        for operand in operands.iter() {
+            let limbs = self
+                .main_gate
+                .assign_row(ctx, &operand.terms(), Default::default())?;
+            assigned.push(AssignedInteger {
+                limbs: limbs[..NUM_LIMBS].to_vec(),
+            });
+        }
+        let quotient_terms: Vec<Term<'_>> = if exact {
+            let mut limbs = vec![Term::Constant(Fr::from(QUOTIENT_OFFSET))];
+            limbs.resize(NUM_LIMBS, Term::Constant(Fr::zero()));
+            limbs
+        } else {
+            quotient.iter().map(|limb| Term::Witness(*limb)).collect()
+        };
+        let quotient = self
+            .main_gate
+            .assign_row(ctx, &quotient_terms, Default::default())?;
+        let mut carry_cells = self.main_gate.assign_row(
+            ctx,
+            &carries[..NUM_LIMBS]
+                .iter()
+                .map(|carry| Term::Witness(*carry))
+                .collect::<Vec<_>>(),
+            Default::default(),
+        )?;
+        carry_cells.truncate(NUM_LIMBS);
+        let last_carries = self.main_gate.assign_row(
+            ctx,
+            &carries[NUM_LIMBS..]
+                .iter()
+                .map(|carry| Term::Witness(*carry))
+                .collect::<Vec<_>>(),
+            Default::default(),
+        )?;
+        carry_cells.extend_from_slice(&last_carries[..NUM_CARRIES - NUM_LIMBS]);
+
+        if !exact {
+            for (i, limb) in quotient[..NUM_LIMBS].iter().enumerate() {
+                let bits = if i + 1 == NUM_LIMBS {
+                    QUOTIENT_TOP_BITS
+                } else {
+                    LIMB_BITS
+                };
+                self.main_gate.range_check(ctx, limb, bits)?;
+            }
+        }
+        for carry in carry_cells.iter() {
+            self.main_gate.range_check(ctx, carry, CARRY_BITS)?;
+        }
+        for (operand, integer) in operands.iter().zip(assigned.iter()) {
+            if let Operand::Witness(_) = operand {
+                for limb in integer.limbs.iter() {
+                    self.main_gate.range_check(ctx, limb, LIMB_BITS)?;
+                }
+            }
+        }
+
+        Ok(assigned)
+    }
+
+    /// Assigns a witness, reduced but not constrained to be.
+    pub fn assign(
+        &self,
+        ctx: &mut Context<'_>,
+        value: Option<Fq>,
+    ) -> Result<AssignedInteger, Error> {
+        let limbs = (0..NUM_LIMBS)
+            .map(|i| {
+                let limb = value.as_ref().map(|v| Fr::from(fq_to_limbs(v)[i]));
+                let limb = self.main_gate.assign(ctx, limb)?;
+                self.main_gate.range_check(ctx, &limb, LIMB_BITS)?;
+                Ok(limb)
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(AssignedInteger { limbs })
+    }
+
+    /// Assigns a witness, constrained to be reduced.
+    pub fn assign_canonical(
+        &self,
+        ctx: &mut Context<'_>,
+        value: Option<Fq>,
+    ) -> Result<AssignedInteger, Error> {
+        let integer = self.assign(ctx, value)?;
+        self.assert_canonical(ctx, &integer)?;
+        Ok(integer)
+    }
+
+    /// Assigns a constant.
+    pub fn assign_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        value: Fq,
+    ) -> Result<AssignedInteger, Error> {
+        let limbs = fq_to_native_limbs(&value)
+            .into_iter()
+            .map(|limb| self.main_gate.assign_constant(ctx, limb))
+            .collect::<Result<_, Error>>()?;
+        Ok(AssignedInteger { limbs })
+    }
+
+    /// Constrains `a` to be less than `q`, so that its limbs are unique.
+    pub fn assert_canonical(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+    ) -> Result<(), Error> {
+        // a + d = q - 1 for some d in [0, 2^256).
+        let d = a.value().map(|a| -Fq::one() - a);
+        self.assign_gate(
+            ctx,
+            [
+                Operand::Assigned(a),
+                Operand::Constant(Fq::one()),
+                Operand::Witness(d),
+                Operand::Constant(-Fq::one()),
+            ],
+            true,
+        )?;
+        Ok(())
+    }
+
+    /// Constrains `a` to equal a constant. `a` must be reduced.
+    pub fn assert_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+        constant: Fq,
+    ) -> Result<(), Error> {
+        for (limb, constant) in a.limbs.iter().zip(fq_to_native_limbs(&constant)) {
+            self.main_gate.assert_constant(ctx, limb, constant)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the reduced form of `a`.
+    pub fn reduce(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+    ) -> Result<AssignedInteger, Error> {
+        let assigned = self.assign_gate(
+            ctx,
+            [
+                Operand::Assigned(a),
+                Operand::Constant(Fq::one()),
+                Operand::Constant(Fq::zero()),
+                Operand::Witness(a.value()),
+            ],
+            false,
+        )?;
+        let r = assigned[3].clone();
+        self.assert_canonical(ctx, &r)?;
+        Ok(r)
+    }
+
+    /// Returns `a b + c`.
+    pub fn mul_add(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+        c: &AssignedInteger,
+    ) -> Result<AssignedInteger, Error> {
+        let value = a
+            .value()
+            .zip(b.value())
+            .zip(c.value())
+            .map(|((a, b), c)| a * b + c);
+        let mut assigned = self.assign_gate(
+            ctx,
+            [
+                Operand::Assigned(a),
+                Operand::Assigned(b),
+                Operand::Assigned(c),
+                Operand::Witness(value),
+            ],
+            false,
+        )?;
+        Ok(assigned.pop().unwrap())
+    }
+
+    /// Constrains `a b + c = r`.
+    pub fn assert_mul_add(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+        c: &AssignedInteger,
+        r: &AssignedInteger,
+    ) -> Result<(), Error> {
+        self.assign_gate(
+            ctx,
+            [
+                Operand::Assigned(a),
+                Operand::Assigned(b),
+                Operand::Assigned(c),
+                Operand::Assigned(r),
+            ],
+            false,
+        )?;
+        Ok(())
+    }
+
+    /// Returns `a b`.
+    pub fn mul(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+    ) -> Result<AssignedInteger, Error> {
+        let value = a.value().zip(b.value()).map(|(a, b)| a * b);
+        let mut assigned = self.assign_gate(
+            ctx,
+            [
+                Operand::Assigned(a),
+                Operand::Assigned(b),
+                Operand::Constant(Fq::zero()),
+                Operand::Witness(value),
+            ],
+            false,
+        )?;
+        Ok(assigned.pop().unwrap())
+    }
+
+    /// Returns `a * constant`.
+    pub fn mul_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+        constant: Fq,
+    ) -> Result<AssignedInteger, Error> {
+        let value = a.value().map(|a| a * constant);
+        let mut assigned = self.assign_gate(
+            ctx,
+            [
+                Operand::Assigned(a),
+                Operand::Constant(constant),
+                Operand::Constant(Fq::zero()),
+                Operand::Witness(value),
+            ],
+            false,
+        )?;
+        Ok(assigned.pop().unwrap())
+    }
+
+    /// Returns `a + b`.
+    pub fn add(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+    ) -> Result<AssignedInteger, Error> {
+        let value = a.value().zip(b.value()).map(|(a, b)| a + b);
+        let mut assigned = self.assign_gate(
+            ctx,
+            [
+                Operand::Assigned(a),
+                Operand::Constant(Fq::one()),
+                Operand::Assigned(b),
+                Operand::Witness(value),
+            ],
+            false,
+        )?;
+        Ok(assigned.pop().unwrap())
+    }
+
+    /// Returns `a - b`.
+    pub fn sub(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+    ) -> Result<AssignedInteger, Error> {
+        // (a - b) * 1 + b = a
+        let value = a.value().zip(b.value()).map(|(a, b)| a - b);
+        let assigned = self.assign_gate(
+            ctx,
+            [
+                Operand::Witness(value),
+                Operand::Constant(Fq::one()),
+                Operand::Assigned(b),
+                Operand::Assigned(a),
+            ],
+            false,
+        )?;
+        Ok(assigned[0].clone())
+    }
+
+    /// Returns `a^{-1}`, constraining `a` to be nonzero modulo `q`.
+    pub fn invert(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+    ) -> Result<AssignedInteger, Error> {
+        // A zero `a` has no inverse, which leaves the gate unsatisfiable.
+        let value = a
+            .value()
+            .map(|a| Option::from(a.invert()).unwrap_or_else(Fq::zero));
+        let assigned = self.assign_gate(
+            ctx,
+            [
+                Operand::Witness(value),
+                Operand::Assigned(a),
+                Operand::Constant(Fq::zero()),
+                Operand::Constant(Fq::one()),
+            ],
+            false,
+        )?;
+        Ok(assigned[0].clone())
+    }
+
+    /// Returns `a / b`, constraining `b` to be nonzero modulo `q`.
+    pub fn div(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+    ) -> Result<AssignedInteger, Error> {
+        let b_inv = self.invert(ctx, b)?;
+        self.mul(ctx, a, &b_inv)
+    }
+
+    /// Returns `a` if `bit` is set, and `b` otherwise. `bit` must already be
+    /// constrained to be 0 or 1.
+    pub fn select(
+        &self,
+        ctx: &mut Context<'_>,
+        bit: &AssignedValue,
+        a: &AssignedInteger,
+        b: &AssignedInteger,
+    ) -> Result<AssignedInteger, Error> {
+        let limbs = a
+            .limbs
+            .iter()
+            .zip(b.limbs.iter())
+            .map(|(a, b)| self.main_gate.select(ctx, bit, a, b))
+            .collect::<Result<_, Error>>()?;
+        Ok(AssignedInteger { limbs })
+    }
+
+    /// Returns the native field elements `l_0 + 2^64 l_1` and
+    /// `l_2 + 2^64 l_3` built from the limbs `l_i` of `a`.
+    pub fn to_native_halves(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedInteger,
+    ) -> Result<[AssignedValue; 2], Error> {
+        let half = |ctx: &mut Context<'_>, lo: &AssignedValue, hi: &AssignedValue| {
+            self.main_gate.linear_combination(
+                ctx,
+                &[
+                    (Term::Assigned(lo), Fr::one()),
+                    (Term::Assigned(hi), pow2(LIMB_BITS)),
+                ],
+                Fr::zero(),
+            )
+        };
+        Ok([
+            half(ctx, &a.limbs[0], &a.limbs[1])?,
+            half(ctx, &a.limbs[2], &a.limbs[3])?,
+        ])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_arithmetic() {
+        let q = modulus();
+        assert_eq!(fq_from_natural(&q), Fq::zero());
+        assert_eq!(fq_from_natural(&nat_add(&q, &[5])), Fq::from(5));
+
+        let a = fq_to_limbs(&-Fq::from(3));
+        let b = fq_to_limbs(&Fq::from(7));
+        let (quotient, remainder) = nat_div_rem(&nat_add(&nat_mul(&a, &b), &[1]), &q);
+        assert_eq!(fq_from_natural(&remainder), -Fq::from(20));
+        assert_eq!(quotient[0], 6);
+        assert!(quotient[1..].iter().all(|limb| *limb == 0));
+        assert_eq!(nat_sub(&b, &a), None);
+    }
+}
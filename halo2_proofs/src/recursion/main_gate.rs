@@ -0,0 +1,511 @@
+//! Arithmetic over the native field, and range checks.
+
+use ff::{Field, PrimeField};
+use pairing::bn256::Fr;
+
+use super::{Context, RecursionConfig, WIDTH};
+use crate::arithmetic::FieldExt;
+use crate::circuit::AssignedCell;
+use crate::plonk::Error;
+
+/// The number of bits checked by one lookup into the range table.
+pub const LOOKUP_BITS: usize = 16;
+
+/// A native field element assigned in the circuit.
+pub type AssignedValue = AssignedCell<Fr, Fr>;
+
+/// Returns `2^bits` as a field element.
+pub(crate) fn pow2(bits: usize) -> Fr {
+    let mut bytes = [0u8; 64];
+    bytes[bits / 8] = 1 << (bits % 8);
+    Fr::from_bytes_wide(&bytes)
+}
+
+/// A value placed in a cell of a main gate row.
+#[derive(Clone, Debug)]
+pub(crate) enum Term<'a> {
+    /// A copy of an assigned value.
+    Assigned(&'a AssignedValue),
+    /// A new witness.
+    Witness(Option<Fr>),
+    /// A constant, fixed through the constants column.
+    Constant(Fr),
+}
+
+impl<'a> Term<'a> {
+    pub(crate) fn zero() -> Self {
+        Term::Witness(Some(Fr::zero()))
+    }
+
+    pub(crate) fn value(&self) -> Option<Fr> {
+        match self {
+            Term::Assigned(cell) => cell.value().copied(),
+            Term::Witness(value) => *value,
+            Term::Constant(value) => Some(*value),
+        }
+    }
+}
+
+/// The coefficients of a main gate row, which constrains
+///
+/// $$
+/// q_a a + q_b b + q_c c + q_d d + q_e e + q_{ab} a b + q_{cd} c d + q_{const}
+///   + q_{next} e_{next} = 0.
+/// $$
+#[derive(Clone, Copy, Debug, Default)]
+pub(crate) struct Coeffs {
+    pub(crate) linear: [Fr; WIDTH],
+    pub(crate) ab: Fr,
+    pub(crate) cd: Fr,
+    pub(crate) constant: Fr,
+    pub(crate) next: Fr,
+}
+
+/// A chip for arithmetic over the native field, built on a single gate with
+/// two products and five linear terms.
+#[derive(Clone, Debug)]
+pub struct MainGate {
+    config: RecursionConfig,
+}
+
+impl MainGate {
+    /// Constructs a main gate chip.
+    pub fn new(config: RecursionConfig) -> Self {
+        MainGate { config }
+    }
+
+    /// Lays out one row of the main gate, returning its cells.
+    pub(crate) fn assign_row(
+        &self,
+        ctx: &mut Context<'_>,
+        terms: &[Term<'_>],
+        coeffs: Coeffs,
+    ) -> Result<Vec<AssignedValue>, Error> {
+        assert!(terms.len() <= WIDTH);
+        let offset = ctx.offset;
+
+        let mut cells = Vec::with_capacity(WIDTH);
+        for (i, &column) in self.config.advice.iter().enumerate() {
+            let cell = match terms.get(i).cloned().unwrap_or_else(Term::zero) {
+                Term::Assigned(cell) => {
+                    cell.copy_advice(|| "copy", &mut ctx.region, column, offset)?
+                }
+                Term::Witness(value) => ctx.region.assign_advice(
+                    || "witness",
+                    column,
+                    offset,
+                    || value.ok_or(Error::Synthesis),
+                )?,
+                Term::Constant(value) => {
+                    ctx.region
+                        .assign_advice_from_constant(|| "constant", column, offset, value)?
+                }
+            };
+            cells.push(cell);
+        }
+
+        let fixed = self
+            .config
+            .q_linear
+            .iter()
+            .zip(coeffs.linear.iter())
+            .chain(std::iter::once((&self.config.q_ab, &coeffs.ab)))
+            .chain(std::iter::once((&self.config.q_cd, &coeffs.cd)))
+            .chain(std::iter::once((&self.config.q_constant, &coeffs.constant)))
+            .chain(std::iter::once((&self.config.q_next, &coeffs.next)));
+        for (&column, &coeff) in fixed {
+            if coeff != Fr::zero() {
+                ctx.region
+                    .assign_fixed(|| "coefficient", column, offset, || Ok(coeff))?;
+            }
+        }
+
+        ctx.offset += 1;
+        Ok(cells)
+    }
+
+    /// Assigns a witness.
+    pub fn assign(&self, ctx: &mut Context<'_>, value: Option<Fr>) -> Result<AssignedValue, Error> {
+        let mut cells = self.assign_row(ctx, &[Term::Witness(value)], Coeffs::default())?;
+        Ok(cells.swap_remove(0))
+    }
+
+    /// Assigns a constant.
+    pub fn assign_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        value: Fr,
+    ) -> Result<AssignedValue, Error> {
+        let mut cells = self.assign_row(ctx, &[Term::Constant(value)], Coeffs::default())?;
+        Ok(cells.swap_remove(0))
+    }
+
+    /// Constrains two assigned values to be equal.
+    pub fn assert_equal(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        b: &AssignedValue,
+    ) -> Result<(), Error> {
+        ctx.region.constrain_equal(a.cell(), b.cell())
+    }
+
+    /// Constrains an assigned value to equal a constant.
+    pub fn assert_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        constant: Fr,
+    ) -> Result<(), Error> {
+        ctx.region.constrain_constant(a.cell(), constant)
+    }
+
+    /// Returns `\sum_i c_i t_i + constant` for the given terms `(t_i, c_i)`.
+    ///
+    /// Up to four terms fit in one row; longer combinations are chained
+    /// through the last column, which holds the running sum.
+    pub(crate) fn linear_combination(
+        &self,
+        ctx: &mut Context<'_>,
+        terms: &[(Term<'_>, Fr)],
+        constant: Fr,
+    ) -> Result<AssignedValue, Error> {
+        let sum = terms.iter().fold(Some(constant), |acc, (term, coeff)| {
+            acc.zip(term.value())
+                .map(|(acc, value)| acc + value * coeff)
+        });
+
+        if terms.len() < WIDTH {
+            let mut row: Vec<Term<'_>> = terms.iter().map(|(term, _)| term.clone()).collect();
+            let mut coeffs = Coeffs {
+                constant,
+                ..Coeffs::default()
+            };
+            for (coeff, (_, c)) in coeffs.linear.iter_mut().zip(terms.iter()) {
+                *coeff = *c;
+            }
+            row.resize(WIDTH - 1, Term::zero());
+            row.push(Term::Witness(sum));
+            coeffs.linear[WIDTH - 1] = -Fr::one();
+            let mut cells = self.assign_row(ctx, &row, coeffs)?;
+            return Ok(cells.pop().unwrap());
+        }
+
+        let mut acc = Some(constant);
+        for (i, chunk) in terms.chunks(WIDTH - 1).enumerate() {
+            let mut row: Vec<Term<'_>> = chunk.iter().map(|(term, _)| term.clone()).collect();
+            let mut coeffs = Coeffs {
+                next: -Fr::one(),
+                ..Coeffs::default()
+            };
+            for (coeff, (_, c)) in coeffs.linear.iter_mut().zip(chunk.iter()) {
+                *coeff = *c;
+            }
+            row.resize(WIDTH - 1, Term::zero());
+            if i == 0 {
+                // The first row starts the sum with the constant.
+                coeffs.constant = constant;
+                row.push(Term::zero());
+            } else {
+                coeffs.linear[WIDTH - 1] = Fr::one();
+                row.push(Term::Witness(acc));
+            }
+            acc = chunk.iter().fold(acc, |acc, (term, coeff)| {
+                acc.zip(term.value())
+                    .map(|(acc, value)| acc + value * coeff)
+            });
+            self.assign_row(ctx, &row, coeffs)?;
+        }
+
+        let mut row = vec![Term::zero(); WIDTH - 1];
+        row.push(Term::Witness(acc));
+        let mut cells = self.assign_row(ctx, &row, Coeffs::default())?;
+        Ok(cells.pop().unwrap())
+    }
+
+    /// Returns `a + b`.
+    pub fn add(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        b: &AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        self.linear_combination(
+            ctx,
+            &[
+                (Term::Assigned(a), Fr::one()),
+                (Term::Assigned(b), Fr::one()),
+            ],
+            Fr::zero(),
+        )
+    }
+
+    /// Returns `a - b`.
+    pub fn sub(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        b: &AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        self.linear_combination(
+            ctx,
+            &[
+                (Term::Assigned(a), Fr::one()),
+                (Term::Assigned(b), -Fr::one()),
+            ],
+            Fr::zero(),
+        )
+    }
+
+    /// Returns `-a`.
+    pub fn neg(&self, ctx: &mut Context<'_>, a: &AssignedValue) -> Result<AssignedValue, Error> {
+        self.linear_combination(ctx, &[(Term::Assigned(a), -Fr::one())], Fr::zero())
+    }
+
+    /// Returns `a + constant`.
+    pub fn add_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        constant: Fr,
+    ) -> Result<AssignedValue, Error> {
+        self.linear_combination(ctx, &[(Term::Assigned(a), Fr::one())], constant)
+    }
+
+    /// Returns `a * constant`.
+    pub fn mul_constant(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        constant: Fr,
+    ) -> Result<AssignedValue, Error> {
+        self.linear_combination(ctx, &[(Term::Assigned(a), constant)], Fr::zero())
+    }
+
+    /// Returns `a * b + c`.
+    pub fn mul_add(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        b: &AssignedValue,
+        c: &AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        let value = a
+            .value()
+            .zip(b.value())
+            .zip(c.value())
+            .map(|((a, b), c)| *a * b + c);
+        let mut cells = self.assign_row(
+            ctx,
+            &[
+                Term::Assigned(a),
+                Term::Assigned(b),
+                Term::Assigned(c),
+                Term::zero(),
+                Term::Witness(value),
+            ],
+            Coeffs {
+                linear: [Fr::zero(), Fr::zero(), Fr::one(), Fr::zero(), -Fr::one()],
+                ab: Fr::one(),
+                ..Coeffs::default()
+            },
+        )?;
+        Ok(cells.pop().unwrap())
+    }
+
+    /// Returns `a * b`.
+    pub fn mul(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        b: &AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        let value = a.value().zip(b.value()).map(|(a, b)| *a * b);
+        let mut cells = self.assign_row(
+            ctx,
+            &[
+                Term::Assigned(a),
+                Term::Assigned(b),
+                Term::zero(),
+                Term::zero(),
+                Term::Witness(value),
+            ],
+            Coeffs {
+                linear: [Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero(), -Fr::one()],
+                ab: Fr::one(),
+                ..Coeffs::default()
+            },
+        )?;
+        Ok(cells.pop().unwrap())
+    }
+
+    /// Returns `a^{-1}`, constraining `a` to be nonzero.
+    pub fn invert(&self, ctx: &mut Context<'_>, a: &AssignedValue) -> Result<AssignedValue, Error> {
+        // A zero `a` has no inverse, which leaves the row unsatisfiable.
+        let inverse = a
+            .value()
+            .map(|a| Option::from(a.invert()).unwrap_or_else(Fr::zero));
+        let mut cells = self.assign_row(
+            ctx,
+            &[Term::Assigned(a), Term::Witness(inverse)],
+            Coeffs {
+                ab: Fr::one(),
+                constant: -Fr::one(),
+                ..Coeffs::default()
+            },
+        )?;
+        Ok(cells.swap_remove(1))
+    }
+
+    /// Returns `a / b`, constraining `b` to be nonzero.
+    pub fn div(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        b: &AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        let b_inv = self.invert(ctx, b)?;
+        self.mul(ctx, a, &b_inv)
+    }
+
+    /// Constrains `a` to be 0 or 1.
+    pub fn assert_bit(&self, ctx: &mut Context<'_>, a: &AssignedValue) -> Result<(), Error> {
+        self.assign_row(
+            ctx,
+            &[Term::Assigned(a), Term::Assigned(a)],
+            Coeffs {
+                linear: [-Fr::one(), Fr::zero(), Fr::zero(), Fr::zero(), Fr::zero()],
+                ab: Fr::one(),
+                ..Coeffs::default()
+            },
+        )?;
+        Ok(())
+    }
+
+    /// Returns `a` if `bit` is set, and `b` otherwise. `bit` must already be
+    /// constrained to be 0 or 1.
+    pub fn select(
+        &self,
+        ctx: &mut Context<'_>,
+        bit: &AssignedValue,
+        a: &AssignedValue,
+        b: &AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        let value = bit
+            .value()
+            .zip(a.value())
+            .zip(b.value())
+            .map(|((bit, a), b)| if *bit == Fr::one() { *a } else { *b });
+        // bit * a - bit * b + b - r = 0
+        let mut cells = self.assign_row(
+            ctx,
+            &[
+                Term::Assigned(bit),
+                Term::Assigned(a),
+                Term::Assigned(bit),
+                Term::Assigned(b),
+                Term::Witness(value),
+            ],
+            Coeffs {
+                linear: [Fr::zero(), Fr::zero(), Fr::zero(), Fr::one(), -Fr::one()],
+                ab: Fr::one(),
+                cd: -Fr::one(),
+                ..Coeffs::default()
+            },
+        )?;
+        Ok(cells.pop().unwrap())
+    }
+
+    /// Decomposes `a` into `num_bits` little-endian bits.
+    ///
+    /// With `num_bits` equal to the size of the field, the decomposition of
+    /// small values isn't unique; both decompositions are congruent to `a`.
+    pub fn to_bits(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        num_bits: usize,
+    ) -> Result<Vec<AssignedValue>, Error> {
+        let repr = a.value().map(|a| a.to_repr());
+        let bits = (0..num_bits)
+            .map(|i| {
+                let bit = repr
+                    .as_ref()
+                    .map(|repr| Fr::from(((repr.as_ref()[i / 8] >> (i % 8)) & 1) as u64));
+                let bit = self.assign(ctx, bit)?;
+                self.assert_bit(ctx, &bit)?;
+                Ok(bit)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        let terms: Vec<_> = bits
+            .iter()
+            .enumerate()
+            .map(|(i, bit)| (Term::Assigned(bit), pow2(i)))
+            .collect();
+        let composed = self.linear_combination(ctx, &terms, Fr::zero())?;
+        self.assert_equal(ctx, &composed, a)?;
+
+        Ok(bits)
+    }
+
+    /// Constrains `a` to be less than `2^num_bits`, where `num_bits` is a
+    /// multiple of [`LOOKUP_BITS`].
+    ///
+    /// `a` is decomposed into 16-bit chunks, looked up four per row, and
+    /// recomposed through the last column of each row.
+    pub fn range_check(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+        num_bits: usize,
+    ) -> Result<(), Error> {
+        assert_eq!(num_bits % LOOKUP_BITS, 0);
+        let num_chunks = num_bits / LOOKUP_BITS;
+        let repr = a.value().map(|a| a.to_repr());
+        let chunks: Vec<Option<Fr>> = (0..num_chunks)
+            .map(|i| {
+                repr.as_ref().map(|repr| {
+                    let bytes = repr.as_ref();
+                    Fr::from(bytes[2 * i] as u64 | (bytes[2 * i + 1] as u64) << 8)
+                })
+            })
+            .collect();
+
+        let rows = (num_chunks + WIDTH - 2) / (WIDTH - 1);
+        for row in 0..rows {
+            let row_chunks = &chunks[row * (WIDTH - 1)..num_chunks.min((row + 1) * (WIDTH - 1))];
+            let mut terms: Vec<Term<'_>> = row_chunks.iter().map(|c| Term::Witness(*c)).collect();
+            terms.resize(WIDTH - 1, Term::zero());
+
+            // The last column holds the value of the chunks from this row on.
+            let rest =
+                chunks[row * (WIDTH - 1)..]
+                    .iter()
+                    .rev()
+                    .fold(Some(Fr::zero()), |acc, chunk| {
+                        acc.zip(*chunk)
+                            .map(|(acc, chunk)| acc * pow2(LOOKUP_BITS) + chunk)
+                    });
+            terms.push(if row == 0 {
+                Term::Assigned(a)
+            } else {
+                Term::Witness(rest)
+            });
+
+            let mut coeffs = Coeffs::default();
+            for (i, coeff) in coeffs.linear.iter_mut().take(row_chunks.len()).enumerate() {
+                *coeff = pow2(i * LOOKUP_BITS);
+            }
+            coeffs.linear[WIDTH - 1] = -Fr::one();
+            if row + 1 < rows {
+                coeffs.next = pow2((WIDTH - 1) * LOOKUP_BITS);
+            }
+
+            self.config.q_range.enable(&mut ctx.region, ctx.offset)?;
+            self.assign_row(ctx, &terms, coeffs)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+//! An in-circuit replay of the Poseidon transcript.
+
+use std::io;
+
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, GroupEncoding};
+use pairing::bn256::{Fr, G1Affine};
+
+use super::ecc::{AssignedPoint, EccChip};
+use super::main_gate::{AssignedValue, MainGate, Term};
+use super::Context;
+use crate::arithmetic::FieldExt;
+use crate::plonk::Error;
+use crate::transcript::poseidon::{sponge_tag, Spec};
+use crate::transcript::{point_coordinates, TranscriptPrefixes};
+
+/// A chip for the Poseidon permutation, built on the main gate.
+#[derive(Clone, Debug)]
+pub struct PoseidonChip {
+    main_gate: MainGate,
+    spec: Spec<Fr>,
+}
+
+impl PoseidonChip {
+    /// Constructs a Poseidon chip for the permutation with parameters `spec`.
+    pub fn new(main_gate: MainGate, spec: Spec<Fr>) -> Self {
+        PoseidonChip { main_gate, spec }
+    }
+
+    /// Returns `x^5`.
+    fn sbox(&self, ctx: &mut Context<'_>, x: &AssignedValue) -> Result<AssignedValue, Error> {
+        let x2 = self.main_gate.mul(ctx, x, x)?;
+        let x4 = self.main_gate.mul(ctx, &x2, &x2)?;
+        self.main_gate.mul(ctx, &x4, x)
+    }
+
+    /// Applies the permutation to `state`, like [`Spec::permute`].
+    pub fn permute(
+        &self,
+        ctx: &mut Context<'_>,
+        state: &[AssignedValue],
+    ) -> Result<Vec<AssignedValue>, Error> {
+        assert_eq!(state.len(), self.spec.width());
+
+        let half_full_rounds = self.spec.full_rounds() / 2;
+        let partial_end = half_full_rounds + self.spec.partial_rounds();
+        let mut state = state.to_vec();
+        for (round, constants) in self.spec.round_constants().iter().enumerate() {
+            // Round constants that aren't followed by an S-box are folded
+            // into the constant term of the mix.
+            let mut words = vec![];
+            let mut folded = vec![Fr::zero(); state.len()];
+            for (i, (word, constant)) in state.iter().zip(constants.iter()).enumerate() {
+                if round < half_full_rounds || round >= partial_end || i == 0 {
+                    let word = self.main_gate.add_constant(ctx, word, *constant)?;
+                    words.push(self.sbox(ctx, &word)?);
+                } else {
+                    words.push(word.clone());
+                    folded[i] = *constant;
+                }
+            }
+
+            state = self
+                .spec
+                .mds()
+                .iter()
+                .map(|row| {
+                    let terms: Vec<_> = words
+                        .iter()
+                        .zip(row.iter())
+                        .map(|(word, m)| (Term::Assigned(word), *m))
+                        .collect();
+                    let constant = row
+                        .iter()
+                        .zip(folded.iter())
+                        .fold(Fr::zero(), |acc, (m, c)| acc + *m * c);
+                    self.main_gate.linear_combination(ctx, &terms, constant)
+                })
+                .collect::<Result<_, Error>>()?;
+        }
+
+        Ok(state)
+    }
+}
+
+/// A [`PoseidonRead`] transcript replayed in-circuit, reading the proof
+/// from a witness.
+///
+/// Points are read with their coordinates constrained to be reduced, which
+/// makes their absorbed limbs unique. Their compressed encoding is checked
+/// outside of the circuit only: the transcript already binds the `y`
+/// coordinate that the circuit uses.
+///
+/// [`PoseidonRead`]: crate::transcript::poseidon::PoseidonRead
+#[derive(Debug)]
+pub struct TranscriptChip<'p> {
+    ecc: EccChip,
+    poseidon: PoseidonChip,
+    prefixes: TranscriptPrefixes,
+    proof: Option<&'p [u8]>,
+    state: Vec<AssignedValue>,
+    absorbing: Vec<AssignedValue>,
+}
+
+impl<'p> TranscriptChip<'p> {
+    /// Initializes a transcript with message prefixes `prefixes` reading
+    /// `proof`, which is `None` when the circuit is synthesized without
+    /// witnesses.
+    pub fn new(
+        ctx: &mut Context<'_>,
+        ecc: EccChip,
+        spec: Spec<Fr>,
+        prefixes: TranscriptPrefixes,
+        proof: Option<&'p [u8]>,
+    ) -> Result<Self, Error> {
+        let main_gate = ecc.main_gate().clone();
+        let state = std::iter::once(sponge_tag())
+            .chain(std::iter::repeat(Fr::zero()).take(spec.width() - 1))
+            .map(|value| main_gate.assign_constant(ctx, value))
+            .collect::<Result<_, Error>>()?;
+        Ok(TranscriptChip {
+            poseidon: PoseidonChip::new(main_gate, spec),
+            ecc,
+            prefixes,
+            proof,
+            state,
+            absorbing: vec![],
+        })
+    }
+
+    fn rate(&self) -> usize {
+        self.state.len() - 1
+    }
+
+    fn absorb(&mut self, ctx: &mut Context<'_>, value: AssignedValue) -> Result<(), Error> {
+        if self.absorbing.len() == self.rate() {
+            self.flush(ctx)?;
+        }
+        self.absorbing.push(value);
+        Ok(())
+    }
+
+    fn absorb_constant(&mut self, ctx: &mut Context<'_>, value: Fr) -> Result<(), Error> {
+        let value = self.ecc.main_gate().assign_constant(ctx, value)?;
+        self.absorb(ctx, value)
+    }
+
+    fn flush(&mut self, ctx: &mut Context<'_>) -> Result<(), Error> {
+        let main_gate = self.ecc.main_gate();
+        for (word, value) in self.state.iter_mut().skip(1).zip(self.absorbing.drain(..)) {
+            *word = main_gate.add(ctx, word, &value)?;
+        }
+        self.state = self.poseidon.permute(ctx, &self.state)?;
+        Ok(())
+    }
+
+    /// Squeezes a challenge.
+    pub fn squeeze_challenge(&mut self, ctx: &mut Context<'_>) -> Result<AssignedValue, Error> {
+        self.absorb_constant(ctx, Fr::from(self.prefixes.challenge as u64))?;
+        // Pad with a single one, like the native sponge.
+        self.absorb_constant(ctx, Fr::one())?;
+        self.flush(ctx)?;
+        Ok(self.state[1].clone())
+    }
+
+    /// Absorbs a scalar that isn't part of the proof.
+    pub fn common_scalar(
+        &mut self,
+        ctx: &mut Context<'_>,
+        scalar: &AssignedValue,
+    ) -> Result<(), Error> {
+        self.absorb_constant(ctx, Fr::from(self.prefixes.scalar as u64))?;
+        self.absorb(ctx, scalar.clone())
+    }
+
+    /// Absorbs a point that isn't part of the proof. Its coordinates must be
+    /// reduced.
+    pub fn common_point(
+        &mut self,
+        ctx: &mut Context<'_>,
+        point: &AssignedPoint,
+    ) -> Result<(), Error> {
+        self.absorb_constant(ctx, Fr::from(self.prefixes.point as u64))?;
+        for coordinate in [point.x(), point.y()].iter() {
+            let halves = self.ecc.integer().to_native_halves(ctx, coordinate)?;
+            for half in halves.iter() {
+                self.absorb(ctx, half.clone())?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Absorbs a constant point that isn't part of the proof, which may be
+    /// the identity, like the native transcript does.
+    fn common_constant_point(
+        &mut self,
+        ctx: &mut Context<'_>,
+        point: G1Affine,
+    ) -> Result<(), Error> {
+        self.absorb_constant(ctx, Fr::from(self.prefixes.point as u64))?;
+        let (x, y) = point_coordinates(point);
+        for coordinate in [x, y].iter() {
+            for half in coordinate.to_repr().as_ref().chunks(16) {
+                let mut bytes = [0u8; 64];
+                bytes[..half.len()].copy_from_slice(half);
+                self.absorb_constant(ctx, Fr::from_bytes_wide(&bytes))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn read_bytes<R: AsMut<[u8]> + Default>(&mut self) -> Result<Option<R>, Error> {
+        let mut repr = R::default();
+        match self.proof.as_mut() {
+            Some(proof) => {
+                let len = repr.as_mut().len();
+                if proof.len() < len {
+                    return Err(Error::Synthesis);
+                }
+                let rest: &'p [u8] = *proof;
+                repr.as_mut().copy_from_slice(&rest[..len]);
+                *proof = &rest[len..];
+                Ok(Some(repr))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reads a scalar from the proof.
+    pub fn read_scalar(&mut self, ctx: &mut Context<'_>) -> Result<AssignedValue, Error> {
+        let scalar = match self.read_bytes::<<Fr as PrimeField>::Repr>()? {
+            Some(repr) => Some(Option::from(Fr::from_repr(repr)).ok_or(Error::Synthesis)?),
+            None => None,
+        };
+        let scalar = self.ecc.main_gate().assign(ctx, scalar)?;
+        self.common_scalar(ctx, &scalar)?;
+        Ok(scalar)
+    }
+
+    /// Reads a point from the proof.
+    ///
+    /// An [`AssignedPoint`] can't be the identity, so a proof holding the
+    /// identity here returns [`Error::Transcript`]. Honest provers only
+    /// write it for columns that are zero on every row and have no blinding
+    /// factors, which circuits verified in-circuit must avoid.
+    pub fn read_point(&mut self, ctx: &mut Context<'_>) -> Result<AssignedPoint, Error> {
+        let point = match self.read_bytes::<<G1Affine as GroupEncoding>::Repr>()? {
+            Some(repr) => {
+                let point: G1Affine =
+                    Option::from(G1Affine::from_bytes(&repr)).ok_or(Error::Synthesis)?;
+                if bool::from(point.is_identity()) {
+                    return Err(Error::Transcript(io::Error::new(
+                        io::ErrorKind::Other,
+                        "the in-circuit verifier can't read a point at infinity",
+                    )));
+                }
+                Some(point)
+            }
+            None => None,
+        };
+        let point = self.ecc.assign_point(ctx, point)?;
+        self.common_point(ctx, &point)?;
+        Ok(point)
+    }
+
+    /// Reads a point from the proof that must equal the constant `point`,
+    /// which may be the identity. It is absorbed as a constant, so a proof
+    /// holding any other point returns [`Error::Synthesis`].
+    pub fn read_constant_point(
+        &mut self,
+        ctx: &mut Context<'_>,
+        point: G1Affine,
+    ) -> Result<(), Error> {
+        if let Some(repr) = self.read_bytes::<<G1Affine as GroupEncoding>::Repr>()? {
+            if Option::<G1Affine>::from(G1Affine::from_bytes(&repr)) != Some(point) {
+                return Err(Error::Synthesis);
+            }
+        }
+        self.common_constant_point(ctx, point)
+    }
+
+    /// Reads `n` scalars from the proof.
+    pub fn read_scalars(
+        &mut self,
+        ctx: &mut Context<'_>,
+        n: usize,
+    ) -> Result<Vec<AssignedValue>, Error> {
+        (0..n).map(|_| self.read_scalar(ctx)).collect()
+    }
+
+    /// Reads `n` points from the proof.
+    pub fn read_points(
+        &mut self,
+        ctx: &mut Context<'_>,
+        n: usize,
+    ) -> Result<Vec<AssignedPoint>, Error> {
+        (0..n).map(|_| self.read_point(ctx)).collect()
+    }
+}
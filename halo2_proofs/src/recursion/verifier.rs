@@ -0,0 +1,940 @@
+//! The in-circuit verifier.
+
+use std::collections::{BTreeMap, BTreeSet};
+
+use ff::Field;
+use pairing::bn256::{Bn256, Fr, G1Affine};
+
+use super::ecc::{AssignedPoint, Base, EccChip};
+use super::main_gate::{AssignedValue, MainGate, Term};
+use super::transcript::TranscriptChip;
+use super::{AssignedAccumulator, Context, RecursionConfig};
+use crate::arithmetic::FieldExt;
+use crate::plonk::{Any, Column, Error, Expression, VerifyingKey};
+use crate::poly::{
+    commitment::{CommitmentScheme, ParamsVerifier},
+    Rotation,
+};
+use crate::transcript::poseidon::{self, Spec};
+use crate::transcript::TranscriptPrefixes;
+
+/// A multi-scalar multiplication whose evaluation is deferred, like
+/// [`MSM`](crate::poly::msm::MSM). A missing scalar is one.
+#[derive(Clone, Debug, Default)]
+struct Msm {
+    terms: Vec<(Base, Option<AssignedValue>)>,
+}
+
+impl Msm {
+    fn base(base: Base) -> Self {
+        Msm {
+            terms: vec![(base, None)],
+        }
+    }
+
+    fn scale(
+        &self,
+        ctx: &mut Context<'_>,
+        main_gate: &MainGate,
+        factor: &AssignedValue,
+    ) -> Result<Self, Error> {
+        let terms = self
+            .terms
+            .iter()
+            .map(|(base, scalar)| {
+                let scalar = match scalar {
+                    Some(scalar) => main_gate.mul(ctx, scalar, factor)?,
+                    None => factor.clone(),
+                };
+                Ok((base.clone(), Some(scalar)))
+            })
+            .collect::<Result<_, Error>>()?;
+        Ok(Msm { terms })
+    }
+
+    fn extend(&mut self, other: Msm) {
+        self.terms.extend(other.terms);
+    }
+}
+
+/// The evaluations of the circuit's columns at their queried rotations.
+struct Evals {
+    instance: Vec<AssignedValue>,
+    advice: Vec<AssignedValue>,
+    fixed: Vec<AssignedValue>,
+}
+
+struct PermutationSet {
+    commitment: usize,
+    eval: AssignedValue,
+    next_eval: AssignedValue,
+    last_eval: Option<AssignedValue>,
+}
+
+/// The commitments of a lookup argument, in the order they are read, along
+/// with its evaluations.
+struct Lookup {
+    commitments: Vec<usize>,
+    evals: Vec<AssignedValue>,
+}
+
+struct Challenges<'a> {
+    beta: &'a AssignedValue,
+    #[cfg_attr(feature = "mv-lookup", allow(dead_code))]
+    gamma: &'a AssignedValue,
+    l_0: &'a AssignedValue,
+    l_last: &'a AssignedValue,
+    active_rows: &'a AssignedValue,
+}
+
+struct Query {
+    commitment: usize,
+    rotation: Rotation,
+    eval: AssignedValue,
+}
+
+impl Query {
+    fn new(commitment: usize, rotation: Rotation, eval: &AssignedValue) -> Self {
+        Query {
+            commitment,
+            rotation,
+            eval: eval.clone(),
+        }
+    }
+}
+
+/// A chip that verifies proofs created with a [`PoseidonWrite`] transcript.
+///
+/// [`PoseidonWrite`]: crate::transcript::poseidon::PoseidonWrite
+#[derive(Clone, Debug)]
+pub struct VerifierChip {
+    ecc: EccChip,
+    spec: Spec<Fr>,
+    prefixes: TranscriptPrefixes,
+}
+
+impl VerifierChip {
+    /// Constructs a verifier chip for the default transcript parameters.
+    pub fn new(config: RecursionConfig) -> Self {
+        Self::with_spec(
+            config,
+            Spec::new(
+                poseidon::WIDTH,
+                poseidon::FULL_ROUNDS,
+                poseidon::PARTIAL_ROUNDS,
+            ),
+        )
+    }
+
+    /// Constructs a verifier chip for proofs whose transcript uses the
+    /// Poseidon permutation with parameters `spec`.
+    pub fn with_spec(config: RecursionConfig, spec: Spec<Fr>) -> Self {
+        VerifierChip {
+            ecc: EccChip::new(config),
+            spec,
+            prefixes: TranscriptPrefixes::default(),
+        }
+    }
+
+    /// Verifies proofs whose transcript uses the message prefixes
+    /// `prefixes` instead of the default ones.
+    pub fn with_prefixes(mut self, prefixes: TranscriptPrefixes) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    /// Returns the ECC chip this chip is built on.
+    pub fn ecc(&self) -> &EccChip {
+        &self.ecc
+    }
+
+    /// Returns the main gate chip this chip is built on.
+    pub fn main_gate(&self) -> &MainGate {
+        self.ecc.main_gate()
+    }
+
+    /// Verifies a proof for the circuit with verifying key `vk`, up to the
+    /// final pairing check, which is returned as an accumulator.
+    ///
+    /// `proof` is `None` when the circuit is synthesized without witnesses;
+    /// otherwise a malformed proof returns [`Error::Synthesis`]. The
+    /// instance columns are checked like [`verify_proof`] does, and must
+    /// each have a nonzero commitment, as the identity can't be assigned.
+    /// For the same reason, a proof committing to the identity, which honest
+    /// provers only do for columns that are zero on every row and have no
+    /// blinding factors, returns [`Error::Transcript`]. The configuration
+    /// columns are expected to hold their keygen values, and may be zero.
+    ///
+    /// Only the SHPLONK multi-opening argument is implemented in-circuit, so
+    /// params with another scheme return [`Error::Opening`].
+    ///
+    /// [`verify_proof`]: crate::plonk::verify_proof
+    pub fn verify_proof(
+        &self,
+        ctx: &mut Context<'_>,
+        params: &ParamsVerifier<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        instances: &[&[AssignedValue]],
+        proof: Option<&[u8]>,
+    ) -> Result<AssignedAccumulator, Error> {
+        if params.scheme() != CommitmentScheme::KzgShplonk {
+            return Err(Error::Opening);
+        }
+        let main_gate = self.main_gate();
+        let cs = vk.cs();
+        if instances.len() != cs.num_instance_columns {
+            return Err(Error::InvalidInstances);
+        }
+        let max_instances = params.n as usize - (cs.blinding_factors() + 1);
+        if instances
+            .iter()
+            .any(|column| column.len() > max_instances || column.len() > params.g_lagrange.len())
+        {
+            return Err(Error::InstanceTooLarge);
+        }
+
+        let mut transcript = TranscriptChip::new(
+            ctx,
+            self.ecc.clone(),
+            self.spec.clone(),
+            self.prefixes,
+            proof,
+        )?;
+        let mut commitments: Vec<Msm> = vec![];
+        let mut push = |point: AssignedPoint| {
+            commitments.push(Msm::base(Base::Assigned(point)));
+            commitments.len() - 1
+        };
+
+        // Instance commitments
+        let instance_commitments = instances
+            .iter()
+            .map(|column| {
+                let terms: Vec<_> = params
+                    .g_lagrange
+                    .iter()
+                    .zip(column.iter())
+                    .map(|(base, value)| (Base::Constant(*base), value.clone()))
+                    .collect();
+                let commitment = self.ecc.msm(ctx, &terms)?;
+                self.ecc.reduce(ctx, &commitment)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Verifying key and instance commitments
+        let vk_repr = main_gate.assign_constant(ctx, vk.transcript_repr())?;
+        transcript.common_scalar(ctx, &vk_repr)?;
+        for commitment in instance_commitments.iter() {
+            transcript.common_point(ctx, commitment)?;
+        }
+        let instance_commitments: Vec<usize> =
+            instance_commitments.into_iter().map(&mut push).collect();
+
+        // Advice and configuration commitments
+        let advice_commitments: Vec<usize> = transcript
+            .read_points(ctx, cs.num_advice_columns)?
+            .into_iter()
+            .map(&mut push)
+            .collect();
+        for column in cs.configuration_columns.iter() {
+            transcript.read_constant_point(ctx, vk.fixed_commitments()[column.index()])?;
+        }
+        let theta = transcript.squeeze_challenge(ctx)?;
+
+        // Lookup permuted commitments
+        let lookups_permuted = cs
+            .lookups
+            .iter()
+            .map(|_| read_lookup_permuted(ctx, &mut transcript))
+            .collect::<Result<Vec<_>, Error>>()?;
+        let beta = transcript.squeeze_challenge(ctx)?;
+        let gamma = transcript.squeeze_challenge(ctx)?;
+
+        // Permutation, lookup and shuffle product commitments
+        let chunk_len = cs.degree() - 2;
+        let permutation_columns = cs.permutation.get_columns();
+        let permutation_products = transcript
+            .read_points(ctx, permutation_columns.chunks(chunk_len).len())?
+            .into_iter()
+            .map(&mut push)
+            .collect::<Vec<_>>();
+        let lookup_commitments = lookups_permuted
+            .into_iter()
+            .map(|lookup| {
+                let product = transcript.read_point(ctx)?;
+                Ok(lookup.into_iter().chain(std::iter::once(product)).collect())
+            })
+            .collect::<Result<Vec<Vec<AssignedPoint>>, Error>>()?
+            .into_iter()
+            .map(|lookup| lookup.into_iter().map(&mut push).collect())
+            .collect::<Vec<Vec<usize>>>();
+        let shuffle_products = transcript
+            .read_points(ctx, cs.shuffles.len())?
+            .into_iter()
+            .map(&mut push)
+            .collect::<Vec<_>>();
+
+        // Vanishing argument commitments
+        let random_poly = transcript.read_point(ctx)?;
+        let random_poly = push(random_poly);
+        let y = transcript.squeeze_challenge(ctx)?;
+        let h_commitments = transcript.read_points(ctx, vk.domain().get_quotient_poly_degree())?;
+        let x = transcript.squeeze_challenge(ctx)?;
+
+        // Evaluations
+        let evals = Evals {
+            instance: transcript.read_scalars(ctx, cs.instance_queries.len())?,
+            advice: transcript.read_scalars(ctx, cs.advice_queries.len())?,
+            fixed: transcript.read_scalars(ctx, cs.fixed_queries.len())?,
+        };
+        let random_eval = transcript.read_scalar(ctx)?;
+        let permutation_evals = transcript.read_scalars(ctx, vk.permutation_commitments().len())?;
+        let permutation_sets = permutation_products
+            .iter()
+            .enumerate()
+            .map(|(i, &commitment)| {
+                Ok(PermutationSet {
+                    commitment,
+                    eval: transcript.read_scalar(ctx)?,
+                    next_eval: transcript.read_scalar(ctx)?,
+                    last_eval: if i + 1 < permutation_products.len() {
+                        Some(transcript.read_scalar(ctx)?)
+                    } else {
+                        None
+                    },
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let lookups = lookup_commitments
+            .into_iter()
+            .map(|commitments| {
+                Ok(Lookup {
+                    commitments,
+                    evals: transcript.read_scalars(ctx, LOOKUP_EVALS)?,
+                })
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let shuffles = shuffle_products
+            .into_iter()
+            .map(|commitment| {
+                Ok((
+                    commitment,
+                    transcript.read_scalar(ctx)?,
+                    transcript.read_scalar(ctx)?,
+                ))
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+
+        // Lagrange basis evaluations
+        let mut xn = x.clone();
+        for _ in 0..params.k {
+            xn = main_gate.mul(ctx, &xn, &xn)?;
+        }
+        let blinding_factors = cs.blinding_factors();
+        let n_inv = Fr::from(params.n).invert().unwrap();
+        let common = main_gate.linear_combination(ctx, &[(Term::Assigned(&xn), n_inv)], -n_inv)?;
+        let l_evals = (-((blinding_factors + 1) as i32)..=0)
+            .map(|rotation| {
+                let omega = vk.domain().rotate_omega(Fr::one(), Rotation(rotation));
+                let numerator = main_gate.mul_constant(ctx, &common, omega)?;
+                let denominator = main_gate.add_constant(ctx, &x, -omega)?;
+                main_gate.div(ctx, &numerator, &denominator)
+            })
+            .collect::<Result<Vec<_>, Error>>()?;
+        let l_last = &l_evals[0];
+        let l_0 = &l_evals[1 + blinding_factors];
+        let mut active_terms = vec![(Term::Assigned(l_last), -Fr::one())];
+        for eval in l_evals[1..(1 + blinding_factors)].iter() {
+            active_terms.push((Term::Assigned(eval), -Fr::one()));
+        }
+        let active_rows = main_gate.linear_combination(ctx, &active_terms, Fr::one())?;
+
+        // Quotient evaluation
+        let mut h_eval = main_gate.assign_constant(ctx, Fr::zero())?;
+        let mut push_quotient = |ctx: &mut Context<'_>, value: AssignedValue| {
+            h_eval = main_gate.mul_add(ctx, &h_eval, &y, &value)?;
+            Ok::<_, Error>(())
+        };
+        for gate in cs.gates.iter() {
+            for poly in gate.polynomials() {
+                let value = self.evaluate(ctx, poly, &evals)?;
+                push_quotient(ctx, value)?;
+            }
+        }
+
+        let column_eval = |column: &Column<Any>| match column.column_type() {
+            Any::Advice => &evals.advice[cs.get_any_query_index(*column, Rotation::cur())],
+            Any::Fixed => &evals.fixed[cs.get_any_query_index(*column, Rotation::cur())],
+            Any::Instance => &evals.instance[cs.get_any_query_index(*column, Rotation::cur())],
+        };
+        if let Some(first_set) = permutation_sets.first() {
+            let value = main_gate.linear_combination(
+                ctx,
+                &[(Term::Assigned(&first_set.eval), -Fr::one())],
+                Fr::one(),
+            )?;
+            let value = main_gate.mul(ctx, l_0, &value)?;
+            push_quotient(ctx, value)?;
+        }
+        if let Some(last_set) = permutation_sets.last() {
+            let value = self.square_minus_self(ctx, &last_set.eval)?;
+            let value = main_gate.mul(ctx, &value, l_last)?;
+            push_quotient(ctx, value)?;
+        }
+        for (set, last_set) in permutation_sets.iter().skip(1).zip(permutation_sets.iter()) {
+            let value = main_gate.sub(ctx, &set.eval, last_set.last_eval.as_ref().unwrap())?;
+            let value = main_gate.mul(ctx, &value, l_0)?;
+            push_quotient(ctx, value)?;
+        }
+        let beta_x = main_gate.mul(ctx, &beta, &x)?;
+        for (chunk_index, ((set, columns), permutation_evals)) in permutation_sets
+            .iter()
+            .zip(permutation_columns.chunks(chunk_len))
+            .zip(permutation_evals.chunks(chunk_len))
+            .enumerate()
+        {
+            let mut left = set.next_eval.clone();
+            for (column, permutation_eval) in columns.iter().zip(permutation_evals.iter()) {
+                let term = main_gate.mul_add(ctx, &beta, permutation_eval, column_eval(column))?;
+                let term = main_gate.add(ctx, &term, &gamma)?;
+                left = main_gate.mul(ctx, &left, &term)?;
+            }
+            let mut right = set.eval.clone();
+            let mut delta = Fr::DELTA.pow_vartime(&[(chunk_index * chunk_len) as u64]);
+            for column in columns.iter() {
+                let term = main_gate.linear_combination(
+                    ctx,
+                    &[
+                        (Term::Assigned(column_eval(column)), Fr::one()),
+                        (Term::Assigned(&beta_x), delta),
+                        (Term::Assigned(&gamma), Fr::one()),
+                    ],
+                    Fr::zero(),
+                )?;
+                right = main_gate.mul(ctx, &right, &term)?;
+                delta *= &Fr::DELTA;
+            }
+            let value = main_gate.sub(ctx, &left, &right)?;
+            let value = main_gate.mul(ctx, &value, &active_rows)?;
+            push_quotient(ctx, value)?;
+        }
+
+        let challenges = Challenges {
+            beta: &beta,
+            gamma: &gamma,
+            l_0,
+            l_last,
+            active_rows: &active_rows,
+        };
+        for (lookup, argument) in lookups.iter().zip(cs.lookups.iter()) {
+            let inputs = self.compress(ctx, &argument.input_expressions, &evals, &theta)?;
+            let tables = self.compress(ctx, &argument.table_expressions, &evals, &theta)?;
+            for value in lookup_expressions(ctx, main_gate, lookup, &challenges, &inputs, &tables)?
+            {
+                push_quotient(ctx, value)?;
+            }
+        }
+
+        for ((_, product_eval, product_next_eval), argument) in
+            shuffles.iter().zip(cs.shuffles.iter())
+        {
+            let value = main_gate.linear_combination(
+                ctx,
+                &[(Term::Assigned(product_eval), -Fr::one())],
+                Fr::one(),
+            )?;
+            let value = main_gate.mul(ctx, l_0, &value)?;
+            push_quotient(ctx, value)?;
+            let value = self.square_minus_self(ctx, product_eval)?;
+            let value = main_gate.mul(ctx, l_last, &value)?;
+            push_quotient(ctx, value)?;
+            let inputs = self.compress(ctx, &argument.input_expressions, &evals, &theta)?;
+            let shuffled = self.compress(ctx, &argument.shuffle_expressions, &evals, &theta)?;
+            let left = main_gate.add(ctx, &shuffled, &gamma)?;
+            let left = main_gate.mul(ctx, product_next_eval, &left)?;
+            let right = main_gate.add(ctx, &inputs, &gamma)?;
+            let right = main_gate.mul(ctx, product_eval, &right)?;
+            let value = main_gate.sub(ctx, &left, &right)?;
+            let value = main_gate.mul(ctx, &value, &active_rows)?;
+            push_quotient(ctx, value)?;
+        }
+
+        let vanishing = main_gate.add_constant(ctx, &xn, -Fr::one())?;
+        let expected_h_eval = main_gate.div(ctx, &h_eval, &vanishing)?;
+        let mut h_commitment = Msm::default();
+        let mut xn_power: Option<AssignedValue> = None;
+        for h in h_commitments.into_iter() {
+            h_commitment
+                .terms
+                .push((Base::Assigned(h), xn_power.clone()));
+            xn_power = Some(match xn_power {
+                Some(power) => main_gate.mul(ctx, &power, &xn)?,
+                None => xn.clone(),
+            });
+        }
+        commitments.push(h_commitment);
+        let h_commitment = commitments.len() - 1;
+
+        // Queries
+        let mut queries = vec![];
+        for (&(column, at), eval) in cs.instance_queries.iter().zip(evals.instance.iter()) {
+            queries.push(Query::new(instance_commitments[column.index()], at, eval));
+        }
+        for (&(column, at), eval) in cs.advice_queries.iter().zip(evals.advice.iter()) {
+            queries.push(Query::new(advice_commitments[column.index()], at, eval));
+        }
+        let last_rotation = Rotation(-((blinding_factors + 1) as i32));
+        for set in permutation_sets.iter() {
+            queries.push(Query::new(set.commitment, Rotation::cur(), &set.eval));
+            queries.push(Query::new(set.commitment, Rotation::next(), &set.next_eval));
+        }
+        for set in permutation_sets.iter().rev().skip(1) {
+            queries.push(Query::new(
+                set.commitment,
+                last_rotation,
+                set.last_eval.as_ref().unwrap(),
+            ));
+        }
+        for lookup in lookups.iter() {
+            queries.extend(lookup_queries(lookup));
+        }
+        for (commitment, product_eval, product_next_eval) in shuffles.iter() {
+            queries.push(Query::new(*commitment, Rotation::cur(), product_eval));
+            queries.push(Query::new(*commitment, Rotation::next(), product_next_eval));
+        }
+        let mut fixed_commitments = BTreeMap::new();
+        for (&(column, at), eval) in cs.fixed_queries.iter().zip(evals.fixed.iter()) {
+            let commitment = *fixed_commitments.entry(column.index()).or_insert_with(|| {
+                let base = Base::Constant(vk.fixed_commitments()[column.index()]);
+                commitments.push(Msm::base(base));
+                commitments.len() - 1
+            });
+            queries.push(Query::new(commitment, at, eval));
+        }
+        for (commitment, eval) in vk
+            .permutation_commitments()
+            .iter()
+            .zip(permutation_evals.iter())
+        {
+            commitments.push(Msm::base(Base::Constant(*commitment)));
+            queries.push(Query::new(commitments.len() - 1, Rotation::cur(), eval));
+        }
+        queries.push(Query::new(h_commitment, Rotation::cur(), &expected_h_eval));
+        queries.push(Query::new(random_poly, Rotation::cur(), &random_eval));
+
+        // Like the native verifier, only open each commitment once per rotation.
+        let mut unique_queries: Vec<Query> = vec![];
+        for query in queries {
+            if !unique_queries
+                .iter()
+                .any(|q| q.commitment == query.commitment && q.rotation == query.rotation)
+            {
+                unique_queries.push(query);
+            }
+        }
+        let queries = unique_queries;
+
+        let mut points = BTreeMap::new();
+        for query in queries.iter() {
+            if !points.contains_key(&query.rotation) {
+                let omega = vk.domain().rotate_omega(Fr::one(), query.rotation);
+                points.insert(query.rotation, main_gate.mul_constant(ctx, &x, omega)?);
+            }
+        }
+
+        // Multi-opening argument
+        let (lhs, rhs) = self.multiopen(
+            ctx,
+            &mut transcript,
+            params,
+            vk,
+            &commitments,
+            &queries,
+            &points,
+            &x,
+        )?;
+
+        let one = main_gate.assign_constant(ctx, Fr::one())?;
+        let terms: Vec<_> = rhs
+            .terms
+            .into_iter()
+            .map(|(base, scalar)| (base, scalar.unwrap_or_else(|| one.clone())))
+            .collect();
+        let rhs = self.ecc.msm(ctx, &terms)?;
+        let rhs = self.ecc.reduce(ctx, &rhs)?;
+
+        Ok(AssignedAccumulator { lhs, rhs })
+    }
+
+    /// Evaluates an expression at the queried evaluations.
+    fn evaluate(
+        &self,
+        ctx: &mut Context<'_>,
+        expression: &Expression<Fr>,
+        evals: &Evals,
+    ) -> Result<AssignedValue, Error> {
+        let main_gate = self.main_gate();
+        match expression {
+            Expression::Constant(scalar) => main_gate.assign_constant(ctx, *scalar),
+            Expression::Selector(_) => panic!("virtual selectors are removed during optimization"),
+            Expression::Fixed { query_index, .. } => Ok(evals.fixed[*query_index].clone()),
+            Expression::Advice { query_index, .. } => Ok(evals.advice[*query_index].clone()),
+            Expression::Instance { query_index, .. } => Ok(evals.instance[*query_index].clone()),
+            Expression::Negated(a) => {
+                let a = self.evaluate(ctx, a, evals)?;
+                main_gate.neg(ctx, &a)
+            }
+            Expression::Sum(a, b) => {
+                let a = self.evaluate(ctx, a, evals)?;
+                let b = self.evaluate(ctx, b, evals)?;
+                main_gate.add(ctx, &a, &b)
+            }
+            Expression::Product(a, b) => {
+                let a = self.evaluate(ctx, a, evals)?;
+                let b = self.evaluate(ctx, b, evals)?;
+                main_gate.mul(ctx, &a, &b)
+            }
+            Expression::Scaled(a, scalar) => {
+                let a = self.evaluate(ctx, a, evals)?;
+                main_gate.mul_constant(ctx, &a, *scalar)
+            }
+        }
+    }
+
+    /// Evaluates `\theta^{m-1} e_0 + ... + e_{m-1}`.
+    fn compress(
+        &self,
+        ctx: &mut Context<'_>,
+        expressions: &[Expression<Fr>],
+        evals: &Evals,
+        theta: &AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        let main_gate = self.main_gate();
+        let mut acc = main_gate.assign_constant(ctx, Fr::zero())?;
+        for expression in expressions {
+            let eval = self.evaluate(ctx, expression, evals)?;
+            acc = main_gate.mul_add(ctx, &acc, theta, &eval)?;
+        }
+        Ok(acc)
+    }
+
+    /// Returns `a^2 - a`.
+    fn square_minus_self(
+        &self,
+        ctx: &mut Context<'_>,
+        a: &AssignedValue,
+    ) -> Result<AssignedValue, Error> {
+        let main_gate = self.main_gate();
+        let minus_a = main_gate.neg(ctx, a)?;
+        main_gate.mul_add(ctx, a, a, &minus_a)
+    }
+
+    /// Verifies the SHPLONK multi-opening argument, returning the two points
+    /// of the final pairing check.
+    fn multiopen(
+        &self,
+        ctx: &mut Context<'_>,
+        transcript: &mut TranscriptChip<'_>,
+        params: &ParamsVerifier<Bn256>,
+        vk: &VerifyingKey<G1Affine>,
+        commitments: &[Msm],
+        queries: &[Query],
+        points: &BTreeMap<Rotation, AssignedValue>,
+        x: &AssignedValue,
+    ) -> Result<(AssignedPoint, Msm), Error> {
+        let main_gate = self.main_gate();
+
+        // Group the commitments by the set of rotations at which they are
+        // opened, in the same order as the native verifier.
+        let mut commitment_rotations: Vec<(usize, BTreeSet<Rotation>)> = vec![];
+        for query in queries.iter() {
+            if let Some(pos) = commitment_rotations
+                .iter()
+                .position(|(commitment, _)| *commitment == query.commitment)
+            {
+                commitment_rotations[pos].1.insert(query.rotation);
+            } else {
+                let rotations = std::iter::once(query.rotation).collect();
+                commitment_rotations.push((query.commitment, rotations));
+            }
+        }
+        let mut rotation_sets = BTreeMap::<BTreeSet<Rotation>, Vec<usize>>::new();
+        for (commitment, rotations) in commitment_rotations {
+            let commitments = rotation_sets.entry(rotations).or_insert_with(Vec::new);
+            if !commitments.contains(&commitment) {
+                commitments.push(commitment);
+            }
+        }
+        let eval = |commitment: usize, rotation: Rotation| {
+            &queries
+                .iter()
+                .find(|query| query.commitment == commitment && query.rotation == rotation)
+                .unwrap()
+                .eval
+        };
+
+        let y = transcript.squeeze_challenge(ctx)?;
+        let v = transcript.squeeze_challenge(ctx)?;
+        let h1 = transcript.read_point(ctx)?;
+        let u = transcript.squeeze_challenge(ctx)?;
+        let h2 = transcript.read_point(ctx)?;
+
+        let x_inv = main_gate.invert(ctx, x)?;
+        let v_powers = self.descending_powers(ctx, &v, rotation_sets.len())?;
+        let max_commitments = rotation_sets.values().map(Vec::len).max().unwrap();
+        let y_powers = self.descending_powers(ctx, &y, max_commitments)?;
+        let u_minus_points = points
+            .iter()
+            .map(|(rotation, point)| Ok((*rotation, main_gate.sub(ctx, &u, point)?)))
+            .collect::<Result<BTreeMap<_, _>, Error>>()?;
+
+        let mut right = Msm::default();
+        let mut r_outer = main_gate.assign_constant(ctx, Fr::zero())?;
+        let mut z_0 = None;
+        let mut z_0_diff_inv = None;
+        for (i, (rotations, set_commitments)) in rotation_sets.iter().enumerate() {
+            let diffs: Vec<_> = u_minus_points
+                .iter()
+                .filter(|(rotation, _)| !rotations.contains(*rotation))
+                .map(|(_, diff)| diff)
+                .collect();
+            let z_diff = self.product(ctx, &diffs)?;
+            let z_diff = match &z_0_diff_inv {
+                None => {
+                    let set_diffs: Vec<_> = rotations.iter().map(|r| &u_minus_points[r]).collect();
+                    z_0 = Some(self.product(ctx, &set_diffs)?);
+                    z_0_diff_inv = Some(main_gate.invert(ctx, &z_diff)?);
+                    main_gate.assign_constant(ctx, Fr::one())?
+                }
+                Some(inv) => main_gate.mul(ctx, &z_diff, inv)?,
+            };
+
+            // The Lagrange basis of the set's points, evaluated at u. The
+            // points are x \omega^{r_k}, so the denominators are x^{m-1}
+            // times constants.
+            let rotations: Vec<Rotation> = rotations.iter().cloned().collect();
+            let omegas: Vec<Fr> = rotations
+                .iter()
+                .map(|&rotation| vk.domain().rotate_omega(Fr::one(), rotation))
+                .collect();
+            let mut x_inv_pow = main_gate.assign_constant(ctx, Fr::one())?;
+            for _ in 1..rotations.len() {
+                x_inv_pow = main_gate.mul(ctx, &x_inv_pow, &x_inv)?;
+            }
+            let basis = (0..rotations.len())
+                .map(|k| {
+                    let denominator = omegas
+                        .iter()
+                        .enumerate()
+                        .filter(|&(l, _)| l != k)
+                        .fold(Fr::one(), |acc, (_, omega)| acc * (omegas[k] - omega));
+                    let mut basis =
+                        main_gate.mul_constant(ctx, &x_inv_pow, denominator.invert().unwrap())?;
+                    for (l, rotation) in rotations.iter().enumerate() {
+                        if l != k {
+                            basis = main_gate.mul(ctx, &basis, &u_minus_points[rotation])?;
+                        }
+                    }
+                    Ok(basis)
+                })
+                .collect::<Result<Vec<_>, Error>>()?;
+
+            let mut r_inner = main_gate.assign_constant(ctx, Fr::zero())?;
+            let set_y_powers = &y_powers[max_commitments - set_commitments.len()..];
+            for (&commitment, y_power) in set_commitments.iter().zip(set_y_powers.iter()) {
+                let mut r_eval = main_gate.assign_constant(ctx, Fr::zero())?;
+                for (rotation, basis) in rotations.iter().zip(basis.iter()) {
+                    r_eval = main_gate.mul_add(ctx, eval(commitment, *rotation), basis, &r_eval)?;
+                }
+                r_inner = main_gate.mul_add(ctx, &r_inner, &y, &r_eval)?;
+
+                let coeff = main_gate.mul(ctx, &z_diff, y_power)?;
+                let coeff = main_gate.mul(ctx, &v_powers[i], &coeff)?;
+                right.extend(commitments[commitment].scale(ctx, main_gate, &coeff)?);
+            }
+            let r_inner = main_gate.mul(ctx, &r_inner, &z_diff)?;
+            r_outer = main_gate.mul_add(ctx, &r_outer, &v, &r_inner)?;
+        }
+
+        let minus_r_outer = main_gate.neg(ctx, &r_outer)?;
+        let minus_z_0 = main_gate.neg(ctx, &z_0.unwrap())?;
+        right
+            .terms
+            .push((Base::Constant(params.g1), Some(minus_r_outer)));
+        right.terms.push((Base::Assigned(h1), Some(minus_z_0)));
+        right.terms.push((Base::Assigned(h2.clone()), Some(u)));
+
+        Ok((h2, right))
+    }
+
+    /// Returns the product of `values`, or one if there are none.
+    fn product(
+        &self,
+        ctx: &mut Context<'_>,
+        values: &[&AssignedValue],
+    ) -> Result<AssignedValue, Error> {
+        let main_gate = self.main_gate();
+        let mut acc = main_gate.assign_constant(ctx, Fr::one())?;
+        for value in values {
+            acc = main_gate.mul(ctx, &acc, value)?;
+        }
+        Ok(acc)
+    }
+
+    /// Returns `[base^{n-1}, ..., base, 1]`.
+    fn descending_powers(
+        &self,
+        ctx: &mut Context<'_>,
+        base: &AssignedValue,
+        n: usize,
+    ) -> Result<Vec<AssignedValue>, Error> {
+        let main_gate = self.main_gate();
+        let mut powers = vec![main_gate.assign_constant(ctx, Fr::one())?];
+        for _ in 1..n {
+            let last = powers.last().unwrap();
+            let next = main_gate.mul(ctx, last, base)?;
+            powers.push(next);
+        }
+        powers.reverse();
+        Ok(powers)
+    }
+}
+
+#[cfg(not(feature = "mv-lookup"))]
+const LOOKUP_EVALS: usize = 5;
+
+/// Reads the permuted input and table commitments of a lookup.
+#[cfg(not(feature = "mv-lookup"))]
+fn read_lookup_permuted(
+    ctx: &mut Context<'_>,
+    transcript: &mut TranscriptChip<'_>,
+) -> Result<Vec<AssignedPoint>, Error> {
+    transcript.read_points(ctx, 2)
+}
+
+#[cfg(not(feature = "mv-lookup"))]
+fn lookup_expressions(
+    ctx: &mut Context<'_>,
+    main_gate: &MainGate,
+    lookup: &Lookup,
+    challenges: &Challenges<'_>,
+    inputs: &AssignedValue,
+    tables: &AssignedValue,
+) -> Result<Vec<AssignedValue>, Error> {
+    let (product_eval, product_next_eval, input_eval, input_inv_eval, table_eval) = (
+        &lookup.evals[0],
+        &lookup.evals[1],
+        &lookup.evals[2],
+        &lookup.evals[3],
+        &lookup.evals[4],
+    );
+    let Challenges {
+        beta,
+        gamma,
+        l_0,
+        l_last,
+        active_rows,
+    } = *challenges;
+
+    let input_beta = main_gate.add(ctx, input_eval, beta)?;
+    let table_gamma = main_gate.add(ctx, table_eval, gamma)?;
+    let left = main_gate.mul(ctx, product_next_eval, &input_beta)?;
+    let left = main_gate.mul(ctx, &left, &table_gamma)?;
+    let inputs_beta = main_gate.add(ctx, inputs, beta)?;
+    let tables_gamma = main_gate.add(ctx, tables, gamma)?;
+    let right = main_gate.mul(ctx, product_eval, &inputs_beta)?;
+    let right = main_gate.mul(ctx, &right, &tables_gamma)?;
+
+    let one_minus_product = main_gate.linear_combination(
+        ctx,
+        &[(Term::Assigned(product_eval), -Fr::one())],
+        Fr::one(),
+    )?;
+    let minus_product = main_gate.neg(ctx, product_eval)?;
+    let product_square = main_gate.mul_add(ctx, product_eval, product_eval, &minus_product)?;
+    let left_right = main_gate.sub(ctx, &left, &right)?;
+    let input_table = main_gate.sub(ctx, input_eval, table_eval)?;
+    let input_input_inv = main_gate.sub(ctx, input_eval, input_inv_eval)?;
+    let input_product = main_gate.mul(ctx, &input_table, &input_input_inv)?;
+
+    Ok(vec![
+        main_gate.mul(ctx, l_0, &one_minus_product)?,
+        main_gate.mul(ctx, l_last, &product_square)?,
+        main_gate.mul(ctx, &left_right, active_rows)?,
+        main_gate.mul(ctx, l_0, &input_table)?,
+        main_gate.mul(ctx, &input_product, active_rows)?,
+    ])
+}
+
+#[cfg(not(feature = "mv-lookup"))]
+fn lookup_queries(lookup: &Lookup) -> Vec<Query> {
+    let (input, table, product) = (
+        lookup.commitments[0],
+        lookup.commitments[1],
+        lookup.commitments[2],
+    );
+    vec![
+        Query::new(product, Rotation::cur(), &lookup.evals[0]),
+        Query::new(input, Rotation::cur(), &lookup.evals[2]),
+        Query::new(table, Rotation::cur(), &lookup.evals[4]),
+        Query::new(input, Rotation::prev(), &lookup.evals[3]),
+        Query::new(product, Rotation::next(), &lookup.evals[1]),
+    ]
+}
+
+#[cfg(feature = "mv-lookup")]
+const LOOKUP_EVALS: usize = 3;
+
+/// Reads the multiplicity commitment of a lookup.
+#[cfg(feature = "mv-lookup")]
+fn read_lookup_permuted(
+    ctx: &mut Context<'_>,
+    transcript: &mut TranscriptChip<'_>,
+) -> Result<Vec<AssignedPoint>, Error> {
+    transcript.read_points(ctx, 1)
+}
+
+#[cfg(feature = "mv-lookup")]
+fn lookup_expressions(
+    ctx: &mut Context<'_>,
+    main_gate: &MainGate,
+    lookup: &Lookup,
+    challenges: &Challenges<'_>,
+    inputs: &AssignedValue,
+    tables: &AssignedValue,
+) -> Result<Vec<AssignedValue>, Error> {
+    let (phi_eval, phi_next_eval, multiplicity_eval) =
+        (&lookup.evals[0], &lookup.evals[1], &lookup.evals[2]);
+    let Challenges {
+        beta,
+        l_0,
+        l_last,
+        active_rows,
+        ..
+    } = *challenges;
+
+    let input_term = main_gate.add(ctx, inputs, beta)?;
+    let table_term = main_gate.add(ctx, tables, beta)?;
+    let phi_diff = main_gate.sub(ctx, phi_next_eval, phi_eval)?;
+    let left = main_gate.mul(ctx, &phi_diff, &input_term)?;
+    let left = main_gate.mul(ctx, &left, &table_term)?;
+    let multiplicity_input = main_gate.mul(ctx, multiplicity_eval, &input_term)?;
+    let right = main_gate.sub(ctx, &table_term, &multiplicity_input)?;
+    let left_right = main_gate.sub(ctx, &left, &right)?;
+
+    Ok(vec![
+        main_gate.mul(ctx, l_0, phi_eval)?,
+        main_gate.mul(ctx, l_last, phi_eval)?,
+        main_gate.mul(ctx, &left_right, active_rows)?,
+    ])
+}
+
+#[cfg(feature = "mv-lookup")]
+fn lookup_queries(lookup: &Lookup) -> Vec<Query> {
+    let (multiplicity, phi) = (lookup.commitments[0], lookup.commitments[1]);
+    vec![
+        Query::new(phi, Rotation::cur(), &lookup.evals[0]),
+        Query::new(multiplicity, Rotation::cur(), &lookup.evals[2]),
+        Query::new(phi, Rotation::next(), &lookup.evals[1]),
+    ]
+}
@@ -2,7 +2,8 @@
 //! transcripts.
 
 use blake2b_simd::{Params as Blake2bParams, State as Blake2bState};
-use group::ff::PrimeField;
+use group::ff::{Field, PrimeField};
+use group::prime::PrimeCurveAffine;
 use std::convert::TryInto;
 
 use crate::arithmetic::{BaseExt, Coordinates, CurveAffine, FieldExt};
@@ -10,14 +11,85 @@ use crate::arithmetic::{BaseExt, Coordinates, CurveAffine, FieldExt};
 use std::io::{self, Read, Write};
 use std::marker::PhantomData;
 
+mod envelope;
+pub mod keccak;
+pub mod poseidon;
+
+pub use envelope::ProofEnvelope;
+
 /// Prefix to a prover's message soliciting a challenge
-const BLAKE2B_PREFIX_CHALLENGE: u8 = 0;
+pub(crate) const BLAKE2B_PREFIX_CHALLENGE: u8 = 0;
 
 /// Prefix to a prover's message containing a curve point
-const BLAKE2B_PREFIX_POINT: u8 = 1;
+pub(crate) const BLAKE2B_PREFIX_POINT: u8 = 1;
 
 /// Prefix to a prover's message containing a scalar
-const BLAKE2B_PREFIX_SCALAR: u8 = 2;
+pub(crate) const BLAKE2B_PREFIX_SCALAR: u8 = 2;
+
+/// The prefixes that a transcript absorbs before each message, which tell
+/// points, scalars and challenge requests apart.
+///
+/// Deployments sharing infrastructure with other halo2-based systems can pick
+/// their own prefixes to domain-separate their proofs from the others. The
+/// verifier must use the same prefixes as the prover. The default prefixes
+/// are those of upstream halo2. [`keccak`] transcripts absorb no prefixes, to
+/// match the generated Solidity verifier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TranscriptPrefixes {
+    pub(crate) challenge: u8,
+    pub(crate) point: u8,
+    pub(crate) scalar: u8,
+}
+
+impl TranscriptPrefixes {
+    /// Constructs the prefixes to messages soliciting a challenge, containing
+    /// a curve point and containing a scalar respectively.
+    ///
+    /// Panics if two of the prefixes are equal, which would make the
+    /// messages ambiguous.
+    pub fn new(challenge: u8, point: u8, scalar: u8) -> Self {
+        assert!(
+            challenge != point && challenge != scalar && point != scalar,
+            "transcript prefixes must be distinct"
+        );
+        TranscriptPrefixes {
+            challenge,
+            point,
+            scalar,
+        }
+    }
+}
+
+impl Default for TranscriptPrefixes {
+    fn default() -> Self {
+        TranscriptPrefixes::new(
+            BLAKE2B_PREFIX_CHALLENGE,
+            BLAKE2B_PREFIX_POINT,
+            BLAKE2B_PREFIX_SCALAR,
+        )
+    }
+}
+
+/// How a [`Blake2bWrite`] writes curve points to a proof, and a [`Blake2bRead`]
+/// reads them.
+///
+/// Transcripts absorb the coordinates of points whatever their encoding, so the
+/// encoding changes the size of a proof but not its challenges.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PointEncoding {
+    /// The compressed encoding of the curve. This is the default.
+    Compressed,
+    /// The canonical encodings of the affine `x` and `y` coordinates, which
+    /// take twice the space but are decoded without computing a square root.
+    /// The point at infinity is encoded as `(0, 0)`.
+    Uncompressed,
+}
+
+impl Default for PointEncoding {
+    fn default() -> Self {
+        PointEncoding::Compressed
+    }
+}
 
 /// Generic transcript view (from either the prover or verifier's perspective)
 pub trait Transcript<C: CurveAffine, E: EncodedChallenge<C>> {
@@ -49,6 +121,25 @@ pub trait TranscriptRead<C: CurveAffine, E: EncodedChallenge<C>>: Transcript<C,
 
     /// Read a curve scalar from the prover.
     fn read_scalar(&mut self) -> io::Result<C::Scalar>;
+
+    /// Read a curve point that an honest prover never sends as the point at
+    /// infinity, such as the commitment to the random polynomial of the
+    /// vanishing argument. By default this is [`TranscriptRead::read_point`];
+    /// the transcript of [`verify_proof_strict`] rejects the point at infinity.
+    ///
+    /// [`verify_proof_strict`]: crate::plonk::verify_proof_strict
+    fn read_non_identity_point(&mut self) -> io::Result<C> {
+        self.read_point()
+    }
+
+    /// Returns an error if the proof has bytes left after everything that has
+    /// been read from it.
+    ///
+    /// [`verify_proof_strict`] relies on it to reject trailing bytes, so there is
+    /// no default: a transcript whose reader can't tell must return an error.
+    ///
+    /// [`verify_proof_strict`]: crate::plonk::verify_proof_strict
+    fn ensure_exhausted(&mut self) -> io::Result<()>;
 }
 
 /// Transcript view from the perspective of a prover that has access to an
@@ -59,6 +150,15 @@ pub trait TranscriptWrite<C: CurveAffine, E: EncodedChallenge<C>>: Transcript<C,
 
     /// Write a scalar to the proof and the transcript.
     fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()>;
+
+    /// Flushes the proof written so far to the underlying writer.
+    ///
+    /// The prover calls this at the end of each round of the protocol, so that
+    /// the proof can be streamed through a buffered writer. The default
+    /// implementation does nothing.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
 }
 
 /// We will replace BLAKE2b with an algebraic hash function in a later version.
@@ -66,6 +166,8 @@ pub trait TranscriptWrite<C: CurveAffine, E: EncodedChallenge<C>>: Transcript<C,
 pub struct Blake2bRead<R: Read, C: CurveAffine, E: EncodedChallenge<C>> {
     state: Blake2bState,
     reader: R,
+    prefixes: TranscriptPrefixes,
+    point_encoding: PointEncoding,
     _marker: PhantomData<(C, E)>,
 }
 
@@ -78,18 +180,44 @@ impl<R: Read, C: CurveAffine, E: EncodedChallenge<C>> Blake2bRead<R, C, E> {
                 .personal(b"Halo2-Transcript")
                 .to_state(),
             reader,
+            prefixes: TranscriptPrefixes::default(),
+            point_encoding: PointEncoding::default(),
             _marker: PhantomData,
         }
     }
+
+    /// Uses `prefixes` instead of the default ones. This must be done before
+    /// anything is absorbed.
+    pub fn with_prefixes(mut self, prefixes: TranscriptPrefixes) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    /// Uses `point_encoding` for the points of the proof instead of the
+    /// compressed encoding.
+    pub fn with_point_encoding(mut self, point_encoding: PointEncoding) -> Self {
+        self.point_encoding = point_encoding;
+        self
+    }
 }
 
 impl<R: Read, C: CurveAffine> TranscriptRead<C, Challenge255<C>>
     for Blake2bRead<R, C, Challenge255<C>>
 {
     fn read_point(&mut self) -> io::Result<C> {
-        let mut compressed = C::Repr::default();
-        self.reader.read_exact(compressed.as_mut())?;
-        let point: C = Option::from(C::from_bytes(&compressed)).ok_or_else(|| {
+        let point = match self.point_encoding {
+            PointEncoding::Compressed => {
+                let mut compressed = C::Repr::default();
+                self.reader.read_exact(compressed.as_mut())?;
+                Option::from(C::from_bytes(&compressed))
+            }
+            PointEncoding::Uncompressed => {
+                let x = read_base::<_, C>(&mut self.reader)?;
+                let y = read_base::<_, C>(&mut self.reader)?;
+                point_from_coordinates(x, y)
+            }
+        };
+        let point: C = point.ok_or_else(|| {
             io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof")
         })?;
         self.common_point(point)?;
@@ -110,34 +238,33 @@ impl<R: Read, C: CurveAffine> TranscriptRead<C, Challenge255<C>>
 
         Ok(scalar)
     }
+
+    fn ensure_exhausted(&mut self) -> io::Result<()> {
+        ensure_exhausted(&mut self.reader)
+    }
 }
 
 impl<R: Read, C: CurveAffine> Transcript<C, Challenge255<C>>
     for Blake2bRead<R, C, Challenge255<C>>
 {
     fn squeeze_challenge(&mut self) -> Challenge255<C> {
-        self.state.update(&[BLAKE2B_PREFIX_CHALLENGE]);
+        self.state.update(&[self.prefixes.challenge]);
         let hasher = self.state.clone();
         let result: [u8; 64] = hasher.finalize().as_bytes().try_into().unwrap();
         Challenge255::<C>::new(&result)
     }
 
     fn common_point(&mut self, point: C) -> io::Result<()> {
-        self.state.update(&[BLAKE2B_PREFIX_POINT]);
-        let coords: Coordinates<C> = Option::from(point.coordinates()).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                "cannot write points at infinity to the transcript",
-            )
-        })?;
-        coords.x().write(&mut self.state)?;
-        coords.y().write(&mut self.state)?;
+        self.state.update(&[self.prefixes.point]);
+        let (x, y) = point_coordinates(point);
+        x.write(&mut self.state)?;
+        y.write(&mut self.state)?;
 
         Ok(())
     }
 
     fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
-        self.state.update(&[BLAKE2B_PREFIX_SCALAR]);
+        self.state.update(&[self.prefixes.scalar]);
         self.state.update(scalar.to_repr().as_ref());
 
         Ok(())
@@ -149,6 +276,8 @@ impl<R: Read, C: CurveAffine> Transcript<C, Challenge255<C>>
 pub struct Blake2bWrite<W: Write, C: CurveAffine, E: EncodedChallenge<C>> {
     state: Blake2bState,
     writer: W,
+    prefixes: TranscriptPrefixes,
+    point_encoding: PointEncoding,
     _marker: PhantomData<(C, E)>,
 }
 
@@ -161,10 +290,26 @@ impl<W: Write, C: CurveAffine, E: EncodedChallenge<C>> Blake2bWrite<W, C, E> {
                 .personal(b"Halo2-Transcript")
                 .to_state(),
             writer,
+            prefixes: TranscriptPrefixes::default(),
+            point_encoding: PointEncoding::default(),
             _marker: PhantomData,
         }
     }
 
+    /// Uses `prefixes` instead of the default ones. This must be done before
+    /// anything is absorbed.
+    pub fn with_prefixes(mut self, prefixes: TranscriptPrefixes) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    /// Uses `point_encoding` for the points of the proof instead of the
+    /// compressed encoding.
+    pub fn with_point_encoding(mut self, point_encoding: PointEncoding) -> Self {
+        self.point_encoding = point_encoding;
+        self
+    }
+
     /// Conclude the interaction and return the output buffer (writer).
     pub fn finalize(self) -> W {
         // TODO: handle outstanding scalars? see issue #138
@@ -177,42 +322,50 @@ impl<W: Write, C: CurveAffine> TranscriptWrite<C, Challenge255<C>>
 {
     fn write_point(&mut self, point: C) -> io::Result<()> {
         self.common_point(point)?;
-        let compressed = point.to_bytes();
-        self.writer.write_all(compressed.as_ref())
+        match self.point_encoding {
+            PointEncoding::Compressed => {
+                let compressed = point.to_bytes();
+                self.writer.write_all(compressed.as_ref())
+            }
+            PointEncoding::Uncompressed => {
+                let (x, y) = point_coordinates(point);
+                self.writer.write_all(x.to_repr().as_ref())?;
+                self.writer.write_all(y.to_repr().as_ref())
+            }
+        }
     }
     fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
         self.common_scalar(scalar)?;
         let data = scalar.to_repr();
         self.writer.write_all(data.as_ref())
     }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
 }
 
 impl<W: Write, C: CurveAffine> Transcript<C, Challenge255<C>>
     for Blake2bWrite<W, C, Challenge255<C>>
 {
     fn squeeze_challenge(&mut self) -> Challenge255<C> {
-        self.state.update(&[BLAKE2B_PREFIX_CHALLENGE]);
+        self.state.update(&[self.prefixes.challenge]);
         let hasher = self.state.clone();
         let result: [u8; 64] = hasher.finalize().as_bytes().try_into().unwrap();
         Challenge255::<C>::new(&result)
     }
 
     fn common_point(&mut self, point: C) -> io::Result<()> {
-        self.state.update(&[BLAKE2B_PREFIX_POINT]);
-        let coords: Coordinates<C> = Option::from(point.coordinates()).ok_or_else(|| {
-            io::Error::new(
-                io::ErrorKind::Other,
-                "cannot write points at infinity to the transcript",
-            )
-        })?;
-        coords.x().write(&mut self.state)?;
-        coords.y().write(&mut self.state)?;
+        self.state.update(&[self.prefixes.point]);
+        let (x, y) = point_coordinates(point);
+        x.write(&mut self.state)?;
+        y.write(&mut self.state)?;
 
         Ok(())
     }
 
     fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
-        self.state.update(&[BLAKE2B_PREFIX_SCALAR]);
+        self.state.update(&[self.prefixes.scalar]);
         self.state.update(scalar.to_repr().as_ref());
 
         Ok(())
@@ -293,6 +446,101 @@ impl<C: CurveAffine> EncodedChallenge<C> for Challenge255<C> {
     }
 }
 
+/// A [`TranscriptRead`] that only accepts the unique encoding of a proof: the
+/// points read with [`TranscriptRead::read_non_identity_point`] can't be the
+/// point at infinity. Non-canonical scalar and point encodings are rejected by
+/// every transcript in this crate.
+#[derive(Debug)]
+pub(crate) struct StrictRead<'t, T>(pub(crate) &'t mut T);
+
+impl<'t, C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>> Transcript<C, E>
+    for StrictRead<'t, T>
+{
+    fn squeeze_challenge(&mut self) -> E {
+        self.0.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        self.0.common_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.0.common_scalar(scalar)
+    }
+}
+
+impl<'t, C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>> TranscriptRead<C, E>
+    for StrictRead<'t, T>
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        self.0.read_point()
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        self.0.read_scalar()
+    }
+
+    fn read_non_identity_point(&mut self) -> io::Result<C> {
+        let point = self.0.read_non_identity_point()?;
+        if bool::from(point.is_identity()) {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "point at infinity in proof",
+            ));
+        }
+        Ok(point)
+    }
+
+    fn ensure_exhausted(&mut self) -> io::Result<()> {
+        self.0.ensure_exhausted()
+    }
+}
+
+/// Returns the affine coordinates of `point`, with the point at infinity as
+/// `(0, 0)`. This isn't on any curve `y^2 = x^3 + b` with `b != 0`, so that the
+/// encoding is unambiguous. Honest provers commit to the point at infinity
+/// whenever a column is zero on every row, which happens without blinding.
+pub(crate) fn point_coordinates<C: CurveAffine>(point: C) -> (C::Base, C::Base) {
+    let coords: Option<Coordinates<C>> = point.coordinates().into();
+    coords.map_or((C::Base::zero(), C::Base::zero()), |coords| {
+        (*coords.x(), *coords.y())
+    })
+}
+
+/// Returns the point with affine coordinates `(x, y)`, or the point at infinity
+/// for `(0, 0)`, as encoded by [`point_coordinates`].
+pub(crate) fn point_from_coordinates<C: CurveAffine>(x: C::Base, y: C::Base) -> Option<C> {
+    if x.is_zero_vartime() && y.is_zero_vartime() {
+        Some(C::identity())
+    } else {
+        C::from_xy(x, y).into()
+    }
+}
+
+/// Returns an error if `reader` has any bytes left.
+pub(crate) fn ensure_exhausted<R: Read>(reader: &mut R) -> io::Result<()> {
+    let mut byte = [0u8; 1];
+    match reader.read(&mut byte)? {
+        0 => Ok(()),
+        _ => Err(io::Error::new(
+            io::ErrorKind::Other,
+            "trailing bytes after proof",
+        )),
+    }
+}
+
+/// Reads the canonical encoding of an element of the base field of `C`.
+fn read_base<R: Read, C: CurveAffine>(reader: &mut R) -> io::Result<C::Base> {
+    let mut data = <C::Base as PrimeField>::Repr::default();
+    reader.read_exact(data.as_mut())?;
+    Option::from(C::Base::from_repr(data)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "invalid field element encoding in proof",
+        )
+    })
+}
+
 pub(crate) fn read_n_points<C: CurveAffine, E: EncodedChallenge<C>, T: TranscriptRead<C, E>>(
     transcript: &mut T,
     n: usize,
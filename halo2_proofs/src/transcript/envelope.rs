@@ -0,0 +1,218 @@
+//! A header identifying the format of a proof.
+
+use std::io::{self, Read, Write};
+
+use super::{Blake2bRead, Blake2bWrite, Challenge255, PointEncoding};
+use crate::arithmetic::CurveAffine;
+use crate::poly::commitment::CommitmentScheme;
+
+/// The header of a proof, which identifies the format of the transcript that
+/// follows it.
+///
+/// A bare proof is an opaque sequence of bytes: a verifier reading a proof
+/// written by another version of this crate, with another commitment scheme or
+/// another point encoding, fails somewhere in the middle of the proof with an
+/// unrelated error, or rejects it as invalid. A proof written after an envelope
+/// is instead rejected up front, with an error saying what doesn't match.
+///
+/// The envelope is the four bytes of [`ProofEnvelope::MAGIC`], followed by one
+/// byte each for the version of the format, the commitment scheme and the point
+/// encoding.
+///
+/// ```
+/// use halo2_proofs::poly::commitment::CommitmentScheme;
+/// use halo2_proofs::transcript::{PointEncoding, ProofEnvelope};
+///
+/// let envelope = ProofEnvelope::new(CommitmentScheme::KzgGwc, PointEncoding::Uncompressed);
+/// let mut proof = vec![];
+/// envelope.write(&mut proof).unwrap();
+/// assert_eq!(ProofEnvelope::read(&mut &proof[..]).unwrap(), envelope);
+/// assert!(ProofEnvelope::read(&mut &proof[1..]).is_err());
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ProofEnvelope {
+    /// The commitment scheme the proof was created with.
+    pub scheme: CommitmentScheme,
+    /// The encoding of the curve points in the proof.
+    pub point_encoding: PointEncoding,
+}
+
+impl ProofEnvelope {
+    /// The bytes every envelope starts with.
+    pub const MAGIC: [u8; 4] = *b"H2PF";
+
+    /// The version of the format written by this build.
+    pub const VERSION: u8 = 1;
+
+    /// Returns the envelope of proofs created with `scheme`, with their points
+    /// written with `point_encoding`.
+    pub fn new(scheme: CommitmentScheme, point_encoding: PointEncoding) -> Self {
+        ProofEnvelope {
+            scheme,
+            point_encoding,
+        }
+    }
+
+    /// Writes this envelope to `writer`.
+    pub fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&Self::MAGIC)?;
+        writer.write_all(&[
+            Self::VERSION,
+            self.scheme.id(),
+            match self.point_encoding {
+                PointEncoding::Compressed => 0,
+                PointEncoding::Uncompressed => 1,
+            },
+        ])
+    }
+
+    /// Reads an envelope from `reader`.
+    ///
+    /// Returns an error if `reader` doesn't start with an envelope, if the
+    /// envelope has another version than [`ProofEnvelope::VERSION`], or if it
+    /// names an unknown commitment scheme or point encoding. The caller checks
+    /// that the scheme is that of its params.
+    pub fn read<R: Read>(reader: &mut R) -> io::Result<Self> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != Self::MAGIC {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                "proof does not start with an envelope",
+            ));
+        }
+
+        let mut header = [0u8; 3];
+        reader.read_exact(&mut header)?;
+        let [version, scheme, point_encoding] = header;
+        if version != Self::VERSION {
+            return Err(io::Error::new(
+                io::ErrorKind::Other,
+                format!(
+                    "proof envelope has version {}, but this build reads version {}",
+                    version,
+                    Self::VERSION
+                ),
+            ));
+        }
+        let scheme = CommitmentScheme::from_id(scheme)?;
+        let point_encoding = match point_encoding {
+            0 => PointEncoding::Compressed,
+            1 => PointEncoding::Uncompressed,
+            _ => {
+                return Err(io::Error::new(
+                    io::ErrorKind::Other,
+                    format!("unknown point encoding {}", point_encoding),
+                ))
+            }
+        };
+
+        Ok(ProofEnvelope {
+            scheme,
+            point_encoding,
+        })
+    }
+
+    /// Writes this envelope to `writer`, and returns a transcript writing a
+    /// proof with this envelope's point encoding after it.
+    pub fn blake2b_write<W: Write, C: CurveAffine>(
+        &self,
+        mut writer: W,
+    ) -> io::Result<Blake2bWrite<W, C, Challenge255<C>>> {
+        self.write(&mut writer)?;
+        Ok(Blake2bWrite::init(writer).with_point_encoding(self.point_encoding))
+    }
+
+    /// Reads an envelope from `reader`, and returns it with a transcript reading
+    /// the proof after it.
+    pub fn blake2b_read<R: Read, C: CurveAffine>(
+        mut reader: R,
+    ) -> io::Result<(Self, Blake2bRead<R, C, Challenge255<C>>)> {
+        let envelope = Self::read(&mut reader)?;
+        let transcript = Blake2bRead::init(reader).with_point_encoding(envelope.point_encoding);
+        Ok((envelope, transcript))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ProofEnvelope;
+    use crate::poly::commitment::CommitmentScheme;
+    use crate::transcript::{PointEncoding, Transcript, TranscriptRead, TranscriptWrite};
+    use ff::Field;
+    use group::{Curve, Group};
+    use pairing::bn256::{Fr, G1Affine, G1};
+    use rand_core::OsRng;
+
+    #[test]
+    fn envelope_round_trip() {
+        let point = G1::random(OsRng).to_affine();
+        let scalar = Fr::random(OsRng);
+
+        let mut challenges = vec![];
+        let mut sizes = vec![];
+        for point_encoding in [PointEncoding::Compressed, PointEncoding::Uncompressed].iter() {
+            let envelope = ProofEnvelope::new(CommitmentScheme::KzgShplonk, *point_encoding);
+            let mut transcript = envelope.blake2b_write::<_, G1Affine>(vec![]).unwrap();
+            transcript.write_point(point).unwrap();
+            transcript.write_scalar(scalar).unwrap();
+            let challenge = *transcript.squeeze_challenge_scalar::<()>();
+            let proof = transcript.finalize();
+
+            let (read_envelope, mut transcript) =
+                ProofEnvelope::blake2b_read::<_, G1Affine>(&proof[..]).unwrap();
+            assert_eq!(read_envelope, envelope);
+            assert_eq!(transcript.read_point().unwrap(), point);
+            assert_eq!(transcript.read_scalar().unwrap(), scalar);
+            assert_eq!(*transcript.squeeze_challenge_scalar::<()>(), challenge);
+            assert!(transcript.ensure_exhausted().is_ok());
+
+            challenges.push(challenge);
+            sizes.push(proof.len());
+        }
+
+        // The encoding of the points doesn't change the challenges.
+        assert_eq!(challenges[0], challenges[1]);
+        assert_eq!(sizes[1] - sizes[0], 32);
+    }
+
+    #[test]
+    fn envelope_mismatch() {
+        let mut proof = vec![];
+        ProofEnvelope::new(CommitmentScheme::KzgGwc, PointEncoding::Compressed)
+            .write(&mut proof)
+            .unwrap();
+
+        // A wrong magic, a future version, unknown commitment schemes and an
+        // unknown point encoding are rejected.
+        for (byte, value) in [
+            (0, b'h'),
+            (4, ProofEnvelope::VERSION + 1),
+            (5, 0),
+            (5, 3),
+            (6, 2),
+        ]
+        .iter()
+        {
+            let mut proof = proof.clone();
+            proof[*byte] = *value;
+            assert!(ProofEnvelope::read(&mut &proof[..]).is_err());
+        }
+
+        // Both schemes are read back, so the caller can check them against
+        // its params.
+        for scheme in [CommitmentScheme::KzgGwc, CommitmentScheme::KzgShplonk].iter() {
+            let mut proof = vec![];
+            ProofEnvelope::new(*scheme, PointEncoding::Compressed)
+                .write(&mut proof)
+                .unwrap();
+            assert_eq!(
+                ProofEnvelope::read(&mut &proof[..]).unwrap().scheme,
+                *scheme
+            );
+        }
+
+        // Bare proofs are rejected.
+        assert!(ProofEnvelope::blake2b_read::<_, G1Affine>(&[0u8; 64][..]).is_err());
+    }
+}
@@ -0,0 +1,264 @@
+//! A Fiat-Shamir transcript based on the Keccak256 hash function.
+//!
+//! This transcript is designed to be cheap to re-execute on the EVM, where
+//! Keccak256 is available as an opcode, and is the one expected by the
+//! verifier contracts emitted by [`crate::codegen`]. To that end everything is
+//! encoded the way the EVM sees it:
+//!
+//! - scalars are written as 32-byte big-endian integers;
+//! - curve points are written uncompressed, as the 32-byte big-endian
+//!   encodings of their affine `x` and `y` coordinates, with the point at
+//!   infinity as `(0, 0)`, like the EVM precompiles;
+//! - the transcript state is the concatenation of everything absorbed since
+//!   the last challenge, prefixed by that challenge's hash. A challenge is the
+//!   Keccak256 hash of the state, reduced modulo the scalar field.
+
+use group::ff::PrimeField;
+use sha3::{Digest, Keccak256};
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+#[cfg(feature = "zeroize")]
+use zeroize::Zeroize;
+
+use super::{
+    ensure_exhausted, point_coordinates, point_from_coordinates, Challenge255, EncodedChallenge,
+    Transcript, TranscriptRead, TranscriptWrite,
+};
+use crate::arithmetic::CurveAffine;
+
+/// Keccak256-based transcript, from the perspective of the verifier.
+#[derive(Debug, Clone)]
+pub struct Keccak256Read<R: Read, C: CurveAffine, E: EncodedChallenge<C>> {
+    state: Vec<u8>,
+    reader: R,
+    _marker: PhantomData<(C, E)>,
+}
+
+impl<R: Read, C: CurveAffine, E: EncodedChallenge<C>> Keccak256Read<R, C, E> {
+    /// Initialize a transcript given an input buffer.
+    pub fn init(reader: R) -> Self {
+        Keccak256Read {
+            state: vec![],
+            reader,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<R: Read, C: CurveAffine> TranscriptRead<C, Challenge255<C>>
+    for Keccak256Read<R, C, Challenge255<C>>
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let x = read_field::<_, C::Base>(&mut self.reader)?;
+        let y = read_field::<_, C::Base>(&mut self.reader)?;
+        let point: C = point_from_coordinates(x, y).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof")
+        })?;
+        self.common_point(point)?;
+
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let scalar = read_field::<_, C::Scalar>(&mut self.reader)?;
+        self.common_scalar(scalar)?;
+
+        Ok(scalar)
+    }
+
+    fn ensure_exhausted(&mut self) -> io::Result<()> {
+        ensure_exhausted(&mut self.reader)
+    }
+}
+
+impl<R: Read, C: CurveAffine> Transcript<C, Challenge255<C>>
+    for Keccak256Read<R, C, Challenge255<C>>
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        squeeze_challenge(&mut self.state)
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        absorb_point(&mut self.state, point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        absorb_field(&mut self.state, &scalar);
+
+        Ok(())
+    }
+}
+
+/// Keccak256-based transcript, from the perspective of the prover.
+#[derive(Debug, Clone)]
+pub struct Keccak256Write<W: Write, C: CurveAffine, E: EncodedChallenge<C>> {
+    state: Vec<u8>,
+    writer: W,
+    _marker: PhantomData<(C, E)>,
+}
+
+impl<W: Write, C: CurveAffine, E: EncodedChallenge<C>> Keccak256Write<W, C, E> {
+    /// Initialize a transcript given an output buffer.
+    pub fn init(writer: W) -> Self {
+        Keccak256Write {
+            state: vec![],
+            writer,
+            _marker: PhantomData,
+        }
+    }
+
+    /// Conclude the interaction and return the output buffer (writer).
+    pub fn finalize(self) -> W {
+        #[cfg(feature = "zeroize")]
+        {
+            let mut state = self.state;
+            state.zeroize();
+        }
+        self.writer
+    }
+}
+
+impl<W: Write, C: CurveAffine> TranscriptWrite<C, Challenge255<C>>
+    for Keccak256Write<W, C, Challenge255<C>>
+{
+    fn write_point(&mut self, point: C) -> io::Result<()> {
+        self.common_point(point)?;
+        let (x, y) = point_coordinates(point);
+        self.writer.write_all(&to_be_bytes(&x))?;
+        self.writer.write_all(&to_be_bytes(&y))
+    }
+
+    fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.common_scalar(scalar)?;
+        self.writer.write_all(&to_be_bytes(&scalar))
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write, C: CurveAffine> Transcript<C, Challenge255<C>>
+    for Keccak256Write<W, C, Challenge255<C>>
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<C> {
+        squeeze_challenge(&mut self.state)
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        absorb_point(&mut self.state, point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        absorb_field(&mut self.state, &scalar);
+
+        Ok(())
+    }
+}
+
+/// Returns the big-endian encoding of a field element.
+pub(crate) fn to_be_bytes<F: PrimeField>(value: &F) -> Vec<u8> {
+    let mut bytes = value.to_repr().as_ref().to_vec();
+    bytes.reverse();
+    bytes
+}
+
+fn read_field<R: Read, F: PrimeField>(reader: &mut R) -> io::Result<F> {
+    let mut repr = F::Repr::default();
+    reader.read_exact(repr.as_mut())?;
+    repr.as_mut().reverse();
+    Option::from(F::from_repr(repr)).ok_or_else(|| {
+        io::Error::new(
+            io::ErrorKind::Other,
+            "invalid field element encoding in proof",
+        )
+    })
+}
+
+fn absorb_field<F: PrimeField>(state: &mut Vec<u8>, value: &F) {
+    state.extend(to_be_bytes(value));
+}
+
+fn absorb_point<C: CurveAffine>(state: &mut Vec<u8>, point: C) -> io::Result<()> {
+    let (x, y) = point_coordinates(point);
+    absorb_field(state, &x);
+    absorb_field(state, &y);
+
+    Ok(())
+}
+
+fn squeeze_challenge<C: CurveAffine>(state: &mut Vec<u8>) -> Challenge255<C> {
+    let hash = Keccak256::digest(&state[..]);
+    #[cfg(feature = "zeroize")]
+    state.zeroize();
+    #[cfg(not(feature = "zeroize"))]
+    state.clear();
+    state.extend_from_slice(&hash);
+
+    // The hash is read as a big-endian integer, which `Challenge255` reduces
+    // modulo the scalar field from its little-endian encoding.
+    let mut wide = [0u8; 64];
+    for (dst, src) in wide.iter_mut().zip(hash.iter().rev()) {
+        *dst = *src;
+    }
+    Challenge255::new(&wide)
+}
+
+#[cfg(test)]
+mod tests {
+    use group::{prime::PrimeCurveAffine, Curve};
+    use pairing::bn256::{Fr, G1Affine};
+    use rand_core::OsRng;
+
+    use super::*;
+    use crate::arithmetic::{Field, FieldExt};
+
+    #[test]
+    fn transcript_roundtrip() {
+        let point = (G1Affine::generator() * Fr::random(OsRng)).to_affine();
+        let scalar = Fr::random(OsRng);
+
+        let mut transcript = Keccak256Write::<_, G1Affine, Challenge255<_>>::init(vec![]);
+        transcript.common_scalar(Fr::one()).unwrap();
+        transcript.write_point(point).unwrap();
+        let challenge_1 = transcript.squeeze_challenge().get_scalar();
+        transcript.write_scalar(scalar).unwrap();
+        let challenge_2 = transcript.squeeze_challenge().get_scalar();
+        let challenge_3 = transcript.squeeze_challenge().get_scalar();
+        let proof = transcript.finalize();
+
+        assert_eq!(proof.len(), 64 + 32);
+        assert_ne!(challenge_1, challenge_2);
+        assert_ne!(challenge_2, challenge_3);
+
+        let mut transcript = Keccak256Read::<_, G1Affine, Challenge255<_>>::init(&proof[..]);
+        transcript.common_scalar(Fr::one()).unwrap();
+        assert_eq!(transcript.read_point().unwrap(), point);
+        assert_eq!(transcript.squeeze_challenge().get_scalar(), challenge_1);
+        assert_eq!(transcript.read_scalar().unwrap(), scalar);
+        assert_eq!(transcript.squeeze_challenge().get_scalar(), challenge_2);
+        assert_eq!(transcript.squeeze_challenge().get_scalar(), challenge_3);
+    }
+
+    #[test]
+    fn challenge_is_big_endian_hash() {
+        // An empty state hashes to the well-known Keccak256 digest of "".
+        let mut state = vec![];
+        let challenge: Challenge255<G1Affine> = squeeze_challenge(&mut state);
+        let mut expected = [0u8; 64];
+        expected[..32].copy_from_slice(&hex_le(
+            "c5d2460186f7233c927e7db2dcc703c0e500b653ca82273b7bfad8045d85a470",
+        ));
+        assert_eq!(challenge.get_scalar(), Fr::from_bytes_wide(&expected));
+        assert_eq!(state.len(), 32);
+    }
+
+    fn hex_le(hex: &str) -> Vec<u8> {
+        let mut bytes: Vec<u8> = (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect();
+        bytes.reverse();
+        bytes
+    }
+}
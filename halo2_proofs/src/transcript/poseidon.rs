@@ -0,0 +1,651 @@
+//! A Fiat-Shamir transcript based on the Poseidon hash function.
+//!
+//! Unlike [`Blake2bRead`] and [`Blake2bWrite`], the sponge used here operates
+//! natively over the scalar field of the proof. This makes it cheap to
+//! re-execute the transcript inside another circuit over that field, which is
+//! what a recursive verifier needs to do.
+//!
+//! [`Blake2bRead`]: super::Blake2bRead
+//! [`Blake2bWrite`]: super::Blake2bWrite
+
+use group::ff::PrimeField;
+use std::collections::VecDeque;
+use std::io::{self, Read, Write};
+use std::marker::PhantomData;
+
+use super::{
+    ensure_exhausted, point_coordinates, EncodedChallenge, Transcript, TranscriptPrefixes,
+    TranscriptRead, TranscriptWrite,
+};
+use crate::arithmetic::{CurveAffine, FieldExt};
+
+/// Width of the Poseidon permutation used by the transcript.
+pub const WIDTH: usize = 3;
+
+/// Number of full rounds of the Poseidon permutation used by the transcript.
+pub const FULL_ROUNDS: usize = 8;
+
+/// Number of partial rounds of the Poseidon permutation used by the transcript.
+///
+/// This is the number recommended for a width-3 permutation with $x^5$ S-boxes
+/// over a ~254-bit field such as the BN256 scalar field.
+pub const PARTIAL_ROUNDS: usize = 57;
+
+/// The parameters of a Poseidon permutation with $x^5$ S-boxes.
+///
+/// The round constants and MDS matrix are generated deterministically from the
+/// Grain LFSR as described in the [Poseidon paper], so an in-circuit
+/// implementation can reproduce them from the same three parameters. The
+/// $x^5$ S-box is only a permutation of fields where $\gcd(5, p - 1) = 1$.
+///
+/// [Poseidon paper]: https://eprint.iacr.org/2019/458
+#[derive(Clone, Debug)]
+pub struct Spec<F: FieldExt> {
+    width: usize,
+    full_rounds: usize,
+    partial_rounds: usize,
+    round_constants: Vec<Vec<F>>,
+    mds: Vec<Vec<F>>,
+}
+
+impl<F: FieldExt> Spec<F> {
+    /// Generates the parameters of a Poseidon permutation over `F` with the
+    /// given width and number of rounds. `full_rounds` must be even.
+    pub fn new(width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        assert!(width >= 2);
+        assert_eq!(full_rounds % 2, 0);
+
+        let mut grain = Grain::new(F::NUM_BITS as usize, width, full_rounds, partial_rounds);
+
+        let round_constants = (0..full_rounds + partial_rounds)
+            .map(|_| {
+                (0..width)
+                    .map(|_| grain.next_field_element::<F>())
+                    .collect()
+            })
+            .collect();
+
+        // A Cauchy matrix M[i][j] = 1 / (x_i + y_j) is MDS as long as the x_i
+        // are distinct, the y_j are distinct, and no x_i + y_j is zero. Like
+        // the reference generator, resample unless all 2 * width are distinct.
+        let mds = loop {
+            let sampled: Vec<F> = (0..2 * width)
+                .map(|_| grain.next_field_element_without_rejection())
+                .collect();
+            if !(0..sampled.len()).all(|i| (0..i).all(|j| sampled[i] != sampled[j])) {
+                continue;
+            }
+            let (xs, ys) = sampled.split_at(width);
+
+            let mds: Option<Vec<Vec<F>>> = xs
+                .iter()
+                .map(|x| ys.iter().map(|y| Option::from((*x + y).invert())).collect())
+                .collect();
+            if let Some(mds) = mds {
+                break mds;
+            }
+        };
+
+        Spec {
+            width,
+            full_rounds,
+            partial_rounds,
+            round_constants,
+            mds,
+        }
+    }
+
+    /// The width of the permutation.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The number of full rounds of the permutation, half of which come
+    /// before the partial rounds.
+    pub fn full_rounds(&self) -> usize {
+        self.full_rounds
+    }
+
+    /// The number of partial rounds of the permutation.
+    pub fn partial_rounds(&self) -> usize {
+        self.partial_rounds
+    }
+
+    /// The round constants, one row of `width` elements per round.
+    pub fn round_constants(&self) -> &[Vec<F>] {
+        &self.round_constants
+    }
+
+    /// The MDS matrix, as a list of rows.
+    pub fn mds(&self) -> &[Vec<F>] {
+        &self.mds
+    }
+
+    /// Applies the permutation to `state` in place.
+    pub fn permute(&self, state: &mut [F]) {
+        assert_eq!(state.len(), self.width);
+
+        let half_full_rounds = self.full_rounds / 2;
+        let partial_end = half_full_rounds + self.partial_rounds;
+        for (round, constants) in self.round_constants.iter().enumerate() {
+            for (word, constant) in state.iter_mut().zip(constants.iter()) {
+                *word += constant;
+            }
+
+            if round < half_full_rounds || round >= partial_end {
+                for word in state.iter_mut() {
+                    *word = sbox(*word);
+                }
+            } else {
+                state[0] = sbox(state[0]);
+            }
+
+            let mixed: Vec<F> = self
+                .mds
+                .iter()
+                .map(|row| {
+                    row.iter()
+                        .zip(state.iter())
+                        .fold(F::zero(), |acc, (m, word)| acc + *m * word)
+                })
+                .collect();
+            state.copy_from_slice(&mixed);
+        }
+    }
+}
+
+fn sbox<F: FieldExt>(x: F) -> F {
+    x.square().square() * x
+}
+
+/// The Grain LFSR used to generate Poseidon parameters.
+#[derive(Debug)]
+struct Grain {
+    state: VecDeque<bool>,
+    field_bits: usize,
+}
+
+impl Grain {
+    fn new(field_bits: usize, width: usize, full_rounds: usize, partial_rounds: usize) -> Self {
+        fn append_bits(state: &mut VecDeque<bool>, value: usize, bits: usize) {
+            state.extend((0..bits).rev().map(|i| (value >> i) & 1 == 1));
+        }
+
+        let mut state = VecDeque::with_capacity(80);
+        // Prime field, x^alpha S-box.
+        append_bits(&mut state, 1, 2);
+        append_bits(&mut state, 0, 4);
+        append_bits(&mut state, field_bits, 12);
+        append_bits(&mut state, width, 12);
+        append_bits(&mut state, full_rounds, 10);
+        append_bits(&mut state, partial_rounds, 10);
+        state.extend(std::iter::repeat(true).take(30));
+        assert_eq!(state.len(), 80);
+
+        let mut grain = Grain { state, field_bits };
+        for _ in 0..160 {
+            grain.clock();
+        }
+        grain
+    }
+
+    fn clock(&mut self) -> bool {
+        let s = &self.state;
+        let bit = s[62] ^ s[51] ^ s[38] ^ s[23] ^ s[13] ^ s[0];
+        self.state.pop_front();
+        self.state.push_back(bit);
+        bit
+    }
+
+    fn next_bit(&mut self) -> bool {
+        // Self-shrinking: keep the second bit of each pair whose first bit is set.
+        loop {
+            let keep = self.clock();
+            let bit = self.clock();
+            if keep {
+                return bit;
+            }
+        }
+    }
+
+    /// Samples a field element uniformly by rejecting out-of-range values.
+    fn next_field_element<F: FieldExt>(&mut self) -> F {
+        loop {
+            let mut repr = F::Repr::default();
+            let bits: Vec<bool> = (0..self.field_bits).map(|_| self.next_bit()).collect();
+            // The bits are generated most significant first, and the field
+            // representation is little-endian.
+            for (i, bit) in bits.iter().rev().enumerate() {
+                if *bit {
+                    repr.as_mut()[i / 8] |= 1 << (i % 8);
+                }
+            }
+            if let Some(f) = Option::from(F::from_repr(repr)) {
+                return f;
+            }
+        }
+    }
+
+    /// Samples a field element by reducing the next `field_bits` bits.
+    fn next_field_element_without_rejection<F: FieldExt>(&mut self) -> F {
+        (0..self.field_bits).fold(F::zero(), |acc, _| {
+            let acc = acc.double();
+            if self.next_bit() {
+                acc + F::one()
+            } else {
+                acc
+            }
+        })
+    }
+}
+
+/// Returns the initial capacity element of the transcript sponge, which
+/// domain-separates the transcript from other uses of the permutation.
+pub(crate) fn sponge_tag<F: FieldExt>() -> F {
+    personal_tag(b"Halo2-Transcript")
+}
+
+fn personal_tag<F: FieldExt>(personal: &[u8; 16]) -> F {
+    let mut tag = [0u8; 64];
+    tag[..16].copy_from_slice(personal);
+    F::from_bytes_wide(&tag)
+}
+
+/// Hashes a byte string to a field element with the transcript's permutation,
+/// in a sponge whose capacity is initialized from `personal` rather than from
+/// the transcript's tag.
+///
+/// The length of `bytes` is absorbed first, followed by the bytes in
+/// little-endian chunks of 31, each of which fits in the field.
+pub(crate) fn hash_bytes<F: FieldExt>(personal: &[u8; 16], bytes: &[u8]) -> F {
+    let mut sponge = Sponge::with_tag(
+        Spec::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS),
+        personal_tag(personal),
+    );
+    sponge.absorb(F::from(bytes.len() as u64));
+    for chunk in bytes.chunks(31) {
+        let mut wide = [0u8; 64];
+        wide[..chunk.len()].copy_from_slice(chunk);
+        sponge.absorb(F::from_bytes_wide(&wide));
+    }
+    sponge.squeeze()
+}
+
+/// A duplex sponge over the Poseidon permutation.
+#[derive(Clone, Debug)]
+struct Sponge<F: FieldExt> {
+    spec: Spec<F>,
+    state: Vec<F>,
+    absorbing: Vec<F>,
+}
+
+impl<F: FieldExt> Sponge<F> {
+    fn new(spec: Spec<F>) -> Self {
+        Self::with_tag(spec, sponge_tag())
+    }
+
+    fn with_tag(spec: Spec<F>, tag: F) -> Self {
+        let mut state = vec![F::zero(); spec.width()];
+        state[0] = tag;
+        Sponge {
+            spec,
+            state,
+            absorbing: vec![],
+        }
+    }
+
+    fn rate(&self) -> usize {
+        self.spec.width() - 1
+    }
+
+    fn absorb(&mut self, value: F) {
+        if self.absorbing.len() == self.rate() {
+            self.flush();
+        }
+        self.absorbing.push(value);
+    }
+
+    fn flush(&mut self) {
+        for (word, value) in self.state.iter_mut().skip(1).zip(self.absorbing.drain(..)) {
+            *word += value;
+        }
+        self.spec.permute(&mut self.state);
+    }
+
+    fn squeeze(&mut self) -> F {
+        // Pad with a single one, so that inputs differing only in trailing
+        // zeroes are absorbed differently.
+        self.absorb(F::one());
+        self.flush();
+        self.state[1]
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: FieldExt> zeroize::Zeroize for Sponge<F> {
+    fn zeroize(&mut self) {
+        crate::helpers::wipe(&mut self.state);
+        crate::helpers::wipe(&mut self.absorbing);
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl<F: FieldExt> Drop for Sponge<F> {
+    fn drop(&mut self) {
+        zeroize::Zeroize::zeroize(self);
+    }
+}
+
+/// Absorbs a curve point as the 128-bit limbs of its affine coordinates, so
+/// that the encoding is injective even when the base field is larger than the
+/// scalar field. The point at infinity is absorbed as `(0, 0)`.
+fn absorb_point<C: CurveAffine>(
+    sponge: &mut Sponge<C::Scalar>,
+    prefixes: &TranscriptPrefixes,
+    point: C,
+) -> io::Result<()> {
+    sponge.absorb(C::Scalar::from(prefixes.point as u64));
+    let (x, y) = point_coordinates(point);
+    for coord in [x, y].iter() {
+        for limb in coord.to_repr().as_ref().chunks(16) {
+            let mut bytes = [0u8; 64];
+            bytes[..limb.len()].copy_from_slice(limb);
+            sponge.absorb(C::Scalar::from_bytes_wide(&bytes));
+        }
+    }
+
+    Ok(())
+}
+
+fn absorb_scalar<F: FieldExt>(sponge: &mut Sponge<F>, prefixes: &TranscriptPrefixes, scalar: F) {
+    sponge.absorb(F::from(prefixes.scalar as u64));
+    sponge.absorb(scalar);
+}
+
+fn squeeze_challenge<C: CurveAffine>(
+    sponge: &mut Sponge<C::Scalar>,
+    prefixes: &TranscriptPrefixes,
+) -> PoseidonChallenge<C> {
+    sponge.absorb(C::Scalar::from(prefixes.challenge as u64));
+    PoseidonChallenge::new(&sponge.squeeze())
+}
+
+/// A challenge squeezed from a Poseidon sponge; it is already an element of
+/// the scalar field.
+#[derive(Copy, Clone, Debug)]
+pub struct PoseidonChallenge<C: CurveAffine>(C::Scalar);
+
+impl<C: CurveAffine> EncodedChallenge<C> for PoseidonChallenge<C> {
+    type Input = C::Scalar;
+
+    fn new(challenge_input: &C::Scalar) -> Self {
+        PoseidonChallenge(*challenge_input)
+    }
+
+    fn get_scalar(&self) -> C::Scalar {
+        self.0
+    }
+}
+
+/// Poseidon-based transcript, from the perspective of the verifier.
+#[derive(Debug, Clone)]
+pub struct PoseidonRead<R: Read, C: CurveAffine, E: EncodedChallenge<C>> {
+    sponge: Sponge<C::Scalar>,
+    reader: R,
+    prefixes: TranscriptPrefixes,
+    _marker: PhantomData<(C, E)>,
+}
+
+impl<R: Read, C: CurveAffine, E: EncodedChallenge<C>> PoseidonRead<R, C, E> {
+    /// Initialize a transcript given an input buffer.
+    pub fn init(reader: R) -> Self {
+        Self::init_with_spec(reader, Spec::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS))
+    }
+
+    /// Initialize a transcript given an input buffer and the parameters of
+    /// the Poseidon permutation.
+    pub fn init_with_spec(reader: R, spec: Spec<C::Scalar>) -> Self {
+        PoseidonRead {
+            sponge: Sponge::new(spec),
+            reader,
+            prefixes: TranscriptPrefixes::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Uses `prefixes` instead of the default ones. This must be done before
+    /// anything is absorbed.
+    pub fn with_prefixes(mut self, prefixes: TranscriptPrefixes) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+}
+
+impl<R: Read, C: CurveAffine> TranscriptRead<C, PoseidonChallenge<C>>
+    for PoseidonRead<R, C, PoseidonChallenge<C>>
+{
+    fn read_point(&mut self) -> io::Result<C> {
+        let mut compressed = C::Repr::default();
+        self.reader.read_exact(compressed.as_mut())?;
+        let point: C = Option::from(C::from_bytes(&compressed)).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "invalid point encoding in proof")
+        })?;
+        self.common_point(point)?;
+
+        Ok(point)
+    }
+
+    fn read_scalar(&mut self) -> io::Result<C::Scalar> {
+        let mut data = <C::Scalar as PrimeField>::Repr::default();
+        self.reader.read_exact(data.as_mut())?;
+        let scalar: C::Scalar = Option::from(C::Scalar::from_repr(data)).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::Other,
+                "invalid field element encoding in proof",
+            )
+        })?;
+        self.common_scalar(scalar)?;
+
+        Ok(scalar)
+    }
+
+    fn ensure_exhausted(&mut self) -> io::Result<()> {
+        ensure_exhausted(&mut self.reader)
+    }
+}
+
+impl<R: Read, C: CurveAffine> Transcript<C, PoseidonChallenge<C>>
+    for PoseidonRead<R, C, PoseidonChallenge<C>>
+{
+    fn squeeze_challenge(&mut self) -> PoseidonChallenge<C> {
+        squeeze_challenge(&mut self.sponge, &self.prefixes)
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        absorb_point(&mut self.sponge, &self.prefixes, point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        absorb_scalar(&mut self.sponge, &self.prefixes, scalar);
+
+        Ok(())
+    }
+}
+
+/// Poseidon-based transcript, from the perspective of the prover.
+#[derive(Debug, Clone)]
+pub struct PoseidonWrite<W: Write, C: CurveAffine, E: EncodedChallenge<C>> {
+    sponge: Sponge<C::Scalar>,
+    writer: W,
+    prefixes: TranscriptPrefixes,
+    _marker: PhantomData<(C, E)>,
+}
+
+impl<W: Write, C: CurveAffine, E: EncodedChallenge<C>> PoseidonWrite<W, C, E> {
+    /// Initialize a transcript given an output buffer.
+    pub fn init(writer: W) -> Self {
+        Self::init_with_spec(writer, Spec::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS))
+    }
+
+    /// Initialize a transcript given an output buffer and the parameters of
+    /// the Poseidon permutation.
+    pub fn init_with_spec(writer: W, spec: Spec<C::Scalar>) -> Self {
+        PoseidonWrite {
+            sponge: Sponge::new(spec),
+            writer,
+            prefixes: TranscriptPrefixes::default(),
+            _marker: PhantomData,
+        }
+    }
+
+    /// Uses `prefixes` instead of the default ones. This must be done before
+    /// anything is absorbed.
+    pub fn with_prefixes(mut self, prefixes: TranscriptPrefixes) -> Self {
+        self.prefixes = prefixes;
+        self
+    }
+
+    /// Conclude the interaction and return the output buffer (writer).
+    pub fn finalize(self) -> W {
+        self.writer
+    }
+}
+
+impl<W: Write, C: CurveAffine> TranscriptWrite<C, PoseidonChallenge<C>>
+    for PoseidonWrite<W, C, PoseidonChallenge<C>>
+{
+    fn write_point(&mut self, point: C) -> io::Result<()> {
+        self.common_point(point)?;
+        let compressed = point.to_bytes();
+        self.writer.write_all(compressed.as_ref())
+    }
+
+    fn write_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        self.common_scalar(scalar)?;
+        let data = scalar.to_repr();
+        self.writer.write_all(data.as_ref())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+impl<W: Write, C: CurveAffine> Transcript<C, PoseidonChallenge<C>>
+    for PoseidonWrite<W, C, PoseidonChallenge<C>>
+{
+    fn squeeze_challenge(&mut self) -> PoseidonChallenge<C> {
+        squeeze_challenge(&mut self.sponge, &self.prefixes)
+    }
+
+    fn common_point(&mut self, point: C) -> io::Result<()> {
+        absorb_point(&mut self.sponge, &self.prefixes, point)
+    }
+
+    fn common_scalar(&mut self, scalar: C::Scalar) -> io::Result<()> {
+        absorb_scalar(&mut self.sponge, &self.prefixes, scalar);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use group::{prime::PrimeCurveAffine, Curve};
+    use pairing::bn256::{Fr, G1Affine};
+    use rand_core::OsRng;
+
+    use super::*;
+    use crate::arithmetic::Field;
+
+    #[test]
+    fn transcript_roundtrip() {
+        let point = (G1Affine::generator() * Fr::random(OsRng)).to_affine();
+        let scalar = Fr::random(OsRng);
+
+        let mut transcript = PoseidonWrite::<_, G1Affine, PoseidonChallenge<_>>::init(vec![]);
+        transcript.common_scalar(Fr::one()).unwrap();
+        transcript.write_point(point).unwrap();
+        let challenge_1 = transcript.squeeze_challenge().get_scalar();
+        transcript.write_scalar(scalar).unwrap();
+        let challenge_2 = transcript.squeeze_challenge().get_scalar();
+        let challenge_3 = transcript.squeeze_challenge().get_scalar();
+        let proof = transcript.finalize();
+
+        assert_ne!(challenge_1, challenge_2);
+        assert_ne!(challenge_2, challenge_3);
+
+        let mut transcript = PoseidonRead::<_, G1Affine, PoseidonChallenge<_>>::init(&proof[..]);
+        transcript.common_scalar(Fr::one()).unwrap();
+        assert_eq!(transcript.read_point().unwrap(), point);
+        assert_eq!(transcript.squeeze_challenge().get_scalar(), challenge_1);
+        assert_eq!(transcript.read_scalar().unwrap(), scalar);
+        assert_eq!(transcript.squeeze_challenge().get_scalar(), challenge_2);
+        assert_eq!(transcript.squeeze_challenge().get_scalar(), challenge_3);
+    }
+
+    #[test]
+    fn prefixes_separate_transcripts() {
+        let prefixes = TranscriptPrefixes::new(7, 8, 9);
+        let challenge = |prefixes: TranscriptPrefixes| {
+            let mut transcript = PoseidonWrite::<_, G1Affine, PoseidonChallenge<_>>::init(vec![])
+                .with_prefixes(prefixes);
+            transcript.common_scalar(Fr::one()).unwrap();
+            transcript.squeeze_challenge().get_scalar()
+        };
+        assert_ne!(
+            challenge(prefixes),
+            challenge(TranscriptPrefixes::default())
+        );
+
+        let mut transcript = PoseidonRead::<_, G1Affine, PoseidonChallenge<_>>::init(&b""[..])
+            .with_prefixes(prefixes);
+        transcript.common_scalar(Fr::one()).unwrap();
+        assert_eq!(
+            transcript.squeeze_challenge().get_scalar(),
+            challenge(prefixes)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "transcript prefixes must be distinct")]
+    fn prefixes_must_be_distinct() {
+        TranscriptPrefixes::new(0, 1, 0);
+    }
+
+    #[test]
+    fn permutation_known_answer() {
+        // Test vector of `poseidonperm_x5_254_3` from the reference
+        // implementation accompanying the Poseidon paper, whose parameters are
+        // generated by the same Grain LFSR.
+        let fr = |hex: &str| {
+            let mut repr = <Fr as PrimeField>::Repr::default();
+            for (i, byte) in repr.as_mut().iter_mut().rev().enumerate() {
+                *byte = u8::from_str_radix(&hex[2 * i..2 * i + 2], 16).unwrap();
+            }
+            Fr::from_repr(repr).unwrap()
+        };
+
+        let spec = Spec::<Fr>::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS);
+        let mut state = [Fr::zero(), Fr::one(), Fr::from(2)];
+        spec.permute(&mut state);
+        assert_eq!(
+            state,
+            [
+                fr("115cc0f5e7d690413df64c6b9662e9cf2a3617f2743245519e19607a4417189a"),
+                fr("0fca49b798923ab0239de1c9e7a4a9a2210312b6a2f616d18b5a87f9b628ae29"),
+                fr("0e7ae82e40091e63cbd4f16a6d16310b3729d4b6e138fcf54110e2867045a30c"),
+            ]
+        );
+    }
+
+    #[test]
+    fn trailing_zero_is_absorbed() {
+        let mut a = Sponge::new(Spec::<Fr>::new(WIDTH, FULL_ROUNDS, PARTIAL_ROUNDS));
+        let mut b = a.clone();
+        a.absorb(Fr::one());
+        b.absorb(Fr::one());
+        b.absorb(Fr::zero());
+        assert_ne!(a.squeeze(), b.squeeze());
+    }
+}
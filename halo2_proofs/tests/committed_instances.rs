@@ -0,0 +1,193 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        commit_instances, create_proof, keygen_pk, keygen_vk, verify_proof,
+        verify_proof_with_instance_commitments, Advice, Circuit, Column, ConstraintSystem, Error,
+        Instance, PreprocessedVerifier, ProvingKey, SingleVerifier,
+    },
+    poly::commitment::{Params, ParamsVerifier},
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 6;
+
+/// The number of public inputs, which is more than the verifier's parameters
+/// have Lagrange bases for.
+const INPUTS: usize = 40;
+
+#[derive(Clone, Debug)]
+struct ExposeConfig {
+    a: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Copies each of its witnesses to a public input.
+#[derive(Default)]
+struct ExposeCircuit {
+    values: Option<Vec<Fp>>,
+}
+
+impl Circuit<Fp> for ExposeCircuit {
+    type Config = ExposeConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = ExposeConfig {
+            a: meta.advice_column(),
+            instance: meta.instance_column(),
+        };
+        meta.enable_equality(config.a);
+        meta.enable_equality(config.instance);
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let cells = layouter.assign_region(
+            || "values",
+            |mut region| {
+                (0..INPUTS)
+                    .map(|row| {
+                        region
+                            .assign_advice(
+                                || "a",
+                                config.a,
+                                row,
+                                || self.values.as_ref().map(|v| v[row]).ok_or(Error::Synthesis),
+                            )
+                            .map(|cell| cell.cell())
+                    })
+                    .collect::<Result<Vec<_>, _>>()
+            },
+        )?;
+        for (row, cell) in cells.into_iter().enumerate() {
+            layouter.constrain_instance(cell, config.instance, row)?;
+        }
+        Ok(())
+    }
+}
+
+fn prove(params: &Params<G1Affine>, pk: &ProvingKey<G1Affine>, values: &[Fp]) -> Vec<u8> {
+    let circuit = ExposeCircuit {
+        values: Some(values.to_vec()),
+    };
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(params, pk, &[circuit], &[&[values]], OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+#[test]
+fn committed_instances() {
+    let values: Vec<Fp> = (0..INPUTS).map(|i| Fp::from(i as u64 * 7 + 1)).collect();
+    let instances: &[&[&[Fp]]] = &[&[&values]];
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let vk = keygen_vk(&params, &ExposeCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &ExposeCircuit::default()).expect("keygen_pk should not fail");
+
+    let proof = prove(&params, &pk, &values);
+
+    // The same proof verifies against the values and against their commitments.
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(INPUTS).unwrap();
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        SingleVerifier::new(&params_verifier),
+        instances,
+        &mut transcript,
+    )
+    .expect("proof should verify");
+
+    let commitments = commit_instances(&params, pk.get_vk(), &[&values]).unwrap();
+    assert_eq!(commitments.len(), 1);
+
+    // The verifier needs no Lagrange bases when given the commitments.
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+    let verify = |commitments: &[G1Affine]| {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        verify_proof_with_instance_commitments(
+            &params_verifier,
+            pk.get_vk(),
+            SingleVerifier::new(&params_verifier),
+            &[commitments],
+            &mut transcript,
+        )
+    };
+    verify(&commitments).expect("proof should verify");
+
+    // Commitments to other public inputs are rejected.
+    let mut other = values.clone();
+    other[INPUTS - 1] += Fp::from(1);
+    let other_commitments = commit_instances(&params, pk.get_vk(), &[&other]).unwrap();
+    assert!(verify(&other_commitments).is_err());
+
+    // So are commitments to the wrong number of instance columns.
+    assert!(matches!(verify(&[]), Err(Error::InvalidInstances)));
+    assert!(matches!(
+        commit_instances(&params, pk.get_vk(), &[]),
+        Err(Error::InvalidInstances)
+    ));
+}
+
+#[test]
+fn preprocessed_verifier() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(INPUTS).unwrap();
+    let vk = keygen_vk(&params, &ExposeCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &ExposeCircuit::default()).expect("keygen_pk should not fail");
+    let verifier = PreprocessedVerifier::new(pk.get_vk());
+
+    // The same verifier checks any number of proofs, against their values or
+    // their commitments, and accepts exactly what `verify_proof` accepts.
+    for seed in 0..3u64 {
+        let values: Vec<Fp> = (0..INPUTS)
+            .map(|i| Fp::from(seed * 100 + i as u64))
+            .collect();
+        let mut other = values.clone();
+        other[0] += Fp::from(1);
+        let proof = prove(&params, &pk, &values);
+
+        for (instance, valid) in [(&values, true), (&other, false)] {
+            let instances: &[&[&[Fp]]] = &[&[instance]];
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            let preprocessed = verifier.verify(
+                &params_verifier,
+                SingleVerifier::new(&params_verifier),
+                instances,
+                &mut transcript,
+            );
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            let direct = verify_proof(
+                &params_verifier,
+                pk.get_vk(),
+                SingleVerifier::new(&params_verifier),
+                instances,
+                &mut transcript,
+            );
+            assert_eq!(preprocessed.is_ok(), valid);
+            assert_eq!(direct.is_ok(), valid);
+
+            let commitments = commit_instances(&params, pk.get_vk(), &[instance]).unwrap();
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            let committed = verifier.verify_with_instance_commitments(
+                &params_verifier,
+                SingleVerifier::new(&params_verifier),
+                &[&commitments],
+                &mut transcript,
+            );
+            assert_eq!(committed.is_ok(), valid);
+        }
+    }
+}
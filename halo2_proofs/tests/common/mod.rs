@@ -0,0 +1,200 @@
+//! Fixtures shared by the integration tests.
+
+// Each test crate only uses some of the fixtures.
+#![allow(dead_code)]
+
+use ff::Field;
+use halo2_proofs::{
+    circuit::{Cell, Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, ProvingKey, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::{OsRng, RngCore};
+
+/// A deterministic RNG, so that proofs created with the same randomness can be
+/// compared.
+pub struct XorShiftRng(pub u64);
+
+impl RngCore for XorShiftRng {
+    fn next_u32(&mut self) -> u32 {
+        self.next_u64() as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        rand_core::impls::fill_bytes_via_next(self, dest)
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct SquareConfig {
+    pub q: Selector,
+    pub a: Column<Advice>,
+    pub b: Column<Advice>,
+}
+
+impl SquareConfig {
+    /// Configures the gate `q * (a * a - b)`. Equality is enabled on `a` and
+    /// `b` when the rows are `chained`.
+    pub fn configure(meta: &mut ConstraintSystem<Fp>, q: Selector, chained: bool) -> Self {
+        let config = SquareConfig {
+            q,
+            a: meta.advice_column(),
+            b: meta.advice_column(),
+        };
+        if chained {
+            meta.enable_equality(config.a);
+            meta.enable_equality(config.b);
+        }
+
+        meta.create_gate("square", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let b = meta.query_advice(config.b, Rotation::cur());
+
+            vec![q * (a.clone() * a - b)]
+        });
+
+        config
+    }
+
+    /// Assigns `b = a^2` on each of the first `rows` rows, copying each `b` to
+    /// the next row's `a`, and returns the cell of the first `a`.
+    pub fn assign(
+        &self,
+        layouter: &mut impl Layouter<Fp>,
+        a: Option<Fp>,
+        rows: usize,
+    ) -> Result<Cell, Error> {
+        layouter.assign_region(
+            || "squares",
+            |mut region| {
+                let mut value = a;
+                let mut previous = None;
+                let mut first = None;
+                for row in 0..rows {
+                    self.q.enable(&mut region, row)?;
+                    let a = region.assign_advice(
+                        || "a",
+                        self.a,
+                        row,
+                        || value.ok_or(Error::Synthesis),
+                    )?;
+                    if let Some(previous) = previous {
+                        region.constrain_equal(previous, a.cell())?;
+                    }
+                    first.get_or_insert(a.cell());
+                    value = value.map(|v| v.square());
+                    let b = region.assign_advice(
+                        || "b",
+                        self.b,
+                        row,
+                        || value.ok_or(Error::Synthesis),
+                    )?;
+                    previous = Some(b.cell());
+                }
+                Ok(first.unwrap())
+            },
+        )
+    }
+}
+
+/// Constrains `b = a^2` on each of the first `ROWS` rows, and copies each `b`
+/// to the next row's `a`.
+#[derive(Default)]
+pub struct SquareCircuit<const ROWS: usize> {
+    pub a: Option<Fp>,
+}
+
+impl<const ROWS: usize> SquareCircuit<ROWS> {
+    pub fn new(a: u64) -> Self {
+        SquareCircuit {
+            a: Some(Fp::from(a)),
+        }
+    }
+}
+
+impl<const ROWS: usize> Circuit<Fp> for SquareCircuit<ROWS> {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let q = meta.selector();
+        SquareConfig::configure(meta, q, ROWS > 1)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.assign(&mut layouter, self.a, ROWS)?;
+        Ok(())
+    }
+}
+
+/// Returns the params of size `2^k`, the verifier params for `instances`
+/// instances, and the proving key of `circuit`.
+pub fn setup<C: Circuit<Fp>>(
+    k: u32,
+    circuit: &C,
+    instances: usize,
+) -> (
+    Params<G1Affine>,
+    ParamsVerifier<Bn256>,
+    ProvingKey<G1Affine>,
+) {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(k);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(instances).unwrap();
+    let vk = keygen_vk(&params, circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, circuit).expect("keygen_pk should not fail");
+    (params, params_verifier, pk)
+}
+
+/// Returns a Blake2b proof of `circuit`.
+pub fn prove<C: Circuit<Fp>>(
+    params: &Params<G1Affine>,
+    pk: &ProvingKey<G1Affine>,
+    circuit: C,
+    instances: &[&[&[Fp]]],
+) -> Vec<u8> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(params, pk, &[circuit], instances, OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+/// Verifies a Blake2b proof.
+pub fn verify(
+    params: &ParamsVerifier<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    instances: &[&[&[Fp]]],
+    proof: &[u8],
+) -> Result<(), Error> {
+    let strategy = SingleVerifier::new(params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+    verify_proof(params, pk.get_vk(), strategy, instances, &mut transcript)
+}
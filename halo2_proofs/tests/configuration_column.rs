@@ -0,0 +1,175 @@
+use ff::Field;
+use group::Curve;
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, verify_proof_with_configuration, Advice,
+        Circuit, Column, ConstraintSystem, Error, Fixed, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+#[derive(Clone, Debug)]
+struct ScaleConfig {
+    q_scale: Selector,
+    input: Column<Advice>,
+    output: Column<Advice>,
+    factor: Column<Fixed>,
+}
+
+/// Constrains `output = factor * input`, where `factor` is a configuration
+/// column holding a single per-proof value.
+#[derive(Default)]
+struct ScaleCircuit<F: FieldExt> {
+    factor: F,
+    input: Option<F>,
+}
+
+impl<F: FieldExt> Circuit<F> for ScaleCircuit<F> {
+    type Config = ScaleConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        ScaleCircuit {
+            factor: self.factor,
+            input: None,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let config = ScaleConfig {
+            q_scale: meta.selector(),
+            input: meta.advice_column(),
+            output: meta.advice_column(),
+            factor: meta.configuration_column(),
+        };
+
+        meta.create_gate("scale", |meta| {
+            let q = meta.query_selector(config.q_scale);
+            let input = meta.query_advice(config.input, Rotation::cur());
+            let output = meta.query_advice(config.output, Rotation::cur());
+            let factor = meta.query_fixed(config.factor, Rotation::cur());
+
+            vec![q * (factor * input - output)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "scale",
+            |mut region| {
+                config.q_scale.enable(&mut region, 0)?;
+                region.assign_fixed(|| "factor", config.factor, 0, || Ok(self.factor))?;
+                region.assign_advice(
+                    || "input",
+                    config.input,
+                    0,
+                    || self.input.ok_or(Error::Synthesis),
+                )?;
+                region.assign_advice(
+                    || "output",
+                    config.output,
+                    0,
+                    || {
+                        self.input
+                            .map(|input| input * self.factor)
+                            .ok_or(Error::Synthesis)
+                    },
+                )?;
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn configuration_column() {
+    let circuit = ScaleCircuit {
+        factor: Fp::from(5),
+        input: Some(Fp::from(7)),
+    };
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // The keys are generated once, with a default factor of zero.
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(1).unwrap();
+    let vk = keygen_vk(&params, &ScaleCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &ScaleCircuit::default()).expect("keygen_pk should not fail");
+
+    let instances: &[&[&[Fp]]] = &[&[]];
+    let prove = |circuit: ScaleCircuit<Fp>| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof(&params, &pk, &[circuit], instances, OsRng, &mut transcript)
+            .expect("proof generation should not fail");
+        transcript.finalize()
+    };
+    let proof: Vec<u8> = prove(circuit);
+
+    let verify = |proof: &[u8], factor: u64| {
+        let configuration = [params_verifier
+            .commit_lagrange(vec![Fp::from(factor)])
+            .to_affine()];
+        let strategy = SingleVerifier::new(&params_verifier);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+        verify_proof_with_configuration(
+            &params_verifier,
+            pk.get_vk(),
+            strategy,
+            &configuration,
+            instances,
+            &mut transcript,
+        )
+    };
+    assert!(verify(&proof, 5).is_ok());
+    assert!(matches!(
+        verify(&proof, 6),
+        Err(Error::ConfigurationMismatch)
+    ));
+
+    // Without an explicit configuration, the keygen values are expected.
+    let verify_keygen = |proof: &[u8]| {
+        let strategy = SingleVerifier::new(&params_verifier);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+        verify_proof(
+            &params_verifier,
+            pk.get_vk(),
+            strategy,
+            instances,
+            &mut transcript,
+        )
+    };
+    assert!(matches!(
+        verify_keygen(&proof),
+        Err(Error::ConfigurationMismatch)
+    ));
+
+    // A factor of zero, like the keygen one, leaves the configuration column
+    // zero on every row, so that it commits to the point at infinity.
+    let proof = prove(ScaleCircuit {
+        factor: Fp::zero(),
+        input: Some(Fp::from(7)),
+    });
+    assert!(verify(&proof, 0).is_ok());
+    assert!(verify_keygen(&proof).is_ok());
+    assert!(matches!(
+        verify(&proof, 5),
+        Err(Error::ConfigurationMismatch)
+    ));
+}
@@ -0,0 +1,87 @@
+use halo2_proofs::plonk::{Any, Column, ConstraintSystem};
+use halo2_proofs::poly::Rotation;
+use pairing::bn256::Fr as Fp;
+
+#[test]
+fn inspect_constraint_system() {
+    let mut meta = ConstraintSystem::<Fp>::default();
+
+    let a = meta.advice_column();
+    let b = meta.advice_column();
+    let instance = meta.instance_column();
+    let constant = meta.fixed_column();
+    let table = meta.lookup_table_column();
+    let q = meta.selector();
+    let q_lookup = meta.complex_selector();
+
+    meta.enable_equality(a);
+    meta.enable_equality(instance);
+    meta.enable_constant(constant);
+
+    meta.create_gate("next is square", |meta| {
+        let q = meta.query_selector(q);
+        let a_cur = meta.query_advice(a, Rotation::cur());
+        let a_next = meta.query_advice(a, Rotation::next());
+        vec![("square", q * (a_cur.clone() * a_cur - a_next))]
+    });
+    meta.lookup("b in table", |meta| {
+        let q = meta.query_selector(q_lookup);
+        let b = meta.query_advice(b, Rotation::cur());
+        vec![(q * b, table)]
+    });
+    meta.shuffle("b is a shuffle of a", |meta| {
+        let a = meta.query_advice(a, Rotation::cur());
+        let b = meta.query_advice(b, Rotation::cur());
+        vec![(b, a)]
+    });
+
+    assert_eq!(meta.num_advice_columns(), 2);
+    assert_eq!(meta.num_instance_columns(), 1);
+    assert_eq!(meta.num_fixed_columns(), 2);
+    assert_eq!(meta.num_selectors(), 2);
+
+    let gates = meta.gates();
+    assert_eq!(gates.len(), 1);
+    assert_eq!(gates[0].name(), "next is square");
+    assert_eq!(gates[0].constraint_name(0), "square");
+    assert_eq!(gates[0].polynomials().len(), 1);
+    assert_eq!(gates[0].polynomials()[0].degree(), 3);
+    assert_eq!(gates[0].queried_selectors(), &[q]);
+    let cells: Vec<(Column<Any>, Rotation)> = gates[0]
+        .queried_cells()
+        .iter()
+        .map(|cell| (cell.column(), cell.rotation()))
+        .collect();
+    assert_eq!(
+        cells,
+        vec![(a.into(), Rotation::cur()), (a.into(), Rotation::next())]
+    );
+
+    let lookups = meta.lookups();
+    assert_eq!(lookups.len(), 1);
+    assert_eq!(lookups[0].name(), "b in table");
+    assert_eq!(lookups[0].input_expressions().len(), 1);
+    assert_eq!(lookups[0].table_expressions().len(), 1);
+
+    let shuffles = meta.shuffles();
+    assert_eq!(shuffles.len(), 1);
+    assert_eq!(shuffles[0].name(), "b is a shuffle of a");
+    assert_eq!(shuffles[0].input_expressions().len(), 1);
+    assert_eq!(shuffles[0].shuffle_expressions().len(), 1);
+
+    let permutation_columns: Vec<Column<Any>> = vec![a.into(), instance.into(), constant.into()];
+    assert_eq!(meta.permutation_columns(), permutation_columns);
+    assert_eq!(meta.constants(), &[constant]);
+
+    assert_eq!(
+        meta.advice_queries(),
+        &[
+            (a, Rotation::cur()),
+            (a, Rotation::next()),
+            (b, Rotation::cur())
+        ]
+    );
+    assert_eq!(meta.instance_queries(), &[(instance, Rotation::cur())]);
+    assert!(meta.fixed_queries().contains(&(constant, Rotation::cur())));
+    assert_eq!(meta.degree(), meta.stats().degree);
+}
@@ -0,0 +1,117 @@
+mod common;
+
+use common::{setup, SquareCircuit};
+use halo2_proofs::{
+    plonk::{create_proof, create_proof_with_memory, ProverMemory, ProvingKey},
+    poly::commitment::Params,
+    transcript::{Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Fr as Fp, G1Affine};
+use rand::{rngs::StdRng, SeedableRng};
+use std::io::{self, Write};
+
+const K: u32 = 5;
+
+type Squares = SquareCircuit<8>;
+
+fn prove(
+    params: &Params<G1Affine>,
+    pk: &ProvingKey<G1Affine>,
+    memory: ProverMemory,
+    seed: u64,
+) -> Vec<u8> {
+    let instances: &[&[&[Fp]]] = &[&[]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_memory(
+        params,
+        pk,
+        &[Squares::new(3)],
+        instances,
+        memory,
+        StdRng::seed_from_u64(seed),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+#[test]
+fn deterministic_proof() {
+    let (params, _, pk) = setup(K, &Squares::default(), 0);
+
+    let proof = prove(&params, &pk, ProverMemory::Fast, 0);
+    assert_eq!(proof, prove(&params, &pk, ProverMemory::Fast, 0));
+    assert_eq!(proof, prove(&params, &pk, ProverMemory::Low, 0));
+    assert_ne!(proof, prove(&params, &pk, ProverMemory::Fast, 1));
+
+    // `create_proof` is `create_proof_with_memory` with `ProverMemory::Fast`.
+    let instances: &[&[&[Fp]]] = &[&[]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[Squares::new(3)],
+        instances,
+        StdRng::seed_from_u64(0),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    assert_eq!(proof, transcript.finalize());
+
+    // The number of threads doesn't change the proof.
+    #[cfg(feature = "multicore")]
+    for num_threads in [1, 3].iter() {
+        use halo2_proofs::worker::{with_thread_pool, ThreadPoolBuilder};
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(*num_threads)
+            .build()
+            .unwrap();
+        let threaded = with_thread_pool(&pool, || prove(&params, &pk, ProverMemory::Fast, 0));
+        assert_eq!(proof, threaded);
+    }
+}
+
+/// A writer that records how much had been written at each flush.
+#[derive(Default)]
+struct Rounds {
+    written: Vec<u8>,
+    flushed: Vec<usize>,
+}
+
+impl Write for Rounds {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.written.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.flushed.push(self.written.len());
+        Ok(())
+    }
+}
+
+#[test]
+fn streamed_proof() {
+    let (params, _, pk) = setup(K, &Squares::default(), 0);
+
+    let instances: &[&[&[Fp]]] = &[&[]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(Rounds::default());
+    create_proof(
+        &params,
+        &pk,
+        &[Squares::new(3)],
+        instances,
+        StdRng::seed_from_u64(0),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let rounds = transcript.finalize();
+
+    // The proof is flushed at the end of each of its five rounds, the first of
+    // which holds the two compressed advice commitments.
+    assert_eq!(rounds.written, prove(&params, &pk, ProverMemory::Fast, 0));
+    assert_eq!(rounds.flushed.len(), 5);
+    assert_eq!(rounds.flushed[0], 2 * 32);
+    assert!(rounds.flushed.windows(2).all(|w| w[0] <= w[1]));
+    assert_eq!(*rounds.flushed.last().unwrap(), rounds.written.len());
+}
@@ -0,0 +1,324 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    codegen::generate_verifier,
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Fixed, Instance, ProvingKey, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{CommitmentScheme, Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{
+        keccak::{Keccak256Read, Keccak256Write},
+        Challenge255,
+    },
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+use std::env;
+use std::fs;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+const K: u32 = 5;
+
+#[derive(Clone, Debug)]
+struct MulConfig {
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+    table: Column<Fixed>,
+    q_mul: Selector,
+    q_range: Selector,
+    instance: Column<Instance>,
+}
+
+/// Exposes `c = a * b` as a public input, with `a` looked up in `0..8`.
+#[derive(Default)]
+struct MulCircuit {
+    a: Option<Fp>,
+    b: Option<Fp>,
+}
+
+impl Circuit<Fp> for MulCircuit {
+    type Config = MulConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = MulConfig {
+            a: meta.advice_column(),
+            b: meta.advice_column(),
+            c: meta.advice_column(),
+            table: meta.fixed_column(),
+            q_mul: meta.selector(),
+            q_range: meta.complex_selector(),
+            instance: meta.instance_column(),
+        };
+        meta.enable_equality(config.c);
+        meta.enable_equality(config.instance);
+
+        meta.create_gate("mul", |meta| {
+            let q = meta.query_selector(config.q_mul);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let b = meta.query_advice(config.b, Rotation::cur());
+            let c = meta.query_advice(config.c, Rotation::cur());
+            vec![q * (a * b - c)]
+        });
+
+        meta.lookup_any("range", |meta| {
+            let q = meta.query_selector(config.q_range);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let table = meta.query_fixed(config.table, Rotation::cur());
+            vec![(q * a, table)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "table",
+            |mut region| {
+                for i in 0..8 {
+                    region.assign_fixed(|| "table", config.table, i, || Ok(Fp::from(i as u64)))?;
+                }
+                Ok(())
+            },
+        )?;
+
+        let c = layouter.assign_region(
+            || "mul",
+            |mut region| {
+                config.q_mul.enable(&mut region, 0)?;
+                config.q_range.enable(&mut region, 0)?;
+                region.assign_advice(|| "a", config.a, 0, || self.a.ok_or(Error::Synthesis))?;
+                region.assign_advice(|| "b", config.b, 0, || self.b.ok_or(Error::Synthesis))?;
+                region.assign_advice(
+                    || "c",
+                    config.c,
+                    0,
+                    || Ok(self.a.ok_or(Error::Synthesis)? * self.b.ok_or(Error::Synthesis)?),
+                )
+            },
+        )?;
+
+        layouter.constrain_instance(c.cell(), config.instance, 0)
+    }
+}
+
+/// Returns the verifier params and proving key of [`MulCircuit`], and a proof
+/// of `3 * 5 = 15` created with `scheme` and the Keccak256 transcript.
+fn setup(scheme: CommitmentScheme) -> (ParamsVerifier<Bn256>, ProvingKey<G1Affine>, Vec<u8>) {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K).with_scheme(scheme);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(1).unwrap();
+    let vk = keygen_vk(&params, &MulCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MulCircuit::default()).expect("keygen_pk should not fail");
+
+    let circuit = MulCircuit {
+        a: Some(Fp::from(3)),
+        b: Some(Fp::from(5)),
+    };
+    let instances = [Fp::from(15)];
+
+    let mut transcript = Keccak256Write::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[&instances[..]]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+
+    (params_verifier, pk, transcript.finalize())
+}
+
+#[test]
+fn evm_verifier() {
+    let (params_verifier, pk, proof) = setup(CommitmentScheme::KzgShplonk);
+    let instances = [Fp::from(15)];
+
+    // The Keccak256 transcript is a regular transcript for the native verifier.
+    let strategy = SingleVerifier::new(&params_verifier);
+    let mut transcript = Keccak256Read::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        strategy,
+        &[&[&instances[..]]],
+        &mut transcript,
+    )
+    .is_ok());
+
+    let verifier = generate_verifier(&params_verifier, pk.get_vk(), &[1]).unwrap();
+    assert!(verifier.source().contains("contract Halo2Verifier"));
+    assert_eq!(verifier.num_instances(), &[1]);
+    assert_eq!(verifier.proof_len(), proof.len());
+
+    // selector || offsets || instances || proof, padded to whole words.
+    let calldata = verifier.encode_calldata(&[&instances[..]], &proof).unwrap();
+    assert_eq!(
+        calldata.len(),
+        4 + 0x20 * 5 + (proof.len() + 0x1f) / 0x20 * 0x20
+    );
+    assert_eq!(&calldata[..4], &[0x5a, 0x36, 0x4d, 0x2d]);
+    assert_eq!(calldata[4 + 0x1f], 0x40);
+    assert_eq!(calldata[4 + 0x3f], 0x80);
+    assert_eq!(calldata[4 + 0x5f], 1);
+    assert_eq!(calldata[4 + 0x7f], 15);
+    assert_eq!(calldata[4 + 0x9f] as usize, proof.len() % 0x100);
+    assert_eq!(&calldata[4 + 0xa0..4 + 0xa0 + proof.len()], &proof[..]);
+
+    assert!(matches!(
+        generate_verifier(&params_verifier, pk.get_vk(), &[]),
+        Err(Error::InvalidInstances)
+    ));
+    assert!(matches!(
+        generate_verifier(&params_verifier, pk.get_vk(), &[2]),
+        Err(Error::InstanceTooLarge)
+    ));
+    assert!(matches!(
+        verifier.encode_calldata(&[&[Fp::from(1), Fp::from(2)][..]], &proof),
+        Err(Error::InstanceTooLarge)
+    ));
+
+    // The contract follows the scheme of the params.
+    let (params_verifier, pk, proof) = setup(CommitmentScheme::KzgGwc);
+    let gwc = generate_verifier(&params_verifier, pk.get_vk(), &[1]).unwrap();
+    assert_eq!(gwc.proof_len(), proof.len());
+    assert_ne!(gwc.source(), verifier.source());
+}
+
+/// Returns whether `program` can be run, so that the contract is only executed
+/// where `solc` and go-ethereum's `evm` are installed. CI installs both and
+/// sets `HALO2_EVM_TESTS`, which makes them required.
+fn available(program: &str) -> bool {
+    let found = Command::new(program).arg("--version").output().is_ok();
+    if !found {
+        assert!(
+            env::var_os("HALO2_EVM_TESTS").is_none(),
+            "`{}` must be installed when HALO2_EVM_TESTS is set",
+            program
+        );
+        eprintln!("`{}` not found, skipping", program);
+    }
+    found
+}
+
+fn decode_hex(hex: &str) -> Vec<u8> {
+    let hex = hex.trim().trim_start_matches("0x");
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+        .collect()
+}
+
+fn encode_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{:02x}", byte)).collect()
+}
+
+/// Compiles `source` with `solc`, and returns the runtime bytecode of its
+/// contract.
+fn compile(source: &str) -> Vec<u8> {
+    let mut solc = Command::new("solc")
+        .args(&["--bin-runtime", "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    solc.stdin
+        .take()
+        .unwrap()
+        .write_all(source.as_bytes())
+        .unwrap();
+    let output = solc.wait_with_output().unwrap();
+    assert!(
+        output.status.success(),
+        "solc failed: {}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    // The bytecode is the last line, after the name of the contract.
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    decode_hex(stdout.split_whitespace().last().unwrap())
+}
+
+/// Runs `code` on `calldata` with `evm`, and returns whether it returned
+/// `true`. A revert is printed as an error rather than returned.
+fn call(code: &[u8], calldata: &[u8]) -> bool {
+    let dir = env::temp_dir().join(format!("halo2_evm_verifier_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let codefile = dir.join("code.hex");
+    fs::write(&codefile, encode_hex(code)).unwrap();
+
+    let output = Command::new("evm")
+        .arg("--codefile")
+        .arg(&codefile)
+        .args(&["--input", &encode_hex(calldata), "run"])
+        .output()
+        .unwrap();
+    fs::remove_dir_all(&dir).unwrap();
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let mut returned = [0u8; 32];
+    returned[31] = 1;
+    output.status.success()
+        && !stdout.contains("error")
+        && stdout.trim() == format!("0x{}", encode_hex(&returned))
+}
+
+#[test]
+fn evm_verifier_execution() {
+    if !available("solc") || !available("evm") {
+        return;
+    }
+
+    for scheme in [CommitmentScheme::KzgGwc, CommitmentScheme::KzgShplonk].iter() {
+        execute(*scheme);
+    }
+}
+
+fn execute(scheme: CommitmentScheme) {
+    let (params_verifier, pk, proof) = setup(scheme);
+    let instances = [Fp::from(15)];
+    let verifier = generate_verifier(&params_verifier, pk.get_vk(), &[1]).unwrap();
+    let code = compile(verifier.source());
+
+    let calldata = verifier.encode_calldata(&[&instances[..]], &proof).unwrap();
+    assert!(call(&code, &calldata));
+
+    // A wrong instance.
+    let calldata = verifier
+        .encode_calldata(&[&[Fp::from(16)][..]], &proof)
+        .unwrap();
+    assert!(!call(&code, &calldata));
+
+    // A tampered proof. With SHPLONK, this is the last evaluation, just before
+    // the two points of the multi-opening argument, and it stays canonical.
+    if scheme == CommitmentScheme::KzgShplonk {
+        let mut tampered = proof.clone();
+        let last_scalar = tampered.len() - 2 * 0x40 - 1;
+        tampered[last_scalar] ^= 1;
+        let calldata = verifier
+            .encode_calldata(&[&instances[..]], &tampered)
+            .unwrap();
+        assert!(!call(&code, &calldata));
+    }
+
+    // A truncated proof.
+    let calldata = verifier
+        .encode_calldata(&[&instances[..]], &proof[..proof.len() - 0x20])
+        .unwrap();
+    assert!(!call(&code, &calldata));
+}
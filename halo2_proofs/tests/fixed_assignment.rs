@@ -0,0 +1,111 @@
+#![cfg(debug_assertions)]
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error,
+        Fixed, Selector,
+    },
+    poly::{commitment::Params, Rotation},
+    transcript::{Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+#[derive(Clone, Debug)]
+struct ConstantConfig {
+    q: Selector,
+    a: Column<Advice>,
+    c: Column<Fixed>,
+}
+
+/// Constrains `a` to equal a constant baked into a fixed column.
+struct ConstantCircuit {
+    constant: u64,
+    a: Option<u64>,
+}
+
+impl<F: FieldExt> Circuit<F> for ConstantCircuit {
+    type Config = ConstantConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        ConstantCircuit {
+            constant: self.constant,
+            a: None,
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let config = ConstantConfig {
+            q: meta.selector(),
+            a: meta.advice_column(),
+            c: meta.fixed_column(),
+        };
+
+        meta.create_gate("constant", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let c = meta.query_fixed(config.c, Rotation::cur());
+
+            Some(q * (a - c))
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "constant",
+            |mut region| {
+                config.q.enable(&mut region, 0)?;
+                region.assign_fixed(|| "c", config.c, 0, || Ok(F::from(self.constant)))?;
+                region.assign_advice(
+                    || "a",
+                    config.a,
+                    0,
+                    || self.a.map(F::from).ok_or(Error::Synthesis),
+                )
+            },
+        )
+    }
+}
+
+#[test]
+fn fixed_assignment_mismatch() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let keygen_circuit = ConstantCircuit {
+        constant: 3,
+        a: None,
+    };
+    let vk = keygen_vk(&params, &keygen_circuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &keygen_circuit).expect("keygen_pk should not fail");
+    let instances: &[&[&[Fp]]] = &[&[]];
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let circuit = ConstantCircuit {
+        constant: 3,
+        a: Some(3),
+    };
+    create_proof(&params, &pk, &[circuit], instances, OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+
+    // A circuit with a different constant is caught before it produces an invalid
+    // proof.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let circuit = ConstantCircuit {
+        constant: 4,
+        a: Some(4),
+    };
+    assert!(matches!(
+        create_proof(&params, &pk, &[circuit], instances, OsRng, &mut transcript),
+        Err(Error::FixedAssignmentMismatch { column, row: 0 }) if column.index() == 0
+    ));
+}
@@ -0,0 +1,131 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Selector, SingleVerifier, TableColumn,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 7;
+
+/// The number of rows of the table, which only fits in `K` or more.
+const ROWS: usize = 100;
+
+#[derive(Clone, Debug)]
+struct SquareConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    input: TableColumn,
+    square: TableColumn,
+}
+
+/// Checks that each pair `(a, b)` has `b = a^2` and `a < ROWS`, with a table
+/// that is filled at keygen rather than by `synthesize`.
+#[derive(Default)]
+struct SquareCircuit {
+    pairs: Vec<(u64, u64)>,
+}
+
+impl Circuit<Fp> for SquareCircuit {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        SquareCircuit {
+            pairs: vec![(0, 0); self.pairs.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = SquareConfig {
+            q: meta.complex_selector(),
+            a: meta.advice_column(),
+            b: meta.advice_column(),
+            input: meta.lookup_table_from(ROWS, |i| Fp::from(i as u64)),
+            square: meta.lookup_table_from(ROWS, |i| Fp::from((i * i) as u64)),
+        };
+
+        meta.lookup("square", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let b = meta.query_advice(config.b, Rotation::cur());
+            vec![(q.clone() * a, config.input), (q * b, config.square)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "pairs",
+            |mut region| {
+                for (row, (a, b)) in self.pairs.iter().enumerate() {
+                    config.q.enable(&mut region, row)?;
+                    region.assign_advice(|| "a", config.a, row, || Ok(Fp::from(*a)))?;
+                    region.assign_advice(|| "b", config.b, row, || Ok(Fp::from(*b)))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn generated_table() {
+    let circuit = SquareCircuit {
+        pairs: vec![(0, 0), (7, 49), (99, 9801)],
+    };
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // A wrong square, and a square beyond the table.
+    for pairs in [vec![(7, 48)], vec![(100, 10000)]] {
+        let prover = MockProver::run(K, &SquareCircuit { pairs }, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // The table doesn't fit in fewer rows.
+    assert!(matches!(
+        MockProver::run(K - 1, &circuit, vec![]),
+        Err(Error::NotEnoughRowsAvailable { minimum_k: K, .. })
+    ));
+    let small_params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K - 1);
+    assert!(matches!(
+        keygen_vk(&small_params, &circuit),
+        Err(Error::NotEnoughRowsAvailable { minimum_k: K, .. })
+    ));
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+    let empty = circuit.without_witnesses();
+    let vk = keygen_vk(&params, &empty).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &empty).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        SingleVerifier::new(&params_verifier),
+        &[&[]],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "alloc-stats")]
+
+use std::alloc::System;
+
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::heap::{self, CountingAllocator},
+    plonk::{create_proof, keygen_pk, keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error},
+    poly::commitment::Params,
+    transcript::{Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+#[global_allocator]
+static ALLOCATOR: CountingAllocator = CountingAllocator::new(System);
+
+const K: u32 = 8;
+
+/// Fills an advice column with its row numbers.
+struct RowsCircuit;
+
+impl Circuit<Fp> for RowsCircuit {
+    type Config = Column<Advice>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        RowsCircuit
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        meta.advice_column()
+    }
+
+    fn synthesize(&self, a: Self::Config, mut layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rows",
+            |mut region| {
+                for row in 0..100 {
+                    region.assign_advice(|| "a", a, row, || Ok(Fp::from(row as u64)))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+// The counts are shared by all threads, so everything is measured in one test.
+#[test]
+fn peak_heap_usage() {
+    // A nested measurement reports its own peak, and doesn't hide the enclosing one.
+    let ((), outer_peak) = heap::peak_during(|| {
+        drop(vec![0u8; 1 << 20]);
+        let (buffer, inner_peak) = heap::peak_during(|| vec![0u8; 1 << 10]);
+        assert!(inner_peak >= buffer.len());
+        assert!(inner_peak < 1 << 20);
+    });
+    assert!(outer_peak >= 1 << 20);
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+
+    let before = heap::allocated();
+    let (pk, keygen_peak) = heap::peak_during(|| {
+        let vk = keygen_vk(&params, &RowsCircuit).expect("keygen_vk should not fail");
+        keygen_pk(&params, vk, &RowsCircuit).expect("keygen_pk should not fail")
+    });
+    assert!(keygen_peak > before);
+
+    let before = heap::allocated();
+    let (proof, prover_peak) = heap::peak_during(|| {
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof(&params, &pk, &[RowsCircuit], &[&[]], OsRng, &mut transcript)
+            .expect("proof generation should not fail");
+        transcript.finalize()
+    });
+    // The prover holds at least one polynomial of 2^K scalars at a time.
+    assert!(prover_peak >= before + proof.len() + (32 << K));
+}
@@ -0,0 +1,215 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Instance, InstanceLayoutError, InstanceSet, ProvingKey, Selector,
+        SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+/// The number of rows of each instance column that the circuit checks.
+const ROWS: usize = 4;
+
+#[derive(Clone, Debug)]
+struct MirrorConfig {
+    q: Selector,
+    advice: [Column<Advice>; 2],
+    instance: [Column<Instance>; 2],
+}
+
+/// Witnesses a copy of the first `ROWS` rows of each of its two instance
+/// columns, so that the columns may hold any number of values up to `ROWS`.
+#[derive(Clone, Default)]
+struct MirrorCircuit {
+    columns: [Vec<u64>; 2],
+}
+
+impl MirrorCircuit {
+    fn instances(&self) -> Vec<Vec<Fp>> {
+        self.columns
+            .iter()
+            .map(|column| column.iter().map(|v| Fp::from(*v)).collect())
+            .collect()
+    }
+}
+
+impl Circuit<Fp> for MirrorCircuit {
+    type Config = MirrorConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = MirrorConfig {
+            q: meta.selector(),
+            advice: [meta.advice_column(), meta.advice_column()],
+            instance: [meta.instance_column(), meta.instance_column()],
+        };
+
+        meta.create_gate("mirror", |meta| {
+            let q = meta.query_selector(config.q);
+            config
+                .advice
+                .iter()
+                .zip(config.instance.iter())
+                .map(|(advice, instance)| {
+                    let advice = meta.query_advice(*advice, Rotation::cur());
+                    let instance = meta.query_instance(*instance, Rotation::cur());
+                    q.clone() * (advice - instance)
+                })
+                .collect::<Vec<_>>()
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "mirror",
+            |mut region| {
+                for row in 0..ROWS {
+                    config.q.enable(&mut region, row)?;
+                    for (advice, column) in config.advice.iter().zip(self.columns.iter()) {
+                        // Rows after the end of the column are zero.
+                        let value = column.get(row).copied().unwrap_or(0);
+                        region.assign_advice(|| "copy", *advice, row, || Ok(Fp::from(value)))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn prove(
+    params: &Params<G1Affine>,
+    pk: &ProvingKey<G1Affine>,
+    circuits: &[MirrorCircuit],
+    instances: &[&[&[Fp]]],
+) -> Result<Vec<u8>, Error> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(params, pk, circuits, instances, OsRng, &mut transcript)?;
+    Ok(transcript.finalize())
+}
+
+#[test]
+fn instance_layout() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(ROWS).unwrap();
+    let vk = keygen_vk(&params, &MirrorCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MirrorCircuit::default()).expect("keygen_pk should not fail");
+
+    // The columns of each circuit have different lengths.
+    let circuits = [
+        MirrorCircuit {
+            columns: [vec![1, 2, 3], vec![4]],
+        },
+        MirrorCircuit {
+            columns: [vec![5], vec![6, 7, 8, 9]],
+        },
+    ];
+    let instances: InstanceSet<Fp> = circuits.iter().map(|c| c.instances()).collect();
+    assert_eq!(instances.len(), 2);
+    assert_eq!(instances.circuit(1), Some(&circuits[1].instances()[..]));
+    instances
+        .validate(pk.get_vk(), circuits.len())
+        .expect("instances should be valid");
+
+    let proof = instances
+        .with_slices(|instances| prove(&params, &pk, &circuits, instances))
+        .expect("proof generation should not fail");
+    let verify = |instances: &InstanceSet<Fp>| {
+        instances.with_slices(|instances| {
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            verify_proof(
+                &params_verifier,
+                pk.get_vk(),
+                SingleVerifier::new(&params_verifier),
+                instances,
+                &mut transcript,
+            )
+        })
+    };
+    verify(&instances).expect("proof should verify");
+
+    // The instances of the circuits aren't interchangeable.
+    let swapped: InstanceSet<Fp> = circuits.iter().rev().map(|c| c.instances()).collect();
+    assert!(verify(&swapped).is_err());
+
+    // Layout errors are reported before proving, naming the circuit and column.
+    let layout_error = |instances: &InstanceSet<Fp>, num_circuits: usize| {
+        let validated = instances.validate(pk.get_vk(), num_circuits);
+        let proved = instances
+            .with_slices(|instances| prove(&params, &pk, &circuits[..num_circuits], instances));
+        match (validated, proved) {
+            (Err(Error::InstanceLayout(validated)), Err(Error::InstanceLayout(proved))) => {
+                assert_eq!(validated, proved);
+                validated
+            }
+            _ => panic!("expected an instance layout error"),
+        }
+    };
+
+    assert_eq!(
+        layout_error(&instances, 1),
+        InstanceLayoutError::CircuitCount {
+            circuits: 1,
+            instances: 2,
+        }
+    );
+
+    // Every circuit is given every instance column.
+    let missing_column: InstanceSet<Fp> = vec![circuits[0].instances(), vec![vec![Fp::from(5)]]]
+        .into_iter()
+        .collect();
+    assert_eq!(
+        layout_error(&missing_column, 2),
+        InstanceLayoutError::ColumnCount {
+            circuit: 1,
+            columns: 1,
+            expected: 2,
+        }
+    );
+
+    // A column can't be longer than the circuit.
+    let len = 1 << K;
+    let too_long: InstanceSet<Fp> = vec![
+        circuits[0].instances(),
+        vec![vec![Fp::from(5)], vec![Fp::from(6); len]],
+    ]
+    .into_iter()
+    .collect();
+    let error = layout_error(&too_long, 2);
+    let max = match &error {
+        InstanceLayoutError::ColumnTooLong {
+            circuit: 1,
+            column: 1,
+            len: actual,
+            max,
+        } if *actual == len && *max < len => *max,
+        error => panic!("unexpected error {:?}", error),
+    };
+    assert_eq!(
+        Error::InstanceLayout(error).to_string(),
+        format!(
+            "Invalid instance layout: Instance column 1 of circuit 1 holds {} values, \
+             but the circuit has {} usable rows",
+            len, max
+        )
+    );
+}
@@ -0,0 +1,145 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof, keygen_pk2, keygen_pks, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Fixed, ProvingKey, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+/// The smallest `k` that fits the circuit.
+const K: u32 = 4;
+const INSTANCES: &[&[&[Fp]]] = &[&[]];
+
+#[derive(Clone, Debug)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    f: Column<Fixed>,
+}
+
+/// Constrains each `a` to equal the fixed value on its row, and the first `a` to
+/// equal a constant.
+#[derive(Default)]
+struct MyCircuit {
+    witness: bool,
+}
+
+impl MyCircuit {
+    const ROWS: usize = 6;
+}
+
+impl Circuit<Fp> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = MyConfig {
+            q: meta.selector(),
+            a: meta.advice_column(),
+            f: meta.fixed_column(),
+        };
+        meta.enable_equality(config.a);
+        let constants = meta.fixed_column();
+        meta.enable_constant(constants);
+
+        meta.create_gate("a = f", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let f = meta.query_fixed(config.f, Rotation::cur());
+
+            vec![q * (a - f)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "rows",
+            |mut region| {
+                for row in 0..Self::ROWS {
+                    let value = Fp::from(row as u64 + 1);
+                    config.q.enable(&mut region, row)?;
+                    region.assign_fixed(|| "f", config.f, row, || Ok(value))?;
+                    let a = region.assign_advice(
+                        || "a",
+                        config.a,
+                        row,
+                        || Some(value).filter(|_| self.witness).ok_or(Error::Synthesis),
+                    )?;
+                    if row == 0 {
+                        region.constrain_constant(a.cell(), value)?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn vk_bytes(pk: &ProvingKey<G1Affine>) -> Vec<u8> {
+    let mut buffer = vec![];
+    pk.get_vk().write(&mut buffer).unwrap();
+    buffer
+}
+
+#[test]
+fn keygen_pks_at_each_k() {
+    let params: Vec<Params<G1Affine>> = (K..K + 3)
+        .map(|k| Params::<G1Affine>::unsafe_setup::<Bn256>(k))
+        .collect();
+    let pks = keygen_pks(&params.iter().collect::<Vec<_>>(), &MyCircuit::default())
+        .expect("keygen_pks should not fail");
+    assert_eq!(pks.len(), params.len());
+
+    for (params, pk) in params.iter().zip(pks.iter()) {
+        let pk2 = keygen_pk2(params, &MyCircuit::default()).expect("keygen_pk2 should not fail");
+        assert_eq!(vk_bytes(pk), vk_bytes(&pk2));
+
+        let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+        create_proof(
+            params,
+            pk,
+            &[MyCircuit { witness: true }],
+            INSTANCES,
+            OsRng,
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        let proof = transcript.finalize();
+
+        let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+        let strategy = SingleVerifier::new(&params_verifier);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        assert!(verify_proof(
+            &params_verifier,
+            pk.get_vk(),
+            strategy,
+            INSTANCES,
+            &mut transcript
+        )
+        .is_ok());
+    }
+
+    // A single size that is too small fails the whole family.
+    let too_small = Params::<G1Affine>::unsafe_setup::<Bn256>(K - 1);
+    assert!(matches!(
+        keygen_pks(&[&too_small, &params[0]], &MyCircuit::default()),
+        Err(Error::NotEnoughRowsAvailable { current_k, .. }) if current_k == K - 1
+    ));
+}
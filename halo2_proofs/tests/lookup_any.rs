@@ -4,10 +4,18 @@ use halo2_proofs::{
     arithmetic::FieldExt,
     circuit::{Layouter, SimpleFloorPlanner},
     dev::MockProver,
-    plonk::{Advice, Circuit, Column, ConstraintSystem, Error, Instance, Selector},
-    poly::Rotation,
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Instance, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
 };
-use pairing::bn256::Fr as Fp;
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
 
 #[test]
 fn lookup_any() {
@@ -205,6 +213,40 @@ fn lookup_any() {
 
     // If we pass in a public input containing only even numbers,
     // the odd number lookup will fail.
-    let prover = MockProver::run(k, &circuit, vec![even_lookup]).unwrap();
-    assert!(prover.verify().is_err())
+    let prover = MockProver::run(k, &circuit, vec![even_lookup.clone()]).unwrap();
+    assert!(prover.verify().is_err());
+
+    // The real prover and verifier accept tables built from advice and instance
+    // columns as well.
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(k);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(odd_lookup.len()).unwrap();
+    let vk = keygen_vk(&params, &MyCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit::default()).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[circuit],
+        &[&[&odd_lookup[..]]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof: Vec<u8> = transcript.finalize();
+
+    let verify = |table: &[Fp]| {
+        let strategy = SingleVerifier::new(&params_verifier);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        verify_proof(
+            &params_verifier,
+            pk.get_vk(),
+            strategy,
+            &[&[table]],
+            &mut transcript,
+        )
+    };
+    assert!(verify(&odd_lookup).is_ok());
+    // The instance table is bound to the proof.
+    assert!(verify(&even_lookup).is_err());
 }
@@ -0,0 +1,118 @@
+mod common;
+
+use common::{setup, verify, SquareConfig, XorShiftRng};
+use ff::Field;
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof_with_memory, Circuit, Column, ConstraintSystem, Error, Fixed, Instance,
+        ProverMemory,
+    },
+    poly::Rotation,
+    transcript::{Blake2bWrite, Challenge255},
+};
+use pairing::bn256::Fr as Fp;
+
+const K: u32 = 5;
+
+#[derive(Clone, Debug)]
+struct TableConfig {
+    square: SquareConfig,
+    table: Column<Fixed>,
+    instance: Column<Instance>,
+}
+
+/// Constrains `b = a^2` on each of the first `ROWS` rows, copies each `b` to the
+/// next row's `a`, and looks up each `a` in a fixed table of the same values. The
+/// first `a` is the instance.
+#[derive(Default)]
+struct TableCircuit {
+    a: Option<Fp>,
+}
+
+impl TableCircuit {
+    const ROWS: usize = 4;
+}
+
+impl Circuit<Fp> for TableCircuit {
+    type Config = TableConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let q = meta.complex_selector();
+        let config = TableConfig {
+            square: SquareConfig::configure(meta, q, true),
+            table: meta.fixed_column(),
+            instance: meta.instance_column(),
+        };
+        meta.enable_equality(config.instance);
+
+        meta.lookup_any("a in table", |meta| {
+            let q = meta.query_selector(config.square.q);
+            let a = meta.query_advice(config.square.a, Rotation::cur());
+            let table = meta.query_fixed(config.table, Rotation::cur());
+
+            vec![(q * a, table)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let first = config.square.assign(&mut layouter, self.a, Self::ROWS)?;
+        layouter.constrain_instance(first, config.instance, 0)?;
+
+        layouter.assign_region(
+            || "table",
+            |mut region| {
+                let mut value = Fp::from(3);
+                for row in 0..Self::ROWS {
+                    region.assign_fixed(|| "table", config.table, row, || Ok(value))?;
+                    value = value.square();
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn low_memory_proof() {
+    let instances: &[&[&[Fp]]] = &[&[&[Fp::from(3)]]];
+
+    let (params, params_verifier, pk) = setup(K, &TableCircuit::default(), 1);
+
+    let proofs: Vec<Vec<u8>> = [ProverMemory::Fast, ProverMemory::Low]
+        .iter()
+        .map(|memory| {
+            let circuit = TableCircuit {
+                a: Some(Fp::from(3)),
+            };
+            let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+            create_proof_with_memory(
+                &params,
+                &pk,
+                &[circuit],
+                instances,
+                *memory,
+                XorShiftRng(0x2545_f491_4f6c_dd1d),
+                &mut transcript,
+            )
+            .expect("proof generation should not fail");
+            transcript.finalize()
+        })
+        .collect();
+
+    // The same randomness gives the same proof.
+    assert_eq!(proofs[0], proofs[1]);
+
+    assert!(verify(&params_verifier, &pk, instances, &proofs[1]).is_ok());
+}
@@ -0,0 +1,64 @@
+use halo2_proofs::plonk::ConstraintSystem;
+use halo2_proofs::poly::Rotation;
+use pairing::bn256::Fr as Fp;
+
+#[test]
+fn gate_within_maximum_degree() {
+    let mut meta = ConstraintSystem::<Fp>::default();
+    meta.set_maximum_degree(3);
+
+    let q = meta.selector();
+    let a = meta.advice_column();
+    meta.create_gate("square", |meta| {
+        let q = meta.query_selector(q);
+        let a = meta.query_advice(a, Rotation::cur());
+        Some(q * (a.clone() * a.clone() - a))
+    });
+    assert_eq!(meta.degree(), 3);
+}
+
+#[test]
+#[should_panic(expected = "gate \"cube\" has degree 4, above the maximum degree 3")]
+fn gate_above_maximum_degree() {
+    let mut meta = ConstraintSystem::<Fp>::default();
+    meta.set_maximum_degree(3);
+
+    let q = meta.selector();
+    let a = meta.advice_column();
+    meta.create_gate("cube", |meta| {
+        let q = meta.query_selector(q);
+        let a = meta.query_advice(a, Rotation::cur());
+        Some(q * (a.clone() * a.clone() * a.clone() - a))
+    });
+}
+
+#[test]
+#[should_panic(expected = "lookup \"square\" has degree 5, above the maximum degree 4")]
+fn lookup_above_maximum_degree() {
+    let mut meta = ConstraintSystem::<Fp>::default();
+    meta.set_maximum_degree(4);
+
+    let q = meta.complex_selector();
+    let a = meta.advice_column();
+    let table = meta.lookup_table_column();
+    meta.lookup("square", |meta| {
+        let q = meta.query_selector(q);
+        let a = meta.query_advice(a, Rotation::cur());
+        vec![(q * a, table)]
+    });
+}
+
+#[test]
+#[should_panic(expected = "the constraint system already has degree 4, above the maximum degree 3")]
+fn maximum_degree_below_existing_gate() {
+    let mut meta = ConstraintSystem::<Fp>::default();
+
+    let q = meta.selector();
+    let a = meta.advice_column();
+    meta.create_gate("cube", |meta| {
+        let q = meta.query_selector(q);
+        let a = meta.query_advice(a, Rotation::cur());
+        Some(q * (a.clone() * a.clone() * a.clone() - a))
+    });
+    meta.set_maximum_degree(3);
+}
@@ -0,0 +1,252 @@
+mod common;
+
+use common::{prove, setup, verify, SquareConfig};
+use group::{prime::PrimeCurveAffine, GroupEncoding};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{
+        create_proof, verify_proof, verify_proof_strict, Advice, Circuit, Column, ConstraintSystem,
+        Error, Selector, SingleVerifier, TableColumn,
+    },
+    poly::Rotation,
+    transcript::{
+        keccak::{Keccak256Read, Keccak256Write},
+        Blake2bRead, Blake2bWrite, Challenge255, PointEncoding,
+    },
+};
+use pairing::bn256::{Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+const INSTANCES: &[&[&[Fp]]] = &[&[]];
+
+/// Constrains `b = a^2` on each of the first `ROWS` rows, and copies each `b` to the
+/// next row's `a`.
+#[derive(Default)]
+struct SquareCircuit<const ZK: bool> {
+    a: Option<Fp>,
+}
+
+impl<const ZK: bool> SquareCircuit<ZK> {
+    /// All of the rows that are usable without blinding factors.
+    const ROWS: usize = (1 << K) - 1;
+}
+
+impl<const ZK: bool> Circuit<Fp> for SquareCircuit<ZK> {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        if !ZK {
+            meta.disable_zero_knowledge();
+        }
+
+        let q = meta.selector();
+        SquareConfig::configure(meta, q, true)
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        config.assign(&mut layouter, self.a, Self::ROWS)?;
+        Ok(())
+    }
+}
+
+#[test]
+fn non_zk_rows() {
+    let circuit = SquareCircuit::<false> {
+        a: Some(Fp::from(3)),
+    };
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    assert_eq!(prover.usable_rows(), &(0..SquareCircuit::<false>::ROWS));
+
+    // The same rows don't fit with blinding factors.
+    let zk = SquareCircuit::<true> {
+        a: Some(Fp::from(3)),
+    };
+    assert!(matches!(
+        MockProver::run(K, &zk, vec![]),
+        Err(Error::NotEnoughRowsAvailable { .. })
+    ));
+
+    let (params, params_verifier, pk) = setup(K, &SquareCircuit::<false>::default(), 0);
+    let proof = prove(&params, &pk, circuit, INSTANCES);
+    assert!(verify(&params_verifier, &pk, INSTANCES, &proof).is_ok());
+}
+
+#[derive(Clone, Debug)]
+struct UnusedConfig {
+    q: Selector,
+    q_lookup: Selector,
+    a: Column<Advice>,
+    unused: Column<Advice>,
+    table: TableColumn,
+}
+
+/// Assigns `a` on a few rows, but never assigns `unused` and never enables the
+/// lookup of `a`. Without blinding factors, `unused` and the permuted input of
+/// the lookup are zero on every row, and commit to the point at infinity.
+#[derive(Default)]
+struct UnusedCircuit {
+    a: Option<Fp>,
+}
+
+impl Circuit<Fp> for UnusedCircuit {
+    type Config = UnusedConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        meta.disable_zero_knowledge();
+
+        let config = UnusedConfig {
+            q: meta.selector(),
+            q_lookup: meta.complex_selector(),
+            a: meta.advice_column(),
+            unused: meta.advice_column(),
+            table: meta.lookup_table_column(),
+        };
+
+        meta.create_gate("unused", |meta| {
+            let q = meta.query_selector(config.q);
+            let unused = meta.query_advice(config.unused, Rotation::cur());
+
+            vec![q * unused]
+        });
+
+        meta.lookup("a", |meta| {
+            let q_lookup = meta.query_selector(config.q_lookup);
+            let a = meta.query_advice(config.a, Rotation::cur());
+
+            vec![(q_lookup * a, config.table)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "table",
+            |mut table| {
+                for row in 0..4 {
+                    table.assign_cell(
+                        || "table",
+                        config.table,
+                        row,
+                        || Ok(Fp::from(row as u64)),
+                    )?;
+                }
+                Ok(())
+            },
+        )?;
+
+        layouter.assign_region(
+            || "a",
+            |mut region| {
+                for row in 0..4 {
+                    config.q.enable(&mut region, row)?;
+                    region.assign_advice(
+                        || "a",
+                        config.a,
+                        row,
+                        || self.a.ok_or(Error::Synthesis),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn non_zk_zero_columns() {
+    // `a` isn't in the table, but the lookup is never enabled.
+    let circuit = || UnusedCircuit {
+        a: Some(Fp::from(7)),
+    };
+    let prover = MockProver::run(K, &circuit(), vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let (params, params_verifier, pk) = setup(K, &UnusedCircuit::default(), 0);
+
+    let blake2b = |point_encoding| {
+        let mut transcript =
+            Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]).with_point_encoding(point_encoding);
+        create_proof(
+            &params,
+            &pk,
+            &[circuit()],
+            INSTANCES,
+            OsRng,
+            &mut transcript,
+        )
+        .expect("proof generation should not fail");
+        transcript.finalize()
+    };
+    let verify_blake2b = |proof: &[u8], point_encoding| {
+        let strategy = SingleVerifier::new(&params_verifier);
+        let mut transcript =
+            Blake2bRead::<_, _, Challenge255<_>>::init(proof).with_point_encoding(point_encoding);
+        verify_proof(
+            &params_verifier,
+            pk.get_vk(),
+            strategy,
+            INSTANCES,
+            &mut transcript,
+        )
+    };
+
+    // The points at infinity are written in either encoding.
+    for point_encoding in [PointEncoding::Compressed, PointEncoding::Uncompressed].iter() {
+        let proof = blake2b(*point_encoding);
+        assert!(verify_blake2b(&proof, *point_encoding).is_ok());
+    }
+
+    let mut transcript = Keccak256Write::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[circuit()],
+        INSTANCES,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+    // The points at infinity are also accepted by the strict verifier.
+    for verifier in [verify_proof, verify_proof_strict].iter() {
+        let strategy = SingleVerifier::new(&params_verifier);
+        let mut transcript = Keccak256Read::<_, _, Challenge255<_>>::init(&proof[..]);
+        assert!(verifier(
+            &params_verifier,
+            pk.get_vk(),
+            strategy,
+            INSTANCES,
+            &mut transcript
+        )
+        .is_ok());
+    }
+
+    // The proof starts with the commitments to `a` and to `unused`, which is the
+    // point at infinity. Replacing it with the commitment to `a` breaks the gate.
+    let mut proof = blake2b(PointEncoding::Compressed);
+    assert_eq!(&proof[32..64], G1Affine::identity().to_bytes().as_ref());
+    proof.copy_within(..32, 32);
+    assert!(verify_blake2b(&proof, PointEncoding::Compressed).is_err());
+}
@@ -0,0 +1,168 @@
+use std::{env, fs};
+
+use group::ff::PrimeField;
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::assert_pinned_snapshot,
+    plonk::{
+        keygen_vk, Advice, Circuit, Column, ConstraintSystem, Error, Selector, TranscriptHash,
+    },
+    poly::{commitment::Params, Rotation},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+
+const K: u32 = 4;
+
+#[derive(Clone, Debug)]
+struct PowConfig {
+    q_pow: Selector,
+    x: Column<Advice>,
+    y: Column<Advice>,
+}
+
+/// Constrains `y = x^DEGREE` on a single row.
+#[derive(Default)]
+struct PowCircuit<const DEGREE: usize>;
+
+impl<F: FieldExt, const DEGREE: usize> Circuit<F> for PowCircuit<DEGREE> {
+    type Config = PowConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        PowCircuit
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let config = PowConfig {
+            q_pow: meta.selector(),
+            x: meta.advice_column(),
+            y: meta.advice_column(),
+        };
+
+        meta.create_gate("pow", |meta| {
+            let q = meta.query_selector(config.q_pow);
+            let x = meta.query_advice(config.x, Rotation::cur());
+            let y = meta.query_advice(config.y, Rotation::cur());
+
+            let pow = (1..DEGREE).fold(x.clone(), |acc, _| acc * x.clone());
+            vec![q * (pow - y)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(|| "pow", |mut region| config.q_pow.enable(&mut region, 0))
+    }
+}
+
+#[test]
+fn pinned_snapshot() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let square = keygen_vk(&params, &PowCircuit::<2>).expect("keygen_vk should not fail");
+    let cube = keygen_vk(&params, &PowCircuit::<3>).expect("keygen_vk should not fail");
+
+    let snapshot = square.pinned().to_snapshot();
+    assert!(square.pinned().check_snapshot(&snapshot).is_ok());
+    assert!(square
+        .pinned()
+        .check_snapshot(&snapshot.replace('\n', "\r\n"))
+        .is_ok());
+
+    let mismatch = cube.pinned().check_snapshot(&snapshot).unwrap_err();
+    assert!(mismatch.expected.is_some());
+    assert_ne!(mismatch.expected, mismatch.actual);
+
+    // A truncated snapshot is reported at the first missing line.
+    let truncated: String = snapshot
+        .lines()
+        .take(3)
+        .map(|l| format!("{}\n", l))
+        .collect();
+    let mismatch = square.pinned().check_snapshot(&truncated).unwrap_err();
+    assert_eq!(mismatch.line, 4);
+    assert_eq!(mismatch.expected, None);
+}
+
+#[test]
+fn assert_pinned_snapshot_writes_missing_file() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let vk = keygen_vk(&params, &PowCircuit::<2>).expect("keygen_vk should not fail");
+
+    let dir = env::temp_dir().join(format!("halo2-pinned-{}", std::process::id()));
+    let path = dir.join("pow.txt");
+    let _ = fs::remove_dir_all(&dir);
+
+    // The first run records the snapshot, and the second one compares to it.
+    assert_pinned_snapshot(&vk, &path);
+    assert_eq!(
+        fs::read_to_string(&path).unwrap(),
+        vk.pinned().to_snapshot()
+    );
+    assert_pinned_snapshot(&vk, &path);
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn pinned_json() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let square = keygen_vk(&params, &PowCircuit::<2>).expect("keygen_vk should not fail");
+    let cube = keygen_vk(&params, &PowCircuit::<3>).expect("keygen_vk should not fail");
+
+    let json = square.pinned().to_json();
+    assert_eq!(json, square.pinned().to_json());
+    assert!(json.starts_with("{\n"));
+    assert!(json.ends_with("}\n"));
+    assert!(json.contains("\"k\": 4,"));
+    assert!(json.contains("\"name\": \"pow\","));
+    assert!(json.contains("\"column\": \"advice 1\","));
+
+    // The two circuits only differ in the polynomial of their gate, and in the
+    // extended domain that its degree requires.
+    let cube_json = cube.pinned().to_json();
+    let differences: Vec<_> = json
+        .lines()
+        .zip(cube_json.lines())
+        .filter(|(square, cube)| square != cube)
+        .map(|(square, _)| square.trim_start().split(':').next().unwrap())
+        .collect();
+    assert_eq!(json.lines().count(), cube_json.lines().count());
+    assert_eq!(differences, vec!["\"extended_k\"", "\"polynomial\""]);
+
+    let mut buffer = vec![];
+    square.pinned().write_json(&mut buffer).unwrap();
+    assert_eq!(buffer, json.as_bytes());
+}
+
+#[test]
+fn fingerprint() {
+    let hashes = [
+        TranscriptHash::Blake2b,
+        TranscriptHash::Keccak256,
+        TranscriptHash::Poseidon,
+    ];
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let square = keygen_vk(&params, &PowCircuit::<2>).expect("keygen_vk should not fail");
+    let square_again = keygen_vk(&params, &PowCircuit::<2>).expect("keygen_vk should not fail");
+    let cube = keygen_vk(&params, &PowCircuit::<3>).expect("keygen_vk should not fail");
+
+    let fingerprints: Vec<_> = hashes.iter().map(|h| square.fingerprint(*h)).collect();
+    for (hash, fingerprint) in hashes.iter().zip(fingerprints.iter()) {
+        // Stable across key generations, and specific to the circuit.
+        assert_eq!(*fingerprint, square_again.fingerprint(*hash));
+        assert_ne!(*fingerprint, cube.fingerprint(*hash));
+    }
+    assert_ne!(fingerprints[0], fingerprints[1]);
+    assert_ne!(fingerprints[0], fingerprints[2]);
+    assert_ne!(fingerprints[1], fingerprints[2]);
+
+    // The Poseidon fingerprint is a canonical scalar, usable as a public input.
+    assert!(bool::from(Fp::from_repr(fingerprints[2]).is_some()));
+}
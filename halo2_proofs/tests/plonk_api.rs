@@ -6,9 +6,11 @@ use halo2_proofs::arithmetic::FieldExt;
 use halo2_proofs::circuit::{Cell, Layouter, SimpleFloorPlanner};
 use halo2_proofs::dev::MockProver;
 use halo2_proofs::plonk::{
-    create_proof, keygen_pk, keygen_vk, verify_proof, Advice, BatchVerifier, Circuit, Column,
-    ConstraintSystem, Error, Fixed, SingleVerifier, TableColumn, VerifyingKey,
+    create_proof, keygen_pk, keygen_pk2, keygen_vk, proofs_digest, verify_proof,
+    verify_proofs_batch, Accumulator, AccumulatorStrategy, Advice, BatchVerifier, Circuit, Column,
+    ConstraintSystem, Constraints, Error, Fixed, SingleVerifier, TableColumn, VerifyingKey,
 };
+use halo2_proofs::query_cells;
 
 use halo2_proofs::poly::{
     commitment::{Params, ParamsVerifier},
@@ -242,10 +244,11 @@ fn plonk_api() {
             layouter.assign_table(
                 || "",
                 |mut table| {
-                    for (index, &value) in values.iter().enumerate() {
-                        table.assign_cell(|| "table col", self.config.sl, index, || Ok(value))?;
-                    }
-                    Ok(())
+                    table.assign_table_from_iter(
+                        || "table col",
+                        &[self.config.sl],
+                        values.iter().map(|value| [*value]),
+                    )
                 },
             )?;
             Ok(())
@@ -305,27 +308,22 @@ fn plonk_api() {
             });
 
             meta.create_gate("Combined add-mult", |meta| {
-                let d = meta.query_advice(d, Rotation::next());
-                let a = meta.query_advice(a, Rotation::cur());
-                let sf = meta.query_fixed(sf, Rotation::cur());
-                let e = meta.query_advice(e, Rotation::prev());
-                let b = meta.query_advice(b, Rotation::cur());
-                let c = meta.query_advice(c, Rotation::cur());
-
-                let sa = meta.query_fixed(sa, Rotation::cur());
-                let sb = meta.query_fixed(sb, Rotation::cur());
-                let sc = meta.query_fixed(sc, Rotation::cur());
-                let sm = meta.query_fixed(sm, Rotation::cur());
+                query_cells!(meta;
+                    advice: d @ Rotation::next(), a, e @ Rotation::prev(), b, c;
+                    fixed: sf, sa, sb, sc, sm;
+                );
 
                 vec![a.clone() * sa + b.clone() * sb + a * b * sm - (c * sc) + sf * (d * e)]
             });
 
             meta.create_gate("Public input", |meta| {
-                let a = meta.query_advice(a, Rotation::cur());
-                let p = meta.query_instance(p, Rotation::cur());
-                let sp = meta.query_fixed(sp, Rotation::cur());
+                query_cells!(meta;
+                    advice: a;
+                    instance: p;
+                    fixed: sp;
+                );
 
-                vec![sp * (a - p)]
+                Constraints::with_selector(sp, Some(a - p))
             });
 
             meta.enable_equality(sf);
@@ -411,7 +409,8 @@ fn plonk_api() {
         keygen_vk(&much_too_small_params, &empty_circuit),
         Err(Error::NotEnoughRowsAvailable {
             current_k,
-        }) if current_k == 1
+            minimum_k,
+        }) if current_k == 1 && minimum_k == K
     );
 
     // Check that we get an error if we try to initialize the proving key with a value of
@@ -422,13 +421,24 @@ fn plonk_api() {
         keygen_vk(&slightly_too_small_params, &empty_circuit),
         Err(Error::NotEnoughRowsAvailable {
             current_k,
-        }) if current_k == K - 1
+            minimum_k,
+        }) if current_k == K - 1 && minimum_k == K
     );
 
     // Initialize the proving key
     let vk = keygen_vk(&params, &empty_circuit).expect("keygen_vk should not fail");
     let pk = keygen_pk(&params, vk, &empty_circuit).expect("keygen_pk should not fail");
 
+    // Single-pass key generation should produce the same verifying key.
+    {
+        let pk2 = keygen_pk2(&params, &empty_circuit).expect("keygen_pk2 should not fail");
+        let mut vk_buffer = vec![];
+        pk.get_vk().write(&mut vk_buffer).unwrap();
+        let mut vk2_buffer = vec![];
+        pk2.get_vk().write(&mut vk2_buffer).unwrap();
+        assert_eq!(vk_buffer, vk2_buffer);
+    }
+
     let pubinputs = vec![instance];
 
     // Check this circuit is satisfied.
@@ -508,5 +518,112 @@ fn plonk_api() {
             // Check the batch.
             assert!(strategy.finalize());
         }
+
+        //
+        // Test accumulator strategy.
+        //
+
+        {
+            let strategy = AccumulatorStrategy::new(&params_verifier, OsRng);
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            let strategy = verify_proof(
+                &params_verifier,
+                pk.get_vk(),
+                strategy,
+                &[&[&pubinputs[..]], &[&pubinputs[..]]],
+                &mut transcript,
+            )
+            .unwrap();
+            let accumulator = strategy.accumulator();
+            assert!(accumulator.decide(&params_verifier));
+
+            // Fold the proof again into the previous accumulator.
+            let strategy =
+                AccumulatorStrategy::with_accumulator(&params_verifier, accumulator, OsRng);
+            let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            let strategy = verify_proof(
+                &params_verifier,
+                pk.get_vk(),
+                strategy,
+                &[&[&pubinputs[..]], &[&pubinputs[..]]],
+                &mut transcript,
+            )
+            .unwrap();
+            assert!(strategy.accumulator().decide(&params_verifier));
+
+            // Swapping the two points breaks the pairing check.
+            let swapped = Accumulator {
+                lhs: accumulator.rhs,
+                rhs: accumulator.lhs,
+            };
+            assert!(!swapped.decide(&params_verifier));
+            let strategy = AccumulatorStrategy::with_accumulator(&params_verifier, swapped, OsRng);
+            assert!(!strategy.finalize());
+        }
+
+        //
+        // Test batch verification.
+        //
+
+        {
+            let instances: &[&[&[Fp]]] = &[&[&pubinputs[..]], &[&pubinputs[..]]];
+            let transcript = || Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+            assert!(verify_proofs_batch(
+                &params_verifier,
+                &mut [
+                    (pk.get_vk(), instances, transcript()),
+                    (pk.get_vk(), instances, transcript()),
+                ],
+                OsRng,
+            )
+            .is_ok());
+
+            // A proof checked against the wrong instances fails the whole batch.
+            let wrong_instance = [instance + Fp::one()];
+            let wrong_instances: &[&[&[Fp]]] = &[&[&wrong_instance[..]], &[&pubinputs[..]]];
+            assert!(verify_proofs_batch(
+                &params_verifier,
+                &mut [
+                    (pk.get_vk(), instances, transcript()),
+                    (pk.get_vk(), wrong_instances, transcript()),
+                ],
+                OsRng,
+            )
+            .is_err());
+
+            // The digest of a batch binds its proofs, their instances and their order.
+            let digest = proofs_digest(&[
+                (pk.get_vk(), instances, &proof[..]),
+                (pk.get_vk(), wrong_instances, &proof[..]),
+            ]);
+            assert_eq!(
+                digest,
+                proofs_digest(&[
+                    (pk.get_vk(), instances, &proof[..]),
+                    (pk.get_vk(), wrong_instances, &proof[..]),
+                ])
+            );
+            assert_ne!(
+                digest,
+                proofs_digest(&[
+                    (pk.get_vk(), wrong_instances, &proof[..]),
+                    (pk.get_vk(), instances, &proof[..]),
+                ])
+            );
+            assert_ne!(
+                digest,
+                proofs_digest(&[
+                    (pk.get_vk(), instances, &proof[..]),
+                    (pk.get_vk(), instances, &proof[..]),
+                ])
+            );
+            assert_ne!(
+                digest,
+                proofs_digest(&[
+                    (pk.get_vk(), instances, &proof[..]),
+                    (pk.get_vk(), wrong_instances, &proof[..proof.len() - 1]),
+                ])
+            );
+        }
     }
 }
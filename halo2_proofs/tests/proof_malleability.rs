@@ -0,0 +1,190 @@
+mod common;
+
+use std::io;
+
+use common::{setup, SquareCircuit};
+use ff::{Field, PrimeField};
+use group::{prime::PrimeCurveAffine, GroupEncoding};
+use halo2_proofs::{
+    plonk::{create_proof, verify_proof, verify_proof_strict, Error, ProvingKey, SingleVerifier},
+    poly::commitment::{Params, ParamsVerifier},
+    transcript::{
+        keccak::{Keccak256Read, Keccak256Write},
+        Blake2bRead, Blake2bWrite, Challenge255, Transcript, TranscriptWrite,
+    },
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+const INSTANCES: &[&[&[Fp]]] = &[&[]];
+
+/// Constrains `b = a^2` on the first row.
+type Square = SquareCircuit<1>;
+
+/// Records where in the proof each scalar is written.
+struct ScalarOffsets<T> {
+    inner: T,
+    written: usize,
+    scalars: Vec<usize>,
+}
+
+impl<T: TranscriptWrite<G1Affine, Challenge255<G1Affine>>>
+    Transcript<G1Affine, Challenge255<G1Affine>> for ScalarOffsets<T>
+{
+    fn squeeze_challenge(&mut self) -> Challenge255<G1Affine> {
+        self.inner.squeeze_challenge()
+    }
+
+    fn common_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.inner.common_point(point)
+    }
+
+    fn common_scalar(&mut self, scalar: Fp) -> io::Result<()> {
+        self.inner.common_scalar(scalar)
+    }
+}
+
+impl<T: TranscriptWrite<G1Affine, Challenge255<G1Affine>>>
+    TranscriptWrite<G1Affine, Challenge255<G1Affine>> for ScalarOffsets<T>
+{
+    fn write_point(&mut self, point: G1Affine) -> io::Result<()> {
+        self.written += point.to_bytes().as_ref().len();
+        self.inner.write_point(point)
+    }
+
+    fn write_scalar(&mut self, scalar: Fp) -> io::Result<()> {
+        self.scalars.push(self.written);
+        self.written += scalar.to_repr().as_ref().len();
+        self.inner.write_scalar(scalar)
+    }
+}
+
+fn circuit() -> Square {
+    Square::new(3)
+}
+
+/// Returns a Blake2b proof, and the offsets of the scalars in it.
+fn blake2b_proof(params: &Params<G1Affine>, pk: &ProvingKey<G1Affine>) -> (Vec<u8>, Vec<usize>) {
+    let mut transcript = ScalarOffsets {
+        inner: Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]),
+        written: 0,
+        scalars: vec![],
+    };
+    create_proof(params, pk, &[circuit()], INSTANCES, OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    (transcript.inner.finalize(), transcript.scalars)
+}
+
+fn verify_blake2b(
+    params: &ParamsVerifier<Bn256>,
+    pk: &ProvingKey<G1Affine>,
+    proof: &[u8],
+    strict: bool,
+) -> bool {
+    let strategy = SingleVerifier::new(params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+    if strict {
+        verify_proof_strict(params, pk.get_vk(), strategy, INSTANCES, &mut transcript).is_ok()
+    } else {
+        verify_proof(params, pk.get_vk(), strategy, INSTANCES, &mut transcript).is_ok()
+    }
+}
+
+#[test]
+fn trailing_bytes() {
+    let (params, params_verifier, pk) = setup(K, &Square::default(), 0);
+    let (mut proof, _) = blake2b_proof(&params, &pk);
+    assert!(verify_blake2b(&params_verifier, &pk, &proof, true));
+
+    // The non-strict verifier ignores anything after the proof.
+    proof.push(0);
+    assert!(verify_blake2b(&params_verifier, &pk, &proof, false));
+    assert!(!verify_blake2b(&params_verifier, &pk, &proof, true));
+}
+
+#[test]
+fn non_canonical_scalar() {
+    let (params, params_verifier, pk) = setup(K, &Square::default(), 0);
+    let (mut proof, scalars) = blake2b_proof(&params, &pk);
+
+    // Add the modulus to the first scalar, as `p - 1` and a carry of one. Scalars
+    // are little-endian, and twice the modulus still fits in 256 bits.
+    let offset = scalars[0];
+    let modulus_minus_one = (-Fp::one()).to_repr();
+    let mut carry = 1u16;
+    for (byte, m) in proof[offset..offset + 32]
+        .iter_mut()
+        .zip(modulus_minus_one.as_ref())
+    {
+        let sum = *byte as u16 + *m as u16 + carry;
+        *byte = sum as u8;
+        carry = sum >> 8;
+    }
+    assert_eq!(carry, 0);
+
+    assert!(!verify_blake2b(&params_verifier, &pk, &proof, false));
+    assert!(!verify_blake2b(&params_verifier, &pk, &proof, true));
+}
+
+#[test]
+fn point_at_infinity() {
+    let (params, params_verifier, pk) = setup(K, &Square::default(), 0);
+    let is_point_at_infinity = |result: Result<(), Error>| {
+        matches!(
+            result,
+            Err(Error::Transcript(e)) if e.to_string() == "point at infinity in proof"
+        )
+    };
+
+    // The proof starts with the two advice commitments. Columns may be committed
+    // to as the point at infinity, so this is only rejected as the gate no
+    // longer holds.
+    let (proof, _) = blake2b_proof(&params, &pk);
+    let identity = G1Affine::identity().to_bytes();
+    let len = identity.as_ref().len();
+    let mut tampered = proof.clone();
+    tampered[..len].copy_from_slice(identity.as_ref());
+    assert!(!verify_blake2b(&params_verifier, &pk, &tampered, false));
+    assert!(!verify_blake2b(&params_verifier, &pk, &tampered, true));
+
+    // They are followed by the commitment to the random polynomial of the
+    // vanishing argument, which is never the point at infinity.
+    let mut tampered = proof;
+    tampered[2 * len..3 * len].copy_from_slice(identity.as_ref());
+    assert!(!verify_blake2b(&params_verifier, &pk, &tampered, false));
+    let strategy = SingleVerifier::new(&params_verifier);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&tampered[..]);
+    assert!(is_point_at_infinity(verify_proof_strict(
+        &params_verifier,
+        pk.get_vk(),
+        strategy,
+        INSTANCES,
+        &mut transcript
+    )));
+
+    // The Keccak256 transcript writes points uncompressed, and encodes the point at
+    // infinity as (0, 0).
+    let mut transcript = Keccak256Write::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[circuit()],
+        INSTANCES,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let mut proof = transcript.finalize();
+    proof[2 * 64..3 * 64].copy_from_slice(&[0; 64]);
+
+    let strategy = SingleVerifier::new(&params_verifier);
+    let mut transcript = Keccak256Read::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(is_point_at_infinity(verify_proof_strict(
+        &params_verifier,
+        pk.get_vk(),
+        strategy,
+        INSTANCES,
+        &mut transcript
+    )));
+}
@@ -0,0 +1,56 @@
+mod common;
+
+use common::{setup, SquareCircuit};
+use halo2_proofs::{
+    plonk::create_proof_with_metrics,
+    transcript::{Blake2bWrite, Challenge255},
+};
+use pairing::bn256::Fr as Fp;
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+/// Constrains `b = a^2` on the first row.
+type Square = SquareCircuit<1>;
+
+#[test]
+fn proof_metrics() {
+    let (params, _, pk) = setup(K, &Square::default(), 0);
+    let instances: &[&[&[Fp]]] = &[&[]];
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    let metrics = create_proof_with_metrics(
+        &params,
+        &pk,
+        &[Square::new(3)],
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+
+    let stages: Vec<_> = metrics.stages.iter().map(|(name, _)| *name).collect();
+    assert_eq!(
+        stages,
+        [
+            "instance",
+            "advice",
+            "lookup_permuted",
+            "permutation",
+            "lookup_product",
+            "shuffle",
+            "quotient",
+            "evaluate",
+            "multiopen"
+        ]
+    );
+    assert!(metrics.stage("advice").is_some());
+    assert!(metrics.stage("keygen").is_none());
+    assert!(metrics
+        .stages
+        .iter()
+        .all(|(_, elapsed)| *elapsed <= metrics.total));
+    assert!(metrics
+        .to_string()
+        .ends_with(&format!("total: {:?}", metrics.total)));
+}
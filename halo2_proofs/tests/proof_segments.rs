@@ -0,0 +1,194 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof_segments, Advice, BatchVerifier, Circuit,
+        Column, ConstraintSystem, Error, Expression, Instance, Selector,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+#[derive(Clone, Debug)]
+struct MyConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    c: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+impl MyConfig {
+    fn configure(
+        meta: &mut ConstraintSystem<Fp>,
+        gate: fn(Expression<Fp>, Expression<Fp>) -> Expression<Fp>,
+    ) -> Self {
+        let config = MyConfig {
+            q: meta.selector(),
+            a: meta.advice_column(),
+            b: meta.advice_column(),
+            c: meta.advice_column(),
+            instance: meta.instance_column(),
+        };
+        meta.enable_equality(config.c);
+        meta.enable_equality(config.instance);
+
+        meta.create_gate("c = gate(a, b)", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let b = meta.query_advice(config.b, Rotation::cur());
+            let c = meta.query_advice(config.c, Rotation::cur());
+
+            vec![q * (gate(a, b) - c)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        mut layouter: impl Layouter<Fp>,
+        values: Option<(Fp, Fp, Fp)>,
+    ) -> Result<(), Error> {
+        let c = layouter.assign_region(
+            || "gate",
+            |mut region| {
+                self.q.enable(&mut region, 0)?;
+                let value =
+                    |f: fn((Fp, Fp, Fp)) -> Fp| move || values.map(f).ok_or(Error::Synthesis);
+                region.assign_advice(|| "a", self.a, 0, value(|v| v.0))?;
+                region.assign_advice(|| "b", self.b, 0, value(|v| v.1))?;
+                region.assign_advice(|| "c", self.c, 0, value(|v| v.2))
+            },
+        )?;
+        layouter.constrain_instance(c.cell(), self.instance, 0)
+    }
+}
+
+/// Proves knowledge of `a` and `b` such that `a * b` is the instance.
+#[derive(Default)]
+struct MulCircuit {
+    values: Option<(Fp, Fp)>,
+}
+
+impl Circuit<Fp> for MulCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyConfig::configure(meta, |a, b| a * b)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        config.synthesize(layouter, self.values.map(|(a, b)| (a, b, a * b)))
+    }
+}
+
+/// Proves knowledge of `a` and `b` such that `a + b` is the instance.
+#[derive(Default)]
+struct AddCircuit {
+    values: Option<(Fp, Fp)>,
+}
+
+impl Circuit<Fp> for AddCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyConfig::configure(meta, |a, b| a + b)
+    }
+
+    fn synthesize(&self, config: Self::Config, layouter: impl Layouter<Fp>) -> Result<(), Error> {
+        config.synthesize(layouter, self.values.map(|(a, b)| (a, b, a + b)))
+    }
+}
+
+#[test]
+fn proof_segments() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(1).unwrap();
+
+    let mul_vk = keygen_vk(&params, &MulCircuit::default()).expect("keygen_vk should not fail");
+    let mul_pk =
+        keygen_pk(&params, mul_vk, &MulCircuit::default()).expect("keygen_pk should not fail");
+    let add_vk = keygen_vk(&params, &AddCircuit::default()).expect("keygen_vk should not fail");
+    let add_pk =
+        keygen_pk(&params, add_vk, &AddCircuit::default()).expect("keygen_pk should not fail");
+
+    let (a, b) = (Fp::from(3), Fp::from(5));
+    let mul_instances: &[&[&[Fp]]] = &[&[&[a * b]]];
+    let add_instances: &[&[&[Fp]]] = &[&[&[a + b]]];
+
+    // The proofs are written one after the other into the same transcript.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &mul_pk,
+        &[MulCircuit {
+            values: Some((a, b)),
+        }],
+        mul_instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    create_proof(
+        &params,
+        &add_pk,
+        &[AddCircuit {
+            values: Some((a, b)),
+        }],
+        add_instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let verify = |segments: &[(_, &[&[&[Fp]]])]| {
+        let strategy = BatchVerifier::new(&params_verifier, OsRng);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        verify_proof_segments(&params_verifier, segments, strategy, &mut transcript)
+            .map(|strategy| strategy.finalize())
+    };
+
+    assert!(matches!(
+        verify(&[
+            (mul_pk.get_vk(), mul_instances),
+            (add_pk.get_vk(), add_instances)
+        ]),
+        Ok(true)
+    ));
+
+    // The segments must be verified in the order they were proven in.
+    assert!(!matches!(
+        verify(&[
+            (add_pk.get_vk(), add_instances),
+            (mul_pk.get_vk(), mul_instances)
+        ]),
+        Ok(true)
+    ));
+
+    // Each segment is checked against its own instances.
+    assert!(!matches!(
+        verify(&[
+            (mul_pk.get_vk(), add_instances),
+            (add_pk.get_vk(), mul_instances)
+        ]),
+        Ok(true)
+    ));
+}
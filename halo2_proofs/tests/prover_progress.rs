@@ -0,0 +1,82 @@
+mod common;
+
+use common::{setup, SquareCircuit};
+use halo2_proofs::{
+    plonk::{create_proof_with_progress, Error, ProverProgress},
+    transcript::{Blake2bWrite, Challenge255},
+};
+use pairing::bn256::Fr as Fp;
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+/// Constrains `b = a^2` on the first row.
+type Square = SquareCircuit<1>;
+
+/// Records the updates it receives, and cancels the proof once it reaches the
+/// stage `cancel_at`, if any.
+#[derive(Default)]
+struct Recorder {
+    updates: Vec<(&'static str, u8)>,
+    cancel_at: Option<&'static str>,
+}
+
+impl ProverProgress for Recorder {
+    fn stage(&mut self, stage: &'static str, percent: u8) {
+        self.updates.push((stage, percent));
+    }
+
+    fn cancelled(&self) -> bool {
+        matches!(
+            (self.cancel_at, self.updates.last()),
+            (Some(cancel_at), Some((stage, _))) if *stage == cancel_at
+        )
+    }
+}
+
+#[test]
+fn prover_progress() {
+    let (params, _, pk) = setup(K, &Square::default(), 0);
+    let instances: &[&[&[Fp]]] = &[&[]];
+
+    let mut progress = Recorder::default();
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_progress(
+        &params,
+        &pk,
+        &[Square::new(3)],
+        instances,
+        &mut progress,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+
+    assert_eq!(progress.updates.first(), Some(&("instance", 0)));
+    assert_eq!(progress.updates.last(), Some(&("done", 100)));
+    assert_eq!(progress.updates.len(), 10);
+    assert!(progress
+        .updates
+        .windows(2)
+        .all(|updates| updates[0].1 < updates[1].1));
+
+    // The proof stops at the start of the stage after the cancellation.
+    let mut progress = Recorder {
+        cancel_at: Some("permutation"),
+        ..Recorder::default()
+    };
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    assert!(matches!(
+        create_proof_with_progress(
+            &params,
+            &pk,
+            &[Square::new(3)],
+            instances,
+            &mut progress,
+            OsRng,
+            &mut transcript,
+        ),
+        Err(Error::Aborted)
+    ));
+    assert_eq!(progress.updates.last(), Some(&("permutation", 33)));
+}
@@ -0,0 +1,141 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+/// The smallest `k` whose usable rows fit a table of `2^BITS` values.
+const K: u32 = 9;
+
+const BITS: usize = 8;
+
+#[derive(Clone, Debug)]
+struct BytesConfig {
+    q: Selector,
+    a: Column<Advice>,
+}
+
+/// Checks that each of its values, and the sum of each value with the next,
+/// fit in a byte.
+#[derive(Default)]
+struct BytesCircuit {
+    values: Vec<u64>,
+}
+
+impl Circuit<Fp> for BytesCircuit {
+    type Config = BytesConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        BytesCircuit {
+            values: vec![0; self.values.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = BytesConfig {
+            q: meta.complex_selector(),
+            a: meta.advice_column(),
+        };
+
+        meta.range_check("byte", BITS, |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            q * a
+        });
+        meta.range_check("sum of bytes", BITS, |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let next = meta.query_advice(config.a, Rotation::next());
+            q * (a + next)
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for (row, value) in self.values.iter().enumerate() {
+                    if row + 1 < self.values.len() {
+                        config.q.enable(&mut region, row)?;
+                    }
+                    region.assign_advice(|| "a", config.a, row, || Ok(Fp::from(*value)))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn range_check() {
+    let cs = {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        BytesCircuit::configure(&mut cs);
+        cs
+    };
+    // Both checks share a table.
+    assert_eq!(cs.range_tables().len(), 1);
+    assert_eq!(cs.range_tables()[0].1, BITS);
+    assert_eq!(cs.lookups().len(), 2);
+
+    let circuit = BytesCircuit {
+        values: vec![3, 100, 155, 0, 255],
+    };
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // A value out of range, and a sum out of range.
+    for values in [vec![3, 256, 0], vec![3, 200, 100]] {
+        let prover = MockProver::run(K, &BytesCircuit { values }, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // The table doesn't fit in fewer rows.
+    assert!(matches!(
+        MockProver::run(K - 1, &circuit, vec![]),
+        Err(Error::NotEnoughRowsAvailable { minimum_k: K, .. })
+    ));
+    let small_params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K - 1);
+    assert!(matches!(
+        keygen_vk(&small_params, &circuit),
+        Err(Error::NotEnoughRowsAvailable { minimum_k: K, .. })
+    ));
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+    let empty = circuit.without_witnesses();
+    let vk = keygen_vk(&params, &empty).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &empty).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        SingleVerifier::new(&params_verifier),
+        &[&[]],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+}
@@ -0,0 +1,149 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Instance, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+/// The number of public inputs.
+const INPUTS: usize = 6;
+
+#[derive(Clone, Debug)]
+struct SpreadConfig {
+    q: Selector,
+    a: Column<Advice>,
+    instance: Column<Instance>,
+}
+
+/// Witnesses the spread `i[r + 1] - i[r - 1]` of the public inputs around each
+/// inner row `r`, querying the instance column at the previous and next rows.
+#[derive(Default)]
+struct SpreadCircuit {
+    spreads: Option<Vec<Fp>>,
+}
+
+impl SpreadCircuit {
+    fn new(inputs: &[Fp]) -> Self {
+        SpreadCircuit {
+            spreads: Some(inputs.windows(3).map(|w| w[2] - w[0]).collect()),
+        }
+    }
+}
+
+impl Circuit<Fp> for SpreadCircuit {
+    type Config = SpreadConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = SpreadConfig {
+            q: meta.selector(),
+            a: meta.advice_column(),
+            instance: meta.instance_column(),
+        };
+
+        meta.create_gate("spread", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let prev = meta.query_instance(config.instance, Rotation::prev());
+            let next = meta.query_instance(config.instance, Rotation::next());
+
+            vec![q * (next - prev - a)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        // The region is assigned at the first row, so that its rows line up
+        // with those of the public inputs.
+        layouter.assign_region(
+            || "spreads",
+            |mut region| {
+                for row in 1..INPUTS - 1 {
+                    config.q.enable(&mut region, row)?;
+                    region.assign_advice(
+                        || "spread",
+                        config.a,
+                        row,
+                        || {
+                            self.spreads
+                                .as_ref()
+                                .map(|s| s[row - 1])
+                                .ok_or(Error::Synthesis)
+                        },
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn rotated_instance_queries() {
+    let inputs: Vec<Fp> = (0..INPUTS as u64).map(|i| Fp::from(i * i + 3)).collect();
+    let mut other = inputs.clone();
+    other[INPUTS - 1] += Fp::from(1);
+
+    let cs = {
+        let mut cs = ConstraintSystem::<Fp>::default();
+        SpreadCircuit::configure(&mut cs);
+        cs
+    };
+    assert_eq!(cs.rotated_instance_queries().len(), 2);
+
+    // The mock prover reads the public inputs at the queried rotations.
+    let prover = MockProver::run(K, &SpreadCircuit::new(&inputs), vec![inputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+    let prover = MockProver::run(K, &SpreadCircuit::new(&inputs), vec![other.clone()]).unwrap();
+    assert!(prover.verify().is_err());
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(INPUTS).unwrap();
+    let vk = keygen_vk(&params, &SpreadCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &SpreadCircuit::default()).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[SpreadCircuit::new(&inputs)],
+        &[&[&inputs]],
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let verify = |inputs: &[Fp]| {
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        verify_proof(
+            &params_verifier,
+            pk.get_vk(),
+            SingleVerifier::new(&params_verifier),
+            &[&[inputs]],
+            &mut transcript,
+        )
+    };
+    verify(&inputs).expect("proof should verify");
+    assert!(verify(&other).is_err());
+}
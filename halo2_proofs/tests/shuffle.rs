@@ -0,0 +1,172 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::{FailureLocation, MockProver, VerifyFailure},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+#[derive(Clone, Debug)]
+struct ShuffleConfig<F: FieldExt> {
+    q_shuffle: Selector,
+    original: [Column<Advice>; 2],
+    shuffled: [Column<Advice>; 2],
+    _marker: PhantomData<F>,
+}
+
+#[derive(Default)]
+struct ShuffleCircuit<F: FieldExt> {
+    original: Vec<(F, F)>,
+    shuffled: Vec<(F, F)>,
+}
+
+impl<F: FieldExt> Circuit<F> for ShuffleCircuit<F> {
+    type Config = ShuffleConfig<F>;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let config = ShuffleConfig {
+            q_shuffle: meta.complex_selector(),
+            original: [meta.advice_column(), meta.advice_column()],
+            shuffled: [meta.advice_column(), meta.advice_column()],
+            _marker: PhantomData,
+        };
+
+        // Rows of (original[0], original[1]) are a permutation of the rows of
+        // (shuffled[0], shuffled[1]).
+        meta.shuffle("shuffle", |meta| {
+            let q = meta.query_selector(config.q_shuffle);
+            config
+                .original
+                .iter()
+                .zip(config.shuffled.iter())
+                .map(|(original, shuffled)| {
+                    (
+                        q.clone() * meta.query_advice(*original, Rotation::cur()),
+                        q.clone() * meta.query_advice(*shuffled, Rotation::cur()),
+                    )
+                })
+                .collect()
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "shuffle",
+            |mut region| {
+                for (offset, (original, shuffled)) in
+                    self.original.iter().zip(self.shuffled.iter()).enumerate()
+                {
+                    config.q_shuffle.enable(&mut region, offset)?;
+                    for (column, value) in config
+                        .original
+                        .iter()
+                        .zip([original.0, original.1].iter())
+                        .chain(config.shuffled.iter().zip([shuffled.0, shuffled.1].iter()))
+                    {
+                        region.assign_advice(|| "value", *column, offset, || Ok(*value))?;
+                    }
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn rows(values: &[(u64, u64)]) -> Vec<(Fp, Fp)> {
+    values
+        .iter()
+        .map(|(a, b)| (Fp::from(*a), Fp::from(*b)))
+        .collect()
+}
+
+#[test]
+fn shuffle() {
+    const K: u32 = 5;
+
+    let circuit = ShuffleCircuit {
+        original: rows(&[(1, 10), (2, 20), (3, 30), (2, 20)]),
+        shuffled: rows(&[(2, 20), (3, 30), (2, 20), (1, 10)]),
+    };
+
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+    let vk = keygen_vk(&params, &ShuffleCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &ShuffleCircuit::default()).expect("keygen_pk should not fail");
+
+    let instances: &[&[&[Fp]]] = &[&[]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(&params, &pk, &[circuit], instances, OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    let proof: Vec<u8> = transcript.finalize();
+
+    let strategy = SingleVerifier::new(&params_verifier);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        strategy,
+        instances,
+        &mut transcript,
+    )
+    .is_ok());
+}
+
+#[test]
+fn bad_shuffle() {
+    const K: u32 = 5;
+
+    // The pairs are swapped between columns, so the tuples do not match.
+    let circuit = ShuffleCircuit {
+        original: rows(&[(1, 10), (2, 20), (3, 30)]),
+        shuffled: rows(&[(2, 20), (3, 30), (10, 1)]),
+    };
+
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(
+        prover.verify(),
+        Err(vec![VerifyFailure::Shuffle {
+            name: "shuffle",
+            shuffle_index: 0,
+            location: FailureLocation::InRegion {
+                region: (0, "shuffle").into(),
+                offset: 0,
+            },
+        }])
+    );
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let vk = keygen_vk(&params, &ShuffleCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &ShuffleCircuit::default()).expect("keygen_pk should not fail");
+
+    let instances: &[&[&[Fp]]] = &[&[]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    assert!(matches!(
+        create_proof(&params, &pk, &[circuit], instances, OsRng, &mut transcript),
+        Err(Error::ConstraintSystemFailure)
+    ));
+}
@@ -0,0 +1,278 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Expression, Selector, SingleVerifier, TableColumn,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 6;
+
+const SQUARE: u64 = 1;
+const DOUBLE: u64 = 2;
+
+#[derive(Clone, Debug)]
+struct TaggedConfig {
+    q_square: Selector,
+    q_double: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    tag: TableColumn,
+    input: TableColumn,
+    output: TableColumn,
+}
+
+/// Checks pairs `(a, b)` against one of two logical tables, of squares and of
+/// doubles, sharing the same table columns.
+#[derive(Clone, Default)]
+struct TaggedCircuit {
+    tags: [u64; 2],
+    squares: Vec<(u64, u64)>,
+    doubles: Vec<(u64, u64)>,
+}
+
+impl TaggedCircuit {
+    fn new(squares: &[(u64, u64)], doubles: &[(u64, u64)]) -> Self {
+        TaggedCircuit {
+            tags: [SQUARE, DOUBLE],
+            squares: squares.to_vec(),
+            doubles: doubles.to_vec(),
+        }
+    }
+}
+
+impl Circuit<Fp> for TaggedCircuit {
+    type Config = TaggedConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        TaggedCircuit {
+            tags: self.tags,
+            squares: vec![(0, 0); self.squares.len()],
+            doubles: vec![(0, 0); self.doubles.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = TaggedConfig {
+            q_square: meta.complex_selector(),
+            q_double: meta.complex_selector(),
+            a: meta.advice_column(),
+            b: meta.advice_column(),
+            tag: meta.lookup_table_column(),
+            input: meta.lookup_table_column(),
+            output: meta.lookup_table_column(),
+        };
+
+        for (name, q, tag) in [
+            ("square", config.q_square, SQUARE),
+            ("double", config.q_double, DOUBLE),
+        ] {
+            meta.lookup(name, |meta| {
+                let q = meta.query_selector(q);
+                let a = meta.query_advice(config.a, Rotation::cur());
+                let b = meta.query_advice(config.b, Rotation::cur());
+                vec![
+                    (q.clone() * Expression::Constant(Fp::from(tag)), config.tag),
+                    (q.clone() * a, config.input),
+                    (q * b, config.output),
+                ]
+            });
+        }
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let squares: Vec<[Fp; 2]> = (0..8).map(|x| [Fp::from(x), Fp::from(x * x)]).collect();
+        let doubles: Vec<[Fp; 2]> = (0..8).map(|x| [Fp::from(x), Fp::from(2 * x)]).collect();
+        layouter.assign_table(
+            || "squares and doubles",
+            |mut table| {
+                table.assign_tagged_tables(
+                    || "squares and doubles",
+                    config.tag,
+                    &[config.input, config.output],
+                    &[
+                        (Fp::from(self.tags[0]), &squares[..]),
+                        (Fp::from(self.tags[1]), &doubles[..]),
+                    ],
+                )
+            },
+        )?;
+
+        layouter.assign_region(
+            || "pairs",
+            |mut region| {
+                let rows = self
+                    .squares
+                    .iter()
+                    .map(|pair| (config.q_square, pair))
+                    .chain(self.doubles.iter().map(|pair| (config.q_double, pair)));
+                for (row, (q, (a, b))) in rows.enumerate() {
+                    q.enable(&mut region, row)?;
+                    region.assign_advice(|| "a", config.a, row, || Ok(Fp::from(*a)))?;
+                    region.assign_advice(|| "b", config.b, row, || Ok(Fp::from(*b)))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn tagged_tables() {
+    let circuit = TaggedCircuit::new(&[(3, 9), (7, 49), (0, 0)], &[(3, 6), (5, 10)]);
+    let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    // Entries of one logical table are not in the other, and neither are
+    // the zeros filling the unused rows.
+    for circuit in [
+        TaggedCircuit::new(&[(3, 6)], &[]),
+        TaggedCircuit::new(&[], &[(3, 9)]),
+        TaggedCircuit::new(&[], &[(1, 0)]),
+    ] {
+        let prover = MockProver::run(K, &circuit, vec![]).unwrap();
+        assert!(prover.verify().is_err());
+    }
+
+    // Tags must be distinct and non-zero.
+    for tags in [[0, DOUBLE], [SQUARE, SQUARE]] {
+        let circuit = TaggedCircuit {
+            tags,
+            ..circuit.clone()
+        };
+        assert!(matches!(
+            MockProver::run(K, &circuit, vec![]),
+            Err(Error::Synthesis)
+        ));
+    }
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+    let empty = circuit.without_witnesses();
+    let vk = keygen_vk(&params, &empty).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &empty).expect("keygen_pk should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(&params, &pk, &[circuit], &[&[]], OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        SingleVerifier::new(&params_verifier),
+        &[&[]],
+        &mut transcript,
+    )
+    .expect("proof should verify");
+}
+
+#[derive(Clone, Debug)]
+struct DefaultConfig {
+    q: Selector,
+    a: Column<Advice>,
+    table: TableColumn,
+}
+
+/// Looks up its values in a table of `1..=4`, whose unused rows are filled
+/// with `default`, or with the value at offset 0 if `default` is `None`.
+struct DefaultCircuit {
+    default: Option<u64>,
+    values: Vec<u64>,
+}
+
+impl Circuit<Fp> for DefaultCircuit {
+    type Config = DefaultConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        DefaultCircuit {
+            default: self.default,
+            values: vec![1; self.values.len()],
+        }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = DefaultConfig {
+            q: meta.complex_selector(),
+            a: meta.advice_column(),
+            table: meta.lookup_table_column(),
+        };
+
+        // Disabled rows look up 1 rather than 0.
+        meta.lookup("table", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let not_q = Expression::Constant(Fp::one()) - q.clone();
+            vec![(q * a + not_q, config.table)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_table(
+            || "1..=4",
+            |mut table| {
+                if let Some(default) = self.default {
+                    table.set_default(config.table, Fp::from(default))?;
+                }
+                table.assign_table_from_iter(
+                    || "1..=4",
+                    &[config.table],
+                    (1..=4).map(|i| [Fp::from(i)]),
+                )
+            },
+        )?;
+
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for (row, value) in self.values.iter().enumerate() {
+                    config.q.enable(&mut region, row)?;
+                    region.assign_advice(|| "a", config.a, row, || Ok(Fp::from(*value)))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn table_default() {
+    let run = |default, value| {
+        let circuit = DefaultCircuit {
+            default,
+            values: vec![2, value],
+        };
+        MockProver::run(K, &circuit, vec![]).unwrap().verify()
+    };
+
+    // The unused rows repeat the value at offset 0 by default.
+    assert_eq!(run(None, 1), Ok(()));
+    assert!(run(None, 5).is_err());
+    // An explicit default is in the table, but isn't otherwise required.
+    assert_eq!(run(Some(5), 5), Ok(()));
+    assert_eq!(run(Some(5), 1), Ok(()));
+    assert!(run(Some(5), 6).is_err());
+}
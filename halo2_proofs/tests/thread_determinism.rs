@@ -0,0 +1,215 @@
+//! Checks the guarantee documented on `create_proof`: for a fixed witness and a
+//! fixed `rng`, the proof bytes don't depend on the number of threads the prover
+//! runs on.
+
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof_with_memory, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Fixed, Instance, ProverMemory, ProvingKey, Selector,
+        SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand::{rngs::StdRng, SeedableRng};
+
+const K: u32 = 7;
+
+/// The number of rows of the circuit, enough for the prover's parallel loops to
+/// be split into several chunks.
+const ROWS: usize = 100;
+
+#[derive(Clone, Debug)]
+struct ChainConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+    table: Column<Fixed>,
+    instance: Column<Instance>,
+}
+
+/// Constrains `b = a^2 + a` on each row, with the `a` of each row copied from
+/// the `b` of the previous one, every `a` looked up in a fixed table, and the
+/// last `b` exposed as an instance.
+#[derive(Clone, Default)]
+struct ChainCircuit {
+    start: Option<Fp>,
+}
+
+impl ChainCircuit {
+    fn values(start: Fp) -> Vec<Fp> {
+        let mut values = vec![start];
+        for _ in 0..ROWS {
+            let a = *values.last().unwrap();
+            values.push(a * a + a);
+        }
+        values
+    }
+}
+
+impl Circuit<Fp> for ChainCircuit {
+    type Config = ChainConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = ChainConfig {
+            q: meta.complex_selector(),
+            a: meta.advice_column(),
+            b: meta.advice_column(),
+            table: meta.fixed_column(),
+            instance: meta.instance_column(),
+        };
+        meta.enable_equality(config.a);
+        meta.enable_equality(config.b);
+        meta.enable_equality(config.instance);
+
+        meta.create_gate("b = a^2 + a", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let b = meta.query_advice(config.b, Rotation::cur());
+
+            vec![q * (a.clone() * a.clone() + a - b)]
+        });
+
+        meta.lookup_any("a in table", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let table = meta.query_fixed(config.table, Rotation::cur());
+
+            vec![(q * a, table)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        let values = self.start.map(Self::values);
+
+        let last = layouter.assign_region(
+            || "chain",
+            |mut region| {
+                let mut previous = None;
+                for row in 0..ROWS {
+                    config.q.enable(&mut region, row)?;
+                    let a = region.assign_advice(
+                        || "a",
+                        config.a,
+                        row,
+                        || values.as_ref().map(|v| v[row]).ok_or(Error::Synthesis),
+                    )?;
+                    if let Some(previous) = previous {
+                        region.constrain_equal(previous, a.cell())?;
+                    }
+                    let b = region.assign_advice(
+                        || "b",
+                        config.b,
+                        row,
+                        || values.as_ref().map(|v| v[row + 1]).ok_or(Error::Synthesis),
+                    )?;
+                    previous = Some(b.cell());
+                }
+                Ok(previous.unwrap())
+            },
+        )?;
+        layouter.constrain_instance(last, config.instance, 0)?;
+
+        // The table holds the chain from the fixed start 2, which all proofs
+        // below use.
+        layouter.assign_region(
+            || "table",
+            |mut region| {
+                for (row, value) in Self::values(Fp::from(2)).into_iter().enumerate() {
+                    region.assign_fixed(|| "table", config.table, row, || Ok(value))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn prove(
+    params: &Params<G1Affine>,
+    pk: &ProvingKey<G1Affine>,
+    instances: &[&[&[Fp]]],
+    memory: ProverMemory,
+) -> Vec<u8> {
+    let circuit = ChainCircuit {
+        start: Some(Fp::from(2)),
+    };
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_memory(
+        params,
+        pk,
+        &[circuit.clone(), circuit],
+        instances,
+        memory,
+        StdRng::seed_from_u64(0x5eed),
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+#[test]
+fn proof_is_independent_of_thread_count() {
+    let last = *ChainCircuit::values(Fp::from(2)).last().unwrap();
+    let instance = [last];
+    let instances: &[&[&[Fp]]] = &[&[&instance], &[&instance]];
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(1).unwrap();
+    let empty = ChainCircuit::default();
+    let vk = keygen_vk(&params, &empty).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &empty).expect("keygen_pk should not fail");
+
+    let proof = prove(&params, &pk, instances, ProverMemory::Fast);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        SingleVerifier::new(&params_verifier),
+        instances,
+        &mut transcript,
+    )
+    .expect("proof should verify");
+
+    // Repeated runs on the default thread pool.
+    for _ in 0..2 {
+        assert_eq!(proof, prove(&params, &pk, instances, ProverMemory::Fast));
+        assert_eq!(proof, prove(&params, &pk, instances, ProverMemory::Low));
+    }
+
+    // Thread pools of various sizes, including ones that don't divide the
+    // number of rows, and more threads than there are cores.
+    #[cfg(feature = "multicore")]
+    for num_threads in [1, 2, 3, 4, 5, 8, 16] {
+        use halo2_proofs::worker::{with_thread_pool, ThreadPoolBuilder};
+
+        let pool = ThreadPoolBuilder::new()
+            .num_threads(num_threads)
+            .build()
+            .unwrap();
+        for memory in [ProverMemory::Fast, ProverMemory::Low] {
+            let threaded = with_thread_pool(&pool, || prove(&params, &pk, instances, memory));
+            assert!(
+                proof == threaded,
+                "the proof created with {} threads and {:?} memory differs",
+                num_threads,
+                memory
+            );
+        }
+    }
+}
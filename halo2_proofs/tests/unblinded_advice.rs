@@ -0,0 +1,116 @@
+use ff::Field;
+use group::{prime::PrimeCurveAffine, GroupEncoding};
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, ProvingKey, SingleVerifier,
+    },
+    poly::commitment::{Params, ParamsVerifier},
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+const INSTANCES: &[&[&[Fp]]] = &[&[]];
+
+#[derive(Clone, Debug)]
+struct MyConfig {
+    unblinded: Column<Advice>,
+    blinded: Column<Advice>,
+}
+
+/// Assigns the same values to an unblinded and a blinded advice column.
+#[derive(Default)]
+struct MyCircuit {
+    values: Option<[Fp; 3]>,
+}
+
+impl Circuit<Fp> for MyCircuit {
+    type Config = MyConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        MyConfig {
+            unblinded: meta.unblinded_advice_column(),
+            blinded: meta.advice_column(),
+        }
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "values",
+            |mut region| {
+                for row in 0..3 {
+                    let value = || self.values.map(|v| v[row]).ok_or(Error::Synthesis);
+                    region.assign_advice(|| "unblinded", config.unblinded, row, value)?;
+                    region.assign_advice(|| "blinded", config.blinded, row, value)?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+fn circuit() -> MyCircuit {
+    MyCircuit {
+        values: Some([Fp::from(1), Fp::from(2), Fp::from(3)]),
+    }
+}
+
+fn prove(params: &Params<G1Affine>, pk: &ProvingKey<G1Affine>, circuit: MyCircuit) -> Vec<u8> {
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(params, pk, &[circuit], INSTANCES, OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    transcript.finalize()
+}
+
+fn verify(params: &ParamsVerifier<Bn256>, pk: &ProvingKey<G1Affine>, proof: &[u8]) -> bool {
+    let strategy = SingleVerifier::new(params);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(proof);
+    verify_proof(params, pk.get_vk(), strategy, INSTANCES, &mut transcript).is_ok()
+}
+
+#[test]
+fn unblinded_advice_column() {
+    let prover = MockProver::run(K, &circuit(), vec![]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+    let vk = keygen_vk(&params, &MyCircuit::default()).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &MyCircuit::default()).expect("keygen_pk should not fail");
+
+    let proofs = [
+        prove(&params, &pk, circuit()),
+        prove(&params, &pk, circuit()),
+    ];
+    for proof in proofs.iter() {
+        assert!(verify(&params_verifier, &pk, proof));
+    }
+
+    // The proofs start with the advice commitments, in the order in which the
+    // columns were allocated. Only the blinded one changes between proofs.
+    let (unblinded, blinded) = (0..32, 32..64);
+    assert_eq!(proofs[0][unblinded.clone()], proofs[1][unblinded.clone()]);
+    assert_ne!(proofs[0][blinded.clone()], proofs[1][blinded]);
+
+    // An unblinded column that is zero on every row commits to the point at
+    // infinity.
+    let zeros = MyCircuit {
+        values: Some([Fp::zero(); 3]),
+    };
+    let proof = prove(&params, &pk, zeros);
+    assert_eq!(proof[unblinded], *G1Affine::identity().to_bytes().as_ref());
+    assert!(verify(&params_verifier, &pk, &proof));
+}
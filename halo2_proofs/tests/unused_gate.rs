@@ -0,0 +1,113 @@
+use halo2_proofs::{
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof, keygen_pk, keygen_vk, verify_proof, Advice, Circuit, Column,
+        ConstraintSystem, Error, Selector, SingleVerifier,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 5;
+
+#[derive(Clone, Debug)]
+struct GatesConfig {
+    q_double: Selector,
+    q_unused: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Doubles a value with one gate, and never enables the other one, which the
+/// assigned values don't satisfy.
+struct GatesCircuit;
+
+impl Circuit<Fp> for GatesCircuit {
+    type Config = GatesConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        GatesCircuit
+    }
+
+    fn configure(meta: &mut ConstraintSystem<Fp>) -> Self::Config {
+        let config = GatesConfig {
+            q_double: meta.selector(),
+            q_unused: meta.complex_selector(),
+            a: meta.advice_column(),
+            b: meta.advice_column(),
+        };
+
+        meta.create_gate("double", |meta| {
+            let q = meta.query_selector(config.q_double);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let b = meta.query_advice(config.b, Rotation::cur());
+            vec![q * (a.clone() + a - b)]
+        });
+        meta.create_gate("square", |meta| {
+            let q = meta.query_selector(config.q_unused);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let b = meta.query_advice(config.b, Rotation::next());
+            vec![q * (a.clone() * a - b)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<Fp>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "double",
+            |mut region| {
+                for row in 0..4 {
+                    config.q_double.enable(&mut region, row)?;
+                    let a = Fp::from(row as u64 + 3);
+                    region.assign_advice(|| "a", config.a, row, || Ok(a))?;
+                    region.assign_advice(|| "b", config.b, row, || Ok(a + a))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn unused_gate() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(0).unwrap();
+    let vk = keygen_vk(&params, &GatesCircuit).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &GatesCircuit).expect("keygen_pk should not fail");
+
+    // The prover skips the gate whose selector is never enabled, and the
+    // verifier, which evaluates it, agrees that it holds.
+    let instances: &[&[&[Fp]]] = &[&[]];
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[GatesCircuit],
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let proof = transcript.finalize();
+
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        SingleVerifier::new(&params_verifier),
+        instances,
+        &mut transcript,
+    )
+    .expect("proof should verify");
+}
@@ -0,0 +1,265 @@
+use std::marker::PhantomData;
+
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, SimpleFloorPlanner},
+    dev::MockProver,
+    plonk::{
+        create_proof, create_proof_with_witness, keygen_pk, keygen_vk, verify_proof, Advice,
+        Assigned, Circuit, Column, ConstraintSystem, Error, Instance, ProvingKey, Selector,
+        SingleVerifier, WitnessCalculator, WitnessColumns, WitnessCompression,
+    },
+    poly::{
+        commitment::{Params, ParamsVerifier},
+        Rotation,
+    },
+    transcript::{Blake2bRead, Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+const ROWS: usize = 4;
+
+#[derive(Clone, Debug)]
+struct SquareConfig {
+    q_square: Selector,
+    x: Column<Advice>,
+    y: Column<Advice>,
+    input: Column<Instance>,
+}
+
+/// Constrains `x = input` and `y = x^2` on each of the first `ROWS` rows.
+#[derive(Default)]
+struct SquareCircuit<F: FieldExt> {
+    inputs: Option<Vec<F>>,
+}
+
+impl<F: FieldExt> Circuit<F> for SquareCircuit<F> {
+    type Config = SquareConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        Self::default()
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let config = SquareConfig {
+            q_square: meta.selector(),
+            x: meta.advice_column(),
+            y: meta.advice_column(),
+            input: meta.instance_column(),
+        };
+
+        meta.create_gate("square", |meta| {
+            let q = meta.query_selector(config.q_square);
+            let x = meta.query_advice(config.x, Rotation::cur());
+            let y = meta.query_advice(config.y, Rotation::cur());
+            let input = meta.query_instance(config.input, Rotation::cur());
+
+            vec![q.clone() * (x.clone() - input), q * (x.clone() * x - y)]
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "square",
+            |mut region| {
+                for offset in 0..ROWS {
+                    config.q_square.enable(&mut region, offset)?;
+                    let x = self.inputs.as_ref().map(|inputs| inputs[offset]);
+                    region.assign_advice(|| "x", config.x, offset, || x.ok_or(Error::Synthesis))?;
+                    region.assign_advice(
+                        || "y",
+                        config.y,
+                        offset,
+                        || x.map(|x| x.square()).ok_or(Error::Synthesis),
+                    )?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+/// Computes the same witness as `SquareCircuit` directly from the instance
+/// column, without going through a layouter.
+struct SquareTrace<F: FieldExt> {
+    extra_rows: usize,
+    extra_columns: usize,
+    _marker: PhantomData<F>,
+}
+
+impl<F: FieldExt> SquareTrace<F> {
+    fn new() -> Self {
+        SquareTrace {
+            extra_rows: 0,
+            extra_columns: 0,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F: FieldExt> WitnessCalculator<F> for SquareTrace<F> {
+    fn calculate_witness(
+        &self,
+        instances: &[&[F]],
+        usable_rows: usize,
+    ) -> Result<Vec<Vec<Assigned<F>>>, Error> {
+        let rows = ROWS + self.extra_rows * usable_rows;
+        let x: Vec<Assigned<F>> = instances[0]
+            .iter()
+            .cycle()
+            .take(rows)
+            .map(|x| (*x).into())
+            .collect();
+        let y = x.iter().map(|x| *x * *x).collect();
+
+        let mut columns = vec![x, y];
+        columns.extend((0..self.extra_columns).map(|_| vec![]));
+        Ok(columns)
+    }
+}
+
+fn setup() -> (
+    Params<G1Affine>,
+    ParamsVerifier<Bn256>,
+    ProvingKey<G1Affine>,
+) {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let params_verifier: ParamsVerifier<Bn256> = params.verifier(ROWS).unwrap();
+    let vk =
+        keygen_vk(&params, &SquareCircuit::<Fp>::default()).expect("keygen_vk should not fail");
+    let pk =
+        keygen_pk(&params, vk, &SquareCircuit::<Fp>::default()).expect("keygen_pk should not fail");
+    (params, params_verifier, pk)
+}
+
+#[test]
+fn witness_calculator() {
+    let inputs: Vec<Fp> = [3, 5, 7, 11].iter().map(|x| Fp::from(*x)).collect();
+
+    let circuit = SquareCircuit {
+        inputs: Some(inputs.clone()),
+    };
+    let prover = MockProver::run(K, &circuit, vec![inputs.clone()]).unwrap();
+    assert_eq!(prover.verify(), Ok(()));
+
+    let (params, params_verifier, pk) = setup();
+    let instances: &[&[&[Fp]]] = &[&[&inputs]];
+
+    // The same key proves both the synthesized and the calculated witness.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(&params, &pk, &[circuit], instances, OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    let synthesized_proof: Vec<u8> = transcript.finalize();
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_witness(
+        &params,
+        &pk,
+        &[SquareTrace::new()],
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+    let calculated_proof: Vec<u8> = transcript.finalize();
+
+    for proof in [synthesized_proof, calculated_proof].iter() {
+        let strategy = SingleVerifier::new(&params_verifier);
+        let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+        assert!(verify_proof(
+            &params_verifier,
+            pk.get_vk(),
+            strategy,
+            instances,
+            &mut transcript,
+        )
+        .is_ok());
+    }
+}
+
+#[test]
+fn witness_calculator_shape() {
+    let inputs: Vec<Fp> = [3, 5, 7, 11].iter().map(|x| Fp::from(*x)).collect();
+    let (params, _, pk) = setup();
+    let instances: &[&[&[Fp]]] = &[&[&inputs]];
+
+    let too_many_columns = SquareTrace {
+        extra_columns: 1,
+        ..SquareTrace::new()
+    };
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    assert!(matches!(
+        create_proof_with_witness(
+            &params,
+            &pk,
+            &[too_many_columns],
+            instances,
+            OsRng,
+            &mut transcript,
+        ),
+        Err(Error::BoundsFailure)
+    ));
+
+    let too_many_rows = SquareTrace {
+        extra_rows: 1,
+        ..SquareTrace::new()
+    };
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    assert!(matches!(
+        create_proof_with_witness(
+            &params,
+            &pk,
+            &[too_many_rows],
+            instances,
+            OsRng,
+            &mut transcript,
+        ),
+        Err(Error::NotEnoughRowsAvailable { current_k: K, .. })
+    ));
+}
+
+#[test]
+fn stored_witness() {
+    let inputs: Vec<Fp> = [3, 5, 7, 11].iter().map(|x| Fp::from(*x)).collect();
+    let (params, params_verifier, pk) = setup();
+    let instances: &[&[&[Fp]]] = &[&[&inputs]];
+
+    // Generate the witness on one machine...
+    let circuit = SquareCircuit {
+        inputs: Some(inputs.clone()),
+    };
+    let witness = WitnessColumns::generate(&pk, &circuit, instances[0]).unwrap();
+    let mut buffer = vec![];
+    witness
+        .write(&mut buffer, WitnessCompression::Packed)
+        .unwrap();
+
+    // ...and prove it on another.
+    let witness = WitnessColumns::<Fp>::read(&mut &buffer[..]).unwrap();
+    let squares: Vec<Fp> = [9, 25, 49, 121].iter().map(|x| Fp::from(*x)).collect();
+    assert_eq!(witness.advice()[1][..ROWS], squares[..]);
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_with_witness(&params, &pk, &[witness], instances, OsRng, &mut transcript)
+        .expect("proof generation should not fail");
+    let proof: Vec<u8> = transcript.finalize();
+
+    let strategy = SingleVerifier::new(&params_verifier);
+    let mut transcript = Blake2bRead::<_, _, Challenge255<_>>::init(&proof[..]);
+    assert!(verify_proof(
+        &params_verifier,
+        pk.get_vk(),
+        strategy,
+        instances,
+        &mut transcript,
+    )
+    .is_ok());
+}
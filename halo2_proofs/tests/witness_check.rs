@@ -0,0 +1,122 @@
+use halo2_proofs::{
+    arithmetic::FieldExt,
+    circuit::{Layouter, SimpleFloorPlanner},
+    plonk::{
+        create_proof, create_proof_checked, keygen_pk, keygen_vk, Advice, Circuit, Column,
+        ConstraintSystem, Error, Selector,
+    },
+    poly::{commitment::Params, Rotation},
+    transcript::{Blake2bWrite, Challenge255},
+};
+use pairing::bn256::{Bn256, Fr as Fp, G1Affine};
+use rand_core::OsRng;
+
+const K: u32 = 4;
+
+#[derive(Clone, Debug)]
+struct EqualConfig {
+    q: Selector,
+    a: Column<Advice>,
+    b: Column<Advice>,
+}
+
+/// Constrains `a` on the second row to equal `b` on the first, optionally
+/// forgetting to assign `b`.
+struct EqualCircuit {
+    assign_b: bool,
+}
+
+impl<F: FieldExt> Circuit<F> for EqualCircuit {
+    type Config = EqualConfig;
+    type FloorPlanner = SimpleFloorPlanner;
+
+    fn without_witnesses(&self) -> Self {
+        EqualCircuit { assign_b: true }
+    }
+
+    fn configure(meta: &mut ConstraintSystem<F>) -> Self::Config {
+        let config = EqualConfig {
+            q: meta.selector(),
+            a: meta.advice_column(),
+            b: meta.advice_column(),
+        };
+
+        meta.create_gate("equal", |meta| {
+            let q = meta.query_selector(config.q);
+            let a = meta.query_advice(config.a, Rotation::cur());
+            let b = meta.query_advice(config.b, Rotation::prev());
+
+            Some(q * (a - b))
+        });
+
+        config
+    }
+
+    fn synthesize(
+        &self,
+        config: Self::Config,
+        mut layouter: impl Layouter<F>,
+    ) -> Result<(), Error> {
+        layouter.assign_region(
+            || "equal",
+            |mut region| {
+                config.q.enable(&mut region, 1)?;
+                region.assign_advice(|| "a", config.a, 1, || Ok(F::zero()))?;
+                if self.assign_b {
+                    region.assign_advice(|| "b", config.b, 0, || Ok(F::zero()))?;
+                }
+                Ok(())
+            },
+        )
+    }
+}
+
+#[test]
+fn unassigned_cell() {
+    let params: Params<G1Affine> = Params::<G1Affine>::unsafe_setup::<Bn256>(K);
+    let vk =
+        keygen_vk(&params, &EqualCircuit { assign_b: true }).expect("keygen_vk should not fail");
+    let pk = keygen_pk(&params, vk, &EqualCircuit { assign_b: true })
+        .expect("keygen_pk should not fail");
+    let instances: &[&[&[Fp]]] = &[&[]];
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof_checked(
+        &params,
+        &pk,
+        &[EqualCircuit { assign_b: true }],
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+
+    // Unassigned cells default to zero, so the proof is created without the check.
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    create_proof(
+        &params,
+        &pk,
+        &[EqualCircuit { assign_b: false }],
+        instances,
+        OsRng,
+        &mut transcript,
+    )
+    .expect("proof generation should not fail");
+
+    let mut transcript = Blake2bWrite::<_, _, Challenge255<_>>::init(vec![]);
+    assert!(matches!(
+        create_proof_checked(
+            &params,
+            &pk,
+            &[EqualCircuit { assign_b: false }],
+            instances,
+            OsRng,
+            &mut transcript,
+        ),
+        Err(Error::CellNotAssigned {
+            gate: "equal",
+            column,
+            row: 0,
+        }) if column.index() == 1
+    ));
+}